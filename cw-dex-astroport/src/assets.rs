@@ -0,0 +1,155 @@
+//! Checked conversions between [`AssetList`] and the asset vectors of Astroport
+//! messages and query responses.
+//!
+//! The `From` conversions of `apollo-cw-asset` pass duplicate and zero amount
+//! entries of an `AssetList` on to the pair, which rejects them with errors
+//! that do not name the offending asset, and silently merge and drop them in
+//! the other direction, panicking if a merged amount overflows.
+
+use apollo_cw_asset::{Asset, AssetList};
+use astroport::asset::Asset as AstroAsset;
+use cosmwasm_schema::cw_serde;
+
+use cw_dex::assets::merge_assets;
+use cw_dex::CwDexError;
+
+/// How duplicate entries and entries with a zero amount are handled when
+/// converting assets, see [`to_astro_assets`] and [`from_astro_assets`].
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum AssetConversion {
+    /// Return [`CwDexError::DuplicateAsset`] or [`CwDexError::ZeroAmount`]
+    #[default]
+    Strict,
+    /// Merge duplicate entries by summing their amounts and remove entries
+    /// with a zero amount, see [`merge_assets`]
+    Merge,
+    /// Convert with the `From` conversions of `apollo-cw-asset`, the legacy
+    /// behavior
+    Lenient,
+}
+
+impl AssetConversion {
+    /// Returns `assets` handled as selected by the mode. With
+    /// [`AssetConversion::Lenient`] they are returned as they are.
+    pub(crate) fn apply(&self, assets: Vec<Asset>) -> Result<Vec<Asset>, CwDexError> {
+        match self {
+            AssetConversion::Strict => {
+                for (i, asset) in assets.iter().enumerate() {
+                    if asset.amount.is_zero() {
+                        return Err(CwDexError::ZeroAmount {
+                            asset: asset.info.clone(),
+                        });
+                    }
+                    if assets[..i].iter().any(|a| a.info == asset.info) {
+                        return Err(CwDexError::DuplicateAsset {
+                            asset: asset.info.clone(),
+                        });
+                    }
+                }
+                Ok(assets)
+            }
+            AssetConversion::Merge => Ok(merge_assets(&assets)?.to_vec()),
+            AssetConversion::Lenient => Ok(assets),
+        }
+    }
+}
+
+/// Converts `assets` into the assets of an Astroport message, handling
+/// duplicate and zero amount entries as selected by `mode`.
+///
+/// An [`AssetList`] built with its `From` conversions or `add` never contains
+/// such entries, but one deserialized from a message can.
+pub fn to_astro_assets<'a>(
+    assets: impl IntoIterator<Item = &'a Asset>,
+    mode: AssetConversion,
+) -> Result<Vec<AstroAsset>, CwDexError> {
+    let assets = mode.apply(assets.into_iter().cloned().collect())?;
+    Ok(assets.into_iter().map(Into::into).collect())
+}
+
+/// Converts the assets of an Astroport query response into an [`AssetList`],
+/// handling duplicate and zero amount entries as selected by `mode`.
+pub fn from_astro_assets(
+    assets: Vec<AstroAsset>,
+    mode: AssetConversion,
+) -> Result<AssetList, CwDexError> {
+    match mode {
+        AssetConversion::Lenient => Ok(assets.into()),
+        _ => Ok(AssetList::from(
+            mode.apply(assets.into_iter().map(Into::into).collect())?,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use astroport::asset::Asset as AstroAsset;
+    use cosmwasm_std::Uint128;
+    use cw_dex::CwDexError;
+    use test_case::test_case;
+
+    use super::{from_astro_assets, to_astro_assets, AssetConversion};
+
+    fn assets(amounts: &[(&str, u128)]) -> Vec<Asset> {
+        amounts
+            .iter()
+            .map(|(denom, amount)| Asset::native(*denom, *amount))
+            .collect()
+    }
+
+    #[test_case(&[("uatom", 1), ("uosmo", 2)], Ok(&[("uatom", 1), ("uosmo", 2)]); "valid")]
+    #[test_case(&[("uatom", 1), ("uosmo", 2), ("uatom", 3)], Err(CwDexError::DuplicateAsset { asset: AssetInfo::native("uatom") }); "duplicate")]
+    #[test_case(&[("uatom", 1), ("uosmo", 0)], Err(CwDexError::ZeroAmount { asset: AssetInfo::native("uosmo") }); "zero amount")]
+    fn test_strict(input: &[(&str, u128)], expected: Result<&[(&str, u128)], CwDexError>) {
+        let input = assets(input);
+        let expected = expected.map(assets);
+        assert_eq!(
+            to_astro_assets(&input, AssetConversion::Strict).map_err(|err| err.to_string()),
+            expected
+                .as_ref()
+                .map(|expected| expected.iter().cloned().map(AstroAsset::from).collect())
+                .map_err(|err| err.to_string())
+        );
+
+        let astro: Vec<AstroAsset> = input.into_iter().map(Into::into).collect();
+        assert_eq!(
+            from_astro_assets(astro, AssetConversion::Strict),
+            expected.map(AssetList::from)
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let input = assets(&[("uatom", 1), ("uosmo", 0), ("uatom", 3)]);
+        let expected = assets(&[("uatom", 4)]);
+        assert_eq!(
+            to_astro_assets(&input, AssetConversion::Merge).unwrap(),
+            expected
+                .iter()
+                .cloned()
+                .map(AstroAsset::from)
+                .collect::<Vec<_>>()
+        );
+
+        // Overflows are returned as errors instead of panicking
+        let input: Vec<AstroAsset> = vec![
+            Asset::native("uatom", Uint128::MAX).into(),
+            Asset::native("uatom", 1u128).into(),
+        ];
+        assert!(from_astro_assets(input, AssetConversion::Merge).is_err());
+    }
+
+    #[test]
+    fn test_lenient() {
+        let input = assets(&[("uatom", 1), ("uosmo", 0), ("uatom", 3)]);
+        let astro = to_astro_assets(&input, AssetConversion::Lenient).unwrap();
+        assert_eq!(astro.len(), 3);
+
+        assert_eq!(
+            from_astro_assets(astro, AssetConversion::Lenient).unwrap(),
+            AssetList::from(assets(&[("uatom", 4)]))
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Contains [`ChainProfile`], selecting the quirks of the Astroport deployment
+//! of a chain, so that the same contract can be configured per chain at
+//! instantiation instead of being compiled for each of them.
+
+use astroport::asset::Asset as AstroAsset;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Uint128};
+
+/// The Astroport deployment of a chain, determining the type of the LP tokens
+/// of its pairs and whether it has an incentives contract.
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum ChainProfile {
+    /// LP tokens are cw20 tokens and pools can be staked in the incentives
+    /// contract, e.g. on Terra and Neutron
+    #[default]
+    Standard,
+    /// LP tokens are token factory denoms and there is no incentives contract
+    Sei,
+    /// LP tokens are token factory denoms and pools can be staked in the
+    /// incentives contract. Cw20 rewards can be wrapped with the cw20 adapter,
+    /// see [`crate::AstroportStaking::cw20_adapter`].
+    Injective,
+    /// A deployment with the given quirks
+    Custom {
+        /// Whether LP tokens are token factory denoms
+        native_lp_token: bool,
+        /// Whether pools can be staked in an incentives contract
+        incentives: bool,
+    },
+}
+
+impl ChainProfile {
+    /// Returns whether the LP tokens of the pairs are token factory denoms
+    /// instead of cw20 tokens. The pairs of such deployments are provided to
+    /// and withdrawn from directly, as the liquidity manager only supports
+    /// cw20 LP tokens.
+    pub fn native_lp_token(&self) -> bool {
+        match self {
+            ChainProfile::Standard => false,
+            ChainProfile::Sei | ChainProfile::Injective => true,
+            ChainProfile::Custom {
+                native_lp_token, ..
+            } => *native_lp_token,
+        }
+    }
+
+    /// Returns whether pools can be staked in an incentives contract.
+    pub fn incentives(&self) -> bool {
+        match self {
+            ChainProfile::Standard | ChainProfile::Injective => true,
+            ChainProfile::Sei => false,
+            ChainProfile::Custom { incentives, .. } => *incentives,
+        }
+    }
+}
+
+/// The end of the denoms of token factory LP tokens, which are of the form
+/// `factory/{pair}/astroport/share`
+pub(crate) const NATIVE_LP_TOKEN_SUBDENOM: &str = "/astroport/share";
+
+/// The execute messages of pair contracts with token factory LP tokens that
+/// differ from [`astroport::pair::ExecuteMsg`].
+#[cw_serde]
+pub(crate) enum NativeLpPairExecuteMsg {
+    ProvideLiquidity {
+        assets: Vec<AstroAsset>,
+        slippage_tolerance: Option<Decimal>,
+        auto_stake: Option<bool>,
+        receiver: Option<String>,
+        min_lp_to_receive: Option<Uint128>,
+    },
+    WithdrawLiquidity {
+        assets: Vec<AstroAsset>,
+        min_assets_to_receive: Option<Vec<AstroAsset>>,
+    },
+}
+
+/// The query messages of pair contracts with token factory LP tokens that
+/// differ from [`astroport::pair::QueryMsg`].
+#[cw_serde]
+pub(crate) enum NativeLpPairQueryMsg {
+    SimulateProvide {
+        assets: Vec<AstroAsset>,
+        slippage_tolerance: Option<Decimal>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use astroport::asset::{Asset as AstroAsset, AssetInfo as AstroAssetInfo};
+    use cosmwasm_std::{from_json, to_json_binary, to_json_string, Decimal, Uint128};
+    use test_case::test_case;
+
+    use super::{ChainProfile, NativeLpPairExecuteMsg, NativeLpPairQueryMsg};
+
+    #[test_case(ChainProfile::Standard, false, true; "standard")]
+    #[test_case(ChainProfile::Sei, true, false; "sei")]
+    #[test_case(ChainProfile::Injective, true, true; "injective")]
+    #[test_case(ChainProfile::Custom { native_lp_token: false, incentives: false }, false, false; "custom")]
+    fn test_quirks(profile: ChainProfile, native_lp_token: bool, incentives: bool) {
+        assert_eq!(profile.native_lp_token(), native_lp_token);
+        assert_eq!(profile.incentives(), incentives);
+        assert_eq!(
+            from_json::<ChainProfile>(to_json_binary(&profile).unwrap()).unwrap(),
+            profile
+        );
+    }
+
+    fn uatom(amount: u128) -> Vec<AstroAsset> {
+        vec![AstroAsset {
+            info: AstroAssetInfo::NativeToken {
+                denom: "uatom".to_string(),
+            },
+            amount: Uint128::new(amount),
+        }]
+    }
+
+    #[test]
+    fn test_native_lp_pair_msgs() {
+        let msg = NativeLpPairExecuteMsg::ProvideLiquidity {
+            assets: uatom(100),
+            slippage_tolerance: Some(Decimal::percent(1)),
+            auto_stake: Some(false),
+            receiver: None,
+            min_lp_to_receive: Some(Uint128::new(90)),
+        };
+        assert_eq!(
+            to_json_string(&msg).unwrap(),
+            r#"{"provide_liquidity":{"assets":[{"info":{"native_token":{"denom":"uatom"}},"amount":"100"}],"slippage_tolerance":"0.01","auto_stake":false,"receiver":null,"min_lp_to_receive":"90"}}"#
+        );
+
+        let msg = NativeLpPairQueryMsg::SimulateProvide {
+            assets: uatom(100),
+            slippage_tolerance: None,
+        };
+        assert_eq!(
+            to_json_string(&msg).unwrap(),
+            r#"{"simulate_provide":{"assets":[{"info":{"native_token":{"denom":"uatom"}},"amount":"100"}],"slippage_tolerance":null}}"#
+        );
+    }
+}
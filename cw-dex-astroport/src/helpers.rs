@@ -0,0 +1,12 @@
+use cosmwasm_std::StdError;
+
+/// Returns true if `err` is the error of a query of an item missing from the
+/// storage of the queried contract, e.g. of a pool the incentives contract has
+/// no info on. Errors of contract queries reach the caller as generic errors
+/// carrying the message of the `StdError::NotFound` of the contract.
+pub(crate) fn is_not_found(err: &StdError) -> bool {
+    match err {
+        StdError::NotFound { .. } => true,
+        err => err.to_string().contains("not found"),
+    }
+}
@@ -1,9 +1,26 @@
 //! Pool and Staking implementations for Astroport
 
+mod assets;
+mod chain;
+mod helpers;
+pub mod math;
+mod oracle;
 mod pool;
+mod refund;
+mod router;
 mod staking;
+mod xastro;
 
-pub use pool::AstroportPool;
-pub use staking::AstroportStaking;
+pub use assets::{from_astro_assets, to_astro_assets, AssetConversion};
+pub use chain::ChainProfile;
+pub use oracle::{AstroportCumulativePriceOracle, CumulativePriceSnapshot};
+pub use pool::{AstroportPool, Cw20AllowanceMode, PairVersion, PoolInvariant, SwapOptions};
+pub use refund::{ProvideRefundTracker, ASTROPORT_PROVIDE_REFUND_REPLY_ID};
+pub use router::AstroportRouter;
+pub use staking::{
+    project_rewards, AstroportStaking, Cw20HookMsg, RewardIndex, RewardSchedule, RewardSchedules,
+    UserRewardIndex, VestingRewards,
+};
+pub use xastro::AstroportXAstroStaking;
 
 pub use {astroport, astroport_v3};
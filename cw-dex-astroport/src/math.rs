@@ -0,0 +1,96 @@
+//! Constant product math of the Astroport xyk pair, used by the local
+//! simulations of [`crate::AstroportPool`].
+//!
+//! The functions take reserves and amounts rather than `Deps`, so off-chain
+//! clients, e.g. bots and tests, can compute quotes with exactly the formulas
+//! the contracts use. The stableswap math of Osmosis is in the `math` module of
+//! `cw-dex-osmosis`.
+
+use cosmwasm_std::{Decimal, Decimal256, Isqrt, StdError, StdResult, Uint128, Uint256};
+
+/// Returns the invariant `sqrt(x * y)` of a constant product pool with the
+/// reserves `reserve_a` and `reserve_b`, rounded down.
+pub fn xyk_invariant(reserve_a: Uint128, reserve_b: Uint128) -> Uint256 {
+    reserve_a.full_mul(reserve_b).isqrt()
+}
+
+/// Returns the amount received for swapping `offer_amount` in a constant
+/// product pool, after deducting the commission. Same as `compute_swap` of
+/// the Astroport xyk pair contract.
+///
+/// Arguments:
+/// - `offer_pool`: The reserve of the offered asset
+/// - `ask_pool`: The reserve of the asked asset
+/// - `offer_amount`: The amount offered
+/// - `commission_rate`: The total fee of the pair
+pub fn compute_xyk_swap(
+    offer_pool: Uint128,
+    ask_pool: Uint128,
+    offer_amount: Uint128,
+    commission_rate: Decimal,
+) -> StdResult<Uint128> {
+    if offer_pool.is_zero() || ask_pool.is_zero() {
+        return Err(StdError::generic_err("One of the pools is empty"));
+    }
+    if offer_amount.is_zero() {
+        return Err(StdError::generic_err("Swap amount must not be zero"));
+    }
+
+    let offer_pool = Uint256::from(offer_pool);
+    let ask_pool = Uint256::from(ask_pool);
+    let offer_amount = Uint256::from(offer_amount);
+
+    // ask_amount = ask_pool - cp / (offer_pool + offer_amount)
+    let cp = offer_pool * ask_pool;
+    let return_amount = (Decimal256::from_ratio(ask_pool, 1u8)
+        - Decimal256::from_ratio(cp, offer_pool + offer_amount))
+        * Uint256::one();
+    let commission_amount = return_amount * Decimal256::from(commission_rate);
+
+    Ok((return_amount - commission_amount).try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{Decimal, Uint128, Uint256};
+    use test_case::test_case;
+
+    use super::{compute_xyk_swap, xyk_invariant};
+
+    #[test_case(1_000_000, 1_000_000, 1_000, Decimal::permille(3) => 997; "small swap")]
+    #[test_case(2_000_000, 1_000_000, 1_000_000, Decimal::zero() => 333_333; "no commission")]
+    fn test_compute_xyk_swap(
+        offer_pool: u128,
+        ask_pool: u128,
+        offer_amount: u128,
+        commission_rate: Decimal,
+    ) -> u128 {
+        compute_xyk_swap(
+            offer_pool.into(),
+            ask_pool.into(),
+            offer_amount.into(),
+            commission_rate,
+        )
+        .unwrap()
+        .u128()
+    }
+
+    #[test]
+    fn test_compute_xyk_swap_empty() {
+        let one = Uint128::one();
+        assert!(compute_xyk_swap(Uint128::zero(), one, one, Decimal::zero()).is_err());
+        assert!(compute_xyk_swap(one, one, Uint128::zero(), Decimal::zero()).is_err());
+    }
+
+    #[test]
+    fn test_xyk_invariant() {
+        assert_eq!(
+            xyk_invariant(Uint128::new(4), Uint128::new(9)),
+            Uint256::from(6u8)
+        );
+        assert_eq!(
+            xyk_invariant(Uint128::MAX, Uint128::MAX),
+            Uint256::from(Uint128::MAX)
+        );
+    }
+}
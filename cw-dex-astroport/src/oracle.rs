@@ -0,0 +1,243 @@
+//! Oracle implementation using the cumulative prices of Astroport pairs
+
+use apollo_cw_asset::AssetInfo;
+use astroport::pair::{CumulativePricesResponse, QueryMsg as PairQueryMsg, TWAP_PRECISION};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Deps, Env, QuerierWrapper, StdResult, Uint128};
+
+use cw_dex::oracle::Oracle;
+use cw_dex::CwDexError;
+
+/// The cumulative prices of an Astroport pair at a point in time, see
+/// [`AstroportCumulativePriceOracle`].
+#[cw_serde]
+pub struct CumulativePriceSnapshot {
+    /// The block time of the snapshot in seconds
+    pub time: u64,
+    /// The cumulative prices of the pair. Each entry holds the cumulative
+    /// price of the first asset in units of the second asset, scaled by
+    /// `10^TWAP_PRECISION`.
+    pub cumulative_prices: Vec<(AssetInfo, AssetInfo, Uint128)>,
+}
+
+impl CumulativePriceSnapshot {
+    /// Queries the current cumulative prices of the pair contract at
+    /// `pair_addr`.
+    pub fn query(querier: &QuerierWrapper, env: &Env, pair_addr: &Addr) -> StdResult<Self> {
+        let res: CumulativePricesResponse =
+            querier.query_wasm_smart(pair_addr.to_string(), &PairQueryMsg::CumulativePrices {})?;
+        Ok(Self {
+            time: env.block.time.seconds(),
+            cumulative_prices: res
+                .cumulative_prices
+                .into_iter()
+                .map(|(a, b, price)| (a.into(), b.into(), price))
+                .collect(),
+        })
+    }
+
+    fn find(&self, asset: &AssetInfo, quote: &AssetInfo) -> Option<Uint128> {
+        self.cumulative_prices
+            .iter()
+            .find(|(a, b, _)| a == asset && b == quote)
+            .map(|(_, _, price)| *price)
+    }
+
+    /// Returns the time weighted average price of `asset` in units of `quote`
+    /// between this snapshot and the later snapshot `end` of the same pair,
+    /// e.g. a snapshot saved in the contract's state and one queried with
+    /// [`crate::AstroportPool::query_cumulative_prices`].
+    ///
+    /// Returns [`CwDexError::PriceUnavailable`] if no time elapsed between the
+    /// snapshots, or if they have no cumulative price of `asset` in `quote`.
+    pub fn twap(
+        &self,
+        end: &CumulativePriceSnapshot,
+        asset: &AssetInfo,
+        quote: &AssetInfo,
+    ) -> Result<Decimal, CwDexError> {
+        let unavailable = |reason: String| CwDexError::PriceUnavailable {
+            asset: asset.clone(),
+            reason,
+        };
+
+        let elapsed = end.time.saturating_sub(self.time);
+        if elapsed == 0 {
+            return Err(unavailable(
+                "no time elapsed since the snapshot".to_string(),
+            ));
+        }
+        let (Some(start_price), Some(end_price)) =
+            (self.find(asset, quote), end.find(asset, quote))
+        else {
+            return Err(unavailable(format!("no cumulative price in {quote}")));
+        };
+
+        // The cumulative prices wrap around on overflow
+        let scale = Uint128::from(elapsed) * Uint128::from(10u128.pow(TWAP_PRECISION.into()));
+        Decimal::checked_from_ratio(end_price.wrapping_sub(start_price), scale)
+            .map_err(|err| unavailable(err.to_string()))
+    }
+}
+
+/// An [`Oracle`] quoting the time weighted average price of the assets of an
+/// Astroport pair since a snapshot of its cumulative prices.
+///
+/// The contract must save a [`CumulativePriceSnapshot`] and refresh it
+/// periodically, e.g. when the TWAP window has passed. Prices are unavailable
+/// in the block of the snapshot, as no time has elapsed.
+#[cw_serde]
+pub struct AstroportCumulativePriceOracle {
+    /// The address of the pair contract
+    pub pair_addr: Addr,
+    /// The asset prices are quoted in. Must be an asset of the pair.
+    pub quote: AssetInfo,
+    /// The cumulative prices at the start of the averaging window
+    pub snapshot: CumulativePriceSnapshot,
+}
+
+impl AstroportCumulativePriceOracle {
+    /// Creates an oracle of the pair at `pair_addr`, with a snapshot of the
+    /// current cumulative prices starting the averaging window.
+    pub fn new(deps: Deps, env: &Env, pair_addr: Addr, quote: AssetInfo) -> StdResult<Self> {
+        let snapshot = CumulativePriceSnapshot::query(&deps.querier, env, &pair_addr)?;
+        Ok(Self {
+            pair_addr,
+            quote,
+            snapshot,
+        })
+    }
+}
+
+impl Oracle for AstroportCumulativePriceOracle {
+    fn quote_asset(&self) -> AssetInfo {
+        self.quote.clone()
+    }
+
+    fn price(&self, deps: Deps, env: &Env, asset: &AssetInfo) -> Result<Decimal, CwDexError> {
+        if asset == &self.quote {
+            return Ok(Decimal::one());
+        }
+        let now = CumulativePriceSnapshot::query(&deps.querier, env, &self.pair_addr)?;
+        self.snapshot.twap(&now, asset, &self.quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    use astroport::asset::AssetInfo as AstroAssetInfo;
+    use astroport::pair::CumulativePricesResponse;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{
+        to_json_binary, Addr, ContractResult, Decimal, SystemResult, Uint128, WasmQuery,
+    };
+    use cw_dex::oracle::Oracle;
+    use cw_dex::CwDexError;
+
+    use super::{AstroportCumulativePriceOracle, CumulativePriceSnapshot};
+
+    #[test]
+    fn test_price() {
+        let native = |denom: &str| AstroAssetInfo::NativeToken {
+            denom: denom.to_string(),
+        };
+        let mut deps = mock_dependencies();
+        // 100 seconds at 2.5 uusdc per uosmo since the snapshot, wrapping
+        // around
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&CumulativePricesResponse {
+                    assets: vec![],
+                    total_share: Uint128::zero(),
+                    cumulative_prices: vec![
+                        (
+                            native("uosmo"),
+                            native("uusdc"),
+                            Uint128::new(250_000_000 - 1),
+                        ),
+                        (native("uusdc"), native("uosmo"), Uint128::zero()),
+                    ],
+                })
+                .unwrap(),
+            )),
+            _ => unimplemented!(),
+        });
+        let mut env = mock_env();
+        let oracle = AstroportCumulativePriceOracle {
+            pair_addr: Addr::unchecked("pair"),
+            quote: AssetInfo::native("uusdc"),
+            snapshot: CumulativePriceSnapshot {
+                time: env.block.time.seconds() - 100,
+                cumulative_prices: vec![(
+                    AssetInfo::native("uosmo"),
+                    AssetInfo::native("uusdc"),
+                    Uint128::MAX,
+                )],
+            },
+        };
+
+        assert_eq!(
+            oracle
+                .price(deps.as_ref(), &env, &AssetInfo::native("uosmo"))
+                .unwrap(),
+            Decimal::percent(250)
+        );
+        assert_eq!(
+            oracle
+                .price(deps.as_ref(), &env, &AssetInfo::native("uusdc"))
+                .unwrap(),
+            Decimal::one()
+        );
+        assert!(matches!(
+            oracle
+                .price(deps.as_ref(), &env, &AssetInfo::native("uatom"))
+                .unwrap_err(),
+            CwDexError::PriceUnavailable { .. }
+        ));
+
+        env.block.time = env.block.time.minus_seconds(100);
+        assert!(matches!(
+            oracle
+                .price(deps.as_ref(), &env, &AssetInfo::native("uosmo"))
+                .unwrap_err(),
+            CwDexError::PriceUnavailable { .. }
+        ));
+    }
+
+    #[test]
+    fn test_twap() {
+        let snapshot = |time: u64, price: u128| CumulativePriceSnapshot {
+            time,
+            cumulative_prices: vec![(
+                AssetInfo::native("uatom"),
+                AssetInfo::native("uosmo"),
+                Uint128::new(price),
+            )],
+        };
+        let uatom = AssetInfo::native("uatom");
+        let uosmo = AssetInfo::native("uosmo");
+
+        // 50 seconds at 1.5 uosmo per uatom
+        let start = snapshot(1_000, 7_000_000);
+        let end = snapshot(1_050, 7_000_000 + 75_000_000);
+        assert_eq!(
+            start.twap(&end, &uatom, &uosmo).unwrap(),
+            Decimal::percent(150)
+        );
+
+        // No time elapsed, or the end is before the start
+        for end in [snapshot(1_000, 7_000_000), snapshot(900, 0)] {
+            assert!(matches!(
+                start.twap(&end, &uatom, &uosmo).unwrap_err(),
+                CwDexError::PriceUnavailable { .. }
+            ));
+        }
+
+        // No cumulative price of the asset in the quote
+        assert!(matches!(
+            start.twap(&end, &uosmo, &uatom).unwrap_err(),
+            CwDexError::PriceUnavailable { .. }
+        ));
+    }
+}
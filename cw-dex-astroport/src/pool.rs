@@ -1,5 +1,6 @@
 //! Pool trait implementation for Astroport
 
+use std::fmt;
 use std::str::FromStr;
 
 use apollo_cw_asset::{Asset, AssetInfo, AssetInfoBase, AssetList};
@@ -7,29 +8,48 @@ use apollo_utils::iterators::IntoElementwise;
 use astroport::liquidity_manager;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    to_json_binary, wasm_execute, Addr, CosmosMsg, Decimal, Deps, Env, Event, QuerierWrapper,
-    QueryRequest, Response, StdError, StdResult, Uint128, WasmMsg, WasmQuery,
+    from_json, to_json_binary, wasm_execute, Addr, Coin, CosmosMsg, Decimal, Decimal256, Deps, Env,
+    Event, QuerierWrapper, QueryRequest, Response, StdError, StdResult, Uint128, Uint256, WasmMsg,
+    WasmQuery,
 };
-use cw20::Cw20ExecuteMsg;
+use cw2::ContractVersion;
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, MinterResponse, TokenInfoResponse};
 use cw_utils::Expiration;
 
 use apollo_utils::assets::separate_natives_and_cw20s;
 use astroport::asset::{Asset as AstroAsset, PairInfo};
-use astroport::factory::PairType;
+use astroport::factory::{FeeInfoResponse, PairType, QueryMsg as FactoryQueryMsg};
 use astroport::pair::{
-    Cw20HookMsg as PairCw20HookMsg, ExecuteMsg as PairExecuteMsg, PoolResponse,
+    ConfigResponse, Cw20HookMsg as PairCw20HookMsg, ExecuteMsg as PairExecuteMsg, PoolResponse,
     QueryMsg as PairQueryMsg, SimulationResponse, MAX_ALLOWED_SLIPPAGE,
 };
+use astroport::pair_concentrated::ConcentratedPoolConfig;
 use astroport::querier::query_supply;
-use cw_dex::traits::Pool;
-use cw_dex::CwDexError;
+use astroport_v3::pair::{FeeShareConfig, StablePoolConfig, XYKPoolConfig};
+use astroport_v3::pair_concentrated::ConcentratedPoolConfig as ConcentratedPoolConfigV3;
+use cw_dex::conv::uint256_to_uint128;
+use cw_dex::events::provide_liquidity_event;
+use cw_dex::metrics::{PoolMetrics, QueryPoolMetrics};
+use cw_dex::reply::reply_on_last_message;
+use cw_dex::traits::{Pool, SimulationMode};
+use cw_dex::{CwDexError, PoolType};
+
+use crate::assets::{from_astro_assets, to_astro_assets, AssetConversion};
+use crate::chain::{
+    ChainProfile, NativeLpPairExecuteMsg, NativeLpPairQueryMsg, NATIVE_LP_TOKEN_SUBDENOM,
+};
+use crate::helpers::is_not_found;
+use crate::math;
+use crate::oracle::CumulativePriceSnapshot;
+use crate::refund::{ProvideRefundTracker, ASTROPORT_PROVIDE_REFUND_REPLY_ID};
 
 /// Represents an AMM pool on Astroport
 #[cw_serde]
 pub struct AstroportPool {
     /// The address of the associated pair contract
     pub pair_addr: Addr,
-    /// The address of the associated LP token contract
+    /// The address of the associated LP token contract, or the denom of the LP
+    /// token if it is a token factory denom, see [`ChainProfile`]
     pub lp_token_addr: Addr,
     /// The assets of the pool
     pub pool_assets: Vec<AssetInfo>,
@@ -37,27 +57,236 @@ pub struct AstroportPool {
     pub pair_type: PairType,
     /// The address of the Astroport liquidity manager contract
     pub liquidity_manager: Addr,
+    /// The version of the pair contract, determining the messages it accepts
+    #[serde(default)]
+    pub pair_version: PairVersion,
+    /// How the simulations of the pool are computed
+    #[serde(default)]
+    pub simulation_mode: SimulationMode,
+    /// The reply ID of the provide liquidity message of
+    /// [`AstroportPool::provide_liquidity_tracking_refunds`]
+    #[serde(default = "default_provide_refund_reply_id")]
+    pub provide_refund_reply_id: u64,
+    /// The Astroport deployment of the chain, determining the type of the LP
+    /// token and how liquidity is provided and withdrawn
+    #[serde(default)]
+    pub chain_profile: ChainProfile,
+    /// How the allowances of the cw20 assets provided to the pool are granted
+    #[serde(default)]
+    pub allowance_mode: Cw20AllowanceMode,
+    /// How duplicate and zero amount entries of the assets provided to the
+    /// pool are handled, both when providing and simulating
+    #[serde(default)]
+    pub asset_conversion: AssetConversion,
+}
+
+fn default_provide_refund_reply_id() -> u64 {
+    ASTROPORT_PROVIDE_REFUND_REPLY_ID
+}
+
+/// Whether a deployed Astroport pair contract accepts `ask_asset_info` in
+/// swap messages and simulations, which depends on the type of the pair and
+/// the version of its contract.
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum PairVersion {
+    /// Pair contracts from before `ask_asset_info` was added to their
+    /// messages: constant product pairs before 1.3 and stableswap pairs before
+    /// 2.0. These infer the ask asset from the offer asset.
+    Legacy,
+    /// Pair contracts accepting `ask_asset_info`, which stableswap pairs of
+    /// more than two assets require.
+    #[default]
+    Current,
+}
+
+impl PairVersion {
+    /// Queries the cw2 contract info of `pair_addr` and returns the version of
+    /// the pair contract of type `pair_type`. Returns `None` if the contract
+    /// has no cw2 info.
+    pub fn query(
+        querier: &QuerierWrapper,
+        pair_addr: &Addr,
+        pair_type: &PairType,
+    ) -> StdResult<Option<Self>> {
+        let Some(raw) = querier.query_wasm_raw(pair_addr.to_string(), b"contract_info".to_vec())?
+        else {
+            return Ok(None);
+        };
+        let info: ContractVersion = from_json(raw)?;
+        Self::from_version_str(pair_type, &info.version).map(Some)
+    }
+
+    /// Parses a semver version string such as `"2.1.4"` of a pair contract of
+    /// type `pair_type` into a `PairVersion`. Concentrated liquidity and other
+    /// custom pairs accept `ask_asset_info` in all versions.
+    pub fn from_version_str(pair_type: &PairType, version: &str) -> StdResult<Self> {
+        let mut parts = version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse::<u64>().ok());
+        let (Some(Some(major)), Some(Some(minor))) = (parts.next(), parts.next()) else {
+            return Err(StdError::generic_err(format!(
+                "Invalid pair contract version: {}",
+                version
+            )));
+        };
+        let legacy = match pair_type {
+            PairType::Xyk {} => (major, minor) < (1, 3),
+            PairType::Stable {} => major < 2,
+            PairType::Custom(_) => false,
+        };
+        Ok(if legacy { Self::Legacy } else { Self::Current })
+    }
+
+    /// Returns true if swap messages of the pair contract accept
+    /// `ask_asset_info`.
+    pub fn supports_ask_asset_info(&self) -> bool {
+        matches!(self, Self::Current)
+    }
+}
+
+/// How the allowances of the cw20 assets provided to a pool are granted to
+/// the contract transferring them. Astroport pairs and the liquidity manager
+/// have no cw20 receive hook for providing liquidity, so the assets are always
+/// transferred with an allowance.
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum Cw20AllowanceMode {
+    /// The allowances expire after the current block. Fails if the provide
+    /// message executes in a later block than the one the messages were built
+    /// in, e.g. when they are built for an IBC packet or a delayed execution.
+    #[default]
+    ExpireNextBlock,
+    /// The allowances never expire and are removed by a `DecreaseAllowance`
+    /// message of the provided amount after the provide message, so that no
+    /// allowance is left over regardless of the block the messages execute
+    /// in.
+    DecreaseAfterProvide,
+}
+
+/// The `belief_price` and `max_spread` sent to an Astroport pair when
+/// swapping, which the pair uses to check the return of the swap.
+#[cw_serde]
+#[derive(Copy, Default)]
+pub struct SwapOptions {
+    /// The price of the offer asset in the ask asset the swap is expected to
+    /// happen at. If not set, the spread is computed against the pool price.
+    pub belief_price: Option<Decimal>,
+    /// The maximum spread from the belief price, or the pool price if
+    /// `belief_price` is not set. The pair uses its default if not set.
+    pub max_spread: Option<Decimal>,
+}
+
+impl SwapOptions {
+    /// Returns the options making the pair return at least `min_out` when
+    /// swapping `offer_amount`: a belief price of `offer_amount / min_out`
+    /// with zero max spread. The belief price is rounded up, so that the
+    /// return the pair requires never exceeds `min_out`.
+    ///
+    /// If `min_out` is zero, no belief price is set and the max spread is the
+    /// maximum allowed by the pair, as any return is acceptable.
+    pub fn from_min_out(offer_amount: Uint128, min_out: Uint128) -> StdResult<Self> {
+        if min_out.is_zero() {
+            return Ok(Self {
+                belief_price: None,
+                max_spread: Some(Decimal::from_str(MAX_ALLOWED_SLIPPAGE)?),
+            });
+        }
+        let belief_price = Decimal::checked_from_ratio(offer_amount, min_out)
+            .map_err(|e| StdError::generic_err(format!("Invalid belief price: {e}")))?;
+        let belief_price = if belief_price * min_out < offer_amount {
+            belief_price + Decimal::raw(1)
+        } else {
+            belief_price
+        };
+        Ok(Self {
+            belief_price: Some(belief_price),
+            max_spread: Some(Decimal::zero()),
+        })
+    }
+}
+
+/// A snapshot of the invariant of an Astroport pool, see
+/// [`AstroportPool::query_invariant`].
+#[cw_serde]
+#[derive(Copy)]
+pub struct PoolInvariant {
+    /// `sqrt(x * y)` for constant product pairs, `D` for stableswap pairs
+    pub invariant: Uint256,
+    /// The total supply of LP tokens
+    pub total_share: Uint128,
+}
+
+impl PoolInvariant {
+    /// Returns the invariant per LP token, or zero for an empty pool.
+    pub fn per_share(&self) -> Decimal256 {
+        if self.total_share.is_zero() {
+            return Decimal256::zero();
+        }
+        Decimal256::from_ratio(self.invariant, self.total_share)
+    }
+
+    /// Checks that the invariant per LP token of `after` did not decrease by
+    /// more than the fraction `tolerance` compared to `self`.
+    ///
+    /// Swaps leave their fees in the pool, and providing and withdrawing
+    /// liquidity round in favour of the pool, so none of them should decrease
+    /// the invariant per LP token. A small `tolerance` absorbs the rounding of
+    /// `D`, which the stableswap pair computes iteratively.
+    pub fn verify(&self, after: &Self, tolerance: Decimal) -> Result<(), CwDexError> {
+        let before = self.per_share();
+        let after = after.per_share();
+        if after < before * (Decimal256::one() - Decimal256::from(tolerance.min(Decimal::one()))) {
+            return Err(CwDexError::InvariantDecreased { before, after });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AstroportPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "astroport:pair {}/{}", self.pair_addr, self.pair_type)
+    }
 }
 
 impl AstroportPool {
     /// Creates a new instance of `AstroportPool`
     ///
+    /// The version of the pair contract is detected from its cw2 contract info.
+    /// Pair contracts without it are assumed to be [`PairVersion::Current`],
+    /// use [`AstroportPool::new_with_version`] to override the version.
+    ///
     /// Arguments:
     /// - `pair_addr`: The address of the pair contract associated with the pool
     pub fn new(deps: Deps, pair_addr: Addr, liquidity_manager: Addr) -> StdResult<Self> {
+        let mut pool =
+            Self::new_with_version(deps, pair_addr, liquidity_manager, PairVersion::default())?;
+        if let Some(version) = PairVersion::query(&deps.querier, &pool.pair_addr, &pool.pair_type)?
+        {
+            pool.pair_version = version;
+        }
+        Ok(pool)
+    }
+
+    /// Creates a new instance of `AstroportPool` for a pair contract of the
+    /// given version, skipping version detection. Useful for legacy pair
+    /// contracts missing cw2 contract info.
+    ///
+    /// Arguments:
+    /// - `pair_addr`: The address of the pair contract associated with the pool
+    /// - `pair_version`: The version of the pair contract
+    pub fn new_with_version(
+        deps: Deps,
+        pair_addr: Addr,
+        liquidity_manager: Addr,
+        pair_version: PairVersion,
+    ) -> StdResult<Self> {
         let pair_info = deps
             .querier
             .query_wasm_smart::<PairInfo>(pair_addr.clone(), &PairQueryMsg::Pair {})?;
 
-        // Validate pair type. We only support XYK, stable swap, and PCL pools
-        match &pair_info.pair_type {
-            PairType::Custom(t) => match t.as_str() {
-                "concentrated" => Ok(()),
-                "astroport-pair-xyk-sale-tax" => Ok(()),
-                _ => Err(StdError::generic_err("Custom pair type is not supported")),
-            },
-            _ => Ok(()),
-        }?;
+        check_pair_type(&pair_info.pair_type)?;
 
         Ok(Self {
             pair_addr,
@@ -65,9 +294,143 @@ impl AstroportPool {
             pool_assets: pair_info.asset_infos.into_elementwise(),
             pair_type: pair_info.pair_type,
             liquidity_manager,
+            pair_version,
+            simulation_mode: SimulationMode::default(),
+            provide_refund_reply_id: ASTROPORT_PROVIDE_REFUND_REPLY_ID,
+            chain_profile: ChainProfile::default(),
+            allowance_mode: Cw20AllowanceMode::default(),
+            asset_conversion: AssetConversion::default(),
         })
     }
 
+    /// Creates an `AstroportPool` without querying the pair contract, e.g. in
+    /// tests or migrations where only the addresses are available. The pair
+    /// contract is assumed to be [`PairVersion::Current`]. Use
+    /// [`AstroportPool::validate`] to check the pool against the pair contract
+    /// later.
+    pub fn unchecked(
+        pair_addr: Addr,
+        lp_token_addr: Addr,
+        pool_assets: Vec<AssetInfo>,
+        pair_type: PairType,
+        liquidity_manager: Addr,
+    ) -> Self {
+        Self {
+            pair_addr,
+            lp_token_addr,
+            pool_assets,
+            pair_type,
+            liquidity_manager,
+            pair_version: PairVersion::default(),
+            simulation_mode: SimulationMode::default(),
+            provide_refund_reply_id: ASTROPORT_PROVIDE_REFUND_REPLY_ID,
+            chain_profile: ChainProfile::default(),
+            allowance_mode: Cw20AllowanceMode::default(),
+            asset_conversion: AssetConversion::default(),
+        }
+    }
+
+    /// Checks that the LP token, assets and pair type of the pool match the
+    /// pair contract, that the pair type is supported, and that the
+    /// [`PairVersion`] matches the cw2 contract info of the pair contract if it
+    /// has any. Useful for pools created with [`AstroportPool::unchecked`].
+    pub fn validate(&self, deps: Deps) -> StdResult<()> {
+        let pair_info = deps
+            .querier
+            .query_wasm_smart::<PairInfo>(self.pair_addr.clone(), &PairQueryMsg::Pair {})?;
+        check_pair_type(&pair_info.pair_type)?;
+
+        let pool_assets: Vec<AssetInfo> = pair_info.asset_infos.into_elementwise();
+        if pair_info.liquidity_token != self.lp_token_addr
+            || pool_assets != self.pool_assets
+            || pair_info.pair_type != self.pair_type
+        {
+            return Err(StdError::generic_err(format!(
+                "Pool {self} does not match pair contract: expected LP token {}, assets {:?} and \
+                 pair type {}",
+                pair_info.liquidity_token, pool_assets, pair_info.pair_type
+            )));
+        }
+
+        let pair_version = PairVersion::query(&deps.querier, &self.pair_addr, &self.pair_type)?;
+        if let Some(pair_version) = pair_version.filter(|version| *version != self.pair_version) {
+            return Err(StdError::generic_err(format!(
+                "Pool {self} does not match pair contract: expected pair version {:?}",
+                pair_version
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the pool with its simulations computed as selected by `mode`.
+    /// [`SimulationMode::Local`] is supported for swaps in constant product
+    /// (*Xyk*) pools, and swap simulations of other pair types return an
+    /// error in that mode. A local simulation queries the reserves of the
+    /// pair, its config and the fee info of the factory instead of the
+    /// simulation of the pair, so it does not save gas.
+    pub fn with_simulation_mode(mut self, mode: SimulationMode) -> Self {
+        self.simulation_mode = mode;
+        self
+    }
+
+    /// Returns the pool with the provide liquidity message of
+    /// [`AstroportPool::provide_liquidity_tracking_refunds`] sent with reply
+    /// ID `id` instead of [`ASTROPORT_PROVIDE_REFUND_REPLY_ID`].
+    pub fn with_provide_refund_reply_id(mut self, id: u64) -> Self {
+        self.provide_refund_reply_id = id;
+        self
+    }
+
+    /// Returns the pool for the Astroport deployment described by `profile`.
+    /// If its LP tokens are token factory denoms, `lp_token_addr` must be the
+    /// denom of the LP token, which [`AstroportPool::new`] reads from the pair
+    /// contract.
+    pub fn with_chain_profile(mut self, profile: ChainProfile) -> Self {
+        self.chain_profile = profile;
+        self
+    }
+
+    /// Returns the type of the pool. Custom pair types other than
+    /// `concentrated` (PCL) are returned as [`PoolType::Custom`].
+    pub fn pool_type(&self) -> PoolType {
+        match &self.pair_type {
+            PairType::Xyk {} => PoolType::ConstantProduct,
+            PairType::Stable {} => PoolType::StableSwap,
+            PairType::Custom(name) if name == "concentrated" => PoolType::Concentrated,
+            PairType::Custom(name) => PoolType::Custom(name.clone()),
+        }
+    }
+
+    /// Returns the pool with the allowances of provided cw20 assets granted as
+    /// selected by `mode`.
+    pub fn with_allowance_mode(mut self, mode: Cw20AllowanceMode) -> Self {
+        self.allowance_mode = mode;
+        self
+    }
+
+    /// Returns `assets` to provide to the pool in the order of the pool assets,
+    /// with duplicate and zero amount entries handled as selected by
+    /// [`AstroportPool::asset_conversion`]. Used by both
+    /// [`Pool::provide_liquidity`] and [`Pool::simulate_provide_liquidity`],
+    /// so that the simulation matches the provide.
+    fn convert_provided_assets(
+        &self,
+        deps: Deps,
+        assets: AssetList,
+    ) -> Result<AssetList, CwDexError> {
+        let assets = self.asset_conversion.apply(assets.to_vec())?;
+        self.canonicalize_assets(deps, assets.into())
+    }
+
+    /// Returns the pool with duplicate and zero amount entries of the assets
+    /// provided to it handled as selected by `mode`, e.g.
+    /// [`AssetConversion::Lenient`] for the legacy behavior of passing them on
+    /// to `AssetList`, which merges them.
+    pub fn with_asset_conversion(mut self, mode: AssetConversion) -> Self {
+        self.asset_conversion = mode;
+        self
+    }
+
     /// Returns the matching pool given a LP token.
     ///
     /// Arguments:
@@ -101,11 +464,195 @@ impl AstroportPool {
         }
     }
 
+    /// Returns the pool of `lp_token` registered with the Astroport factory,
+    /// without knowing the address of its pair contract.
+    ///
+    /// The pair is the minter of a cw20 LP token, or the creator of a token
+    /// factory LP token with a denom of the form
+    /// `factory/{pair}/astroport/share`. Unlike
+    /// [`AstroportPool::get_pool_for_lp_token`], the pair is only accepted if
+    /// it is the pair the factory has registered for its assets and has
+    /// `lp_token` as LP token, so tokens of other contracts are rejected.
+    ///
+    /// Pools of token factory LP tokens get a [`ChainProfile::Custom`] with
+    /// native LP tokens and incentives, use
+    /// [`AstroportPool::with_chain_profile`] on chains without incentives.
+    ///
+    /// Returns [`CwDexError::NotLpToken`] if `lp_token` is not the LP token of
+    /// a pair registered with the factory.
+    ///
+    /// Arguments:
+    /// - `factory`: The Astroport factory address
+    /// - `liquidity_manager`: The Astroport liquidity manager address, used for
+    ///   the returned pool
+    pub fn get_pool_for_lp_token_from_factory(
+        deps: Deps,
+        lp_token: &AssetInfo,
+        factory: &Addr,
+        liquidity_manager: Addr,
+    ) -> Result<Self, CwDexError> {
+        let (pair_addr, chain_profile) = match lp_token {
+            AssetInfo::Cw20(address) => {
+                let minter = deps
+                    .querier
+                    .query_wasm_smart::<Option<MinterResponse>>(address, &Cw20QueryMsg::Minter {})
+                    .ok()
+                    .flatten()
+                    .ok_or(CwDexError::NotLpToken {})?;
+                (
+                    deps.api.addr_validate(&minter.minter)?,
+                    ChainProfile::Standard,
+                )
+            }
+            AssetInfo::Native(denom) => {
+                let pair = denom
+                    .strip_prefix("factory/")
+                    .and_then(|rest| rest.strip_suffix(NATIVE_LP_TOKEN_SUBDENOM))
+                    .ok_or(CwDexError::NotLpToken {})?;
+                let profile = ChainProfile::Custom {
+                    native_lp_token: true,
+                    incentives: true,
+                };
+                (deps.api.addr_validate(pair)?, profile)
+            }
+        };
+
+        // Anything can mint a cw20 token, so the pair is checked against the
+        // factory before it is trusted
+        let pair_info = deps
+            .querier
+            .query_wasm_smart::<PairInfo>(&pair_addr, &PairQueryMsg::Pair {})
+            .map_err(|_| CwDexError::NotLpToken {})?;
+        let registered = deps
+            .querier
+            .query_wasm_smart::<PairInfo>(
+                factory,
+                &FactoryQueryMsg::Pair {
+                    asset_infos: pair_info.asset_infos,
+                },
+            )
+            .map_err(|_| CwDexError::NotLpToken {})?;
+        if registered.contract_addr != pair_addr
+            || registered.liquidity_token.as_str() != lp_token.to_string()
+        {
+            return Err(CwDexError::NotLpToken {});
+        }
+
+        Ok(Self::new(deps, pair_addr, liquidity_manager)?.with_chain_profile(chain_profile))
+    }
+
+    /// Returns the pools of `asset_a` and `asset_b` registered with the
+    /// Astroport factory. The factory registers at most one pair per
+    /// combination of assets, so the returned list has at most one element,
+    /// and none if the factory has no pair of the assets. Other errors of the
+    /// factory query are returned.
+    ///
+    /// Arguments:
+    /// - `factory`: The Astroport factory address
+    /// - `liquidity_manager`: The Astroport liquidity manager address, used for
+    ///   the returned pools
+    pub fn find_pools(
+        deps: Deps,
+        factory: &Addr,
+        liquidity_manager: &Addr,
+        asset_a: &AssetInfo,
+        asset_b: &AssetInfo,
+    ) -> StdResult<Vec<Self>> {
+        // The factory returns a not found error if no pair of the assets exists
+        let pair_info = deps.querier.query_wasm_smart::<PairInfo>(
+            factory.to_string(),
+            &FactoryQueryMsg::Pair {
+                asset_infos: vec![asset_a.clone().into(), asset_b.clone().into()],
+            },
+        );
+        match pair_info {
+            Ok(pair_info) => Ok(vec![Self::new(
+                deps,
+                pair_info.contract_addr,
+                liquidity_manager.clone(),
+            )?]),
+            Err(err) if is_not_found(&err) => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Returns the total supply of the associated LP token
     pub fn query_lp_token_supply(&self, querier: &QuerierWrapper) -> StdResult<Uint128> {
+        if self.chain_profile.native_lp_token() {
+            // The pair tracks the supply of its token factory LP token
+            return Ok(self.query_pool_info(querier)?.total_share);
+        }
         query_supply(querier, self.lp_token_addr.to_owned())
     }
 
+    /// Simulates swapping `offer_asset` in a constant product pool with the
+    /// math of the Astroport xyk pair, from the current reserves and the swap
+    /// fee of the pool.
+    fn simulate_xyk_swap_locally(
+        &self,
+        deps: Deps,
+        offer_asset: &Asset,
+        ask_asset_info: &AssetInfo,
+    ) -> StdResult<Uint128> {
+        let reserves = from_astro_assets(
+            self.query_pool_info(&deps.querier)?.assets,
+            AssetConversion::Merge,
+        )?;
+        let reserve = |info: &AssetInfo| {
+            reserves
+                .find(info)
+                .map(|asset| asset.amount)
+                .ok_or_else(|| StdError::generic_err(format!("{info} is not in pool {self}")))
+        };
+
+        math::compute_xyk_swap(
+            reserve(&offer_asset.info)?,
+            reserve(ask_asset_info)?,
+            offer_asset.amount,
+            self.query_swap_fee(&deps.querier)?,
+        )
+    }
+
+    /// Simulates swapping `offer_asset` for `ask_asset_info`, see
+    /// [`Pool::simulate_swap`].
+    ///
+    /// Returns [`CwDexError::AssetNotInPool`] if either asset is not in the
+    /// pool, checked before querying the pair contract, whose simulation
+    /// would otherwise fail with an opaque error.
+    pub fn try_simulate_swap(
+        &self,
+        deps: Deps,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+    ) -> Result<Uint128, CwDexError> {
+        self.validate_swap_assets(deps, &offer_asset.info, &ask_asset_info)?;
+
+        if self.simulation_mode == SimulationMode::Local {
+            if self.pair_type != (PairType::Xyk {}) {
+                return Err(StdError::generic_err(format!(
+                    "Local swap simulation is not supported for {} pairs",
+                    self.pair_type
+                ))
+                .into());
+            }
+            return Ok(self.simulate_xyk_swap_locally(deps, &offer_asset, &ask_asset_info)?);
+        }
+
+        Ok(deps
+            .querier
+            .query::<SimulationResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: self.pair_addr.to_string(),
+                msg: to_json_binary(&PairQueryMsg::Simulation {
+                    offer_asset: offer_asset.into(),
+                    ask_asset_info: self
+                        .pair_version
+                        .supports_ask_asset_info()
+                        .then(|| ask_asset_info.into()),
+                })?,
+            }))?
+            .return_amount)
+    }
+
     /// Queries the pair contract for the current pool state
     pub fn query_pool_info(&self, querier: &QuerierWrapper) -> StdResult<PoolResponse> {
         querier.query::<PoolResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
@@ -113,127 +660,276 @@ impl AstroportPool {
             msg: to_json_binary(&PairQueryMsg::Pool {})?,
         }))
     }
-}
 
-impl Pool for AstroportPool {
-    fn provide_liquidity(
+    /// Queries the current cumulative prices of the pair, e.g. to save as an
+    /// observation in the contract's state and compute a time weighted average
+    /// price against a later one with [`CumulativePriceSnapshot::twap`].
+    pub fn query_cumulative_prices(
         &self,
-        _deps: Deps,
+        querier: &QuerierWrapper,
+        env: &Env,
+    ) -> StdResult<CumulativePriceSnapshot> {
+        CumulativePriceSnapshot::query(querier, env, &self.pair_addr)
+    }
+
+    /// Returns the amounts of `assets` that would not earn LP tokens if
+    /// provided now, rounded up.
+    ///
+    /// Constant product pairs mint LP tokens for the provided assets in the
+    /// ratio of the pool reserves only, and the liquidity manager refunds the
+    /// excess of the over-supplied asset, see [`ProvideRefundTracker`]. Other
+    /// pair types use all provided assets, so an empty list is returned for
+    /// them, as well as for empty pools.
+    pub fn simulate_unused_assets(
+        &self,
+        deps: Deps,
+        assets: AssetList,
+    ) -> Result<AssetList, CwDexError> {
+        let is_xyk = match &self.pair_type {
+            PairType::Xyk {} => true,
+            PairType::Custom(t) => t == "astroport-pair-xyk-sale-tax",
+            _ => false,
+        };
+        let assets = self.canonicalize_assets(deps, assets)?;
+        let pool_info = self.query_pool_info(&deps.querier)?;
+        if !is_xyk || pool_info.total_share.is_zero() {
+            return Ok(AssetList::new());
+        }
+
+        let reserves: Vec<(Uint256, Uint256)> = self
+            .pool_assets
+            .iter()
+            .map(|info| {
+                let provided = assets.find(info).map(|a| a.amount).unwrap_or_default();
+                let reserve = pool_info
+                    .assets
+                    .iter()
+                    .find(|a| AssetInfo::from(a.info.clone()) == *info)
+                    .map(|a| a.amount)
+                    .unwrap_or_default();
+                (provided.into(), reserve.into())
+            })
+            .collect();
+
+        // The limiting asset is the one with the smallest ratio of provided
+        // amount to reserve
+        let Some(&(limit_provided, limit_reserve)) = reserves
+            .iter()
+            .min_by(|(a, ra), (b, rb)| (*a * *rb).cmp(&(*b * *ra)))
+        else {
+            return Ok(AssetList::new());
+        };
+        if limit_reserve.is_zero() {
+            return Ok(AssetList::new());
+        }
+
+        let mut unused = vec![];
+        for (info, (provided, reserve)) in self.pool_assets.iter().zip(reserves) {
+            let used = reserve.multiply_ratio(limit_provided, limit_reserve);
+            let amount = uint256_to_uint128(provided.saturating_sub(used), "unused amount")?;
+            if !amount.is_zero() {
+                unused.push(Asset::new(info.clone(), amount));
+            }
+        }
+        Ok(unused.into())
+    }
+
+    /// Same as [`Pool::provide_liquidity`], but sends the provide liquidity
+    /// message as a submessage with reply ID `provide_refund_reply_id`
+    /// ([`ASTROPORT_PROVIDE_REFUND_REPLY_ID`] by default), and returns a
+    /// [`ProvideRefundTracker`] that the contract must save and use to handle
+    /// the reply, measuring the assets refunded by the pair contract.
+    pub fn provide_liquidity_tracking_refunds(
+        &self,
+        deps: Deps,
         env: &Env,
         assets: AssetList,
         min_out: Uint128,
-    ) -> Result<Response, CwDexError> {
-        let (funds, cw20s) = separate_natives_and_cw20s(&assets);
+    ) -> Result<(Response, ProvideRefundTracker), CwDexError> {
+        let assets = self.canonicalize_assets(deps, assets)?;
+
+        let mut expected_balances = vec![];
+        for info in &self.pool_assets {
+            let balance = info.query_balance(&deps.querier, &env.contract.address)?;
+            let provided = assets.find(info).map(|a| a.amount).unwrap_or_default();
+            expected_balances.push(Asset::new(info.clone(), balance.checked_sub(provided)?));
+        }
+        let tracker = ProvideRefundTracker {
+            expected_balances,
+            unused: self.simulate_unused_assets(deps, assets.clone())?,
+        };
+
+        let res = self.provide_liquidity(deps, env, assets, min_out)?;
+        let res = reply_on_last_message(res, self.provide_refund_reply_id)?;
+
+        Ok((res, tracker))
+    }
+
+    /// Returns the messages providing `assets` to the pool, receiving at least
+    /// `min_out` LP tokens, without any queries or validation: an allowance
+    /// for each cw20 asset, and the provide message of the liquidity manager,
+    /// or of the pair if its LP token is a token factory denom. The native
+    /// assets are sent as funds. With
+    /// [`Cw20AllowanceMode::DecreaseAfterProvide`] the allowances are
+    /// removed after the provide message.
+    ///
+    /// For integrators composing messages from their own cached state.
+    /// [`Pool::provide_liquidity`] checks and canonicalizes `assets` before
+    /// building these messages. `assets` must only contain assets of the pool,
+    /// at most once each, sorted as the funds of a message.
+    pub fn build_provide_msgs(
+        &self,
+        env: &Env,
+        assets: &AssetList,
+        min_out: Uint128,
+    ) -> StdResult<Vec<CosmosMsg>> {
+        let (funds, cw20s) = separate_natives_and_cw20s(assets);
+
+        // Pairs with token factory LP tokens are provided to directly
+        let spender = if self.chain_profile.native_lp_token() {
+            &self.pair_addr
+        } else {
+            &self.liquidity_manager
+        };
 
         // Increase allowance on all Cw20s
-        let allowance_msgs: Vec<CosmosMsg> = cw20s
-            .into_iter()
+        let expires = match self.allowance_mode {
+            Cw20AllowanceMode::ExpireNextBlock => Expiration::AtHeight(env.block.height + 1),
+            Cw20AllowanceMode::DecreaseAfterProvide => Expiration::Never {},
+        };
+        let allowance_msg = |asset: &Cw20Coin, msg: Cw20ExecuteMsg| -> StdResult<CosmosMsg> {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: asset.address.clone(),
+                msg: to_json_binary(&msg)?,
+                funds: vec![],
+            }))
+        };
+        let mut msgs: Vec<CosmosMsg> = cw20s
+            .iter()
             .map(|asset| {
-                Ok(CosmosMsg::Wasm(WasmMsg::Execute {
-                    contract_addr: asset.address,
-                    msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
-                        spender: self.liquidity_manager.to_string(),
+                allowance_msg(
+                    asset,
+                    Cw20ExecuteMsg::IncreaseAllowance {
+                        spender: spender.to_string(),
                         amount: asset.amount,
-                        expires: Some(Expiration::AtHeight(env.block.height + 1)),
-                    })?,
-                    funds: vec![],
-                }))
+                        expires: Some(expires),
+                    },
+                )
             })
             .collect::<StdResult<Vec<_>>>()?;
 
         // Liquidity manager requires assets vec to contain all assets in the pool
-        let mut assets_vec = assets.to_vec();
-        for pool_asset_info in &self.pool_assets {
-            if !assets_vec.iter().any(|x| &x.info == pool_asset_info) {
-                assets_vec.push(Asset::new(pool_asset_info.clone(), Uint128::zero()));
-            }
-        }
+        let assets_vec: Vec<Asset> = self
+            .pool_assets
+            .iter()
+            .map(|info| {
+                assets
+                    .find(info)
+                    .cloned()
+                    .unwrap_or_else(|| Asset::new(info.clone(), Uint128::zero()))
+            })
+            .collect();
 
         // Create the provide liquidity message
-        let provide_liquidity_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: self.liquidity_manager.to_string(),
-            msg: to_json_binary(&liquidity_manager::ExecuteMsg::ProvideLiquidity {
+        let slippage_tolerance = Some(Decimal::from_str(MAX_ALLOWED_SLIPPAGE)?);
+        let msg = if self.chain_profile.native_lp_token() {
+            to_json_binary(&NativeLpPairExecuteMsg::ProvideLiquidity {
+                assets: assets_vec.into_elementwise(),
+                slippage_tolerance,
+                auto_stake: Some(false),
+                receiver: None,
+                min_lp_to_receive: Some(min_out),
+            })?
+        } else {
+            to_json_binary(&liquidity_manager::ExecuteMsg::ProvideLiquidity {
                 pair_addr: self.pair_addr.to_string(),
                 min_lp_to_receive: Some(min_out),
                 pair_msg: astroport::pair::ExecuteMsg::ProvideLiquidity {
                     assets: assets_vec.into_elementwise(),
-                    slippage_tolerance: Some(Decimal::from_str(MAX_ALLOWED_SLIPPAGE)?),
+                    slippage_tolerance,
                     auto_stake: Some(false),
                     receiver: None,
                 },
-            })?,
+            })?
+        };
+        msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: spender.to_string(),
+            msg,
             funds,
-        });
+        }));
 
-        let event = Event::new("apollo/cw-dex/provide_liquidity")
-            .add_attribute("pair_addr", &self.pair_addr)
-            .add_attribute("assets", format!("{:?}", assets));
+        // Remove whatever is left of the allowances. The cw20 contract removes
+        // an allowance that is decreased by at least its amount.
+        if self.allowance_mode == Cw20AllowanceMode::DecreaseAfterProvide {
+            for asset in &cw20s {
+                msgs.push(allowance_msg(
+                    asset,
+                    Cw20ExecuteMsg::DecreaseAllowance {
+                        spender: spender.to_string(),
+                        amount: asset.amount,
+                        expires: None,
+                    },
+                )?);
+            }
+        }
 
-        Ok(Response::new()
-            .add_messages(allowance_msgs)
-            .add_message(provide_liquidity_msg)
-            .add_event(event))
+        Ok(msgs)
     }
 
-    fn withdraw_liquidity(
-        &self,
-        _deps: Deps,
-        _env: &Env,
-        asset: Asset,
-        mut min_out: AssetList,
-    ) -> Result<Response, CwDexError> {
-        if let AssetInfoBase::Cw20(token_addr) = &asset.info {
-            // Liquidity manager requires min_out to contain all assets in the pool
-            for asset in &self.pool_assets {
-                if min_out.find(asset).is_none() {
-                    // Add one unit as AssetList does not allow zero amounts (calls self.purge on
-                    // add)
-                    min_out.add(&Asset::new(asset.clone(), Uint128::one()))?;
-                }
-            }
-
-            let withdraw_liquidity = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: token_addr.to_string(),
-                msg: to_json_binary(&Cw20ExecuteMsg::Send {
-                    contract: self.liquidity_manager.to_string(),
-                    amount: asset.amount,
-                    msg: to_json_binary(&liquidity_manager::Cw20HookMsg::WithdrawLiquidity {
-                        pair_msg: astroport::pair::Cw20HookMsg::WithdrawLiquidity {
-                            // This field is currently not used...
-                            assets: vec![],
-                        },
-                        min_assets_to_receive: min_out.to_vec().into_elementwise(),
-                    })?,
+    /// Returns the message withdrawing `amount` LP tokens from the pool,
+    /// receiving at least `min_out`, without any queries or validation. The
+    /// liquidity manager and pairs with token factory LP tokens require
+    /// `min_out` to contain each asset of the pool, which
+    /// [`Pool::withdraw_liquidity`] ensures before building this message.
+    pub fn build_withdraw_msg(&self, amount: Uint128, min_out: &AssetList) -> StdResult<CosmosMsg> {
+        if self.chain_profile.native_lp_token() {
+            return Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: self.pair_addr.to_string(),
+                msg: to_json_binary(&NativeLpPairExecuteMsg::WithdrawLiquidity {
+                    assets: vec![],
+                    min_assets_to_receive: Some(min_out.to_vec().into_elementwise()),
                 })?,
-                funds: vec![],
-            });
-
-            let event = Event::new("apollo/cw-dex/withdraw_liquidity")
-                .add_attribute("pair_addr", &self.pair_addr)
-                .add_attribute("asset", format!("{:?}", asset))
-                .add_attribute("token_amount", asset.amount);
-
-            Ok(Response::new()
-                .add_message(withdraw_liquidity)
-                .add_event(event))
-        } else {
-            Err(CwDexError::InvalidInAsset { a: asset })
+                funds: vec![Coin::new(amount.u128(), self.lp_token_addr.as_str())],
+            }));
         }
+
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: self.lp_token_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: self.liquidity_manager.to_string(),
+                amount,
+                msg: to_json_binary(&liquidity_manager::Cw20HookMsg::WithdrawLiquidity {
+                    pair_msg: astroport::pair::Cw20HookMsg::WithdrawLiquidity {
+                        // This field is currently not used...
+                        assets: vec![],
+                    },
+                    min_assets_to_receive: min_out.to_vec().into_elementwise(),
+                })?,
+            })?,
+            funds: vec![],
+        }))
     }
 
-    fn swap(
+    /// Returns the message swapping `offer_asset` for `ask_asset_info` in the
+    /// pair with `options`, sending the return to `recipient`, without any
+    /// queries or validation. See [`AstroportPool::swap_with_options`] for the
+    /// validated version.
+    pub fn build_swap_msg(
         &self,
-        _deps: Deps,
-        env: &Env,
-        offer_asset: Asset,
-        ask_asset_info: AssetInfo,
-        min_out: Uint128,
-    ) -> Result<Response, CwDexError> {
-        // Setting belief price to the minimium acceptable return and max spread to zero
-        // simplifies things Astroport will make the best possible swap that
-        // returns at least `min_out`.
-        let belief_price = Some(Decimal::from_ratio(offer_asset.amount, min_out));
-        let swap_msg = match &offer_asset.info {
+        recipient: &Addr,
+        offer_asset: &Asset,
+        ask_asset_info: &AssetInfo,
+        options: SwapOptions,
+    ) -> StdResult<CosmosMsg> {
+        let SwapOptions {
+            belief_price,
+            max_spread,
+        } = options;
+        let ask_asset_info_msg = self
+            .pair_version
+            .supports_ask_asset_info()
+            .then(|| ask_asset_info.clone().into());
+        match &offer_asset.info {
             AssetInfo::Native(_) => {
                 let asset = offer_asset.clone().into();
                 wasm_execute(
@@ -241,9 +937,9 @@ impl Pool for AstroportPool {
                     &PairExecuteMsg::Swap {
                         offer_asset: asset,
                         belief_price,
-                        max_spread: Some(Decimal::zero()),
-                        to: Some(env.contract.address.to_string()),
-                        ask_asset_info: Some(ask_asset_info.to_owned().into()),
+                        max_spread,
+                        to: Some(recipient.to_string()),
+                        ask_asset_info: ask_asset_info_msg,
                     },
                     vec![offer_asset.clone().try_into()?],
                 )
@@ -255,25 +951,280 @@ impl Pool for AstroportPool {
                     amount: offer_asset.amount,
                     msg: to_json_binary(&PairCw20HookMsg::Swap {
                         belief_price,
-                        max_spread: Some(Decimal::zero()),
-                        to: Some(env.contract.address.to_string()),
-                        ask_asset_info: Some(ask_asset_info.to_owned().into()),
+                        max_spread,
+                        to: Some(recipient.to_string()),
+                        ask_asset_info: ask_asset_info_msg,
                     })?,
                 },
                 vec![],
             ),
-        }?;
+        }
+        .map(Into::into)
+    }
+
+    /// Checks that the token contracts of the cw20 assets of the pool answer
+    /// a `TokenInfo` query. Returns [`CwDexError::AssetUnavailable`]
+    /// identifying the first asset whose token contract fails to respond,
+    /// e.g. because it has been migrated to a broken code or fails all
+    /// queries while paused.
+    ///
+    /// This does not check that the tokens can be transferred. A token that
+    /// blocks transfers, e.g. because it is paused or blacklists the pair,
+    /// while still answering queries passes the check, and the operation
+    /// fails when its messages execute.
+    ///
+    /// With the `preflight-checks` feature enabled this is called before
+    /// providing liquidity, withdrawing liquidity and swapping.
+    pub fn check_cw20_assets(&self, querier: &QuerierWrapper) -> Result<(), CwDexError> {
+        for asset in &self.pool_assets {
+            if let AssetInfo::Cw20(token_addr) = asset {
+                querier
+                    .query_wasm_smart::<TokenInfoResponse>(
+                        token_addr.to_string(),
+                        &Cw20QueryMsg::TokenInfo {},
+                    )
+                    .map_err(|e| CwDexError::AssetUnavailable {
+                        asset: asset.clone(),
+                        reason: e.to_string(),
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the pre-flight checks enabled by crate features.
+    fn preflight_checks(&self, _querier: &QuerierWrapper) -> Result<(), CwDexError> {
+        #[cfg(feature = "preflight-checks")]
+        self.check_cw20_assets(_querier)?;
+        Ok(())
+    }
+
+    /// Queries the fee charged on swaps in the pool. For concentrated liquidity
+    /// pools the fee is dynamic and this returns the lower bound `mid_fee`.
+    pub fn query_swap_fee(&self, querier: &QuerierWrapper) -> StdResult<Decimal> {
+        let config: ConfigResponse =
+            querier.query_wasm_smart(self.pair_addr.to_string(), &PairQueryMsg::Config {})?;
+
+        if self.pair_type == PairType::Custom("concentrated".to_string()) {
+            let params: ConcentratedPoolConfig = from_json(
+                config
+                    .params
+                    .ok_or_else(|| StdError::generic_err("Concentrated pool has no params"))?,
+            )?;
+            return Ok(params.mid_fee);
+        }
+
+        let fee_info: FeeInfoResponse = querier.query_wasm_smart(
+            config.factory_addr.to_string(),
+            &FactoryQueryMsg::FeeInfo {
+                pair_type: self.pair_type.clone(),
+            },
+        )?;
+
+        Ok(Decimal::from_ratio(fee_info.total_fee_bps, 10_000u16))
+    }
+
+    /// Queries the fee share config of the pair. Pairs with fee sharing
+    /// enabled send a share of the commission of every swap to an external
+    /// address, e.g. a dev fund. Returns `None` if fee sharing is disabled or
+    /// the pair has no params.
+    ///
+    /// The shared fee is paid out of the commission, so it reduces the fees
+    /// accruing to liquidity providers but not the amount returned by a swap.
+    /// [`Pool::simulate_swap`] therefore needs no adjustment for it, in either
+    /// [`SimulationMode`].
+    pub fn query_fee_share(&self, querier: &QuerierWrapper) -> StdResult<Option<FeeShareConfig>> {
+        let config: ConfigResponse =
+            querier.query_wasm_smart(self.pair_addr.to_string(), &PairQueryMsg::Config {})?;
+        let Some(params) = config.params else {
+            return Ok(None);
+        };
+
+        Ok(match &self.pair_type {
+            PairType::Xyk {} => from_json::<XYKPoolConfig>(params)?.fee_share,
+            PairType::Stable {} => from_json::<StablePoolConfig>(params)?.fee_share,
+            PairType::Custom(t) if t == "concentrated" => {
+                from_json::<ConcentratedPoolConfigV3>(params)?.fee_share
+            }
+            PairType::Custom(_) => None,
+        })
+    }
+
+    /// Queries the current invariant of the pool and the total supply of LP
+    /// tokens. The invariant is `sqrt(x * y)` for constant product pairs and
+    /// `D` as computed by the pair contract for stableswap pairs. Concentrated
+    /// liquidity pairs rescale their invariant as the price moves, so they are
+    /// not supported.
+    ///
+    /// Intended as a sanity check of the pool state: snapshot the invariant
+    /// before an operation and [`PoolInvariant::verify`] it against a snapshot
+    /// taken after, e.g. in integration tests or in a reply.
+    pub fn query_invariant(&self, querier: &QuerierWrapper) -> Result<PoolInvariant, CwDexError> {
+        let pool_info = self.query_pool_info(querier)?;
+        let invariant = match &self.pair_type {
+            PairType::Stable {} => Uint256::from(querier.query_wasm_smart::<Uint128>(
+                self.pair_addr.to_string(),
+                &PairQueryMsg::QueryComputeD {},
+            )?),
+            PairType::Xyk {} => xyk_invariant(&pool_info.assets)?,
+            PairType::Custom(t) if t == "astroport-pair-xyk-sale-tax" => {
+                xyk_invariant(&pool_info.assets)?
+            }
+            PairType::Custom(t) => {
+                return Err(StdError::generic_err(format!(
+                    "Invariant of pair type {t} is not supported"
+                ))
+                .into())
+            }
+        };
+
+        Ok(PoolInvariant {
+            invariant,
+            total_share: pool_info.total_share,
+        })
+    }
+
+    /// Swaps `offer_asset` for `ask_asset_info` passing `options` to the
+    /// pair, instead of the options derived from `min_out` by
+    /// [`Pool::swap`]. `min_out` is only used for the emitted event, so
+    /// `options` must enforce the minimum return.
+    pub fn swap_with_options(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+        options: SwapOptions,
+    ) -> Result<Response, CwDexError> {
+        self.validate_swap_assets(deps, &offer_asset.info, &ask_asset_info)?;
+        self.preflight_checks(&deps.querier)?;
+
+        let swap_msg = self.build_swap_msg(
+            &env.contract.address,
+            &offer_asset,
+            &ask_asset_info,
+            options,
+        )?;
+        let max_spread = options.max_spread;
         let event = Event::new("apollo/cw-dex/swap")
             .add_attribute("pair_addr", &self.pair_addr)
             .add_attribute("ask_asset", format!("{:?}", ask_asset_info))
             .add_attribute("offer_asset", format!("{:?}", offer_asset.info))
             .add_attribute("minimum_out_amount", min_out);
+        let event = match max_spread {
+            Some(max_spread) => event.add_attribute("max_spread", max_spread.to_string()),
+            None => event,
+        };
         Ok(Response::new().add_message(swap_msg).add_event(event))
     }
+}
+
+impl QueryPoolMetrics for AstroportPool {
+    fn query_pool_metrics(&self, deps: Deps) -> Result<PoolMetrics, CwDexError> {
+        let pool_info = self.query_pool_info(&deps.querier)?;
+
+        Ok(PoolMetrics {
+            lp_token: self.lp_token(),
+            pool_type: self.pool_type(),
+            reserves: from_astro_assets(pool_info.assets, AssetConversion::Merge)?,
+            total_shares: pool_info.total_share,
+            swap_fee: self.query_swap_fee(&deps.querier)?,
+            // Astroport pairs do not charge a fee on withdrawals
+            exit_fee: Decimal::zero(),
+            // Emissions are handled by the incentives contract, see
+            // `AstroportStaking::query_reward_emissions`.
+            emissions: vec![],
+        })
+    }
+}
+
+impl Pool for AstroportPool {
+    fn provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.preflight_checks(&deps.querier)?;
+
+        let assets = self.convert_provided_assets(deps, assets)?;
+
+        // Constant product pairs refund the assets exceeding the ratio of the
+        // pool reserves
+        let mut used = assets.clone();
+        used.deduct_many(&self.simulate_unused_assets(deps, assets.clone())?)?;
+        // The liquidity manager can not simulate provisions to custom pairs
+        let expected_lp = match self.pair_type {
+            PairType::Custom(_) => None,
+            _ => Some(
+                self.simulate_provide_liquidity(deps, env, assets.clone())?
+                    .amount,
+            ),
+        };
+
+        let msgs = self.build_provide_msgs(env, &assets, min_out)?;
+
+        let event = provide_liquidity_event(self, &used, expected_lp, min_out)
+            .add_attribute("pair_addr", &self.pair_addr)
+            .add_attribute("assets", format!("{:?}", assets));
+
+        Ok(Response::new().add_messages(msgs).add_event(event))
+    }
+
+    fn withdraw_liquidity(
+        &self,
+        deps: Deps,
+        _env: &Env,
+        asset: Asset,
+        min_out: AssetList,
+    ) -> Result<Response, CwDexError> {
+        if asset.info != self.lp_token() {
+            return Err(CwDexError::InvalidInAsset { a: asset });
+        }
+        self.preflight_checks(&deps.querier)?;
+
+        let mut min_out = self.canonicalize_assets(deps, min_out)?;
+
+        // The liquidity manager and pairs with token factory LP tokens require
+        // min_out to contain all assets in the pool
+        for asset in &self.pool_assets {
+            if min_out.find(asset).is_none() {
+                // Add one unit as AssetList does not allow zero amounts (calls self.purge on
+                // add)
+                min_out.add(&Asset::new(asset.clone(), Uint128::one()))?;
+            }
+        }
+        let min_out = self.canonicalize_assets(deps, min_out)?;
+
+        let event = Event::new("apollo/cw-dex/withdraw_liquidity")
+            .add_attribute("pair_addr", &self.pair_addr)
+            .add_attribute("asset", format!("{:?}", asset))
+            .add_attribute("token_amount", asset.amount);
+
+        let withdraw_liquidity = self.build_withdraw_msg(asset.amount, &min_out)?;
+
+        Ok(Response::new()
+            .add_message(withdraw_liquidity)
+            .add_event(event))
+    }
+
+    fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        let options = SwapOptions::from_min_out(offer_asset.amount, min_out)?;
+        self.swap_with_options(deps, env, offer_asset, ask_asset_info, min_out, options)
+    }
 
     fn get_pool_liquidity(&self, deps: Deps) -> Result<AssetList, CwDexError> {
         let resp = self.query_pool_info(&deps.querier)?;
-        Ok(resp.assets.to_vec().into())
+        from_astro_assets(resp.assets, AssetConversion::Merge)
     }
 
     fn simulate_provide_liquidity(
@@ -282,25 +1233,41 @@ impl Pool for AstroportPool {
         _env: &Env,
         assets: AssetList,
     ) -> Result<Asset, CwDexError> {
-        let amount: Uint128 = deps.querier.query_wasm_smart(
-            self.liquidity_manager.to_string(),
-            &liquidity_manager::QueryMsg::SimulateProvide {
-                pair_addr: self.pair_addr.to_string(),
-                pair_msg: astroport::pair::ExecuteMsg::ProvideLiquidity {
-                    assets: assets.into(),
-                    slippage_tolerance: Some(Decimal::from_str(MAX_ALLOWED_SLIPPAGE)?),
-                    auto_stake: Some(false),
-                    receiver: None,
+        if let Some(asset) = assets
+            .iter()
+            .find(|asset| !self.pool_assets.contains(&asset.info))
+        {
+            return Err(CwDexError::AssetNotInPool {
+                asset: asset.info.clone(),
+            });
+        }
+        let assets = self.convert_provided_assets(deps, assets)?;
+        let assets = to_astro_assets(&assets, AssetConversion::Strict)?;
+        let slippage_tolerance = Some(Decimal::from_str(MAX_ALLOWED_SLIPPAGE)?);
+        let amount: Uint128 = if self.chain_profile.native_lp_token() {
+            deps.querier.query_wasm_smart(
+                self.pair_addr.to_string(),
+                &NativeLpPairQueryMsg::SimulateProvide {
+                    assets,
+                    slippage_tolerance,
                 },
-            },
-        )?;
-
-        let lp_token = Asset {
-            info: AssetInfo::Cw20(self.lp_token_addr.clone()),
-            amount,
+            )?
+        } else {
+            deps.querier.query_wasm_smart(
+                self.liquidity_manager.to_string(),
+                &liquidity_manager::QueryMsg::SimulateProvide {
+                    pair_addr: self.pair_addr.to_string(),
+                    pair_msg: astroport::pair::ExecuteMsg::ProvideLiquidity {
+                        assets,
+                        slippage_tolerance,
+                        auto_stake: Some(false),
+                        receiver: None,
+                    },
+                },
+            )?
         };
 
-        Ok(lp_token)
+        Ok(Asset::new(self.lp_token(), amount))
     }
 
     fn simulate_withdraw_liquidity(
@@ -308,40 +1275,351 @@ impl Pool for AstroportPool {
         deps: Deps,
         lp_token: &Asset,
     ) -> Result<AssetList, CwDexError> {
-        let assets: Vec<AstroAsset> = deps.querier.query_wasm_smart(
-            self.liquidity_manager.to_string(),
-            &liquidity_manager::QueryMsg::SimulateWithdraw {
-                pair_addr: self.pair_addr.to_string(),
-                lp_tokens: lp_token.amount,
-            },
-        )?;
+        if lp_token.info != self.lp_token() {
+            return Err(CwDexError::InvalidLpToken {});
+        }
+        let assets: Vec<AstroAsset> = if self.chain_profile.native_lp_token() {
+            deps.querier.query_wasm_smart(
+                self.pair_addr.to_string(),
+                &PairQueryMsg::Share {
+                    amount: lp_token.amount,
+                },
+            )?
+        } else {
+            deps.querier.query_wasm_smart(
+                self.liquidity_manager.to_string(),
+                &liquidity_manager::QueryMsg::SimulateWithdraw {
+                    pair_addr: self.pair_addr.to_string(),
+                    lp_tokens: lp_token.amount,
+                },
+            )?
+        };
 
-        Ok(assets.into())
+        from_astro_assets(assets, AssetConversion::Merge)
     }
 
+    /// Same as [`AstroportPool::try_simulate_swap`], with the error converted
+    /// into a [`StdError`].
     fn simulate_swap(
         &self,
         deps: Deps,
         offer_asset: Asset,
         ask_asset_info: AssetInfo,
     ) -> StdResult<Uint128> {
-        Ok(deps
-            .querier
-            .query::<SimulationResponse>(&QueryRequest::Wasm(WasmQuery::Smart {
-                contract_addr: self.pair_addr.to_string(),
-                msg: to_json_binary(&PairQueryMsg::Simulation {
-                    offer_asset: offer_asset.into(),
-                    ask_asset_info: Some(ask_asset_info.into()),
-                })?,
-            }))?
-            .return_amount)
+        Ok(self.try_simulate_swap(deps, offer_asset, ask_asset_info)?)
     }
 
     fn lp_token(&self) -> AssetInfo {
-        AssetInfoBase::Cw20(self.lp_token_addr.clone())
+        if self.chain_profile.native_lp_token() {
+            AssetInfo::native(self.lp_token_addr.as_str())
+        } else {
+            AssetInfoBase::Cw20(self.lp_token_addr.clone())
+        }
     }
 
     fn pool_assets(&self, _deps: Deps) -> StdResult<Vec<AssetInfo>> {
         Ok(self.pool_assets.clone())
     }
 }
+
+/// Checks that the pair type is supported. We only support XYK, stable swap,
+/// and PCL pools.
+fn check_pair_type(pair_type: &PairType) -> StdResult<()> {
+    match pair_type {
+        PairType::Custom(t) => match t.as_str() {
+            "concentrated" => Ok(()),
+            "astroport-pair-xyk-sale-tax" => Ok(()),
+            _ => Err(StdError::generic_err("Custom pair type is not supported")),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Returns the invariant `sqrt(x * y)` of a constant product pool with the
+/// reserves `assets`.
+fn xyk_invariant(assets: &[AstroAsset]) -> StdResult<Uint256> {
+    let [a, b] = assets else {
+        return Err(StdError::generic_err("Expected a pool with two assets"));
+    };
+    Ok(math::xyk_invariant(a.amount, b.amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use astroport::factory::PairType;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{
+        coins, from_json, to_json_binary, Addr, ContractResult, CosmosMsg, QuerierWrapper,
+        SystemResult, Uint128, WasmMsg, WasmQuery,
+    };
+    use cw20::{Cw20QueryMsg, TokenInfoResponse};
+    use cw_dex::traits::{Pool, SimulationMode};
+    use cw_dex::CwDexError;
+    use test_case::test_case;
+
+    use super::{AstroportPool, PairVersion};
+    use crate::assets::AssetConversion;
+    use crate::chain::{ChainProfile, NativeLpPairExecuteMsg};
+
+    fn pool(pair_type: PairType) -> AstroportPool {
+        AstroportPool::unchecked(
+            Addr::unchecked("pair"),
+            Addr::unchecked("lp_token"),
+            vec![AssetInfo::native("uluna"), AssetInfo::native("uatom")],
+            pair_type,
+            Addr::unchecked("liquidity_manager"),
+        )
+    }
+
+    fn not_in_pool() -> CwDexError {
+        CwDexError::AssetNotInPool {
+            asset: AssetInfo::native("uosmo"),
+        }
+    }
+
+    #[test_case(PairType::Xyk {}; "xyk")]
+    #[test_case(PairType::Stable {}; "stable")]
+    #[test_case(PairType::Custom("concentrated".to_string()); "pcl")]
+    fn test_simulate_wrong_assets(pair_type: PairType) {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let asset = |denom: &str| Asset::native(denom, 100u128);
+
+        for pool in [
+            pool(pair_type.clone()),
+            pool(pair_type).with_simulation_mode(SimulationMode::Local),
+        ] {
+            for (offer, ask) in [("uosmo", "uatom"), ("uluna", "uosmo")] {
+                assert_eq!(
+                    pool.try_simulate_swap(deps.as_ref(), asset(offer), AssetInfo::native(ask))
+                        .unwrap_err(),
+                    not_in_pool()
+                );
+                assert!(pool
+                    .simulate_swap(deps.as_ref(), asset(offer), AssetInfo::native(ask))
+                    .unwrap_err()
+                    .to_string()
+                    .contains("Asset is not in pool: uosmo"));
+            }
+
+            let assets = AssetList::from(vec![asset("uluna"), asset("uosmo")]);
+            assert_eq!(
+                pool.simulate_provide_liquidity(deps.as_ref(), &env, assets.clone())
+                    .unwrap_err(),
+                not_in_pool()
+            );
+            assert_eq!(
+                pool.simulate_unused_assets(deps.as_ref(), assets)
+                    .unwrap_err(),
+                not_in_pool()
+            );
+
+            assert_eq!(
+                pool.simulate_withdraw_liquidity(
+                    deps.as_ref(),
+                    &Asset::cw20(Addr::unchecked("other_lp_token"), Uint128::new(100)),
+                )
+                .unwrap_err(),
+                CwDexError::InvalidLpToken {}
+            );
+        }
+    }
+
+    #[test]
+    fn test_native_lp_token_withdraw() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = pool(PairType::Xyk {}).with_chain_profile(ChainProfile::Sei);
+        assert_eq!(pool.lp_token(), AssetInfo::native("lp_token"));
+
+        let res = pool
+            .withdraw_liquidity(
+                deps.as_ref(),
+                &env,
+                Asset::native("lp_token", 100u128),
+                AssetList::from(vec![Asset::native("uluna", 10u128)]),
+            )
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "pair".to_string(),
+                msg: to_json_binary(&NativeLpPairExecuteMsg::WithdrawLiquidity {
+                    assets: vec![],
+                    min_assets_to_receive: Some(vec![
+                        Asset::native("uluna", 10u128).into(),
+                        Asset::native("uatom", 1u128).into(),
+                    ]),
+                })
+                .unwrap(),
+                funds: coins(100, "lp_token"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_provided_asset_conversion() {
+        let deps = mock_dependencies();
+        // An AssetList deserialized from a message can contain duplicates
+        let assets: AssetList = from_json(
+            to_json_binary(&vec![
+                Asset::native("uatom", 10u128),
+                Asset::native("uluna", 20u128),
+                Asset::native("uatom", 5u128),
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(assets.len(), 3);
+
+        // Providing and simulating use the same mode
+        let strict = pool(PairType::Xyk {});
+        let duplicate = CwDexError::DuplicateAsset {
+            asset: AssetInfo::native("uatom"),
+        };
+        assert_eq!(
+            strict
+                .convert_provided_assets(deps.as_ref(), assets.clone())
+                .unwrap_err(),
+            duplicate
+        );
+        assert_eq!(
+            strict
+                .provide_liquidity(deps.as_ref(), &mock_env(), assets.clone(), Uint128::one())
+                .unwrap_err(),
+            duplicate
+        );
+        assert_eq!(
+            strict
+                .simulate_provide_liquidity(deps.as_ref(), &mock_env(), assets.clone())
+                .unwrap_err(),
+            duplicate
+        );
+
+        for mode in [AssetConversion::Merge, AssetConversion::Lenient] {
+            let pool = pool(PairType::Xyk {}).with_asset_conversion(mode);
+            assert_eq!(
+                pool.convert_provided_assets(deps.as_ref(), assets.clone())
+                    .unwrap()
+                    .to_vec(),
+                vec![
+                    Asset::native("uluna", 20u128),
+                    Asset::native("uatom", 15u128)
+                ]
+            );
+        }
+    }
+
+    #[test_case(PairType::Xyk {}, "1.2.0" => PairVersion::Legacy; "xyk 1.2")]
+    #[test_case(PairType::Xyk {}, "1.3.2" => PairVersion::Current; "xyk 1.3")]
+    #[test_case(PairType::Xyk {}, "2.0.0" => PairVersion::Current; "xyk 2.0")]
+    #[test_case(PairType::Stable {}, "1.5.0" => PairVersion::Legacy; "stable 1.5")]
+    #[test_case(PairType::Stable {}, "v2.1.3" => PairVersion::Current; "stable 2.1")]
+    #[test_case(PairType::Custom("concentrated".to_string()), "1.2.7" => PairVersion::Current; "pcl")]
+    fn test_pair_version(pair_type: PairType, version: &str) -> PairVersion {
+        PairVersion::from_version_str(&pair_type, version).unwrap()
+    }
+
+    #[test]
+    fn test_invalid_pair_version() {
+        for version in ["", "3", "x.1.0"] {
+            PairVersion::from_version_str(&PairType::Xyk {}, version).unwrap_err();
+        }
+    }
+
+    #[test_case(PairType::Stable {}; "stable")]
+    #[test_case(PairType::Custom("concentrated".to_string()); "pcl")]
+    fn test_local_simulation_unsupported(pair_type: PairType) {
+        let deps = mock_dependencies();
+        let pool = pool(pair_type).with_simulation_mode(SimulationMode::Local);
+
+        let err = pool
+            .try_simulate_swap(
+                deps.as_ref(),
+                Asset::native("uluna", 100u128),
+                AssetInfo::native("uatom"),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Local swap simulation is not supported"));
+    }
+
+    #[test]
+    fn test_check_cw20_assets() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, msg } = query else {
+                panic!("Unexpected query");
+            };
+            assert_eq!(
+                from_json::<Cw20QueryMsg>(msg).unwrap(),
+                Cw20QueryMsg::TokenInfo {}
+            );
+            SystemResult::Ok(match contract_addr.as_str() {
+                "token" => ContractResult::Ok(
+                    to_json_binary(&TokenInfoResponse {
+                        name: "Token".to_string(),
+                        symbol: "TKN".to_string(),
+                        decimals: 6,
+                        total_supply: Uint128::new(1_000),
+                    })
+                    .unwrap(),
+                ),
+                _ => ContractResult::Err("token is broken".to_string()),
+            })
+        });
+        let pool = |token: &str| {
+            AstroportPool::unchecked(
+                Addr::unchecked("pair"),
+                Addr::unchecked("lp_token"),
+                vec![
+                    AssetInfo::native("uluna"),
+                    AssetInfo::cw20(Addr::unchecked(token)),
+                ],
+                PairType::Xyk {},
+                Addr::unchecked("liquidity_manager"),
+            )
+        };
+        let querier = QuerierWrapper::new(&deps.querier);
+
+        // Native assets are not queried
+        pool("token").check_cw20_assets(&querier).unwrap();
+        let err = pool("broken_token")
+            .check_cw20_assets(&querier)
+            .unwrap_err();
+        let CwDexError::AssetUnavailable { asset, reason } = err else {
+            panic!("Unexpected error {err}");
+        };
+        assert_eq!(asset, AssetInfo::cw20(Addr::unchecked("broken_token")));
+        assert!(reason.contains("token is broken"));
+    }
+
+    #[test]
+    fn test_find_pools_errors() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, .. } = query else {
+                panic!("Unexpected query");
+            };
+            let err = match contract_addr.as_str() {
+                "factory" => "astroport_factory::state::Addr not found",
+                _ => "Unknown contract",
+            };
+            SystemResult::Ok(ContractResult::Err(err.to_string()))
+        });
+        let find_pools = |factory: &str| {
+            AstroportPool::find_pools(
+                deps.as_ref(),
+                &Addr::unchecked(factory),
+                &Addr::unchecked("liquidity_manager"),
+                &AssetInfo::native("uatom"),
+                &AssetInfo::native("uluna"),
+            )
+        };
+
+        assert_eq!(find_pools("factory").unwrap(), vec![]);
+        find_pools("other").unwrap_err();
+    }
+}
@@ -0,0 +1,75 @@
+//! Accounting of assets returned to the contract when providing liquidity to
+//! Astroport pairs
+
+use apollo_cw_asset::{Asset, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Deps, Env, Event, Response};
+
+use cw_dex::CwDexError;
+
+/// Reply ID for the provide liquidity message of
+/// [`crate::AstroportPool::provide_liquidity_tracking_refunds`]. The contract
+/// must call [`ProvideRefundTracker::handle_reply`] when handling this reply.
+pub const ASTROPORT_PROVIDE_REFUND_REPLY_ID: u64 = 567;
+
+/// Tracks the assets returned to the contract by a liquidity provision, see
+/// [`crate::AstroportPool::provide_liquidity_tracking_refunds`].
+///
+/// Constant product pairs only mint LP tokens for the assets in the ratio of
+/// the pool reserves. The Astroport liquidity manager, which `AstroportPool`
+/// provides liquidity through, refunds the excess of the over-supplied asset
+/// to the sender in separate transfers, along with any rounding remainders.
+/// Refunds are not part of the response of the provide liquidity message, so
+/// they are measured as the difference between the contract's balances after
+/// the provision and `expected_balances`.
+#[cw_serde]
+pub struct ProvideRefundTracker {
+    /// The balances of the pool assets the contract would hold after the
+    /// provision if nothing was refunded. This is a `Vec` rather than an
+    /// [`AssetList`], as the latter drops zero amounts.
+    pub expected_balances: Vec<Asset>,
+    /// The amounts of the provided assets that were expected not to earn LP
+    /// tokens, see [`crate::AstroportPool::simulate_unused_assets`]
+    pub unused: AssetList,
+}
+
+impl ProvideRefundTracker {
+    /// Returns the assets refunded to the contract, i.e. the amounts by which
+    /// the contract's balances exceed `expected_balances`.
+    pub fn query_refunded(&self, deps: Deps, env: &Env) -> Result<AssetList, CwDexError> {
+        let mut refunded = vec![];
+        for expected in self.expected_balances.iter() {
+            let balance = expected
+                .info
+                .query_balance(&deps.querier, &env.contract.address)?;
+            let amount = balance.saturating_sub(expected.amount);
+            if !amount.is_zero() {
+                refunded.push(Asset::new(expected.info.clone(), amount));
+            }
+        }
+        Ok(refunded.into())
+    }
+
+    /// Measures the refunded assets. Must be called when handling the reply
+    /// with ID [`crate::AstroportPool::provide_refund_reply_id`], by default
+    /// [`ASTROPORT_PROVIDE_REFUND_REPLY_ID`].
+    ///
+    /// Returns a response with an `apollo/cw-dex/provide_liquidity_refund`
+    /// event, along with the refunded assets. The `unused` and `refunded`
+    /// attributes of the event are omitted when empty.
+    pub fn handle_reply(&self, deps: Deps, env: &Env) -> Result<(Response, AssetList), CwDexError> {
+        let refunded = self.query_refunded(deps, env)?;
+
+        let mut event =
+            Event::new("apollo/cw-dex/provide_liquidity_refund").add_attribute("type", "astroport");
+        // Empty attribute values are not allowed, so empty lists are left out
+        if self.unused.len() > 0 {
+            event = event.add_attribute("unused", self.unused.to_string());
+        }
+        if refunded.len() > 0 {
+            event = event.add_attribute("refunded", refunded.to_string());
+        }
+
+        Ok((Response::new().add_event(event), refunded))
+    }
+}
@@ -0,0 +1,256 @@
+//! Router trait implementation for the Astroport router contract
+
+use std::fmt;
+use std::str::FromStr;
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, wasm_execute, Addr, Decimal, Deps, Env, Event, Response, StdError, StdResult,
+    Uint128,
+};
+use cw20::Cw20ExecuteMsg;
+
+use astroport::pair::MAX_ALLOWED_SLIPPAGE;
+use astroport::router::{
+    ConfigResponse, Cw20HookMsg as RouterCw20HookMsg, ExecuteMsg as RouterExecuteMsg,
+    QueryMsg as RouterQueryMsg, SimulateSwapOperationsResponse, SwapOperation, MAX_SWAP_OPERATIONS,
+};
+use cw_dex::traits::Router;
+use cw_dex::CwDexError;
+
+/// Represents the Astroport router contract, which swaps along a path of
+/// assets through the pairs of its factory in a single message.
+#[cw_serde]
+pub struct AstroportRouter {
+    /// The address of the router contract
+    pub router_addr: Addr,
+    /// The address of the factory contract whose pairs the router swaps in
+    pub factory_addr: Addr,
+    /// The maximum spread of each hop, passed to the pairs by the router. If
+    /// not set, the maximum allowed by the pairs is used and only the
+    /// `min_out` of the whole swap protects against slippage.
+    pub max_spread: Option<Decimal>,
+}
+
+impl fmt::Display for AstroportRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "astroport:router {}", self.router_addr)
+    }
+}
+
+impl AstroportRouter {
+    /// Creates a new instance of `AstroportRouter`, querying the router
+    /// contract for the address of its factory.
+    ///
+    /// Arguments:
+    /// - `router_addr`: The address of the Astroport router contract
+    pub fn new(deps: Deps, router_addr: Addr) -> StdResult<Self> {
+        let config: ConfigResponse = deps
+            .querier
+            .query_wasm_smart(router_addr.to_string(), &RouterQueryMsg::Config {})?;
+
+        Ok(Self {
+            router_addr,
+            factory_addr: deps.api.addr_validate(&config.astroport_factory)?,
+            max_spread: None,
+        })
+    }
+
+    /// Returns the router with the maximum spread of each hop set to
+    /// `max_spread`. Swaps fail if any hop exceeds it, even if the whole swap
+    /// returns more than `min_out`.
+    pub fn with_max_spread(mut self, max_spread: Decimal) -> Self {
+        self.max_spread = Some(max_spread);
+        self
+    }
+
+    /// Converts `path` into the swap operations of the router, one per hop.
+    pub fn swap_operations(path: &[AssetInfo]) -> Result<Vec<SwapOperation>, CwDexError> {
+        let operations: Vec<SwapOperation> = path
+            .windows(2)
+            .map(|hop| SwapOperation::AstroSwap {
+                offer_asset_info: hop[0].clone().into(),
+                ask_asset_info: hop[1].clone().into(),
+            })
+            .collect();
+        if operations.len() > MAX_SWAP_OPERATIONS {
+            return Err(StdError::generic_err(format!(
+                "astroport error: swap path has {} hops, the router allows at most {}",
+                operations.len(),
+                MAX_SWAP_OPERATIONS
+            ))
+            .into());
+        }
+        Ok(operations)
+    }
+}
+
+impl Router for AstroportRouter {
+    fn swap_path(
+        &self,
+        _deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        path: &[AssetInfo],
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.validate_path(&offer_asset.info, path)?;
+        let operations = Self::swap_operations(path)?;
+
+        let max_spread = match self.max_spread {
+            Some(max_spread) => max_spread,
+            None => Decimal::from_str(MAX_ALLOWED_SLIPPAGE)?,
+        };
+        let minimum_receive = (!min_out.is_zero()).then_some(min_out);
+        let to = Some(env.contract.address.to_string());
+
+        let swap_msg = match &offer_asset.info {
+            AssetInfo::Native(_) => wasm_execute(
+                self.router_addr.to_string(),
+                &RouterExecuteMsg::ExecuteSwapOperations {
+                    operations,
+                    minimum_receive,
+                    to,
+                    max_spread: Some(max_spread),
+                },
+                vec![offer_asset.clone().try_into()?],
+            ),
+            AssetInfo::Cw20(addr) => wasm_execute(
+                addr.to_string(),
+                &Cw20ExecuteMsg::Send {
+                    contract: self.router_addr.to_string(),
+                    amount: offer_asset.amount,
+                    msg: to_json_binary(&RouterCw20HookMsg::ExecuteSwapOperations {
+                        operations,
+                        minimum_receive,
+                        to,
+                        max_spread: Some(max_spread),
+                    })?,
+                },
+                vec![],
+            ),
+        }?;
+
+        let event = Event::new("apollo/cw-dex/swap_path")
+            .add_attribute("type", "astroport_router")
+            .add_attribute("router_addr", &self.router_addr)
+            .add_attribute("offer_asset", offer_asset.to_string())
+            .add_attribute(
+                "path",
+                path.iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )
+            .add_attribute("minimum_out_amount", min_out)
+            .add_attribute("max_spread", max_spread.to_string());
+
+        Ok(Response::new().add_message(swap_msg).add_event(event))
+    }
+
+    fn simulate_swap_path(
+        &self,
+        deps: Deps,
+        offer_asset: Asset,
+        path: &[AssetInfo],
+    ) -> StdResult<Uint128> {
+        self.validate_path(&offer_asset.info, path)
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+        let operations =
+            Self::swap_operations(path).map_err(|e| StdError::generic_err(e.to_string()))?;
+
+        let res: SimulateSwapOperationsResponse = deps.querier.query_wasm_smart(
+            self.router_addr.to_string(),
+            &RouterQueryMsg::SimulateSwapOperations {
+                offer_amount: offer_asset.amount,
+                operations,
+            },
+        )?;
+        Ok(res.amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo};
+    use astroport::router::{ExecuteMsg as RouterExecuteMsg, SwapOperation};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{from_json, Addr, CosmosMsg, Decimal, Uint128, WasmMsg};
+    use cw_dex::traits::Router;
+
+    use super::AstroportRouter;
+
+    fn router() -> AstroportRouter {
+        AstroportRouter {
+            router_addr: Addr::unchecked("router"),
+            factory_addr: Addr::unchecked("factory"),
+            max_spread: None,
+        }
+    }
+
+    #[test]
+    fn test_swap_path() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let path = vec![
+            AssetInfo::native("uatom"),
+            AssetInfo::native("uluna"),
+            AssetInfo::cw20(Addr::unchecked("astro")),
+        ];
+
+        let res = router()
+            .with_max_spread(Decimal::percent(2))
+            .swap_path(
+                deps.as_ref(),
+                &env,
+                Asset::native("uatom", 100u128),
+                &path,
+                Uint128::new(90),
+            )
+            .unwrap();
+
+        let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds,
+        }) = &res.messages[0].msg
+        else {
+            panic!("Unexpected message");
+        };
+        assert_eq!(contract_addr, "router");
+        assert_eq!(funds, &[cosmwasm_std::coin(100, "uatom")]);
+        match from_json(msg).unwrap() {
+            RouterExecuteMsg::ExecuteSwapOperations {
+                operations,
+                minimum_receive,
+                to,
+                max_spread,
+            } => {
+                assert_eq!(operations.len(), 2);
+                assert_eq!(
+                    operations[1],
+                    SwapOperation::AstroSwap {
+                        offer_asset_info: path[1].clone().into(),
+                        ask_asset_info: path[2].clone().into(),
+                    }
+                );
+                assert_eq!(minimum_receive, Some(Uint128::new(90)));
+                assert_eq!(to, Some(env.contract.address.to_string()));
+                assert_eq!(max_spread, Some(Decimal::percent(2)));
+            }
+            _ => panic!("Unexpected router message"),
+        }
+
+        // The path must start with the offered asset
+        assert!(router()
+            .swap_path(
+                deps.as_ref(),
+                &env,
+                Asset::native("uluna", 100u128),
+                &path,
+                Uint128::zero(),
+            )
+            .is_err());
+    }
+}
@@ -1,45 +1,698 @@
 //! Staking/rewards traits implementations for Astroport
 
+use std::fmt;
+
 use apollo_utils::assets::separate_natives_and_cw20s;
+use apollo_utils::responses::merge_responses;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    to_json_binary, Addr, CosmosMsg, Deps, Empty, Env, Event, QuerierWrapper, QueryRequest,
-    Response, Uint128, WasmMsg, WasmQuery,
+    from_json, to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps, Empty,
+    Env, Event, MessageInfo, QuerierWrapper, QueryRequest, Response, StdError, StdResult, Uint128,
+    WasmMsg, WasmQuery,
 };
-use cw20::Cw20ExecuteMsg;
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse};
 
-use apollo_cw_asset::AssetList;
-use astroport::asset::Asset as AstroAsset;
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use astroport::asset::{Asset as AstroAsset, PairInfo};
+use astroport::factory::QueryMsg as FactoryQueryMsg;
+use astroport::pair::QueryMsg as PairQueryMsg;
+use astroport_v3::asset::AssetInfo as AstroAssetInfoV3;
 use astroport_v3::incentives::{
-    Cw20Msg as IncentivesCw20Msg, ExecuteMsg as IncentivesExecuteMsg,
-    QueryMsg as IncentivesQueryMsg,
+    Config as IncentivesConfig, ExecuteMsg as IncentivesExecuteMsg, PoolInfoResponse,
+    QueryMsg as IncentivesQueryMsg, RewardInfo as IncentivesRewardInfo, RewardType,
+    ScheduleResponse,
+};
+use astroport_v3::vesting::{
+    ConfigResponse as VestingConfigResponse, ExecuteMsg as VestingExecuteMsg,
+    QueryMsg as VestingQueryMsg, VestingAccountResponse,
 };
 
-use cw_dex::traits::{Rewards, Stake, Staking, Unstake};
+use cw_dex::conv::uint256_to_uint128;
+use cw_dex::metrics::RewardEmission;
+use cw_dex::traits::{
+    MigratePosition, QueryPosition, Rewards, SimulateStaking, Stake, Staking, Unstake,
+};
 use cw_dex::CwDexError;
 
+use crate::chain::ChainProfile;
+use crate::helpers::is_not_found;
+
+/// The hook message sent along with LP tokens to the Astroport incentives
+/// contract to stake them
+pub use astroport_v3::incentives::Cw20Msg as Cw20HookMsg;
+
 /// Represents staking of tokens on Astroport
 #[cw_serde]
 pub struct AstroportStaking {
-    /// The address of the associated LP token contract
+    /// The address of the associated LP token contract, or the denom of the LP
+    /// token if it is a token factory denom, see [`ChainProfile`]
     pub lp_token_addr: Addr,
     /// The address of the astroport incentives contract
     pub incentives: Addr,
+    /// If set, querying and claiming the rewards of a user without a position
+    /// in the incentives contract, e.g. because the pool was never
+    /// incentivized, returns the error of the incentives contract instead of
+    /// no rewards.
+    #[serde(default)]
+    pub strict_rewards: bool,
+    /// The Injective cw20-adapter contract to wrap cw20 rewards with into
+    /// token factory denoms when claiming, see
+    /// [`AstroportStaking::cw20_adapter_denom`]. The cw20 rewards must be
+    /// registered in the adapter. If `None`, cw20 rewards are kept as is.
+    #[serde(default)]
+    pub cw20_adapter: Option<Addr>,
+    /// The Astroport deployment of the chain, determining the type of the LP
+    /// token and whether there is an incentives contract. Without one, staking
+    /// and unstaking return [`CwDexError::StakingNotSupported`] and there
+    /// are never any rewards or staked LP tokens.
+    #[serde(default)]
+    pub chain_profile: ChainProfile,
 }
 
-impl Staking for AstroportStaking {}
+/// The rewards of a user in the Astroport vesting contract, see
+/// [`AstroportStaking::query_vesting_rewards`]
+#[cw_serde]
+pub struct VestingRewards {
+    /// The vested rewards that can be claimed with
+    /// [`AstroportStaking::claim_vested`]
+    pub claimable: Asset,
+    /// The rewards that have not vested yet
+    pub vesting: Asset,
+}
 
-impl Stake for AstroportStaking {
-    fn stake(&self, _deps: Deps, _env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
-        let stake_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+/// The reward index of a reward of the pool, i.e. the rewards accrued per
+/// staked LP token, and the index a position accrues the reward from, see
+/// [`AstroportStaking::query_user_reward_index`]
+#[cw_serde]
+pub struct RewardIndex {
+    /// The reward asset
+    pub info: AssetInfo,
+    /// Whether the reward is an external reward rather than the ASTRO
+    /// emissions of the pool. A pool can have both for the same asset.
+    pub external: bool,
+    /// The rewards accrued per staked LP token by the pool up to the current
+    /// block
+    pub pool_index: Decimal,
+    /// The index at which the rewards of the position were last checkpointed,
+    /// i.e. when it was last changed or claimed. Zero if the position never
+    /// accrued the reward.
+    pub user_index: Decimal,
+}
+
+impl RewardIndex {
+    /// Returns the rewards accrued by `staked` LP tokens since the position
+    /// was last checkpointed, rounded down like in the incentives contract.
+    pub fn accrued(&self, staked: Uint128) -> Uint128 {
+        staked.mul_floor(self.pool_index.saturating_sub(self.user_index))
+    }
+}
+
+/// The reward indices of a position in the Astroport incentives contract, see
+/// [`AstroportStaking::query_user_reward_index`]
+#[cw_serde]
+pub struct UserRewardIndex {
+    /// The amount of LP tokens staked by the user
+    pub staked: Uint128,
+    /// The time in seconds when the user last claimed rewards
+    pub last_claim_time: u64,
+    /// The reward indices of the active rewards of the pool
+    pub indices: Vec<RewardIndex>,
+}
+
+/// A period in which a reward of a pool is emitted at a constant rate, see
+/// [`AstroportStaking::query_reward_schedules`]
+#[cw_serde]
+pub struct RewardSchedule {
+    /// The reward asset
+    pub info: AssetInfo,
+    /// The rewards emitted per second to all stakers of the pool
+    pub per_second: Decimal,
+    /// The time in seconds the period starts at
+    pub start: u64,
+    /// The time in seconds the period ends at, or `None` if the rate holds
+    /// until it is changed, as for the ASTRO emissions of the pool
+    pub end: Option<u64>,
+}
+
+/// The reward schedules and total staked amount of a pool in the Astroport
+/// incentives contract, see [`AstroportStaking::query_reward_schedules`]
+#[cw_serde]
+pub struct RewardSchedules {
+    /// The total amount of LP tokens staked in the pool
+    pub total_staked: Uint128,
+    /// The current and scheduled reward periods of the pool
+    pub schedules: Vec<RewardSchedule>,
+}
+
+/// Returns the rewards that `staked` LP tokens receive from `schedules` between
+/// the times `from` and `to` in seconds, if `total_staked` LP tokens are
+/// staked in the pool throughout. `total_staked` must include `staked`, e.g.
+/// to project the rewards of a new deposit, add it to the current total. The
+/// amounts are rounded down.
+pub fn project_rewards(
+    schedules: &[RewardSchedule],
+    staked: Uint128,
+    total_staked: Uint128,
+    from: u64,
+    to: u64,
+) -> Result<AssetList, CwDexError> {
+    if staked > total_staked {
+        return Err(StdError::generic_err(format!(
+            "staked amount {staked} is larger than the total staked amount {total_staked}"
+        ))
+        .into());
+    }
+
+    let mut rewards = AssetList::new();
+    if staked.is_zero() {
+        return Ok(rewards);
+    }
+    for schedule in schedules {
+        let start = schedule.start.max(from);
+        let end = schedule.end.unwrap_or(to).min(to);
+        if end <= start {
+            continue;
+        }
+        let amount = Decimal256::from(schedule.per_second)
+            .checked_mul(Decimal256::from_ratio(end - start, 1u8))?
+            .checked_mul(Decimal256::from_ratio(staked, 1u8))?
+            .checked_div(Decimal256::from_ratio(total_staked, 1u8))
+            .map_err(|err| StdError::generic_err(err.to_string()))?
+            .to_uint_floor();
+        rewards.add(&Asset::new(
+            schedule.info.clone(),
+            uint256_to_uint128(amount, "projected rewards")?,
+        ))?;
+    }
+
+    Ok(rewards)
+}
+
+/// The position of a user as stored in the `user_info` map of the Astroport
+/// incentives contract
+#[cw_serde]
+struct IncentivesUserInfo {
+    amount: Uint128,
+    last_rewards_index: Vec<(RewardType, Decimal)>,
+    last_claim_time: u64,
+}
+
+impl fmt::Display for AstroportStaking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "astroport:incentives {}/{}",
+            self.incentives, self.lp_token_addr
+        )
+    }
+}
+
+impl AstroportStaking {
+    /// Creates a new instance of `AstroportStaking`, checking that
+    /// `incentives` is an Astroport incentives contract and that
+    /// `lp_token_addr` is the LP token of a pair registered in the factory of
+    /// the incentives contract, as the incentives contract requires when
+    /// staking. Constructing the struct directly defers these errors to the
+    /// execution of the staking message.
+    ///
+    /// Returns [`CwDexError::StakingContractMismatch`] if any of the checks
+    /// fails.
+    ///
+    /// Arguments:
+    /// - `lp_token_addr`: The address of the LP token contract
+    /// - `incentives`: The address of the Astroport incentives contract
+    pub fn new_checked(
+        deps: Deps,
+        lp_token_addr: Addr,
+        incentives: Addr,
+    ) -> Result<Self, CwDexError> {
+        let mismatch = |reason: String| CwDexError::StakingContractMismatch {
+            lp_token: lp_token_addr.to_string(),
+            staking_contract: incentives.to_string(),
+            reason,
+        };
+
+        let config: IncentivesConfig = deps
+            .querier
+            .query_wasm_smart(incentives.to_string(), &IncentivesQueryMsg::Config {})
+            .map_err(|e| mismatch(format!("not an Astroport incentives contract: {e}")))?;
+
+        // The LP token of an Astroport pair is a cw20 minted by the pair
+        let minter: MinterResponse = deps
+            .querier
+            .query_wasm_smart(lp_token_addr.to_string(), &Cw20QueryMsg::Minter {})
+            .map_err(|e| mismatch(format!("not a cw20 with a minter: {e}")))?;
+        let pair_info: PairInfo = deps
+            .querier
+            .query_wasm_smart(minter.minter.clone(), &PairQueryMsg::Pair {})
+            .map_err(|e| {
+                mismatch(format!(
+                    "minter {} is not an Astroport pair: {e}",
+                    minter.minter
+                ))
+            })?;
+        if pair_info.liquidity_token != lp_token_addr {
+            return Err(mismatch(format!(
+                "the LP token of pair {} is {}",
+                pair_info.contract_addr, pair_info.liquidity_token
+            )));
+        }
+
+        let factory_pair: PairInfo = deps
+            .querier
+            .query_wasm_smart(
+                config.factory.to_string(),
+                &FactoryQueryMsg::Pair {
+                    asset_infos: pair_info.asset_infos.clone(),
+                },
+            )
+            .map_err(|e| {
+                mismatch(format!(
+                    "pair {} is not registered in factory {}: {e}",
+                    pair_info.contract_addr, config.factory
+                ))
+            })?;
+        if factory_pair.contract_addr != pair_info.contract_addr {
+            return Err(mismatch(format!(
+                "factory {} has pair {} instead of {} for the assets",
+                config.factory, factory_pair.contract_addr, pair_info.contract_addr
+            )));
+        }
+
+        Ok(Self {
+            lp_token_addr,
+            incentives,
+            strict_rewards: false,
+            cw20_adapter: None,
+            chain_profile: ChainProfile::default(),
+        })
+    }
+
+    /// Queries the incentives contract for the current emission rates of the
+    /// rewards of the pool. Internal (ASTRO) and external rewards of the same
+    /// asset are summed. Returns an empty list if the pool has never been
+    /// incentivized, as the incentives contract then has no info on it. Other
+    /// query errors are returned.
+    pub fn query_reward_emissions(
+        &self,
+        querier: &QuerierWrapper,
+    ) -> Result<Vec<RewardEmission>, CwDexError> {
+        let pool_info: PoolInfoResponse = match querier.query_wasm_smart(
+            self.incentives.to_string(),
+            &IncentivesQueryMsg::PoolInfo {
+                lp_token: self.lp_token_addr.to_string(),
+            },
+        ) {
+            Ok(pool_info) => pool_info,
+            Err(err) if is_not_found(&err) => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut emissions: Vec<RewardEmission> = vec![];
+        for reward in pool_info.rewards {
+            let info = asset_info_from_v3(reward.reward.asset_info());
+            match emissions.iter_mut().find(|e| e.info == info) {
+                Some(emission) => emission.per_second += reward.rps,
+                None => emissions.push(RewardEmission {
+                    info,
+                    per_second: reward.rps,
+                }),
+            }
+        }
+
+        Ok(emissions)
+    }
+
+    /// Queries the reward indices of the position of `user` in the incentives
+    /// contract, with the pool indices updated to the current block. Reward
+    /// accounting contracts can compute the rewards accrued by the position
+    /// between two blocks from the difference of the pool indices instead of
+    /// differencing claimed amounts. Returns `None` if the user has no
+    /// position.
+    ///
+    /// The incentives contract has no query for the indices of a user, so they
+    /// are read from its storage with a raw query. Rewards of external
+    /// schedules that have finished are not included.
+    pub fn query_user_reward_index(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<Option<UserRewardIndex>, CwDexError> {
+        let Some(raw) = querier.query_wasm_raw(
+            self.incentives.to_string(),
+            user_info_key(self.lp_token_addr.as_str(), user),
+        )?
+        else {
+            return Ok(None);
+        };
+        let user_info: IncentivesUserInfo = from_json(raw)?;
+
+        let rewards: Vec<IncentivesRewardInfo> = querier.query_wasm_smart(
+            self.incentives.to_string(),
+            &IncentivesQueryMsg::RewardInfo {
+                lp_token: self.lp_token_addr.to_string(),
+            },
+        )?;
+        let indices = rewards
+            .into_iter()
+            .map(|reward| {
+                let user_index = user_info
+                    .last_rewards_index
+                    .iter()
+                    .find(|(reward_type, _)| reward_type.matches(&reward.reward))
+                    .map(|(_, index)| *index)
+                    // The incentives contract accrues a reward from zero if the
+                    // user index is from a finished schedule of the reward
+                    .filter(|index| *index <= reward.index)
+                    .unwrap_or_default();
+                RewardIndex {
+                    info: asset_info_from_v3(reward.reward.asset_info()),
+                    external: reward.reward.is_external(),
+                    pool_index: reward.index,
+                    user_index,
+                }
+            })
+            .collect();
+
+        Ok(Some(UserRewardIndex {
+            staked: user_info.amount,
+            last_claim_time: user_info.last_claim_time,
+            indices,
+        }))
+    }
+
+    /// Queries the total staked amount of the pool and its reward schedules
+    /// from the current block time until `until`: the ASTRO emissions, and
+    /// the current and upcoming schedules of the external rewards. The
+    /// upcoming schedules of an external reward are only queried if its current
+    /// schedule ends before `until`. Returns no schedules if the pool has never
+    /// been incentivized. The emission rate of ASTRO can be changed by
+    /// governance at any time.
+    pub fn query_reward_schedules(
+        &self,
+        querier: &QuerierWrapper,
+        env: &Env,
+        until: u64,
+    ) -> Result<RewardSchedules, CwDexError> {
+        let pool_info: PoolInfoResponse = match querier.query_wasm_smart(
+            self.incentives.to_string(),
+            &IncentivesQueryMsg::PoolInfo {
+                lp_token: self.lp_token_addr.to_string(),
+            },
+        ) {
+            Ok(pool_info) => pool_info,
+            Err(_) => {
+                return Ok(RewardSchedules {
+                    total_staked: Uint128::zero(),
+                    schedules: vec![],
+                })
+            }
+        };
+
+        let now = env.block.time.seconds();
+        let mut schedules = vec![];
+        for reward in pool_info.rewards {
+            let (info, next_update_ts) = match reward.reward {
+                RewardType::Int(info) => {
+                    schedules.push(RewardSchedule {
+                        info: asset_info_from_v3(&info),
+                        per_second: reward.rps,
+                        start: now,
+                        end: None,
+                    });
+                    continue;
+                }
+                RewardType::Ext {
+                    info,
+                    next_update_ts,
+                } => (info, next_update_ts),
+            };
+            schedules.push(RewardSchedule {
+                info: asset_info_from_v3(&info),
+                per_second: reward.rps,
+                start: now,
+                end: Some(next_update_ts),
+            });
+            if next_update_ts >= until {
+                continue;
+            }
+
+            // The schedules after the current one. If the current one has
+            // already ended, the pool info is stale and the query returns the
+            // schedule active now as well.
+            let reward_id = match &info {
+                AstroAssetInfoV3::NativeToken { denom } => denom.to_string(),
+                AstroAssetInfoV3::Token { contract_addr } => contract_addr.to_string(),
+            };
+            let mut start_after = (next_update_ts > now).then_some(next_update_ts);
+            loop {
+                let page: Vec<ScheduleResponse> = querier.query_wasm_smart(
+                    self.incentives.to_string(),
+                    &IncentivesQueryMsg::ExternalRewardSchedules {
+                        reward: reward_id.clone(),
+                        lp_token: self.lp_token_addr.to_string(),
+                        start_after,
+                        limit: None,
+                    },
+                )?;
+                let Some(last_end) = page.last().map(|schedule| schedule.end_ts) else {
+                    break;
+                };
+                schedules.extend(page.into_iter().map(|schedule| RewardSchedule {
+                    info: asset_info_from_v3(&info),
+                    per_second: schedule.rps,
+                    start: schedule.start_ts,
+                    end: Some(schedule.end_ts),
+                }));
+                if last_end >= until {
+                    break;
+                }
+                start_after = Some(last_end);
+            }
+        }
+
+        Ok(RewardSchedules {
+            total_staked: pool_info.total_lp,
+            schedules,
+        })
+    }
+
+    /// Returns the rewards that `staked` LP tokens, which are part of the total
+    /// staked amount of the pool, receive over the next `duration` seconds,
+    /// e.g. 86400 for daily rewards, according to the current reward schedules,
+    /// see [`project_rewards`]. To project the rewards of a deposit that is not
+    /// staked yet, call [`project_rewards`] with the deposit added to the total
+    /// staked amount returned by [`AstroportStaking::query_reward_schedules`].
+    pub fn query_projected_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        env: &Env,
+        staked: Uint128,
+        duration: u64,
+    ) -> Result<AssetList, CwDexError> {
+        let now = env.block.time.seconds();
+        let until = now.checked_add(duration).ok_or_else(|| {
+            StdError::generic_err(format!("Projection duration {duration} is too long"))
+        })?;
+        let RewardSchedules {
+            total_staked,
+            schedules,
+        } = self.query_reward_schedules(querier, env, until)?;
+        project_rewards(&schedules, staked, total_staked, now, until)
+    }
+
+    /// Returns the staking with cw20 rewards wrapped into token factory denoms
+    /// by the Injective cw20-adapter contract `adapter` when claiming.
+    pub fn with_cw20_adapter(mut self, adapter: Addr) -> Self {
+        self.cw20_adapter = Some(adapter);
+        self
+    }
+
+    /// Returns the token factory denom that `cw20` is wrapped into by the
+    /// cw20 adapter, or `None` if no adapter is set.
+    pub fn cw20_adapter_denom(&self, cw20: &Addr) -> Option<String> {
+        self.cw20_adapter
+            .as_ref()
+            .map(|adapter| format!("factory/{adapter}/{cw20}"))
+    }
+
+    /// Queries the rewards of `user` in the Astroport vesting contract
+    /// `vesting`, e.g. ASTRO rewards that are paid out over time instead of
+    /// when claiming. Returns zero amounts if the user has no vesting account,
+    /// and other errors of the vesting contract queries.
+    pub fn query_vesting_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        vesting: &Addr,
+        user: &Addr,
+    ) -> Result<VestingRewards, CwDexError> {
+        let config: VestingConfigResponse =
+            querier.query_wasm_smart(vesting.to_string(), &VestingQueryMsg::Config {})?;
+        let info = asset_info_from_v3(&config.vesting_token);
+
+        // The vesting contract errors with not found if the user has no
+        // vesting account
+        let account: VestingAccountResponse = match querier.query_wasm_smart(
+            vesting.to_string(),
+            &VestingQueryMsg::VestingAccount {
+                address: user.to_string(),
+            },
+        ) {
+            Ok(account) => account,
+            Err(err) if is_not_found(&err) => {
+                return Ok(VestingRewards {
+                    claimable: Asset::new(info.clone(), Uint128::zero()),
+                    vesting: Asset::new(info, Uint128::zero()),
+                })
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let claimable: Uint128 = querier.query_wasm_smart(
+            vesting.to_string(),
+            &VestingQueryMsg::AvailableAmount {
+                address: user.to_string(),
+            },
+        )?;
+
+        // The amount of the end point of a schedule is the total amount vested
+        // by it
+        let total = account
+            .info
+            .schedules
+            .iter()
+            .map(|schedule| schedule.end_point.as_ref().unwrap_or(&schedule.start_point))
+            .map(|point| point.amount)
+            .sum::<Uint128>();
+        let still_vesting = total
+            .checked_sub(account.info.released_amount)?
+            .checked_sub(claimable)?;
+
+        Ok(VestingRewards {
+            claimable: Asset::new(info.clone(), claimable),
+            vesting: Asset::new(info, still_vesting),
+        })
+    }
+
+    /// Claims the vested rewards of the contract from the Astroport vesting
+    /// contract `vesting`. Returns a response without messages if nothing has
+    /// vested yet.
+    pub fn claim_vested(
+        &self,
+        deps: Deps,
+        env: &Env,
+        vesting: &Addr,
+    ) -> Result<Response, CwDexError> {
+        let rewards = self.query_vesting_rewards(&deps.querier, vesting, &env.contract.address)?;
+
+        let event =
+            Event::new("apollo/cw-dex/claim_vested").add_attribute("type", "astroport_staking");
+
+        if rewards.claimable.amount.is_zero() {
+            return Ok(Response::new().add_event(event.add_attribute("skipped", "no_rewards")));
+        }
+
+        let claim_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: vesting.to_string(),
+            msg: to_json_binary(&VestingExecuteMsg::Claim {
+                recipient: None,
+                amount: None,
+            })?,
+            funds: vec![],
+        });
+
+        Ok(Response::new()
+            .add_message(claim_msg)
+            .add_event(event.add_attribute("rewards", rewards.claimable.to_string())))
+    }
+
+    /// Returns the hook message to attach to a cw20 `Send` of LP tokens to the
+    /// incentives contract, staking them on behalf of `recipient`, or the
+    /// sender if `None`.
+    pub fn stake_hook_msg(recipient: Option<String>) -> StdResult<Binary> {
+        to_json_binary(&Cw20HookMsg::Deposit { recipient })
+    }
+
+    /// Returns a cw20 `Send` message staking `amount` LP tokens in a single
+    /// message, on behalf of `recipient`, or the sender if `None`. If the LP
+    /// token is a token factory denom, a deposit with the LP tokens as funds
+    /// is returned instead.
+    pub fn stake_msg(&self, amount: Uint128, recipient: Option<String>) -> StdResult<CosmosMsg> {
+        if self.chain_profile.native_lp_token() {
+            return Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: self.incentives.to_string(),
+                msg: to_json_binary(&IncentivesExecuteMsg::Deposit { recipient })?,
+                funds: vec![Coin::new(amount.u128(), self.lp_token_addr.as_str())],
+            }));
+        }
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: self.lp_token_addr.to_string(),
             msg: to_json_binary(&Cw20ExecuteMsg::Send {
                 contract: self.incentives.to_string(),
                 amount,
-                msg: to_json_binary(&IncentivesCw20Msg::Deposit { recipient: None })?,
+                msg: Self::stake_hook_msg(recipient)?,
             })?,
             funds: vec![],
-        });
+        }))
+    }
+
+    /// Returns [`CwDexError::StakingNotSupported`] if the chain has no
+    /// incentives contract, see [`ChainProfile::incentives`].
+    fn check_incentives(&self) -> Result<(), CwDexError> {
+        if self.chain_profile.incentives() {
+            return Ok(());
+        }
+        Err(CwDexError::StakingNotSupported {
+            reason: "the chain has no Astroport incentives contract".to_string(),
+        })
+    }
+
+    /// Verifies a [`Cw20ReceiveMsg`] received by a contract, for contracts
+    /// that accept LP tokens via cw20 `Send` with a [`Cw20HookMsg`] payload
+    /// and route them into staking. Checks that the tokens received are the
+    /// LP tokens of this staking and that the payload is a valid stake hook.
+    ///
+    /// Returns the validated recipient of the hook, or `None` if the LP tokens
+    /// should be staked on behalf of the receiving contract.
+    ///
+    /// Arguments:
+    /// - `info`: The `MessageInfo` of the receive call. The sender must be the
+    ///   LP token contract.
+    /// - `receive_msg`: The received `Cw20ReceiveMsg`.
+    pub fn verify_stake_hook(
+        &self,
+        deps: Deps,
+        info: &MessageInfo,
+        receive_msg: &Cw20ReceiveMsg,
+    ) -> Result<Option<Addr>, CwDexError> {
+        if info.sender != self.lp_token_addr {
+            return Err(CwDexError::InvalidLpToken {});
+        }
+
+        let recipient = match from_json(&receive_msg.msg)? {
+            Cw20HookMsg::Deposit { recipient } => recipient,
+            Cw20HookMsg::DepositFor(recipient) => Some(recipient),
+        };
+
+        Ok(recipient
+            .map(|recipient| deps.api.addr_validate(&recipient))
+            .transpose()?)
+    }
+}
+
+impl Staking for AstroportStaking {}
+
+impl Stake for AstroportStaking {
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        if self.chain_profile.native_lp_token() {
+            Some(AssetInfo::native(self.lp_token_addr.as_str()))
+        } else {
+            Some(AssetInfo::Cw20(self.lp_token_addr.clone()))
+        }
+    }
+
+    fn stake(&self, _deps: Deps, _env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        self.check_incentives()?;
+        let stake_msg = self.stake_msg(amount, None)?;
 
         let event = Event::new("apollo/cw-dex/stake")
             .add_attribute("type", "astroport_staking")
@@ -50,6 +703,9 @@ impl Stake for AstroportStaking {
     }
 }
 
+/// The incentives contract claims all rewards of an LP token at once, so
+/// [`Rewards::claim_rewards_filtered`] claims all pending rewards as well and
+/// sends the unselected ones on to its `return_to` address.
 impl Rewards for AstroportStaking {
     fn claim_rewards(&self, deps: Deps, env: &Env) -> Result<Response, CwDexError> {
         let claimable_rewards: AssetList =
@@ -58,8 +714,13 @@ impl Rewards for AstroportStaking {
         let event =
             Event::new("apollo/cw-dex/claim_rewards").add_attribute("type", "astroport_staking");
 
+        if !self.chain_profile.incentives() {
+            return Ok(
+                Response::new().add_event(event.add_attribute("skipped", "incentives_unavailable"))
+            );
+        }
         if claimable_rewards.len() == 0 {
-            return Ok(Response::new().add_event(event));
+            return Ok(Response::new().add_event(event.add_attribute("skipped", "no_rewards")));
         }
 
         let claim_rewards_msg = CosmosMsg::Wasm(WasmMsg::Execute {
@@ -75,21 +736,33 @@ impl Rewards for AstroportStaking {
         // Astroport generator only supports CW20 tokens as proxy rewards and wraps
         // native tokens in their "CW20 wrapper". We need to unwrap them here.
         let (_, cw20s) = separate_natives_and_cw20s(&claimable_rewards);
+        let mut wrapped = vec![];
         for cw20 in cw20s {
             // Query the cw20s creator to get the address of the wrapper contract
             let contract_info = deps.querier.query_wasm_contract_info(&cw20.address)?;
             let wrapper_contract = deps.api.addr_validate(&contract_info.creator)?;
 
             // Query the wrapper contract's cw2 info to check if it is a native token
-            // wrapper, otherwise skip it
+            // wrapper, otherwise wrap it with the cw20 adapter if set
             let contract_version = cw2::query_contract_info(&deps.querier, &wrapper_contract).ok();
-            match contract_version {
-                Some(contract_version) => {
-                    if &contract_version.contract != "astroport-native-coin-wrapper" {
-                        continue;
-                    }
+            if contract_version.map(|version| version.contract)
+                != Some("astroport-native-coin-wrapper".to_string())
+            {
+                if let Some(adapter) = &self.cw20_adapter {
+                    // The adapter mints the token factory denom of any cw20 it
+                    // receives to the sender, ignoring the message
+                    res = res.add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: cw20.address.to_string(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                            contract: adapter.to_string(),
+                            amount: cw20.amount,
+                            msg: Binary::default(),
+                        })?,
+                        funds: vec![],
+                    }));
+                    wrapped.push(cw20.address.to_string());
                 }
-                None => continue,
+                continue;
             }
 
             // Unwrap the native token
@@ -105,6 +778,11 @@ impl Rewards for AstroportStaking {
             res = res.add_message(unwrap_msg);
         }
 
+        let event = if wrapped.is_empty() {
+            event
+        } else {
+            event.add_attribute("wrapped", wrapped.join(","))
+        };
         Ok(res.add_event(event))
     }
 
@@ -113,14 +791,29 @@ impl Rewards for AstroportStaking {
         querier: &QuerierWrapper,
         user: &Addr,
     ) -> Result<AssetList, CwDexError> {
-        let pending_rewards: Vec<AstroAsset> = querier
-            .query::<Vec<AstroAsset>>(&QueryRequest::Wasm(WasmQuery::Smart {
+        if !self.chain_profile.incentives() {
+            return Ok(AssetList::new());
+        }
+        let pending_rewards =
+            querier.query::<Vec<AstroAsset>>(&QueryRequest::Wasm(WasmQuery::Smart {
                 contract_addr: self.incentives.to_string(),
                 msg: to_json_binary(&IncentivesQueryMsg::PendingRewards {
                     lp_token: self.lp_token_addr.to_string(),
                     user: user.to_string(),
                 })?,
-            }))?
+            }));
+        // The incentives contract errors if the pool or the user's position
+        // does not exist, which is the case until the pool is first staked in
+        let pending_rewards = match pending_rewards {
+            Ok(pending_rewards) => pending_rewards,
+            Err(_)
+                if !self.strict_rewards && self.query_staked_amount(querier, user)?.is_zero() =>
+            {
+                return Ok(AssetList::new())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let pending_rewards: Vec<AstroAsset> = pending_rewards
             .into_iter()
             .filter(|asset| !asset.amount.is_zero()) //TODO: Is this necessary?
             .collect();
@@ -129,8 +822,38 @@ impl Rewards for AstroportStaking {
     }
 }
 
+impl SimulateStaking for AstroportStaking {
+    fn query_staked_amount(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<Uint128, CwDexError> {
+        if !self.chain_profile.incentives() {
+            return Ok(Uint128::zero());
+        }
+        Ok(querier.query_wasm_smart(
+            self.incentives.to_string(),
+            &IncentivesQueryMsg::Deposit {
+                lp_token: self.lp_token_addr.to_string(),
+                user: user.to_string(),
+            },
+        )?)
+    }
+
+    /// The incentives contract pays out the pending rewards of the user on
+    /// every deposit and withdrawal.
+    fn pays_rewards_on_stake_change(&self) -> bool {
+        true
+    }
+}
+
+/// Withdrawals from the incentives contract are instant, so the position has
+/// no unlocking amounts or lockup duration.
+impl QueryPosition for AstroportStaking {}
+
 impl Unstake for AstroportStaking {
     fn unstake(&self, _deps: Deps, _env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        self.check_incentives()?;
         let unstake_msg = CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: self.incentives.to_string(),
             msg: to_json_binary(&IncentivesExecuteMsg::Withdraw {
@@ -145,3 +868,367 @@ impl Unstake for AstroportStaking {
         Ok(Response::new().add_message(unstake_msg).add_event(event))
     }
 }
+
+impl MigratePosition for AstroportStaking {
+    /// Withdraws `amount` from the incentives contract of `self` and deposits
+    /// it in the incentives contract of `to` in the same response. Withdrawing
+    /// pays out the pending rewards of `self`.
+    fn migrate_position(
+        &self,
+        deps: Deps,
+        env: &Env,
+        amount: Uint128,
+        to: &Self,
+    ) -> Result<Response, CwDexError> {
+        if to.lp_token_addr != self.lp_token_addr {
+            return Err(StdError::generic_err(format!(
+                "astroport error: can not migrate position of {} to staking of {}",
+                self.lp_token_addr, to.lp_token_addr
+            ))
+            .into());
+        }
+
+        let event = Event::new("apollo/cw-dex/migrate_position")
+            .add_attribute("type", "astroport_staking")
+            .add_attribute("from", self.to_string())
+            .add_attribute("to", to.to_string())
+            .add_attribute("amount", amount);
+
+        Ok(merge_responses(vec![
+            self.unstake(deps, env, amount)?,
+            to.stake(deps, env, amount)?,
+        ])
+        .add_event(event))
+    }
+}
+
+/// Returns the storage key of the position of `user` in the pool of `lp_token`
+/// in the `user_info` map of the Astroport incentives contract, which is keyed
+/// by the LP token and the user.
+fn user_info_key(lp_token: &str, user: &Addr) -> Vec<u8> {
+    let mut key = vec![];
+    for prefix in [b"user_info".as_slice(), lp_token.as_bytes()] {
+        key.extend_from_slice(&(prefix.len() as u16).to_be_bytes());
+        key.extend_from_slice(prefix);
+    }
+    key.extend_from_slice(user.as_bytes());
+    key
+}
+
+fn asset_info_from_v3(info: &AstroAssetInfoV3) -> AssetInfo {
+    match info {
+        AstroAssetInfoV3::Token { contract_addr } => AssetInfo::Cw20(contract_addr.clone()),
+        AstroAssetInfoV3::NativeToken { denom } => AssetInfo::Native(denom.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use astroport::asset::{AssetInfo as AstroAssetInfo, PairInfo};
+    use astroport::factory::PairType;
+    use astroport_v3::asset::AssetInfo as AstroAssetInfoV3;
+    use astroport_v3::incentives::{
+        Config as IncentivesConfig, ExecuteMsg as IncentivesExecuteMsg,
+    };
+    use astroport_v3::vesting::{
+        ConfigResponse as VestingConfigResponse, QueryMsg as VestingQueryMsg,
+    };
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{
+        coins, from_json, to_json_binary, Addr, ContractResult, CosmosMsg, Decimal, Deps,
+        OwnedDeps, SystemResult, Uint128, WasmMsg, WasmQuery,
+    };
+    use cw20::MinterResponse;
+    use cw_dex::traits::{Stake, Unstake};
+    use cw_dex::CwDexError;
+
+    use super::{project_rewards, AstroportStaking, RewardSchedule};
+    use crate::chain::ChainProfile;
+
+    /// Mocks the incentives contract of `factory`, `lp_token` minted by `pair`,
+    /// `pair` with LP token `lp_token_of_pair`, and `factory` returning
+    /// `factory_pair` as the pair of the assets of `pair`.
+    fn mock_contracts(
+        lp_token_of_pair: &'static str,
+        factory_pair: &'static str,
+    ) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(move |query| {
+            let WasmQuery::Smart { contract_addr, .. } = query else {
+                panic!("Unexpected query");
+            };
+            let pair_info = |pair: &str| PairInfo {
+                asset_infos: vec![
+                    AstroAssetInfo::NativeToken {
+                        denom: "uatom".to_string(),
+                    },
+                    AstroAssetInfo::NativeToken {
+                        denom: "uluna".to_string(),
+                    },
+                ],
+                contract_addr: Addr::unchecked(pair),
+                liquidity_token: Addr::unchecked(lp_token_of_pair),
+                pair_type: PairType::Xyk {},
+            };
+            let res = match contract_addr.as_str() {
+                "incentives" => to_json_binary(&IncentivesConfig {
+                    owner: Addr::unchecked("owner"),
+                    factory: Addr::unchecked("factory"),
+                    generator_controller: None,
+                    astro_token: AstroAssetInfoV3::NativeToken {
+                        denom: "astro".to_string(),
+                    },
+                    astro_per_second: Uint128::zero(),
+                    total_alloc_points: Uint128::zero(),
+                    vesting_contract: Addr::unchecked("vesting"),
+                    guardian: None,
+                    incentivization_fee_info: None,
+                }),
+                "lp_token" => to_json_binary(&MinterResponse {
+                    minter: "pair".to_string(),
+                    cap: None,
+                }),
+                "pair" => to_json_binary(&pair_info("pair")),
+                "factory" => to_json_binary(&pair_info(factory_pair)),
+                _ => return SystemResult::Ok(ContractResult::Err("Unknown contract".to_string())),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        });
+        deps
+    }
+
+    fn new_checked(
+        deps: Deps,
+        lp_token: &str,
+        incentives: &str,
+    ) -> Result<AstroportStaking, CwDexError> {
+        AstroportStaking::new_checked(deps, Addr::unchecked(lp_token), Addr::unchecked(incentives))
+    }
+
+    fn assert_mismatch(res: Result<AstroportStaking, CwDexError>, reason: &str) {
+        match res.unwrap_err() {
+            CwDexError::StakingContractMismatch { reason: r, .. } => {
+                assert!(r.contains(reason), "unexpected reason: {r}")
+            }
+            err => panic!("Unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_new_checked() {
+        let deps = mock_contracts("lp_token", "pair");
+        let staking = new_checked(deps.as_ref(), "lp_token", "incentives").unwrap();
+        assert_eq!(staking.lp_token_addr, Addr::unchecked("lp_token"));
+        assert_eq!(staking.incentives, Addr::unchecked("incentives"));
+
+        // Not an incentives contract
+        assert_mismatch(
+            new_checked(deps.as_ref(), "lp_token", "lp_token"),
+            "not an Astroport incentives contract",
+        );
+        // Not a cw20
+        assert_mismatch(
+            new_checked(deps.as_ref(), "uatom", "incentives"),
+            "not a cw20 with a minter",
+        );
+
+        // The minter is a pair with another LP token
+        let deps = mock_contracts("other_lp_token", "pair");
+        assert_mismatch(
+            new_checked(deps.as_ref(), "lp_token", "incentives"),
+            "the LP token of pair pair is other_lp_token",
+        );
+
+        // The pair is not the one registered in the factory
+        let deps = mock_contracts("lp_token", "other_pair");
+        assert_mismatch(
+            new_checked(deps.as_ref(), "lp_token", "incentives"),
+            "factory factory has pair other_pair instead of pair",
+        );
+    }
+
+    #[test]
+    fn test_query_reward_emissions_errors() {
+        let staking = |incentives: &str| AstroportStaking {
+            lp_token_addr: Addr::unchecked("lp_token"),
+            incentives: Addr::unchecked(incentives),
+            strict_rewards: false,
+            cw20_adapter: None,
+            chain_profile: ChainProfile::default(),
+        };
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, .. } = query else {
+                panic!("Unexpected query");
+            };
+            let err = match contract_addr.as_str() {
+                "incentives" => "astroport_incentives::state::PoolInfo not found",
+                _ => "Unknown contract",
+            };
+            SystemResult::Ok(ContractResult::Err(err.to_string()))
+        });
+
+        // The incentives contract has no info on pools never incentivized
+        assert_eq!(
+            staking("incentives")
+                .query_reward_emissions(&deps.as_ref().querier)
+                .unwrap(),
+            vec![]
+        );
+        staking("other")
+            .query_reward_emissions(&deps.as_ref().querier)
+            .unwrap_err();
+
+        // A response that is not pool info
+        let deps = mock_contracts("lp_token", "pair");
+        staking("incentives")
+            .query_reward_emissions(&deps.as_ref().querier)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_query_vesting_rewards_errors() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| {
+            let WasmQuery::Smart { contract_addr, msg } = query else {
+                panic!("Unexpected query");
+            };
+            let res = match from_json(msg).unwrap() {
+                VestingQueryMsg::Config {} => to_json_binary(&VestingConfigResponse {
+                    owner: Addr::unchecked("owner"),
+                    vesting_token: AstroAssetInfoV3::NativeToken {
+                        denom: "astro".to_string(),
+                    },
+                }),
+                VestingQueryMsg::VestingAccount { .. } if contract_addr == "vesting" => {
+                    return SystemResult::Ok(ContractResult::Err(
+                        "astroport_vesting::state::VestingInfo not found".to_string(),
+                    ))
+                }
+                _ => return SystemResult::Ok(ContractResult::Err("Invalid input".to_string())),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        });
+        let query = |vesting: &str| {
+            AstroportStaking {
+                lp_token_addr: Addr::unchecked("lp_token"),
+                incentives: Addr::unchecked("incentives"),
+                strict_rewards: false,
+                cw20_adapter: None,
+                chain_profile: ChainProfile::default(),
+            }
+            .query_vesting_rewards(
+                &deps.as_ref().querier,
+                &Addr::unchecked(vesting),
+                &Addr::unchecked("user"),
+            )
+        };
+
+        // Users without a vesting account have no rewards
+        let rewards = query("vesting").unwrap();
+        assert_eq!(rewards.claimable, Asset::native("astro", 0u128));
+        assert_eq!(rewards.vesting, Asset::native("astro", 0u128));
+        query("other_vesting").unwrap_err();
+    }
+
+    #[test]
+    fn test_project_rewards() {
+        let schedule =
+            |denom: &str, per_second: &str, start: u64, end: Option<u64>| RewardSchedule {
+                info: AssetInfo::native(denom),
+                per_second: per_second.parse::<Decimal>().unwrap(),
+                start,
+                end,
+            };
+        let schedules = vec![
+            // ASTRO emissions
+            schedule("astro", "10", 1000, None),
+            // A schedule ending half way through the day, and the next one
+            schedule("uluna", "2.5", 1000, Some(44_200)),
+            schedule("uluna", "1", 44_200, Some(200_000)),
+            // A schedule that already ended
+            schedule("untrn", "100", 0, Some(1000)),
+        ];
+        let project = |staked: u128, total_staked: u128| {
+            project_rewards(
+                &schedules,
+                Uint128::new(staked),
+                Uint128::new(total_staked),
+                1000,
+                87_400,
+            )
+        };
+
+        // A quarter of the daily rewards, rounded down
+        let expected: AssetList = vec![
+            Asset::native("astro", 216_000u128),
+            Asset::native("uluna", 27_000u128 + 10_800u128),
+        ]
+        .into();
+        assert_eq!(project(250, 1000).unwrap(), expected);
+
+        assert_eq!(
+            project(1, 3).unwrap(),
+            vec![
+                Asset::native("astro", 288_000u128),
+                Asset::native("uluna", 50_400u128),
+            ]
+            .into()
+        );
+        assert_eq!(project(0, 0).unwrap(), AssetList::new());
+        assert!(project(1001, 1000).is_err());
+    }
+
+    fn staking(chain_profile: ChainProfile) -> AstroportStaking {
+        AstroportStaking {
+            lp_token_addr: Addr::unchecked("factory/pair/astroport/share"),
+            incentives: Addr::unchecked("incentives"),
+            strict_rewards: false,
+            cw20_adapter: None,
+            chain_profile,
+        }
+    }
+
+    #[test]
+    fn test_stake_msg_native_lp_token() {
+        // The LP tokens are deposited as funds
+        let msg = staking(ChainProfile::Injective)
+            .stake_msg(Uint128::new(100), Some("recipient".to_string()))
+            .unwrap();
+        assert_eq!(
+            msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "incentives".to_string(),
+                msg: to_json_binary(&IncentivesExecuteMsg::Deposit {
+                    recipient: Some("recipient".to_string()),
+                })
+                .unwrap(),
+                funds: coins(100, "factory/pair/astroport/share"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_stake_without_incentives() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = staking(ChainProfile::Sei);
+        let not_supported = CwDexError::StakingNotSupported {
+            reason: "the chain has no Astroport incentives contract".to_string(),
+        };
+
+        assert_eq!(
+            staking
+                .stake(deps.as_ref(), &env, Uint128::new(100))
+                .unwrap_err(),
+            not_supported
+        );
+        assert_eq!(
+            staking
+                .unstake(deps.as_ref(), &env, Uint128::new(100))
+                .unwrap_err(),
+            not_supported
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Staking/rewards traits implementations for the Astroport ASTRO staking
+//! (xASTRO) contract
+
+use std::fmt;
+
+use apollo_cw_asset::{AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, CosmosMsg, Decimal, Deps, Env, Event, QuerierWrapper, Response, StdError,
+    StdResult, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use astroport_v3::staking::{
+    ConfigResponse, Cw20HookMsg as StakingCw20HookMsg, QueryMsg as StakingQueryMsg,
+};
+
+use cw_dex::traits::{Rewards, Stake, Staking, Unstake};
+use cw_dex::CwDexError;
+
+/// Represents staking of ASTRO in the Astroport staking contract in exchange
+/// for xASTRO. Rewards are not paid out separately, instead the amount of ASTRO
+/// that one xASTRO can be redeemed for increases over time.
+#[cw_serde]
+pub struct AstroportXAstroStaking {
+    /// The address of the Astroport staking contract
+    pub staking_addr: Addr,
+    /// The address of the ASTRO token contract
+    pub astro_token_addr: Addr,
+    /// The address of the xASTRO token contract
+    pub xastro_token_addr: Addr,
+}
+
+impl fmt::Display for AstroportXAstroStaking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "astroport:xastro {}", self.staking_addr)
+    }
+}
+
+impl AstroportXAstroStaking {
+    /// Creates a new instance of `AstroportXAstroStaking`, querying the
+    /// staking contract for the addresses of the ASTRO and xASTRO tokens.
+    ///
+    /// Arguments:
+    /// - `staking_addr`: The address of the Astroport staking contract
+    pub fn new(deps: Deps, staking_addr: Addr) -> StdResult<Self> {
+        let config: ConfigResponse = deps
+            .querier
+            .query_wasm_smart(staking_addr.to_string(), &StakingQueryMsg::Config {})?;
+
+        Ok(Self {
+            staking_addr,
+            astro_token_addr: config.deposit_token_addr,
+            xastro_token_addr: config.share_token_addr,
+        })
+    }
+
+    /// Returns the amount of ASTRO that one xASTRO can currently be redeemed
+    /// for. Returns one if no xASTRO has been minted yet.
+    pub fn query_exchange_rate(&self, querier: &QuerierWrapper) -> StdResult<Decimal> {
+        let total_shares: Uint128 = querier.query_wasm_smart(
+            self.staking_addr.to_string(),
+            &StakingQueryMsg::TotalShares {},
+        )?;
+        let total_deposit: Uint128 = querier.query_wasm_smart(
+            self.staking_addr.to_string(),
+            &StakingQueryMsg::TotalDeposit {},
+        )?;
+
+        if total_shares.is_zero() {
+            return Ok(Decimal::one());
+        }
+        Decimal::checked_from_ratio(total_deposit, total_shares)
+            .map_err(|e| StdError::generic_err(e.to_string()))
+    }
+}
+
+impl Staking for AstroportXAstroStaking {}
+
+impl Stake for AstroportXAstroStaking {
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        Some(AssetInfo::Cw20(self.astro_token_addr.clone()))
+    }
+
+    /// Stakes `amount` of ASTRO in exchange for xASTRO.
+    fn stake(&self, _deps: Deps, _env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        let enter_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: self.astro_token_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: self.staking_addr.to_string(),
+                amount,
+                msg: to_json_binary(&StakingCw20HookMsg::Enter {})?,
+            })?,
+            funds: vec![],
+        });
+
+        let event = Event::new("apollo/cw-dex/stake")
+            .add_attribute("type", "astroport_xastro_staking")
+            .add_attribute("amount", amount);
+
+        Ok(Response::new().add_message(enter_msg).add_event(event))
+    }
+}
+
+impl Unstake for AstroportXAstroStaking {
+    /// Burns `amount` of xASTRO in exchange for ASTRO. Note that `amount` is
+    /// denominated in xASTRO, see
+    /// [`AstroportXAstroStaking::query_exchange_rate`].
+    fn unstake(&self, _deps: Deps, _env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        let leave_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: self.xastro_token_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: self.staking_addr.to_string(),
+                amount,
+                msg: to_json_binary(&StakingCw20HookMsg::Leave {})?,
+            })?,
+            funds: vec![],
+        });
+
+        let event = Event::new("apollo/cw-dex/unstake")
+            .add_attribute("type", "astroport_xastro_staking")
+            .add_attribute("amount", amount);
+
+        Ok(Response::new().add_message(leave_msg).add_event(event))
+    }
+}
+
+impl Rewards for AstroportXAstroStaking {
+    fn claim_rewards(&self, _deps: Deps, _env: &Env) -> Result<Response, CwDexError> {
+        // Rewards accrue to the xASTRO exchange rate and are received when
+        // unstaking.
+        let event = Event::new("apollo/cw-dex/claim_rewards")
+            .add_attribute("type", "astroport_xastro_staking");
+        Ok(Response::new().add_event(event))
+    }
+
+    fn query_pending_rewards(
+        &self,
+        _querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<AssetList, CwDexError> {
+        // Rewards accrue to the xASTRO exchange rate, so there is nothing to
+        // claim.
+        Ok(AssetList::new())
+    }
+}
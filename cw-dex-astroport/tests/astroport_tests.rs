@@ -3,25 +3,38 @@ mod tests {
     use apollo_utils::assets::separate_natives_and_cw20s;
     use apollo_utils::coins::coin_from_str;
     use apollo_utils::submessages::{find_event, parse_attribute_value};
+    use astroport::asset::AssetInfo as AstroAssetInfo;
     use astroport::factory::PairType;
     use astroport_v3::asset::Asset as AstroportAsset;
-    use cosmwasm_std::{assert_approx_eq, coin, coins, Addr, Coin, SubMsgResponse, Uint128};
+    use cosmwasm_std::{
+        assert_approx_eq, coin, coins, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal,
+        Empty, SubMsgResponse, Uint128, WasmMsg,
+    };
+    use cw20::Cw20ExecuteMsg;
 
     use cw_dex_test_contract::msg::{AstroportExecuteMsg, ExecuteMsg, QueryMsg};
     use cw_dex_test_helpers::astroport::setup_pool_and_test_contract;
     use cw_dex_test_helpers::{cw20_balance_query, cw20_transfer, query_asset_balance};
-    use cw_it::astroport::utils::AstroportContracts;
-    use cw_it::helpers::Unwrap;
+    use cw_it::astroport::utils::{create_astroport_pair, AstroportContracts};
+    use cw_it::cw_multi_test::ContractWrapper;
+    use cw_it::helpers::{upload_wasm_file, Unwrap};
     use cw_it::multi_test::MultiTestRunner;
     use cw_it::test_tube::cosmrs::proto::cosmwasm::wasm::v1::MsgExecuteContractResponse;
     use cw_it::test_tube::{
         Account, ExecuteResponse, Module, Runner, RunnerResult, SigningAccount, Wasm,
     };
     use cw_it::traits::CwItRunner;
-    use cw_it::{OwnedTestRunner, TestRunner};
+    use cw_it::{ContractType, OwnedTestRunner, TestRunner};
     use test_case::test_case;
 
-    use cw_dex_astroport::AstroportPool;
+    use cw_dex::dust::{DustConfig, DustPolicy};
+    use cw_dex::metrics::PoolMetrics;
+    use cw_dex::traits::{StakingPosition, StakingPositionInfo};
+    use cw_dex::PoolType;
+    use cw_dex_astroport::astroport_v3::pair::FeeShareConfig;
+    use cw_dex_astroport::{
+        AstroportPool, AstroportStaking, PairVersion, PoolInvariant, UserRewardIndex,
+    };
 
     #[cfg(feature = "osmosis-test-tube")]
     use cw_it::osmosis_test_tube::OsmosisTestApp;
@@ -122,13 +135,28 @@ mod tests {
             assets: asset_list.clone(),
             min_out: expected_out,
         };
-        let _res = runner
+        let res = runner
             .execute_cosmos_msgs::<MsgExecuteContractResponse>(
                 &[provide_msg.into_cosmos_msg(contract_addr.clone(), funds)],
                 admin,
             )
             .unwrap();
 
+        // The event has the used amounts and, except for concentrated liquidity
+        // pools, the expected LP tokens
+        let response = SubMsgResponse {
+            events: res.events,
+            data: None,
+        };
+        let event = find_event(&response, "wasm-apollo/cw-dex/provide_liquidity").unwrap();
+        let used = event.attributes.iter().filter(|a| a.key == "used").count();
+        assert_eq!(used, asset_list.len());
+        let expected_lp = event.attributes.iter().find(|a| a.key == "expected_lp");
+        match &pool_type {
+            PairType::Custom(_) => assert!(expected_lp.is_none()),
+            _ => assert_eq!(expected_lp.unwrap().value, expected_out.to_string()),
+        }
+
         // Query LP token balance after
         let lp_token_after =
             cw20_balance_query(&runner, lp_token_addr, contract_addr.clone()).unwrap();
@@ -141,6 +169,62 @@ mod tests {
         }
     }
 
+    #[test_case(vec![("uluna",1_000_000), ("astro", 1_000_000)]; "native-cw20")]
+    #[test_case(vec![("apollo",1_000_000), ("astro", 1_000_000)]; "cw20-cw20")]
+    fn test_provide_liquidity_decreasing_allowances(initial_liquidity: Vec<(&str, u64)>) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, lp_token_addr, _pair_addr, contract_addr, asset_list, astroport_contracts) =
+            setup_pool_and_testing_contract(&runner, PairType::Xyk {}, initial_liquidity).unwrap();
+        let admin = &accs[0];
+        let wasm = Wasm::new(&runner);
+
+        let (funds, cw20s) = separate_natives_and_cw20s(&asset_list);
+        for cw20 in &cw20s {
+            cw20_transfer(
+                &runner,
+                cw20.address.clone(),
+                contract_addr.clone(),
+                cw20.amount,
+                admin,
+            )
+            .unwrap();
+        }
+
+        let provide_msg = AstroportExecuteMsg::ProvideLiquidityDecreasingAllowances {
+            assets: asset_list.clone(),
+            min_out: Uint128::one(),
+        };
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[provide_msg.into_cosmos_msg(contract_addr.clone(), funds)],
+                admin,
+            )
+            .unwrap();
+
+        let lp_balance = cw20_balance_query(&runner, lp_token_addr, contract_addr.clone()).unwrap();
+        assert!(!lp_balance.is_zero());
+        for asset in asset_list.into_iter() {
+            let asset_balance = query_asset_balance(&runner, &asset.info, &contract_addr);
+            assert_eq!(asset_balance, Uint128::zero());
+        }
+
+        // No allowance is left to the liquidity manager
+        for cw20 in cw20s {
+            let allowance: cw20::AllowanceResponse = wasm
+                .query(
+                    &cw20.address,
+                    &cw20::Cw20QueryMsg::Allowance {
+                        owner: contract_addr.clone(),
+                        spender: astroport_contracts.liquidity_manager.address.clone(),
+                    },
+                )
+                .unwrap();
+            assert_eq!(allowance.allowance, Uint128::zero());
+            assert_eq!(allowance.expires, cw20::Expiration::Never {});
+        }
+    }
+
     #[test_case(PairType::Xyk { }, vec![("uluna",1_000_000), ("astro", 1_000_000)]; "withdraw_liquidity: xyk native-cw20")]
     #[test_case(PairType::Xyk { }, vec![("apollo",1_000_000), ("astro", 1_000_000)]; "withdraw_liquidity: xyk cw20-cw20")]
     #[test_case(PairType::Stable { }, vec![("uluna",1_000_000), ("astro", 1_000_000)]; "withdraw_liquidity: stableswap native-cw20")]
@@ -228,6 +312,52 @@ mod tests {
         }
     }
 
+    #[test_case(PairType::Xyk {}, vec![("uluna",1_000_000), ("astro", 1_000_000)]; "withdraw_liquidity_to: xyk native-cw20")]
+    #[test_case(PairType::Stable {}, vec![("uluna",1_000_000), ("uatom", 1_000_000)]; "withdraw_liquidity_to: stableswap native-native")]
+    #[test_case(PairType::Custom("concentrated".to_string()), vec![("apollo",1_000_000), ("astro", 1_000_000)]; "withdraw_liquidity_to: concentrated cw20-cw20")]
+    fn test_withdraw_liquidity_to(pool_type: PairType, initial_liquidity: Vec<(&str, u64)>) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, lp_token_addr, _pair_addr, contract_addr, asset_list, _) =
+            setup_pool_and_testing_contract(&runner, pool_type, initial_liquidity).unwrap();
+        let admin = &accs[0];
+        let recipient = &runner.init_account(&[]).unwrap();
+        let wasm = Wasm::new(&runner);
+
+        // Send LP tokens to contract
+        let amount = cw20_balance_query(&runner, lp_token_addr.clone(), admin.address()).unwrap()
+            / Uint128::from(2u128);
+        cw20_transfer(&runner, lp_token_addr, contract_addr.clone(), amount, admin).unwrap();
+
+        let expected_out: AssetList = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::SimulateWithdrawLiquidty { amount },
+            )
+            .unwrap();
+        let withdraw_msg = AstroportExecuteMsg::WithdrawLiquidityTo {
+            amount,
+            min_out: expected_out.clone(),
+            recipient: recipient.address(),
+        };
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[withdraw_msg.into_cosmos_msg(contract_addr.clone(), vec![])],
+                admin,
+            )
+            .unwrap();
+
+        // Assert that the withdrawn assets were sent to the recipient and none
+        // were left in the contract
+        for asset in asset_list.into_iter() {
+            let expected = expected_out.find(&asset.info).unwrap().amount;
+            let recipient_balance = query_asset_balance(&runner, &asset.info, &recipient.address());
+            assert_eq!(recipient_balance, expected);
+            let contract_balance = query_asset_balance(&runner, &asset.info, &contract_addr);
+            assert_eq!(contract_balance, Uint128::zero());
+        }
+    }
+
     fn stake_all_lp_tokens<'a, R: Runner<'a>>(
         runner: &'a R,
         contract_addr: String,
@@ -327,6 +457,364 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_migrate_position() {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, lp_token_addr, _pair_addr, contract_addr, _asset_list, astroport_contracts) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+        let wasm = Wasm::new(&runner);
+
+        let lp_amount =
+            cw20_balance_query(&runner, lp_token_addr.clone(), admin.address()).unwrap();
+        cw20_transfer(
+            &runner,
+            lp_token_addr.clone(),
+            contract_addr.clone(),
+            lp_amount,
+            admin,
+        )
+        .unwrap();
+        stake_all_lp_tokens(&runner, contract_addr.clone(), lp_token_addr.clone(), admin);
+
+        // A new incentives contract, as after a migration of Astroport
+        let new_incentives = wasm
+            .instantiate(
+                astroport_contracts.incentives.code_id,
+                &astroport_v3::incentives::InstantiateMsg {
+                    astro_token: astroport_v3::asset::AssetInfo::Token {
+                        contract_addr: Addr::unchecked(&astroport_contracts.astro_token.address),
+                    },
+                    factory: astroport_contracts.factory.address.clone(),
+                    guardian: None,
+                    incentivization_fee_info: None,
+                    owner: admin.address(),
+                    vesting_contract: astroport_contracts.vesting.address.clone(),
+                },
+                None,
+                Some("New Incentives"),
+                &[],
+                admin,
+            )
+            .unwrap()
+            .data
+            .address;
+
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::MigratePosition {
+                    amount: lp_amount,
+                    incentives: new_incentives.clone(),
+                }
+                .into_cosmos_msg(contract_addr.clone(), vec![])],
+                admin,
+            )
+            .unwrap();
+
+        let query_deposit = |incentives: &str| -> Uint128 {
+            wasm.query(
+                incentives,
+                &astroport_v3::incentives::QueryMsg::Deposit {
+                    lp_token: lp_token_addr.clone(),
+                    user: contract_addr.clone(),
+                },
+            )
+            .unwrap()
+        };
+        assert_eq!(query_deposit(&new_incentives), lp_amount);
+        assert!(query_deposit(&astroport_contracts.incentives.address).is_zero());
+        let lp_balance =
+            cw20_balance_query(&runner, lp_token_addr.clone(), contract_addr.clone()).unwrap();
+        assert!(lp_balance.is_zero());
+    }
+
+    #[test_case(None; "stake_via_cw20_send: no recipient")]
+    #[test_case(Some(1); "stake_via_cw20_send: recipient")]
+    fn test_stake_via_cw20_send(recipient_idx: Option<usize>) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, lp_token_addr, _pair_addr, contract_addr, _asset_list, astroport_contracts) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+
+        let admin = &accs[0];
+        let recipient = recipient_idx.map(|i| accs[i].address());
+        let stake_amount = cw20_balance_query(&runner, lp_token_addr.clone(), admin.address())
+            .unwrap()
+            / Uint128::new(2);
+
+        // Send LP tokens to the test contract with the stake hook as payload
+        let send_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: lp_token_addr.clone(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: contract_addr.clone(),
+                amount: stake_amount,
+                msg: AstroportStaking::stake_hook_msg(recipient.clone()).unwrap(),
+            })
+            .unwrap(),
+            funds: vec![],
+        });
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(&[send_msg], admin)
+            .unwrap();
+
+        // Assert that the LP tokens were staked on behalf of the recipient
+        let wasm = Wasm::new(&runner);
+        let staked: Uint128 = wasm
+            .query(
+                &astroport_contracts.incentives.address,
+                &astroport_v3::incentives::QueryMsg::Deposit {
+                    lp_token: lp_token_addr.clone(),
+                    user: recipient.clone().unwrap_or_else(|| contract_addr.clone()),
+                },
+            )
+            .unwrap();
+        assert_eq!(staked, stake_amount);
+
+        // Simulate staking and unstaking of the test contract's own position
+        if recipient.is_none() {
+            let position: StakingPosition = wasm
+                .query(
+                    &contract_addr,
+                    &QueryMsg::SimulateStake {
+                        amount: Uint128::one(),
+                    },
+                )
+                .unwrap();
+            assert_eq!(position.staked, stake_amount + Uint128::one());
+            let position: StakingPosition = wasm
+                .query(
+                    &contract_addr,
+                    &QueryMsg::SimulateUnstake {
+                        amount: stake_amount,
+                    },
+                )
+                .unwrap();
+            assert_eq!(position.staked, Uint128::zero());
+            // No incentives are set up, so no rewards are paid out
+            assert_eq!(position.rewards, AssetList::new());
+
+            let position: StakingPositionInfo = wasm
+                .query(&contract_addr, &QueryMsg::StakingPosition {})
+                .unwrap();
+            assert_eq!(
+                position,
+                StakingPositionInfo {
+                    staked: stake_amount,
+                    unlocking: vec![],
+                    pending_rewards: AssetList::new(),
+                    lockup_duration: None,
+                }
+            );
+        }
+
+        // Assert that a payload that is not a stake hook is rejected
+        let invalid_send_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: lp_token_addr,
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: contract_addr,
+                amount: stake_amount,
+                msg: to_json_binary(&AstroportExecuteMsg::ClaimRewards {}).unwrap(),
+            })
+            .unwrap(),
+            funds: vec![],
+        });
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(&[invalid_send_msg], admin)
+            .unwrap_err();
+    }
+
+    #[test_case(PairType::Xyk {}, vec![("uluna",1_000_000), ("uatom", 1_000_000)]; "swap_and_stake: xyk native-native")]
+    #[test_case(PairType::Xyk {}, vec![("uluna",68_582_147), ("uatom", 3_467_256)]; "swap_and_stake: xyk native-native, random prices")]
+    #[test_case(PairType::Stable {}, vec![("uluna",1_000_000), ("uatom", 1_000_000)]; "swap_and_stake: stableswap native-native")]
+    #[test_case(PairType::Custom("concentrated".to_string()), vec![("uluna",1_000_000), ("uatom", 1_000_000)]; "swap_and_stake: concentrated native-native")]
+    fn test_swap_and_stake(pool_type: PairType, initial_liquidity: Vec<(&str, u64)>) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, lp_token_addr, _pair_addr, contract_addr, _asset_list, astroport_contracts) =
+            setup_pool_and_testing_contract(&runner, pool_type, initial_liquidity).unwrap();
+
+        let admin = &accs[0];
+        let offer = coin(100_000, "uluna");
+
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::SwapAndStake {
+                    offer: offer.clone().into(),
+                    min_lp_out: Uint128::one(),
+                    dust: None,
+                }
+                .into_cosmos_msg(contract_addr.clone(), vec![offer])],
+                admin,
+            )
+            .unwrap();
+
+        // Assert that all received LP tokens were staked
+        let lp_token_balance =
+            cw20_balance_query(&runner, lp_token_addr.clone(), contract_addr.clone()).unwrap();
+        assert_eq!(lp_token_balance, Uint128::zero());
+
+        let wasm = Wasm::new(&runner);
+        let staked: Uint128 = wasm
+            .query(
+                &astroport_contracts.incentives.address,
+                &astroport_v3::incentives::QueryMsg::Deposit {
+                    lp_token: lp_token_addr,
+                    user: contract_addr.clone(),
+                },
+            )
+            .unwrap();
+        assert!(!staked.is_zero());
+
+        // Assert that only dust of the pool assets is left in the contract
+        for denom in ["uluna", "uatom"] {
+            let balance = query_asset_balance(&runner, &AssetInfo::native(denom), &contract_addr);
+            assert!(balance < Uint128::new(1_000), "{denom} balance: {balance}");
+        }
+    }
+
+    #[test_case(false; "swap_and_stake_dust: provide on next")]
+    #[test_case(true; "swap_and_stake_dust: sweep")]
+    fn test_swap_and_stake_dust(sweep: bool) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+
+        let admin = &accs[0];
+        let recipient = runner.init_account(&[]).unwrap();
+        let policy = if sweep {
+            DustPolicy::Sweep {
+                recipient: Addr::unchecked(recipient.address()),
+            }
+        } else {
+            DustPolicy::ProvideOnNext
+        };
+        let dust = DustConfig {
+            policy,
+            thresholds: vec![
+                Asset::native("uluna", 1_000u128),
+                Asset::native("uatom", 1_000u128),
+            ],
+        };
+
+        // Leave some dust in the contract
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[BankMsg::Send {
+                    to_address: contract_addr.clone(),
+                    amount: coins(500, "uatom"),
+                }
+                .into()],
+                admin,
+            )
+            .unwrap();
+
+        let offer = coin(100_000, "uluna");
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::SwapAndStake {
+                    offer: offer.clone().into(),
+                    min_lp_out: Uint128::one(),
+                    dust: Some(dust),
+                }
+                .into_cosmos_msg(contract_addr.clone(), vec![offer])],
+                admin,
+            )
+            .unwrap();
+
+        let uatom_balance =
+            query_asset_balance(&runner, &AssetInfo::native("uatom"), &contract_addr);
+        if sweep {
+            // All dust was sent to the recipient
+            for denom in ["uluna", "uatom"] {
+                let balance =
+                    query_asset_balance(&runner, &AssetInfo::native(denom), &contract_addr);
+                assert_eq!(balance, Uint128::zero(), "{denom} balance: {balance}");
+            }
+            let swept =
+                query_asset_balance(&runner, &AssetInfo::native("uatom"), &recipient.address());
+            assert!(swept >= Uint128::new(500));
+        } else {
+            // The dust was provided as liquidity
+            assert!(
+                uatom_balance < Uint128::new(500),
+                "uatom balance: {uatom_balance}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_provide_liquidity_tracking_refunds() {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+
+        // Provide more uluna than the pool ratio requires
+        let funds = vec![coin(1_000, "uatom"), coin(1_100, "uluna")];
+        let assets: AssetList = funds.clone().into();
+        let res = runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::ProvideLiquidityTrackingRefunds {
+                    assets,
+                    min_out: Uint128::one(),
+                }
+                .into_cosmos_msg(contract_addr.clone(), funds)],
+                admin,
+            )
+            .unwrap();
+
+        let response = SubMsgResponse {
+            events: res.events,
+            data: None,
+        };
+        let event = find_event(&response, "wasm-apollo/cw-dex/provide_liquidity_refund").unwrap();
+        let unused: String = parse_attribute_value(event, "unused").unwrap();
+        let refunded = event
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "refunded")
+            .map(|attr| attr.value.clone())
+            .unwrap_or_default();
+
+        let expected_unused: AssetList = vec![Asset::native("uluna", 100u128)].into();
+        assert_eq!(unused, expected_unused.to_string());
+
+        // The refunded amounts are exactly what is left in the contract
+        let left: Vec<Asset> = ["uluna", "uatom"]
+            .into_iter()
+            .map(|denom| {
+                let info = AssetInfo::native(denom);
+                let amount = query_asset_balance(&runner, &info, &contract_addr);
+                Asset::new(info, amount)
+            })
+            .filter(|asset| !asset.amount.is_zero())
+            .collect();
+        assert_eq!(refunded, AssetList::from(left).to_string());
+    }
+
     #[test_case(PairType::Xyk{},vec![("astro",1_000_000), ("uluna", 1_000_000)], Uint128::new(1_000_000); "swap_and_simulate_swap: basic pool")]
     #[test_case(PairType::Xyk{},vec![("uluna",1_000_000), ("astro", 1_000_000)], Uint128::new(2); "swap_and_simulate_swap: basic pool small amount")]
     #[test_case(PairType::Xyk{},vec![("uluna",1_000_000), ("astro", 1_000_000)], Uint128::new(100_000_000); "swap_and_simulate_swap: basic pool, high slippage")]
@@ -416,37 +904,330 @@ mod tests {
         assert_eq!(offer_balance, Uint128::zero());
     }
 
-    #[test_case(vec![(coin(2_000_000_000, "uluna"), 1)], vec![]; "one native incentive one period")]
-    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2)], vec![]; "one native incentive two periods")]
-    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2), (coin(2_000_000_000, "untrn"), 1)], vec![]; "two native incentive different periods")]
-    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2), (coin(2_000_000_000, "untrn"), 1)], vec![(4_000_000_000u128.into(), 2)]; "two native incentive different periods one cw20 incentive")]
-    fn test_claim_rewards(
-        native_incentives: Vec<(Coin, u64)>,
-        cw20_incentives: Vec<(Uint128, u64)>,
-    ) -> RunnerResult<()> {
-        let pool_type = PairType::Xyk {};
-        let initial_liquidity = vec![("uluna", 1_000_000), ("astro", 1_000_000)];
-
+    #[test]
+    fn test_claim_rewards_without_incentives() {
         let owned_runner = get_test_runner();
         let runner = owned_runner.as_ref();
-        let (
-            accs,
-            lp_token_addr,
-            _pair_addr,
-            testing_contract_addr,
-            _asset_list,
-            astroport_contracts,
-        ) = setup_pool_and_testing_contract(&runner, pool_type, initial_liquidity).unwrap();
-
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
         let admin = &accs[0];
 
-        // Increase time to current time. For some reason the start time of the
-        // incenitve epochs is hard coded in the astroport incentives contract
-        // and the logic doesn't work for earlier timestamps
-        let block_time = runner.query_block_time_nanos() / 1_000_000_000;
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
+        // The pool was never staked in, so the incentives contract has no info on
+        // it. Pending rewards are empty and claiming is a no-op.
+        let wasm = Wasm::new(&runner);
+        let pending: AssetList = wasm
+            .query(&contract_addr, &QueryMsg::PendingRewards {})
+            .unwrap();
+        assert_eq!(pending, AssetList::new());
+
+        let res = runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::ClaimRewards {}
+                    .into_cosmos_msg(contract_addr.clone(), vec![])],
+                admin,
+            )
+            .unwrap();
+        let response = SubMsgResponse {
+            events: res.events,
+            data: None,
+        };
+        let event = find_event(&response, "wasm-apollo/cw-dex/claim_rewards").unwrap();
+        assert_eq!(
+            parse_attribute_value::<String, _>(event, "skipped").unwrap(),
+            "no_rewards"
+        );
+
+        let res = runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::ClaimRewardsFiltered {
+                    assets: vec![AssetInfo::native("uluna")],
+                    return_to: None,
+                }
+                .into_cosmos_msg(contract_addr, vec![])],
+                admin,
+            )
+            .unwrap();
+        let response = SubMsgResponse {
+            events: res.events,
+            data: None,
+        };
+        let event = find_event(&response, "wasm-apollo/cw-dex/claim_rewards_filtered").unwrap();
+        assert_eq!(
+            parse_attribute_value::<String, _>(event, "skipped").unwrap(),
+            "no_rewards"
+        );
+    }
+
+    #[test]
+    fn test_vesting_rewards() {
+        use astroport_v3::vesting::{
+            Cw20HookMsg as VestingHookMsg, VestingAccount, VestingSchedule, VestingSchedulePoint,
+        };
+        use cw_dex_astroport::VestingRewards;
+
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, astroport_contracts) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+        let wasm = Wasm::new(&runner);
+        let vesting = astroport_contracts.vesting.address.clone();
+        let astro = AssetInfo::cw20(Addr::unchecked(&astroport_contracts.astro_token.address));
+        let query_vesting_rewards = || -> VestingRewards {
+            wasm.query(
+                &contract_addr,
+                &QueryMsg::VestingRewards {
+                    vesting: vesting.clone(),
+                },
+            )
+            .unwrap()
+        };
+        let claim_vested = || {
+            let res = runner
+                .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                    &[AstroportExecuteMsg::ClaimVested {
+                        vesting: vesting.clone(),
+                    }
+                    .into_cosmos_msg(contract_addr.clone(), vec![])],
+                    admin,
+                )
+                .unwrap();
+            SubMsgResponse {
+                events: res.events,
+                data: None,
+            }
+        };
+
+        // Without a vesting account there are no rewards
+        let rewards = query_vesting_rewards();
+        assert_eq!(rewards.claimable, Asset::new(astro.clone(), 0u128));
+        assert_eq!(rewards.vesting, Asset::new(astro.clone(), 0u128));
+
+        // Vest 1000 ASTRO to the contract linearly over 1000 seconds
+        let now = runner.query_block_time_nanos() / 1_000_000_000;
+        wasm.execute(
+            &astroport_contracts.astro_token.address,
+            &Cw20ExecuteMsg::Send {
+                contract: vesting.clone(),
+                amount: Uint128::new(1000),
+                msg: to_json_binary(&VestingHookMsg::RegisterVestingAccounts {
+                    vesting_accounts: vec![VestingAccount {
+                        address: contract_addr.clone(),
+                        schedules: vec![VestingSchedule {
+                            start_point: VestingSchedulePoint {
+                                time: now,
+                                amount: Uint128::zero(),
+                            },
+                            end_point: Some(VestingSchedulePoint {
+                                time: now + 1000,
+                                amount: Uint128::new(1000),
+                            }),
+                        }],
+                    }],
+                })
+                .unwrap(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let rewards = query_vesting_rewards();
+        assert_eq!(rewards.claimable, Asset::new(astro.clone(), 0u128));
+        assert_eq!(rewards.vesting, Asset::new(astro.clone(), 1000u128));
+        let response = claim_vested();
+        let event = find_event(&response, "wasm-apollo/cw-dex/claim_vested").unwrap();
+        assert_eq!(
+            parse_attribute_value::<String, _>(event, "skipped").unwrap(),
+            "no_rewards"
+        );
+
+        // Half of the rewards vest after half of the schedule
+        runner.increase_time(500).unwrap();
+        let rewards = query_vesting_rewards();
+        assert_eq!(rewards.claimable, Asset::new(astro.clone(), 500u128));
+        assert_eq!(rewards.vesting, Asset::new(astro.clone(), 500u128));
+
+        claim_vested();
+        assert_eq!(
+            query_asset_balance(&runner, &astro, &contract_addr),
+            Uint128::new(500)
+        );
+        let rewards = query_vesting_rewards();
+        assert_eq!(rewards.claimable, Asset::new(astro.clone(), 0u128));
+        assert_eq!(rewards.vesting, Asset::new(astro, 500u128));
+    }
+
+    #[test]
+    fn test_swap_asset_not_in_pool() {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+
+        let offer = coin(1_000, "uluna");
+        let err = runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[ExecuteMsg::Swap {
+                    offer: offer.clone().into(),
+                    ask: AssetInfo::native("uosmo"),
+                    min_out: Uint128::zero(),
+                }
+                .into_cosmos_msg(contract_addr.clone(), vec![offer])],
+                admin,
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Asset is not in pool: uosmo"),
+            "{err}"
+        );
+    }
+
+    #[test_case(PairType::Xyk {}; "swap_zero_min_out: xyk")]
+    #[test_case(PairType::Stable {}; "swap_zero_min_out: stableswap")]
+    #[test_case(PairType::Custom("concentrated".to_string()); "swap_zero_min_out: concentrated")]
+    fn test_swap_zero_min_out(pool_type: PairType) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                pool_type,
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+
+        // No belief price is sent for a zero min_out, so the swap succeeds
+        let offer = coin(1_000, "uluna");
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[ExecuteMsg::Swap {
+                    offer: offer.clone().into(),
+                    ask: AssetInfo::native("uatom"),
+                    min_out: Uint128::zero(),
+                }
+                .into_cosmos_msg(contract_addr.clone(), vec![offer])],
+                admin,
+            )
+            .unwrap();
+
+        let ask_balance = query_asset_balance(&runner, &AssetInfo::native("uatom"), &contract_addr);
+        assert!(!ask_balance.is_zero());
+    }
+
+    #[test_case(PairType::Xyk {}, vec![("uluna", 1_000_000), ("uatom", 1_000_000)], 1_000; "simulate_swap_local: xyk")]
+    #[test_case(PairType::Xyk {}, vec![("uluna", 68_582_147), ("uatom", 3_467_256)], 1_000_000; "simulate_swap_local: xyk, random prices")]
+    #[test_case(PairType::Xyk {}, vec![("uluna", 1_000_000), ("astro", 1_000_000)], 100_000_000; "simulate_swap_local: xyk, high slippage, native-cw20")]
+    fn test_simulate_swap_local(
+        pool_type: PairType,
+        initial_liquidity: Vec<(&str, u64)>,
+        offer_amount: u128,
+    ) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (_accs, _lp_token_addr, _pair_addr, contract_addr, asset_list, _) =
+            setup_pool_and_testing_contract(&runner, pool_type, initial_liquidity).unwrap();
+        let wasm = Wasm::new(&runner);
+
+        let assets = asset_list.to_vec();
+        let offer = Asset::new(assets[0].info.clone(), offer_amount);
+        let ask = assets[1].info.clone();
+        let on_chain: Uint128 = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::SimulateSwap {
+                    offer: offer.clone(),
+                    ask: ask.clone(),
+                },
+            )
+            .unwrap();
+        let local: Uint128 = wasm
+            .query(&contract_addr, &QueryMsg::SimulateSwapLocal { offer, ask })
+            .unwrap();
+
+        assert_eq!(local, on_chain);
+    }
+
+    #[test]
+    fn test_simulate_swap_local_stableswap_unsupported() {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (_accs, _lp_token_addr, _pair_addr, contract_addr, asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Stable {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let wasm = Wasm::new(&runner);
+
+        let assets = asset_list.to_vec();
+        let err = wasm
+            .query::<_, Uint128>(
+                &contract_addr,
+                &QueryMsg::SimulateSwapLocal {
+                    offer: Asset::new(assets[0].info.clone(), 1_000u128),
+                    ask: assets[1].info.clone(),
+                },
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Local swap simulation is not supported for stable pairs"));
+    }
+
+    #[test_case(vec![(coin(2_000_000_000, "uluna"), 1)], vec![], false, false; "one native incentive one period")]
+    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2)], vec![], false, false; "one native incentive two periods")]
+    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2), (coin(2_000_000_000, "untrn"), 1)], vec![], false, false; "two native incentive different periods")]
+    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2), (coin(2_000_000_000, "untrn"), 1)], vec![(4_000_000_000u128.into(), 2)], false, false; "two native incentive different periods one cw20 incentive")]
+    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2), (coin(2_000_000_000, "untrn"), 1)], vec![(4_000_000_000u128.into(), 2)], true, false; "two native incentive different periods one cw20 incentive with accounting")]
+    #[test_case(vec![(coin(4_000_000_000, "uluna"), 2)], vec![(4_000_000_000u128.into(), 2)], false, true; "one native incentive one cw20 incentive with cw20 adapter")]
+    fn test_claim_rewards(
+        native_incentives: Vec<(Coin, u64)>,
+        cw20_incentives: Vec<(Uint128, u64)>,
+        accounting: bool,
+        cw20_adapter: bool,
+    ) -> RunnerResult<()> {
+        let pool_type = PairType::Xyk {};
+        let initial_liquidity = vec![("uluna", 1_000_000), ("astro", 1_000_000)];
+
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (
+            accs,
+            lp_token_addr,
+            _pair_addr,
+            testing_contract_addr,
+            _asset_list,
+            astroport_contracts,
+        ) = setup_pool_and_testing_contract(&runner, pool_type, initial_liquidity).unwrap();
+
+        let admin = &accs[0];
+
+        // Increase time to current time. For some reason the start time of the
+        // incenitve epochs is hard coded in the astroport incentives contract
+        // and the logic doesn't work for earlier timestamps
+        let block_time = runner.query_block_time_nanos() / 1_000_000_000;
+        let current_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
             .as_secs();
         runner
             .increase_time((current_time.saturating_sub(block_time)) as u64)
@@ -477,6 +1258,7 @@ mod tests {
         // Create Cw20 tokens for each Cw20 incentive, mint incentive amount to
         // incentives_provider and add to incentives
         let cw20_code_id = astroport_contracts.astro_token.code_id;
+        let mut cw20_incentive_addrs = vec![];
         for (i, (amount, duration)) in cw20_incentives.iter().enumerate() {
             // Instantiate Cw20 token
             let cw20_addr = wasm
@@ -502,6 +1284,8 @@ mod tests {
                 .data
                 .address;
 
+            cw20_incentive_addrs.push((cw20_addr.clone(), *amount));
+
             // Add Cw20 incentive to incentives
             incentives.push((
                 AstroportAsset::cw20(Addr::unchecked(cw20_addr), *amount),
@@ -547,6 +1331,54 @@ mod tests {
             .unwrap();
         }
 
+        // Wrap the cw20 rewards into token factory denoms with a mock of the
+        // cw20 adapter, funded with the denoms it pays out
+        let adapter_addr = if cw20_adapter {
+            let contract =
+                ContractType::MultiTestContract(Box::new(ContractWrapper::new_with_empty(
+                    mock_cw20_adapter::execute,
+                    mock_cw20_adapter::instantiate,
+                    mock_cw20_adapter::query,
+                )));
+            let code_id = upload_wasm_file(&runner, admin, contract).unwrap();
+            let adapter_addr = wasm
+                .instantiate(code_id, &Empty {}, None, Some("cw20_adapter"), &[], admin)
+                .unwrap()
+                .data
+                .address;
+            let funds: Vec<Coin> = cw20_incentive_addrs
+                .iter()
+                .map(|(cw20_addr, amount)| {
+                    coin(
+                        amount.u128(),
+                        mock_cw20_adapter::denom(&adapter_addr, cw20_addr),
+                    )
+                })
+                .collect();
+            let funder = runner
+                .init_account(&[funds.clone(), coins(10000000000, "uosmo")].concat())
+                .unwrap();
+            wasm.execute(
+                &adapter_addr,
+                &mock_cw20_adapter::ExecuteMsg::Fund {},
+                &funds,
+                &funder,
+            )
+            .unwrap();
+            wasm.execute(
+                &testing_contract_addr,
+                &AstroportExecuteMsg::SetCw20Adapter {
+                    adapter: adapter_addr.clone(),
+                },
+                &[],
+                admin,
+            )
+            .unwrap();
+            Some(adapter_addr)
+        } else {
+            None
+        };
+
         // Query LP token balance
         let lp_token_balance =
             cw20_balance_query(&runner, lp_token_addr.clone(), admin.address()).unwrap();
@@ -570,6 +1402,16 @@ mod tests {
         )
         .events;
 
+        // Project the rewards of the next day
+        let projected_rewards: AssetList = wasm
+            .query(
+                &testing_contract_addr,
+                &QueryMsg::ProjectedRewards {
+                    duration: 60 * 60 * 24,
+                },
+            )
+            .unwrap();
+
         // Increase time by 1 week
         runner.increase_time(60 * 60 * 24).unwrap();
 
@@ -607,24 +1449,159 @@ mod tests {
             assert_eq!(amount, asset.amount);
         }
 
+        // The projected rewards are the pending rewards up to rounding
+        for reward in cw_dex_pending_rewards.to_vec() {
+            let projected = projected_rewards.find(&reward.info).unwrap().amount;
+            assert_approx_eq!(projected, reward.amount, "0.000001");
+        }
+
+        // The rewards accrued according to the reward indices are the pending
+        // rewards, as the position was not checkpointed since staking
+        let reward_index: UserRewardIndex = wasm
+            .query(&testing_contract_addr, &QueryMsg::UserRewardIndex {})
+            .unwrap();
+        assert_eq!(reward_index.staked, lp_token_balance);
+        let mut accrued = AssetList::new();
+        for index in &reward_index.indices {
+            accrued
+                .add(&Asset::new(
+                    index.info.clone(),
+                    index.accrued(reward_index.staked),
+                ))
+                .unwrap();
+        }
+        for reward in cw_dex_pending_rewards.to_vec() {
+            assert_eq!(accrued.find(&reward.info).unwrap().amount, reward.amount);
+        }
+
         // Claim rewards
-        wasm.execute(
-            &testing_contract_addr,
-            &cw_dex_test_contract::msg::AstroportExecuteMsg::ClaimRewards {},
-            &[],
-            admin,
-        )
-        .unwrap();
+        let reward_tokens: Vec<AssetInfo> = cw_dex_pending_rewards
+            .iter()
+            .map(|reward| reward.info.clone())
+            .collect();
+        let msg = if accounting {
+            cw_dex_test_contract::msg::AstroportExecuteMsg::ClaimRewardsWithAccounting {
+                reward_tokens: reward_tokens.clone(),
+            }
+        } else {
+            cw_dex_test_contract::msg::AstroportExecuteMsg::ClaimRewards {}
+        };
+        let res = wasm
+            .execute(&testing_contract_addr, &msg, &[], admin)
+            .unwrap();
 
-        // Assert that testing contract has correct asset balances
+        // Assert that testing contract has correct asset balances. With the
+        // cw20 adapter, the cw20 rewards are received as token factory denoms.
+        let mut wrapped = vec![];
         for reward in cw_dex_pending_rewards.to_vec() {
-            let asset_balance = query_asset_balance(&runner, &reward.info, &testing_contract_addr);
+            let info = match (&reward.info, &adapter_addr) {
+                (AssetInfo::Cw20(cw20_addr), Some(adapter_addr)) => {
+                    assert!(
+                        query_asset_balance(&runner, &reward.info, &testing_contract_addr)
+                            .is_zero()
+                    );
+                    wrapped.push(cw20_addr.to_string());
+                    AssetInfo::native(mock_cw20_adapter::denom(adapter_addr, cw20_addr.as_str()))
+                }
+                _ => reward.info.clone(),
+            };
+            let asset_balance = query_asset_balance(&runner, &info, &testing_contract_addr);
             assert_approx_eq!(asset_balance, reward.amount, "0.0001"); // TODO: Why is there a diff here?
         }
+        if adapter_addr.is_some() {
+            assert!(!wrapped.is_empty());
+            let response = SubMsgResponse {
+                events: res.events.clone(),
+                data: None,
+            };
+            let event = find_event(&response, "wasm-apollo/cw-dex/claim_rewards").unwrap();
+            assert_eq!(
+                parse_attribute_value::<String, _>(event, "wrapped").unwrap(),
+                wrapped.join(",")
+            );
+        }
+
+        // The accounting event reports exactly the claimed amounts, i.e. the
+        // balances of the contract, which held no reward tokens before
+        if accounting {
+            let response = SubMsgResponse {
+                events: res.events,
+                data: None,
+            };
+            let event = find_event(&response, "wasm-apollo/cw-dex/rewards_claimed").unwrap();
+            let balances: AssetList = reward_tokens
+                .iter()
+                .map(|info| {
+                    Asset::new(
+                        info.clone(),
+                        query_asset_balance(&runner, info, &testing_contract_addr),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .into();
+            assert_eq!(
+                parse_attribute_value::<String, _>(event, "claimed").unwrap(),
+                balances.to_string()
+            );
+        }
 
         Ok(())
     }
 
+    /// A mock of the Injective cw20-adapter contract, which pays out the token
+    /// factory denom of received cw20 tokens from its balance instead of
+    /// minting it
+    mod mock_cw20_adapter {
+        use cosmwasm_schema::cw_serde;
+        use cosmwasm_std::{
+            coins, BankMsg, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError,
+            StdResult,
+        };
+        use cw20::Cw20ReceiveMsg;
+
+        #[cw_serde]
+        pub enum ExecuteMsg {
+            /// Accepts the denoms to pay out
+            Fund {},
+            Receive(Cw20ReceiveMsg),
+        }
+
+        pub fn denom(adapter: &str, cw20: &str) -> String {
+            format!("factory/{adapter}/{cw20}")
+        }
+
+        pub fn instantiate(
+            _deps: DepsMut,
+            _env: Env,
+            _info: MessageInfo,
+            _msg: Empty,
+        ) -> StdResult<Response> {
+            Ok(Response::default())
+        }
+
+        pub fn execute(
+            _deps: DepsMut,
+            env: Env,
+            info: MessageInfo,
+            msg: ExecuteMsg,
+        ) -> StdResult<Response> {
+            match msg {
+                ExecuteMsg::Fund {} => Ok(Response::default()),
+                ExecuteMsg::Receive(msg) => Ok(Response::new().add_message(BankMsg::Send {
+                    to_address: msg.sender,
+                    amount: coins(
+                        msg.amount.u128(),
+                        denom(env.contract.address.as_str(), info.sender.as_str()),
+                    ),
+                })),
+            }
+        }
+
+        pub fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+            Err(StdError::generic_err("no queries"))
+        }
+    }
+
     #[test]
     fn test_get_pool_for_lp_token() {
         let owned_runner = get_test_runner();
@@ -655,5 +1632,400 @@ mod tests {
                 .map(|x| x.info.clone())
                 .collect::<Vec<AssetInfo>>()
         );
+        // The pair contracts used in the tests are astroport-pair 1.3, which
+        // accepts `ask_asset_info`
+        assert_eq!(pool.pair_version, PairVersion::Current);
+        assert_eq!(
+            pool.to_string(),
+            format!("astroport:pair {}/xyk", pool.pair_addr)
+        );
+    }
+
+    #[test]
+    fn test_unchecked_pool() {
+        use cosmwasm_std::testing::mock_dependencies;
+        use cosmwasm_std::{ContractResult, SystemResult, WasmQuery};
+
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (_accs, lp_token_addr, pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let wasm = Wasm::new(&runner);
+        let checked: AstroportPool = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::GetPoolForLpToken {
+                    lp_token: AssetInfo::cw20(Addr::unchecked(&lp_token_addr)),
+                },
+            )
+            .unwrap();
+
+        let unchecked = AstroportPool::unchecked(
+            Addr::unchecked(&pair_addr),
+            Addr::unchecked(&lp_token_addr),
+            vec![AssetInfo::native("uluna"), AssetInfo::native("uatom")],
+            PairType::Xyk {},
+            checked.liquidity_manager.clone(),
+        );
+        assert_eq!(unchecked, checked);
+
+        // Validate against the pair info of the pair contract
+        let pair_info: astroport::asset::PairInfo = wasm
+            .query(&pair_addr, &astroport::pair::QueryMsg::Pair {})
+            .unwrap();
+        let deps_with_version = |version: Option<&str>| {
+            let pair_info = pair_info.clone();
+            let contract_info = version.map(|version| {
+                to_json_binary(&cw2::ContractVersion {
+                    contract: "astroport-pair".to_string(),
+                    version: version.to_string(),
+                })
+                .unwrap()
+            });
+            let mut deps = mock_dependencies();
+            deps.querier.update_wasm(move |query| match query {
+                WasmQuery::Smart { .. } => {
+                    SystemResult::Ok(ContractResult::Ok(to_json_binary(&pair_info).unwrap()))
+                }
+                WasmQuery::Raw { .. } => SystemResult::Ok(ContractResult::Ok(
+                    contract_info.clone().unwrap_or_default(),
+                )),
+                _ => unimplemented!(),
+            });
+            deps
+        };
+        let deps = deps_with_version(Some("1.3.2"));
+        unchecked.validate(deps.as_ref()).unwrap();
+        // Pair contracts without cw2 info are not checked for their version
+        unchecked
+            .validate(deps_with_version(None).as_ref())
+            .unwrap();
+
+        let mismatched = AstroportPool {
+            pair_type: PairType::Stable {},
+            ..unchecked.clone()
+        };
+        let err = mismatched.validate(deps.as_ref()).unwrap_err();
+        assert!(err.to_string().contains("does not match pair contract"));
+
+        // A legacy pair contract does not accept the messages of the default
+        // version
+        let err = unchecked
+            .validate(deps_with_version(Some("1.2.0")).as_ref())
+            .unwrap_err();
+        assert!(err.to_string().contains("expected pair version Legacy"));
+        AstroportPool {
+            pair_version: PairVersion::Legacy,
+            ..unchecked
+        }
+        .validate(deps_with_version(Some("1.2.0")).as_ref())
+        .unwrap();
+    }
+
+    #[test]
+    fn test_find_pools() {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (_accs, _lp_token_addr, pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let wasm = Wasm::new(&runner);
+
+        // The order of the assets does not matter
+        let pools: Vec<AstroportPool> = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::FindPools {
+                    asset_a: AssetInfo::native("uatom"),
+                    asset_b: AssetInfo::native("uluna"),
+                },
+            )
+            .unwrap();
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].pair_addr, Addr::unchecked(pair_addr));
+
+        // No pair of the assets
+        let pools: Vec<AstroportPool> = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::FindPools {
+                    asset_a: AssetInfo::native("uluna"),
+                    asset_b: AssetInfo::native("uosmo"),
+                },
+            )
+            .unwrap();
+        assert!(pools.is_empty());
+    }
+
+    #[test_case(PairType::Xyk {}; "pool_metrics: xyk")]
+    #[test_case(PairType::Stable {}; "pool_metrics: stableswap")]
+    #[test_case(PairType::Custom("concentrated".to_string()); "pool_metrics: concentrated")]
+    fn test_pool_metrics(pair_type: PairType) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (_accs, lp_token_addr, _pair_addr, contract_addr, asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                pair_type.clone(),
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+
+        let wasm = Wasm::new(&runner);
+
+        let metrics: PoolMetrics = wasm
+            .query(&contract_addr, &QueryMsg::PoolMetrics {})
+            .unwrap();
+        let lp_token_supply: cw20::TokenInfoResponse = wasm
+            .query(&lp_token_addr, &cw20::Cw20QueryMsg::TokenInfo {})
+            .unwrap();
+
+        assert_eq!(
+            metrics.lp_token,
+            AssetInfo::Cw20(Addr::unchecked(lp_token_addr))
+        );
+        assert_eq!(metrics.reserves, asset_list);
+        assert_eq!(metrics.total_shares, lp_token_supply.total_supply);
+        assert!(!metrics.swap_fee.is_zero());
+        assert!(metrics.exit_fee.is_zero());
+        let pool_type = match pair_type {
+            PairType::Xyk {} => PoolType::ConstantProduct,
+            PairType::Stable {} => PoolType::StableSwap,
+            PairType::Custom(_) => PoolType::Concentrated,
+        };
+        assert_eq!(metrics.pool_type, pool_type);
+    }
+
+    #[test_case(PairType::Xyk {}; "fee_share: xyk")]
+    #[test_case(PairType::Stable {}; "fee_share: stableswap")]
+    #[test_case(PairType::Custom("concentrated".to_string()); "fee_share: concentrated")]
+    fn test_query_fee_share(pool_type: PairType) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (_accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                pool_type,
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let wasm = Wasm::new(&runner);
+
+        // The params of pairs without fee sharing parse to no fee share
+        let fee_share: Option<FeeShareConfig> =
+            wasm.query(&contract_addr, &QueryMsg::FeeShare {}).unwrap();
+        assert_eq!(fee_share, None);
+    }
+
+    #[test_case(PairType::Xyk {}; "invariant: xyk")]
+    #[test_case(PairType::Stable {}; "invariant: stableswap")]
+    fn test_pool_invariant(pool_type: PairType) {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+            setup_pool_and_testing_contract(
+                &runner,
+                pool_type,
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+        let wasm = Wasm::new(&runner);
+        let query_invariant = || -> PoolInvariant {
+            wasm.query(&contract_addr, &QueryMsg::PoolInvariant {})
+                .unwrap()
+        };
+
+        let initial = query_invariant();
+        assert!(!initial.invariant.is_zero());
+
+        // Swaps back and forth leave their fees in the pool
+        let mut before = initial;
+        for (offer, ask) in [("uluna", "uatom"), ("uatom", "uluna")] {
+            let offer = coin(100_000, offer);
+            runner
+                .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                    &[ExecuteMsg::Swap {
+                        offer: offer.clone().into(),
+                        ask: AssetInfo::native(ask),
+                        min_out: Uint128::zero(),
+                    }
+                    .into_cosmos_msg(contract_addr.clone(), vec![offer])],
+                    admin,
+                )
+                .unwrap();
+            let after = query_invariant();
+            before.verify(&after, Decimal::zero()).unwrap();
+            before = after;
+        }
+        assert!(before.per_share() > initial.per_share());
+
+        // A decrease is reported
+        let decreased = PoolInvariant {
+            invariant: initial.invariant,
+            total_share: initial.total_share * Uint128::new(2),
+        };
+        assert!(initial.verify(&decreased, Decimal::zero()).is_err());
+        initial.verify(&decreased, Decimal::percent(50)).unwrap();
+    }
+
+    #[test]
+    fn test_route_and_provide_liquidity() {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, lp_token_addr, pair_addr, contract_addr, _asset_list, astroport_contracts) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+
+        // Intermediate pool of uosmo and uluna
+        let (intermediate_pair, _) = create_astroport_pair(
+            &runner,
+            &astroport_contracts.factory.address,
+            PairType::Xyk {},
+            [
+                AstroAssetInfo::NativeToken {
+                    denom: "uluna".to_string(),
+                },
+                AstroAssetInfo::NativeToken {
+                    denom: "uosmo".to_string(),
+                },
+            ],
+            None,
+            admin,
+            Some([Uint128::new(1_000_000), Uint128::new(1_000_000)]),
+        );
+
+        let assets: AssetList = vec![
+            Asset::native("uosmo", 10_000u128),
+            Asset::native("uatom", 5_000u128),
+        ]
+        .into();
+        let route_and_provide = |intermediate_pair: &str| {
+            runner.execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::RouteAndProvideLiquidity {
+                    assets: assets.clone(),
+                    intermediate_pair: intermediate_pair.to_string(),
+                    min_lp_out: Uint128::one(),
+                }
+                .into_cosmos_msg(
+                    contract_addr.clone(),
+                    vec![coin(5_000, "uatom"), coin(10_000, "uosmo")],
+                )],
+                admin,
+            )
+        };
+
+        // The target pool does not contain uosmo
+        let err = route_and_provide(&pair_addr).unwrap_err();
+        assert!(err.to_string().contains("Asset is not in pool"), "{err}");
+
+        let res = route_and_provide(&intermediate_pair).unwrap();
+        let response = SubMsgResponse {
+            events: res.events,
+            data: None,
+        };
+        let event =
+            find_event(&response, "wasm-apollo/cw-dex/route_and_provide_liquidity").unwrap();
+        let routed: String = parse_attribute_value(event, "routed").unwrap();
+        assert_eq!(routed, Asset::native("uosmo", 10_000u128).to_string());
+
+        let lp_balance =
+            cw20_balance_query(&runner, lp_token_addr.clone(), contract_addr.clone()).unwrap();
+        assert!(!lp_balance.is_zero());
+        // All uosmo was routed. The xyk pair refunds the uluna in excess of the
+        // pool ratio.
+        let uosmo_balance =
+            query_asset_balance(&runner, &AssetInfo::native("uosmo"), &contract_addr);
+        assert!(uosmo_balance.is_zero());
+    }
+
+    #[test]
+    fn test_swap_path() {
+        let owned_runner = get_test_runner();
+        let runner = owned_runner.as_ref();
+        let (accs, _lp_token_addr, _pair_addr, contract_addr, _asset_list, astroport_contracts) =
+            setup_pool_and_testing_contract(
+                &runner,
+                PairType::Xyk {},
+                vec![("uluna", 1_000_000), ("uatom", 1_000_000)],
+            )
+            .unwrap();
+        let admin = &accs[0];
+        let wasm = Wasm::new(&runner);
+        let router = astroport_contracts.router.address.clone();
+
+        // Second hop through a pool of uluna and uosmo
+        create_astroport_pair(
+            &runner,
+            &astroport_contracts.factory.address,
+            PairType::Xyk {},
+            [
+                AstroAssetInfo::NativeToken {
+                    denom: "uluna".to_string(),
+                },
+                AstroAssetInfo::NativeToken {
+                    denom: "uosmo".to_string(),
+                },
+            ],
+            None,
+            admin,
+            Some([Uint128::new(1_000_000), Uint128::new(1_000_000)]),
+        );
+
+        let offer = Asset::native("uatom", 10_000u128);
+        let path = vec![
+            AssetInfo::native("uatom"),
+            AssetInfo::native("uluna"),
+            AssetInfo::native("uosmo"),
+        ];
+        let expected_out: Uint128 = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::SimulateSwapPath {
+                    router: router.clone(),
+                    offer: offer.clone(),
+                    path: path.clone(),
+                },
+            )
+            .unwrap();
+        assert!(!expected_out.is_zero());
+
+        let swap_path = |min_out: Uint128| {
+            runner.execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[AstroportExecuteMsg::SwapPath {
+                    router: router.clone(),
+                    offer: offer.clone(),
+                    path: path.clone(),
+                    min_out,
+                }
+                .into_cosmos_msg(contract_addr.clone(), vec![coin(10_000, "uatom")])],
+                admin,
+            )
+        };
+
+        // The router checks the minimum return of the whole path
+        swap_path(expected_out + Uint128::one()).unwrap_err();
+
+        swap_path(expected_out).unwrap();
+        let uosmo_balance =
+            query_asset_balance(&runner, &AssetInfo::native("uosmo"), &contract_addr);
+        assert_eq!(uosmo_balance, expected_out);
+        let uluna_balance =
+            query_asset_balance(&runner, &AssetInfo::native("uluna"), &contract_addr);
+        assert!(uluna_balance.is_zero());
     }
 }
@@ -0,0 +1,342 @@
+//! Configuration of pools and staking for each of the supported dexes
+
+use std::fmt;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, Binary, Deps, StdError};
+use cw_dex::CwDexError;
+
+#[cfg(feature = "astroport")]
+use cw_dex_astroport::{AstroportPool, AstroportStaking};
+#[cfg(feature = "osmosis")]
+use {
+    apollo_cw_asset::AssetInfo,
+    cw_dex_osmosis::{OsmosisPool, OsmosisStaking},
+};
+
+use crate::{DexPool, DexStaking};
+
+/// The decentralized exchanges that pools and staking can be configured for
+#[cw_serde]
+#[derive(Copy)]
+pub enum Dex {
+    /// Osmosis, via crate `cw-dex-osmosis`
+    Osmosis,
+    /// Astroport, via crate `cw-dex-astroport`
+    Astroport,
+}
+
+impl fmt::Display for Dex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dex::Osmosis => write!(f, "osmosis"),
+            Dex::Astroport => write!(f, "astroport"),
+        }
+    }
+}
+
+impl Dex {
+    /// Constructs a pool of the dex from `config`, the JSON encoded pool
+    /// config of the dex, e.g. an [`OsmosisPoolConfig`] for [`Dex::Osmosis`].
+    /// See [`PoolConfig::build`].
+    pub fn pool_from_config(&self, deps: Deps, config: &Binary) -> Result<DexPool, CwDexError> {
+        let config = match self {
+            Dex::Osmosis => PoolConfig::Osmosis(from_json(config)?),
+            Dex::Astroport => PoolConfig::Astroport(from_json(config)?),
+        };
+        config.build(deps)
+    }
+
+    /// Constructs a staking of the dex from `config`, the JSON encoded staking
+    /// config of the dex, e.g. an [`OsmosisStakingConfig`] for
+    /// [`Dex::Osmosis`]. See [`StakingConfig::build`].
+    pub fn staking_from_config(
+        &self,
+        deps: Deps,
+        config: &Binary,
+    ) -> Result<DexStaking, CwDexError> {
+        let config = match self {
+            Dex::Osmosis => StakingConfig::Osmosis(from_json(config)?),
+            Dex::Astroport => StakingConfig::Astroport(from_json(config)?),
+        };
+        config.build(deps)
+    }
+}
+
+/// Configuration of an Osmosis pool
+#[cw_serde]
+pub struct OsmosisPoolConfig {
+    /// The id of the pool
+    pub pool_id: u64,
+}
+
+/// Configuration of an Astroport pool
+#[cw_serde]
+pub struct AstroportPoolConfig {
+    /// The address of the pair contract
+    pub pair_addr: String,
+    /// The address of the Astroport liquidity manager contract
+    pub liquidity_manager: String,
+}
+
+/// Configuration of a pool on any of the supported dexes
+#[cw_serde]
+pub enum PoolConfig {
+    /// An Osmosis pool
+    Osmosis(OsmosisPoolConfig),
+    /// An Astroport pool
+    Astroport(AstroportPoolConfig),
+}
+
+impl PoolConfig {
+    /// Returns the dex of the pool
+    pub fn dex(&self) -> Dex {
+        match self {
+            PoolConfig::Osmosis(_) => Dex::Osmosis,
+            PoolConfig::Astroport(_) => Dex::Astroport,
+        }
+    }
+
+    /// Constructs the configured pool, querying that it exists. Returns an
+    /// error if the support for the dex is not enabled.
+    #[allow(unreachable_patterns)]
+    pub fn build(&self, deps: Deps) -> Result<DexPool, CwDexError> {
+        match self {
+            #[cfg(feature = "osmosis")]
+            PoolConfig::Osmosis(config) => {
+                Ok(DexPool::Osmosis(OsmosisPool::new(config.pool_id, deps)?))
+            }
+            #[cfg(feature = "astroport")]
+            PoolConfig::Astroport(config) => Ok(DexPool::Astroport(AstroportPool::new(
+                deps,
+                deps.api.addr_validate(&config.pair_addr)?,
+                deps.api.addr_validate(&config.liquidity_manager)?,
+            )?)),
+            config => Err(not_enabled(config.dex())),
+        }
+    }
+}
+
+/// Configuration of staking in the Osmosis lockup module
+#[cw_serde]
+pub struct OsmosisStakingConfig {
+    /// The denom of the LP token to stake, e.g. `gamm/pool/1`
+    pub lp_token_denom: String,
+    /// The lockup duration in seconds. Allowed values are 1 day, 1 week or 2
+    /// weeks.
+    pub lockup_duration: u64,
+    /// The id of an existing lock to stake to
+    pub lock_id: Option<u64>,
+}
+
+/// Configuration of staking in the Astroport incentives contract
+#[cw_serde]
+pub struct AstroportStakingConfig {
+    /// The address of the LP token contract
+    pub lp_token_addr: String,
+    /// The address of the Astroport incentives contract
+    pub incentives: String,
+    /// See [`cw_dex_astroport::AstroportStaking::strict_rewards`]
+    #[serde(default)]
+    pub strict_rewards: bool,
+    /// See [`cw_dex_astroport::AstroportStaking::cw20_adapter`]
+    #[serde(default)]
+    pub cw20_adapter: Option<String>,
+}
+
+/// Configuration of staking on any of the supported dexes
+#[cw_serde]
+pub enum StakingConfig {
+    /// Staking in the Osmosis lockup module
+    Osmosis(OsmosisStakingConfig),
+    /// Staking in the Astroport incentives contract
+    Astroport(AstroportStakingConfig),
+}
+
+impl StakingConfig {
+    /// Returns the dex of the staking
+    pub fn dex(&self) -> Dex {
+        match self {
+            StakingConfig::Osmosis(_) => Dex::Osmosis,
+            StakingConfig::Astroport(_) => Dex::Astroport,
+        }
+    }
+
+    /// Constructs the configured staking, querying that the LP token is the
+    /// LP token of a pool. Returns an error if the support for the dex is not
+    /// enabled.
+    #[allow(unreachable_patterns)]
+    pub fn build(&self, deps: Deps) -> Result<DexStaking, CwDexError> {
+        match self {
+            #[cfg(feature = "osmosis")]
+            StakingConfig::Osmosis(config) => {
+                let staking = OsmosisStaking::new(
+                    config.lockup_duration,
+                    config.lock_id,
+                    config.lp_token_denom.clone(),
+                )?;
+                OsmosisPool::get_pool_for_lp_token(
+                    deps,
+                    &AssetInfo::native(&config.lp_token_denom),
+                )?;
+                Ok(DexStaking::Osmosis(staking))
+            }
+            #[cfg(feature = "astroport")]
+            StakingConfig::Astroport(config) => {
+                let staking = AstroportStaking::new_checked(
+                    deps,
+                    deps.api.addr_validate(&config.lp_token_addr)?,
+                    deps.api.addr_validate(&config.incentives)?,
+                )?;
+                Ok(DexStaking::Astroport(AstroportStaking {
+                    strict_rewards: config.strict_rewards,
+                    cw20_adapter: config
+                        .cw20_adapter
+                        .as_ref()
+                        .map(|adapter| deps.api.addr_validate(adapter))
+                        .transpose()?,
+                    ..staking
+                }))
+            }
+            config => Err(not_enabled(config.dex())),
+        }
+    }
+}
+
+pub(crate) fn not_enabled(dex: Dex) -> CwDexError {
+    StdError::generic_err(format!("Support for {dex} is not enabled")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::to_json_binary;
+
+    use super::*;
+
+    #[test]
+    fn test_config_json() {
+        let config: PoolConfig = from_json(br#"{"osmosis":{"pool_id":1}}"#).unwrap();
+        assert_eq!(
+            config,
+            PoolConfig::Osmosis(OsmosisPoolConfig { pool_id: 1 })
+        );
+        assert_eq!(config.dex(), Dex::Osmosis);
+
+        let config: StakingConfig =
+            from_json(br#"{"astroport":{"lp_token_addr":"lp_token","incentives":"incentives"}}"#)
+                .unwrap();
+        assert_eq!(
+            config,
+            StakingConfig::Astroport(AstroportStakingConfig {
+                lp_token_addr: "lp_token".to_string(),
+                incentives: "incentives".to_string(),
+                strict_rewards: false,
+                cw20_adapter: None,
+            })
+        );
+        assert_eq!(config.dex(), Dex::Astroport);
+        assert_eq!(Dex::Astroport.to_string(), "astroport");
+    }
+
+    #[test]
+    fn test_pool_from_invalid_config() {
+        let deps = mock_dependencies();
+        let config = to_json_binary(&OsmosisPoolConfig { pool_id: 1 }).unwrap();
+
+        let err = Dex::Astroport
+            .pool_from_config(deps.as_ref(), &config)
+            .unwrap_err();
+        assert!(matches!(err, CwDexError::Std(StdError::ParseErr { .. })));
+    }
+
+    #[cfg(not(feature = "astroport"))]
+    #[test]
+    fn test_dex_not_enabled() {
+        let deps = mock_dependencies();
+        let config = PoolConfig::Astroport(AstroportPoolConfig {
+            pair_addr: "pair".to_string(),
+            liquidity_manager: "liquidity_manager".to_string(),
+        });
+
+        let err = config.build(deps.as_ref()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Support for astroport is not enabled"));
+    }
+
+    #[cfg(feature = "osmosis")]
+    #[test]
+    fn test_osmosis_staking_invalid_lockup_duration() {
+        let deps = mock_dependencies();
+        let config = StakingConfig::Osmosis(OsmosisStakingConfig {
+            lp_token_denom: "gamm/pool/1".to_string(),
+            lockup_duration: 1,
+            lock_id: None,
+        });
+
+        let err = config.build(deps.as_ref()).unwrap_err();
+        assert!(err.to_string().contains("invalid lockup duration"));
+    }
+
+    #[cfg(feature = "astroport")]
+    #[test]
+    fn test_astroport_staking_from_config() {
+        use cosmwasm_std::{Addr, Binary, ContractResult, SystemResult, WasmQuery};
+
+        let pair_info = Binary::from(
+            br#"{"asset_infos":[{"native_token":{"denom":"uatom"}},{"native_token":{"denom":"uluna"}}],"contract_addr":"pair","liquidity_token":"lp_token","pair_type":{"xyk":{}}}"#,
+        );
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == "lp_token" => {
+                SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&cw20::MinterResponse {
+                        minter: "pair".to_string(),
+                        cap: None,
+                    })
+                    .unwrap(),
+                ))
+            }
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == "incentives" => {
+                SystemResult::Ok(ContractResult::Ok(Binary::from(
+                    br#"{"owner":"owner","factory":"factory","astro_token":{"native_token":{"denom":"astro"}},"astro_per_second":"0","total_alloc_points":"0","vesting_contract":"vesting"}"#,
+                )))
+            }
+            WasmQuery::Smart { contract_addr, .. }
+                if contract_addr == "pair" || contract_addr == "factory" =>
+            {
+                SystemResult::Ok(ContractResult::Ok(pair_info.clone()))
+            }
+            _ => SystemResult::Ok(ContractResult::Err("not a cw20".to_string())),
+        });
+        let config = AstroportStakingConfig {
+            lp_token_addr: "lp_token".to_string(),
+            incentives: "incentives".to_string(),
+            strict_rewards: true,
+            cw20_adapter: None,
+        };
+
+        let staking = Dex::Astroport
+            .staking_from_config(deps.as_ref(), &to_json_binary(&config).unwrap())
+            .unwrap();
+        assert_eq!(
+            staking,
+            DexStaking::Astroport(AstroportStaking {
+                lp_token_addr: Addr::unchecked("lp_token"),
+                incentives: Addr::unchecked("incentives"),
+                strict_rewards: true,
+                cw20_adapter: None,
+                chain_profile: Default::default(),
+            })
+        );
+
+        let config = AstroportStakingConfig {
+            lp_token_addr: "not_a_token".to_string(),
+            ..config
+        };
+        Dex::Astroport
+            .staking_from_config(deps.as_ref(), &to_json_binary(&config).unwrap())
+            .unwrap_err();
+    }
+}
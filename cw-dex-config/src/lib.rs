@@ -0,0 +1,23 @@
+//! Constructs cw-dex pools and staking implementations from configuration.
+//!
+//! A [`PoolConfig`] or [`StakingConfig`] declares a pool or staking of one of
+//! the supported [`Dex`]es, e.g. in a deployment config file or an instantiate
+//! message, and is turned into a [`DexPool`] or [`DexStaking`], which
+//! implement the cw-dex traits, after validating it with queries. Support for
+//! each dex is enabled with the crate feature of the same name.
+
+// Without any dex enabled the pool and staking enums are empty
+#![cfg_attr(
+    not(any(feature = "osmosis", feature = "astroport")),
+    allow(unused_variables)
+)]
+
+mod config;
+mod pool;
+mod resolver;
+mod staking;
+
+pub use config::*;
+pub use pool::*;
+pub use resolver::*;
+pub use staking::*;
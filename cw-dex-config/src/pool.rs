@@ -0,0 +1,155 @@
+//! The [`DexPool`] enum of the pools of all enabled dexes
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Deps, Env, Response, StdResult, Uint128};
+use cw_dex::traits::Pool;
+use cw_dex::CwDexError;
+
+#[cfg(feature = "astroport")]
+use cw_dex_astroport::AstroportPool;
+#[cfg(feature = "osmosis")]
+use cw_dex_osmosis::OsmosisPool;
+
+use crate::Dex;
+
+/// A pool of any of the enabled dexes, e.g. as constructed by
+/// [`crate::PoolConfig::build`]. Implements [`Pool`] by delegating to the
+/// pool of the dex.
+#[cw_serde]
+pub enum DexPool {
+    /// An Osmosis pool
+    #[cfg(feature = "osmosis")]
+    Osmosis(OsmosisPool),
+    /// An Astroport pool
+    #[cfg(feature = "astroport")]
+    Astroport(AstroportPool),
+}
+
+/// Calls `$e` with `$pool` bound to the pool of the dex
+macro_rules! delegate {
+    ($self:ident, $pool:ident => $e:expr) => {
+        match *$self {
+            #[cfg(feature = "osmosis")]
+            DexPool::Osmosis(ref $pool) => $e,
+            #[cfg(feature = "astroport")]
+            DexPool::Astroport(ref $pool) => $e,
+        }
+    };
+}
+
+impl DexPool {
+    /// Returns the dex of the pool
+    pub fn dex(&self) -> Dex {
+        match *self {
+            #[cfg(feature = "osmosis")]
+            DexPool::Osmosis(_) => Dex::Osmosis,
+            #[cfg(feature = "astroport")]
+            DexPool::Astroport(_) => Dex::Astroport,
+        }
+    }
+
+    /// Returns the Osmosis pool, e.g. to get its pool ID, or `None` if the
+    /// pool is of another dex
+    #[cfg(feature = "osmosis")]
+    #[allow(unreachable_patterns)]
+    pub fn as_osmosis(&self) -> Option<&OsmosisPool> {
+        match self {
+            DexPool::Osmosis(pool) => Some(pool),
+            _ => None,
+        }
+    }
+
+    /// Returns the Astroport pool, e.g. to get its pair address, or `None` if
+    /// the pool is of another dex
+    #[cfg(feature = "astroport")]
+    #[allow(unreachable_patterns)]
+    pub fn as_astroport(&self) -> Option<&AstroportPool> {
+        match self {
+            DexPool::Astroport(pool) => Some(pool),
+            _ => None,
+        }
+    }
+}
+
+impl Pool for DexPool {
+    fn provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        delegate!(self, pool => pool.provide_liquidity(deps, env, assets, min_out))
+    }
+
+    fn lp_token(&self) -> AssetInfo {
+        delegate!(self, pool => pool.lp_token())
+    }
+
+    fn withdraw_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+    ) -> Result<Response, CwDexError> {
+        delegate!(self, pool => pool.withdraw_liquidity(deps, env, lp_token, min_out))
+    }
+
+    fn withdraw_liquidity_to(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+        recipient: Option<Addr>,
+    ) -> Result<Response, CwDexError> {
+        delegate!(self, pool => pool.withdraw_liquidity_to(deps, env, lp_token, min_out, recipient))
+    }
+
+    fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        delegate!(self, pool => pool.swap(deps, env, offer_asset, ask_asset_info, min_out))
+    }
+
+    fn get_pool_liquidity(&self, deps: Deps) -> Result<AssetList, CwDexError> {
+        delegate!(self, pool => pool.get_pool_liquidity(deps))
+    }
+
+    fn simulate_provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+    ) -> Result<Asset, CwDexError> {
+        delegate!(self, pool => pool.simulate_provide_liquidity(deps, env, assets))
+    }
+
+    fn simulate_withdraw_liquidity(
+        &self,
+        deps: Deps,
+        lp_token: &Asset,
+    ) -> Result<AssetList, CwDexError> {
+        delegate!(self, pool => pool.simulate_withdraw_liquidity(deps, lp_token))
+    }
+
+    fn simulate_swap(
+        &self,
+        deps: Deps,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+    ) -> StdResult<Uint128> {
+        delegate!(self, pool => pool.simulate_swap(deps, offer_asset, ask_asset_info))
+    }
+
+    fn pool_assets(&self, deps: Deps) -> StdResult<Vec<AssetInfo>> {
+        delegate!(self, pool => pool.pool_assets(deps))
+    }
+}
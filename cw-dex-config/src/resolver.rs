@@ -0,0 +1,219 @@
+//! Resolving the pool of an LP token on any of the enabled dexes
+
+use apollo_cw_asset::AssetInfo;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Deps;
+use cw_dex::CwDexError;
+
+#[cfg(feature = "osmosis")]
+use cw_dex_osmosis::OsmosisPool;
+#[cfg(feature = "astroport")]
+use {cosmwasm_std::Addr, cw_dex_astroport::AstroportPool};
+
+use crate::DexPool;
+#[cfg(not(all(feature = "osmosis", feature = "astroport")))]
+use crate::{config::not_enabled, Dex};
+
+/// A function returning the pool of an LP token, or `None` if the token is not
+/// an LP token it knows about
+pub type LpTokenResolverFn<'a> =
+    Box<dyn Fn(Deps, &AssetInfo) -> Result<Option<DexPool>, CwDexError> + 'a>;
+
+/// Resolves the pool of an LP token by trying a list of resolvers in order,
+/// e.g. the Osmosis gamm denoms, the pairs of an Astroport factory and any
+/// custom resolvers for other pools.
+///
+/// ```ignore
+/// let pool = LpTokenResolver::new()
+///     .with_osmosis()
+///     .with_astroport_factory(factory, liquidity_manager)
+///     .resolve(deps, &lp_token)?;
+/// ```
+#[derive(Default)]
+pub struct LpTokenResolver<'a> {
+    resolvers: Vec<LpTokenResolverFn<'a>>,
+}
+
+impl<'a> LpTokenResolver<'a> {
+    /// Returns a resolver without any resolvers, which rejects all tokens
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a custom resolver. It is only called if the resolvers added before
+    /// it returned `None`.
+    pub fn with_resolver(
+        mut self,
+        resolver: impl Fn(Deps, &AssetInfo) -> Result<Option<DexPool>, CwDexError> + 'a,
+    ) -> Self {
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+
+    /// Adds resolving Osmosis `gamm/pool/{id}` LP tokens, see
+    /// [`OsmosisPool::get_pool_for_lp_token`]
+    #[cfg(feature = "osmosis")]
+    pub fn with_osmosis(self) -> Self {
+        self.with_resolver(|deps, lp_token| {
+            not_lp_token_to_none(
+                OsmosisPool::get_pool_for_lp_token(deps, lp_token).map(DexPool::Osmosis),
+            )
+        })
+    }
+
+    /// Adds resolving the LP tokens of the pairs registered with the Astroport
+    /// `factory`, see [`AstroportPool::get_pool_for_lp_token_from_factory`]
+    #[cfg(feature = "astroport")]
+    pub fn with_astroport_factory(self, factory: Addr, liquidity_manager: Addr) -> Self {
+        self.with_resolver(move |deps, lp_token| {
+            not_lp_token_to_none(
+                AstroportPool::get_pool_for_lp_token_from_factory(
+                    deps,
+                    lp_token,
+                    &factory,
+                    liquidity_manager.clone(),
+                )
+                .map(DexPool::Astroport),
+            )
+        })
+    }
+
+    /// Returns the pool of the first resolver that knows `lp_token`.
+    ///
+    /// Returns [`CwDexError::NotLpToken`] if none of them does, and the error
+    /// of a resolver that fails for another reason, e.g. a failed query of a
+    /// pool that should exist.
+    pub fn resolve(&self, deps: Deps, lp_token: &AssetInfo) -> Result<DexPool, CwDexError> {
+        for resolver in &self.resolvers {
+            if let Some(pool) = resolver(deps, lp_token)? {
+                return Ok(pool);
+            }
+        }
+        Err(CwDexError::NotLpToken {})
+    }
+}
+
+/// Treats [`CwDexError::NotLpToken`] as an unknown token, so that the next
+/// resolver is tried
+#[cfg(any(feature = "osmosis", feature = "astroport"))]
+fn not_lp_token_to_none(
+    result: Result<DexPool, CwDexError>,
+) -> Result<Option<DexPool>, CwDexError> {
+    match result {
+        Ok(pool) => Ok(Some(pool)),
+        Err(CwDexError::NotLpToken {}) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+impl DexPool {
+    /// Returns the pool of `lp_token`, as resolved by `resolver`. See
+    /// [`LpTokenResolver::resolve`].
+    pub fn get_pool_for_lp_token(
+        deps: Deps,
+        lp_token: &AssetInfo,
+        resolver: &LpTokenResolver,
+    ) -> Result<Self, CwDexError> {
+        resolver.resolve(deps, lp_token)
+    }
+}
+
+/// Configuration of resolving Astroport LP tokens with a factory
+#[cw_serde]
+pub struct AstroportResolverConfig {
+    /// The address of the Astroport factory contract
+    pub factory: String,
+    /// The address of the Astroport liquidity manager contract, used for the
+    /// resolved pools
+    pub liquidity_manager: String,
+}
+
+/// Configuration of an [`LpTokenResolver`] of the built-in resolvers, e.g. in
+/// an instantiate message. Custom resolvers can be added to the built
+/// resolver with [`LpTokenResolver::with_resolver`].
+#[cw_serde]
+#[derive(Default)]
+pub struct LpTokenResolverConfig {
+    /// Whether to resolve Osmosis LP tokens
+    #[serde(default)]
+    pub osmosis: bool,
+    /// The Astroport factory to resolve LP tokens with, if any
+    #[serde(default)]
+    pub astroport: Option<AstroportResolverConfig>,
+}
+
+impl LpTokenResolverConfig {
+    /// Constructs the configured resolver, trying Osmosis before Astroport.
+    /// Returns an error if the support for a configured dex is not enabled.
+    #[allow(unused_variables, unused_mut)]
+    pub fn build(&self, deps: Deps) -> Result<LpTokenResolver<'static>, CwDexError> {
+        let mut resolver = LpTokenResolver::new();
+        if self.osmosis {
+            #[cfg(feature = "osmosis")]
+            {
+                resolver = resolver.with_osmosis();
+            }
+            #[cfg(not(feature = "osmosis"))]
+            return Err(not_enabled(Dex::Osmosis));
+        }
+        if let Some(config) = &self.astroport {
+            #[cfg(feature = "astroport")]
+            {
+                resolver = resolver.with_astroport_factory(
+                    deps.api.addr_validate(&config.factory)?,
+                    deps.api.addr_validate(&config.liquidity_manager)?,
+                );
+            }
+            #[cfg(not(feature = "astroport"))]
+            return Err(not_enabled(Dex::Astroport));
+        }
+        Ok(resolver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::{from_json, StdError};
+
+    use super::*;
+
+    #[test]
+    fn test_resolver_order() {
+        let deps = mock_dependencies();
+        let resolver = LpTokenResolver::new()
+            .with_resolver(|_, lp_token| match lp_token {
+                AssetInfo::Native(denom) if denom == "known" => {
+                    Err(StdError::generic_err("first").into())
+                }
+                _ => Ok(None),
+            })
+            .with_resolver(|_, _| Err(StdError::generic_err("second").into()));
+
+        let err = resolver
+            .resolve(deps.as_ref(), &AssetInfo::native("known"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Generic error: first");
+        let err = resolver
+            .resolve(deps.as_ref(), &AssetInfo::native("other"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Generic error: second");
+
+        let err = LpTokenResolver::new()
+            .resolve(deps.as_ref(), &AssetInfo::native("known"))
+            .unwrap_err();
+        assert_eq!(err, CwDexError::NotLpToken {});
+    }
+
+    #[test]
+    fn test_resolver_config_json() {
+        let config: LpTokenResolverConfig = from_json(br#"{"osmosis":true}"#).unwrap();
+        assert_eq!(
+            config,
+            LpTokenResolverConfig {
+                osmosis: true,
+                astroport: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! The [`DexStaking`] enum of the staking implementations of all enabled dexes
+
+use apollo_cw_asset::{AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Deps, Env, QuerierWrapper, Response, StdError, Uint128};
+use cw_dex::traits::{
+    MigratePosition, QueryPosition, Rewards, SimulateStaking, Stake, Staking, UnlockingPosition,
+    Unstake,
+};
+use cw_dex::CwDexError;
+use cw_utils::Duration as CwDuration;
+
+#[cfg(feature = "astroport")]
+use cw_dex_astroport::AstroportStaking;
+#[cfg(feature = "osmosis")]
+use cw_dex_osmosis::OsmosisStaking;
+
+use crate::Dex;
+
+/// A staking implementation of any of the enabled dexes, e.g. as constructed
+/// by [`crate::StakingConfig::build`]. Implements the staking traits by
+/// delegating to the staking of the dex.
+#[cw_serde]
+pub enum DexStaking {
+    /// Staking in the Osmosis lockup module
+    #[cfg(feature = "osmosis")]
+    Osmosis(OsmosisStaking),
+    /// Staking in the Astroport incentives contract
+    #[cfg(feature = "astroport")]
+    Astroport(AstroportStaking),
+}
+
+/// Calls `$e` with `$staking` bound to the staking of the dex
+macro_rules! delegate {
+    ($self:ident, $staking:ident => $e:expr) => {
+        match *$self {
+            #[cfg(feature = "osmosis")]
+            DexStaking::Osmosis(ref $staking) => $e,
+            #[cfg(feature = "astroport")]
+            DexStaking::Astroport(ref $staking) => $e,
+        }
+    };
+}
+
+impl DexStaking {
+    /// Returns the dex of the staking
+    pub fn dex(&self) -> Dex {
+        match *self {
+            #[cfg(feature = "osmosis")]
+            DexStaking::Osmosis(_) => Dex::Osmosis,
+            #[cfg(feature = "astroport")]
+            DexStaking::Astroport(_) => Dex::Astroport,
+        }
+    }
+}
+
+impl Rewards for DexStaking {
+    fn claim_rewards(&self, deps: Deps, env: &Env) -> Result<Response, CwDexError> {
+        delegate!(self, staking => staking.claim_rewards(deps, env))
+    }
+
+    fn query_pending_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<AssetList, CwDexError> {
+        delegate!(self, staking => staking.query_pending_rewards(querier, user))
+    }
+}
+
+impl Stake for DexStaking {
+    fn stake(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        delegate!(self, staking => staking.stake(deps, env, amount))
+    }
+
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        delegate!(self, staking => staking.stake_asset())
+    }
+}
+
+impl SimulateStaking for DexStaking {
+    fn query_staked_amount(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<Uint128, CwDexError> {
+        delegate!(self, staking => staking.query_staked_amount(querier, user))
+    }
+
+    fn pays_rewards_on_stake_change(&self) -> bool {
+        delegate!(self, staking => staking.pays_rewards_on_stake_change())
+    }
+}
+
+impl QueryPosition for DexStaking {
+    fn query_unlocking(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<Vec<UnlockingPosition>, CwDexError> {
+        delegate!(self, staking => staking.query_unlocking(querier, user))
+    }
+
+    fn query_lockup_duration(&self, deps: Deps) -> Result<Option<CwDuration>, CwDexError> {
+        delegate!(self, staking => staking.query_lockup_duration(deps))
+    }
+}
+
+impl Unstake for DexStaking {
+    /// Unstakes `amount` of LP tokens. Osmosis staking is locked and returns
+    /// [`CwDexError::UnstakingDurationNotSupported`], use
+    /// [`cw_dex::traits::Unlock`] on the [`OsmosisStaking`] instead.
+    #[allow(unused_variables)]
+    fn unstake(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        match *self {
+            #[cfg(feature = "osmosis")]
+            DexStaking::Osmosis(_) => Err(CwDexError::UnstakingDurationNotSupported {}),
+            #[cfg(feature = "astroport")]
+            DexStaking::Astroport(ref staking) => staking.unstake(deps, env, amount),
+        }
+    }
+}
+
+impl Staking for DexStaking {}
+
+impl MigratePosition for DexStaking {
+    /// Migrates the position within the staking of the same dex. Returns an
+    /// error if `to` is the staking of another dex.
+    #[allow(unused_variables)]
+    fn migrate_position(
+        &self,
+        deps: Deps,
+        env: &Env,
+        amount: Uint128,
+        to: &Self,
+    ) -> Result<Response, CwDexError> {
+        match (self, to) {
+            #[cfg(feature = "osmosis")]
+            (DexStaking::Osmosis(from), DexStaking::Osmosis(to)) => {
+                from.migrate_position(deps, env, amount, to)
+            }
+            #[cfg(feature = "astroport")]
+            (DexStaking::Astroport(from), DexStaking::Astroport(to)) => {
+                from.migrate_position(deps, env, amount, to)
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(StdError::generic_err(format!(
+                "Can not migrate position from {} to {}",
+                self.dex(),
+                to.dex()
+            ))
+            .into()),
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Runs the same execute and query flows of the generic test contract against
+//! pools of every supported dex, checking that contracts written against the
+//! cw-dex traits behave the same on each of them.
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use astroport::factory::PairType;
+use cosmwasm_std::{coin, Addr, Coin, Uint128};
+use cw_dex::traits::{Pool, StakingPositionInfo};
+use cw_dex_config::{
+    AstroportPoolConfig, AstroportResolverConfig, AstroportStakingConfig, DexPool,
+    LpTokenResolverConfig, OsmosisPoolConfig, OsmosisStakingConfig, PoolConfig, StakingConfig,
+};
+use cw_dex_test_contract::msg::{ExecuteMsg, GenericTestContractInstantiateMsg, QueryMsg};
+use cw_dex_test_helpers::astroport::setup_pool_and_test_contract;
+use cw_dex_test_helpers::osmosis_multi_test::OsmosisPoolsModule;
+use cw_dex_test_helpers::query_asset_balance;
+use cw_it::cw_multi_test::ContractWrapper;
+use cw_it::helpers::upload_wasm_file;
+use cw_it::multi_test::MultiTestRunner;
+use cw_it::osmosis_std::types::osmosis::gamm::poolmodels::balancer::v1beta1::{
+    MsgCreateBalancerPool, MsgCreateBalancerPoolResponse,
+};
+use cw_it::osmosis_std::types::osmosis::gamm::v1beta1::{PoolAsset, PoolParams};
+use cw_it::test_tube::{Account, Module, Runner, SigningAccount, Wasm};
+use cw_it::traits::CwItRunner;
+use cw_it::{ContractType, OwnedTestRunner, TestRunner};
+use test_case::test_case;
+
+/// The dexes the generic test contract is tested against. Osmosis runs on
+/// multi-test with the Stargate shim of `cw-dex-test-helpers` emulating pools.
+#[derive(Clone, Copy, Debug)]
+enum Venue {
+    Osmosis,
+    Astroport,
+}
+
+impl Venue {
+    fn runner(self) -> OwnedTestRunner<'static> {
+        match self {
+            Venue::Osmosis => OwnedTestRunner::MultiTest(OsmosisPoolsModule::runner("osmo")),
+            Venue::Astroport => OwnedTestRunner::MultiTest(MultiTestRunner::new("osmo")),
+        }
+    }
+
+    /// Creates a uatom/uluna pool with 1_000_000 of each asset and
+    /// instantiates the generic test contract for it. Returns the admin
+    /// account, the address of the contract and the LP token of the pool.
+    fn setup<'a>(self, runner: &'a TestRunner<'a>) -> (SigningAccount, String, AssetInfo) {
+        match self {
+            Venue::Osmosis => {
+                let admin = runner
+                    .init_account(&[coin(u128::MAX, "uatom"), coin(u128::MAX, "uluna")])
+                    .unwrap();
+                let pool_id = create_osmosis_pool(
+                    runner,
+                    &admin,
+                    &[coin(1_000_000, "uatom"), coin(1_000_000, "uluna")],
+                );
+                let contract_addr = instantiate_generic_contract(
+                    runner,
+                    &admin,
+                    GenericTestContractInstantiateMsg {
+                        pool: PoolConfig::Osmosis(OsmosisPoolConfig { pool_id }),
+                        staking: Some(StakingConfig::Osmosis(OsmosisStakingConfig {
+                            lp_token_denom: format!("gamm/pool/{pool_id}"),
+                            lockup_duration: 86400,
+                            lock_id: None,
+                        })),
+                        resolver: Some(LpTokenResolverConfig {
+                            osmosis: true,
+                            astroport: None,
+                        }),
+                    },
+                );
+                let lp_token = AssetInfo::native(format!("gamm/pool/{pool_id}"));
+                (admin, contract_addr, lp_token)
+            }
+            Venue::Astroport => {
+                let (mut accs, lp_token_addr, pair_addr, _, _, astroport_contracts) =
+                    setup_pool_and_test_contract(
+                        runner,
+                        PairType::Xyk {},
+                        vec![("uatom", 1_000_000), ("uluna", 1_000_000)],
+                        2,
+                        "",
+                    )
+                    .unwrap();
+                let admin = accs.swap_remove(0);
+                let contract_addr = instantiate_generic_contract(
+                    runner,
+                    &admin,
+                    GenericTestContractInstantiateMsg {
+                        pool: PoolConfig::Astroport(AstroportPoolConfig {
+                            pair_addr,
+                            liquidity_manager: astroport_contracts
+                                .liquidity_manager
+                                .address
+                                .clone(),
+                        }),
+                        staking: Some(StakingConfig::Astroport(AstroportStakingConfig {
+                            lp_token_addr: lp_token_addr.clone(),
+                            incentives: astroport_contracts.incentives.address,
+                            strict_rewards: false,
+                            cw20_adapter: None,
+                        })),
+                        resolver: Some(LpTokenResolverConfig {
+                            osmosis: false,
+                            astroport: Some(AstroportResolverConfig {
+                                factory: astroport_contracts.factory.address,
+                                liquidity_manager: astroport_contracts.liquidity_manager.address,
+                            }),
+                        }),
+                    },
+                );
+                let lp_token = AssetInfo::cw20(Addr::unchecked(lp_token_addr));
+                (admin, contract_addr, lp_token)
+            }
+        }
+    }
+}
+
+fn create_osmosis_pool(runner: &TestRunner, creator: &SigningAccount, assets: &[Coin]) -> u64 {
+    let msg = MsgCreateBalancerPool {
+        sender: creator.address(),
+        pool_params: Some(PoolParams {
+            swap_fee: "0.003".to_string(),
+            exit_fee: "0".to_string(),
+            smooth_weight_change_params: None,
+        }),
+        pool_assets: assets
+            .iter()
+            .map(|c| PoolAsset {
+                token: Some(c.clone().into()),
+                weight: "1".to_string(),
+            })
+            .collect(),
+        future_pool_governor: String::new(),
+    };
+    runner
+        .execute_cosmos_msgs::<MsgCreateBalancerPoolResponse>(&[msg.into()], creator)
+        .unwrap()
+        .data
+        .pool_id
+}
+
+fn instantiate_generic_contract<'a>(
+    runner: &'a TestRunner<'a>,
+    admin: &SigningAccount,
+    msg: GenericTestContractInstantiateMsg,
+) -> String {
+    let contract = ContractType::MultiTestContract(Box::new(
+        ContractWrapper::new_with_empty(
+            generic_test_contract::contract::execute,
+            generic_test_contract::contract::instantiate,
+            generic_test_contract::contract::query,
+        )
+        .with_reply(generic_test_contract::contract::reply),
+    ));
+    let code_id = upload_wasm_file(runner, admin, contract).unwrap();
+    Wasm::new(runner)
+        .instantiate(
+            code_id,
+            &msg,
+            None,
+            Some("generic test contract"),
+            &[],
+            admin,
+        )
+        .unwrap()
+        .data
+        .address
+}
+
+#[test_case(Venue::Osmosis; "osmosis")]
+#[test_case(Venue::Astroport; "astroport")]
+fn test_pool_flows(venue: Venue) {
+    let owned_runner = venue.runner();
+    let runner = owned_runner.as_ref();
+    let (admin, contract_addr, lp_token) = venue.setup(&runner);
+    let wasm = Wasm::new(&runner);
+    let uatom = AssetInfo::native("uatom");
+    let uluna = AssetInfo::native("uluna");
+
+    let liquidity: AssetList = wasm
+        .query(&contract_addr, &QueryMsg::PoolLiquidity {})
+        .unwrap();
+    assert_eq!(liquidity.find(&uatom).unwrap().amount.u128(), 1_000_000);
+    assert_eq!(liquidity.find(&uluna).unwrap().amount.u128(), 1_000_000);
+
+    // Provide liquidity, failing if more than the simulated LP tokens are
+    // required
+    let assets: AssetList = vec![
+        Asset::new(uatom.clone(), 10_000u128),
+        Asset::new(uluna.clone(), 10_000u128),
+    ]
+    .into();
+    let funds = vec![coin(10_000, "uatom"), coin(10_000, "uluna")];
+    let expected_lp: Uint128 = wasm
+        .query(
+            &contract_addr,
+            &QueryMsg::SimulateProvideLiquidity {
+                assets: assets.clone(),
+            },
+        )
+        .unwrap();
+    assert!(!expected_lp.is_zero());
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::ProvideLiquidity {
+            assets: assets.clone(),
+            min_out: expected_lp + Uint128::one(),
+        },
+        &funds,
+        &admin,
+    )
+    .unwrap_err();
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::ProvideLiquidity {
+            assets,
+            min_out: expected_lp,
+        },
+        &funds,
+        &admin,
+    )
+    .unwrap();
+    assert_eq!(
+        query_asset_balance(&runner, &lp_token, &contract_addr),
+        expected_lp
+    );
+
+    // Swap, receiving the simulated amount
+    let offer = Asset::new(uatom.clone(), 1_000u128);
+    let expected_out: Uint128 = wasm
+        .query(
+            &contract_addr,
+            &QueryMsg::SimulateSwap {
+                offer: offer.clone(),
+                ask: uluna.clone(),
+            },
+        )
+        .unwrap();
+    let uluna_before = query_asset_balance(&runner, &uluna, &contract_addr);
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::Swap {
+            offer,
+            ask: uluna.clone(),
+            min_out: expected_out,
+        },
+        &[coin(1_000, "uatom")],
+        &admin,
+    )
+    .unwrap();
+    assert_eq!(
+        query_asset_balance(&runner, &uluna, &contract_addr) - uluna_before,
+        expected_out
+    );
+
+    // Withdraw all liquidity, receiving the simulated assets
+    let expected_assets: AssetList = wasm
+        .query(
+            &contract_addr,
+            &QueryMsg::SimulateWithdrawLiquidty {
+                amount: expected_lp,
+            },
+        )
+        .unwrap();
+    let before: Vec<Uint128> = [&uatom, &uluna]
+        .iter()
+        .map(|info| query_asset_balance(&runner, info, &contract_addr))
+        .collect();
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::WithdrawLiquidity {
+            amount: expected_lp,
+            min_out: expected_assets.clone(),
+        },
+        &[],
+        &admin,
+    )
+    .unwrap();
+    for (info, before) in [&uatom, &uluna].into_iter().zip(before) {
+        let received = query_asset_balance(&runner, info, &contract_addr) - before;
+        assert_eq!(received, expected_assets.find(info).unwrap().amount);
+    }
+    assert!(query_asset_balance(&runner, &lp_token, &contract_addr).is_zero());
+}
+
+/// Resolving the pool of the LP token with the configured resolver, e.g. the
+/// Astroport factory, without knowing the pool
+#[test_case(Venue::Osmosis; "osmosis")]
+#[test_case(Venue::Astroport; "astroport")]
+fn test_get_pool_for_lp_token(venue: Venue) {
+    let owned_runner = venue.runner();
+    let runner = owned_runner.as_ref();
+    let (_, contract_addr, lp_token) = venue.setup(&runner);
+    let wasm = Wasm::new(&runner);
+
+    let pool: DexPool = wasm
+        .query(
+            &contract_addr,
+            &QueryMsg::GetPoolForLpToken {
+                lp_token: lp_token.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pool.lp_token(), lp_token);
+    match venue {
+        Venue::Osmosis => {
+            let osmosis_pool = pool.as_osmosis().unwrap();
+            assert_eq!(
+                lp_token,
+                AssetInfo::native(format!("gamm/pool/{}", osmosis_pool.pool_id()))
+            );
+            assert!(pool.as_astroport().is_none());
+        }
+        Venue::Astroport => {
+            assert!(pool.as_astroport().is_some());
+            assert!(pool.as_osmosis().is_none());
+        }
+    }
+
+    // Neither a pool asset nor a cw20 contract that is not an LP token is
+    // resolved
+    for not_lp_token in [
+        AssetInfo::native("uatom"),
+        AssetInfo::cw20(Addr::unchecked(&contract_addr)),
+    ] {
+        wasm.query::<_, DexPool>(
+            &contract_addr,
+            &QueryMsg::GetPoolForLpToken {
+                lp_token: not_lp_token,
+            },
+        )
+        .unwrap_err();
+    }
+}
+
+/// Staking flows of the generic test contract. Only run against Astroport:
+/// the Osmosis multi-test shim emulates gamm pools but not the lockup module,
+/// so the `MsgLockTokens` of a stake is rejected, see
+/// [`test_staking_not_emulated_on_osmosis`]. Osmosis staking is instead covered
+/// by the test-tube tests of `cw-dex-osmosis`.
+#[test_case(Venue::Astroport; "astroport")]
+fn test_staking_flows(venue: Venue) {
+    let owned_runner = venue.runner();
+    let runner = owned_runner.as_ref();
+    let (admin, contract_addr, lp_token) = venue.setup(&runner);
+    let wasm = Wasm::new(&runner);
+
+    let assets: AssetList = vec![
+        Asset::native("uatom", 10_000u128),
+        Asset::native("uluna", 10_000u128),
+    ]
+    .into();
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::ProvideLiquidity {
+            assets,
+            min_out: Uint128::zero(),
+        },
+        &[coin(10_000, "uatom"), coin(10_000, "uluna")],
+        &admin,
+    )
+    .unwrap();
+    let lp_amount = query_asset_balance(&runner, &lp_token, &contract_addr);
+
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::Stake { amount: lp_amount },
+        &[],
+        &admin,
+    )
+    .unwrap();
+    let position: StakingPositionInfo = wasm
+        .query(&contract_addr, &QueryMsg::StakingPosition {})
+        .unwrap();
+    assert_eq!(position.staked, lp_amount);
+    assert!(query_asset_balance(&runner, &lp_token, &contract_addr).is_zero());
+
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::Unlock { amount: lp_amount },
+        &[],
+        &admin,
+    )
+    .unwrap();
+    let position: StakingPositionInfo = wasm
+        .query(&contract_addr, &QueryMsg::StakingPosition {})
+        .unwrap();
+    assert!(position.staked.is_zero());
+    assert_eq!(
+        query_asset_balance(&runner, &lp_token, &contract_addr),
+        lp_amount
+    );
+}
+
+/// Pins why [`test_staking_flows`] does not run against Osmosis. Once the
+/// multi-test shim emulates the lockup module this test fails and Osmosis
+/// should be added to the staking flows instead.
+#[test]
+fn test_staking_not_emulated_on_osmosis() {
+    let owned_runner = Venue::Osmosis.runner();
+    let runner = owned_runner.as_ref();
+    let (admin, contract_addr, lp_token) = Venue::Osmosis.setup(&runner);
+    let wasm = Wasm::new(&runner);
+
+    wasm.execute(
+        &contract_addr,
+        &ExecuteMsg::ProvideLiquidity {
+            assets: vec![
+                Asset::native("uatom", 10_000u128),
+                Asset::native("uluna", 10_000u128),
+            ]
+            .into(),
+            min_out: Uint128::zero(),
+        },
+        &[coin(10_000, "uatom"), coin(10_000, "uluna")],
+        &admin,
+    )
+    .unwrap();
+    let lp_amount = query_asset_balance(&runner, &lp_token, &contract_addr);
+
+    let err = wasm
+        .execute(
+            &contract_addr,
+            &ExecuteMsg::Stake { amount: lp_amount },
+            &[],
+            &admin,
+        )
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Unsupported stargate message: /osmosis.lockup.MsgLockTokens"),
+        "{err}"
+    );
+}
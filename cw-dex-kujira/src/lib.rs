@@ -0,0 +1,6 @@
+//! Contains cw-dex OrderBook implementations for Kujira
+
+mod orderbook;
+
+pub use kujira_fin;
+pub use orderbook::*;
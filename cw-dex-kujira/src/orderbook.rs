@@ -0,0 +1,294 @@
+//! OrderBook trait implementation for Kujira FIN
+
+use std::fmt;
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use apollo_utils::assets::assert_native_coin;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    wasm_execute, Addr, Decimal, Decimal256, Deps, Env, Event, Fraction, QuerierWrapper, ReplyOn,
+    Response, StdError, StdResult, SubMsg, Uint128,
+};
+use kujira_fin::{
+    ConfigResponse, ExecuteMsg as FinExecuteMsg, OrderResponse, QueryMsg as FinQueryMsg,
+};
+
+use cw_dex::traits::{LimitOrder, OrderBook};
+use cw_dex::CwDexError;
+
+/// Reply ID for submitting an order. The reply data contains the index of the
+/// new order.
+pub const KUJIRA_FIN_SUBMIT_ORDER_REPLY_ID: u64 = 345;
+
+/// Represents a market on the Kujira FIN order book
+#[cw_serde]
+pub struct KujiraFinOrderBook {
+    /// The address of the FIN pair contract
+    pub pair_addr: Addr,
+    /// The base asset of the market
+    pub base: AssetInfo,
+    /// The quote asset of the market. FIN prices are expressed in units of
+    /// this asset per unit of `base`.
+    pub quote: AssetInfo,
+    /// The reply ID of the submit order submessage
+    #[serde(default = "default_submit_order_reply_id")]
+    pub submit_order_reply_id: u64,
+}
+
+fn default_submit_order_reply_id() -> u64 {
+    KUJIRA_FIN_SUBMIT_ORDER_REPLY_ID
+}
+
+impl fmt::Display for KujiraFinOrderBook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kujira:fin {}", self.pair_addr)
+    }
+}
+
+impl KujiraFinOrderBook {
+    /// Creates a new instance of `KujiraFinOrderBook`, querying the pair
+    /// contract for the denoms of the market.
+    ///
+    /// Arguments:
+    /// - `pair_addr`: The address of the FIN pair contract
+    pub fn new(deps: Deps, pair_addr: Addr) -> StdResult<Self> {
+        let config: ConfigResponse = deps
+            .querier
+            .query_wasm_smart(pair_addr.to_string(), &FinQueryMsg::Config {})?;
+        let [base, quote] = config.denoms;
+
+        Ok(Self {
+            pair_addr,
+            base: base.into(),
+            quote: quote.into(),
+            submit_order_reply_id: KUJIRA_FIN_SUBMIT_ORDER_REPLY_ID,
+        })
+    }
+
+    /// Returns the order book with orders submitted with reply ID `id`
+    /// instead of [`KUJIRA_FIN_SUBMIT_ORDER_REPLY_ID`].
+    pub fn with_submit_order_reply_id(mut self, id: u64) -> Self {
+        self.submit_order_reply_id = id;
+        self
+    }
+
+    /// Converts a price in terms of `ask` per unit of `offer` into the FIN
+    /// quote price, i.e. `quote` per unit of `base`.
+    fn to_fin_price(&self, offer: &AssetInfo, price: Decimal) -> Result<Decimal256, CwDexError> {
+        if offer == &self.base {
+            Ok(price.into())
+        } else if offer == &self.quote {
+            Ok(price
+                .inv()
+                .ok_or_else(|| StdError::generic_err("kujira error: price must be non-zero"))?
+                .into())
+        } else {
+            Err(CwDexError::AssetNotInPool {
+                asset: offer.clone(),
+            })
+        }
+    }
+
+    /// Returns the asset on the other side of the market from `asset_info`.
+    fn other_asset(&self, asset_info: &AssetInfo) -> Result<AssetInfo, CwDexError> {
+        if asset_info == &self.base {
+            Ok(self.quote.clone())
+        } else if asset_info == &self.quote {
+            Ok(self.base.clone())
+        } else {
+            Err(CwDexError::AssetNotInPool {
+                asset: asset_info.clone(),
+            })
+        }
+    }
+}
+
+impl OrderBook for KujiraFinOrderBook {
+    fn place_limit_order(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        price: Decimal,
+    ) -> Result<Response, CwDexError> {
+        let offer = assert_native_coin(&offer_asset)?;
+        if self.other_asset(&offer_asset.info)? != ask_asset_info {
+            return Err(CwDexError::AssetNotInPool {
+                asset: ask_asset_info,
+            });
+        }
+        let fin_price = self.to_fin_price(&offer_asset.info, price)?;
+
+        let submit_order_msg = wasm_execute(
+            self.pair_addr.to_string(),
+            &FinExecuteMsg::SubmitOrder {
+                price: fin_price,
+                callback: None,
+            },
+            vec![offer.clone()],
+        )?;
+
+        let event = Event::new("apollo/cw-dex/place_limit_order")
+            .add_attribute("type", "kujira_fin")
+            .add_attribute("pair_addr", &self.pair_addr)
+            .add_attribute("offer", offer.to_string())
+            .add_attribute("ask", ask_asset_info.to_string())
+            .add_attribute("price", fin_price.to_string());
+
+        Ok(Response::new()
+            .add_submessage(SubMsg {
+                id: self.submit_order_reply_id,
+                msg: submit_order_msg.into(),
+                gas_limit: None,
+                reply_on: ReplyOn::Success,
+            })
+            .add_event(event))
+    }
+
+    fn cancel_order(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        order_id: Uint128,
+    ) -> Result<Response, CwDexError> {
+        let retract_msg = wasm_execute(
+            self.pair_addr.to_string(),
+            &FinExecuteMsg::RetractOrder {
+                order_idx: order_id,
+                amount: None,
+                callback: None,
+            },
+            vec![],
+        )?;
+
+        let event = Event::new("apollo/cw-dex/cancel_order")
+            .add_attribute("type", "kujira_fin")
+            .add_attribute("pair_addr", &self.pair_addr)
+            .add_attribute("order_id", order_id);
+
+        Ok(Response::new().add_message(retract_msg).add_event(event))
+    }
+
+    fn claim_filled(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        order_ids: Vec<Uint128>,
+    ) -> Result<Response, CwDexError> {
+        let event = Event::new("apollo/cw-dex/claim_filled")
+            .add_attribute("type", "kujira_fin")
+            .add_attribute("pair_addr", &self.pair_addr);
+
+        // An empty list of order idxs would make FIN withdraw the first 30 orders
+        // of the sender, so we do nothing instead.
+        if order_ids.is_empty() {
+            return Ok(Response::new().add_event(event));
+        }
+
+        let event = event.add_attribute(
+            "order_ids",
+            order_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+
+        let withdraw_msg = wasm_execute(
+            self.pair_addr.to_string(),
+            &FinExecuteMsg::WithdrawOrders {
+                order_idxs: Some(order_ids),
+                callback: None,
+            },
+            vec![],
+        )?;
+
+        Ok(Response::new().add_message(withdraw_msg).add_event(event))
+    }
+
+    fn query_order(
+        &self,
+        querier: &QuerierWrapper,
+        order_id: Uint128,
+    ) -> Result<LimitOrder, CwDexError> {
+        let order: OrderResponse = querier.query_wasm_smart(
+            self.pair_addr.to_string(),
+            &FinQueryMsg::Order {
+                order_idx: order_id,
+            },
+        )?;
+
+        let offer_asset_info: AssetInfo = order.offer_denom.into();
+        let ask_asset_info = self.other_asset(&offer_asset_info)?;
+
+        // FIN quote prices are always quote per base, so we need to invert the
+        // price for bids.
+        let price = if offer_asset_info == self.base {
+            order.quote_price
+        } else {
+            order
+                .quote_price
+                .inv()
+                .ok_or_else(|| StdError::generic_err("kujira error: order has zero price"))?
+        };
+
+        Ok(LimitOrder {
+            id: order.idx,
+            owner: order.owner,
+            offer: Asset::new(offer_asset_info, Uint128::try_from(order.offer_amount)?),
+            original_offer_amount: Uint128::try_from(order.original_offer_amount)?,
+            ask_asset_info,
+            price: Decimal::try_from(price)?,
+            filled_amount: Uint128::try_from(order.filled_amount)?,
+        })
+    }
+
+    fn market_assets(&self) -> Vec<AssetInfo> {
+        vec![self.base.clone(), self.quote.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::{Addr, Decimal, Decimal256};
+
+    use super::KujiraFinOrderBook;
+
+    fn order_book() -> KujiraFinOrderBook {
+        KujiraFinOrderBook {
+            pair_addr: Addr::unchecked("fin"),
+            base: AssetInfo::native("ukuji"),
+            quote: AssetInfo::native("uusdc"),
+            submit_order_reply_id: super::KUJIRA_FIN_SUBMIT_ORDER_REPLY_ID,
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(order_book().to_string(), "kujira:fin fin");
+    }
+
+    #[test]
+    fn test_to_fin_price() {
+        let book = order_book();
+        let price = Decimal::percent(50);
+
+        // Asks are quoted in quote per base
+        assert_eq!(
+            book.to_fin_price(&AssetInfo::native("ukuji"), price)
+                .unwrap(),
+            Decimal256::percent(50)
+        );
+        // Bids are inverted
+        assert_eq!(
+            book.to_fin_price(&AssetInfo::native("uusdc"), price)
+                .unwrap(),
+            Decimal256::percent(200)
+        );
+        assert!(book
+            .to_fin_price(&AssetInfo::native("uatom"), price)
+            .is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Helpers for querying the Osmosis epochs module. Lockup rewards are
+//! distributed automatically at the start of each distribution epoch, so
+//! these can be used to schedule compounding right after a distribution.
+
+use cosmwasm_std::{Env, QuerierWrapper, StdError, StdResult, Timestamp};
+use osmosis_std::types::osmosis::epochs::v1beta1::{EpochInfo, EpochsQuerier};
+
+/// The identifier of the epoch on which the Osmosis incentives module
+/// distributes lockup rewards.
+pub const OSMOSIS_DISTRIBUTION_EPOCH_IDENTIFIER: &str = "day";
+
+/// Queries the epochs module for the start time of the next epoch with the
+/// given identifier.
+///
+/// Arguments:
+/// - `epoch_identifier`: The identifier of the epoch, e.g.
+///   [`OSMOSIS_DISTRIBUTION_EPOCH_IDENTIFIER`].
+pub fn query_next_epoch_start(
+    querier: &QuerierWrapper,
+    epoch_identifier: &str,
+) -> StdResult<Timestamp> {
+    let epoch = EpochsQuerier::new(querier)
+        .epoch_infos()?
+        .epochs
+        .into_iter()
+        .find(|epoch| epoch.identifier == epoch_identifier)
+        .ok_or_else(|| {
+            StdError::generic_err(format!("osmosis error: epoch {epoch_identifier} not found"))
+        })?;
+
+    next_epoch_start(&epoch)
+}
+
+/// Returns the number of seconds until the next epoch with the given
+/// identifier starts, or zero if it is due to start at the next block.
+///
+/// Arguments:
+/// - `epoch_identifier`: The identifier of the epoch, e.g.
+///   [`OSMOSIS_DISTRIBUTION_EPOCH_IDENTIFIER`].
+pub fn query_seconds_until_next_epoch(
+    querier: &QuerierWrapper,
+    env: &Env,
+    epoch_identifier: &str,
+) -> StdResult<u64> {
+    let next_epoch_start = query_next_epoch_start(querier, epoch_identifier)?;
+    Ok(next_epoch_start
+        .seconds()
+        .saturating_sub(env.block.time.seconds()))
+}
+
+/// Returns the start time of the epoch following `epoch`. The epochs module
+/// starts a new epoch in the first block at or after this time.
+fn next_epoch_start(epoch: &EpochInfo) -> StdResult<Timestamp> {
+    let current_start = epoch
+        .current_epoch_start_time
+        .as_ref()
+        .ok_or_else(|| StdError::generic_err("osmosis error: epoch has no start time"))?;
+    let duration = epoch
+        .duration
+        .as_ref()
+        .ok_or_else(|| StdError::generic_err("osmosis error: epoch has no duration"))?;
+
+    let seconds = u64::try_from(current_start.seconds + duration.seconds)
+        .map_err(|_| StdError::generic_err("osmosis error: invalid epoch time"))?;
+    let nanos = u64::try_from(current_start.nanos as i64 + duration.nanos as i64)
+        .map_err(|_| StdError::generic_err("osmosis error: invalid epoch time"))?;
+
+    Ok(Timestamp::from_seconds(seconds).plus_nanos(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Timestamp;
+    use osmosis_std::shim::{Duration, Timestamp as ProtoTimestamp};
+    use osmosis_std::types::osmosis::epochs::v1beta1::EpochInfo;
+
+    use super::next_epoch_start;
+
+    #[test]
+    fn test_next_epoch_start() {
+        let epoch = EpochInfo {
+            identifier: "day".to_string(),
+            start_time: None,
+            duration: Some(Duration {
+                seconds: 86400,
+                nanos: 0,
+            }),
+            current_epoch: 10,
+            current_epoch_start_time: Some(ProtoTimestamp {
+                seconds: 1_700_000_000,
+                nanos: 500,
+            }),
+            epoch_counting_started: true,
+            current_epoch_start_height: 1,
+        };
+
+        assert_eq!(
+            next_epoch_start(&epoch).unwrap(),
+            Timestamp::from_seconds(1_700_086_400).plus_nanos(500)
+        );
+    }
+}
@@ -0,0 +1,657 @@
+//! Estimates of swaps, joins and exits of Osmosis balancer and stableswap
+//! pools from snapshots of their state, without a querier, e.g. for off-chain
+//! simulations against pending state. Built on the formulas of
+//! [`crate::math`]. The local simulations of [`crate::OsmosisPool`] run on
+//! these snapshots, so the estimates are the same.
+//!
+//! The snapshots mirror the state of the pools on chain and can be converted
+//! from the pool types of `osmosis-std`. [`PoolSnapshot::apply_exit`] and
+//! [`PoolSnapshot::apply_swap`] update a snapshot, e.g. to estimate a sequence
+//! of operations in the same pool.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, Coin, Decimal, Decimal256, StdError, StdResult, Uint128, Uint256};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::Pool as StableswapPool;
+use osmosis_std::types::osmosis::gamm::v1beta1::Pool as BalancerPool;
+
+use cw_dex::conv::{parse_coin, parse_decimal256, parse_uint128};
+
+use crate::deduct_taker_fee;
+use crate::math::{
+    calc_exit_amount, calc_out_amount_given_in, calc_pool_shares_out_given_single_asset_in,
+    calc_stableswap_out_amount_given_in,
+};
+
+/// A reserve of a balancer pool with its weight, mirroring `PoolAsset` of the
+/// gamm module.
+#[cw_serde]
+pub struct BalancerPoolAsset {
+    /// The reserve of the asset
+    pub token: Coin,
+    /// The weight of the asset
+    pub weight: Uint128,
+}
+
+/// The state of a balancer pool, mirroring `Pool` of the gamm module.
+#[cw_serde]
+pub struct BalancerPoolSnapshot {
+    /// The reserves of the pool with their weights
+    pub pool_assets: Vec<BalancerPoolAsset>,
+    /// The total amount of pool shares
+    pub total_shares: Uint128,
+    /// The swap fee of the pool
+    pub swap_fee: Decimal256,
+    /// The exit fee of the pool
+    pub exit_fee: Decimal256,
+}
+
+/// The state of a stableswap pool, mirroring `Pool` of the stableswap pool
+/// model.
+#[cw_serde]
+pub struct StableswapPoolSnapshot {
+    /// The reserves of the pool
+    pub pool_liquidity: Vec<Coin>,
+    /// The scaling factors of the reserves, in the order of `pool_liquidity`
+    pub scaling_factors: Vec<u64>,
+    /// The total amount of pool shares
+    pub total_shares: Uint128,
+    /// The swap fee of the pool
+    pub swap_fee: Decimal256,
+    /// The exit fee of the pool
+    pub exit_fee: Decimal256,
+}
+
+/// The state of a balancer or stableswap pool.
+#[cw_serde]
+pub enum PoolSnapshot {
+    /// A balancer pool
+    Balancer(BalancerPoolSnapshot),
+    /// A stableswap pool
+    Stableswap(StableswapPoolSnapshot),
+}
+
+/// The result of [`PoolSnapshot::estimate_join_no_swap`].
+#[cw_serde]
+pub struct JoinEstimate {
+    /// The pool shares minted
+    pub shares_out: Uint128,
+    /// The tokens joined, in the order of the reserves of the pool. The rest of
+    /// the tokens in is not used.
+    pub tokens_joined: Vec<Coin>,
+}
+
+impl PoolSnapshot {
+    /// Converts a pool as returned by the `Pool` query of the poolmanager.
+    /// Returns `None` if it is neither a balancer nor a stableswap pool.
+    pub fn from_any(pool: Any) -> StdResult<Option<Self>> {
+        Ok(match pool.type_url.as_str() {
+            BalancerPool::TYPE_URL => Some(
+                BalancerPoolSnapshot::try_from(BalancerPool::try_from(Binary::from(pool.value))?)?
+                    .into(),
+            ),
+            StableswapPool::TYPE_URL => Some(
+                StableswapPoolSnapshot::try_from(StableswapPool::try_from(Binary::from(
+                    pool.value,
+                ))?)?
+                .into(),
+            ),
+            _ => None,
+        })
+    }
+
+    /// Returns the reserves of the pool.
+    pub fn reserves(&self) -> Vec<Coin> {
+        match self {
+            PoolSnapshot::Balancer(pool) => pool
+                .pool_assets
+                .iter()
+                .map(|asset| asset.token.clone())
+                .collect(),
+            PoolSnapshot::Stableswap(pool) => pool.pool_liquidity.clone(),
+        }
+    }
+
+    fn total_shares(&self) -> Uint128 {
+        match self {
+            PoolSnapshot::Balancer(pool) => pool.total_shares,
+            PoolSnapshot::Stableswap(pool) => pool.total_shares,
+        }
+    }
+
+    fn total_shares_mut(&mut self) -> &mut Uint128 {
+        match self {
+            PoolSnapshot::Balancer(pool) => &mut pool.total_shares,
+            PoolSnapshot::Stableswap(pool) => &mut pool.total_shares,
+        }
+    }
+
+    fn reserve_mut(&mut self, denom: &str) -> StdResult<&mut Uint128> {
+        let mut reserves: Vec<&mut Coin> = match self {
+            PoolSnapshot::Balancer(pool) => pool
+                .pool_assets
+                .iter_mut()
+                .map(|asset| &mut asset.token)
+                .collect(),
+            PoolSnapshot::Stableswap(pool) => pool.pool_liquidity.iter_mut().collect(),
+        };
+        let index = reserves
+            .iter()
+            .position(|coin| coin.denom == denom)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("osmosis error: {denom} is not in the pool"))
+            })?;
+        Ok(&mut reserves.swap_remove(index).amount)
+    }
+
+    fn exit_fee(&self) -> Decimal256 {
+        match self {
+            PoolSnapshot::Balancer(pool) => pool.exit_fee,
+            PoolSnapshot::Stableswap(pool) => pool.exit_fee,
+        }
+    }
+
+    /// Estimates the amount of `out_denom` received for swapping `token_in`
+    /// with the poolmanager, which first deducts `taker_fee` from `token_in`,
+    /// see [`deduct_taker_fee`].
+    pub fn estimate_swap(
+        &self,
+        token_in: &Coin,
+        out_denom: &str,
+        taker_fee: Decimal,
+    ) -> StdResult<Uint128> {
+        if token_in.denom == out_denom {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: can not swap {out_denom} for itself"
+            )));
+        }
+        let amount_in = deduct_taker_fee(token_in.amount, taker_fee);
+        match self {
+            PoolSnapshot::Balancer(pool) => {
+                let asset_in = pool.asset(&token_in.denom)?;
+                let asset_out = pool.asset(out_denom)?;
+                calc_out_amount_given_in(
+                    asset_in.token.amount,
+                    asset_in.weight,
+                    asset_out.token.amount,
+                    asset_out.weight,
+                    amount_in,
+                    pool.swap_fee,
+                )
+            }
+            PoolSnapshot::Stableswap(pool) => {
+                let reserves: Vec<Uint128> =
+                    pool.pool_liquidity.iter().map(|coin| coin.amount).collect();
+                calc_stableswap_out_amount_given_in(
+                    &reserves,
+                    &pool.scaling_factors,
+                    reserve_index(&pool.pool_liquidity, &token_in.denom)?,
+                    reserve_index(&pool.pool_liquidity, out_denom)?,
+                    amount_in,
+                    pool.swap_fee,
+                )
+            }
+        }
+    }
+
+    /// Estimates the tokens received for exiting the pool with `shares_in`,
+    /// deducting the exit fee, in the order of the reserves of the pool. See
+    /// [`calc_exit_amount`].
+    pub fn estimate_exit(&self, shares_in: Uint128) -> StdResult<Vec<Coin>> {
+        self.reserves()
+            .into_iter()
+            .map(|reserve| {
+                let amount = calc_exit_amount(
+                    reserve.amount,
+                    self.total_shares(),
+                    shares_in,
+                    self.exit_fee(),
+                )?;
+                Ok(Coin::new(amount.u128(), reserve.denom))
+            })
+            .collect()
+    }
+
+    /// Exits the pool with `shares_in` like [`PoolSnapshot::estimate_exit`]
+    /// and removes the exited tokens and shares from the snapshot. The exit fee
+    /// stays in the pool. Returns the tokens exited.
+    pub fn apply_exit(&mut self, shares_in: Uint128) -> StdResult<Vec<Coin>> {
+        let exited = self.estimate_exit(shares_in)?;
+        let total_shares = self.total_shares_mut();
+        *total_shares = total_shares.checked_sub(shares_in)?;
+        for coin in &exited {
+            let reserve = self.reserve_mut(&coin.denom)?;
+            *reserve = reserve.checked_sub(coin.amount)?;
+        }
+        Ok(exited)
+    }
+
+    /// Swaps `token_in` for `out_denom` like [`PoolSnapshot::estimate_swap`]
+    /// and updates the reserves of the snapshot. The swap fee stays in the
+    /// pool, the taker fee does not. Returns the amount of `out_denom`
+    /// received.
+    pub fn apply_swap(
+        &mut self,
+        token_in: &Coin,
+        out_denom: &str,
+        taker_fee: Decimal,
+    ) -> StdResult<Uint128> {
+        let amount_out = self.estimate_swap(token_in, out_denom, taker_fee)?;
+        let reserve_in = self.reserve_mut(&token_in.denom)?;
+        *reserve_in = reserve_in.checked_add(deduct_taker_fee(token_in.amount, taker_fee))?;
+        let reserve_out = self.reserve_mut(out_denom)?;
+        *reserve_out = reserve_out.checked_sub(amount_out)?;
+        Ok(amount_out)
+    }
+
+    /// Estimates joining the pool with `tokens_in` without swapping, as with
+    /// `MsgJoinPool`. Like `MaximalExactRatioJoin` of the gamm module, the
+    /// shares minted are the largest share of the pool the tokens cover in the
+    /// ratio of the reserves, and the tokens joined that share of each
+    /// reserve, rounded up.
+    ///
+    /// Returns an error if `tokens_in` does not contain all assets of the pool
+    /// or contains assets not in the pool.
+    pub fn estimate_join_no_swap(&self, tokens_in: &[Coin]) -> StdResult<JoinEstimate> {
+        let reserves = self.reserves();
+        if let Some(token) = tokens_in
+            .iter()
+            .find(|token| reserve_index(&reserves, &token.denom).is_err())
+        {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: {} is not in the pool",
+                token.denom
+            )));
+        }
+
+        let mut share_ratio: Option<Decimal256> = None;
+        for reserve in &reserves {
+            let amount_in = tokens_in
+                .iter()
+                .find(|token| token.denom == reserve.denom)
+                .map(|token| token.amount)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "osmosis error: joining without swap requires all assets of the pool, \
+                         missing {}",
+                        reserve.denom
+                    ))
+                })?;
+            let ratio = Decimal256::from_ratio(amount_in, reserve.amount);
+            share_ratio = Some(share_ratio.map_or(ratio, |min| min.min(ratio)));
+        }
+        let share_ratio = share_ratio.unwrap_or_default();
+
+        let shares_out: Uint128 = (Uint256::from(self.total_shares()) * share_ratio).try_into()?;
+        if shares_out.is_zero() {
+            return Err(StdError::generic_err(
+                "osmosis error: no pool shares minted",
+            ));
+        }
+        let tokens_joined = reserves
+            .into_iter()
+            .map(|reserve| {
+                let amount: Uint128 = (Decimal256::from_ratio(reserve.amount, 1u8) * share_ratio)
+                    .to_uint_ceil()
+                    .try_into()?;
+                Ok(Coin::new(amount.u128(), reserve.denom))
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(JoinEstimate {
+            shares_out,
+            tokens_joined,
+        })
+    }
+
+    /// Estimates the pool shares minted for joining the pool with only
+    /// `token_in`, as with `MsgJoinSwapExternAmountIn`. See
+    /// [`calc_pool_shares_out_given_single_asset_in`].
+    ///
+    /// Only supported for balancer pools, as the single sided joins of
+    /// stableswap pools solve the CFMM for the shares, which is not ported.
+    pub fn estimate_join_single_asset(&self, token_in: &Coin) -> StdResult<Uint128> {
+        let PoolSnapshot::Balancer(pool) = self else {
+            return Err(StdError::generic_err(
+                "osmosis error: single asset join estimates are only supported for balancer pools",
+            ));
+        };
+        let asset_in = pool.asset(&token_in.denom)?;
+        let total_weight = pool
+            .pool_assets
+            .iter()
+            .try_fold(Uint128::zero(), |total, asset| {
+                total.checked_add(asset.weight)
+            })?;
+
+        calc_pool_shares_out_given_single_asset_in(
+            asset_in.token.amount,
+            Decimal256::from_ratio(asset_in.weight, total_weight),
+            pool.total_shares,
+            token_in.amount,
+            pool.swap_fee,
+        )
+    }
+}
+
+impl BalancerPoolSnapshot {
+    fn asset(&self, denom: &str) -> StdResult<&BalancerPoolAsset> {
+        self.pool_assets
+            .iter()
+            .find(|asset| asset.token.denom == denom)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("osmosis error: {denom} is not in the pool"))
+            })
+    }
+}
+
+impl TryFrom<BalancerPool> for BalancerPoolSnapshot {
+    type Error = StdError;
+
+    fn try_from(pool: BalancerPool) -> StdResult<Self> {
+        let params = pool
+            .pool_params
+            .ok_or_else(|| StdError::generic_err("Pool has no params"))?;
+        let pool_assets = pool
+            .pool_assets
+            .into_iter()
+            .map(|asset| {
+                let token = asset
+                    .token
+                    .ok_or_else(|| StdError::generic_err("Pool asset has no token"))?;
+                Ok(BalancerPoolAsset {
+                    token: parse_coin(&token.denom, &token.amount, "pool asset amount")?,
+                    weight: parse_uint128(&asset.weight, "pool asset weight")?,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(Self {
+            pool_assets,
+            total_shares: parse_total_shares(pool.total_shares)?,
+            swap_fee: parse_decimal256(&params.swap_fee, "swap_fee")?,
+            exit_fee: parse_decimal256(&params.exit_fee, "exit_fee")?,
+        })
+    }
+}
+
+impl TryFrom<StableswapPool> for StableswapPoolSnapshot {
+    type Error = StdError;
+
+    fn try_from(pool: StableswapPool) -> StdResult<Self> {
+        let params = pool
+            .pool_params
+            .ok_or_else(|| StdError::generic_err("Pool has no params"))?;
+        let pool_liquidity = pool
+            .pool_liquidity
+            .iter()
+            .map(|coin| parse_coin(&coin.denom, &coin.amount, "pool liquidity amount"))
+            .collect::<StdResult<_>>()?;
+
+        Ok(Self {
+            pool_liquidity,
+            scaling_factors: pool.scaling_factors,
+            total_shares: parse_total_shares(pool.total_shares)?,
+            swap_fee: parse_decimal256(&params.swap_fee, "swap_fee")?,
+            exit_fee: parse_decimal256(&params.exit_fee, "exit_fee")?,
+        })
+    }
+}
+
+impl From<BalancerPoolSnapshot> for PoolSnapshot {
+    fn from(pool: BalancerPoolSnapshot) -> Self {
+        PoolSnapshot::Balancer(pool)
+    }
+}
+
+impl From<StableswapPoolSnapshot> for PoolSnapshot {
+    fn from(pool: StableswapPoolSnapshot) -> Self {
+        PoolSnapshot::Stableswap(pool)
+    }
+}
+
+fn parse_total_shares(total_shares: Option<ProtoCoin>) -> StdResult<Uint128> {
+    let total_shares =
+        total_shares.ok_or_else(|| StdError::generic_err("Pool has no total shares"))?;
+    Ok(parse_coin(&total_shares.denom, &total_shares.amount, "total_shares")?.amount)
+}
+
+fn reserve_index(reserves: &[Coin], denom: &str) -> StdResult<usize> {
+    reserves
+        .iter()
+        .position(|coin| coin.denom == denom)
+        .ok_or_else(|| StdError::generic_err(format!("osmosis error: {denom} is not in the pool")))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{coin, Decimal, Decimal256, Uint128};
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::gamm::v1beta1::{
+        Pool as BalancerPool, PoolAsset, PoolParams as BalancerPoolParams,
+    };
+
+    use super::{
+        BalancerPoolAsset, BalancerPoolSnapshot, JoinEstimate, PoolSnapshot, StableswapPoolSnapshot,
+    };
+
+    fn balancer() -> PoolSnapshot {
+        BalancerPoolSnapshot {
+            pool_assets: vec![
+                BalancerPoolAsset {
+                    token: coin(1_000_000, "uatom"),
+                    weight: Uint128::new(1),
+                },
+                BalancerPoolAsset {
+                    token: coin(1_000_000, "uosmo"),
+                    weight: Uint128::new(1),
+                },
+            ],
+            total_shares: Uint128::new(100_000_000),
+            swap_fee: Decimal256::permille(3),
+            exit_fee: Decimal256::zero(),
+        }
+        .into()
+    }
+
+    fn stableswap() -> PoolSnapshot {
+        StableswapPoolSnapshot {
+            pool_liquidity: vec![coin(1_000_000, "uusdc"), coin(1_000_000, "uusdt")],
+            scaling_factors: vec![1, 1],
+            total_shares: Uint128::new(100_000_000),
+            swap_fee: Decimal256::zero(),
+            exit_fee: Decimal256::percent(1),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_estimate_swap() {
+        // Same as the constant product case of `calc_out_amount_given_in`
+        let out = balancer()
+            .estimate_swap(&coin(10_000, "uatom"), "uosmo", Decimal::zero())
+            .unwrap();
+        assert_eq!(out, Uint128::new(9871));
+
+        // The taker fee is deducted from the amount in
+        let with_taker_fee = balancer()
+            .estimate_swap(&coin(10_000, "uatom"), "uosmo", Decimal::percent(10))
+            .unwrap();
+        let without_taker_fee = balancer()
+            .estimate_swap(&coin(9_000, "uatom"), "uosmo", Decimal::zero())
+            .unwrap();
+        assert_eq!(with_taker_fee, without_taker_fee);
+
+        // A stableswap pool of balanced reserves swaps at close to one to one
+        let out = stableswap()
+            .estimate_swap(&coin(1_000, "uusdc"), "uusdt", Decimal::zero())
+            .unwrap();
+        assert!(
+            out > Uint128::new(995) && out < Uint128::new(1_000),
+            "{out}"
+        );
+
+        assert!(balancer()
+            .estimate_swap(&coin(1_000, "uatom"), "uusdc", Decimal::zero())
+            .is_err());
+        assert!(balancer()
+            .estimate_swap(&coin(1_000, "uatom"), "uatom", Decimal::zero())
+            .is_err());
+    }
+
+    #[test]
+    fn test_estimate_exit() {
+        assert_eq!(
+            balancer().estimate_exit(Uint128::new(1_000_000)).unwrap(),
+            vec![coin(10_000, "uatom"), coin(10_000, "uosmo")]
+        );
+        // The exit fee is deducted from the shares
+        assert_eq!(
+            stableswap().estimate_exit(Uint128::new(1_000_000)).unwrap(),
+            vec![coin(9_900, "uusdc"), coin(9_900, "uusdt")]
+        );
+        // The last shares can not be withdrawn
+        assert!(balancer().estimate_exit(Uint128::new(100_000_000)).is_err());
+    }
+
+    #[test]
+    fn test_estimate_join_no_swap() {
+        // The uusdt limits the join to 0.5% of the pool, the excess uusdc is
+        // not joined
+        let estimate = stableswap()
+            .estimate_join_no_swap(&[coin(7_000, "uusdc"), coin(5_000, "uusdt")])
+            .unwrap();
+        assert_eq!(
+            estimate,
+            JoinEstimate {
+                shares_out: Uint128::new(500_000),
+                tokens_joined: vec![coin(5_000, "uusdc"), coin(5_000, "uusdt")],
+            }
+        );
+
+        // The tokens joined are rounded up
+        let estimate = balancer()
+            .estimate_join_no_swap(&[coin(1, "uatom"), coin(1, "uosmo")])
+            .unwrap();
+        assert_eq!(estimate.shares_out, Uint128::new(100));
+        assert_eq!(
+            estimate.tokens_joined,
+            vec![coin(1, "uatom"), coin(1, "uosmo")]
+        );
+
+        // All assets of the pool and no others are required
+        assert!(balancer()
+            .estimate_join_no_swap(&[coin(1_000, "uatom")])
+            .is_err());
+        assert!(balancer()
+            .estimate_join_no_swap(&[
+                coin(1_000, "uatom"),
+                coin(1_000, "uosmo"),
+                coin(1_000, "uion")
+            ])
+            .is_err());
+    }
+
+    #[test]
+    fn test_estimate_join_single_asset() {
+        // Half of the tokens is charged the swap fee, and single sided joins
+        // mint fewer shares than the proportional share of the reserve
+        let shares = balancer()
+            .estimate_join_single_asset(&coin(10_000, "uatom"))
+            .unwrap();
+        assert!(
+            shares > Uint128::new(495_000) && shares < Uint128::new(500_000),
+            "{shares}"
+        );
+
+        assert!(stableswap()
+            .estimate_join_single_asset(&coin(10_000, "uusdc"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_balancer_snapshot_from_pool() {
+        let proto_coin = |amount: &str, denom: &str| ProtoCoin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        };
+        let pool = BalancerPool {
+            address: "osmo1pool".to_string(),
+            id: 1,
+            pool_params: Some(BalancerPoolParams {
+                swap_fee: "0.003000000000000000".to_string(),
+                exit_fee: "0.000000000000000000".to_string(),
+                smooth_weight_change_params: None,
+            }),
+            future_pool_governor: String::new(),
+            total_shares: Some(proto_coin("100000000", "gamm/pool/1")),
+            pool_assets: vec![
+                PoolAsset {
+                    token: Some(proto_coin("1000000", "uatom")),
+                    weight: "1".to_string(),
+                },
+                PoolAsset {
+                    token: Some(proto_coin("1000000", "uosmo")),
+                    weight: "1".to_string(),
+                },
+            ],
+            total_weight: "2".to_string(),
+        };
+
+        let snapshot: PoolSnapshot = BalancerPoolSnapshot::try_from(pool).unwrap().into();
+        assert_eq!(snapshot, balancer());
+    }
+
+    #[test]
+    fn test_apply_exit_and_swap() {
+        let mut pool: PoolSnapshot = BalancerPoolSnapshot {
+            pool_assets: vec![
+                BalancerPoolAsset {
+                    token: coin(1_000_000, "uatom"),
+                    weight: Uint128::new(1),
+                },
+                BalancerPoolAsset {
+                    token: coin(1_000_000, "uosmo"),
+                    weight: Uint128::new(1),
+                },
+            ],
+            total_shares: Uint128::new(100),
+            swap_fee: Decimal256::zero(),
+            exit_fee: Decimal256::percent(1),
+        }
+        .into();
+
+        // 10 shares less the exit fee are 9.9% of the pool
+        let exited = pool.apply_exit(Uint128::new(10)).unwrap();
+        assert_eq!(exited, vec![coin(99_000, "uatom"), coin(99_000, "uosmo")]);
+
+        // The swap is against the reserves left by the exit:
+        // 901_000 * (1 - 901_000 / (901_000 + 99_000))
+        let out = pool
+            .apply_swap(&exited[0], "uosmo", Decimal::zero())
+            .unwrap();
+        assert_eq!(out, Uint128::new(89_199));
+
+        let PoolSnapshot::Balancer(state) = &pool else {
+            unreachable!()
+        };
+        assert_eq!(state.total_shares, Uint128::new(90));
+        assert_eq!(
+            pool.reserves(),
+            vec![coin(1_000_000, "uatom"), coin(811_801, "uosmo")]
+        );
+
+        // The taker fee leaves the pool
+        let out = pool
+            .apply_swap(&coin(10_000, "uatom"), "uosmo", Decimal::percent(10))
+            .unwrap();
+        assert_eq!(
+            pool.reserves(),
+            vec![
+                coin(1_009_000, "uatom"),
+                coin(811_801 - out.u128(), "uosmo")
+            ]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Multi-hop swaps for an exact amount out with the poolmanager module, e.g.
+//! for contracts that must pay an exact amount of an asset and fund it with
+//! whatever asset they hold, through several pools if needed.
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use apollo_utils::assets::assert_native_coin;
+use cosmwasm_std::{Deps, Env, Event, Response, StdError, StdResult, Uint128};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountOut, PoolmanagerQuerier, SwapAmountOutRoute,
+};
+
+use cw_dex::conv::parse_uint128;
+use cw_dex::traits::Pool;
+use cw_dex::CwDexError;
+
+use crate::OsmosisPool;
+
+/// Returns the hops of `routes` ending in `token_out_denom`, as the pool ID
+/// with the denoms swapped in and out of the pool. The denom swapped out of a
+/// pool is the `token_in_denom` of the next route, and `token_out_denom` for
+/// the last one.
+///
+/// Returns an error if `routes` is empty or a hop swaps a denom for itself.
+pub fn exact_out_route_hops(
+    routes: &[SwapAmountOutRoute],
+    token_out_denom: &str,
+) -> Result<Vec<(u64, String, String)>, CwDexError> {
+    if routes.is_empty() {
+        return Err(StdError::generic_err("osmosis error: no swap routes given").into());
+    }
+    let out_denoms = routes
+        .iter()
+        .skip(1)
+        .map(|route| route.token_in_denom.as_str())
+        .chain([token_out_denom]);
+    routes
+        .iter()
+        .zip(out_denoms)
+        .map(|(route, out_denom)| {
+            if route.token_in_denom == out_denom {
+                return Err(StdError::generic_err(format!(
+                    "osmosis error: route through pool {} swaps {} for itself",
+                    route.pool_id, out_denom
+                ))
+                .into());
+            }
+            Ok((
+                route.pool_id,
+                route.token_in_denom.clone(),
+                out_denom.to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Swaps at most `max_in` for exactly `token_out` along `routes`, with one
+/// route per pool swapped in. The first route must swap in the asset of
+/// `max_in`. The unused part of `max_in` stays with the contract. See
+/// [`estimate_swap_route_exact_out`] for the amount that will be swapped in.
+///
+/// Returns an error if the route is invalid, see [`exact_out_route_hops`], or
+/// if any of the pools does not contain the assets of its hop.
+pub fn swap_route_exact_out(
+    deps: Deps,
+    env: &Env,
+    routes: Vec<SwapAmountOutRoute>,
+    max_in: Asset,
+    token_out: Asset,
+) -> Result<Response, CwDexError> {
+    let max_in = assert_native_coin(&max_in)?;
+    let token_out = assert_native_coin(&token_out)?;
+    if token_out.amount.is_zero() {
+        return Err(StdError::generic_err("osmosis error: token out amount is zero").into());
+    }
+    let hops = exact_out_route_hops(&routes, &token_out.denom)?;
+    if hops[0].1 != max_in.denom {
+        return Err(StdError::generic_err(format!(
+            "osmosis error: swap route starts with {} instead of {}",
+            hops[0].1, max_in.denom
+        ))
+        .into());
+    }
+    for (pool_id, in_denom, out_denom) in &hops {
+        OsmosisPool::unchecked(*pool_id).validate_swap_assets(
+            deps,
+            &AssetInfo::native(in_denom),
+            &AssetInfo::native(out_denom),
+        )?;
+    }
+
+    let swap_msg = MsgSwapExactAmountOut {
+        sender: env.contract.address.to_string(),
+        routes,
+        token_in_max_amount: max_in.amount.to_string(),
+        token_out: Some(token_out.clone().into()),
+    };
+
+    let event = Event::new("apollo/cw-dex/swap_route_exact_out")
+        .add_attribute("type", "osmosis")
+        .add_attribute("token_in_max", max_in.to_string())
+        .add_attribute("token_out", token_out.to_string())
+        .add_attribute(
+            "routes",
+            hops.iter()
+                .map(|(pool_id, in_denom, _)| format!("{pool_id}:{in_denom}"))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+
+    Ok(Response::new().add_message(swap_msg).add_event(event))
+}
+
+/// Estimates the amount of the first asset of `routes` that
+/// [`swap_route_exact_out`] swaps in to receive exactly `token_out`, with the
+/// estimate query of the poolmanager.
+pub fn estimate_swap_route_exact_out(
+    deps: Deps,
+    routes: &[SwapAmountOutRoute],
+    token_out: Asset,
+) -> StdResult<Uint128> {
+    let token_out = assert_native_coin(&token_out)?;
+    exact_out_route_hops(routes, &token_out.denom)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    let res = PoolmanagerQuerier::new(&deps.querier).estimate_swap_exact_amount_out(
+        routes[0].pool_id,
+        routes.to_vec(),
+        token_out.to_string(),
+    )?;
+    parse_uint128(&res.token_in_amount, "token_in_amount")
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::Asset;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountOutRoute;
+    use test_case::test_case;
+
+    use super::{exact_out_route_hops, swap_route_exact_out};
+
+    fn routes(hops: &[(u64, &str)]) -> Vec<SwapAmountOutRoute> {
+        hops.iter()
+            .map(|(pool_id, token_in_denom)| SwapAmountOutRoute {
+                pool_id: *pool_id,
+                token_in_denom: token_in_denom.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_out_route_hops() {
+        let hops = exact_out_route_hops(&routes(&[(1, "uosmo"), (2, "uatom")]), "uluna").unwrap();
+        assert_eq!(
+            hops,
+            vec![
+                (1, "uosmo".to_string(), "uatom".to_string()),
+                (2, "uatom".to_string(), "uluna".to_string()),
+            ]
+        );
+    }
+
+    #[test_case(&[]; "no routes")]
+    #[test_case(&[(1, "uosmo"), (2, "uosmo")]; "swaps denom for itself")]
+    #[test_case(&[(1, "uosmo"), (2, "uluna")]; "last hop swaps denom for itself")]
+    fn test_exact_out_route_hops_invalid(hops: &[(u64, &str)]) {
+        assert!(exact_out_route_hops(&routes(hops), "uluna").is_err());
+    }
+
+    #[test_case(Asset::native("uatom", 1000u128), Asset::native("uluna", 100u128); "route does not start with offered asset")]
+    #[test_case(Asset::native("uosmo", 1000u128), Asset::native("uluna", 0u128); "zero token out")]
+    fn test_swap_route_exact_out_invalid(max_in: Asset, token_out: Asset) {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let err = swap_route_exact_out(
+            deps.as_ref(),
+            &env,
+            routes(&[(1, "uosmo"), (2, "uatom")]),
+            max_in,
+            token_out,
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("Generic error: osmosis error"));
+    }
+}
@@ -12,3 +12,16 @@ impl ToProtobufDuration for Duration {
         }
     }
 }
+
+pub(crate) trait ToProtobufTimestamp {
+    fn to_protobuf_timestamp(&self) -> osmosis_std::shim::Timestamp;
+}
+
+impl ToProtobufTimestamp for cosmwasm_std::Timestamp {
+    fn to_protobuf_timestamp(&self) -> osmosis_std::shim::Timestamp {
+        osmosis_std::shim::Timestamp {
+            seconds: self.seconds() as i64,
+            nanos: self.subsec_nanos() as i32,
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Queries of the external incentive gauges of the Osmosis incentives module,
+//! e.g. to compute the APR of the rewards of locked LP tokens.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, QuerierWrapper, StdError, StdResult, Timestamp, Uint128};
+use osmosis_std::types::cosmos::base::query::v1beta1::PageRequest;
+use osmosis_std::types::osmosis::incentives::{Gauge, IncentivesQuerier};
+use osmosis_std::types::osmosis::lockup::LockQueryType;
+use osmosis_std::types::osmosis::poolincentives::v1beta1::PoolincentivesQuerier;
+
+use cw_dex::conv::parse_coin;
+
+/// An active gauge of the incentives module distributing rewards to the
+/// lockups of an LP token, as returned by [`query_external_incentive_gauges`].
+#[cw_serde]
+pub struct ExternalIncentiveGauge {
+    /// The ID of the gauge
+    pub id: u64,
+    /// The minimum lockup duration in seconds of the lockups receiving the
+    /// rewards
+    pub lockup_duration_secs: u64,
+    /// Whether the gauge is perpetual. Perpetual gauges distribute all of their
+    /// coins at the next epoch and can be refilled.
+    pub is_perpetual: bool,
+    /// The number of epochs the gauge still distributes rewards in. One for a
+    /// perpetual gauge with coins left to distribute.
+    pub remaining_epochs: u64,
+    /// The rewards distributed in each of the remaining epochs, rounded down
+    pub rewards_per_epoch: Vec<Coin>,
+    /// When the gauge started distributing
+    pub start_time: Timestamp,
+}
+
+/// Queries the active gauges of the incentives module distributing rewards to
+/// the lockups of `lp_denom`, excluding the gauges of the pool incentives
+/// module through which the chain distributes its own incentives to `gamm`
+/// pools.
+pub fn query_external_incentive_gauges(
+    querier: &QuerierWrapper,
+    lp_denom: &str,
+) -> StdResult<Vec<ExternalIncentiveGauge>> {
+    let internal_gauge_ids: Vec<u64> = match lp_denom
+        .strip_prefix("gamm/pool/")
+        .and_then(|pool_id| pool_id.parse().ok())
+    {
+        Some(pool_id) => PoolincentivesQuerier::new(querier)
+            .gauge_ids(pool_id)?
+            .gauge_ids_with_duration
+            .into_iter()
+            .map(|gauge| gauge.gauge_id)
+            .collect(),
+        None => vec![],
+    };
+
+    let incentives = IncentivesQuerier::new(querier);
+    let mut gauges = vec![];
+    let mut key = vec![];
+    loop {
+        let res = incentives.active_gauges_per_denom(
+            lp_denom.to_string(),
+            Some(PageRequest {
+                key,
+                offset: 0,
+                limit: 0,
+                count_total: false,
+                reverse: false,
+            }),
+        )?;
+        for gauge in &res.data {
+            if internal_gauge_ids.contains(&gauge.id) {
+                continue;
+            }
+            if let Some(gauge) = incentive_gauge_from_proto(gauge, lp_denom)? {
+                gauges.push(gauge);
+            }
+        }
+        match res.pagination {
+            Some(pagination) if !pagination.next_key.is_empty() => key = pagination.next_key,
+            _ => break,
+        }
+    }
+    Ok(gauges)
+}
+
+/// Converts `gauge` if it distributes to lockups of `lp_denom` by lockup
+/// duration, and returns `None` otherwise.
+fn incentive_gauge_from_proto(
+    gauge: &Gauge,
+    lp_denom: &str,
+) -> StdResult<Option<ExternalIncentiveGauge>> {
+    let Some(distribute_to) = &gauge.distribute_to else {
+        return Ok(None);
+    };
+    if distribute_to.lock_query_type != i32::from(LockQueryType::ByDuration)
+        || distribute_to.denom != lp_denom
+    {
+        return Ok(None);
+    }
+    let lockup_duration_secs = distribute_to
+        .duration
+        .as_ref()
+        .and_then(|duration| u64::try_from(duration.seconds).ok())
+        .ok_or_else(|| {
+            StdError::generic_err(format!(
+                "osmosis error: gauge {} has no valid lockup duration",
+                gauge.id
+            ))
+        })?;
+    let start_time = gauge
+        .start_time
+        .as_ref()
+        .and_then(|time| {
+            Some(
+                Timestamp::from_seconds(u64::try_from(time.seconds).ok()?)
+                    .plus_nanos(u64::try_from(time.nanos).ok()?),
+            )
+        })
+        .ok_or_else(|| {
+            StdError::generic_err(format!(
+                "osmosis error: gauge {} has no valid start time",
+                gauge.id
+            ))
+        })?;
+
+    let remaining_epochs = if gauge.is_perpetual {
+        1
+    } else {
+        gauge
+            .num_epochs_paid_over
+            .saturating_sub(gauge.filled_epochs)
+    };
+    let mut rewards_per_epoch = vec![];
+    if remaining_epochs > 0 {
+        for coin in &gauge.coins {
+            let total = parse_coin(&coin.denom, &coin.amount, "coins")?;
+            let distributed = gauge
+                .distributed_coins
+                .iter()
+                .find(|c| c.denom == coin.denom)
+                .map(|c| parse_coin(&c.denom, &c.amount, "distributed_coins"))
+                .transpose()?
+                .map_or(Uint128::zero(), |c| c.amount);
+            let amount = total
+                .amount
+                .saturating_sub(distributed)
+                .checked_div(Uint128::from(remaining_epochs))?;
+            if !amount.is_zero() {
+                rewards_per_epoch.push(Coin::new(amount.u128(), coin.denom.clone()));
+            }
+        }
+    }
+
+    Ok(Some(ExternalIncentiveGauge {
+        id: gauge.id,
+        lockup_duration_secs,
+        is_perpetual: gauge.is_perpetual,
+        remaining_epochs: if rewards_per_epoch.is_empty() {
+            0
+        } else {
+            remaining_epochs
+        },
+        rewards_per_epoch,
+        start_time,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{coin, Timestamp};
+    use osmosis_std::shim::{Duration, Timestamp as ProtoTimestamp};
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::incentives::Gauge;
+    use osmosis_std::types::osmosis::lockup::{LockQueryType, QueryCondition};
+
+    use super::{incentive_gauge_from_proto, ExternalIncentiveGauge};
+
+    const LP_DENOM: &str = "gamm/pool/1";
+
+    fn gauge(is_perpetual: bool, num_epochs_paid_over: u64, filled_epochs: u64) -> Gauge {
+        Gauge {
+            id: 7,
+            is_perpetual,
+            distribute_to: Some(QueryCondition {
+                lock_query_type: LockQueryType::ByDuration.into(),
+                denom: LP_DENOM.to_string(),
+                duration: Some(Duration {
+                    seconds: 1_209_600,
+                    nanos: 0,
+                }),
+                timestamp: None,
+            }),
+            coins: vec![
+                ProtoCoin {
+                    denom: "uosmo".to_string(),
+                    amount: "1000".to_string(),
+                },
+                ProtoCoin {
+                    denom: "uion".to_string(),
+                    amount: "10".to_string(),
+                },
+            ],
+            start_time: Some(ProtoTimestamp {
+                seconds: 1_700_000_000,
+                nanos: 0,
+            }),
+            num_epochs_paid_over,
+            filled_epochs,
+            distributed_coins: vec![ProtoCoin {
+                denom: "uosmo".to_string(),
+                amount: "400".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_incentive_gauge_from_proto() {
+        // 600 uosmo and 10 uion left over 3 epochs
+        let res = incentive_gauge_from_proto(&gauge(false, 5, 2), LP_DENOM)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res,
+            ExternalIncentiveGauge {
+                id: 7,
+                lockup_duration_secs: 1_209_600,
+                is_perpetual: false,
+                remaining_epochs: 3,
+                rewards_per_epoch: vec![coin(200, "uosmo"), coin(3, "uion")],
+                start_time: Timestamp::from_seconds(1_700_000_000),
+            }
+        );
+
+        // A perpetual gauge distributes everything left at the next epoch
+        let res = incentive_gauge_from_proto(&gauge(true, 1, 0), LP_DENOM)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.remaining_epochs, 1);
+        assert_eq!(
+            res.rewards_per_epoch,
+            vec![coin(600, "uosmo"), coin(10, "uion")]
+        );
+
+        // All epochs filled
+        let res = incentive_gauge_from_proto(&gauge(false, 5, 5), LP_DENOM)
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.remaining_epochs, 0);
+        assert!(res.rewards_per_epoch.is_empty());
+    }
+
+    #[test]
+    fn test_incentive_gauge_from_proto_other_lockups() {
+        assert!(
+            incentive_gauge_from_proto(&gauge(false, 5, 2), "gamm/pool/2")
+                .unwrap()
+                .is_none()
+        );
+
+        let mut by_time = gauge(false, 5, 2);
+        by_time.distribute_to.as_mut().unwrap().lock_query_type = LockQueryType::ByTime.into();
+        assert!(incentive_gauge_from_proto(&by_time, LP_DENOM)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,9 +1,27 @@
 //! Contains cw-dex Pool and Staking implementations for Osmosis
 
+mod epochs;
+pub mod estimate;
+mod exact_out_route;
 mod helpers;
+mod incentives;
+pub mod math;
+mod oracle;
+mod params;
 mod pool;
+mod poolmanager;
+mod split_route;
+mod stableswap;
 mod staking;
 
+pub use epochs::*;
+pub use exact_out_route::*;
+pub use incentives::*;
+pub use oracle::*;
 pub use osmosis_std;
+pub use params::*;
 pub use pool::*;
+pub use poolmanager::*;
+pub use split_route::*;
+pub use stableswap::*;
 pub use staking::*;
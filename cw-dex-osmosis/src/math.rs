@@ -0,0 +1,676 @@
+//! Balancer pool math of the Osmosis gamm module, ported from `osmomath` and
+//! `x/gamm/pool-models/balancer/amm.go` using 256 bit fixed point decimals,
+//! and the stableswap swap math of `x/gamm/pool-models/stableswap/amm.go`
+//! using 256 bit integers at [`STABLESWAP_PRECISION`] decimal places, or less
+//! for reserves of tokens with many decimals, see [`adjust_precision`].
+//!
+//! `Decimal256` has the same 18 decimal places as `osmomath.Dec`, but
+//! truncates where `osmomath` rounds half up, so intermediate results differ
+//! from the ones computed by Osmosis in the last decimal places. After
+//! truncating to integer amounts the results are at most one unit off.
+//!
+//! Like in `osmomath`, the series approximating [`pow`] is cut off once a term
+//! is smaller than [`POW_PRECISION`]. The error of the approximation itself
+//! grows with the distance of the base from one, e.g. to about `2e-8` for a
+//! base of `0.25`, and is the same as on Osmosis.
+
+use std::cmp::Ordering;
+
+use cosmwasm_std::{Decimal256, StdError, StdResult, Uint128, Uint256, Uint512};
+
+/// The precision to which the fractional part of an exponent is
+/// approximated, same as `powPrecision` in `osmomath`.
+pub const POW_PRECISION: Decimal256 = Decimal256::raw(10_000_000_000);
+
+/// The maximum number of terms of the series in [`pow`], same as
+/// `powIterationLimit` in `osmomath`.
+const POW_ITERATION_LIMIT: u64 = 150_000;
+
+/// The maximum ratio of the pool reserve that can be joined with in a single
+/// sided join, same as `MaxInRatio` in the gamm module.
+pub const MAX_IN_RATIO: Decimal256 = Decimal256::raw(500_000_000_000_000_000);
+
+/// Returns `base ^ exp`.
+///
+/// The integer part of the exponent is computed exactly, the fractional part
+/// with the binomial series to a precision of [`POW_PRECISION`]. Like
+/// `osmomath.Pow` this fails for a `base` of zero or of two and greater, for
+/// which the series does not converge.
+pub fn pow(base: Decimal256, exp: Decimal256) -> StdResult<Decimal256> {
+    if base.is_zero() {
+        return Err(StdError::generic_err("base must be greater than 0"));
+    }
+    if base >= Decimal256::percent(200) {
+        return Err(StdError::generic_err("base must be lesser than two"));
+    }
+
+    let integer = exp.floor();
+    let fractional = exp - integer;
+    let integer = u32::try_from(Uint128::try_from(integer.to_uint_floor())?.u128())
+        .map_err(|_| StdError::generic_err("exponent too large"))?;
+    let integer_pow = base.checked_pow(integer)?;
+    if fractional.is_zero() {
+        return Ok(integer_pow);
+    }
+
+    Ok(integer_pow.checked_mul(pow_approx(base, fractional, POW_PRECISION)?)?)
+}
+
+/// Returns `base ^ exp` for an `exp` in `[0, 1)`, approximated with the
+/// binomial series `(1 + x) ^ exp = 1 + sum(term_k)` where
+/// `term_k = term_(k-1) * (exp - (k - 1)) * x / k`. The series is summed
+/// until a term is smaller than `precision`.
+fn pow_approx(base: Decimal256, exp: Decimal256, precision: Decimal256) -> StdResult<Decimal256> {
+    if exp.is_zero() {
+        return Ok(Decimal256::one());
+    }
+    // Common case of the square root, same as in osmomath
+    if exp == Decimal256::percent(50) {
+        return Ok(base.sqrt());
+    }
+
+    let (x, x_negative) = abs_difference_with_sign(base, Decimal256::one());
+    let mut term = Decimal256::one();
+    let mut sum = Decimal256::one();
+    let mut negative = false;
+    let mut k = 1u64;
+    while term >= precision {
+        // The terms alternate in sign where `base < 1` or `exp < k - 1`
+        let (c, c_negative) = abs_difference_with_sign(exp, Decimal256::from_ratio(k - 1, 1u8));
+        term = term.checked_mul(c)?.checked_mul(x)? / Decimal256::from_ratio(k, 1u8);
+        if term.is_zero() {
+            break;
+        }
+        negative ^= x_negative ^ c_negative;
+        sum = if negative {
+            sum.checked_sub(term)?
+        } else {
+            sum.checked_add(term)?
+        };
+
+        if k == POW_ITERATION_LIMIT {
+            return Err(StdError::generic_err(format!(
+                "failed to reach precision within {POW_ITERATION_LIMIT} iterations"
+            )));
+        }
+        k += 1;
+    }
+
+    Ok(sum)
+}
+
+/// Returns `|a - b|` and whether `a - b` is negative.
+fn abs_difference_with_sign(a: Decimal256, b: Decimal256) -> (Decimal256, bool) {
+    if a >= b {
+        (a - b, false)
+    } else {
+        (b - a, true)
+    }
+}
+
+/// Returns the amount of the unknown token after a change of the balance of
+/// the fixed token, keeping the weighted product of the pool constant. The
+/// result is `balance_unknown_before * (1 - (before / after) ^ (weight_fixed /
+/// weight_unknown))`, negated if the unknown balance grows.
+fn solve_constant_function_invariant(
+    balance_fixed_before: Decimal256,
+    balance_fixed_after: Decimal256,
+    weight_fixed: Decimal256,
+    balance_unknown_before: Decimal256,
+    weight_unknown: Decimal256,
+) -> StdResult<(Decimal256, bool)> {
+    let weight_ratio = weight_fixed / weight_unknown;
+    let y = balance_fixed_before / balance_fixed_after;
+    let (paranthetical, negative) =
+        abs_difference_with_sign(Decimal256::one(), pow(y, weight_ratio)?);
+
+    Ok((balance_unknown_before.checked_mul(paranthetical)?, negative))
+}
+
+/// Returns the share of the tokens in that is not charged the swap fee. When
+/// joining with a single asset, the part of the tokens corresponding to the
+/// normalized weight of the asset is considered not to be swapped.
+fn fee_ratio(normalized_weight: Decimal256, swap_fee: Decimal256) -> Decimal256 {
+    Decimal256::one() - (Decimal256::one() - normalized_weight) * swap_fee
+}
+
+/// Returns the amount received for swapping `amount_in` in a weighted pool,
+/// truncated.
+pub fn calc_out_amount_given_in(
+    balance_in: Uint128,
+    weight_in: Uint128,
+    balance_out: Uint128,
+    weight_out: Uint128,
+    amount_in: Uint128,
+    swap_fee: Decimal256,
+) -> StdResult<Uint128> {
+    let amount_in_after_fee = to_dec(amount_in) * (Decimal256::one() - swap_fee);
+    let balance_in = to_dec(balance_in);
+    let (amount_out, _) = solve_constant_function_invariant(
+        balance_in,
+        balance_in + amount_in_after_fee,
+        to_dec(weight_in),
+        to_dec(balance_out),
+        to_dec(weight_out),
+    )?;
+
+    Ok(amount_out.to_uint_floor().try_into()?)
+}
+
+/// Returns the pool shares minted for joining a weighted pool with only
+/// `amount_in` of one asset, truncated. Fails if `amount_in` exceeds
+/// [`MAX_IN_RATIO`] of the balance of the asset.
+pub fn calc_pool_shares_out_given_single_asset_in(
+    balance_in: Uint128,
+    normalized_weight_in: Decimal256,
+    total_shares: Uint128,
+    amount_in: Uint128,
+    swap_fee: Decimal256,
+) -> StdResult<Uint128> {
+    if to_dec(amount_in) > to_dec(balance_in) * MAX_IN_RATIO {
+        return Err(StdError::generic_err(format!(
+            "max in ratio exceeded: {amount_in} is more than {MAX_IN_RATIO} of {balance_in}"
+        )));
+    }
+    let amount_in_after_fee = to_dec(amount_in) * fee_ratio(normalized_weight_in, swap_fee);
+    let balance_in = to_dec(balance_in);
+    // The shares are linear in the weighted product of the pool, so this is
+    // the same invariant with the sign of the result reversed
+    let (shares, negative) = solve_constant_function_invariant(
+        balance_in + amount_in_after_fee,
+        balance_in,
+        normalized_weight_in,
+        to_dec(total_shares),
+        Decimal256::one(),
+    )?;
+    if !negative && !shares.is_zero() {
+        return Err(StdError::generic_err(
+            "joining must not decrease the pool shares",
+        ));
+    }
+
+    Ok(shares.to_uint_floor().try_into()?)
+}
+
+/// Returns the amount of a reserve of `balance` received for exiting a pool
+/// with `shares_in` of its `total_shares`, truncated, same as `CalcExitPool` in
+/// the gamm module. The `exit_fee` is deducted from the shares before
+/// computing their part of the reserve. Fails if `shares_in` is not less than
+/// `total_shares`, as the last shares of a pool can not be withdrawn.
+pub fn calc_exit_amount(
+    balance: Uint128,
+    total_shares: Uint128,
+    shares_in: Uint128,
+    exit_fee: Decimal256,
+) -> StdResult<Uint128> {
+    if shares_in >= total_shares {
+        return Err(StdError::generic_err(format!(
+            "cannot exit all shares of a pool: {shares_in} of {total_shares}"
+        )));
+    }
+    let refunded_shares = to_dec(shares_in) * (Decimal256::one() - exit_fee);
+    let share_out_ratio = refunded_shares / to_dec(total_shares);
+
+    Ok((to_dec(balance) * share_out_ratio)
+        .to_uint_floor()
+        .try_into()?)
+}
+
+/// The number of decimal places of the scaled reserves in the stableswap math,
+/// same as `Decimal256`. [`calc_stableswap_out_amount_given_in`] lowers it for
+/// reserves too large to solve the CFMM at this precision.
+pub const STABLESWAP_PRECISION: u8 = 18;
+
+/// The largest scaled reserve, in units of the precision, for which the
+/// stableswap CFMM of up to 16 assets fits in a `Uint512`: `x * y` is less than
+/// `2^252` and `x^2 + y^2 + w` less than `16 * 2^252`.
+const MAX_STABLESWAP_RESERVE: Uint256 = Uint256::from_u128(1 << 126);
+
+/// Returns `value` with `current_precision` decimal places converted to
+/// `new_precision` decimal places, rounding down when reducing the precision.
+/// Fails if the result overflows.
+pub fn adjust_precision(
+    value: Uint256,
+    current_precision: u8,
+    new_precision: u8,
+) -> StdResult<Uint256> {
+    let factor = |exp: u8| Uint256::from(10u8).checked_pow(exp.into());
+    Ok(match current_precision.cmp(&new_precision) {
+        Ordering::Equal => value,
+        Ordering::Less => value.checked_mul(factor(new_precision - current_precision)?)?,
+        Ordering::Greater => value / factor(current_precision - new_precision)?,
+    })
+}
+
+/// Returns the constant `x * y * (x^2 + y^2 + w)` of the stableswap CFMM for
+/// the scaled reserves `x` and `y` of the swapped assets and the sum of the
+/// squares `w` of the other scaled reserves.
+fn stableswap_cfmm(x: Uint256, y: Uint256, w: Uint512) -> StdResult<Uint512> {
+    let (x, y) = (Uint512::from(x), Uint512::from(y));
+    let sum_squares = x
+        .checked_mul(x)?
+        .checked_add(y.checked_mul(y)?)?
+        .checked_add(w)?;
+    Ok(x.checked_mul(y)?.checked_mul(sum_squares)?)
+}
+
+/// Returns the amount of `x` received for adding `y_in` to the reserve of `y`,
+/// keeping the stableswap CFMM constant. Same as `solveCFMMBinarySearchMulti`
+/// of the gamm module, except that the binary search runs to the smallest
+/// unit of the scaled reserves and rounds the amount out down.
+fn solve_stableswap_cfmm(
+    x_reserve: Uint256,
+    y_reserve: Uint256,
+    w: Uint512,
+    y_in: Uint256,
+) -> StdResult<Uint256> {
+    if x_reserve.is_zero() || y_reserve.is_zero() {
+        return Err(StdError::generic_err("pool reserves must be positive"));
+    }
+    if y_in >= y_reserve {
+        return Err(StdError::generic_err(
+            "cannot input more than pool reserves",
+        ));
+    }
+    let y_final = y_reserve.checked_add(y_in)?;
+    let k = stableswap_cfmm(x_reserve, y_reserve, w)?;
+
+    // The CFMM is increasing in x, so search for the smallest x keeping it at
+    // least at k
+    let (mut low, mut high) = (Uint256::zero(), x_reserve);
+    while high - low > Uint256::one() {
+        let mid = low + (high - low) / Uint256::from(2u8);
+        if stableswap_cfmm(mid, y_final, w)? >= k {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    Ok(x_reserve - high)
+}
+
+/// Returns the amount received for swapping `amount_in` in a stableswap pool,
+/// truncated. `reserves` and `scaling_factors` are in the order of the pool
+/// liquidity, and `index_in` and `index_out` are the positions of the swapped
+/// assets in them.
+///
+/// The reserves are divided by their scaling factors before solving the CFMM,
+/// so the current factors of the pool must be passed, as they can be changed
+/// by the scaling factor controller of the pool at any time.
+///
+/// The CFMM is solved at [`STABLESWAP_PRECISION`] decimal places, or at the
+/// highest precision below it for which it does not overflow, e.g. for the
+/// reserves of 18 decimal tokens with a scaling factor of one. A lower
+/// precision truncates the scaled reserves and amounts to fewer decimal
+/// places, so the amount out can be a few units lower than at full precision.
+/// See [`calc_stableswap_out_amount_given_in_with_precision`] to set the
+/// precision.
+pub fn calc_stableswap_out_amount_given_in(
+    reserves: &[Uint128],
+    scaling_factors: &[u64],
+    index_in: usize,
+    index_out: usize,
+    amount_in: Uint128,
+    swap_fee: Decimal256,
+) -> StdResult<Uint128> {
+    // The largest reserve in whole units of the scaled reserves, including the
+    // amount in, as the CFMM is evaluated after the swap
+    let max_reserve = reserves
+        .iter()
+        .zip(scaling_factors)
+        .enumerate()
+        .map(|(i, (reserve, factor))| {
+            let reserve = match i == index_in {
+                true => Uint256::from(*reserve) + Uint256::from(amount_in),
+                false => Uint256::from(*reserve),
+            };
+            reserve / Uint256::from((*factor).max(1))
+        })
+        .max()
+        .unwrap_or_default();
+    let mut precision = STABLESWAP_PRECISION;
+    while precision > 0 && adjust_precision(max_reserve, 0, precision)? > MAX_STABLESWAP_RESERVE {
+        precision -= 1;
+    }
+
+    calc_stableswap_out_amount_given_in_with_precision(
+        reserves,
+        scaling_factors,
+        index_in,
+        index_out,
+        amount_in,
+        swap_fee,
+        precision,
+    )
+}
+
+/// Same as [`calc_stableswap_out_amount_given_in`], but solves the CFMM with
+/// the scaled reserves at `precision` decimal places. Fails if the CFMM
+/// overflows at the precision.
+pub fn calc_stableswap_out_amount_given_in_with_precision(
+    reserves: &[Uint128],
+    scaling_factors: &[u64],
+    index_in: usize,
+    index_out: usize,
+    amount_in: Uint128,
+    swap_fee: Decimal256,
+    precision: u8,
+) -> StdResult<Uint128> {
+    if reserves.len() != scaling_factors.len() || scaling_factors.contains(&0) {
+        return Err(StdError::generic_err(
+            "there must be a non-zero scaling factor for each reserve",
+        ));
+    }
+    if index_in == index_out || index_in >= reserves.len() || index_out >= reserves.len() {
+        return Err(StdError::generic_err("invalid assets to swap"));
+    }
+    let scaled = |amount: Uint128, factor: u64| -> StdResult<Uint256> {
+        Ok(adjust_precision(amount.into(), 0, precision)? / Uint256::from(factor))
+    };
+    let scaled_reserves = reserves
+        .iter()
+        .zip(scaling_factors)
+        .map(|(reserve, factor)| scaled(*reserve, *factor))
+        .collect::<StdResult<Vec<_>>>()?;
+    let w = scaled_reserves
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index_in && *i != index_out)
+        .try_fold(Uint512::zero(), |w, (_, reserve)| {
+            let reserve = Uint512::from(*reserve);
+            w.checked_add(reserve.checked_mul(reserve)?)
+        })?;
+    let amount_in_after_fee =
+        scaled(amount_in, scaling_factors[index_in])? * (Decimal256::one() - swap_fee);
+
+    let amount_out = solve_stableswap_cfmm(
+        scaled_reserves[index_out],
+        scaled_reserves[index_in],
+        w,
+        amount_in_after_fee,
+    )?;
+
+    let amount_out = amount_out.checked_mul(scaling_factors[index_out].into())?;
+    Ok(adjust_precision(amount_out, precision, 0)?.try_into()?)
+}
+
+fn to_dec(amount: Uint128) -> Decimal256 {
+    Decimal256::from_ratio(Uint256::from(amount), 1u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use cosmwasm_std::{Decimal256, Uint128, Uint256};
+    use test_case::test_case;
+
+    use super::*;
+
+    fn dec(value: &str) -> Decimal256 {
+        Decimal256::from_str(value).unwrap()
+    }
+
+    /// Asserts that `actual` is within the error of the series in `pow` of the
+    /// exact `expected` value
+    fn assert_within_precision(actual: Decimal256, expected: &str) {
+        let expected = dec(expected);
+        let tolerance = POW_PRECISION * Decimal256::percent(1000);
+        let (diff, _) = abs_difference_with_sign(actual, expected);
+        assert!(
+            diff <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test_case("1.5", "0.5", "1.224744871391589049"; "square root")]
+    #[test_case("1.5", "0.3", "1.129346935456855451"; "fractional")]
+    #[test_case("0.5", "2.5", "0.176776695296636881"; "integer and fractional")]
+    #[test_case("0.25", "0.75", "0.353553390593273762"; "base below one")]
+    #[test_case("1.000000001", "0.3", "1.0000000003"; "base close to one")]
+    #[test_case("0.999999999", "0.7", "0.9999999993"; "base close to one from below")]
+    #[test_case("1.9", "0.9", "1.781879128378147896"; "base close to two")]
+    #[test_case("1.2", "3", "1.728"; "integer")]
+    #[test_case("1.2", "0", "1"; "zero exponent")]
+    fn test_pow(base: &str, exp: &str, expected: &str) {
+        assert_within_precision(pow(dec(base), dec(exp)).unwrap(), expected);
+    }
+
+    #[test_case("0", "0.5"; "zero base")]
+    #[test_case("2", "0.5"; "base of two")]
+    #[test_case("2.5", "0.5"; "base greater than two")]
+    fn test_pow_invalid_base(base: &str, exp: &str) {
+        let err = pow(dec(base), dec(exp)).unwrap_err();
+        assert!(err.to_string().contains("base must be"), "{err}");
+    }
+
+    #[test]
+    fn test_calc_out_amount_given_in() {
+        // Equal weights are a constant product pool
+        let out = calc_out_amount_given_in(
+            Uint128::new(1_000_000),
+            Uint128::new(1),
+            Uint128::new(1_000_000),
+            Uint128::new(1),
+            Uint128::new(10_000),
+            Decimal256::permille(3),
+        )
+        .unwrap();
+        assert_eq!(out, Uint128::new(9871));
+
+        // 80/20 pool: 1_000_000 * (1 - (1_000_000 / 1_010_000) ^ 4)
+        let out = calc_out_amount_given_in(
+            Uint128::new(1_000_000),
+            Uint128::new(4),
+            Uint128::new(1_000_000),
+            Uint128::new(1),
+            Uint128::new(10_000),
+            Decimal256::zero(),
+        )
+        .unwrap();
+        assert_eq!(out, Uint128::new(39019));
+    }
+
+    #[test]
+    fn test_calc_pool_shares_out_given_single_asset_in() {
+        // 100 * ((1 + 10_000 * 0.9985 / 1_000_000) ^ 0.5 - 1)
+        let shares = calc_pool_shares_out_given_single_asset_in(
+            Uint128::new(1_000_000),
+            Decimal256::percent(50),
+            Uint128::new(100_000_000),
+            Uint128::new(10_000),
+            Decimal256::permille(3),
+        )
+        .unwrap();
+        assert_eq!(shares, Uint128::new(498_009));
+
+        // 100 * ((1 + 10_000 * 0.9994 / 1_000_000) ^ 0.8 - 1)
+        let shares = calc_pool_shares_out_given_single_asset_in(
+            Uint128::new(1_000_000),
+            Decimal256::percent(80),
+            Uint128::new(100_000_000),
+            Uint128::new(10_000),
+            Decimal256::permille(3),
+        )
+        .unwrap();
+        assert_eq!(shares, Uint128::new(798_724));
+    }
+
+    #[test]
+    fn test_single_asset_join_exceeding_max_in_ratio() {
+        let err = calc_pool_shares_out_given_single_asset_in(
+            Uint128::new(1_000_000),
+            Decimal256::percent(50),
+            Uint128::new(100_000_000),
+            Uint128::new(500_001),
+            Decimal256::permille(3),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("max in ratio exceeded"), "{err}");
+    }
+
+    #[test_case(1_000_000, 100, 10, "0", 100_000; "no exit fee")]
+    #[test_case(1_000_000, 100, 10, "0.01", 99_000; "exit fee")]
+    #[test_case(1_000, 3, 1, "0", 333; "truncated")]
+    fn test_calc_exit_amount(
+        balance: u128,
+        total_shares: u128,
+        shares_in: u128,
+        exit_fee: &str,
+        expected: u128,
+    ) {
+        let amount = calc_exit_amount(
+            Uint128::new(balance),
+            Uint128::new(total_shares),
+            Uint128::new(shares_in),
+            dec(exit_fee),
+        )
+        .unwrap();
+        assert_eq!(amount, Uint128::new(expected));
+    }
+
+    #[test]
+    fn test_exit_all_shares() {
+        let err = calc_exit_amount(
+            Uint128::new(1_000_000),
+            Uint128::new(100),
+            Uint128::new(100),
+            Decimal256::zero(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("cannot exit all shares"), "{err}");
+    }
+
+    #[test_case(&[1_000_000, 1_000_000], &[1, 1], 1_000, "0", 999; "balanced")]
+    #[test_case(&[1_000_000, 2_000_000], &[1, 2], 1_000, "0", 1_999; "scaled")]
+    #[test_case(&[1_000_000, 1_000_000], &[1, 1], 500_000, "0", 472_604; "large swap")]
+    #[test_case(&[1_000_000_000, 1_000_000], &[1_000, 1], 1_000_000, "0.003", 996; "scaled with fee")]
+    #[test_case(&[1_000_000, 1_000_000, 1_000_000], &[1, 1, 1], 10_000, "0.01", 9_880; "three assets")]
+    fn test_calc_stableswap_out_amount_given_in(
+        reserves: &[u128],
+        scaling_factors: &[u64],
+        amount_in: u128,
+        swap_fee: &str,
+        expected: u128,
+    ) {
+        let reserves = reserves
+            .iter()
+            .map(|r| Uint128::new(*r))
+            .collect::<Vec<_>>();
+        // Swap the first asset for the last one
+        let amount_out = calc_stableswap_out_amount_given_in(
+            &reserves,
+            scaling_factors,
+            0,
+            reserves.len() - 1,
+            Uint128::new(amount_in),
+            dec(swap_fee),
+        )
+        .unwrap();
+        assert_eq!(amount_out, Uint128::new(expected));
+    }
+
+    #[test_case(1_234_567, 6, 18 => 1_234_567_000_000_000_000; "increase")]
+    #[test_case(1_234_567, 6, 3 => 1_234; "decrease rounds down")]
+    #[test_case(1_234_567, 6, 6 => 1_234_567; "unchanged")]
+    fn test_adjust_precision(value: u128, current_precision: u8, new_precision: u8) -> u128 {
+        adjust_precision(value.into(), current_precision, new_precision)
+            .unwrap()
+            .try_into()
+            .map(|value: Uint128| value.u128())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_adjust_precision_overflow() {
+        assert!(adjust_precision(Uint256::MAX, 0, 1).is_err());
+        assert!(adjust_precision(Uint256::one(), 0, 78).is_err());
+        assert_eq!(
+            adjust_precision(Uint256::MAX, 77, 0).unwrap(),
+            Uint256::one()
+        );
+    }
+
+    /// A pool of two 18 decimal tokens with a billion tokens on each side
+    /// overflows the CFMM at 18 decimal places, as the scaled reserves are
+    /// `10^45`. The result must match the same pool of 6 decimal tokens.
+    #[test_case(1, 1_000_000_000_000; "scaling factor one")]
+    #[test_case(1_000_000_000_000, 1; "scaled to 6 decimals")]
+    fn test_stableswap_18_decimals(factor_18: u64, factor_6: u64) {
+        let e = |exp: u32| Uint128::new(10u128.pow(exp));
+        let swap_fee = Decimal256::permille(3);
+        let reserves_18 = [e(27), e(27)];
+        let amount_in_18 = e(24);
+
+        if factor_18 == 1 {
+            let err = calc_stableswap_out_amount_given_in_with_precision(
+                &reserves_18,
+                &[1, 1],
+                0,
+                1,
+                amount_in_18,
+                swap_fee,
+                STABLESWAP_PRECISION,
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("Overflow"), "{err}");
+        }
+
+        let out_18 = calc_stableswap_out_amount_given_in(
+            &reserves_18,
+            &[factor_18, factor_18],
+            0,
+            1,
+            amount_in_18,
+            swap_fee,
+        )
+        .unwrap();
+        let out_6 = calc_stableswap_out_amount_given_in(
+            &[e(15), e(15)],
+            &[factor_6, factor_6],
+            0,
+            1,
+            e(12),
+            swap_fee,
+        )
+        .unwrap();
+        assert!(out_18 < amount_in_18 && !out_18.is_zero());
+        let diff = out_6.abs_diff(out_18 / e(12));
+        assert!(diff <= Uint128::one(), "{out_6} != {out_18} / 10^12");
+    }
+
+    #[test]
+    fn test_stableswap_precision() {
+        // Integer reserves with scaling factors of one are solved exactly at
+        // any precision
+        let reserves = [Uint128::new(1_000_000), Uint128::new(1_000_000)];
+        for precision in [0, 6, STABLESWAP_PRECISION] {
+            let out = calc_stableswap_out_amount_given_in_with_precision(
+                &reserves,
+                &[1, 1],
+                0,
+                1,
+                Uint128::new(1_000),
+                Decimal256::zero(),
+                precision,
+            )
+            .unwrap();
+            assert_eq!(out, Uint128::new(999));
+        }
+    }
+
+    #[test]
+    fn test_stableswap_invalid_scaling_factors() {
+        let err = calc_stableswap_out_amount_given_in(
+            &[Uint128::new(1_000_000), Uint128::new(1_000_000)],
+            &[1, 0],
+            0,
+            1,
+            Uint128::new(1_000),
+            Decimal256::zero(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("non-zero scaling factor"), "{err}");
+    }
+}
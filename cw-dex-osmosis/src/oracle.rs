@@ -0,0 +1,99 @@
+//! Oracle implementation using the TWAP module of Osmosis
+
+use apollo_cw_asset::AssetInfo;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Deps, Env};
+use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
+
+use cw_dex::conv::parse_decimal;
+use cw_dex::oracle::Oracle;
+use cw_dex::CwDexError;
+
+use crate::helpers::ToProtobufTimestamp;
+
+/// An [`Oracle`] quoting the arithmetic TWAP of Osmosis pools over a window
+/// ending at the current block.
+#[cw_serde]
+pub struct OsmosisTwapOracle {
+    /// The denom prices are quoted in
+    pub quote_denom: String,
+    /// The denoms priced by the oracle, each with the ID of the pool to take
+    /// the TWAP of. The pool must contain both the denom and `quote_denom`.
+    pub pools: Vec<(String, u64)>,
+    /// The length of the TWAP window in seconds
+    pub window: u64,
+}
+
+impl Oracle for OsmosisTwapOracle {
+    fn quote_asset(&self) -> AssetInfo {
+        AssetInfo::native(&self.quote_denom)
+    }
+
+    fn price(&self, deps: Deps, env: &Env, asset: &AssetInfo) -> Result<Decimal, CwDexError> {
+        let unavailable = |reason: &str| CwDexError::PriceUnavailable {
+            asset: asset.clone(),
+            reason: reason.to_string(),
+        };
+
+        let AssetInfo::Native(denom) = asset else {
+            return Err(unavailable("osmosis TWAPs only price native denoms"));
+        };
+        if denom == &self.quote_denom {
+            return Ok(Decimal::one());
+        }
+        let Some((_, pool_id)) = self.pools.iter().find(|(d, _)| d == denom) else {
+            return Err(unavailable("no TWAP pool configured"));
+        };
+
+        let start_time = env.block.time.minus_seconds(self.window);
+        let twap = TwapQuerier::new(&deps.querier)
+            .arithmetic_twap_to_now(
+                *pool_id,
+                denom.clone(),
+                self.quote_denom.clone(),
+                Some(start_time.to_protobuf_timestamp()),
+            )
+            .map_err(|err| unavailable(&err.to_string()))?
+            .arithmetic_twap;
+        Ok(parse_decimal(&twap, "arithmetic_twap")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::Decimal;
+    use cw_dex::oracle::Oracle;
+    use cw_dex::CwDexError;
+
+    use super::OsmosisTwapOracle;
+
+    #[test]
+    fn test_price_without_query() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let oracle = OsmosisTwapOracle {
+            quote_denom: "uusdc".to_string(),
+            pools: vec![("uosmo".to_string(), 1)],
+            window: 600,
+        };
+
+        assert_eq!(oracle.quote_asset(), AssetInfo::native("uusdc"));
+        assert_eq!(
+            oracle
+                .price(deps.as_ref(), &env, &AssetInfo::native("uusdc"))
+                .unwrap(),
+            Decimal::one()
+        );
+        assert_eq!(
+            oracle
+                .price(deps.as_ref(), &env, &AssetInfo::native("uatom"))
+                .unwrap_err(),
+            CwDexError::PriceUnavailable {
+                asset: AssetInfo::native("uatom"),
+                reason: "no TWAP pool configured".to_string(),
+            }
+        );
+    }
+}
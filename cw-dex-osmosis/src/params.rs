@@ -0,0 +1,154 @@
+//! Queries of the parameters of the lockup and superfluid modules, so that
+//! contracts can adapt to the chain they run on, e.g. forks of Osmosis with
+//! other lockup durations, instead of assuming the values of Osmosis mainnet.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, QuerierWrapper, StdError, StdResult};
+use osmosis_std::types::osmosis::lockup::LockupQuerier;
+use osmosis_std::types::osmosis::poolincentives::v1beta1::PoolincentivesQuerier;
+use osmosis_std::types::osmosis::superfluid::SuperfluidQuerier;
+
+use cw_dex::conv::parse_decimal;
+
+/// The parameters of the lockup module relevant for [`crate::OsmosisStaking`],
+/// as returned by [`query_lockup_params`].
+#[cw_serde]
+pub struct LockupParams {
+    /// The addresses allowed to force unlock lockups, see
+    /// [`cw_dex::traits::ForceUnlock`]
+    pub force_unlock_allowed_addresses: Vec<String>,
+    /// The lockup durations in seconds of LP tokens the pool incentives
+    /// module distributes rewards for, shortest first
+    pub lockable_durations_secs: Vec<u64>,
+}
+
+impl LockupParams {
+    /// Returns whether `address` is allowed to force unlock lockups.
+    pub fn force_unlock_allowed(&self, address: &Addr) -> bool {
+        self.force_unlock_allowed_addresses
+            .iter()
+            .any(|allowed| allowed == address.as_str())
+    }
+
+    /// Returns the shortest lockup duration in seconds the pool incentives
+    /// module distributes rewards for, or `None` if there are no lockable
+    /// durations.
+    pub fn min_lock_duration_secs(&self) -> Option<u64> {
+        self.lockable_durations_secs.first().copied()
+    }
+
+    /// Returns whether the pool incentives module distributes rewards for
+    /// lockups of `duration_secs` seconds, e.g. to validate
+    /// [`crate::OsmosisStaking::lockup_duration`].
+    pub fn is_lockable(&self, duration_secs: u64) -> bool {
+        self.lockable_durations_secs.contains(&duration_secs)
+    }
+}
+
+/// The parameters of the superfluid module, as returned by
+/// [`query_superfluid_params`].
+#[cw_serde]
+pub struct SuperfluidParams {
+    /// The minimum share of the OSMO value of superfluid staked LP tokens cut
+    /// from their delegation
+    pub minimum_risk_factor: Decimal,
+}
+
+/// Queries the parameters of the lockup module and the lockable durations of
+/// the pool incentives module.
+pub fn query_lockup_params(querier: &QuerierWrapper) -> StdResult<LockupParams> {
+    let params = LockupQuerier::new(querier)
+        .params()?
+        .params
+        .ok_or_else(|| StdError::generic_err("osmosis error: lockup module has no params"))?;
+    let lockable_durations = PoolincentivesQuerier::new(querier)
+        .lockable_durations()?
+        .lockable_durations;
+
+    lockup_params_from_proto(params.force_unlock_allowed_addresses, lockable_durations)
+}
+
+/// Queries the parameters of the superfluid module.
+pub fn query_superfluid_params(querier: &QuerierWrapper) -> StdResult<SuperfluidParams> {
+    let params = SuperfluidQuerier::new(querier)
+        .params()?
+        .params
+        .ok_or_else(|| StdError::generic_err("osmosis error: superfluid module has no params"))?;
+
+    Ok(SuperfluidParams {
+        minimum_risk_factor: parse_decimal(&params.minimum_risk_factor, "minimum_risk_factor")?,
+    })
+}
+
+/// Builds the [`LockupParams`] from the query responses, sorting the lockable
+/// durations. Durations that are not whole seconds are rejected.
+fn lockup_params_from_proto(
+    force_unlock_allowed_addresses: Vec<String>,
+    lockable_durations: Vec<osmosis_std::shim::Duration>,
+) -> StdResult<LockupParams> {
+    let mut lockable_durations_secs = lockable_durations
+        .into_iter()
+        .map(|duration| match u64::try_from(duration.seconds) {
+            Ok(seconds) if duration.nanos == 0 => Ok(seconds),
+            _ => Err(StdError::generic_err(
+                "osmosis error: invalid lockable duration",
+            )),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    lockable_durations_secs.sort();
+
+    Ok(LockupParams {
+        force_unlock_allowed_addresses,
+        lockable_durations_secs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Addr;
+    use osmosis_std::shim::Duration as ProtoDuration;
+
+    use super::lockup_params_from_proto;
+
+    const DAY: u64 = 86_400;
+
+    fn proto_duration(seconds: i64) -> ProtoDuration {
+        ProtoDuration { seconds, nanos: 0 }
+    }
+
+    #[test]
+    fn test_lockup_params_from_proto() {
+        let params = lockup_params_from_proto(
+            vec!["osmo1allowed".to_string()],
+            vec![
+                proto_duration(14 * DAY as i64),
+                proto_duration(DAY as i64),
+                proto_duration(7 * DAY as i64),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(params.lockable_durations_secs, vec![DAY, 7 * DAY, 14 * DAY]);
+        assert_eq!(params.min_lock_duration_secs(), Some(DAY));
+        assert!(params.is_lockable(7 * DAY));
+        assert!(!params.is_lockable(2 * DAY));
+        assert!(params.force_unlock_allowed(&Addr::unchecked("osmo1allowed")));
+        assert!(!params.force_unlock_allowed(&Addr::unchecked("osmo1other")));
+    }
+
+    #[test]
+    fn test_lockup_params_from_proto_invalid() {
+        assert!(lockup_params_from_proto(vec![], vec![proto_duration(-1)]).is_err());
+        assert!(lockup_params_from_proto(
+            vec![],
+            vec![ProtoDuration {
+                seconds: DAY as i64,
+                nanos: 1
+            }]
+        )
+        .is_err());
+
+        let params = lockup_params_from_proto(vec![], vec![]).unwrap();
+        assert_eq!(params.min_lock_duration_secs(), None);
+    }
+}
@@ -1,27 +1,43 @@
 //! Pool trait implementation for Osmosis
 
-use std::ops::Deref;
+use std::fmt;
 use std::str::FromStr;
 
 use apollo_utils::assets::{
-    assert_native_asset_info, assert_native_coin, assert_only_native_coins, merge_assets,
+    assert_native_asset_info, assert_native_coin, assert_only_native_coins,
+};
+use apollo_utils::iterators::IntoElementwise;
+use osmosis_std::shim::Any;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool as ClPool;
+use osmosis_std::types::osmosis::cosmwasmpool::v1beta1::CosmWasmPool;
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::{
+    Pool as StableswapPool, PoolParams as StableswapPoolParams,
 };
-use apollo_utils::iterators::{IntoElementwise, TryIntoElementwise};
 use osmosis_std::types::osmosis::gamm::v1beta1::{
-    GammQuerier, MsgExitPool, MsgJoinPool, MsgJoinSwapExternAmountIn, MsgSwapExactAmountIn,
+    GammQuerier, MsgExitPool, MsgExitSwapShareAmountIn, MsgJoinPool, MsgJoinSwapExternAmountIn,
+    MsgSwapExactAmountIn, Pool as BalancerPool, PoolParams as BalancerPoolParams,
 };
 
 use apollo_cw_asset::{Asset, AssetInfo, AssetList};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    Coin, CosmosMsg, Deps, Env, Event, QuerierWrapper, Response, StdResult, Uint128,
+    Addr, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps, Env, Event, QuerierWrapper, Response,
+    SignedDecimal, StdError, StdResult, Uint128,
 };
 use osmosis_std::types::osmosis::poolmanager::v1beta1::{
     PoolmanagerQuerier, SwapAmountInRoute, TotalPoolLiquidityRequest,
 };
 
-use cw_dex::traits::Pool;
-use cw_dex::CwDexError;
+use cw_dex::assets::merge_coins;
+use cw_dex::conv::{parse_coin, parse_decimal, parse_uint128};
+use cw_dex::events::provide_liquidity_event;
+use cw_dex::metrics::{compute_imbalance, PoolMetrics, QueryImbalance, QueryPoolMetrics};
+use cw_dex::oracle::Oracle;
+use cw_dex::traits::{Pool, SimulationMode};
+use cw_dex::{CwDexError, PoolType};
+
+use crate::estimate::PoolSnapshot;
+use crate::poolmanager::query_taker_fee;
 
 /// Struct for interacting with Osmosis v1beta1 balancer pools. If `pool_id`
 /// maps to another type of pool this will fail.
@@ -30,13 +46,22 @@ use cw_dex::CwDexError;
 pub struct OsmosisPool {
     /// The pool id of the pool to interact with
     pool_id: u64,
+    /// How the simulations of the pool are computed
+    #[serde(default)]
+    simulation_mode: SimulationMode,
+}
+
+impl fmt::Display for OsmosisPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "osmosis:pool/{}", self.pool_id)
+    }
 }
 
 impl OsmosisPool {
     /// Creates a new `OsmosisPool` instance with the given `pool_id` and
     /// validates that the pool exists.
     pub fn new(pool_id: u64, deps: Deps) -> StdResult<Self> {
-        let pool = Self { pool_id };
+        let pool = Self::unchecked(pool_id);
         // If this query succeeds then the pool exists
         pool.get_pool_liquidity(deps)?;
         Ok(pool)
@@ -44,7 +69,24 @@ impl OsmosisPool {
 
     /// Creates an unchecked pool for use in testing.
     pub fn unchecked(pool_id: u64) -> Self {
-        Self { pool_id }
+        Self {
+            pool_id,
+            simulation_mode: SimulationMode::default(),
+        }
+    }
+
+    /// Returns the pool with its simulations computed as selected by `mode`.
+    /// [`SimulationMode::Local`] is supported for swaps in balancer and
+    /// stableswap pools, and queries the taker fee of the pair to deduct it
+    /// from the offer amount like the poolmanager does. On-chain simulations
+    /// use the estimate query of the poolmanager, which deducts the taker fee
+    /// itself, so in both modes the simulated amount can be used as the
+    /// `min_out` of a swap. Withdrawals from balancer and stableswap pools are
+    /// also simulated locally, deducting the exit fee of the pool like the
+    /// `calc_exit_pool_coins_from_shares` query used on-chain.
+    pub fn with_simulation_mode(mut self, mode: SimulationMode) -> Self {
+        self.simulation_mode = mode;
+        self
     }
 
     /// Returns the matching pool given a LP token.
@@ -70,11 +112,305 @@ impl OsmosisPool {
         }
     }
 
+    /// Returns the gamm pools (balancer and stableswap) containing both
+    /// `asset_a` and `asset_b`, by listing the pools of `asset_a` in the
+    /// poolmanager module. Returns an empty list for non-native assets, as
+    /// Osmosis pools only contain native assets.
+    pub fn find_pools(
+        deps: Deps,
+        asset_a: &AssetInfo,
+        asset_b: &AssetInfo,
+    ) -> StdResult<Vec<Self>> {
+        let (AssetInfo::Native(denom_a), AssetInfo::Native(denom_b)) = (asset_a, asset_b) else {
+            return Ok(vec![]);
+        };
+
+        let pools = PoolmanagerQuerier::new(&deps.querier)
+            .list_pools_by_denom(denom_a.clone())?
+            .pools;
+        let mut found = vec![];
+        for pool in pools {
+            // Skip pool types other than gamm pools, e.g. concentrated liquidity
+            // pools, as they have no LP token
+            let (pool_id, denoms) = match pool.type_url.as_str() {
+                BalancerPool::TYPE_URL => {
+                    let pool = BalancerPool::try_from(Binary::from(pool.value))?;
+                    let denoms = pool
+                        .pool_assets
+                        .into_iter()
+                        .filter_map(|asset| asset.token.map(|coin| coin.denom))
+                        .collect::<Vec<_>>();
+                    (pool.id, denoms)
+                }
+                StableswapPool::TYPE_URL => {
+                    let pool = StableswapPool::try_from(Binary::from(pool.value))?;
+                    let denoms = pool
+                        .pool_liquidity
+                        .into_iter()
+                        .map(|coin| coin.denom)
+                        .collect::<Vec<_>>();
+                    (pool.id, denoms)
+                }
+                _ => continue,
+            };
+            if denoms.contains(denom_a) && denoms.contains(denom_b) {
+                found.push(Self::unchecked(pool_id));
+            }
+        }
+
+        Ok(found)
+    }
+
     /// Returns the pool id of the pool
     pub fn pool_id(&self) -> u64 {
         self.pool_id
     }
 
+    /// Queries the type of the pool. Balancer pools with equal weights are
+    /// returned as [`PoolType::ConstantProduct`], and CosmWasm pools as
+    /// [`PoolType::Custom`] `cosmwasm`.
+    pub fn query_pool_type(&self, querier: &QuerierWrapper) -> StdResult<PoolType> {
+        pool_type_from_any(self.query_pool_any(querier)?)
+    }
+
+    /// Queries the stableswap pool from the poolmanager module. Fails if the
+    /// pool is not a stableswap pool.
+    fn query_stableswap_pool(&self, querier: &QuerierWrapper) -> StdResult<StableswapPool> {
+        let pool = self.query_pool_any(querier)?;
+        if pool.type_url != StableswapPool::TYPE_URL {
+            return Err(StdError::generic_err(format!(
+                "Pool {} is not a stableswap pool",
+                self.pool_id
+            )));
+        }
+        StableswapPool::try_from(Binary::from(pool.value))
+    }
+
+    /// Queries the pool from the poolmanager module as protobuf `Any`
+    /// Returns the message joining the pool with `assets` for `shares_out`
+    /// shares, without any queries or validation. A single asset is joined
+    /// with `MsgJoinSwapExternAmountIn` receiving at least `shares_out`,
+    /// several assets with `MsgJoinPool` receiving exactly `shares_out` and
+    /// using at most `assets`, which must be sorted by denom.
+    ///
+    /// For integrators composing messages from their own cached state, e.g. a
+    /// simulation done off chain. [`Pool::provide_liquidity`] simulates the
+    /// join and checks `min_out` before building this message.
+    pub fn build_join_msg(&self, sender: &Addr, assets: &[Coin], shares_out: Uint128) -> CosmosMsg {
+        if let [asset] = assets {
+            MsgJoinSwapExternAmountIn {
+                sender: sender.to_string(),
+                pool_id: self.pool_id,
+                share_out_min_amount: shares_out.to_string(),
+                token_in: Some(asset.clone().into()),
+            }
+            .into()
+        } else {
+            MsgJoinPool {
+                sender: sender.to_string(),
+                pool_id: self.pool_id,
+                share_out_amount: shares_out.to_string(),
+                token_in_maxs: assets.iter().cloned().map(Into::into).collect(),
+            }
+            .into()
+        }
+    }
+
+    /// Returns the `MsgExitPool` message exiting the pool with `shares_in`
+    /// shares, receiving at least `min_out`, without any queries or
+    /// validation. `min_out` must be sorted by denom and may be empty.
+    pub fn build_exit_msg(&self, sender: &Addr, shares_in: Uint128, min_out: &[Coin]) -> CosmosMsg {
+        MsgExitPool {
+            sender: sender.to_string(),
+            pool_id: self.pool_id,
+            share_in_amount: shares_in.to_string(),
+            token_out_mins: min_out.iter().cloned().map(Into::into).collect(),
+        }
+        .into()
+    }
+
+    /// Returns the `MsgSwapExactAmountIn` message swapping `offer` for
+    /// `ask_denom` in the pool, receiving at least `min_out`, without any
+    /// queries or validation. Osmosis rejects a `min_out` of zero, which
+    /// [`Pool::swap`] replaces with one.
+    pub fn build_swap_msg(
+        &self,
+        sender: &Addr,
+        offer: &Coin,
+        ask_denom: &str,
+        min_out: Uint128,
+    ) -> CosmosMsg {
+        MsgSwapExactAmountIn {
+            sender: sender.to_string(),
+            routes: vec![SwapAmountInRoute {
+                pool_id: self.pool_id,
+                token_out_denom: ask_denom.to_string(),
+            }],
+            token_in: Some(offer.clone().into()),
+            token_out_min_amount: min_out.to_string(),
+        }
+        .into()
+    }
+
+    fn query_pool_any(&self, querier: &QuerierWrapper) -> StdResult<Any> {
+        PoolmanagerQuerier::new(querier)
+            .pool(self.pool_id)?
+            .pool
+            .ok_or_else(|| StdError::generic_err(format!("Pool {} not found", self.pool_id)))
+    }
+
+    /// Queries the current scaling factors of a stableswap pool, paired with
+    /// the denoms they apply to. The reserves of the pool are divided by their
+    /// scaling factors in the swap math, e.g. to track the redemption rate of
+    /// a liquid staking derivative. Fails if the pool is not a stableswap
+    /// pool.
+    pub fn query_scaling_factors(&self, querier: &QuerierWrapper) -> StdResult<Vec<(String, u64)>> {
+        let pool = self.query_stableswap_pool(querier)?;
+        Ok(pool
+            .pool_liquidity
+            .into_iter()
+            .map(|coin| coin.denom)
+            .zip(pool.scaling_factors)
+            .collect())
+    }
+
+    /// Queries the address allowed to change the scaling factors of a
+    /// stableswap pool, e.g. to monitor it. Returns `None` if the scaling
+    /// factors are fixed. Fails if the pool is not a stableswap pool.
+    pub fn query_scaling_factor_controller(
+        &self,
+        querier: &QuerierWrapper,
+    ) -> StdResult<Option<Addr>> {
+        let pool = self.query_stableswap_pool(querier)?;
+        Ok(Some(pool.scaling_factor_controller)
+            .filter(|controller| !controller.is_empty())
+            .map(Addr::unchecked))
+    }
+
+    /// Simulates swapping `offer` for `ask_denom` with the math of
+    /// [`crate::math`], after deducting the taker fee of the poolmanager from
+    /// `offer`. The state of the pool, including the scaling factors of a
+    /// stableswap pool which the controller can change at any time, is queried
+    /// on every simulation. Returns `None` if the pool is neither a balancer
+    /// nor a stableswap pool.
+    fn simulate_swap_locally(
+        &self,
+        deps: Deps,
+        offer: &Coin,
+        ask_denom: &str,
+    ) -> StdResult<Option<Uint128>> {
+        let Some(pool) = PoolSnapshot::from_any(self.query_pool_any(&deps.querier)?)? else {
+            return Ok(None);
+        };
+        let taker_fee = query_taker_fee(&deps.querier, &offer.denom, ask_denom)?;
+        pool.estimate_swap(offer, ask_denom, taker_fee).map(Some)
+    }
+
+    /// Simulates exiting the pool with `shares_in` from the state of the pool,
+    /// deducting its exit fee. Returns `None` if the pool is neither a
+    /// balancer nor a stableswap pool.
+    fn simulate_exit_locally(
+        &self,
+        querier: &QuerierWrapper,
+        shares_in: Uint128,
+    ) -> StdResult<Option<AssetList>> {
+        let Some(pool) = PoolSnapshot::from_any(self.query_pool_any(querier)?)? else {
+            return Ok(None);
+        };
+        Ok(Some(pool.estimate_exit(shares_in)?.into()))
+    }
+
+    /// Simulates withdrawing `lp_token` from the pool into `ask_denom` only, as
+    /// done by [`OsmosisPool::withdraw_liquidity_single_sided`].
+    ///
+    /// Like the `ExitSwapShareAmountIn` message of the gamm module, this exits
+    /// the pool, deducting its exit fee, and then swaps each other exited coin
+    /// into `ask_denom` in order of their denoms, each swap against the pool
+    /// state left by the exit and the previous swaps. As the state after the
+    /// exit can not be queried, this is computed locally, with the precision
+    /// documented in [`crate::math`], for balancer and stableswap pools.
+    /// Returns an error for other pool types.
+    ///
+    /// Each exited amount and swap return may be one unit off in either
+    /// direction, so the result may differ from the amount received on chain
+    /// by up to one unit per asset of the pool. A `min_out` passed to
+    /// [`OsmosisPool::withdraw_liquidity_single_sided`] must leave at least
+    /// that much room below the simulation.
+    pub fn simulate_single_sided_exit(
+        &self,
+        querier: &QuerierWrapper,
+        lp_token: &Asset,
+        ask_denom: &str,
+    ) -> Result<Uint128, CwDexError> {
+        if lp_token.info != self.lp_token() {
+            return Err(CwDexError::InvalidLpToken {});
+        }
+        let mut pool = PoolSnapshot::from_any(self.query_pool_any(querier)?)?.ok_or_else(|| {
+            StdError::generic_err(format!(
+                "single sided exit is only supported for balancer and stableswap pools, not pool {}",
+                self.pool_id
+            ))
+        })?;
+
+        let mut exited = pool.apply_exit(lp_token.amount)?;
+        if !exited.iter().any(|coin| coin.denom == ask_denom) {
+            return Err(CwDexError::AssetNotInPool {
+                asset: AssetInfo::native(ask_denom),
+            });
+        }
+        exited.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+        let mut amount_out = Uint128::zero();
+        for coin in exited {
+            if coin.denom == ask_denom {
+                amount_out = amount_out.checked_add(coin.amount)?;
+            } else if !coin.amount.is_zero() {
+                // The taker fee is only charged by the poolmanager
+                amount_out = amount_out.checked_add(pool.apply_swap(
+                    &coin,
+                    ask_denom,
+                    Decimal::zero(),
+                )?)?;
+            }
+        }
+
+        Ok(amount_out)
+    }
+
+    /// Withdraws `lp_token` from the pool into `ask_denom` only, with the
+    /// `MsgExitSwapShareAmountIn` message of the gamm module, receiving at
+    /// least `min_out`. Use [`OsmosisPool::simulate_single_sided_exit`] to
+    /// compute `min_out`, deducting slippage. `min_out` is passed to the chain
+    /// as is, and the simulated amount itself can be up to one unit per asset
+    /// of the pool above the amount received.
+    pub fn withdraw_liquidity_single_sided(
+        &self,
+        env: &Env,
+        lp_token: Asset,
+        ask_denom: &str,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        if lp_token.info != self.lp_token() {
+            return Err(CwDexError::InvalidLpToken {});
+        }
+
+        let exit_msg = MsgExitSwapShareAmountIn {
+            sender: env.contract.address.to_string(),
+            pool_id: self.pool_id,
+            token_out_denom: ask_denom.to_string(),
+            share_in_amount: lp_token.amount.to_string(),
+            token_out_min_amount: min_out.to_string(),
+        };
+
+        let event = Event::new("apollo/cw-dex/withdraw_liquidity_single_sided")
+            .add_attribute("pool_id", self.pool_id.to_string())
+            .add_attribute("shares_in", lp_token.to_string())
+            .add_attribute("ask", ask_denom)
+            .add_attribute("min_out", min_out);
+
+        Ok(Response::new().add_message(exit_msg).add_event(event))
+    }
+
     /// Simulates a single sided join and returns `Uint128` amount of LP tokens
     /// returned. A single sided join will use all of the provided asset.
     pub fn simulate_single_sided_join(
@@ -83,10 +419,11 @@ impl OsmosisPool {
         asset: &Asset,
     ) -> StdResult<Uint128> {
         let querier = GammQuerier::new(querier);
-        let share_out_amount = Uint128::from_str(
+        let share_out_amount = parse_uint128(
             &querier
                 .calc_join_pool_shares(self.pool_id, vec![assert_native_coin(asset)?.into()])?
                 .share_out_amount,
+            "share_out_amount",
         )?;
         Ok(share_out_amount)
     }
@@ -104,20 +441,121 @@ impl OsmosisPool {
             self.pool_id,
             assert_only_native_coins(assets)?.into_elementwise(),
         )?;
-        let lp_tokens_returned = Uint128::from_str(&response.shares_out)?;
+        let lp_tokens_returned = parse_uint128(&response.shares_out, "shares_out")?;
         let tokens_used: Vec<Coin> = response
             .tokens_out
             .iter()
-            .map(|x| {
-                Ok(Coin {
-                    denom: x.denom.clone(),
-                    amount: Uint128::from_str(&x.amount)?,
-                })
-            })
+            .map(|x| parse_coin(&x.denom, &x.amount, "tokens_out"))
             .collect::<StdResult<_>>()?;
 
         Ok((lp_tokens_returned, AssetList::from(tokens_used)))
     }
+
+    /// Returns the total amount of LP tokens of the pool
+    pub fn query_total_shares(&self, querier: &QuerierWrapper) -> StdResult<Uint128> {
+        let total_shares = GammQuerier::new(querier)
+            .total_shares(self.pool_id)?
+            .total_shares
+            .ok_or_else(|| StdError::generic_err("Pool has no total shares"))?;
+        parse_uint128(&total_shares.amount, "total_shares")
+    }
+
+    /// Returns the swap fee and the exit fee of the pool
+    fn query_fees(&self, querier: &QuerierWrapper) -> StdResult<(Decimal, Decimal)> {
+        let params = GammQuerier::new(querier)
+            .pool_params(self.pool_id)?
+            .params
+            .ok_or_else(|| StdError::generic_err("Pool has no params"))?;
+
+        let (swap_fee, exit_fee) = if params.type_url == StableswapPoolParams::TYPE_URL {
+            let params = StableswapPoolParams::try_from(params)
+                .map_err(|e| StdError::parse_err("StableswapPoolParams", e))?;
+            (params.swap_fee, params.exit_fee)
+        } else {
+            let params = BalancerPoolParams::try_from(params)
+                .map_err(|e| StdError::parse_err("PoolParams", e))?;
+            (params.swap_fee, params.exit_fee)
+        };
+
+        Ok((
+            parse_decimal(&swap_fee, "swap_fee")?,
+            parse_decimal(&exit_fee, "exit_fee")?,
+        ))
+    }
+
+    /// Returns the swap fee of the pool
+    pub fn query_swap_fee(&self, querier: &QuerierWrapper) -> StdResult<Decimal> {
+        Ok(self.query_fees(querier)?.0)
+    }
+
+    /// Returns the exit fee of the pool, the fraction of the withdrawn shares
+    /// that is kept by the pool when withdrawing liquidity
+    pub fn query_exit_fee(&self, querier: &QuerierWrapper) -> StdResult<Decimal> {
+        Ok(self.query_fees(querier)?.1)
+    }
+}
+
+impl QueryPoolMetrics for OsmosisPool {
+    fn query_pool_metrics(&self, deps: Deps) -> Result<PoolMetrics, CwDexError> {
+        let (swap_fee, exit_fee) = self.query_fees(&deps.querier)?;
+        Ok(PoolMetrics {
+            lp_token: self.lp_token(),
+            pool_type: self.query_pool_type(&deps.querier)?,
+            reserves: self.get_pool_liquidity(deps)?,
+            total_shares: self.query_total_shares(&deps.querier)?,
+            swap_fee,
+            exit_fee,
+            // Osmosis incentives are distributed through lockup gauges, which
+            // are not resolved to a per pool emission rate here.
+            emissions: vec![],
+        })
+    }
+}
+
+/// Stableswap reserves are divided by their scaling factors, see
+/// [`OsmosisPool::query_scaling_factors`], and compared with equal weights.
+/// Returns an error for balancer pools, whose reserves must be valued with an
+/// oracle to be comparable with their weights, see
+/// [`OsmosisPool::query_imbalance_with_oracle`], and for other pool types.
+impl QueryImbalance for OsmosisPool {
+    fn query_imbalance(&self, deps: Deps) -> Result<Vec<(AssetInfo, SignedDecimal)>, CwDexError> {
+        let (reserves, weights): (Vec<_>, Vec<_>) =
+            imbalance_targets(&self.query_pool_snapshot(deps)?, None)?
+                .into_iter()
+                .unzip();
+        compute_imbalance(&reserves, &weights)
+    }
+}
+
+impl OsmosisPool {
+    /// Returns the deviation of the reserves of the pool from its target
+    /// weights like [`QueryImbalance::query_imbalance`], but for balancer
+    /// pools the reserves are valued with the prices of `oracle` before they
+    /// are compared with the weights. Stableswap reserves are compared as in
+    /// [`QueryImbalance::query_imbalance`], without querying `oracle`.
+    pub fn query_imbalance_with_oracle(
+        &self,
+        deps: Deps,
+        env: &Env,
+        oracle: &dyn Oracle,
+    ) -> Result<Vec<(AssetInfo, SignedDecimal)>, CwDexError> {
+        let (reserves, weights): (Vec<_>, Vec<_>) =
+            imbalance_targets(&self.query_pool_snapshot(deps)?, Some((deps, env, oracle)))?
+                .into_iter()
+                .unzip();
+        compute_imbalance(&reserves, &weights)
+    }
+
+    fn query_pool_snapshot(&self, deps: Deps) -> Result<PoolSnapshot, CwDexError> {
+        Ok(
+            PoolSnapshot::from_any(self.query_pool_any(&deps.querier)?)?.ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "imbalance is only supported for balancer and stableswap pools, not pool {}",
+                    self.pool_id
+                ))
+            })?,
+        )
+    }
 }
 
 impl Pool for OsmosisPool {
@@ -128,15 +566,20 @@ impl Pool for OsmosisPool {
         assets: AssetList,
         min_out: Uint128,
     ) -> Result<Response, CwDexError> {
-        let mut assets = assets;
-
-        // Remove all zero amount Coins, merge duplicates and assert that all assets are
-        // native.
-        let mut assets = assert_only_native_coins(&merge_assets(assets.purge().deref())?)?;
+        // Assert that all assets are native, merge duplicates, remove all zero amount
+        // Coins and sort by denom.
+        let assets = merge_coins(assert_only_native_coins(&assets)?)?;
 
-        let expected_shares = self
-            .simulate_provide_liquidity(deps, env, assets.to_owned().into())?
-            .amount;
+        // Joins with several assets use them in the ratio of the pool reserves
+        let (expected_shares, used) = if assets.len() == 1 {
+            let asset: Asset = assets[0].clone().into();
+            (
+                self.simulate_single_sided_join(&deps.querier, &asset)?,
+                vec![asset].into(),
+            )
+        } else {
+            self.simulate_noswap_join(&deps.querier, &assets.to_owned().into())?
+        };
 
         // Assert slippage tolerance
         if min_out > expected_shares {
@@ -146,30 +589,10 @@ impl Pool for OsmosisPool {
             });
         }
 
-        // sort assets
-        assets.sort_by(|a, b| a.denom.to_string().cmp(&b.denom));
-
-        let join_pool: CosmosMsg = if assets.len() == 1 {
-            MsgJoinSwapExternAmountIn {
-                sender: env.contract.address.to_string(),
-                pool_id: self.pool_id,
-                share_out_min_amount: expected_shares.to_string(),
-                token_in: Some(assets[0].clone().into()),
-            }
-            .into()
-        } else {
-            MsgJoinPool {
-                sender: env.contract.address.to_string(),
-                pool_id: self.pool_id,
-                share_out_amount: expected_shares.to_string(),
-                token_in_maxs: assets.into_elementwise(),
-            }
-            .into()
-        };
+        let join_pool = self.build_join_msg(&env.contract.address, &assets, expected_shares);
 
-        let event = Event::new("apollo/cw-dex/provide_liquidity")
+        let event = provide_liquidity_event(self, &used, Some(expected_shares), min_out)
             .add_attribute("pool_id", self.pool_id.to_string())
-            .add_attribute("min_out", min_out)
             .add_attribute("expected_shares", expected_shares);
 
         Ok(Response::new().add_message(join_pool).add_event(event))
@@ -177,23 +600,38 @@ impl Pool for OsmosisPool {
 
     fn withdraw_liquidity(
         &self,
-        _deps: Deps,
+        deps: Deps,
         env: &Env,
         lp_token: Asset,
         min_out: AssetList,
     ) -> Result<Response, CwDexError> {
-        let min_out_coins = assert_only_native_coins(&min_out)?.try_into_elementwise()?;
+        let min_out_coins = merge_coins(assert_only_native_coins(&min_out)?)?;
 
-        let exit_msg = MsgExitPool {
-            sender: env.contract.address.to_string(),
-            pool_id: self.pool_id,
-            share_in_amount: lp_token.amount.to_string(),
-            token_out_mins: min_out_coins,
-        };
+        // The exit fee is deducted from the withdrawn shares, so `min_out`
+        // computed pro rata from the reserves would fail on exit. Assert it
+        // against the simulation, which deducts the exit fee, to fail early.
+        let exit_fee = self.query_exit_fee(&deps.querier)?;
+        if !min_out_coins.is_empty() {
+            let expected = self.simulate_withdraw_liquidity(deps, &lp_token)?;
+            for coin in &min_out_coins {
+                let received = expected
+                    .find(&AssetInfo::native(&coin.denom))
+                    .map(|asset| asset.amount)
+                    .unwrap_or_default();
+                if coin.amount > received {
+                    return Err(CwDexError::MinOutNotReceived {
+                        min_out: coin.amount,
+                        received,
+                    });
+                }
+            }
+        }
+        let exit_msg = self.build_exit_msg(&env.contract.address, lp_token.amount, &min_out_coins);
 
         let mut event = Event::new("apollo/cw-dex/withdraw_liquidity")
             .add_attribute("pool_id", self.pool_id.to_string())
-            .add_attribute("shares_in", lp_token.to_string());
+            .add_attribute("shares_in", lp_token.to_string())
+            .add_attribute("exit_fee", exit_fee.to_string());
 
         // We're not allowed to add empty values as attributes.
         if !min_out.len() == 0 {
@@ -205,12 +643,13 @@ impl Pool for OsmosisPool {
 
     fn swap(
         &self,
-        _deps: Deps,
+        deps: Deps,
         env: &Env,
         offer_asset: Asset,
         ask_asset_info: AssetInfo,
         min_out: Uint128,
     ) -> Result<Response, CwDexError> {
+        self.validate_swap_assets(deps, &offer_asset.info, &ask_asset_info)?;
         let offer = assert_native_coin(&offer_asset)?;
         let ask_denom = assert_native_asset_info(&ask_asset_info)?;
 
@@ -221,15 +660,7 @@ impl Pool for OsmosisPool {
             min_out
         };
 
-        let swap_msg = MsgSwapExactAmountIn {
-            sender: env.contract.address.to_string(),
-            routes: vec![SwapAmountInRoute {
-                pool_id: self.pool_id,
-                token_out_denom: ask_denom.clone(),
-            }],
-            token_in: Some(offer.clone().into()),
-            token_out_min_amount: min_out.to_string(),
-        };
+        let swap_msg = self.build_swap_msg(&env.contract.address, &offer, &ask_denom, min_out);
 
         let event = Event::new("apollo/cw-dex/swap")
             .add_attribute("pool_id", self.pool_id.to_string())
@@ -251,8 +682,8 @@ impl Pool for OsmosisPool {
             .into_iter()
             .map(|coin| {
                 Ok(Asset {
+                    amount: parse_uint128(&coin.amount, "liquidity")?,
                     info: AssetInfo::Native(coin.denom),
-                    amount: Uint128::from_str(&coin.amount)?,
                 })
             })
             .collect::<StdResult<Vec<Asset>>>()?
@@ -290,16 +721,17 @@ impl Pool for OsmosisPool {
             return Err(CwDexError::InvalidLpToken {});
         }
 
+        if self.simulation_mode == SimulationMode::Local {
+            if let Some(tokens_out) = self.simulate_exit_locally(&deps.querier, lp_token.amount)? {
+                return Ok(tokens_out);
+            }
+        }
+
         let tokens_out: Vec<Coin> = querier
             .calc_exit_pool_coins_from_shares(self.pool_id, lp_token.amount.to_string())?
             .tokens_out
             .iter()
-            .map(|c| {
-                Ok(Coin {
-                    denom: c.denom.clone(),
-                    amount: Uint128::from_str(&c.amount)?,
-                })
-            })
+            .map(|c| parse_coin(&c.denom, &c.amount, "tokens_out"))
             .collect::<StdResult<_>>()?;
 
         Ok(tokens_out.into())
@@ -312,15 +744,22 @@ impl Pool for OsmosisPool {
         ask_asset_info: AssetInfo,
     ) -> StdResult<Uint128> {
         let offer: Coin = offer.try_into()?;
+        let ask_denom = assert_native_asset_info(&ask_asset_info)?;
+        if self.simulation_mode == SimulationMode::Local {
+            if let Some(amount) = self.simulate_swap_locally(deps, &offer, &ask_denom)? {
+                return Ok(amount);
+            }
+        }
+
         let swap_response = PoolmanagerQuerier::new(&deps.querier).estimate_swap_exact_amount_in(
             self.pool_id,
             offer.to_string(),
             vec![SwapAmountInRoute {
                 pool_id: self.pool_id,
-                token_out_denom: assert_native_asset_info(&ask_asset_info)?,
+                token_out_denom: ask_denom,
             }],
         )?;
-        Uint128::from_str(swap_response.token_out_amount.as_str())
+        parse_uint128(&swap_response.token_out_amount, "token_out_amount")
     }
 
     fn lp_token(&self) -> AssetInfo {
@@ -328,13 +767,102 @@ impl Pool for OsmosisPool {
     }
 }
 
+/// Returns the type of the pool encoded in `pool`, see
+/// [`OsmosisPool::query_pool_type`]
+fn pool_type_from_any(pool: Any) -> StdResult<PoolType> {
+    Ok(match pool.type_url.as_str() {
+        BalancerPool::TYPE_URL => {
+            let pool = BalancerPool::try_from(Binary::from(pool.value))?;
+            let mut weights = pool.pool_assets.iter().map(|asset| &asset.weight);
+            let first = weights.next();
+            if weights.all(|weight| Some(weight) == first) {
+                PoolType::ConstantProduct
+            } else {
+                PoolType::Weighted
+            }
+        }
+        StableswapPool::TYPE_URL => PoolType::StableSwap,
+        ClPool::TYPE_URL => PoolType::Concentrated,
+        CosmWasmPool::TYPE_URL => PoolType::Custom("cosmwasm".to_string()),
+        type_url => PoolType::Custom(type_url.to_string()),
+    })
+}
+
+/// Normalized reserves of a pool paired with their target weights
+type ImbalanceTargets = Vec<((AssetInfo, Decimal256), Decimal256)>;
+
+/// Returns the reserves of `pool` normalized for [`compute_imbalance`] paired
+/// with their target weights. Balancer reserves are valued with the prices of
+/// `oracle`, which is required for them. Stableswap reserves are divided by
+/// their scaling factors and have equal targets.
+fn imbalance_targets(
+    pool: &PoolSnapshot,
+    oracle: Option<(Deps, &Env, &dyn Oracle)>,
+) -> Result<ImbalanceTargets, CwDexError> {
+    let mut targets = vec![];
+    match pool {
+        PoolSnapshot::Balancer(pool) => {
+            let (deps, env, oracle) = oracle.ok_or_else(|| {
+                StdError::generic_err(
+                    "the reserves of a balancer pool must be valued with an oracle to compute its \
+                     imbalance",
+                )
+            })?;
+            for asset in &pool.pool_assets {
+                let info = AssetInfo::native(&asset.token.denom);
+                let price = Decimal256::from(oracle.price(deps, env, &info)?);
+                targets.push((
+                    (
+                        info,
+                        Decimal256::from_ratio(asset.token.amount, 1u128).checked_mul(price)?,
+                    ),
+                    Decimal256::from_ratio(asset.weight, 1u128),
+                ));
+            }
+        }
+        PoolSnapshot::Stableswap(pool) => {
+            if pool.pool_liquidity.len() != pool.scaling_factors.len()
+                || pool.scaling_factors.contains(&0)
+            {
+                return Err(StdError::generic_err(
+                    "there must be a non-zero scaling factor for each reserve",
+                )
+                .into());
+            }
+            for (coin, factor) in pool.pool_liquidity.iter().zip(&pool.scaling_factors) {
+                targets.push((
+                    (
+                        AssetInfo::native(&coin.denom),
+                        Decimal256::from_ratio(coin.amount, *factor),
+                    ),
+                    Decimal256::one(),
+                ));
+            }
+        }
+    }
+    Ok(targets)
+}
+
 #[cfg(test)]
 mod tests {
     use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{coin, Addr, Coin, Decimal, Decimal256, SignedDecimal, Uint128};
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::Pool as StableswapPool;
+    use osmosis_std::types::osmosis::gamm::v1beta1::{
+        MsgJoinPool, MsgJoinSwapExternAmountIn, Pool as BalancerPool, PoolAsset,
+    };
 
+    use cw_dex::metrics::compute_imbalance;
+    use cw_dex::oracle::FixedPriceOracle;
     use cw_dex::traits::Pool;
+    use cw_dex::{CwDexError, PoolType};
 
-    use super::OsmosisPool;
+    use super::{imbalance_targets, pool_type_from_any, ClPool, CosmWasmPool, OsmosisPool};
+    use crate::estimate::{
+        BalancerPoolAsset, BalancerPoolSnapshot, PoolSnapshot, StableswapPoolSnapshot,
+    };
 
     #[test]
     fn test_lp_token() {
@@ -347,4 +875,154 @@ mod tests {
             AssetInfo::Cw20(_) => panic!("Unexpected cw20 token"),
         }
     }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(OsmosisPool::unchecked(3).to_string(), "osmosis:pool/3");
+    }
+
+    fn proto_coin(denom: &str, amount: u128) -> ProtoCoin {
+        ProtoCoin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_imbalance_targets() {
+        // The stableswap reserves are 2 and 3 scaled units, a share of 40%
+        // instead of 50% for uatom
+        let stableswap = |scaling_factors| -> PoolSnapshot {
+            StableswapPoolSnapshot {
+                pool_liquidity: vec![coin(200, "uatom"), coin(3_000, "ustatom")],
+                scaling_factors,
+                total_shares: Uint128::new(100),
+                swap_fee: Decimal256::zero(),
+                exit_fee: Decimal256::zero(),
+            }
+            .into()
+        };
+        let (reserves, weights): (Vec<_>, Vec<_>) =
+            imbalance_targets(&stableswap(vec![100, 1_000]), None)
+                .unwrap()
+                .into_iter()
+                .unzip();
+        assert_eq!(
+            compute_imbalance(&reserves, &weights).unwrap(),
+            vec![
+                (AssetInfo::native("uatom"), SignedDecimal::percent(-20)),
+                (AssetInfo::native("ustatom"), SignedDecimal::percent(20)),
+            ]
+        );
+
+        assert!(imbalance_targets(&stableswap(vec![100, 0]), None).is_err());
+
+        let pool: PoolSnapshot = BalancerPoolSnapshot {
+            pool_assets: vec![
+                BalancerPoolAsset {
+                    token: coin(100, "uatom"),
+                    weight: Uint128::new(2),
+                },
+                BalancerPoolAsset {
+                    token: coin(50, "uosmo"),
+                    weight: Uint128::new(1),
+                },
+            ],
+            total_shares: Uint128::new(100),
+            swap_fee: Decimal256::zero(),
+            exit_fee: Decimal256::zero(),
+        }
+        .into();
+        let err = imbalance_targets(&pool, None).unwrap_err();
+        assert!(err.to_string().contains("must be valued with an oracle"));
+
+        // Valued at 200 and 50 uosmo, a share of 80% instead of 2/3 for uatom
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let oracle = FixedPriceOracle {
+            quote: AssetInfo::native("uosmo"),
+            prices: vec![(AssetInfo::native("uatom"), Decimal::percent(200))],
+        };
+        let (reserves, weights): (Vec<_>, Vec<_>) =
+            imbalance_targets(&pool, Some((deps.as_ref(), &env, &oracle)))
+                .unwrap()
+                .into_iter()
+                .unzip();
+        assert_eq!(
+            compute_imbalance(&reserves, &weights).unwrap(),
+            vec![
+                (AssetInfo::native("uatom"), SignedDecimal::percent(20)),
+                (AssetInfo::native("uosmo"), SignedDecimal::percent(-40)),
+            ]
+        );
+
+        let oracle = FixedPriceOracle {
+            quote: AssetInfo::native("uosmo"),
+            prices: vec![],
+        };
+        assert_eq!(
+            imbalance_targets(&pool, Some((deps.as_ref(), &env, &oracle))).unwrap_err(),
+            CwDexError::PriceUnavailable {
+                asset: AssetInfo::native("uatom"),
+                reason: "no fixed price".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_pool_type_from_any() {
+        let balancer = |weights: [&str; 2]| BalancerPool {
+            pool_assets: weights
+                .iter()
+                .map(|weight| PoolAsset {
+                    token: Some(proto_coin("uatom", 100)),
+                    weight: weight.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        for (pool, pool_type) in [
+            (balancer(["1", "1"]).to_any(), PoolType::ConstantProduct),
+            (balancer(["2", "1"]).to_any(), PoolType::Weighted),
+            (StableswapPool::default().to_any(), PoolType::StableSwap),
+            (ClPool::default().to_any(), PoolType::Concentrated),
+            (
+                CosmWasmPool::default().to_any(),
+                PoolType::Custom("cosmwasm".to_string()),
+            ),
+        ] {
+            assert_eq!(pool_type_from_any(pool).unwrap(), pool_type);
+        }
+    }
+
+    #[test]
+    fn test_build_join_msg() {
+        let pool = OsmosisPool::unchecked(1);
+        let sender = Addr::unchecked("sender");
+
+        let msg = pool.build_join_msg(&sender, &[Coin::new(100, "uatom")], Uint128::new(10));
+        assert_eq!(
+            msg,
+            MsgJoinSwapExternAmountIn {
+                sender: "sender".to_string(),
+                pool_id: 1,
+                share_out_min_amount: "10".to_string(),
+                token_in: Some(proto_coin("uatom", 100)),
+            }
+            .into()
+        );
+
+        let assets = [Coin::new(100, "uatom"), Coin::new(200, "uosmo")];
+        let msg = pool.build_join_msg(&sender, &assets, Uint128::new(10));
+        assert_eq!(
+            msg,
+            MsgJoinPool {
+                sender: "sender".to_string(),
+                pool_id: 1,
+                share_out_amount: "10".to_string(),
+                token_in_maxs: vec![proto_coin("uatom", 100), proto_coin("uosmo", 200)],
+            }
+            .into()
+        );
+    }
 }
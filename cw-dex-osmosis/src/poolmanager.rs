@@ -0,0 +1,78 @@
+//! Queries of the parameters of the Osmosis poolmanager module, e.g. for
+//! contracts that create pools and need to pay the pool creation fee, or that
+//! account for the taker fee charged on swaps.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, Decimal, QuerierWrapper, StdError, StdResult, Uint128};
+use cw_dex::conv::{parse_coin, parse_decimal};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::PoolmanagerQuerier;
+
+/// The parameters of the Osmosis poolmanager module relevant for creating pools
+/// and swapping.
+#[cw_serde]
+pub struct PoolmanagerParams {
+    /// The fee paid by the creator of a pool
+    pub pool_creation_fee: Vec<Coin>,
+    /// The taker fee charged on swaps of pairs without a custom taker fee, see
+    /// [`query_taker_fee`] for the taker fee of a specific pair
+    pub default_taker_fee: Decimal,
+}
+
+/// Queries the parameters of the poolmanager module.
+pub fn query_poolmanager_params(querier: &QuerierWrapper) -> StdResult<PoolmanagerParams> {
+    let params = PoolmanagerQuerier::new(querier)
+        .params()?
+        .params
+        .ok_or_else(|| StdError::generic_err("osmosis error: poolmanager has no params"))?;
+    let pool_creation_fee = params
+        .pool_creation_fee
+        .into_iter()
+        .map(|coin| parse_coin(&coin.denom, &coin.amount, "pool_creation_fee"))
+        .collect::<StdResult<_>>()?;
+    let default_taker_fee = match params.taker_fee_params {
+        Some(taker_fee_params) => {
+            parse_decimal(&taker_fee_params.default_taker_fee, "default_taker_fee")?
+        }
+        None => Decimal::zero(),
+    };
+    Ok(PoolmanagerParams {
+        pool_creation_fee,
+        default_taker_fee,
+    })
+}
+
+/// Queries the taker fee charged on swaps between `denom_0` and `denom_1`,
+/// which is the custom taker fee of the pair if governance set one, and the
+/// default taker fee otherwise. The order of the denoms does not matter.
+pub fn query_taker_fee(
+    querier: &QuerierWrapper,
+    denom_0: &str,
+    denom_1: &str,
+) -> StdResult<Decimal> {
+    let res = PoolmanagerQuerier::new(querier)
+        .trading_pair_taker_fee(denom_0.to_string(), denom_1.to_string())?;
+    parse_decimal(&res.taker_fee, "taker_fee")
+}
+
+/// Returns the amount of the offer asset of a swap of `amount` that is swapped
+/// in the pool after the poolmanager deducts `taker_fee`, rounded down like on
+/// Osmosis.
+pub fn deduct_taker_fee(amount: Uint128, taker_fee: Decimal) -> Uint128 {
+    amount.mul_floor(Decimal::one() - taker_fee.min(Decimal::one()))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{Decimal, Uint128};
+    use test_case::test_case;
+
+    use super::deduct_taker_fee;
+
+    #[test_case(1_000_000, "0" => 1_000_000; "no taker fee")]
+    #[test_case(1_000_000, "0.0015" => 998_500; "default taker fee")]
+    #[test_case(999, "0.0015" => 997; "rounded down")]
+    #[test_case(1_000, "1" => 0; "whole amount")]
+    fn test_deduct_taker_fee(amount: u128, taker_fee: &str) -> u128 {
+        deduct_taker_fee(Uint128::new(amount), taker_fee.parse::<Decimal>().unwrap()).u128()
+    }
+}
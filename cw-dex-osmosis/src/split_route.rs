@@ -0,0 +1,183 @@
+//! Swaps of the same pair of assets split across several Osmosis pools with
+//! the split route swap of the poolmanager module, which has lower slippage
+//! for large trades than swapping the whole amount in one pool.
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use apollo_utils::assets::{assert_native_asset_info, assert_native_coin};
+use cosmwasm_std::{Decimal, Deps, Env, Event, Response, StdError, StdResult, Uint128};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSplitRouteSwapExactAmountIn, SwapAmountInRoute, SwapAmountInSplitRoute,
+};
+
+use cw_dex::traits::Pool;
+use cw_dex::CwDexError;
+
+use crate::OsmosisPool;
+
+/// Splits `offer_amount` across `routes` of pool IDs and weights, in
+/// proportion to the weights. The weights need not sum to one. The rounding
+/// remainder is added to the first route, and routes receiving nothing are
+/// left out.
+///
+/// Returns an error if `routes` is empty, contains a pool twice, or if any
+/// weight is zero.
+pub fn split_offer_amount(
+    routes: &[(u64, Decimal)],
+    offer_amount: Uint128,
+) -> Result<Vec<(u64, Uint128)>, CwDexError> {
+    if routes.is_empty() {
+        return Err(StdError::generic_err("osmosis error: no split routes given").into());
+    }
+    for (i, (pool_id, weight)) in routes.iter().enumerate() {
+        if weight.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: weight of split route through pool {pool_id} is zero"
+            ))
+            .into());
+        }
+        if routes[..i].iter().any(|(id, _)| id == pool_id) {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: pool {pool_id} is in more than one split route"
+            ))
+            .into());
+        }
+    }
+
+    let total_weight = routes
+        .iter()
+        .try_fold(Decimal::zero(), |total, (_, weight)| {
+            total.checked_add(*weight)
+        })?;
+    let mut amounts: Vec<(u64, Uint128)> = routes
+        .iter()
+        .map(|(pool_id, weight)| {
+            (
+                *pool_id,
+                offer_amount.multiply_ratio(weight.atomics(), total_weight.atomics()),
+            )
+        })
+        .collect();
+
+    let split: Uint128 = amounts.iter().map(|(_, amount)| *amount).sum();
+    amounts[0].1 += offer_amount - split;
+
+    Ok(amounts
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .collect())
+}
+
+/// Swaps `offer_asset` for `ask_asset_info` split across `routes` of pool IDs
+/// and weights, see [`split_offer_amount`]. Each route swaps directly in its
+/// pool. `min_out` is the minimum of the total return of all routes.
+///
+/// Returns an error if any of the pools does not contain both assets.
+pub fn swap_split_routes(
+    deps: Deps,
+    env: &Env,
+    routes: Vec<(u64, Decimal)>,
+    offer_asset: Asset,
+    ask_asset_info: AssetInfo,
+    min_out: Uint128,
+) -> Result<Response, CwDexError> {
+    let offer = assert_native_coin(&offer_asset)?;
+    let ask_denom = assert_native_asset_info(&ask_asset_info)?;
+    for (pool_id, _) in &routes {
+        OsmosisPool::unchecked(*pool_id).validate_swap_assets(
+            deps,
+            &offer_asset.info,
+            &ask_asset_info,
+        )?;
+    }
+    let amounts = split_offer_amount(&routes, offer.amount)?;
+
+    // Min out must be greater than 0 for osmosis.
+    let min_out = if min_out.is_zero() {
+        Uint128::one()
+    } else {
+        min_out
+    };
+
+    let swap_msg = MsgSplitRouteSwapExactAmountIn {
+        sender: env.contract.address.to_string(),
+        routes: amounts
+            .iter()
+            .map(|(pool_id, amount)| SwapAmountInSplitRoute {
+                pools: vec![SwapAmountInRoute {
+                    pool_id: *pool_id,
+                    token_out_denom: ask_denom.clone(),
+                }],
+                token_in_amount: amount.to_string(),
+            })
+            .collect(),
+        token_in_denom: offer.denom.clone(),
+        token_out_min_amount: min_out.to_string(),
+    };
+
+    let event = Event::new("apollo/cw-dex/swap_split_routes")
+        .add_attribute("type", "osmosis")
+        .add_attribute("offer", offer.to_string())
+        .add_attribute("ask", ask_denom)
+        .add_attribute(
+            "routes",
+            amounts
+                .iter()
+                .map(|(pool_id, amount)| format!("{pool_id}:{amount}"))
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+        .add_attribute("token_out_min_amount", min_out);
+
+    Ok(Response::new().add_message(swap_msg).add_event(event))
+}
+
+/// Simulates [`swap_split_routes`], returning the total amount of
+/// `ask_asset_info` received from all routes. Each route is simulated with
+/// [`Pool::simulate_swap`] of its pool.
+pub fn simulate_swap_split_routes(
+    deps: Deps,
+    routes: &[(u64, Decimal)],
+    offer_asset: Asset,
+    ask_asset_info: AssetInfo,
+) -> StdResult<Uint128> {
+    let amounts = split_offer_amount(routes, offer_asset.amount)?;
+    amounts
+        .into_iter()
+        .try_fold(Uint128::zero(), |total, (pool_id, amount)| {
+            let out = OsmosisPool::unchecked(pool_id).simulate_swap(
+                deps,
+                Asset::new(offer_asset.info.clone(), amount),
+                ask_asset_info.clone(),
+            )?;
+            Ok(total.checked_add(out)?)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{Decimal, Uint128};
+    use test_case::test_case;
+
+    use super::split_offer_amount;
+
+    #[test_case(vec![(1, Decimal::percent(50)), (2, Decimal::percent(50))], 1001 => vec![(1, 501), (2, 500)]; "even split with remainder")]
+    #[test_case(vec![(1, Decimal::one()), (2, Decimal::percent(300))], 1000 => vec![(1, 250), (2, 750)]; "weights not summing to one")]
+    #[test_case(vec![(1, Decimal::one()), (2, Decimal::permille(1))], 10 => vec![(1, 10)]; "route receiving nothing")]
+    fn test_split_offer_amount(
+        routes: Vec<(u64, Decimal)>,
+        offer_amount: u128,
+    ) -> Vec<(u64, u128)> {
+        split_offer_amount(&routes, Uint128::new(offer_amount))
+            .unwrap()
+            .into_iter()
+            .map(|(pool_id, amount)| (pool_id, amount.u128()))
+            .collect()
+    }
+
+    #[test_case(vec![]; "no routes")]
+    #[test_case(vec![(1, Decimal::one()), (2, Decimal::zero())]; "zero weight")]
+    #[test_case(vec![(1, Decimal::one()), (1, Decimal::one())]; "duplicate pool")]
+    fn test_split_offer_amount_invalid(routes: Vec<(u64, Decimal)>) {
+        assert!(split_offer_amount(&routes, Uint128::new(1000)).is_err());
+    }
+}
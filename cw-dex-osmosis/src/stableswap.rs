@@ -0,0 +1,235 @@
+//! Helpers for contracts that create Osmosis stableswap pools or control the
+//! scaling factors of one, e.g. to track the redemption rate of a liquid
+//! staking derivative.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, Env, Event, Response, StdError};
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::{
+    MsgCreateStableswapPool, MsgStableSwapAdjustScalingFactors, PoolParams,
+};
+
+use cw_dex::CwDexError;
+
+use crate::OsmosisPool;
+
+/// Helper for managing an Osmosis stableswap pool whose scaling factor
+/// controller is the contract.
+#[cw_serde]
+#[derive(Copy)]
+pub struct OsmosisStableswapAdmin {
+    /// The pool id of the stableswap pool
+    pub pool_id: u64,
+}
+
+impl OsmosisStableswapAdmin {
+    /// Creates a new `OsmosisStableswapAdmin` for the pool with `pool_id`.
+    pub fn new(pool_id: u64) -> Self {
+        Self { pool_id }
+    }
+
+    /// Returns the [`OsmosisPool`] to interact with the pool through the
+    /// `Pool` trait.
+    pub fn pool(&self) -> OsmosisPool {
+        OsmosisPool::unchecked(self.pool_id)
+    }
+
+    /// Creates a stableswap pool with the contract as the sender. The pool id
+    /// is not known until the message is executed, and can be read from the
+    /// `pool_created` event or the `MsgCreateStableswapPoolResponse`.
+    ///
+    /// The pool creation fee of the poolmanager module is paid from the
+    /// balance of the contract, in addition to the initial liquidity.
+    ///
+    /// Arguments:
+    /// - `initial_liquidity`: The coins to create the pool with, each paired
+    ///   with its scaling factor. Must be held by the contract.
+    /// - `swap_fee`: The fee charged on swaps.
+    /// - `scaling_factor_controller`: The address allowed to update the scaling
+    ///   factors, or `None` to fix them. Pass the contract address to manage
+    ///   the pool with [`OsmosisStableswapAdmin::update_scaling_factors`].
+    pub fn create_pool(
+        env: &Env,
+        mut initial_liquidity: Vec<(Coin, u64)>,
+        swap_fee: Decimal,
+        scaling_factor_controller: Option<&Addr>,
+    ) -> Result<Response, CwDexError> {
+        if initial_liquidity.len() < 2 {
+            return Err(StdError::generic_err(
+                "osmosis error: stableswap pool needs at least two assets",
+            )
+            .into());
+        }
+        if initial_liquidity.iter().any(|(_, factor)| *factor == 0) {
+            return Err(
+                StdError::generic_err("osmosis error: scaling factor must be positive").into(),
+            );
+        }
+        // The module requires the initial liquidity sorted by denom, with the
+        // scaling factors in the same order
+        initial_liquidity.sort_by(|(a, _), (b, _)| a.denom.cmp(&b.denom));
+        let (coins, scaling_factors): (Vec<Coin>, Vec<u64>) = initial_liquidity.into_iter().unzip();
+
+        let create_pool_msg = MsgCreateStableswapPool {
+            sender: env.contract.address.to_string(),
+            pool_params: Some(PoolParams {
+                swap_fee: swap_fee.atomics().to_string(),
+                exit_fee: "0".to_string(),
+            }),
+            initial_pool_liquidity: coins.iter().cloned().map(Into::into).collect(),
+            scaling_factors: scaling_factors.clone(),
+            future_pool_governor: String::new(),
+            scaling_factor_controller: scaling_factor_controller
+                .map(Addr::to_string)
+                .unwrap_or_default(),
+        };
+
+        let event = Event::new("apollo/cw-dex/create_pool")
+            .add_attribute("type", "osmosis_stableswap")
+            .add_attribute(
+                "initial_liquidity",
+                format_scaling_factors(&coins, &scaling_factors),
+            )
+            .add_attribute("swap_fee", swap_fee.to_string());
+
+        Ok(Response::new()
+            .add_message(create_pool_msg)
+            .add_event(event))
+    }
+
+    /// Updates the scaling factors of the pool. Fails if the contract is not
+    /// the scaling factor controller of the pool.
+    ///
+    /// Arguments:
+    /// - `scaling_factors`: The new scaling factor of each denom of the pool,
+    ///   in any order. Must contain every denom of the pool exactly once.
+    pub fn update_scaling_factors(
+        &self,
+        deps: Deps,
+        env: &Env,
+        scaling_factors: Vec<(String, u64)>,
+    ) -> Result<Response, CwDexError> {
+        let pool = self.pool();
+        if pool
+            .query_scaling_factor_controller(&deps.querier)?
+            .as_ref()
+            != Some(&env.contract.address)
+        {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: contract is not the scaling factor controller of pool {}",
+                self.pool_id
+            ))
+            .into());
+        }
+
+        // Order the new factors as the denoms of the pool
+        let current = pool.query_scaling_factors(&deps.querier)?;
+        if scaling_factors.len() != current.len() {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: expected {} scaling factors, got {}",
+                current.len(),
+                scaling_factors.len()
+            ))
+            .into());
+        }
+        let ordered = current
+            .iter()
+            .map(|(denom, _)| {
+                let factor = scaling_factors
+                    .iter()
+                    .find(|(d, _)| d == denom)
+                    .map(|(_, factor)| *factor)
+                    .ok_or_else(|| {
+                        StdError::generic_err(format!(
+                            "osmosis error: missing scaling factor for {denom}"
+                        ))
+                    })?;
+                if factor == 0 {
+                    return Err(StdError::generic_err(
+                        "osmosis error: scaling factor must be positive",
+                    ));
+                }
+                Ok(factor)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let adjust_msg = MsgStableSwapAdjustScalingFactors {
+            sender: env.contract.address.to_string(),
+            pool_id: self.pool_id,
+            scaling_factors: ordered.clone(),
+        };
+
+        let event = Event::new("apollo/cw-dex/update_scaling_factors")
+            .add_attribute("type", "osmosis_stableswap")
+            .add_attribute("pool_id", self.pool_id.to_string())
+            .add_attribute(
+                "scaling_factors",
+                current
+                    .iter()
+                    .zip(ordered)
+                    .map(|((denom, _), factor)| format!("{denom}:{factor}"))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+
+        Ok(Response::new().add_message(adjust_msg).add_event(event))
+    }
+}
+
+/// Formats coins paired with their scaling factors as `amountdenom:factor`,
+/// separated by commas.
+fn format_scaling_factors(coins: &[Coin], scaling_factors: &[u64]) -> String {
+    coins
+        .iter()
+        .zip(scaling_factors)
+        .map(|(coin, factor)| format!("{coin}:{factor}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{coin, CosmosMsg, Decimal};
+    use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::MsgCreateStableswapPool;
+
+    use super::OsmosisStableswapAdmin;
+
+    #[test]
+    fn test_create_pool() {
+        let env = mock_env();
+        let res = OsmosisStableswapAdmin::create_pool(
+            &env,
+            vec![(coin(1000, "uosmo"), 1), (coin(1000, "stuosmo"), 2)],
+            Decimal::permille(3),
+            Some(&env.contract.address),
+        )
+        .unwrap();
+
+        let msg = match &res.messages[0].msg {
+            CosmosMsg::Stargate { type_url, value } => {
+                assert_eq!(type_url, MsgCreateStableswapPool::TYPE_URL);
+                MsgCreateStableswapPool::try_from(value.clone()).unwrap()
+            }
+            _ => panic!("Unexpected message"),
+        };
+        // Sorted by denom, keeping the scaling factors paired
+        let denoms: Vec<_> = msg
+            .initial_pool_liquidity
+            .iter()
+            .map(|coin| coin.denom.as_str())
+            .collect();
+        assert_eq!(denoms, vec!["stuosmo", "uosmo"]);
+        assert_eq!(msg.scaling_factors, vec![2, 1]);
+        assert_eq!(msg.pool_params.unwrap().swap_fee, "3000000000000000");
+        assert_eq!(msg.scaling_factor_controller, env.contract.address.as_str());
+
+        // Zero scaling factor
+        assert!(OsmosisStableswapAdmin::create_pool(
+            &env,
+            vec![(coin(1000, "uosmo"), 0), (coin(1000, "stuosmo"), 1)],
+            Decimal::permille(3),
+            None,
+        )
+        .is_err());
+    }
+}
@@ -1,22 +1,33 @@
 //! Staking/rewards traits implementations for Osmosis
 
-use apollo_cw_asset::AssetList;
+use apollo_cw_asset::{AssetInfo, AssetList};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
     Addr, Coin, Deps, Env, Event, QuerierWrapper, ReplyOn, Response, StdError, StdResult, SubMsg,
-    Uint128,
+    Timestamp, Uint128,
+};
+use cw_utils::{Duration as CwDuration, Expiration};
+use osmosis_std::types::osmosis::incentives::{MsgAddToGauge, MsgCreateGauge};
+use osmosis_std::types::osmosis::lockup::{
+    LockQueryType, LockupQuerier, MsgBeginUnlocking, MsgExtendLockup, MsgForceUnlock,
+    MsgLockTokens, PeriodLock, QueryCondition,
 };
-use cw_utils::Duration as CwDuration;
-use osmosis_std::types::osmosis::lockup::{MsgBeginUnlocking, MsgForceUnlock, MsgLockTokens};
 use osmosis_std::types::osmosis::superfluid::{
     MsgLockAndSuperfluidDelegate, MsgSuperfluidUnbondLock, MsgSuperfluidUndelegate,
+    SuperfluidQuerier,
 };
+use std::fmt;
 use std::time::Duration;
 
-use cw_dex::traits::{ForceUnlock, LockedStaking, Rewards, Stake, Unlock};
+use cw_dex::conv::parse_uint128;
+use cw_dex::traits::{
+    ForceUnlock, ForceUnlockRestriction, LockedStaking, MigratePosition, QueryPosition, Rewards,
+    SimulateStaking, Stake, Unlock, UnlockingPosition,
+};
 use cw_dex::CwDexError;
 
-use super::helpers::ToProtobufDuration;
+use super::helpers::{ToProtobufDuration, ToProtobufTimestamp};
+use super::params::query_lockup_params;
 
 /// Implementation of locked staking on osmosis. Using the Staking trait.
 /// `lockup_duration` is the duration of the lockup period in nano seconds.
@@ -29,6 +40,85 @@ pub struct OsmosisStaking {
     pub lock_id: Option<u64>,
     /// Denomination of the associated LP token
     pub lp_token_denom: String,
+    /// The reply IDs of the lock and unlock submessages
+    #[serde(default)]
+    pub reply_ids: OsmosisReplyIds,
+}
+
+/// The reply IDs of the submessages sent by [`OsmosisStaking`] and
+/// [`OsmosisSuperfluidStaking`]. Defaults to [`OSMOSIS_LOCK_TOKENS_REPLY_ID`]
+/// and [`OSMOSIS_UNLOCK_TOKENS_REPLY_ID`].
+#[cw_serde]
+#[derive(Copy)]
+pub struct OsmosisReplyIds {
+    /// Reply ID of the message locking tokens. The reply data contains the ID
+    /// of the new lock.
+    pub lock_tokens: u64,
+    /// Reply ID of the message beginning to unlock tokens. Not used by
+    /// [`OsmosisSuperfluidStaking`], which does not reply on unlock.
+    pub unlock_tokens: u64,
+}
+
+impl Default for OsmosisReplyIds {
+    fn default() -> Self {
+        Self {
+            lock_tokens: OSMOSIS_LOCK_TOKENS_REPLY_ID,
+            unlock_tokens: OSMOSIS_UNLOCK_TOKENS_REPLY_ID,
+        }
+    }
+}
+
+impl OsmosisReplyIds {
+    /// Returns the reply IDs as a list, e.g. to check them for collisions with
+    /// [`cw_dex::reply::assert_unique_reply_ids`].
+    pub fn to_vec(&self) -> Vec<u64> {
+        vec![self.lock_tokens, self.unlock_tokens]
+    }
+}
+
+/// The lockup durations in seconds allowed by the Osmosis lockup module: 1 day,
+/// 1 week and 2 weeks.
+const ALLOWED_LOCKUP_DURATIONS: [u64; 3] = [86400, 604800, 1209600];
+
+/// How the rewards of an Osmosis staking position are paid out.
+///
+/// Osmosis only has claim messages for the incentives and spread rewards of
+/// concentrated liquidity positions (`MsgCollectIncentives` and
+/// `MsgCollectSpreadRewards`). The gamm share lockups of [`OsmosisStaking`]
+/// and [`OsmosisSuperfluidStaking`] are paid out by the incentives and
+/// superfluid modules without a claim, so for them
+/// [`Rewards::claim_rewards`] sends no messages.
+#[cw_serde]
+#[derive(Copy)]
+#[non_exhaustive]
+pub enum OsmosisRewardsMode {
+    /// Rewards are sent to the lockup owner by the chain at the start of each
+    /// distribution epoch, see [`crate::query_next_epoch_start`]. Nothing
+    /// needs to be claimed, and pending rewards can not be queried.
+    EpochDistributed,
+}
+
+impl fmt::Display for OsmosisRewardsMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EpochDistributed => write!(f, "epoch_distributed"),
+        }
+    }
+}
+
+impl fmt::Display for OsmosisStaking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "osmosis:lockup {}/{}s",
+            self.lp_token_denom,
+            self.lockup_duration.as_secs()
+        )?;
+        if let Some(lock_id) = self.lock_id {
+            write!(f, "#{}", lock_id)?;
+        }
+        Ok(())
+    }
 }
 
 impl OsmosisStaking {
@@ -46,7 +136,7 @@ impl OsmosisStaking {
         lock_id: Option<u64>,
         lp_token_denom: String,
     ) -> StdResult<Self> {
-        if !([86400u64, 604800u64, 1209600u64].contains(&lockup_duration)) {
+        if !ALLOWED_LOCKUP_DURATIONS.contains(&lockup_duration) {
             return Err(StdError::generic_err(
                 "osmosis error: invalid lockup duration",
             ));
@@ -55,8 +145,204 @@ impl OsmosisStaking {
             lockup_duration: Duration::from_secs(lockup_duration),
             lock_id,
             lp_token_denom,
+            reply_ids: OsmosisReplyIds::default(),
         })
     }
+
+    /// Returns the staking with its submessages sent with `reply_ids`
+    /// instead of the default reply IDs.
+    pub fn with_reply_ids(mut self, reply_ids: OsmosisReplyIds) -> Self {
+        self.reply_ids = reply_ids;
+        self
+    }
+
+    /// Creates a new OsmosisStaking instance from a [`CwDuration`].
+    ///
+    /// Returns an error if `lockup_duration` is a [`CwDuration::Height`], as
+    /// Osmosis lockups are time based, or if it is not one of the allowed
+    /// values of [`OsmosisStaking::new`].
+    pub fn from_duration(
+        lockup_duration: CwDuration,
+        lock_id: Option<u64>,
+        lp_token_denom: String,
+    ) -> StdResult<Self> {
+        match lockup_duration {
+            CwDuration::Time(seconds) => Self::new(seconds, lock_id, lp_token_denom),
+            CwDuration::Height(_) => Err(StdError::generic_err(
+                "osmosis error: lockup duration must be time based",
+            )),
+        }
+    }
+
+    /// Returns how the rewards of the lockups are paid out. Lockups of gamm
+    /// shares receive the rewards of the incentives module every epoch.
+    pub fn rewards_mode(&self) -> OsmosisRewardsMode {
+        OsmosisRewardsMode::EpochDistributed
+    }
+}
+
+impl OsmosisStaking {
+    /// Creates a gauge that distributes `coins` to the lockups of the LP token
+    /// with the lockup duration of this instance. The gauge creation fee of
+    /// the incentives module is paid from the balance of the contract.
+    ///
+    /// Arguments:
+    /// - `coins`: The rewards to distribute. Must be held by the contract.
+    /// - `num_epochs_paid_over`: The number of epochs to distribute the rewards
+    ///   over. If `None`, a perpetual gauge is created which distributes all of
+    ///   its coins at the next epoch and can be refilled with
+    ///   [`OsmosisStaking::add_to_gauge`].
+    /// - `start_time`: When to start distributing. If `None`, distribution
+    ///   starts at the next epoch.
+    pub fn create_gauge(
+        &self,
+        env: &Env,
+        coins: Vec<Coin>,
+        num_epochs_paid_over: Option<u64>,
+        start_time: Option<Timestamp>,
+    ) -> Result<Response, CwDexError> {
+        let create_gauge_msg = MsgCreateGauge {
+            is_perpetual: num_epochs_paid_over.is_none(),
+            owner: env.contract.address.to_string(),
+            distribute_to: Some(QueryCondition {
+                lock_query_type: LockQueryType::ByDuration.into(),
+                denom: self.lp_token_denom.clone(),
+                duration: Some(self.lockup_duration.to_protobuf_duration()),
+                timestamp: None,
+            }),
+            coins: coins.iter().cloned().map(Into::into).collect(),
+            start_time: start_time.map(|t| t.to_protobuf_timestamp()),
+            num_epochs_paid_over: num_epochs_paid_over.unwrap_or(1),
+            pool_id: 0,
+        };
+
+        let event = Event::new("apollo/cw-dex/create_gauge")
+            .add_attribute("type", "osmosis_staking")
+            .add_attribute("denom", &self.lp_token_denom)
+            .add_attribute(
+                "lockup_duration_secs",
+                self.lockup_duration.as_secs().to_string(),
+            )
+            .add_attribute("coins", format_coins(&coins));
+
+        Ok(Response::new()
+            .add_message(create_gauge_msg)
+            .add_event(event))
+    }
+
+    /// Extends the lockup duration of the lock to `new_duration` seconds
+    /// without unbonding. The lock keeps its ID, so the caller should
+    /// afterwards use an `OsmosisStaking` with the new duration, e.g. created
+    /// with [`OsmosisStaking::new`].
+    ///
+    /// Returns an error if the lock ID is not set, or if `new_duration` is not
+    /// one of the allowed values of [`OsmosisStaking::new`] or not longer than
+    /// the current lockup duration.
+    pub fn extend_lock_duration(
+        &self,
+        env: &Env,
+        new_duration: u64,
+    ) -> Result<Response, CwDexError> {
+        let id = self
+            .lock_id
+            .ok_or_else(|| StdError::generic_err("osmosis error: lock id not set"))?;
+
+        if !ALLOWED_LOCKUP_DURATIONS.contains(&new_duration) {
+            return Err(StdError::generic_err("osmosis error: invalid lockup duration").into());
+        }
+        if new_duration <= self.lockup_duration.as_secs() {
+            return Err(StdError::generic_err(
+                "osmosis error: new lockup duration must be longer than the current one",
+            )
+            .into());
+        }
+
+        let new_duration = Duration::from_secs(new_duration);
+        let extend_lockup_msg = MsgExtendLockup {
+            owner: env.contract.address.to_string(),
+            id,
+            duration: Some(new_duration.to_protobuf_duration()),
+        };
+
+        let event = Event::new("apollo/cw-dex/extend_lock_duration")
+            .add_attribute("type", "osmosis_staking")
+            .add_attribute("lock_id", id.to_string())
+            .add_attribute(
+                "old_lockup_duration_secs",
+                self.lockup_duration.as_secs().to_string(),
+            )
+            .add_attribute(
+                "new_lockup_duration_secs",
+                new_duration.as_secs().to_string(),
+            );
+
+        Ok(Response::new()
+            .add_message(extend_lockup_msg)
+            .add_event(event))
+    }
+
+    /// Force unlocks the whole amount of the lock with ID `lockup_id`, or the
+    /// lock ID of `self` if `None`, as queried from the lockup module. The
+    /// same checks as for [`ForceUnlock::force_unlock`] apply, including the
+    /// force unlock whitelist of the lockup module.
+    pub fn force_unlock_all(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lockup_id: Option<u64>,
+    ) -> Result<Response, CwDexError> {
+        let lock_id = force_unlock_id(lockup_id, self.lock_id)?;
+        let Some(lock) = query_lock_if_exists(&deps.querier, lock_id)? else {
+            return Err(CwDexError::ForceUnlockNotAllowed {
+                lock_id,
+                reason: ForceUnlockRestriction::NotFound,
+            });
+        };
+        let locked = lp_amount_of_lock(&lock, &self.lp_token_denom)?;
+        self.force_unlock(deps, env, Some(lock_id), locked)
+    }
+
+    /// Adds `rewards` to an existing gauge.
+    ///
+    /// Arguments:
+    /// - `gauge_id`: The ID of the gauge to add the rewards to.
+    /// - `rewards`: The rewards to add. Must be held by the contract.
+    pub fn add_to_gauge(
+        &self,
+        env: &Env,
+        gauge_id: u64,
+        rewards: Vec<Coin>,
+    ) -> Result<Response, CwDexError> {
+        let add_to_gauge_msg = MsgAddToGauge {
+            owner: env.contract.address.to_string(),
+            gauge_id,
+            rewards: rewards.iter().cloned().map(Into::into).collect(),
+        };
+
+        let event = Event::new("apollo/cw-dex/add_to_gauge")
+            .add_attribute("type", "osmosis_staking")
+            .add_attribute("gauge_id", gauge_id.to_string())
+            .add_attribute("rewards", format_coins(&rewards));
+
+        Ok(Response::new()
+            .add_message(add_to_gauge_msg)
+            .add_event(event))
+    }
+}
+
+/// Returns `lockup_id`, or `lock_id` if `None`.
+fn force_unlock_id(lockup_id: Option<u64>, lock_id: Option<u64>) -> StdResult<u64> {
+    lockup_id
+        .or(lock_id)
+        .ok_or_else(|| StdError::generic_err("osmosis error: lock id not set"))
+}
+
+fn format_coins(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
 /// Reply ID for locking tokens
@@ -67,8 +353,9 @@ pub const OSMOSIS_UNLOCK_TOKENS_REPLY_ID: u64 = 124;
 impl Rewards for OsmosisStaking {
     fn claim_rewards(&self, _deps: Deps, _env: &Env) -> Result<Response, CwDexError> {
         // Rewards are automatically distributed to stakers every epoch.
-        let event =
-            Event::new("apollo/cw-dex/claim_rewards").add_attribute("type", "osmosis_staking");
+        let event = Event::new("apollo/cw-dex/claim_rewards")
+            .add_attribute("type", "osmosis_staking")
+            .add_attribute("rewards_mode", self.rewards_mode().to_string());
         Ok(Response::new().add_event(event))
     }
 
@@ -85,6 +372,10 @@ impl Rewards for OsmosisStaking {
 }
 
 impl Stake for OsmosisStaking {
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        Some(AssetInfo::native(&self.lp_token_denom))
+    }
+
     fn stake(&self, _deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
         let asset = Coin::new(amount.u128(), self.lp_token_denom.clone());
 
@@ -104,7 +395,7 @@ impl Stake for OsmosisStaking {
 
         Ok(Response::new()
             .add_submessage(SubMsg {
-                id: OSMOSIS_LOCK_TOKENS_REPLY_ID,
+                id: self.reply_ids.lock_tokens,
                 msg: stake_msg.into(),
                 gas_limit: None,
                 reply_on: ReplyOn::Success,
@@ -114,12 +405,17 @@ impl Stake for OsmosisStaking {
 }
 
 impl Unlock for OsmosisStaking {
-    fn unlock(&self, _deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+    /// Begins unlocking `amount` of the lock. Returns
+    /// [`CwDexError::MultiCoinLock`] if the lock contains other coins next to
+    /// the LP token, or [`CwDexError::LpTokenMismatch`] if it only contains
+    /// another coin.
+    fn unlock(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
         let asset = Coin::new(amount.u128(), self.lp_token_denom.clone());
 
         let id = self
             .lock_id
             .ok_or_else(|| StdError::generic_err("osmosis error: lock id not set"))?;
+        lp_amount_of_lock(&query_lock(&deps.querier, id)?, &self.lp_token_denom)?;
 
         let unstake_msg = MsgBeginUnlocking {
             owner: env.contract.address.to_string(),
@@ -138,7 +434,7 @@ impl Unlock for OsmosisStaking {
 
         Ok(Response::new()
             .add_submessage(SubMsg {
-                id: OSMOSIS_UNLOCK_TOKENS_REPLY_ID,
+                id: self.reply_ids.unlock_tokens,
                 msg: unstake_msg.into(),
                 gas_limit: None,
                 reply_on: ReplyOn::Success,
@@ -165,20 +461,64 @@ impl LockedStaking for OsmosisStaking {
     }
 }
 
+impl SimulateStaking for OsmosisStaking {
+    /// Returns the amount of LP tokens in the lock with ID `lock_id`. Returns
+    /// zero if it is not set, the lock does not exist, or the whole lock is
+    /// unlocking, as it is then returned by [`QueryPosition::query_unlocking`].
+    /// `user` is ignored, as the lock is owned by the contract.
+    fn query_staked_amount(
+        &self,
+        querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<Uint128, CwDexError> {
+        query_staked_lock_amount(querier, self.lock_id, &self.lp_token_denom)
+    }
+
+    /// Lockup rewards are distributed at the end of each epoch.
+    fn pays_rewards_on_stake_change(&self) -> bool {
+        false
+    }
+}
+
+impl QueryPosition for OsmosisStaking {
+    /// Returns the locks of `user` of the LP token that are unlocking, with
+    /// any lockup duration.
+    fn query_unlocking(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<Vec<UnlockingPosition>, CwDexError> {
+        query_unlocking_locks(querier, user, &self.lp_token_denom)
+    }
+
+    fn query_lockup_duration(&self, deps: Deps) -> Result<Option<CwDuration>, CwDexError> {
+        self.get_lockup_duration(deps).map(Some)
+    }
+}
+
 impl ForceUnlock for OsmosisStaking {
+    /// Force unlocks `amount` of the lock, which may be less than the locked
+    /// amount. The lock is queried first and the request is checked with
+    /// [`ForceUnlock::can_force_unlock`], so that e.g. unlocking more than is
+    /// locked fails with [`CwDexError::ForceUnlockNotAllowed`] rather than an
+    /// opaque error of the lockup module, and contracts that are not
+    /// whitelisted by the lockup module fail with
+    /// [`ForceUnlockRestriction::NotWhitelisted`]. Locks that contain other
+    /// coins next to the LP token fail with [`CwDexError::MultiCoinLock`] and
+    /// locks of another coin with [`CwDexError::LpTokenMismatch`]. Use
+    /// [`OsmosisStaking::force_unlock_all`] to unlock the whole lock.
     fn force_unlock(
         &self,
-        _deps: Deps,
+        deps: Deps,
         env: &Env,
         lockup_id: Option<u64>,
         amount: Uint128,
     ) -> Result<Response, CwDexError> {
-        let lockup_id = match lockup_id {
-            Some(id) => Ok(id),
-            None => self
-                .lock_id
-                .ok_or_else(|| StdError::generic_err("osmosis error: lock id not set")),
-        }?;
+        let lockup_id = force_unlock_id(lockup_id, self.lock_id)?;
+        if amount.is_zero() {
+            return Err(CwDexError::InvalidZeroAmount {});
+        }
+        self.can_force_unlock(deps, env, Some(lockup_id), amount)?;
 
         let coin_to_unlock = Coin::new(amount.u128(), self.lp_token_denom.clone());
 
@@ -197,6 +537,131 @@ impl ForceUnlock for OsmosisStaking {
             .add_message(force_unlock_msg)
             .add_event(event))
     }
+
+    fn can_force_unlock(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lockup_id: Option<u64>,
+        amount: Uint128,
+    ) -> Result<(), CwDexError> {
+        let lock_id = force_unlock_id(lockup_id, self.lock_id)?;
+        check_force_unlock(deps, env, lock_id, &self.lp_token_denom, amount)
+    }
+}
+
+/// The superfluid delegation status of a lock, see
+/// [`query_superfluid_status`].
+#[cw_serde]
+pub enum SuperfluidLockStatus {
+    /// The locked assets are superfluid delegated to `validator`
+    Delegated {
+        /// The address of the validator
+        validator: String,
+    },
+    /// The locked assets are being superfluid undelegated. The lock can not
+    /// be force unlocked before `until`.
+    Undelegating {
+        /// The end of the undelegation
+        until: Timestamp,
+    },
+    /// The locked assets are not superfluid delegated
+    NotDelegated,
+}
+
+/// Returns the superfluid delegation status of the lock with ID `lock_id`.
+///
+/// Delegated locks are connected to an intermediary account of the superfluid
+/// module. Undelegating removes the connection, but leaves a synthetic lockup
+/// that lives until the end of the unbonding period, during which the lockup
+/// module rejects force unlocking the lock.
+pub fn query_superfluid_status(
+    querier: &QuerierWrapper,
+    env: &Env,
+    lock_id: u64,
+) -> StdResult<SuperfluidLockStatus> {
+    let intermediary_account = SuperfluidQuerier::new(querier)
+        .connected_intermediary_account(lock_id)?
+        .account;
+    if let Some(account) = intermediary_account.filter(|a| !a.val_addr.is_empty()) {
+        return Ok(SuperfluidLockStatus::Delegated {
+            validator: account.val_addr,
+        });
+    }
+
+    // The lockup module returns an empty synthetic lockup if there is none
+    let synthetic_lock = LockupQuerier::new(querier)
+        .synthetic_lockup_by_lockup_id(lock_id)?
+        .synthetic_lock
+        .filter(|l| l.underlying_lock_id != 0);
+    if let Some(end_time) = synthetic_lock.and_then(|l| l.end_time) {
+        let until =
+            Timestamp::from_seconds(end_time.seconds as u64).plus_nanos(end_time.nanos as u64);
+        if until > env.block.time {
+            return Ok(SuperfluidLockStatus::Undelegating { until });
+        }
+    }
+
+    Ok(SuperfluidLockStatus::NotDelegated)
+}
+
+/// Checks whether `amount` of `denom` can be force unlocked from the lock with
+/// ID `lock_id`. The lockup module only accepts `MsgForceUnlock` from the
+/// addresses in its `force_unlock_allowed_addresses` param, so the contract is
+/// checked against those first. The superfluid delegation status is checked
+/// last, so that [`ForceUnlockRestriction::SuperfluidDelegated`] implies that
+/// the lock can be force unlocked once undelegated.
+fn check_force_unlock(
+    deps: Deps,
+    env: &Env,
+    lock_id: u64,
+    denom: &str,
+    amount: Uint128,
+) -> Result<(), CwDexError> {
+    let not_allowed = |reason| CwDexError::ForceUnlockNotAllowed { lock_id, reason };
+
+    if !query_lockup_params(&deps.querier)?.force_unlock_allowed(&env.contract.address) {
+        return Err(not_allowed(ForceUnlockRestriction::NotWhitelisted));
+    }
+
+    let Some(lock) = query_lock_if_exists(&deps.querier, lock_id)? else {
+        return Err(not_allowed(ForceUnlockRestriction::NotFound));
+    };
+    if lock.owner != env.contract.address.as_str() {
+        return Err(not_allowed(ForceUnlockRestriction::NotOwner {
+            owner: lock.owner,
+        }));
+    }
+
+    let locked = lp_amount_of_lock(&lock, denom)?;
+    if amount > locked {
+        return Err(not_allowed(ForceUnlockRestriction::InsufficientLocked {
+            locked,
+            requested: amount,
+        }));
+    }
+
+    // Partially unlocking a lock splits it, which is not possible for locks
+    // that are already unlocking
+    if is_unlocking(&lock) && amount < locked {
+        return Err(not_allowed(ForceUnlockRestriction::PartiallyUnlocking));
+    }
+
+    // Locks with superfluid delegated assets must be unlocked via the
+    // superfluid module
+    match query_superfluid_status(&deps.querier, env, lock_id)? {
+        SuperfluidLockStatus::Delegated { validator } => {
+            Err(not_allowed(ForceUnlockRestriction::SuperfluidDelegated {
+                validator,
+            }))
+        }
+        SuperfluidLockStatus::Undelegating { until } => Err(not_allowed(
+            ForceUnlockRestriction::SuperfluidUndelegating {
+                until: until.seconds(),
+            },
+        )),
+        SuperfluidLockStatus::NotDelegated => Ok(()),
+    }
 }
 
 /// Implementation of superfluid staking for osmosis.
@@ -208,10 +673,28 @@ pub struct OsmosisSuperfluidStaking {
     pub lock_id: Option<u64>,
     /// Denomination of the associated LP token.
     pub lp_token_denom: String,
+    /// The reply IDs of the lock submessage. The unlock message is not sent as
+    /// a submessage.
+    #[serde(default)]
+    pub reply_ids: OsmosisReplyIds,
 }
 
 const TWO_WEEKS_IN_SECS: u64 = 14 * 24 * 60 * 60;
 
+impl fmt::Display for OsmosisSuperfluidStaking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "osmosis:superfluid {}/{}",
+            self.lp_token_denom, self.validator_address
+        )?;
+        if let Some(lock_id) = self.lock_id {
+            write!(f, "#{}", lock_id)?;
+        }
+        Ok(())
+    }
+}
+
 impl OsmosisSuperfluidStaking {
     /// Creates a new instance of `OsmosisSuperfluidStaking`.
     ///
@@ -228,15 +711,31 @@ impl OsmosisSuperfluidStaking {
             validator_address,
             lock_id,
             lp_token_denom,
+            reply_ids: OsmosisReplyIds::default(),
         })
     }
+
+    /// Returns the staking with its lock submessage sent with
+    /// `reply_ids.lock_tokens` instead of the default reply ID.
+    pub fn with_reply_ids(mut self, reply_ids: OsmosisReplyIds) -> Self {
+        self.reply_ids = reply_ids;
+        self
+    }
+
+    /// Returns how the rewards of the lockup are paid out. The staking rewards
+    /// of superfluid delegations are distributed by the superfluid module
+    /// every epoch, like the incentives of the lockup.
+    pub fn rewards_mode(&self) -> OsmosisRewardsMode {
+        OsmosisRewardsMode::EpochDistributed
+    }
 }
 
 impl Rewards for OsmosisSuperfluidStaking {
     fn claim_rewards(&self, _deps: Deps, _env: &Env) -> Result<Response, CwDexError> {
         // Rewards are automatically distributed to stakers every epoch.
         let event = Event::new("apollo/cw-dex/claim_rewards")
-            .add_attribute("type", "osmosis_superfluid_staking");
+            .add_attribute("type", "osmosis_superfluid_staking")
+            .add_attribute("rewards_mode", self.rewards_mode().to_string());
         Ok(Response::new().add_event(event))
     }
 
@@ -253,6 +752,10 @@ impl Rewards for OsmosisSuperfluidStaking {
 }
 
 impl Stake for OsmosisSuperfluidStaking {
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        Some(AssetInfo::native(&self.lp_token_denom))
+    }
+
     fn stake(&self, _deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
         let asset = Coin::new(amount.u128(), self.lp_token_denom.clone());
 
@@ -269,7 +772,7 @@ impl Stake for OsmosisSuperfluidStaking {
 
         Ok(Response::new()
             .add_submessage(SubMsg {
-                id: OSMOSIS_LOCK_TOKENS_REPLY_ID,
+                id: self.reply_ids.lock_tokens,
                 msg: stake_msg.into(),
                 gas_limit: None,
                 reply_on: ReplyOn::Success,
@@ -317,9 +820,813 @@ impl Unlock for OsmosisSuperfluidStaking {
     }
 }
 
+impl ForceUnlock for OsmosisSuperfluidStaking {
+    /// Force unlocks `amount` of the lock, in two steps, as the lockup module
+    /// rejects force unlocking locks with superfluid delegated assets:
+    ///
+    /// 1. If the lock is superfluid delegated, it is undelegated. No assets are
+    ///    unlocked, and the `stage` attribute of the event is `undelegate`.
+    /// 2. Once the undelegation completes, after the unbonding period, `amount`
+    ///    is force unlocked like for [`OsmosisStaking`], with `stage`
+    ///    `force_unlock`.
+    ///
+    /// While the undelegation is in progress this returns
+    /// [`CwDexError::ForceUnlockNotAllowed`] with
+    /// [`ForceUnlockRestriction::SuperfluidUndelegating`]. Other restrictions
+    /// are checked as for [`OsmosisStaking`] before either step, so a contract
+    /// that is not whitelisted by the lockup module fails with
+    /// [`ForceUnlockRestriction::NotWhitelisted`] instead of undelegating a
+    /// lock that it can not force unlock.
+    fn force_unlock(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lockup_id: Option<u64>,
+        amount: Uint128,
+    ) -> Result<Response, CwDexError> {
+        let lock_id = force_unlock_id(lockup_id, self.lock_id)?;
+        if amount.is_zero() {
+            return Err(CwDexError::InvalidZeroAmount {});
+        }
+
+        let event = Event::new("apollo/cw-dex/force-unlock")
+            .add_attribute("type", "osmosis_superfluid_staking")
+            .add_attribute("lockup_id", lock_id.to_string());
+
+        match self.can_force_unlock(deps, env, Some(lock_id), amount) {
+            Err(CwDexError::ForceUnlockNotAllowed {
+                reason: ForceUnlockRestriction::SuperfluidDelegated { validator },
+                ..
+            }) => {
+                let undelegate_msg = MsgSuperfluidUndelegate {
+                    sender: env.contract.address.to_string(),
+                    lock_id,
+                };
+                let event = event
+                    .add_attribute("stage", "undelegate")
+                    .add_attribute("validator_address", validator);
+                Ok(Response::new().add_message(undelegate_msg).add_event(event))
+            }
+            Err(err) => Err(err),
+            Ok(()) => {
+                let force_unlock_msg = MsgForceUnlock {
+                    owner: env.contract.address.to_string(),
+                    id: lock_id,
+                    coins: vec![Coin::new(amount.u128(), self.lp_token_denom.clone()).into()],
+                };
+                let event = event
+                    .add_attribute("stage", "force_unlock")
+                    .add_attribute("amount", amount);
+                Ok(Response::new()
+                    .add_message(force_unlock_msg)
+                    .add_event(event))
+            }
+        }
+    }
+
+    /// Checks whether `amount` can be force unlocked from the lock right away.
+    /// Returns [`ForceUnlockRestriction::SuperfluidDelegated`] for delegated
+    /// locks, in which case [`ForceUnlock::force_unlock`] begins the
+    /// undelegation.
+    fn can_force_unlock(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lockup_id: Option<u64>,
+        amount: Uint128,
+    ) -> Result<(), CwDexError> {
+        let lock_id = force_unlock_id(lockup_id, self.lock_id)?;
+        check_force_unlock(deps, env, lock_id, &self.lp_token_denom, amount)
+    }
+}
+
+impl SimulateStaking for OsmosisSuperfluidStaking {
+    /// Returns the amount of LP tokens in the lock with ID `lock_id`. Returns
+    /// zero if it is not set, the lock does not exist, or the whole lock is
+    /// unlocking, as it is then returned by [`QueryPosition::query_unlocking`].
+    /// `user` is ignored, as the lock is owned by the contract.
+    fn query_staked_amount(
+        &self,
+        querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<Uint128, CwDexError> {
+        query_staked_lock_amount(querier, self.lock_id, &self.lp_token_denom)
+    }
+
+    /// Superfluid rewards are distributed at the end of each epoch.
+    fn pays_rewards_on_stake_change(&self) -> bool {
+        false
+    }
+}
+
 impl LockedStaking for OsmosisSuperfluidStaking {
     fn get_lockup_duration(&self, _deps: Deps) -> Result<CwDuration, CwDexError> {
         // Lockup time for superfluid staking is always 14 days.
         Ok(CwDuration::Time(TWO_WEEKS_IN_SECS))
     }
 }
+
+impl QueryPosition for OsmosisSuperfluidStaking {
+    /// Returns the locks of `user` of the LP token that are unlocking, with
+    /// any lockup duration.
+    fn query_unlocking(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<Vec<UnlockingPosition>, CwDexError> {
+        query_unlocking_locks(querier, user, &self.lp_token_denom)
+    }
+
+    fn query_lockup_duration(&self, deps: Deps) -> Result<Option<CwDuration>, CwDexError> {
+        self.get_lockup_duration(deps).map(Some)
+    }
+}
+
+impl MigratePosition for OsmosisStaking {
+    /// Extends the lockup duration of the lock to the duration of `to`, see
+    /// [`OsmosisStaking::extend_lock_duration`]. The lockup module can only
+    /// extend whole locks, so `amount` must be the whole locked amount. The
+    /// lock keeps its ID, which the caller should set on `to` afterwards.
+    fn migrate_position(
+        &self,
+        deps: Deps,
+        env: &Env,
+        amount: Uint128,
+        to: &Self,
+    ) -> Result<Response, CwDexError> {
+        if to.lp_token_denom != self.lp_token_denom {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: can not migrate lock of {} to lockup of {}",
+                self.lp_token_denom, to.lp_token_denom
+            ))
+            .into());
+        }
+        let locked = query_locked_amount(&deps.querier, self.lock_id, &self.lp_token_denom)?;
+        if amount != locked {
+            return Err(StdError::generic_err(format!(
+                "osmosis error: can only migrate the whole lock of {locked}"
+            ))
+            .into());
+        }
+
+        let event = Event::new("apollo/cw-dex/migrate_position")
+            .add_attribute("type", "osmosis_staking")
+            .add_attribute("from", self.to_string())
+            .add_attribute("to", to.to_string())
+            .add_attribute("amount", amount);
+
+        Ok(self
+            .extend_lock_duration(env, to.lockup_duration.as_secs())?
+            .add_event(event))
+    }
+}
+
+/// Returns the amount of `denom` in the lock with ID `lock_id`, or zero if
+/// `lock_id` is `None`.
+fn query_locked_amount(
+    querier: &QuerierWrapper,
+    lock_id: Option<u64>,
+    denom: &str,
+) -> Result<Uint128, CwDexError> {
+    let Some(lock_id) = lock_id else {
+        return Ok(Uint128::zero());
+    };
+
+    Ok(locked_amount(&query_lock(querier, lock_id)?, denom)?)
+}
+
+/// Returns the amount of `denom` in the lock with ID `lock_id` that is staked
+/// and not unlocking. Returns zero if `lock_id` is `None` or the lock does not
+/// exist, e.g. because it has been unlocked, and if the whole lock has begun
+/// unlocking, in which case it is returned by [`query_unlocking_locks`]
+/// instead.
+fn query_staked_lock_amount(
+    querier: &QuerierWrapper,
+    lock_id: Option<u64>,
+    denom: &str,
+) -> Result<Uint128, CwDexError> {
+    let Some(lock_id) = lock_id else {
+        return Ok(Uint128::zero());
+    };
+
+    match query_lock_if_exists(querier, lock_id)? {
+        Some(lock) if !is_unlocking(&lock) => Ok(locked_amount(&lock, denom)?),
+        _ => Ok(Uint128::zero()),
+    }
+}
+
+/// Returns true if `lock` has begun unlocking. The end time of locks that are
+/// not unlocking is the zero time, before the unix epoch.
+fn is_unlocking(lock: &PeriodLock) -> bool {
+    lock.end_time
+        .as_ref()
+        .map(|t| t.seconds > 0)
+        .unwrap_or(false)
+}
+
+/// Returns the lock with ID `lock_id`, or `None` if it does not exist.
+fn query_lock_if_exists(querier: &QuerierWrapper, lock_id: u64) -> StdResult<Option<PeriodLock>> {
+    // The lockup module errors with `lockup not found` for unknown lock IDs
+    match LockupQuerier::new(querier).locked_by_id(lock_id) {
+        Ok(res) => Ok(res.lock),
+        Err(err) if err.to_string().contains("not found") => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the lock with ID `lock_id`, or an error if it does not exist.
+fn query_lock(querier: &QuerierWrapper, lock_id: u64) -> StdResult<PeriodLock> {
+    query_lock_if_exists(querier, lock_id)?
+        .ok_or_else(|| StdError::generic_err(format!("osmosis error: lock {} not found", lock_id)))
+}
+
+/// Returns the locks of `owner` of `denom` that are unlocking.
+fn query_unlocking_locks(
+    querier: &QuerierWrapper,
+    owner: &Addr,
+    denom: &str,
+) -> Result<Vec<UnlockingPosition>, CwDexError> {
+    let locks = LockupQuerier::new(querier)
+        .account_locked_longer_duration_denom(
+            owner.to_string(),
+            Some(Duration::ZERO.to_protobuf_duration()),
+            denom.to_string(),
+        )?
+        .locks;
+
+    locks
+        .into_iter()
+        .filter(is_unlocking)
+        .filter_map(|lock| {
+            let end_time = lock.end_time.clone()?;
+            Some((lock, end_time))
+        })
+        .map(|(lock, end_time)| {
+            Ok(UnlockingPosition {
+                id: Some(lock.id),
+                amount: locked_amount(&lock, denom)?,
+                expiration: Expiration::AtTime(
+                    Timestamp::from_seconds(end_time.seconds as u64)
+                        .plus_nanos(end_time.nanos as u64),
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Returns the amount of `lp_token_denom` locked in `lock`. Returns
+/// [`CwDexError::LpTokenMismatch`] if the lock only contains another denom, or
+/// [`CwDexError::MultiCoinLock`] if it contains other coins next to the LP
+/// token.
+fn lp_amount_of_lock(lock: &PeriodLock, lp_token_denom: &str) -> Result<Uint128, CwDexError> {
+    if let [coin] = lock.coins.as_slice() {
+        if coin.denom != lp_token_denom {
+            return Err(CwDexError::LpTokenMismatch {
+                lp_token: AssetInfo::native(lp_token_denom),
+                staked_asset: AssetInfo::native(&coin.denom),
+            });
+        }
+    }
+    if lock.coins.iter().any(|coin| coin.denom != lp_token_denom) {
+        return Err(CwDexError::MultiCoinLock {
+            lock_id: lock.id,
+            denoms: lock.coins.iter().map(|coin| coin.denom.clone()).collect(),
+        });
+    }
+    Ok(locked_amount(lock, lp_token_denom)?)
+}
+
+/// Returns the amount of `denom` locked in `lock`.
+fn locked_amount(lock: &PeriodLock, denom: &str) -> StdResult<Uint128> {
+    lock.coins
+        .iter()
+        .filter(|coin| coin.denom == denom)
+        .map(|coin| parse_uint128(&coin.amount, "lock amount"))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockStorage};
+    use cosmwasm_std::{
+        attr, coins, from_json, to_json_binary, Addr, Coin, ContractResult, CosmosMsg, Empty,
+        OwnedDeps, Querier, QuerierResult, QueryRequest, Response, SystemResult, Uint128,
+    };
+    use cw_dex::traits::{
+        ForceUnlock, ForceUnlockRestriction, LockedStaking, MigratePosition, Rewards,
+        SimulateStaking, Stake,
+    };
+    use cw_dex::CwDexError;
+    use cw_utils::{Duration as CwDuration, Expiration};
+    use osmosis_std::shim::{Duration as ProtoDuration, Timestamp as ProtoTimestamp};
+    use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use osmosis_std::types::osmosis::incentives::MsgCreateGauge;
+    use osmosis_std::types::osmosis::lockup::{
+        LockedResponse, MsgExtendLockup, MsgForceUnlock, Params as LockupParams, PeriodLock,
+        QueryParamsResponse as LockupParamsResponse, SyntheticLock,
+        SyntheticLockupByLockupIdResponse,
+    };
+    use osmosis_std::types::osmosis::poolincentives::v1beta1::QueryLockableDurationsResponse;
+    use osmosis_std::types::osmosis::superfluid::{
+        ConnectedIntermediaryAccountResponse, MsgSuperfluidUndelegate,
+        SuperfluidIntermediaryAccountInfo,
+    };
+
+    use super::{
+        lp_amount_of_lock, OsmosisReplyIds, OsmosisRewardsMode, OsmosisStaking,
+        OsmosisSuperfluidStaking,
+    };
+
+    #[test]
+    fn test_from_duration() {
+        let staking =
+            OsmosisStaking::from_duration(CwDuration::Time(86400), None, "gamm/pool/1".into())
+                .unwrap();
+        assert_eq!(staking.lockup_duration.as_secs(), 86400);
+
+        assert!(
+            OsmosisStaking::from_duration(CwDuration::Time(1), None, "gamm/pool/1".into()).is_err()
+        );
+        assert!(
+            OsmosisStaking::from_duration(CwDuration::Height(100), None, "gamm/pool/1".into())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_get_unlock_expiration() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = OsmosisStaking::new(86400, None, "gamm/pool/1".into()).unwrap();
+
+        assert_eq!(
+            staking.get_unlock_expiration(deps.as_ref(), &env).unwrap(),
+            Expiration::AtTime(env.block.time.plus_seconds(86400))
+        );
+    }
+
+    #[test]
+    fn test_reply_ids() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+
+        // Stakings saved before reply IDs were configurable use the defaults
+        let staking: OsmosisStaking = cosmwasm_std::from_json(
+            br#"{"lockup_duration":{"secs":86400,"nanos":0},"lock_id":null,"lp_token_denom":"gamm/pool/1"}"#,
+        )
+        .unwrap();
+        assert_eq!(staking.reply_ids, OsmosisReplyIds::default());
+
+        let reply_ids = OsmosisReplyIds {
+            lock_tokens: 1,
+            unlock_tokens: 2,
+        };
+        let staking = staking.with_reply_ids(reply_ids);
+        let res = staking
+            .stake(deps.as_ref(), &env, Uint128::new(100))
+            .unwrap();
+        assert_eq!(res.messages[0].id, 1);
+        assert_eq!(reply_ids.to_vec(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut staking = OsmosisStaking::new(86400, None, "gamm/pool/1".into()).unwrap();
+        assert_eq!(staking.to_string(), "osmosis:lockup gamm/pool/1/86400s");
+
+        staking.lock_id = Some(12);
+        assert_eq!(staking.to_string(), "osmosis:lockup gamm/pool/1/86400s#12");
+
+        let superfluid = OsmosisSuperfluidStaking {
+            validator_address: Addr::unchecked("osmovaloper1"),
+            lock_id: None,
+            lp_token_denom: "gamm/pool/1".into(),
+            reply_ids: OsmosisReplyIds::default(),
+        };
+        assert_eq!(
+            superfluid.to_string(),
+            "osmosis:superfluid gamm/pool/1/osmovaloper1"
+        );
+    }
+
+    #[test]
+    fn test_claim_rewards() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = OsmosisStaking::new(86400, None, "gamm/pool/1".into()).unwrap();
+        assert_eq!(staking.rewards_mode(), OsmosisRewardsMode::EpochDistributed);
+
+        // Epoch distributed rewards need no claim message
+        let res = staking.claim_rewards(deps.as_ref(), &env).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            res.events[0].attributes[1],
+            attr("rewards_mode", "epoch_distributed")
+        );
+    }
+
+    #[test]
+    fn test_force_unlock_zero() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = OsmosisStaking::new(86400, Some(3), "gamm/pool/1".into()).unwrap();
+
+        assert_eq!(
+            staking
+                .force_unlock(deps.as_ref(), &env, None, Uint128::zero())
+                .unwrap_err(),
+            CwDexError::InvalidZeroAmount {}
+        );
+    }
+
+    #[test]
+    fn test_migrate_position_other_denom() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = OsmosisStaking::new(86400, Some(3), "gamm/pool/1".into()).unwrap();
+        let to = OsmosisStaking::new(1209600, None, "gamm/pool/2".into()).unwrap();
+
+        assert!(staking
+            .migrate_position(deps.as_ref(), &env, Uint128::new(100), &to)
+            .is_err());
+    }
+
+    #[test]
+    fn test_extend_lock_duration() {
+        let env = mock_env();
+        let staking = OsmosisStaking::new(604800, Some(3), "gamm/pool/1".into()).unwrap();
+
+        let res = staking.extend_lock_duration(&env, 1209600).unwrap();
+        let msg = match &res.messages[0].msg {
+            CosmosMsg::Stargate { type_url, value } => {
+                assert_eq!(type_url, MsgExtendLockup::TYPE_URL);
+                MsgExtendLockup::try_from(value.clone()).unwrap()
+            }
+            _ => panic!("Unexpected message"),
+        };
+        assert_eq!(msg.id, 3);
+        assert_eq!(msg.duration.unwrap().seconds, 1209600);
+
+        // Not an allowed duration
+        assert!(staking.extend_lock_duration(&env, 1209601).is_err());
+        // Not longer than the current duration
+        assert!(staking.extend_lock_duration(&env, 86400).is_err());
+        assert!(staking.extend_lock_duration(&env, 604800).is_err());
+        // No lock ID
+        let staking = OsmosisStaking::new(604800, None, "gamm/pool/1".into()).unwrap();
+        assert!(staking.extend_lock_duration(&env, 1209600).is_err());
+    }
+
+    #[test]
+    fn test_create_gauge() {
+        let env = mock_env();
+        let staking = OsmosisStaking::new(604800, None, "gamm/pool/1".into()).unwrap();
+
+        let res = staking
+            .create_gauge(&env, coins(1000, "uosmo"), Some(7), None)
+            .unwrap();
+
+        let msg = match &res.messages[0].msg {
+            CosmosMsg::Stargate { type_url, value } => {
+                assert_eq!(type_url, MsgCreateGauge::TYPE_URL);
+                MsgCreateGauge::try_from(value.clone()).unwrap()
+            }
+            _ => panic!("Unexpected message"),
+        };
+        assert!(!msg.is_perpetual);
+        assert_eq!(msg.num_epochs_paid_over, 7);
+        let distribute_to = msg.distribute_to.unwrap();
+        assert_eq!(distribute_to.denom, "gamm/pool/1");
+        assert_eq!(distribute_to.duration.unwrap().seconds, 604800);
+    }
+
+    #[test]
+    fn test_lp_amount_of_lock() {
+        let lock = |coins: &[(&str, &str)]| PeriodLock {
+            id: 3,
+            coins: coins
+                .iter()
+                .map(|(denom, amount)| ProtoCoin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            lp_amount_of_lock(&lock(&[("gamm/pool/1", "100")]), "gamm/pool/1").unwrap(),
+            Uint128::new(100)
+        );
+        assert_eq!(
+            lp_amount_of_lock(&lock(&[]), "gamm/pool/1").unwrap(),
+            Uint128::zero()
+        );
+
+        let err = lp_amount_of_lock(
+            &lock(&[("gamm/pool/1", "100"), ("uosmo", "5")]),
+            "gamm/pool/1",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::MultiCoinLock {
+                lock_id: 3,
+                denoms: vec!["gamm/pool/1".to_string(), "uosmo".to_string()],
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Lock 3 contains multiple denoms: gamm/pool/1, uosmo"
+        );
+
+        assert_eq!(
+            lp_amount_of_lock(&lock(&[("uosmo", "5")]), "gamm/pool/1").unwrap_err(),
+            CwDexError::LpTokenMismatch {
+                lp_token: AssetInfo::native("gamm/pool/1"),
+                staked_asset: AssetInfo::native("uosmo"),
+            }
+        );
+    }
+
+    /// Answers the lockup and superfluid queries for a single lock of 100 LP
+    /// tokens owned by the contract, unlocking until `unlock_end` if set, or
+    /// for no lock if `missing`. The contract is allowed to force unlock
+    /// unless `not_whitelisted`.
+    #[derive(Default)]
+    struct LockQuerier {
+        validator: Option<String>,
+        undelegation_end: Option<i64>,
+        unlock_end: Option<i64>,
+        missing: bool,
+        not_whitelisted: bool,
+    }
+
+    fn lock_deps(querier: LockQuerier) -> OwnedDeps<MockStorage, MockApi, LockQuerier> {
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier,
+            custom_query_type: PhantomData::<Empty>,
+        }
+    }
+
+    impl Querier for LockQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let QueryRequest::<Empty>::Stargate { path, .. } = from_json(bin_request).unwrap()
+            else {
+                panic!("unexpected query");
+            };
+            let res = match path.as_str() {
+                "/osmosis.lockup.Query/Params" => to_json_binary(&LockupParamsResponse {
+                    params: Some(LockupParams {
+                        force_unlock_allowed_addresses: if self.not_whitelisted {
+                            vec![]
+                        } else {
+                            vec![mock_env().contract.address.to_string()]
+                        },
+                    }),
+                }),
+                "/osmosis.poolincentives.v1beta1.Query/LockableDurations" => {
+                    to_json_binary(&QueryLockableDurationsResponse {
+                        lockable_durations: vec![ProtoDuration {
+                            seconds: 86400,
+                            nanos: 0,
+                        }],
+                    })
+                }
+                "/osmosis.lockup.Query/LockedByID" if self.missing => {
+                    return SystemResult::Ok(ContractResult::Err(
+                        "lock with ID 3 does not exist: lockup not found".to_string(),
+                    ));
+                }
+                "/osmosis.lockup.Query/LockedByID" => to_json_binary(&LockedResponse {
+                    lock: Some(PeriodLock {
+                        id: 3,
+                        owner: mock_env().contract.address.to_string(),
+                        coins: vec![ProtoCoin {
+                            denom: "gamm/pool/1".to_string(),
+                            amount: "100".to_string(),
+                        }],
+                        end_time: Some(ProtoTimestamp {
+                            seconds: self.unlock_end.unwrap_or(-62135596800),
+                            nanos: 0,
+                        }),
+                        ..Default::default()
+                    }),
+                }),
+                "/osmosis.superfluid.Query/ConnectedIntermediaryAccount" => {
+                    to_json_binary(&ConnectedIntermediaryAccountResponse {
+                        account: self.validator.clone().map(|val_addr| {
+                            SuperfluidIntermediaryAccountInfo {
+                                val_addr,
+                                ..Default::default()
+                            }
+                        }),
+                    })
+                }
+                "/osmosis.lockup.Query/SyntheticLockupByLockupID" => {
+                    to_json_binary(&SyntheticLockupByLockupIdResponse {
+                        synthetic_lock: Some(match self.undelegation_end {
+                            Some(seconds) => SyntheticLock {
+                                underlying_lock_id: 3,
+                                end_time: Some(ProtoTimestamp { seconds, nanos: 0 }),
+                                ..Default::default()
+                            },
+                            None => SyntheticLock::default(),
+                        }),
+                    })
+                }
+                _ => panic!("unexpected query {path}"),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        }
+    }
+
+    fn superfluid_force_unlock(
+        validator: Option<&str>,
+        undelegation_end: Option<i64>,
+    ) -> Result<Response, CwDexError> {
+        superfluid_force_unlock_with(LockQuerier {
+            validator: validator.map(String::from),
+            undelegation_end,
+            ..Default::default()
+        })
+    }
+
+    fn superfluid_force_unlock_with(querier: LockQuerier) -> Result<Response, CwDexError> {
+        let deps = lock_deps(querier);
+        let staking = OsmosisSuperfluidStaking::new(
+            Addr::unchecked("osmovaloper1"),
+            Some(3),
+            "gamm/pool/1".into(),
+        )
+        .unwrap();
+        staking.force_unlock(deps.as_ref(), &mock_env(), None, Uint128::new(40))
+    }
+
+    #[test]
+    fn test_superfluid_force_unlock() {
+        let stage = |res: &Response| {
+            res.events[0]
+                .attributes
+                .iter()
+                .find(|a| a.key == "stage")
+                .unwrap()
+                .value
+                .clone()
+        };
+
+        // Delegated locks are undelegated first
+        let res = superfluid_force_unlock(Some("osmovaloper1"), None).unwrap();
+        assert_eq!(stage(&res), "undelegate");
+        match &res.messages[0].msg {
+            CosmosMsg::Stargate { type_url, .. } => {
+                assert_eq!(type_url, MsgSuperfluidUndelegate::TYPE_URL)
+            }
+            _ => panic!("unexpected message"),
+        }
+
+        // Locks are not undelegated if they can not be force unlocked afterwards
+        let err = superfluid_force_unlock_with(LockQuerier {
+            validator: Some("osmovaloper1".to_string()),
+            not_whitelisted: true,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::ForceUnlockNotAllowed {
+                lock_id: 3,
+                reason: ForceUnlockRestriction::NotWhitelisted,
+            }
+        );
+
+        // Force unlocking is not possible until the undelegation completes
+        let now = mock_env().block.time.seconds() as i64;
+        let err = superfluid_force_unlock(None, Some(now + 60)).unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::ForceUnlockNotAllowed {
+                lock_id: 3,
+                reason: ForceUnlockRestriction::SuperfluidUndelegating {
+                    until: now as u64 + 60
+                },
+            }
+        );
+
+        // Once undelegated, the lock is force unlocked
+        for undelegation_end in [None, Some(now)] {
+            let res = superfluid_force_unlock(None, undelegation_end).unwrap();
+            assert_eq!(stage(&res), "force_unlock");
+            match &res.messages[0].msg {
+                CosmosMsg::Stargate { type_url, .. } => {
+                    assert_eq!(type_url, MsgForceUnlock::TYPE_URL)
+                }
+                _ => panic!("unexpected message"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_staked_amount() {
+        let staking = OsmosisStaking::new(86400, Some(3), "gamm/pool/1".into()).unwrap();
+        let staked = |querier: LockQuerier| {
+            let deps = lock_deps(querier);
+            staking.query_staked_amount(&deps.as_ref().querier, &mock_env().contract.address)
+        };
+
+        assert_eq!(staked(LockQuerier::default()).unwrap(), Uint128::new(100));
+        // A lock that is unlocking as a whole is not staked
+        let unlock_end = mock_env().block.time.seconds() as i64 + 86400;
+        assert_eq!(
+            staked(LockQuerier {
+                unlock_end: Some(unlock_end),
+                ..Default::default()
+            })
+            .unwrap(),
+            Uint128::zero()
+        );
+        // Locks that have been unlocked do not exist anymore
+        assert_eq!(
+            staked(LockQuerier {
+                missing: true,
+                ..Default::default()
+            })
+            .unwrap(),
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    fn test_can_force_unlock() {
+        let staking = OsmosisStaking::new(86400, Some(3), "gamm/pool/1".into()).unwrap();
+        let can_force_unlock = |querier: LockQuerier| {
+            let deps = lock_deps(querier);
+            staking.can_force_unlock(deps.as_ref(), &mock_env(), None, Uint128::new(40))
+        };
+        let not_allowed = |reason| CwDexError::ForceUnlockNotAllowed { lock_id: 3, reason };
+
+        can_force_unlock(LockQuerier::default()).unwrap();
+        // Only whitelisted addresses may force unlock
+        assert_eq!(
+            can_force_unlock(LockQuerier {
+                not_whitelisted: true,
+                ..Default::default()
+            })
+            .unwrap_err(),
+            not_allowed(ForceUnlockRestriction::NotWhitelisted)
+        );
+        assert_eq!(
+            can_force_unlock(LockQuerier {
+                missing: true,
+                ..Default::default()
+            })
+            .unwrap_err(),
+            not_allowed(ForceUnlockRestriction::NotFound)
+        );
+        // Unlocking locks can only be force unlocked in full
+        assert_eq!(
+            can_force_unlock(LockQuerier {
+                unlock_end: Some(mock_env().block.time.seconds() as i64 + 86400),
+                ..Default::default()
+            })
+            .unwrap_err(),
+            not_allowed(ForceUnlockRestriction::PartiallyUnlocking)
+        );
+    }
+
+    #[test]
+    fn test_force_unlock_all() {
+        let staking = OsmosisStaking::new(86400, Some(3), "gamm/pool/1".into()).unwrap();
+        let force_unlock_all = |querier: LockQuerier| {
+            let deps = lock_deps(querier);
+            staking.force_unlock_all(deps.as_ref(), &mock_env(), None)
+        };
+
+        let res = force_unlock_all(LockQuerier::default()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            MsgForceUnlock {
+                owner: mock_env().contract.address.to_string(),
+                id: 3,
+                coins: vec![Coin::new(100, "gamm/pool/1").into()],
+            }
+            .into()
+        );
+        assert_eq!(
+            force_unlock_all(LockQuerier {
+                not_whitelisted: true,
+                ..Default::default()
+            })
+            .unwrap_err(),
+            CwDexError::ForceUnlockNotAllowed {
+                lock_id: 3,
+                reason: ForceUnlockRestriction::NotWhitelisted
+            }
+        );
+    }
+}
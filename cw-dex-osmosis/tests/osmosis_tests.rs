@@ -5,6 +5,13 @@ mod tests {
     use apollo_utils::submessages::{find_event, parse_attribute_value};
     use cosmwasm_std::{Coin, SubMsgResponse, Uint128};
     use cw_dex::Pool;
+    use cw_dex_osmosis::osmosis_std::shim::Duration;
+    use cw_dex_osmosis::osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+    use cw_dex_osmosis::osmosis_std::types::osmosis::incentives::{
+        MsgCreateGauge, MsgCreateGaugeResponse,
+    };
+    use cw_dex_osmosis::osmosis_std::types::osmosis::lockup::{LockQueryType, QueryCondition};
+    use cw_dex_osmosis::{ExternalIncentiveGauge, OsmosisPool};
     use cw_dex_test_contract::msg::{ExecuteMsg, QueryMsg};
     use cw_dex_test_helpers::osmosis::setup_pool_and_test_contract;
     use cw_dex_test_helpers::provide_liquidity;
@@ -192,6 +199,58 @@ mod tests {
         assert_eq!(lp_token_balance_after, Uint128::one());
     }
 
+    #[test_case(OsmosisPoolType::Basic; "basic pool")]
+    #[test_case(OsmosisPoolType::Balancer { pool_weights: vec![2, 1], pool_params: None }; "2:1 balancer pool")]
+    #[test_case(OsmosisPoolType::StableSwap { scaling_factors: vec![1, 1], pool_params: None }; "stable swap pool")]
+    #[test_case(OsmosisPoolType::StableSwap { scaling_factors: vec![1, 1000], pool_params: None }; "stable swap pool, uneven scaling factors")]
+    fn test_withdraw_liquidity_single_sided(pool_type: OsmosisPoolType) {
+        let (runner, accs, pool_id, contract_addr) =
+            setup_pool_and_contract(pool_type, INITIAL_TWO_POOL_LIQUIDITY.to_vec(), None).unwrap();
+        let admin = &accs[0];
+        let wasm = Wasm::new(&runner);
+        let lp_token_denom = format!("gamm/pool/{}", pool_id);
+
+        // Send half of the admin's LP tokens to the test contract
+        let admin_lp_token_balance =
+            bank_balance_query(&runner, admin.address(), lp_token_denom.clone()).unwrap();
+        let amount = admin_lp_token_balance / Uint128::new(2);
+        bank_send(
+            &runner,
+            admin,
+            &contract_addr,
+            vec![Coin::new(amount.u128(), lp_token_denom)],
+        )
+        .unwrap();
+
+        let simulated: Uint128 = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::SimulateWithdrawLiquiditySingleSided {
+                    amount,
+                    ask: DENOM1.to_string(),
+                },
+            )
+            .unwrap();
+
+        // The simulation may be one unit off per asset of the pool, which
+        // min_out must leave room for
+        let tolerance = Uint128::new(2);
+        let withdraw_msg = ExecuteMsg::WithdrawLiquiditySingleSided {
+            amount,
+            ask: DENOM1.to_string(),
+            min_out: simulated - tolerance,
+        };
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[withdraw_msg.into_cosmos_msg(contract_addr.clone(), vec![])],
+                admin,
+            )
+            .unwrap();
+
+        let received = bank_balance_query(&runner, contract_addr, DENOM1.to_string()).unwrap();
+        assert!(received.abs_diff(simulated) <= tolerance);
+    }
+
     fn stake_all_lp_tokens<'a, R: Runner<'a>>(
         runner: &'a R,
         contract_addr: String,
@@ -338,6 +397,35 @@ mod tests {
                 .unwrap();
         }
 
+        // More than the locked amount can not be force unlocked
+        let wasm = Wasm::new(&runner);
+        let err = wasm
+            .query::<_, ()>(
+                &contract_addr,
+                &QueryMsg::CanForceUnlock {
+                    amount: Uint128::MAX,
+                    lockup_id: Some(1),
+                },
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Can not force unlock lock 1"));
+        let can_force_unlock = wasm.query::<_, ()>(
+            &contract_addr,
+            &QueryMsg::CanForceUnlock {
+                amount: unlock_amount,
+                lockup_id: Some(1),
+            },
+        );
+        // Contracts that are not whitelisted by the lockup module can not force unlock
+        if !whitelist {
+            let err = can_force_unlock.unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("contract is not allowed to force unlock"));
+            return Err(err);
+        }
+        can_force_unlock.unwrap();
+
         // Force unlock LP tokens
         let force_unlock_msg = ExecuteMsg::ForceUnlock {
             amount: unlock_amount,
@@ -349,12 +437,38 @@ mod tests {
         )?;
 
         // Query LP token balance
-        let lp_token_balance =
-            bank_balance_query(&runner, contract_addr, format!("gamm/pool/{}", pool_id)).unwrap();
+        let lp_token_balance = bank_balance_query(
+            &runner,
+            contract_addr.clone(),
+            format!("gamm/pool/{}", pool_id),
+        )
+        .unwrap();
 
         // Assert that LP tokens have been unlocked
         assert_eq!(lp_token_balance, unlock_amount);
 
+        // Force unlock the rest of the lock
+        runner.execute_cosmos_msgs::<MsgExecuteContractResponse>(
+            &[ExecuteMsg::ForceUnlockAll { lockup_id: Some(1) }
+                .into_cosmos_msg(contract_addr.clone(), vec![])],
+            admin,
+        )?;
+        let lp_token_balance = bank_balance_query(
+            &runner,
+            contract_addr.clone(),
+            format!("gamm/pool/{}", pool_id),
+        )
+        .unwrap();
+        assert!(lp_token_balance > unlock_amount);
+        wasm.query::<_, ()>(
+            &contract_addr,
+            &QueryMsg::CanForceUnlock {
+                amount: Uint128::one(),
+                lockup_id: Some(1),
+            },
+        )
+        .unwrap_err();
+
         Ok(())
     }
 
@@ -418,6 +532,33 @@ mod tests {
         assert_eq!(offer_balance, Uint128::zero());
     }
 
+    #[test_case(OsmosisPoolType::Balancer { pool_weights: vec![2, 1], pool_params: None }; "2:1 balancer pool")]
+    #[test_case(OsmosisPoolType::StableSwap { scaling_factors: vec![1, 1], pool_params: None }; "stable swap pool")]
+    #[test_case(OsmosisPoolType::StableSwap { scaling_factors: vec![1, 1000], pool_params: None }; "stable swap pool, uneven scaling factors")]
+    fn test_simulate_swap_local(pool_type: OsmosisPoolType) {
+        let (runner, _accs, _, contract_addr) =
+            setup_pool_and_contract(pool_type, INITIAL_TWO_POOL_LIQUIDITY.to_vec(), None).unwrap();
+        let wasm = Wasm::new(&runner);
+
+        let offer = Asset::native(DENOM0, 1_000_000u128);
+        let ask = AssetInfo::native(DENOM1);
+        let on_chain: Uint128 = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::SimulateSwap {
+                    offer: offer.clone(),
+                    ask: ask.clone(),
+                },
+            )
+            .unwrap();
+        let local: Uint128 = wasm
+            .query(&contract_addr, &QueryMsg::SimulateSwapLocal { offer, ask })
+            .unwrap();
+
+        // The local math rounds down and may be one unit below the chain
+        assert!(local <= on_chain && on_chain - local <= Uint128::one());
+    }
+
     #[test]
     fn test_get_pool_for_lp_token() {
         let (runner, _accs, pool_id, contract_addr) = setup_pool_and_contract(
@@ -442,4 +583,99 @@ mod tests {
             _ => panic!("Wrong pool type"),
         }
     }
+
+    #[test]
+    fn test_find_pools() {
+        let (runner, _accs, pool_id, contract_addr) = setup_pool_and_contract(
+            OsmosisPoolType::Basic,
+            INITIAL_TWO_POOL_LIQUIDITY.to_vec(),
+            None,
+        )
+        .unwrap();
+        let wasm = Wasm::new(&runner);
+
+        let query = QueryMsg::FindPools {
+            asset_a: AssetInfo::native(DENOM1),
+            asset_b: AssetInfo::native(DENOM0),
+        };
+        let pools = wasm
+            .query::<_, Vec<OsmosisPool>>(&contract_addr, &query)
+            .unwrap();
+        assert_eq!(pools, vec![OsmosisPool::unchecked(pool_id)]);
+
+        // No pool of the assets
+        let query = QueryMsg::FindPools {
+            asset_a: AssetInfo::native(DENOM0),
+            asset_b: AssetInfo::native("uosmo"),
+        };
+        let pools = wasm
+            .query::<_, Vec<OsmosisPool>>(&contract_addr, &query)
+            .unwrap();
+        assert!(pools.is_empty());
+    }
+
+    #[test]
+    fn test_query_external_incentive_gauges() {
+        let (runner, accs, pool_id, contract_addr) = setup_pool_and_contract(
+            OsmosisPoolType::Basic,
+            INITIAL_TWO_POOL_LIQUIDITY.to_vec(),
+            None,
+        )
+        .unwrap();
+        let admin = &accs[0];
+        let lp_token_denom = format!("gamm/pool/{}", pool_id);
+        let wasm = Wasm::new(&runner);
+
+        // The gauges the pool incentives module created with the pool are
+        // excluded
+        let gauges = wasm
+            .query::<_, Vec<ExternalIncentiveGauge>>(
+                &contract_addr,
+                &QueryMsg::ExternalIncentiveGauges {},
+            )
+            .unwrap();
+        assert!(gauges.is_empty());
+
+        // Incentivize the lockups of the LP token with 1_000_000 denom0 over
+        // 10 epochs
+        runner
+            .execute::<_, MsgCreateGaugeResponse>(
+                MsgCreateGauge {
+                    is_perpetual: false,
+                    owner: admin.address(),
+                    distribute_to: Some(QueryCondition {
+                        lock_query_type: LockQueryType::ByDuration.into(),
+                        denom: lp_token_denom,
+                        duration: Some(Duration {
+                            seconds: TWO_WEEKS_IN_SECS as i64,
+                            nanos: 0,
+                        }),
+                        timestamp: None,
+                    }),
+                    coins: vec![ProtoCoin {
+                        denom: DENOM0.to_string(),
+                        amount: ONE_MILLION.to_string(),
+                    }],
+                    start_time: None,
+                    num_epochs_paid_over: 10,
+                    pool_id: 0,
+                },
+                MsgCreateGauge::TYPE_URL,
+                admin,
+            )
+            .unwrap();
+
+        let gauges = wasm
+            .query::<_, Vec<ExternalIncentiveGauge>>(
+                &contract_addr,
+                &QueryMsg::ExternalIncentiveGauges {},
+            )
+            .unwrap();
+        assert_eq!(gauges.len(), 1);
+        let gauge = &gauges[0];
+        assert_eq!(gauge.lockup_duration_secs, TWO_WEEKS_IN_SECS);
+        assert!(!gauge.is_perpetual);
+        assert_eq!(gauge.remaining_epochs, 10);
+        assert_eq!(gauge.rewards_per_epoch, vec![Coin::new(100_000, DENOM0)]);
+    }
 }
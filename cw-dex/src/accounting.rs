@@ -0,0 +1,256 @@
+//! Contains a reply-based pattern for accounting the exact amounts of claimed
+//! rewards.
+//!
+//! Claims like the Astroport `claim_rewards` do not report the claimed
+//! amounts, which are only known after the claim messages have executed. The
+//! pending rewards queried before the claim are an estimate, e.g. rewards can
+//! accrue in the block of the claim. [`claim_rewards_with_accounting`]
+//! records the contract's balances of the reward tokens before the claim in a
+//! [`ClaimRewardsTracker`], which emits the difference to the balances after
+//! the claim when handling the reply. The balances are recorded when the
+//! contract executes, so the claim messages are sent before any other
+//! messages of the contract's response.
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Deps, Env, Event, Response};
+
+use crate::error::CwDexError;
+use crate::reply::reply_on_last_message;
+use crate::traits::Rewards;
+
+/// Reply ID for the last claim message of [`claim_rewards_with_accounting`].
+/// The contract must call [`ClaimRewardsTracker::handle_reply`] when handling
+/// this reply.
+pub const CLAIM_REWARDS_ACCOUNTING_REPLY_ID: u64 = 457;
+
+/// Tracks the rewards claimed by [`claim_rewards_with_accounting`], as the
+/// amounts by which the contract's balances of the reward tokens exceed
+/// `balances_before` after the claim.
+#[cw_serde]
+pub struct ClaimRewardsTracker {
+    /// The balances of the reward tokens before the claim. This is a `Vec`
+    /// rather than an [`AssetList`], as the latter drops zero amounts.
+    pub balances_before: Vec<Asset>,
+}
+
+impl ClaimRewardsTracker {
+    /// Returns a tracker of the contract's current balances of
+    /// `reward_tokens`.
+    pub fn new(deps: Deps, env: &Env, reward_tokens: &[AssetInfo]) -> Result<Self, CwDexError> {
+        let balances_before = reward_tokens
+            .iter()
+            .map(|info| {
+                let balance = info.query_balance(&deps.querier, &env.contract.address)?;
+                Ok(Asset::new(info.clone(), balance))
+            })
+            .collect::<Result<_, CwDexError>>()?;
+        Ok(Self { balances_before })
+    }
+
+    /// Returns the claimed rewards, i.e. the amounts by which the contract's
+    /// balances exceed `balances_before`.
+    pub fn query_claimed(&self, deps: Deps, env: &Env) -> Result<AssetList, CwDexError> {
+        let mut claimed = vec![];
+        for before in self.balances_before.iter() {
+            let balance = before
+                .info
+                .query_balance(&deps.querier, &env.contract.address)?;
+            let amount = balance.saturating_sub(before.amount);
+            if !amount.is_zero() {
+                claimed.push(Asset::new(before.info.clone(), amount));
+            }
+        }
+        Ok(claimed.into())
+    }
+
+    /// Measures the claimed rewards. Must be called when handling the reply
+    /// with ID [`CLAIM_REWARDS_ACCOUNTING_REPLY_ID`], or the reply ID passed
+    /// to [`claim_rewards_with_accounting_and_reply_id`].
+    ///
+    /// Returns a response with an `apollo/cw-dex/rewards_claimed` event, along
+    /// with the claimed rewards, e.g. to compound exactly the claimed amounts.
+    /// The `claimed` attribute of the event is omitted if nothing was claimed.
+    pub fn handle_reply(&self, deps: Deps, env: &Env) -> Result<(Response, AssetList), CwDexError> {
+        let claimed = self.query_claimed(deps, env)?;
+
+        let mut event = Event::new("apollo/cw-dex/rewards_claimed");
+        // Empty attribute values are not allowed, so an empty list is left out
+        if claimed.len() > 0 {
+            event = event.add_attribute("claimed", claimed.to_string());
+        }
+
+        Ok((Response::new().add_event(event), claimed))
+    }
+}
+
+/// Claims the rewards of `staking` like [`Rewards::claim_rewards`], returning
+/// a [`ClaimRewardsTracker`] of the contract's balances of `reward_tokens`
+/// that the contract must save and use to handle the reply.
+///
+/// The balances are recorded when the contract executes, so the claim
+/// messages must be the first messages of the contract's response that can
+/// change them. They are therefore added to `response` before its messages,
+/// and the contract must not add messages in front of them afterwards. A
+/// contract that has to send other messages first must call this function in
+/// a callback message to itself, executed immediately before the claim.
+///
+/// The last claim message is sent as a submessage with reply ID
+/// [`CLAIM_REWARDS_ACCOUNTING_REPLY_ID`]. If the claim has no messages, e.g.
+/// because the staking distributes rewards automatically, no tracker is
+/// returned and the response has an `apollo/cw-dex/rewards_claimed` event with
+/// attribute `skipped` set to `no_messages`.
+///
+/// Arguments:
+/// - `reward_tokens`: The reward tokens to account for, e.g. the assets of
+///   [`Rewards::query_pending_rewards`]. Claimed tokens not in the list are not
+///   reported.
+/// - `response`: The contract's response, whose messages are executed after the
+///   claim.
+pub fn claim_rewards_with_accounting(
+    deps: Deps,
+    env: &Env,
+    staking: &impl Rewards,
+    reward_tokens: &[AssetInfo],
+    response: Response,
+) -> Result<(Response, Option<ClaimRewardsTracker>), CwDexError> {
+    claim_rewards_with_accounting_and_reply_id(
+        deps,
+        env,
+        staking,
+        reward_tokens,
+        response,
+        CLAIM_REWARDS_ACCOUNTING_REPLY_ID,
+    )
+}
+
+/// Same as [`claim_rewards_with_accounting`], but sends the last claim
+/// message with reply ID `reply_id` instead of
+/// [`CLAIM_REWARDS_ACCOUNTING_REPLY_ID`], e.g. to avoid a collision with the
+/// contract's own reply IDs.
+pub fn claim_rewards_with_accounting_and_reply_id(
+    deps: Deps,
+    env: &Env,
+    staking: &impl Rewards,
+    reward_tokens: &[AssetInfo],
+    response: Response,
+    reply_id: u64,
+) -> Result<(Response, Option<ClaimRewardsTracker>), CwDexError> {
+    let claim = staking.claim_rewards(deps, env)?;
+    if claim.messages.is_empty() {
+        let event =
+            Event::new("apollo/cw-dex/rewards_claimed").add_attribute("skipped", "no_messages");
+        return Ok((prepend_claim(claim, response).add_event(event), None));
+    }
+
+    let tracker = ClaimRewardsTracker::new(deps, env, reward_tokens)?;
+    let claim = reply_on_last_message(claim, reply_id)?;
+    Ok((prepend_claim(claim, response), Some(tracker)))
+}
+
+/// Returns `response` with the messages of `claim` before its own messages,
+/// and the attributes and events of both
+fn prepend_claim(claim: Response, mut response: Response) -> Response {
+    let messages = std::mem::take(&mut response.messages);
+    response.messages = claim.messages;
+    response
+        .add_submessages(messages)
+        .add_attributes(claim.attributes)
+        .add_events(claim.events)
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{attr, coin, BankMsg, ReplyOn, Response};
+
+    use super::*;
+    use crate::testing::MockStaking;
+
+    #[test]
+    fn test_claim_rewards_with_accounting() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![coin(100, "uastro")]);
+        let staking = MockStaking::new("lp").with_claim_messages(2);
+        let reward_tokens = [AssetInfo::native("uastro"), AssetInfo::native("untrn")];
+
+        // The claim messages come before the messages of the contract
+        let send = BankMsg::Send {
+            to_address: "user".to_string(),
+            amount: vec![coin(100, "uastro")],
+        };
+        let (res, tracker) = claim_rewards_with_accounting(
+            deps.as_ref(),
+            &env,
+            &staking,
+            &reward_tokens,
+            Response::new().add_message(send.clone()),
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 3);
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Never);
+        assert_eq!(res.messages[1].id, CLAIM_REWARDS_ACCOUNTING_REPLY_ID);
+        assert_eq!(res.messages[1].reply_on, ReplyOn::Success);
+        assert_eq!(res.messages[2].msg, send.into());
+        assert_eq!(res.messages[2].reply_on, ReplyOn::Never);
+        let tracker = tracker.unwrap();
+        assert_eq!(
+            tracker.balances_before,
+            vec![
+                Asset::native("uastro", 100u128),
+                Asset::native("untrn", 0u128)
+            ]
+        );
+
+        // The claim pays out 250 uastro and 7 untrn. Tokens that are not
+        // tracked are not reported.
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![coin(350, "uastro"), coin(7, "untrn"), coin(1, "uother")],
+        );
+        let (res, claimed) = tracker.handle_reply(deps.as_ref(), &env).unwrap();
+        assert_eq!(
+            claimed,
+            AssetList::from(vec![
+                Asset::native("uastro", 250u128),
+                Asset::native("untrn", 7u128),
+            ])
+        );
+        assert_eq!(res.events[0].ty, "apollo/cw-dex/rewards_claimed");
+        assert_eq!(
+            res.events[0].attributes,
+            vec![attr("claimed", claimed.to_string())]
+        );
+
+        // Nothing claimed
+        let tracker = ClaimRewardsTracker::new(deps.as_ref(), &env, &reward_tokens).unwrap();
+        let (res, claimed) = tracker.handle_reply(deps.as_ref(), &env).unwrap();
+        assert_eq!(claimed.len(), 0);
+        assert!(res.events[0].attributes.is_empty());
+    }
+
+    #[test]
+    fn test_claim_rewards_with_accounting_no_messages() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = MockStaking::new("lp").with_claim_messages(0);
+
+        let (res, tracker) = claim_rewards_with_accounting(
+            deps.as_ref(),
+            &env,
+            &staking,
+            &[AssetInfo::native("uosmo")],
+            Response::new(),
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert!(tracker.is_none());
+        assert_eq!(
+            res.events[0].attributes,
+            vec![attr("skipped", "no_messages")]
+        );
+    }
+}
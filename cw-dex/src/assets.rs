@@ -0,0 +1,220 @@
+//! Contains helpers for normalizing lists of assets and coins before building
+//! dex messages. Unlike `AssetList::add`, which moves an asset to the end of
+//! the list when it was purged for a zero amount before, and panicking
+//! conversions into `AssetList`, these have documented ordering and return
+//! errors on overflow.
+
+use std::cmp::Ordering;
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{Coin, StdResult};
+
+/// Returns `assets` with entries of the same asset merged into one by summing
+/// their amounts, and entries with a zero amount removed. Assets keep the
+/// position of their first occurrence in `assets`.
+///
+/// Returns an error if the sum of the amounts of an asset overflows.
+pub fn merge_assets<'a>(assets: impl IntoIterator<Item = &'a Asset>) -> StdResult<AssetList> {
+    let mut merged: Vec<Asset> = vec![];
+    for asset in assets {
+        match merged.iter_mut().find(|a| a.info == asset.info) {
+            Some(existing) => existing.amount = existing.amount.checked_add(asset.amount)?,
+            None => merged.push(asset.clone()),
+        }
+    }
+    Ok(purge_assets(merged).into())
+}
+
+/// Returns `assets` without the entries with a zero amount, keeping the order
+/// of the remaining entries. Duplicate entries are not merged, see
+/// [`merge_assets`]. Returns a `Vec` as converting into an `AssetList` merges
+/// duplicates.
+pub fn purge_assets(assets: impl IntoIterator<Item = Asset>) -> Vec<Asset> {
+    assets
+        .into_iter()
+        .filter(|asset| !asset.amount.is_zero())
+        .collect()
+}
+
+/// Orders asset infos with native tokens first, sorted by denom, followed by
+/// CW20 tokens, sorted by contract address.
+pub fn cmp_asset_info(a: &AssetInfo, b: &AssetInfo) -> Ordering {
+    match (a, b) {
+        (AssetInfo::Native(a), AssetInfo::Native(b)) => a.cmp(b),
+        (AssetInfo::Cw20(a), AssetInfo::Cw20(b)) => a.cmp(b),
+        (AssetInfo::Native(_), AssetInfo::Cw20(_)) => Ordering::Less,
+        (AssetInfo::Cw20(_), AssetInfo::Native(_)) => Ordering::Greater,
+    }
+}
+
+/// Returns `assets` merged as by [`merge_assets`] and sorted by asset info as
+/// by [`cmp_asset_info`]. The result only depends on the total amount of each
+/// asset in `assets`, not on their order or repetition.
+pub fn normalize_assets<'a>(assets: impl IntoIterator<Item = &'a Asset>) -> StdResult<AssetList> {
+    let mut assets = merge_assets(assets)?.to_vec();
+    assets.sort_by(|a, b| cmp_asset_info(&a.info, &b.info));
+    Ok(assets.into())
+}
+
+/// Returns `coins` with coins of the same denom merged into one by summing
+/// their amounts, coins with a zero amount removed, and sorted by denom, as
+/// required for the funds of a message and the coins of Cosmos SDK messages.
+///
+/// Returns an error if the sum of the amounts of a denom overflows.
+pub fn merge_coins(coins: impl IntoIterator<Item = Coin>) -> StdResult<Vec<Coin>> {
+    let mut merged: Vec<Coin> = vec![];
+    for coin in coins {
+        match merged.iter_mut().find(|c| c.denom == coin.denom) {
+            Some(existing) => existing.amount = existing.amount.checked_add(coin.amount)?,
+            None => merged.push(coin),
+        }
+    }
+    merged.retain(|coin| !coin.amount.is_zero());
+    merged.sort_by(|a, b| a.denom.cmp(&b.denom));
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo};
+    use cosmwasm_std::{coin, Addr, Uint128};
+    use test_case::test_case;
+
+    use super::*;
+
+    fn cw20(addr: &str, amount: u128) -> Asset {
+        Asset::cw20(Addr::unchecked(addr), amount)
+    }
+
+    #[test_case(vec![] => Vec::<Asset>::new(); "empty")]
+    #[test_case(
+        vec![Asset::native("uosmo", 1u128), Asset::native("uatom", 2u128)]
+        => vec![Asset::native("uosmo", 1u128), Asset::native("uatom", 2u128)];
+        "no duplicates keeps order"
+    )]
+    #[test_case(
+        vec![Asset::native("uosmo", 1u128), cw20("astro", 3u128), Asset::native("uosmo", 2u128)]
+        => vec![Asset::native("uosmo", 3u128), cw20("astro", 3u128)];
+        "duplicates merged at first occurrence"
+    )]
+    #[test_case(
+        vec![Asset::native("uosmo", 0u128), Asset::native("uatom", 2u128), cw20("astro", 0u128)]
+        => vec![Asset::native("uatom", 2u128)];
+        "zero amounts removed"
+    )]
+    #[test_case(
+        vec![Asset::native("uosmo", 0u128), Asset::native("uosmo", 0u128)]
+        => Vec::<Asset>::new();
+        "duplicates summing to zero removed"
+    )]
+    #[test_case(
+        vec![Asset::native("uosmo", 0u128), Asset::native("uatom", 1u128), Asset::native("uosmo", 1u128)]
+        => vec![Asset::native("uosmo", 1u128), Asset::native("uatom", 1u128)];
+        "zero first occurrence keeps position"
+    )]
+    #[test_case(
+        vec![Asset::native("uosmo", 1u128), cw20("uosmo", 1u128)]
+        => vec![Asset::native("uosmo", 1u128), cw20("uosmo", 1u128)];
+        "native and cw20 with same identifier are distinct"
+    )]
+    fn test_merge_assets(assets: Vec<Asset>) -> Vec<Asset> {
+        merge_assets(&assets).unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_merge_assets_overflow() {
+        let assets = vec![
+            Asset::native("uosmo", Uint128::MAX),
+            Asset::native("uosmo", 1u128),
+        ];
+        assert!(merge_assets(&assets).is_err());
+        assert!(normalize_assets(&assets).is_err());
+    }
+
+    #[test]
+    fn test_purge_assets() {
+        let assets = vec![
+            Asset::native("uosmo", 0u128),
+            Asset::native("uatom", 2u128),
+            Asset::native("uatom", 1u128),
+            cw20("astro", 0u128),
+        ];
+        assert_eq!(
+            purge_assets(assets),
+            vec![Asset::native("uatom", 2u128), Asset::native("uatom", 1u128)]
+        );
+    }
+
+    #[test]
+    fn test_cmp_asset_info() {
+        let mut infos = vec![
+            AssetInfo::cw20(Addr::unchecked("b")),
+            AssetInfo::native("uosmo"),
+            AssetInfo::cw20(Addr::unchecked("a")),
+            AssetInfo::native("uatom"),
+            AssetInfo::native("ibc/27394FB0"),
+        ];
+        infos.sort_by(cmp_asset_info);
+        assert_eq!(
+            infos,
+            vec![
+                AssetInfo::native("ibc/27394FB0"),
+                AssetInfo::native("uatom"),
+                AssetInfo::native("uosmo"),
+                AssetInfo::cw20(Addr::unchecked("a")),
+                AssetInfo::cw20(Addr::unchecked("b")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_assets() {
+        let assets = vec![
+            cw20("astro", 5u128),
+            Asset::native("uosmo", 1u128),
+            Asset::native("uatom", 0u128),
+            Asset::native("uatom", 2u128),
+            Asset::native("uosmo", 3u128),
+        ];
+        let expected = vec![
+            Asset::native("uatom", 2u128),
+            Asset::native("uosmo", 4u128),
+            cw20("astro", 5u128),
+        ];
+        assert_eq!(normalize_assets(&assets).unwrap().to_vec(), expected);
+
+        // The result does not depend on the order of the input
+        let mut reversed = assets.clone();
+        reversed.reverse();
+        assert_eq!(normalize_assets(&reversed).unwrap().to_vec(), expected);
+
+        // Normalizing is idempotent
+        let normalized = normalize_assets(&assets).unwrap();
+        assert_eq!(normalize_assets(&normalized).unwrap(), normalized);
+    }
+
+    #[test_case(vec![] => Vec::<cosmwasm_std::Coin>::new(); "empty")]
+    #[test_case(
+        vec![coin(1, "uosmo"), coin(2, "uatom"), coin(3, "uosmo")]
+        => vec![coin(2, "uatom"), coin(4, "uosmo")];
+        "merged and sorted"
+    )]
+    #[test_case(
+        vec![coin(0, "uosmo"), coin(2, "uatom"), coin(0, "uion"), coin(0, "uion")]
+        => vec![coin(2, "uatom")];
+        "zero amounts removed"
+    )]
+    #[test_case(
+        vec![coin(1, "uosmo"), coin(1, "ibc/27394FB0"), coin(1, "factory/osmo1/x")]
+        => vec![coin(1, "factory/osmo1/x"), coin(1, "ibc/27394FB0"), coin(1, "uosmo")];
+        "sorted bytewise by denom"
+    )]
+    fn test_merge_coins(coins: Vec<cosmwasm_std::Coin>) -> Vec<cosmwasm_std::Coin> {
+        merge_coins(coins).unwrap()
+    }
+
+    #[test]
+    fn test_merge_coins_overflow() {
+        assert!(merge_coins(vec![coin(u128::MAX, "uosmo"), coin(1, "uosmo")]).is_err());
+    }
+}
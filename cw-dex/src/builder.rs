@@ -0,0 +1,554 @@
+//! Contains builders for the main operations of the `Pool` and staking traits,
+//! so that optional behaviors like auto-staking, sending the received assets
+//! to a recipient, deadlines and dust handling can be combined without passing
+//! every option to every call.
+//!
+//! ```ignore
+//! let res = ProvideLiquidity::builder(&pool)
+//!     .assets(assets)
+//!     .min_out(MinOut::Slippage(Decimal::percent(1)))
+//!     .auto_stake(&staking)
+//!     .deadline(deadline)
+//!     .build(deps, &env)?;
+//! ```
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{Addr, Decimal, Deps, Env, Event, Response, StdError, Timestamp, Uint128};
+
+use crate::dust::{DustConfig, DustPolicy};
+use crate::error::CwDexError;
+use crate::traits::{assert_compatible, MinOut, Pool, Stake};
+
+/// Returns an error if the block time of `env` is after `deadline`.
+fn assert_deadline(env: &Env, deadline: Option<Timestamp>) -> Result<(), CwDexError> {
+    match deadline {
+        Some(deadline) if env.block.time > deadline => {
+            Err(CwDexError::DeadlineExceeded { deadline })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Builder for providing liquidity to a pool, see [`Pool::provide_liquidity`].
+///
+/// The LP tokens received are kept by the contract unless
+/// [`ProvideLiquidity::auto_stake`] or [`ProvideLiquidity::recipient`] is set.
+/// Both use the resolved minimum amount of LP tokens, which the pool
+/// guarantees to be received, rather than the simulated amount, which is only
+/// an estimate. Any LP tokens received in excess stay with the contract, and
+/// building fails if the minimum amount is zero.
+pub struct ProvideLiquidity<'a, P: Pool> {
+    pool: &'a P,
+    assets: AssetList,
+    min_out: MinOut,
+    max_in: Option<AssetList>,
+    auto_stake: Option<&'a dyn Stake>,
+    recipient: Option<Addr>,
+    deadline: Option<Timestamp>,
+    dust: DustConfig,
+}
+
+impl<'a, P: Pool> ProvideLiquidity<'a, P> {
+    /// Returns a builder providing no assets to `pool`, with no minimum
+    /// amount of LP tokens, no deadline and dust ignored.
+    pub fn builder(pool: &'a P) -> Self {
+        Self {
+            pool,
+            assets: AssetList::new(),
+            min_out: MinOut::Absolute(Uint128::zero()),
+            max_in: None,
+            auto_stake: None,
+            recipient: None,
+            deadline: None,
+            dust: DustConfig::default(),
+        }
+    }
+
+    /// Sets the assets to provide. They must already be held by the contract.
+    pub fn assets(mut self, assets: AssetList) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Sets the minimum amount of LP tokens to receive, see
+    /// [`Pool::provide_liquidity_with_min_out`].
+    pub fn min_out(mut self, min_out: impl Into<MinOut>) -> Self {
+        self.min_out = min_out.into();
+        self
+    }
+
+    /// Sets the maximum amounts of the assets to provide, see
+    /// [`Pool::provide_liquidity_with_max_in`].
+    pub fn max_in(mut self, max_in: AssetList) -> Self {
+        self.max_in = Some(max_in);
+        self
+    }
+
+    /// Stakes the minimum amount of LP tokens to receive in `staking`. Can not
+    /// be combined with [`ProvideLiquidity::recipient`]. Building fails with
+    /// [`CwDexError::LpTokenMismatch`] if `staking` does not stake the LP
+    /// token of the pool.
+    pub fn auto_stake(mut self, staking: &'a dyn Stake) -> Self {
+        self.auto_stake = Some(staking);
+        self
+    }
+
+    /// Sends the minimum amount of LP tokens to receive to `recipient`. Can not
+    /// be combined with [`ProvideLiquidity::auto_stake`].
+    pub fn recipient(mut self, recipient: Addr) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    /// Fails the operation if it is executed in a block after `deadline`.
+    pub fn deadline(mut self, deadline: Timestamp) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets how dust of the pool assets held by the contract is handled. With
+    /// [`DustPolicy::ProvideOnNext`] it is added to the assets provided, with
+    /// [`DustPolicy::Sweep`] it is sent to the recipient of the policy.
+    pub fn dust(mut self, dust: DustConfig) -> Self {
+        self.dust = dust;
+        self
+    }
+
+    /// Returns the response providing liquidity with the configured options.
+    pub fn build(self, deps: Deps, env: &Env) -> Result<Response, CwDexError> {
+        assert_deadline(env, self.deadline)?;
+        if self.auto_stake.is_some() && self.recipient.is_some() {
+            return Err(StdError::generic_err(
+                "ProvideLiquidity: auto_stake and recipient can not be combined",
+            )
+            .into());
+        }
+        if let Some(staking) = self.auto_stake {
+            assert_compatible(self.pool, staking)?;
+        }
+
+        let mut assets = self.assets;
+        let mut sweep_msgs = vec![];
+        match &self.dust.policy {
+            DustPolicy::Ignore => {}
+            DustPolicy::ProvideOnNext => {
+                let dust =
+                    self.dust
+                        .query_dust(deps, env, &self.pool.pool_assets(deps)?, &assets)?;
+                assets.add_many(&dust)?;
+            }
+            DustPolicy::Sweep { recipient } => {
+                // Only sweep the balances not about to be provided
+                sweep_msgs = self
+                    .dust
+                    .query_dust(deps, env, &self.pool.pool_assets(deps)?, &assets)?
+                    .transfer_msgs(recipient)?;
+            }
+        }
+
+        let min_out = match self.min_out {
+            MinOut::Absolute(min_out) => min_out,
+            MinOut::Slippage(_) => {
+                let lp_out = self
+                    .pool
+                    .simulate_provide_liquidity(deps, env, assets.clone())?
+                    .amount;
+                self.min_out.resolve(lp_out)?
+            }
+        };
+        if min_out.is_zero() && (self.auto_stake.is_some() || self.recipient.is_some()) {
+            return Err(StdError::generic_err(
+                "ProvideLiquidity: min_out must not be zero with auto_stake or recipient",
+            )
+            .into());
+        }
+
+        let mut res =
+            self.pool
+                .provide_liquidity_with_max_in(deps, env, assets, min_out, self.max_in)?;
+        if let Some(staking) = self.auto_stake {
+            res = merge_responses(vec![res, staking.stake(deps, env, min_out)?]);
+        }
+        if let Some(recipient) = self.recipient {
+            let lp_token = Asset::new(self.pool.lp_token(), min_out);
+            res = res
+                .add_message(lp_token.transfer_msg(&recipient)?)
+                .add_event(
+                    Event::new("apollo/cw-dex/provide_liquidity_to")
+                        .add_attribute("recipient", recipient)
+                        .add_attribute("lp_token", lp_token.to_string()),
+                );
+        }
+
+        Ok(res.add_messages(sweep_msgs))
+    }
+}
+
+/// Builder for withdrawing liquidity from a pool, see
+/// [`Pool::withdraw_liquidity`].
+pub struct WithdrawLiquidity<'a, P: Pool> {
+    pool: &'a P,
+    lp_token: Option<Asset>,
+    min_out: AssetList,
+    max_slippage: Option<Decimal>,
+    recipient: Option<Addr>,
+    deadline: Option<Timestamp>,
+}
+
+impl<'a, P: Pool> WithdrawLiquidity<'a, P> {
+    /// Returns a builder withdrawing from `pool`, with no minimum amounts and
+    /// no deadline. [`WithdrawLiquidity::lp_token`] must be set.
+    pub fn builder(pool: &'a P) -> Self {
+        Self {
+            pool,
+            lp_token: None,
+            min_out: AssetList::new(),
+            max_slippage: None,
+            recipient: None,
+            deadline: None,
+        }
+    }
+
+    /// Sets the LP tokens to withdraw.
+    pub fn lp_token(mut self, lp_token: Asset) -> Self {
+        self.lp_token = Some(lp_token);
+        self
+    }
+
+    /// Sets the minimum amounts of the assets to receive.
+    pub fn min_out(mut self, min_out: AssetList) -> Self {
+        self.min_out = min_out;
+        self
+    }
+
+    /// Sets the minimum amounts of the assets to receive to the simulated
+    /// amounts minus `max_slippage`, see [`Pool::simulate_withdraw_min_out`].
+    /// Takes precedence over [`WithdrawLiquidity::min_out`].
+    pub fn max_slippage(mut self, max_slippage: Decimal) -> Self {
+        self.max_slippage = Some(max_slippage);
+        self
+    }
+
+    /// Sends the minimum amounts of the withdrawn assets to `recipient`, see
+    /// [`Pool::withdraw_liquidity_to`].
+    pub fn recipient(mut self, recipient: Addr) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    /// Fails the operation if it is executed in a block after `deadline`.
+    pub fn deadline(mut self, deadline: Timestamp) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the response withdrawing liquidity with the configured options.
+    pub fn build(self, deps: Deps, env: &Env) -> Result<Response, CwDexError> {
+        assert_deadline(env, self.deadline)?;
+        let lp_token = self
+            .lp_token
+            .ok_or_else(|| StdError::generic_err("WithdrawLiquidity: lp_token is not set"))?;
+
+        let min_out = match self.max_slippage {
+            Some(max_slippage) => {
+                self.pool
+                    .simulate_withdraw_min_out(deps, &lp_token, max_slippage)?
+            }
+            None => self.min_out,
+        };
+
+        self.pool
+            .withdraw_liquidity_to(deps, env, lp_token, min_out, self.recipient)
+    }
+}
+
+/// Builder for swapping in a pool, see [`Pool::swap`].
+///
+/// If [`Swap::recipient`] is set, the resolved minimum amount to receive, which
+/// the pool guarantees, is sent to the recipient rather than the amount
+/// returned by [`Pool::simulate_swap`]. Any amount received in excess stays
+/// with the contract, and building fails if the minimum amount is zero.
+pub struct Swap<'a, P: Pool> {
+    pool: &'a P,
+    offer: Option<Asset>,
+    ask: Option<AssetInfo>,
+    min_out: MinOut,
+    recipient: Option<Addr>,
+    deadline: Option<Timestamp>,
+}
+
+impl<'a, P: Pool> Swap<'a, P> {
+    /// Returns a builder swapping in `pool`, with no minimum amount and no
+    /// deadline. [`Swap::offer`] and [`Swap::ask`] must be set.
+    pub fn builder(pool: &'a P) -> Self {
+        Self {
+            pool,
+            offer: None,
+            ask: None,
+            min_out: MinOut::Absolute(Uint128::zero()),
+            recipient: None,
+            deadline: None,
+        }
+    }
+
+    /// Sets the asset to offer. It must already be held by the contract.
+    pub fn offer(mut self, offer: Asset) -> Self {
+        self.offer = Some(offer);
+        self
+    }
+
+    /// Sets the asset to receive.
+    pub fn ask(mut self, ask: AssetInfo) -> Self {
+        self.ask = Some(ask);
+        self
+    }
+
+    /// Sets the minimum amount to receive, see [`Pool::swap_with_min_out`].
+    pub fn min_out(mut self, min_out: impl Into<MinOut>) -> Self {
+        self.min_out = min_out.into();
+        self
+    }
+
+    /// Sends the minimum amount of the asset to receive to `recipient`.
+    pub fn recipient(mut self, recipient: Addr) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    /// Fails the operation if it is executed in a block after `deadline`.
+    pub fn deadline(mut self, deadline: Timestamp) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the response swapping with the configured options.
+    pub fn build(self, deps: Deps, env: &Env) -> Result<Response, CwDexError> {
+        assert_deadline(env, self.deadline)?;
+        let offer = self
+            .offer
+            .ok_or_else(|| StdError::generic_err("Swap: offer is not set"))?;
+        let ask = self
+            .ask
+            .ok_or_else(|| StdError::generic_err("Swap: ask is not set"))?;
+
+        let Some(recipient) = self.recipient else {
+            return self
+                .pool
+                .swap_with_min_out(deps, env, offer, ask, self.min_out);
+        };
+
+        let min_out = match self.min_out {
+            MinOut::Absolute(min_out) => min_out,
+            MinOut::Slippage(_) => {
+                let received = self.pool.simulate_swap(deps, offer.clone(), ask.clone())?;
+                self.min_out.resolve(received)?
+            }
+        };
+        if min_out.is_zero() {
+            return Err(
+                StdError::generic_err("Swap: min_out must not be zero with recipient").into(),
+            );
+        }
+        let sent = Asset::new(ask.clone(), min_out);
+
+        Ok(self
+            .pool
+            .swap(deps, env, offer, ask, min_out)?
+            .add_message(sent.transfer_msg(&recipient)?)
+            .add_event(
+                Event::new("apollo/cw-dex/swap_to")
+                    .add_attribute("recipient", recipient)
+                    .add_attribute("sent", sent.to_string()),
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_dependencies_with_balance, mock_env};
+    use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, Decimal, Uint128};
+
+    use super::{ProvideLiquidity, Swap, WithdrawLiquidity};
+    use crate::dust::{DustConfig, DustPolicy};
+    use crate::error::CwDexError;
+    use crate::testing::{attribute, MockPool, MockStaking};
+    use crate::traits::MinOut;
+
+    /// A pool of uatom and usdc at a price of one, returning one LP token per
+    /// asset provided
+    fn mock_pool() -> MockPool {
+        MockPool::new(&["uatom", "usdc"])
+    }
+
+    fn assets() -> AssetList {
+        vec![
+            Asset::native("uatom", 100u128),
+            Asset::native("usdc", 100u128),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_provide_liquidity() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+
+        let res = ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .min_out(MinOut::Slippage(Decimal::percent(10)))
+            .auto_stake(&MockStaking::new("lp"))
+            .build(deps.as_ref(), &env)
+            .unwrap();
+        // Only the guaranteed amount is staked, not the simulated 200
+        assert_eq!(attribute(&res, "provide", "min_out"), "180");
+        assert_eq!(attribute(&res, "stake", "amount"), "180");
+
+        let res = ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .min_out(Uint128::new(190))
+            .recipient(Addr::unchecked("recipient"))
+            .build(deps.as_ref(), &env)
+            .unwrap();
+        assert_eq!(attribute(&res, "provide", "min_out"), "190");
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: vec![Coin::new(190, "lp")],
+            })
+        );
+
+        let err = ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .recipient(Addr::unchecked("recipient"))
+            .build(deps.as_ref(), &env)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("min_out must not be zero"),
+            "{err}"
+        );
+
+        let err = ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .auto_stake(&MockStaking::new("lp"))
+            .recipient(Addr::unchecked("recipient"))
+            .build(deps.as_ref(), &env)
+            .unwrap_err();
+        assert!(err.to_string().contains("can not be combined"), "{err}");
+
+        let err = ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .auto_stake(&MockStaking::new("other_lp"))
+            .build(deps.as_ref(), &env)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::LpTokenMismatch {
+                lp_token: AssetInfo::native("lp"),
+                staked_asset: AssetInfo::native("other_lp"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_provide_liquidity_dust() {
+        let env = mock_env();
+        let deps =
+            mock_dependencies_with_balance(&[Coin::new(105, "uatom"), Coin::new(103, "usdc")]);
+        let dust = |policy| DustConfig {
+            policy,
+            thresholds: vec![Asset::native("uatom", 10u128)],
+        };
+
+        let res = ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .dust(dust(DustPolicy::ProvideOnNext))
+            .build(deps.as_ref(), &env)
+            .unwrap();
+        assert_eq!(attribute(&res, "provide", "assets"), "uatom:105,usdc:100");
+
+        let res = ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .dust(dust(DustPolicy::Sweep {
+                recipient: Addr::unchecked("treasury"),
+            }))
+            .build(deps.as_ref(), &env)
+            .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![Coin::new(5, "uatom")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_withdraw_liquidity() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+
+        let res = WithdrawLiquidity::builder(&mock_pool())
+            .lp_token(Asset::native("lp", 100u128))
+            .max_slippage(Decimal::percent(10))
+            .build(deps.as_ref(), &env)
+            .unwrap();
+        assert_eq!(attribute(&res, "withdraw", "min_out"), "uatom:45,usdc:45");
+
+        let err = WithdrawLiquidity::builder(&mock_pool())
+            .build(deps.as_ref(), &env)
+            .unwrap_err();
+        assert!(err.to_string().contains("lp_token is not set"), "{err}");
+    }
+
+    #[test]
+    fn test_swap() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+
+        let res = Swap::builder(&mock_pool())
+            .offer(Asset::native("uatom", 100u128))
+            .ask(AssetInfo::native("usdc"))
+            .min_out(MinOut::Slippage(Decimal::percent(1)))
+            .recipient(Addr::unchecked("recipient"))
+            .build(deps.as_ref(), &env)
+            .unwrap();
+        assert_eq!(attribute(&res, "swap", "min_out"), "99");
+        assert_eq!(attribute(&res, "apollo/cw-dex/swap_to", "sent"), "usdc:99");
+
+        let err = Swap::builder(&mock_pool())
+            .offer(Asset::native("uatom", 100u128))
+            .ask(AssetInfo::native("usdc"))
+            .recipient(Addr::unchecked("recipient"))
+            .build(deps.as_ref(), &env)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("min_out must not be zero"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_deadline() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let deadline = env.block.time.minus_seconds(1);
+
+        let err = Swap::builder(&mock_pool())
+            .offer(Asset::native("uatom", 100u128))
+            .ask(AssetInfo::native("usdc"))
+            .deadline(deadline)
+            .build(deps.as_ref(), &env)
+            .unwrap_err();
+        assert_eq!(err, CwDexError::DeadlineExceeded { deadline });
+
+        // A deadline in the current block is not exceeded
+        assert!(ProvideLiquidity::builder(&mock_pool())
+            .assets(assets())
+            .deadline(env.block.time)
+            .build(deps.as_ref(), &env)
+            .is_ok());
+    }
+}
@@ -0,0 +1,213 @@
+//! Contains the `CachedPool` wrapper for memoizing pool queries within a
+//! single contract execution.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{to_json_vec, Addr, Deps, Env, Response, StdResult, Uint128};
+
+use crate::error::CwDexError;
+use crate::traits::Pool;
+
+/// A wrapper around a [`Pool`] that caches the results of its query functions
+/// per unique input for the lifetime of the wrapper.
+///
+/// Messages returned by a contract are only executed after the contract
+/// returns, so the state of the pool can not change during an execution. This
+/// makes it safe to reuse query results within one execution, e.g. when the
+/// same swap is simulated for validation, `min_out` and event data. The
+/// wrapper must however not be kept across executions, e.g. by saving it to
+/// storage.
+///
+/// Errors are not cached.
+pub struct CachedPool<P: Pool> {
+    pool: P,
+    pool_liquidity: RefCell<Option<AssetList>>,
+    provide_liquidity: RefCell<HashMap<Vec<u8>, Asset>>,
+    withdraw_liquidity: RefCell<HashMap<Vec<u8>, AssetList>>,
+    swap: RefCell<HashMap<Vec<u8>, Uint128>>,
+}
+
+impl<P: Pool> CachedPool<P> {
+    /// Creates a new `CachedPool` with an empty cache wrapping `pool`.
+    pub fn new(pool: P) -> Self {
+        Self {
+            pool,
+            pool_liquidity: RefCell::new(None),
+            provide_liquidity: RefCell::new(HashMap::new()),
+            withdraw_liquidity: RefCell::new(HashMap::new()),
+            swap: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns a reference to the wrapped pool.
+    pub fn inner(&self) -> &P {
+        &self.pool
+    }
+
+    /// Consumes the wrapper, returning the wrapped pool.
+    pub fn into_inner(self) -> P {
+        self.pool
+    }
+}
+
+impl<P: Pool> Pool for CachedPool<P> {
+    fn provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.pool.provide_liquidity(deps, env, assets, min_out)
+    }
+
+    fn lp_token(&self) -> AssetInfo {
+        self.pool.lp_token()
+    }
+
+    fn withdraw_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+    ) -> Result<Response, CwDexError> {
+        self.pool.withdraw_liquidity(deps, env, lp_token, min_out)
+    }
+
+    fn withdraw_liquidity_to(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+        recipient: Option<Addr>,
+    ) -> Result<Response, CwDexError> {
+        self.pool
+            .withdraw_liquidity_to(deps, env, lp_token, min_out, recipient)
+    }
+
+    fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.pool
+            .swap(deps, env, offer_asset, ask_asset_info, min_out)
+    }
+
+    fn get_pool_liquidity(&self, deps: Deps) -> Result<AssetList, CwDexError> {
+        if let Some(liquidity) = self.pool_liquidity.borrow().as_ref() {
+            return Ok(liquidity.clone());
+        }
+        let liquidity = self.pool.get_pool_liquidity(deps)?;
+        *self.pool_liquidity.borrow_mut() = Some(liquidity.clone());
+        Ok(liquidity)
+    }
+
+    fn simulate_provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+    ) -> Result<Asset, CwDexError> {
+        let key = to_json_vec(&assets)?;
+        if let Some(lp_token) = self.provide_liquidity.borrow().get(&key) {
+            return Ok(lp_token.clone());
+        }
+        let lp_token = self.pool.simulate_provide_liquidity(deps, env, assets)?;
+        self.provide_liquidity
+            .borrow_mut()
+            .insert(key, lp_token.clone());
+        Ok(lp_token)
+    }
+
+    fn simulate_withdraw_liquidity(
+        &self,
+        deps: Deps,
+        lp_token: &Asset,
+    ) -> Result<AssetList, CwDexError> {
+        let key = to_json_vec(lp_token)?;
+        if let Some(assets) = self.withdraw_liquidity.borrow().get(&key) {
+            return Ok(assets.clone());
+        }
+        let assets = self.pool.simulate_withdraw_liquidity(deps, lp_token)?;
+        self.withdraw_liquidity
+            .borrow_mut()
+            .insert(key, assets.clone());
+        Ok(assets)
+    }
+
+    fn simulate_swap(
+        &self,
+        deps: Deps,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+    ) -> StdResult<Uint128> {
+        let key = to_json_vec(&(&offer_asset, &ask_asset_info))?;
+        if let Some(amount) = self.swap.borrow().get(&key) {
+            return Ok(*amount);
+        }
+        let amount = self.pool.simulate_swap(deps, offer_asset, ask_asset_info)?;
+        self.swap.borrow_mut().insert(key, amount);
+        Ok(amount)
+    }
+
+    fn pool_assets(&self, deps: Deps) -> StdResult<Vec<AssetInfo>> {
+        self.pool.pool_assets(deps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Decimal, Uint128};
+
+    use super::CachedPool;
+    use crate::testing::MockPool;
+    use crate::traits::Pool;
+
+    #[test]
+    fn test_cached_pool() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = CachedPool::new(
+            MockPool::new(&["uatom", "uosmo"]).with_swap_rate(Some(Decimal::percent(50))),
+        );
+
+        let offer = Asset::native("uatom", 10u128);
+        let ask = AssetInfo::native("uosmo");
+        for _ in 0..3 {
+            assert_eq!(
+                pool.simulate_swap(deps.as_ref(), offer.clone(), ask.clone())
+                    .unwrap(),
+                Uint128::new(5)
+            );
+        }
+        assert_eq!(pool.inner().queries.get(), 1);
+
+        // A different input is not served from the cache
+        let offer = Asset::native("uatom", 20u128);
+        assert_eq!(
+            pool.simulate_swap(deps.as_ref(), offer, ask).unwrap(),
+            Uint128::new(10)
+        );
+        assert_eq!(pool.inner().queries.get(), 2);
+
+        let assets: AssetList = vec![Asset::native("uatom", 10u128)].into();
+        for _ in 0..3 {
+            pool.simulate_provide_liquidity(deps.as_ref(), &env, assets.clone())
+                .unwrap();
+            pool.simulate_withdraw_liquidity(deps.as_ref(), &Asset::native("lp", 10u128))
+                .unwrap();
+            pool.get_pool_liquidity(deps.as_ref()).unwrap();
+        }
+        assert_eq!(pool.inner().queries.get(), 5);
+    }
+}
@@ -0,0 +1,1047 @@
+//! Contains composite operations built on top of the `Pool` and staking
+//! traits, so that the ordering and simulation subtleties of common multi-step
+//! flows are implemented once for all dexes.
+
+use std::cmp::Ordering;
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use apollo_utils::responses::merge_responses;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Deps, Env, Event, Response, StdError, StdResult, Uint128, Uint256};
+
+use crate::dust::{DustConfig, DustPolicy};
+use crate::error::CwDexError;
+use crate::reply::reply_on_last_message;
+use crate::traits::{assert_compatible, MinOut, Pool, Rewards, Stake};
+
+/// Reply ID for the last provide liquidity message of [`swap_and_stake`].
+/// The contract must call [`swap_and_stake_reply`] when handling this reply.
+pub const SWAP_AND_STAKE_REPLY_ID: u64 = 456;
+
+/// Swaps part of `offer` into the other asset of a two asset pool, such that
+/// the resulting assets are in the same ratio as the pool reserves after the
+/// swap, provides them as liquidity and stakes the received LP tokens in
+/// `staking`.
+///
+/// The LP tokens are only known once the provide has executed, so they are
+/// staked in a reply. The last provide liquidity message is sent as a
+/// submessage with reply ID [`SWAP_AND_STAKE_REPLY_ID`]. The contract must
+/// handle this reply by calling [`swap_and_stake_reply`] with the same
+/// `staking`.
+///
+/// Arguments:
+/// - `offer`: The asset to deposit. Must be one of the assets of the pool and
+///   already be held by the contract.
+/// - `pool`: The pool to provide liquidity to.
+/// - `staking`: The staking of the LP tokens of `pool`. Returns
+///   [`CwDexError::LpTokenMismatch`] if it stakes another asset, see
+///   [`assert_compatible`].
+/// - `min_lp_out`: The minimum amount of LP tokens to receive.
+/// - `dust`: How to handle remainders of the pool assets. With
+///   [`DustPolicy::ProvideOnNext`], dust held by the contract is included in
+///   the liquidity provided.
+pub fn swap_and_stake(
+    deps: Deps,
+    env: &Env,
+    offer: Asset,
+    pool: &impl Pool,
+    staking: &impl Stake,
+    min_lp_out: Uint128,
+    dust: &DustConfig,
+) -> Result<Response, CwDexError> {
+    swap_and_stake_with_reply_id(
+        deps,
+        env,
+        offer,
+        pool,
+        staking,
+        min_lp_out,
+        dust,
+        SWAP_AND_STAKE_REPLY_ID,
+    )
+}
+
+/// Same as [`swap_and_stake`], but sends the last provide liquidity message
+/// with reply ID `reply_id` instead of [`SWAP_AND_STAKE_REPLY_ID`], e.g. to
+/// avoid a collision with the contract's own reply IDs.
+#[allow(clippy::too_many_arguments)]
+pub fn swap_and_stake_with_reply_id(
+    deps: Deps,
+    env: &Env,
+    offer: Asset,
+    pool: &impl Pool,
+    staking: &impl Stake,
+    min_lp_out: Uint128,
+    dust: &DustConfig,
+    reply_id: u64,
+) -> Result<Response, CwDexError> {
+    assert_compatible(pool, staking)?;
+    swap_and_provide(deps, env, offer, pool, min_lp_out, dust, reply_id)
+}
+
+/// Swaps part of `offer` into the other asset of a two asset pool like
+/// [`swap_and_stake`] and provides them as liquidity, without staking the LP
+/// tokens, e.g. to send them to a user.
+///
+/// The last provide liquidity message is sent as a submessage with reply ID
+/// `reply_id`, so that the contract can measure the received LP tokens when
+/// handling the reply. Returns an error if the provide of `pool` already
+/// replies on its last message.
+///
+/// The optimal swap amount is binary searched to the unit, with one swap
+/// simulation per step, i.e. about as many simulations as the offer amount
+/// has bits.
+pub fn swap_and_provide(
+    deps: Deps,
+    env: &Env,
+    offer: Asset,
+    pool: &impl Pool,
+    min_lp_out: Uint128,
+    dust: &DustConfig,
+    reply_id: u64,
+) -> Result<Response, CwDexError> {
+    let pool_assets = pool.pool_assets(deps)?;
+    if !pool_assets.contains(&offer.info) {
+        return Err(CwDexError::AssetNotInPool { asset: offer.info });
+    }
+    let ask_info = match pool_assets.as_slice() {
+        [a, b] => {
+            if a == &offer.info {
+                b.clone()
+            } else {
+                a.clone()
+            }
+        }
+        _ => {
+            return Err(StdError::generic_err(
+                "swap_and_provide only supports pools with two assets",
+            )
+            .into())
+        }
+    };
+
+    let collected_dust = match dust.policy {
+        DustPolicy::ProvideOnNext => {
+            dust.query_dust(deps, env, &pool_assets, &vec![offer.clone()].into())?
+        }
+        _ => AssetList::new(),
+    };
+    let dust_of = |info: &AssetInfo| {
+        collected_dust
+            .find(info)
+            .map(|asset| asset.amount)
+            .unwrap_or_default()
+    };
+    let deposit = Asset::new(
+        offer.info.clone(),
+        offer.amount.checked_add(dust_of(&offer.info))?,
+    );
+    let ask_held = dust_of(&ask_info);
+
+    let (swap_amount, ask_amount) = optimal_swap_amount(deps, pool, &deposit, &ask_info, ask_held)?;
+
+    let mut responses = vec![];
+    if !swap_amount.is_zero() {
+        responses.push(pool.swap(
+            deps,
+            env,
+            Asset::new(offer.info.clone(), swap_amount),
+            ask_info.clone(),
+            ask_amount,
+        )?);
+    }
+
+    let provide_assets = vec![
+        Asset::new(offer.info.clone(), deposit.amount - swap_amount),
+        Asset::new(ask_info.clone(), ask_amount.checked_add(ask_held)?),
+    ];
+    let provide_res = pool.provide_liquidity(deps, env, provide_assets.into(), min_lp_out)?;
+    responses.push(reply_on_last_message(provide_res, reply_id)?);
+
+    let mut event = Event::new("apollo/cw-dex/swap_and_provide")
+        .add_attribute("offer", offer.to_string())
+        .add_attribute("swap_amount", swap_amount)
+        .add_attribute("ask", Asset::new(ask_info, ask_amount).to_string())
+        .add_attribute("min_lp_out", min_lp_out);
+    if collected_dust.len() > 0 {
+        event = event.add_attribute("dust", collected_dust.to_string());
+    }
+
+    Ok(merge_responses(responses).add_event(event))
+}
+
+/// Stakes the LP tokens received by [`swap_and_stake`]. Must be called when
+/// handling the reply with ID [`SWAP_AND_STAKE_REPLY_ID`], or the reply ID
+/// passed to [`swap_and_stake_with_reply_id`].
+///
+/// NB: This stakes the entire LP token balance of the contract, so the
+/// contract must not hold unstaked LP tokens of `pool` that it wants to keep
+/// unstaked.
+///
+/// With [`DustPolicy::Sweep`], the dust of the pool assets left in the
+/// contract is sent to the recipient. `dust` should be the same config as
+/// passed to [`swap_and_stake`].
+///
+/// Returns [`CwDexError::LpTokenMismatch`] if `staking` does not stake the LP
+/// token of `pool`, see [`assert_compatible`].
+pub fn swap_and_stake_reply(
+    deps: Deps,
+    env: &Env,
+    pool: &impl Pool,
+    staking: &impl Stake,
+    dust: &DustConfig,
+) -> Result<Response, CwDexError> {
+    assert_compatible(pool, staking)?;
+    let lp_balance = pool
+        .lp_token()
+        .query_balance(&deps.querier, &env.contract.address)?;
+    if lp_balance.is_zero() {
+        return Err(StdError::generic_err("swap_and_stake: no LP tokens received").into());
+    }
+
+    let sweep_msgs = dust.sweep_msgs(deps, env, &pool.pool_assets(deps)?)?;
+
+    Ok(staking
+        .stake(deps, env, lp_balance)?
+        .add_messages(sweep_msgs))
+}
+
+/// Provides `assets` as liquidity to `pool`, first swapping the asset of
+/// `assets` that is not an asset of `pool` into one of the pool assets through
+/// `intermediate_pool`. This allows compounding rewards, e.g. ASTRO claimed
+/// with [`crate::traits::Rewards::claim_rewards`], into any pool that has an
+/// asset in common with a pool of the reward token.
+///
+/// The swap uses its simulated return as `min_out`, and the received amount is
+/// provided together with the other assets in the same response. If all of
+/// `assets` are assets of `pool`, liquidity is provided without a swap.
+///
+/// Arguments:
+/// - `assets`: The assets to provide. At most one of them may be an asset not
+///   in `pool`, and it must be an asset of `intermediate_pool`. The assets must
+///   already be held by the contract.
+/// - `intermediate_pool`: The pool to swap the asset not in `pool` in. Must
+///   contain one of the assets of `pool`. If it contains several, the first in
+///   the order of [`Pool::pool_assets`] of `pool` is received.
+/// - `pool`: The pool to provide liquidity to.
+/// - `min_lp_out`: The minimum amount of LP tokens to receive.
+pub fn route_and_provide_liquidity(
+    deps: Deps,
+    env: &Env,
+    assets: AssetList,
+    intermediate_pool: &impl Pool,
+    pool: &impl Pool,
+    min_lp_out: Uint128,
+) -> Result<Response, CwDexError> {
+    let pool_assets = pool.pool_assets(deps)?;
+    let (mut provide_assets, routed): (Vec<Asset>, Vec<Asset>) = assets
+        .into_iter()
+        .filter(|asset| !asset.amount.is_zero())
+        .cloned()
+        .partition(|asset| pool_assets.contains(&asset.info));
+
+    let routed = match routed.as_slice() {
+        [] => {
+            return pool.provide_liquidity(deps, env, provide_assets.into(), min_lp_out);
+        }
+        [asset] => asset.clone(),
+        _ => {
+            return Err(StdError::generic_err(
+                "route_and_provide_liquidity can only route one asset not in the pool",
+            )
+            .into())
+        }
+    };
+
+    let intermediate_assets = intermediate_pool.pool_assets(deps)?;
+    if !intermediate_assets.contains(&routed.info) {
+        return Err(CwDexError::AssetNotInPool { asset: routed.info });
+    }
+    let ask_info = pool_assets
+        .iter()
+        .find(|info| intermediate_assets.contains(info))
+        .cloned()
+        .ok_or_else(|| {
+            StdError::generic_err("Intermediate pool has no asset in common with the pool")
+        })?;
+
+    let received = intermediate_pool.simulate_swap(deps, routed.clone(), ask_info.clone())?;
+    let swap_res = intermediate_pool.swap(deps, env, routed.clone(), ask_info.clone(), received)?;
+
+    match provide_assets
+        .iter_mut()
+        .find(|asset| asset.info == ask_info)
+    {
+        Some(asset) => asset.amount += received,
+        None => provide_assets.push(Asset::new(ask_info.clone(), received)),
+    }
+    let provide_res = pool.provide_liquidity(deps, env, provide_assets.into(), min_lp_out)?;
+
+    let event = Event::new("apollo/cw-dex/route_and_provide_liquidity")
+        .add_attribute("routed", routed.to_string())
+        .add_attribute("received", Asset::new(ask_info, received).to_string())
+        .add_attribute("min_lp_out", min_lp_out);
+
+    Ok(merge_responses(vec![swap_res, provide_res]).add_event(event))
+}
+
+/// Claims the rewards of `staking` only if the contract has pending rewards,
+/// so that calling it twice in a block, e.g. when two users trigger
+/// compounding, does not fail or claim zero rewards in the second call.
+///
+/// If there are no pending rewards, returns a response without messages and
+/// with attribute `skipped` set to `no_rewards` on its event, which can be
+/// checked with [`claim_skipped`]. Otherwise the pending rewards are added in
+/// attribute `rewards`. Staking implementations that distribute rewards
+/// automatically and report no pending rewards, like Osmosis lockups, are
+/// always skipped, which is harmless as their claim sends no messages.
+pub fn claim_rewards_if_any(
+    deps: Deps,
+    env: &Env,
+    staking: &impl Rewards,
+) -> Result<Response, CwDexError> {
+    let pending_rewards = staking.query_pending_rewards(&deps.querier, &env.contract.address)?;
+    let event = Event::new("apollo/cw-dex/claim_rewards_if_any");
+    if pending_rewards.iter().all(|asset| asset.amount.is_zero()) {
+        return Ok(Response::new().add_event(event.add_attribute("skipped", "no_rewards")));
+    }
+
+    Ok(staking
+        .claim_rewards(deps, env)?
+        .add_event(event.add_attribute("rewards", pending_rewards.to_string())))
+}
+
+/// Returns whether `response` is from a claim that was skipped because there
+/// were no pending rewards, i.e. by [`claim_rewards_if_any`] or
+/// [`Rewards::claim_rewards_filtered`]. Contracts can use this to skip the
+/// follow-up steps of compounding, e.g. the reply handling of the swaps.
+pub fn claim_skipped(response: &Response) -> bool {
+    response.events.iter().any(|event| {
+        matches!(
+            event.ty.as_str(),
+            "apollo/cw-dex/claim_rewards_if_any" | "apollo/cw-dex/claim_rewards_filtered"
+        ) && event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "skipped" && attr.value == "no_rewards")
+    })
+}
+
+/// The target allocation of [`rebalance_pools`] and [`rebalance_idle`].
+#[cw_serde]
+pub struct RebalanceConfig {
+    /// The asset positions are valued in. Must be an asset of the rebalanced
+    /// pools, and is the asset held when not invested in a pool.
+    pub quote: AssetInfo,
+    /// The target share of the total value in the first position. Must not be
+    /// larger than one.
+    pub weight: Decimal,
+    /// The maximum slippage of each withdraw, swap and provide relative to its
+    /// simulation. Must not be larger than one.
+    pub max_slippage: Decimal,
+}
+
+/// Moves value between the positions of `lp_a` LP tokens of `pool_a` and
+/// `lp_b` LP tokens of `pool_b`, such that the share of the total value in
+/// `pool_a` is approximately [`RebalanceConfig::weight`].
+///
+/// The positions are valued in [`RebalanceConfig::quote`] at the amount
+/// received for withdrawing the LP tokens and swapping the withdrawn assets
+/// into the quote asset in the same pool. The LP tokens moved are withdrawn
+/// from the overweight pool, the withdrawn assets that are not assets of the
+/// other pool are swapped into the quote asset, and the result is provided to
+/// the other pool.
+///
+/// Each step receives at least its simulated amount less
+/// [`RebalanceConfig::max_slippage`], and only the minimum amounts received by
+/// a step are used by the next one, so the worst case of the operation is
+/// bounded by the combined bounds of its steps. Amounts received above the
+/// minimums are left in the contract. The swaps are simulated against the
+/// reserves before the withdrawal, so `max_slippage` must also cover the price
+/// impact of the withdrawal.
+///
+/// If there is nothing to move, returns a response without messages and with
+/// attribute `skipped` set to `balanced` on its event.
+pub fn rebalance_pools(
+    deps: Deps,
+    env: &Env,
+    pool_a: &impl Pool,
+    lp_a: Uint128,
+    pool_b: &impl Pool,
+    lp_b: Uint128,
+    config: &RebalanceConfig,
+) -> Result<Response, CwDexError> {
+    let value_a = liquidation_value(deps, pool_a, lp_a, &config.quote)?;
+    let value_b = liquidation_value(deps, pool_b, lp_b, &config.quote)?;
+    let event = Event::new("apollo/cw-dex/rebalance")
+        .add_attribute("value_a", value_a)
+        .add_attribute("value_b", value_b);
+
+    let (mut responses, moved) = match excess_share(value_a, value_b, config.weight)? {
+        Excess::None => {
+            return Ok(Response::new().add_event(event.add_attribute("skipped", "balanced")))
+        }
+        Excess::First(share) => {
+            let lp = Asset::new(pool_a.lp_token(), lp_a.mul_floor(share));
+            (move_liquidity(deps, env, pool_a, &lp, pool_b, config)?, lp)
+        }
+        Excess::Second(share) => {
+            let lp = Asset::new(pool_b.lp_token(), lp_b.mul_floor(share));
+            (move_liquidity(deps, env, pool_b, &lp, pool_a, config)?, lp)
+        }
+    };
+    responses.push(Response::new().add_event(event.add_attribute("moved", moved.to_string())));
+
+    Ok(merge_responses(responses))
+}
+
+/// Moves value between the position of `lp_amount` LP tokens of `pool` and
+/// `idle` of [`RebalanceConfig::quote`] held by the contract, such that the
+/// share of the total value in `pool` is approximately
+/// [`RebalanceConfig::weight`]. Works like [`rebalance_pools`], with the
+/// overweight side either withdrawn and swapped into the quote asset, or the
+/// quote asset partly swapped into the other asset of `pool` and provided.
+/// Providing idle quote requires `pool` to have two assets.
+pub fn rebalance_idle(
+    deps: Deps,
+    env: &Env,
+    pool: &impl Pool,
+    lp_amount: Uint128,
+    idle: Uint128,
+    config: &RebalanceConfig,
+) -> Result<Response, CwDexError> {
+    let value = liquidation_value(deps, pool, lp_amount, &config.quote)?;
+    let event = Event::new("apollo/cw-dex/rebalance")
+        .add_attribute("value_a", value)
+        .add_attribute("value_b", idle);
+
+    let (mut responses, moved) = match excess_share(value, idle, config.weight)? {
+        Excess::None => {
+            return Ok(Response::new().add_event(event.add_attribute("skipped", "balanced")))
+        }
+        Excess::First(share) => {
+            let lp = Asset::new(pool.lp_token(), lp_amount.mul_floor(share));
+            let (responses, _) = exit_into(deps, env, pool, &lp, &[], config)?;
+            (responses, lp)
+        }
+        Excess::Second(share) => {
+            let quote = Asset::new(config.quote.clone(), idle.mul_floor(share));
+            (provide_balanced(deps, env, pool, &quote, config)?, quote)
+        }
+    };
+    responses.push(Response::new().add_event(event.add_attribute("moved", moved.to_string())));
+
+    Ok(merge_responses(responses))
+}
+
+/// Provides `quote` to the two asset `pool`, first swapping the part of it
+/// that balances the provide with the pool reserves into the other asset, as
+/// not every pool supports single sided provides, e.g. Astroport xyk pairs.
+fn provide_balanced(
+    deps: Deps,
+    env: &Env,
+    pool: &impl Pool,
+    quote: &Asset,
+    config: &RebalanceConfig,
+) -> Result<Vec<Response>, CwDexError> {
+    let ask_info = match pool.pool_assets(deps)?.as_slice() {
+        [a, b] => {
+            if a == &quote.info {
+                b.clone()
+            } else {
+                a.clone()
+            }
+        }
+        _ => {
+            return Err(StdError::generic_err(
+                "rebalance_idle only supports providing to pools with two assets",
+            )
+            .into())
+        }
+    };
+
+    let (swap_amount, ask_amount) =
+        optimal_swap_amount(deps, pool, quote, &ask_info, Uint128::zero())?;
+    let mut responses = vec![];
+    if !swap_amount.is_zero() {
+        responses.push(pool.swap(
+            deps,
+            env,
+            Asset::new(quote.info.clone(), swap_amount),
+            ask_info.clone(),
+            ask_amount,
+        )?);
+    }
+    let provide_assets = vec![
+        Asset::new(quote.info.clone(), quote.amount - swap_amount),
+        Asset::new(ask_info, ask_amount),
+    ];
+    responses.push(pool.provide_liquidity_with_min_out(
+        deps,
+        env,
+        provide_assets.into(),
+        MinOut::Slippage(config.max_slippage),
+    )?);
+    Ok(responses)
+}
+
+/// Which of two positions is overweight, and the share of it to move.
+enum Excess {
+    None,
+    First(Decimal),
+    Second(Decimal),
+}
+
+/// Returns which of the positions of `value_a` and `value_b` is above its
+/// target share of the total, given the target share `weight` of the first
+/// position, and the share of it to move to the other position.
+fn excess_share(value_a: Uint128, value_b: Uint128, weight: Decimal) -> StdResult<Excess> {
+    if weight > Decimal::one() {
+        return Err(StdError::generic_err(format!(
+            "Rebalance weight {weight} is larger than one"
+        )));
+    }
+    let total = value_a.checked_add(value_b)?;
+    let target_a = total.mul_floor(weight);
+    Ok(match value_a.cmp(&target_a) {
+        Ordering::Greater => Excess::First(Decimal::from_ratio(value_a - target_a, value_a)),
+        Ordering::Less => Excess::Second(Decimal::from_ratio(target_a - value_a, value_b)),
+        Ordering::Equal => Excess::None,
+    })
+}
+
+/// Returns the amount of `quote` received for withdrawing `lp_amount` LP
+/// tokens from `pool` and swapping the withdrawn assets into `quote` in
+/// `pool`.
+fn liquidation_value(
+    deps: Deps,
+    pool: &impl Pool,
+    lp_amount: Uint128,
+    quote: &AssetInfo,
+) -> Result<Uint128, CwDexError> {
+    if !pool.pool_assets(deps)?.contains(quote) {
+        return Err(CwDexError::AssetNotInPool {
+            asset: quote.clone(),
+        });
+    }
+    if lp_amount.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let withdrawn =
+        pool.simulate_withdraw_liquidity(deps, &Asset::new(pool.lp_token(), lp_amount))?;
+    withdrawn
+        .into_iter()
+        .filter(|asset| !asset.amount.is_zero())
+        .try_fold(Uint128::zero(), |value, asset| {
+            let amount = if &asset.info == quote {
+                asset.amount
+            } else {
+                pool.simulate_swap(deps, asset.clone(), quote.clone())?
+            };
+            Ok(value.checked_add(amount)?)
+        })
+}
+
+/// Withdraws `lp` from `from` and provides the received assets to `to`,
+/// swapping the assets that are not assets of `to` into the quote asset.
+fn move_liquidity(
+    deps: Deps,
+    env: &Env,
+    from: &impl Pool,
+    lp: &Asset,
+    to: &impl Pool,
+    config: &RebalanceConfig,
+) -> Result<Vec<Response>, CwDexError> {
+    let (mut responses, received) = exit_into(deps, env, from, lp, &to.pool_assets(deps)?, config)?;
+    responses.push(to.provide_liquidity_with_min_out(
+        deps,
+        env,
+        received,
+        MinOut::Slippage(config.max_slippage),
+    )?);
+    Ok(responses)
+}
+
+/// Withdraws `lp` from `pool` and swaps the withdrawn assets that are not in
+/// `keep` into the quote asset in `pool`. Returns the responses and the
+/// minimum amounts of the assets received.
+fn exit_into(
+    deps: Deps,
+    env: &Env,
+    pool: &impl Pool,
+    lp: &Asset,
+    keep: &[AssetInfo],
+    config: &RebalanceConfig,
+) -> Result<(Vec<Response>, AssetList), CwDexError> {
+    let withdrawn = pool.simulate_withdraw_min_out(deps, lp, config.max_slippage)?;
+    let mut responses = vec![pool.withdraw_liquidity(deps, env, lp.clone(), withdrawn.clone())?];
+
+    let mut received = AssetList::new();
+    for asset in withdrawn
+        .into_iter()
+        .filter(|asset| !asset.amount.is_zero())
+    {
+        if asset.info == config.quote || keep.contains(&asset.info) {
+            received.add(asset)?;
+            continue;
+        }
+        let min_out = MinOut::Slippage(config.max_slippage).resolve(pool.simulate_swap(
+            deps,
+            asset.clone(),
+            config.quote.clone(),
+        )?)?;
+        responses.push(pool.swap(deps, env, asset.clone(), config.quote.clone(), min_out)?);
+        received.add(&Asset::new(config.quote.clone(), min_out))?;
+    }
+
+    Ok((responses, received))
+}
+
+/// The result of [`simulate_zap_out`].
+#[cw_serde]
+pub struct ZapOutSimulation {
+    /// The assets received for withdrawing the LP tokens
+    pub withdrawn: AssetList,
+    /// The expected amount of the ask asset received, with the swaps simulated
+    /// against the current reserves of the pool
+    pub expected: Uint128,
+    /// A lower bound of the amount of the ask asset received, with the swaps
+    /// simulated against the reserves after the withdrawal and the fee margin
+    /// deducted from their returns. Suitable as `min_out` of the zap.
+    pub worst_case: Uint128,
+}
+
+/// Simulates withdrawing `lp_token` from `pool` and swapping the withdrawn
+/// assets other than `ask` into `ask` in `pool`, i.e. exiting the position
+/// into a single asset.
+///
+/// The swaps are executed after the withdrawal, which reduces the reserves of
+/// the pool and so the returns of the swaps. For the worst case they are
+/// simulated against the reduced reserves, using that the return of a swap in
+/// a pool whose reserves are scaled by a factor is the return of the offer
+/// scaled by its inverse, scaled back. This is exact up to rounding for
+/// constant product, weighted and stableswap pools. With pools of more than
+/// two assets, each swap is simulated independently of the others.
+///
+/// Arguments:
+/// - `lp_token`: The LP tokens to exit.
+/// - `ask`: The asset to exit into. Must be an asset of the pool.
+/// - `fee_margin`: A share of the returns of the swaps deducted for the worst
+///   case, e.g. for fees not included in [`Pool::simulate_swap`] or fee changes
+///   before the zap is executed. Must not be larger than one.
+pub fn simulate_zap_out(
+    deps: Deps,
+    pool: &impl Pool,
+    lp_token: &Asset,
+    ask: &AssetInfo,
+    fee_margin: Decimal,
+) -> Result<ZapOutSimulation, CwDexError> {
+    let reserves = pool.get_pool_liquidity(deps)?.to_vec();
+    reserve_of(&reserves, ask)?;
+    let margin_factor = Decimal::one().checked_sub(fee_margin)?;
+
+    let withdrawn = pool.simulate_withdraw_liquidity(deps, lp_token)?;
+    let mut expected = Uint128::zero();
+    let mut worst_case = Uint128::zero();
+    for asset in withdrawn
+        .into_iter()
+        .filter(|asset| !asset.amount.is_zero())
+    {
+        if &asset.info == ask {
+            expected = expected.checked_add(asset.amount)?;
+            worst_case = worst_case.checked_add(asset.amount)?;
+            continue;
+        }
+        expected = expected.checked_add(pool.simulate_swap(deps, asset.clone(), ask.clone())?)?;
+
+        // Swapping x in the reserves scaled by (1 - f) returns (1 - f) times
+        // the return of x / (1 - f) in the current reserves, where f is the
+        // withdrawn share of the reserves
+        let reserve = reserve_of(&reserves, &asset.info)?;
+        let remaining = reserve.checked_sub(asset.amount)?;
+        if remaining.is_zero() {
+            continue;
+        }
+        let scaled_offer = asset
+            .amount
+            .checked_multiply_ratio(reserve, remaining)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        let scaled_return = pool.simulate_swap(
+            deps,
+            Asset::new(asset.info.clone(), scaled_offer),
+            ask.clone(),
+        )?;
+        let swap_return = scaled_return.multiply_ratio(remaining, reserve);
+        worst_case = worst_case.checked_add(swap_return.mul_floor(margin_factor))?;
+    }
+
+    Ok(ZapOutSimulation {
+        withdrawn,
+        expected,
+        worst_case,
+    })
+}
+
+/// Binary searches for the amount of `offer` to swap such that the remaining
+/// offer amount and the amount received plus `ask_held` are in the same ratio
+/// as the pool reserves after the swap. Returns the swap amount and the amount
+/// received.
+///
+/// The search runs until the swap amount is exact to the unit, rather than for
+/// a fixed number of steps, which would leave a share of a large `offer`
+/// unswapped. Each step simulates one swap.
+pub(crate) fn optimal_swap_amount(
+    deps: Deps,
+    pool: &impl Pool,
+    offer: &Asset,
+    ask_info: &AssetInfo,
+    ask_held: Uint128,
+) -> Result<(Uint128, Uint128), CwDexError> {
+    let reserves = pool.get_pool_liquidity(deps)?;
+    let offer_reserve = reserve_of(&reserves.to_vec(), &offer.info)?;
+    let ask_reserve = reserve_of(&reserves.to_vec(), ask_info)?;
+
+    let simulate = |amount: Uint128| -> StdResult<Uint128> {
+        if amount.is_zero() {
+            return Ok(Uint128::zero());
+        }
+        pool.simulate_swap(
+            deps,
+            Asset::new(offer.info.clone(), amount),
+            ask_info.clone(),
+        )
+    };
+
+    // Whether too little of the offer asset is swapped, i.e. the remaining
+    // offer amount is larger than what the pool ratio requires.
+    let swaps_too_little = |swap_amount: Uint128, ask_amount: Uint128| -> bool {
+        let remaining = Uint256::from(offer.amount - swap_amount);
+        let new_offer_reserve = Uint256::from(offer_reserve) + Uint256::from(swap_amount);
+        let new_ask_reserve = Uint256::from(ask_reserve.saturating_sub(ask_amount));
+        remaining * new_ask_reserve
+            > (Uint256::from(ask_amount) + Uint256::from(ask_held)) * new_offer_reserve
+    };
+
+    let (mut low, mut low_out) = (Uint128::zero(), Uint128::zero());
+    let mut high = offer.amount;
+    loop {
+        let mid = low + (high - low) / Uint128::new(2);
+        if mid == low {
+            break;
+        }
+        let mid_out = simulate(mid)?;
+        if swaps_too_little(mid, mid_out) {
+            low = mid;
+            low_out = mid_out;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok((low, low_out))
+}
+
+pub(crate) fn reserve_of(reserves: &[Asset], info: &AssetInfo) -> Result<Uint128, CwDexError> {
+    reserves
+        .iter()
+        .find(|asset| &asset.info == info)
+        .map(|asset| asset.amount)
+        .ok_or_else(|| CwDexError::AssetNotInPool {
+            asset: info.clone(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Decimal, Response, Uint128};
+
+    use super::{
+        claim_rewards_if_any, claim_skipped, optimal_swap_amount, rebalance_idle, rebalance_pools,
+        simulate_zap_out, RebalanceConfig,
+    };
+    use crate::error::CwDexError;
+    use crate::testing::{MockPool, MockStaking, XykPool};
+    use crate::traits::{Pool, Rewards};
+
+    #[test]
+    fn test_claim_rewards_if_any() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = MockStaking::new("lp").with_pending(vec![Asset::native("uastro", 100u128)]);
+
+        let res = claim_rewards_if_any(deps.as_ref(), &env, &staking).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert!(!claim_skipped(&res));
+
+        // The second claim in the same block has nothing to claim
+        let res = claim_rewards_if_any(deps.as_ref(), &env, &staking).unwrap();
+        assert!(res.messages.is_empty());
+        assert!(claim_skipped(&res));
+
+        // Skipped filtered claims are detected as well
+        let res = staking
+            .claim_rewards_filtered(deps.as_ref(), &env, &[], None)
+            .unwrap();
+        assert!(claim_skipped(&res));
+    }
+
+    /// A pool of uatom and usdc at a price of one, whose LP tokens are worth
+    /// one usdc
+    fn atom_pool() -> MockPool {
+        MockPool::new(&["uatom", "usdc"]).with_lp("atom_lp")
+    }
+
+    /// Same as [`atom_pool`] for uosmo
+    fn osmo_pool() -> MockPool {
+        MockPool::new(&["uosmo", "usdc"]).with_lp("osmo_lp")
+    }
+
+    fn config(weight: u64) -> RebalanceConfig {
+        RebalanceConfig {
+            quote: AssetInfo::native("usdc"),
+            weight: Decimal::percent(weight),
+            max_slippage: Decimal::percent(1),
+        }
+    }
+
+    /// Returns the types of the events of `res` with the value of `key`
+    fn events(res: &Response, key: &str) -> Vec<(String, String)> {
+        res.events
+            .iter()
+            .map(|event| {
+                let value = event
+                    .attributes
+                    .iter()
+                    .find(|attr| attr.key == key)
+                    .map(|attr| attr.value.clone())
+                    .unwrap_or_default();
+                (event.ty.clone(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rebalance_pools() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let amount = |amount: u128| Uint128::new(amount);
+
+        // A third of the value of the osmo pool is moved to the atom pool. The
+        // withdrawn uosmo is swapped into usdc, and each step uses the minimum
+        // amounts of the previous one.
+        let res = rebalance_pools(
+            deps.as_ref(),
+            &env,
+            &atom_pool(),
+            amount(1000),
+            &osmo_pool(),
+            amount(3000),
+            &config(50),
+        )
+        .unwrap();
+        assert_eq!(
+            events(&res, "min_out"),
+            vec![
+                ("withdraw".to_string(), "uosmo:494,usdc:494".to_string()),
+                ("swap".to_string(), "489".to_string()),
+                ("provide".to_string(), "973".to_string()),
+                ("apollo/cw-dex/rebalance".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(events(&res, "lp_token")[0].1, "osmo_lp:999");
+        assert_eq!(events(&res, "pool")[2].1, "atom_lp");
+        assert_eq!(events(&res, "assets")[2].1, "usdc:983");
+        assert_eq!(events(&res, "moved")[3].1, "osmo_lp:999");
+
+        // Balanced positions are skipped
+        let res = rebalance_pools(
+            deps.as_ref(),
+            &env,
+            &atom_pool(),
+            amount(1000),
+            &osmo_pool(),
+            amount(3000),
+            &config(25),
+        )
+        .unwrap();
+        assert_eq!(
+            events(&res, "skipped"),
+            vec![(
+                "apollo/cw-dex/rebalance".to_string(),
+                "balanced".to_string()
+            )]
+        );
+
+        // The weight must not be larger than one
+        rebalance_pools(
+            deps.as_ref(),
+            &env,
+            &atom_pool(),
+            amount(1000),
+            &osmo_pool(),
+            amount(3000),
+            &config(101),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_rebalance_idle() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+
+        // Three quarters of the pool position are withdrawn into usdc
+        let res = rebalance_idle(
+            deps.as_ref(),
+            &env,
+            &atom_pool(),
+            Uint128::new(1000),
+            Uint128::zero(),
+            &config(25),
+        )
+        .unwrap();
+        assert_eq!(
+            events(&res, "min_out"),
+            vec![
+                ("withdraw".to_string(), "uatom:371,usdc:371".to_string()),
+                ("swap".to_string(), "367".to_string()),
+                ("apollo/cw-dex/rebalance".to_string(), String::new()),
+            ]
+        );
+
+        // Idle usdc is partly swapped into uatom and provided
+        let pool = XykPool {
+            atom: 1_000_000,
+            usdc: 1_000_000,
+        };
+        let res = rebalance_idle(
+            deps.as_ref(),
+            &env,
+            &pool,
+            Uint128::zero(),
+            Uint128::new(1000),
+            &config(100),
+        )
+        .unwrap();
+        assert_eq!(
+            events(&res, "offer"),
+            vec![
+                ("swap".to_string(), "usdc:500".to_string()),
+                ("provide".to_string(), String::new()),
+                ("apollo/cw-dex/rebalance".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(events(&res, "assets")[1].1, "usdc:500,uatom:499");
+        assert_eq!(events(&res, "min_out")[1].1, "494");
+        assert_eq!(events(&res, "moved")[2].1, "usdc:1000");
+
+        // Providing idle usdc requires a two asset pool
+        let err = rebalance_idle(
+            deps.as_ref(),
+            &env,
+            &MockPool::new(&["uatom", "usdc", "uosmo"]),
+            Uint128::zero(),
+            Uint128::new(1000),
+            &config(100),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("two assets"));
+
+        // The quote asset must be an asset of the pool
+        let err = rebalance_idle(
+            deps.as_ref(),
+            &env,
+            &atom_pool(),
+            Uint128::new(1000),
+            Uint128::zero(),
+            &RebalanceConfig {
+                quote: AssetInfo::native("uosmo"),
+                ..config(25)
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, CwDexError::AssetNotInPool { .. }));
+    }
+
+    #[test]
+    fn test_simulate_zap_out() {
+        let deps = mock_dependencies();
+        let pool = XykPool {
+            atom: 1_000_000,
+            usdc: 1_000_000,
+        };
+        let lp_token = Asset::native("lp", 100_000u128);
+        let usdc = AssetInfo::native("usdc");
+
+        // The swap of 100_000 uatom returns 90_909 against the current
+        // reserves, but 90_000 after the withdrawal of a tenth of the pool,
+        // which the worst case is within rounding of
+        let sim =
+            simulate_zap_out(deps.as_ref(), &pool, &lp_token, &usdc, Decimal::zero()).unwrap();
+        assert_eq!(
+            sim.withdrawn,
+            vec![
+                Asset::native("uatom", 100_000u128),
+                Asset::native("usdc", 100_000u128)
+            ]
+            .into()
+        );
+        assert_eq!(sim.expected, Uint128::new(190_909));
+        assert_eq!(sim.worst_case, Uint128::new(189_999));
+
+        // The fee margin is deducted from the swap returns only
+        let sim =
+            simulate_zap_out(deps.as_ref(), &pool, &lp_token, &usdc, Decimal::percent(1)).unwrap();
+        assert_eq!(sim.worst_case, Uint128::new(189_099));
+
+        // Withdrawing the whole pool leaves nothing to swap in
+        let sim = simulate_zap_out(
+            deps.as_ref(),
+            &pool,
+            &Asset::native("lp", 1_000_000u128),
+            &usdc,
+            Decimal::zero(),
+        )
+        .unwrap();
+        assert_eq!(sim.worst_case, Uint128::new(1_000_000));
+
+        // The ask asset must be an asset of the pool
+        let err = simulate_zap_out(
+            deps.as_ref(),
+            &pool,
+            &lp_token,
+            &AssetInfo::native("uosmo"),
+            Decimal::zero(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, CwDexError::AssetNotInPool { .. }));
+    }
+
+    #[test]
+    fn test_optimal_swap_amount_large_offer() {
+        let deps = mock_dependencies();
+        let pool = XykPool {
+            atom: 1_000_000,
+            usdc: 1_000_000,
+        };
+        let offer = Asset::native("uatom", 1_000_000_000_000u128);
+        let usdc = AssetInfo::native("usdc");
+
+        // Without fees, the optimal swap of x into reserves r is
+        // sqrt(r * (r + x)) - r. The search converges to it up to the rounding
+        // of the swap return, which is a unit of about a thousandth of the
+        // remaining usdc reserve.
+        let (swap_amount, ask_amount) =
+            optimal_swap_amount(deps.as_ref(), &pool, &offer, &usdc, Uint128::zero()).unwrap();
+        let optimal = (1e6f64 * (1e6 + 1e12)).sqrt() - 1e6;
+        assert!((swap_amount.u128() as f64 - optimal).abs() / optimal < 1e-6);
+        assert_eq!(
+            ask_amount,
+            pool.simulate_swap(deps.as_ref(), Asset::native("uatom", swap_amount), usdc)
+                .unwrap()
+        );
+    }
+}
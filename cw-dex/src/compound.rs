@@ -0,0 +1,650 @@
+//! Contains the `CompoundThreshold` helper for deciding whether claiming and
+//! compounding pending rewards is economical, i.e. whether the rewards are
+//! worth more than the gas spent on claiming and compounding them.
+//!
+//! Rewards are valued with an [`Oracle`], so keepers of many vaults can share
+//! one decision rule instead of per-vault heuristics.
+//!
+//! Also contains [`plan_compound`], which plans the swaps and the provide of
+//! compounding rewards of several assets into a pool.
+
+use std::cmp::Ordering;
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use apollo_utils::responses::merge_responses;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Deps, Env, Event, Response, StdError, Uint128, Uint256};
+
+use crate::composite::{optimal_swap_amount, reserve_of};
+use crate::error::CwDexError;
+use crate::oracle::Oracle;
+use crate::reply::reply_on_last_message;
+use crate::traits::{MinOut, Pool, Rewards, Router};
+
+/// The threshold pending rewards must reach for compounding them to be
+/// economical.
+#[cw_serde]
+pub struct CompoundThreshold {
+    /// The estimated gas cost of claiming and compounding, in units of the
+    /// quote asset of the oracle
+    pub gas_cost: Uint128,
+    /// The minimum ratio of the value of the rewards to `gas_cost`, e.g. two
+    /// to only compound rewards worth at least twice the gas cost
+    pub min_ratio: Decimal,
+}
+
+/// A reward asset and its value, see [`CompoundDecision`].
+#[cw_serde]
+pub struct RewardValue {
+    /// The pending reward
+    pub asset: Asset,
+    /// The value of the reward in units of the quote asset of the oracle
+    pub value: Uint128,
+}
+
+/// The result of [`CompoundThreshold::evaluate`], with the breakdown the
+/// decision is based on.
+#[cw_serde]
+pub struct CompoundDecision {
+    /// Whether the rewards are worth compounding
+    pub compound: bool,
+    /// The asset values are quoted in
+    pub quote: AssetInfo,
+    /// The pending rewards the oracle has a price for, with their values
+    pub rewards: Vec<RewardValue>,
+    /// The pending rewards the oracle has no price for. They are valued at
+    /// zero.
+    pub unpriced: Vec<Asset>,
+    /// The total value of the rewards
+    pub rewards_value: Uint128,
+    /// The value the rewards must reach, `gas_cost * min_ratio` rounded up
+    pub required_value: Uint128,
+    /// The value of the rewards minus the gas cost, zero if the gas cost
+    /// exceeds the value of the rewards
+    pub net_value: Uint128,
+}
+
+impl CompoundThreshold {
+    /// Returns a threshold requiring the rewards to be worth at least
+    /// `gas_cost`.
+    pub fn new(gas_cost: impl Into<Uint128>) -> Self {
+        Self {
+            gas_cost: gas_cost.into(),
+            min_ratio: Decimal::one(),
+        }
+    }
+
+    /// Sets the minimum ratio of the value of the rewards to the gas cost.
+    pub fn with_min_ratio(mut self, min_ratio: Decimal) -> Self {
+        self.min_ratio = min_ratio;
+        self
+    }
+
+    /// Values `pending_rewards` with `oracle` and decides whether they are
+    /// worth compounding. Rewards are compounded if their total value is at
+    /// least `gas_cost * min_ratio` and non-zero.
+    ///
+    /// Rewards the oracle has no price for, i.e. for which it returns
+    /// [`CwDexError::PriceUnavailable`], are valued at zero and listed in
+    /// [`CompoundDecision::unpriced`], so a single unpriced reward token does
+    /// not block compounding. Other errors of the oracle are returned.
+    pub fn evaluate(
+        &self,
+        deps: Deps,
+        env: &Env,
+        oracle: &dyn Oracle,
+        pending_rewards: &AssetList,
+    ) -> Result<CompoundDecision, CwDexError> {
+        let mut rewards = vec![];
+        let mut unpriced = vec![];
+        let mut rewards_value = Uint128::zero();
+        for asset in pending_rewards.iter() {
+            match oracle.value(deps, env, &AssetList::from(vec![asset.clone()])) {
+                Ok(value) => {
+                    rewards_value = rewards_value.checked_add(value)?;
+                    rewards.push(RewardValue {
+                        asset: asset.clone(),
+                        value,
+                    });
+                }
+                Err(CwDexError::PriceUnavailable { .. }) => unpriced.push(asset.clone()),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let required_value = self
+            .gas_cost
+            .checked_mul_ceil(self.min_ratio)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+        Ok(CompoundDecision {
+            compound: !rewards_value.is_zero() && rewards_value >= required_value,
+            quote: oracle.quote_asset(),
+            rewards,
+            unpriced,
+            rewards_value,
+            required_value,
+            net_value: rewards_value.saturating_sub(self.gas_cost),
+        })
+    }
+
+    /// Queries the rewards pending for the contract in `staking` and evaluates
+    /// them with [`CompoundThreshold::evaluate`].
+    pub fn evaluate_pending(
+        &self,
+        deps: Deps,
+        env: &Env,
+        oracle: &dyn Oracle,
+        staking: &dyn Rewards,
+    ) -> Result<CompoundDecision, CwDexError> {
+        let pending_rewards =
+            staking.query_pending_rewards(&deps.querier, &env.contract.address)?;
+        self.evaluate(deps, env, oracle, &pending_rewards)
+    }
+}
+
+/// A swap of a [`CompoundPlan`].
+#[cw_serde]
+pub struct PlannedSwap {
+    /// The asset to swap
+    pub offer: Asset,
+    /// The assets to swap through, starting with the offered asset and ending
+    /// with the asset to receive
+    pub path: Vec<AssetInfo>,
+    /// The simulated amount received
+    pub expected: Uint128,
+    /// The minimum amount received, the simulated amount less the max slippage
+    pub min_out: Uint128,
+}
+
+/// The swaps and the provide of compounding rewards into a pool, as returned
+/// by [`plan_compound`].
+#[cw_serde]
+pub struct CompoundPlan {
+    /// The swaps of the rewards that are not assets of the pool into one of
+    /// the pool assets through the router
+    pub routed: Vec<PlannedSwap>,
+    /// The swap in the pool bringing the pool assets to the ratio of the pool
+    /// reserves, if any
+    pub balance: Option<PlannedSwap>,
+    /// The assets provided to the pool
+    pub provide: AssetList,
+    /// The simulated amount of LP tokens received for `provide`
+    pub expected_lp: Uint128,
+    /// The minimum amount of LP tokens to receive, `expected_lp` less the max
+    /// slippage
+    pub min_lp_out: Uint128,
+}
+
+/// Plans compounding `rewards` into the two asset `pool`: the rewards that are
+/// not assets of the pool are swapped into one of the pool assets along their
+/// path in `paths` through `router`, the excess of one of the pool assets is
+/// then swapped in `pool` such that the assets are in the ratio of the pool
+/// reserves after the swap, and the result is provided as liquidity.
+///
+/// Each swap receives at least its simulated amount less `max_slippage`, and
+/// only these minimum amounts are used by the following steps, so the provide
+/// is always funded. Amounts received above the minimums are left in the
+/// contract. The provide is checked once against the LP tokens simulated for
+/// the provided assets less `max_slippage`. This simulation runs against the
+/// reserves of `pool` before the balancing swap, so it does not account for
+/// the price impact of that swap on the LP tokens received, and
+/// `max_slippage` must leave room for it.
+///
+/// Arguments:
+/// - `rewards`: The rewards to compound. Must already be held by the contract.
+///   Rewards with zero amount are ignored.
+/// - `pool`: The pool to provide liquidity to. Must have two assets.
+/// - `router`: The router to swap the rewards that are not pool assets with.
+/// - `paths`: The swap path of each reward that is not a pool asset, starting
+///   with the reward and ending with one of the pool assets.
+/// - `max_slippage`: The maximum slippage of each swap and of the provide
+///   relative to their simulations. Must not be larger than one.
+///
+/// Execute the plan with [`CompoundPlan::into_response`].
+pub fn plan_compound(
+    deps: Deps,
+    env: &Env,
+    rewards: &AssetList,
+    pool: &impl Pool,
+    router: &impl Router,
+    paths: &[Vec<AssetInfo>],
+    max_slippage: Decimal,
+) -> Result<CompoundPlan, CwDexError> {
+    let pool_assets = pool.pool_assets(deps)?;
+    let (first, second) = match pool_assets.as_slice() {
+        [a, b] => (a.clone(), b.clone()),
+        _ => {
+            return Err(
+                StdError::generic_err("plan_compound only supports pools with two assets").into(),
+            )
+        }
+    };
+    let min_out = MinOut::Slippage(max_slippage);
+
+    let mut held = AssetList::new();
+    let mut routed = vec![];
+    for reward in rewards.iter().filter(|asset| !asset.amount.is_zero()) {
+        if pool_assets.contains(&reward.info) {
+            held.add(reward)?;
+            continue;
+        }
+        let path = paths
+            .iter()
+            .find(|path| path.first() == Some(&reward.info))
+            .ok_or_else(|| {
+                StdError::generic_err(format!("No swap path for reward {}", reward.info))
+            })?;
+        router.validate_path(&reward.info, path)?;
+        let ask = &path[path.len() - 1];
+        if !pool_assets.contains(ask) {
+            return Err(CwDexError::AssetNotInPool { asset: ask.clone() });
+        }
+
+        let expected = router.simulate_swap_path(deps, reward.clone(), path)?;
+        let swap = PlannedSwap {
+            offer: reward.clone(),
+            path: path.clone(),
+            expected,
+            min_out: min_out.resolve(expected)?,
+        };
+        held.add(&Asset::new(ask.clone(), swap.min_out))?;
+        routed.push(swap);
+    }
+
+    let amount_of = |info: &AssetInfo| held.find(info).map(|a| a.amount).unwrap_or_default();
+    let (first_amount, second_amount) = (amount_of(&first), amount_of(&second));
+    if first_amount.is_zero() && second_amount.is_zero() {
+        return Err(StdError::generic_err("No rewards to compound").into());
+    }
+
+    // The excess asset is the one whose share of the held assets is larger
+    // than its share of the reserves
+    let reserves = pool.get_pool_liquidity(deps)?.to_vec();
+    let excess = (Uint256::from(first_amount) * Uint256::from(reserve_of(&reserves, &second)?))
+        .cmp(&(Uint256::from(second_amount) * Uint256::from(reserve_of(&reserves, &first)?)));
+    let (offer, ask_info, ask_held) = match excess {
+        Ordering::Less => (Asset::new(second, second_amount), first, first_amount),
+        _ => (Asset::new(first, first_amount), second, second_amount),
+    };
+
+    let (swap_amount, ask_amount) = match excess {
+        Ordering::Equal => (Uint128::zero(), Uint128::zero()),
+        _ => optimal_swap_amount(deps, pool, &offer, &ask_info, ask_held)?,
+    };
+    let balance = if swap_amount.is_zero() {
+        None
+    } else {
+        Some(PlannedSwap {
+            offer: Asset::new(offer.info.clone(), swap_amount),
+            path: vec![offer.info.clone(), ask_info.clone()],
+            expected: ask_amount,
+            min_out: min_out.resolve(ask_amount)?,
+        })
+    };
+    let ask_received = balance.as_ref().map(|s| s.min_out).unwrap_or_default();
+
+    let provide: AssetList = vec![
+        Asset::new(offer.info.clone(), offer.amount - swap_amount),
+        Asset::new(ask_info, ask_held + ask_received),
+    ]
+    .into_iter()
+    .filter(|asset| !asset.amount.is_zero())
+    .collect::<Vec<_>>()
+    .into();
+    let expected_lp = pool
+        .simulate_provide_liquidity(deps, env, provide.clone())?
+        .amount;
+
+    Ok(CompoundPlan {
+        routed,
+        balance,
+        provide,
+        expected_lp,
+        min_lp_out: min_out.resolve(expected_lp)?,
+    })
+}
+
+impl CompoundPlan {
+    /// Returns the messages to execute the plan: the swaps through `router`,
+    /// the swap in `pool` and the provide of liquidity to `pool`. `pool` and
+    /// `router` must be the ones the plan was made with.
+    ///
+    /// The provide liquidity message is sent as a submessage with reply ID
+    /// `reply_id`. The contract must handle this reply by calling
+    /// [`crate::composite::swap_and_stake_reply`], which stakes the received
+    /// LP tokens.
+    pub fn into_response(
+        self,
+        deps: Deps,
+        env: &Env,
+        pool: &impl Pool,
+        router: &impl Router,
+        reply_id: u64,
+    ) -> Result<Response, CwDexError> {
+        let mut responses = vec![];
+        for swap in &self.routed {
+            responses.push(router.swap_path(
+                deps,
+                env,
+                swap.offer.clone(),
+                &swap.path,
+                swap.min_out,
+            )?);
+        }
+        if let Some(swap) = &self.balance {
+            responses.push(pool.swap(
+                deps,
+                env,
+                swap.offer.clone(),
+                swap.path[1].clone(),
+                swap.min_out,
+            )?);
+        }
+        let provide_res =
+            pool.provide_liquidity(deps, env, self.provide.clone(), self.min_lp_out)?;
+        responses.push(reply_on_last_message(provide_res, reply_id)?);
+
+        let mut event = Event::new("apollo/cw-dex/compound")
+            .add_attribute("provide", self.provide.to_string())
+            .add_attribute("expected_lp", self.expected_lp)
+            .add_attribute("min_lp_out", self.min_lp_out);
+        if !self.routed.is_empty() {
+            let routed: AssetList = self
+                .routed
+                .iter()
+                .map(|swap| swap.offer.clone())
+                .collect::<Vec<_>>()
+                .into();
+            event = event.add_attribute("routed", routed.to_string());
+        }
+        if let Some(swap) = &self.balance {
+            event = event.add_attribute("balance_swap", swap.offer.to_string());
+        }
+
+        Ok(merge_responses(responses).add_event(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Decimal, Deps, Env, Event, Response, StdResult, Uint128};
+    use test_case::test_case;
+
+    use super::{plan_compound, CompoundThreshold, PlannedSwap, RewardValue};
+    use crate::error::CwDexError;
+    use crate::oracle::FixedPriceOracle;
+    use crate::testing::XykPool;
+    use crate::traits::Router;
+
+    fn oracle() -> FixedPriceOracle {
+        FixedPriceOracle {
+            quote: AssetInfo::native("uusdc"),
+            prices: vec![
+                (AssetInfo::native("uosmo"), Decimal::percent(50)),
+                (AssetInfo::native("uatom"), Decimal::percent(1000)),
+            ],
+        }
+    }
+
+    #[test_case(100, Decimal::one(), true; "value equals gas cost")]
+    #[test_case(101, Decimal::one(), false; "value below gas cost")]
+    #[test_case(50, Decimal::percent(200), true; "value equals min ratio")]
+    #[test_case(51, Decimal::percent(200), false; "value below min ratio")]
+    #[test_case(0, Decimal::one(), true; "no gas cost")]
+    fn test_evaluate(gas_cost: u128, min_ratio: Decimal, compound: bool) {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pending = AssetList::from(vec![
+            Asset::native("uosmo", 101u128),
+            Asset::native("uatom", 5u128),
+            Asset::native("uunknown", 1_000u128),
+        ]);
+
+        let decision = CompoundThreshold::new(gas_cost)
+            .with_min_ratio(min_ratio)
+            .evaluate(deps.as_ref(), &env, &oracle(), &pending)
+            .unwrap();
+
+        assert_eq!(decision.compound, compound);
+        assert_eq!(decision.quote, AssetInfo::native("uusdc"));
+        assert_eq!(
+            decision.rewards,
+            vec![
+                RewardValue {
+                    asset: Asset::native("uosmo", 101u128),
+                    value: Uint128::new(50),
+                },
+                RewardValue {
+                    asset: Asset::native("uatom", 5u128),
+                    value: Uint128::new(50),
+                },
+            ]
+        );
+        assert_eq!(
+            decision.unpriced,
+            vec![Asset::native("uunknown", 1_000u128)]
+        );
+        assert_eq!(decision.rewards_value, Uint128::new(100));
+        assert_eq!(
+            decision.net_value,
+            Uint128::new(100u128.saturating_sub(gas_cost))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_no_priced_rewards() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pending = AssetList::from(vec![Asset::native("uunknown", 1_000u128)]);
+
+        let decision = CompoundThreshold::new(0u128)
+            .evaluate(deps.as_ref(), &env, &oracle(), &pending)
+            .unwrap();
+
+        assert!(!decision.compound);
+        assert_eq!(decision.rewards_value, Uint128::zero());
+    }
+
+    /// A router returning half of the offered amount
+    struct HalvingRouter;
+
+    impl Router for HalvingRouter {
+        fn swap_path(
+            &self,
+            _deps: Deps,
+            _env: &Env,
+            offer_asset: Asset,
+            path: &[AssetInfo],
+            min_out: Uint128,
+        ) -> Result<Response, CwDexError> {
+            Ok(Response::new().add_event(
+                Event::new("swap_path")
+                    .add_attribute("offer", offer_asset.to_string())
+                    .add_attribute("ask", path[path.len() - 1].to_string())
+                    .add_attribute("min_out", min_out),
+            ))
+        }
+
+        fn simulate_swap_path(
+            &self,
+            _deps: Deps,
+            offer_asset: Asset,
+            _path: &[AssetInfo],
+        ) -> StdResult<Uint128> {
+            Ok(offer_asset.amount / Uint128::new(2))
+        }
+    }
+
+    fn paths() -> Vec<Vec<AssetInfo>> {
+        vec![
+            vec![AssetInfo::native("uosmo"), AssetInfo::native("uatom")],
+            vec![
+                AssetInfo::native("uastro"),
+                AssetInfo::native("uosmo"),
+                AssetInfo::native("usdc"),
+            ],
+        ]
+    }
+
+    #[test]
+    fn test_plan_compound() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = XykPool {
+            atom: 1_000_000,
+            usdc: 2_000_000,
+        };
+        let rewards = AssetList::from(vec![
+            Asset::native("uosmo", 200_000u128),
+            Asset::native("uastro", 20_000u128),
+            Asset::native("usdc", 10_000u128),
+            Asset::native("uion", 0u128),
+        ]);
+
+        let plan = plan_compound(
+            deps.as_ref(),
+            &env,
+            &rewards,
+            &pool,
+            &HalvingRouter,
+            &paths(),
+            Decimal::percent(1),
+        )
+        .unwrap();
+
+        assert_eq!(
+            plan.routed,
+            vec![
+                PlannedSwap {
+                    offer: Asset::native("uosmo", 200_000u128),
+                    path: paths()[0].clone(),
+                    expected: Uint128::new(100_000),
+                    min_out: Uint128::new(99_000),
+                },
+                PlannedSwap {
+                    offer: Asset::native("uastro", 20_000u128),
+                    path: paths()[1].clone(),
+                    expected: Uint128::new(10_000),
+                    min_out: Uint128::new(9_900),
+                },
+            ]
+        );
+
+        // 99_000 uatom and 19_900 usdc are held, so uatom is swapped into usdc
+        let balance = plan.balance.clone().unwrap();
+        assert_eq!(balance.offer.info, AssetInfo::native("uatom"));
+        assert_eq!(
+            balance.path,
+            vec![AssetInfo::native("uatom"), AssetInfo::native("usdc")]
+        );
+        assert_eq!(
+            balance.min_out,
+            balance.expected.mul_floor(Decimal::percent(99))
+        );
+
+        // The provided assets are in the ratio of the reserves after the swap
+        let atom = plan
+            .provide
+            .find(&AssetInfo::native("uatom"))
+            .unwrap()
+            .amount;
+        let usdc = plan
+            .provide
+            .find(&AssetInfo::native("usdc"))
+            .unwrap()
+            .amount;
+        assert_eq!(atom, Uint128::new(99_000) - balance.offer.amount);
+        assert_eq!(usdc, Uint128::new(19_900) + balance.min_out);
+        let ratio = Decimal::from_ratio(usdc, atom);
+        let reserve_ratio = Decimal::from_ratio(
+            2_000_000 - balance.expected.u128(),
+            1_000_000 + balance.offer.amount.u128(),
+        );
+        assert!(ratio.abs_diff(reserve_ratio) < Decimal::percent(2));
+
+        assert_eq!(
+            plan.min_lp_out,
+            plan.expected_lp.mul_floor(Decimal::percent(99))
+        );
+
+        let res = plan
+            .clone()
+            .into_response(deps.as_ref(), &env, &pool, &HalvingRouter, 7)
+            .unwrap();
+        let event_types: Vec<_> = res.events.iter().map(|e| e.ty.as_str()).collect();
+        assert_eq!(
+            event_types,
+            vec![
+                "swap_path",
+                "swap_path",
+                "swap",
+                "provide",
+                "apollo/cw-dex/compound"
+            ]
+        );
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, 7);
+    }
+
+    #[test]
+    fn test_plan_compound_balanced() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = XykPool {
+            atom: 1_000_000,
+            usdc: 2_000_000,
+        };
+        let rewards = AssetList::from(vec![
+            Asset::native("uatom", 1_000u128),
+            Asset::native("usdc", 2_000u128),
+        ]);
+
+        let plan = plan_compound(
+            deps.as_ref(),
+            &env,
+            &rewards,
+            &pool,
+            &HalvingRouter,
+            &[],
+            Decimal::percent(1),
+        )
+        .unwrap();
+
+        assert!(plan.routed.is_empty());
+        assert!(plan.balance.is_none());
+        assert_eq!(plan.provide, rewards);
+        assert_eq!(plan.expected_lp, Uint128::new(1_000));
+        assert_eq!(plan.min_lp_out, Uint128::new(990));
+    }
+
+    #[test_case(vec![Asset::native("uion", 100u128)], &[] => matches CwDexError::Std(_); "no path")]
+    #[test_case(
+        vec![Asset::native("uion", 100u128)],
+        &[vec![AssetInfo::native("uion"), AssetInfo::native("uosmo")]]
+        => matches CwDexError::AssetNotInPool { .. };
+        "path does not end in pool asset"
+    )]
+    #[test_case(vec![Asset::native("uatom", 0u128)], &[] => matches CwDexError::Std(_); "no rewards")]
+    fn test_plan_compound_invalid(rewards: Vec<Asset>, paths: &[Vec<AssetInfo>]) -> CwDexError {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = XykPool {
+            atom: 1_000_000,
+            usdc: 1_000_000,
+        };
+
+        plan_compound(
+            deps.as_ref(),
+            &env,
+            &rewards.into(),
+            &pool,
+            &HalvingRouter,
+            paths,
+            Decimal::percent(1),
+        )
+        .unwrap_err()
+    }
+}
@@ -0,0 +1,136 @@
+//! Contains conversions of the string encoded numbers of protobuf messages and
+//! queries into `Uint128`, `Uint256`, `Decimal` and `Decimal256`, and checked
+//! narrowing conversions of 256 bit values, e.g. of PCL math results. Errors
+//! name the converted field and value, unlike the plain `FromStr` and
+//! `TryFrom` errors.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use cosmwasm_std::{Coin, Decimal, Decimal256, StdError, StdResult, Uint128, Uint256};
+
+/// Parses `value` as `T`, returning an error naming `field` and `value` on
+/// failure.
+fn parse<T>(value: &str, field: &str) -> StdResult<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    T::from_str(value)
+        .map_err(|err| StdError::generic_err(format!("Invalid {field} \"{value}\": {err}")))
+}
+
+/// Parses `value`, the field `field` of a message or query response, as a
+/// `Uint128`.
+pub fn parse_uint128(value: &str, field: &str) -> StdResult<Uint128> {
+    parse(value, field)
+}
+
+/// Parses `value`, the field `field` of a message or query response, as a
+/// `Uint256`.
+pub fn parse_uint256(value: &str, field: &str) -> StdResult<Uint256> {
+    parse(value, field)
+}
+
+/// Parses `value`, the field `field` of a message or query response, as a
+/// `Decimal`. Cosmos SDK decimals with 18 decimal places are parsed exactly.
+pub fn parse_decimal(value: &str, field: &str) -> StdResult<Decimal> {
+    parse(value, field)
+}
+
+/// Parses `value`, the field `field` of a message or query response, as a
+/// `Decimal256`.
+pub fn parse_decimal256(value: &str, field: &str) -> StdResult<Decimal256> {
+    parse(value, field)
+}
+
+/// Returns a `Coin` of `denom` with `amount` parsed as by [`parse_uint128`],
+/// e.g. for the `denom` and `amount` fields of a protobuf coin.
+pub fn parse_coin(denom: &str, amount: &str, field: &str) -> StdResult<Coin> {
+    Ok(Coin {
+        denom: denom.to_string(),
+        amount: parse_uint128(amount, field)?,
+    })
+}
+
+/// Converts `value` to a `Uint128`, returning an error naming `field` if it
+/// does not fit.
+pub fn uint256_to_uint128(value: Uint256, field: &str) -> StdResult<Uint128> {
+    Uint128::try_from(value)
+        .map_err(|_| StdError::generic_err(format!("{field} {value} does not fit in a Uint128")))
+}
+
+/// Converts `value` to a `Decimal`, returning an error naming `field` if it
+/// does not fit.
+pub fn decimal256_to_decimal(value: Decimal256, field: &str) -> StdResult<Decimal> {
+    Decimal::try_from(value)
+        .map_err(|_| StdError::generic_err(format!("{field} {value} does not fit in a Decimal")))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{coin, Decimal, Decimal256, StdError, Uint128, Uint256};
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("0" => Ok(Uint128::zero()); "zero")]
+    #[test_case("340282366920938463463374607431768211455" => Ok(Uint128::MAX); "max")]
+    #[test_case("340282366920938463463374607431768211456" => matches Err(_); "overflow")]
+    #[test_case("" => matches Err(_); "empty")]
+    #[test_case("1.5" => matches Err(_); "decimal")]
+    fn test_parse_uint128(value: &str) -> Result<Uint128, StdError> {
+        parse_uint128(value, "amount")
+    }
+
+    #[test]
+    fn test_error_context() {
+        let err = parse_uint128("12a", "token_out_amount").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Generic error: Invalid token_out_amount \"12a\": "));
+        let err = parse_decimal("x", "swap_fee").unwrap_err();
+        assert!(err.to_string().contains("Invalid swap_fee \"x\""));
+    }
+
+    #[test]
+    fn test_parse_decimals() {
+        assert_eq!(
+            parse_decimal("0.003000000000000000", "swap_fee").unwrap(),
+            Decimal::permille(3)
+        );
+        assert_eq!(
+            parse_decimal256("0.003", "swap_fee").unwrap(),
+            Decimal256::permille(3)
+        );
+        assert!(parse_decimal("0.0000000000000000001", "swap_fee").is_err());
+        assert_eq!(
+            parse_uint256(&Uint256::MAX.to_string(), "invariant").unwrap(),
+            Uint256::MAX
+        );
+    }
+
+    #[test]
+    fn test_parse_coin() {
+        assert_eq!(
+            parse_coin("uosmo", "100", "tokens_out").unwrap(),
+            coin(100, "uosmo")
+        );
+        assert!(parse_coin("uosmo", "-1", "tokens_out").is_err());
+    }
+
+    #[test]
+    fn test_narrowing() {
+        assert_eq!(
+            uint256_to_uint128(Uint256::from(5u8), "amount").unwrap(),
+            Uint128::new(5)
+        );
+        let err = uint256_to_uint128(Uint256::MAX, "amount").unwrap_err();
+        assert!(err.to_string().contains("does not fit in a Uint128"));
+        assert_eq!(
+            decimal256_to_decimal(Decimal256::percent(50), "price").unwrap(),
+            Decimal::percent(50)
+        );
+        assert!(decimal256_to_decimal(Decimal256::MAX, "price").is_err());
+    }
+}
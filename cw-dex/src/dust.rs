@@ -0,0 +1,99 @@
+//! Contains the `DustConfig` for handling the small remainders of pool assets
+//! that accumulate in a contract after providing liquidity.
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, CosmosMsg, Deps, Env, Uint128};
+
+use crate::error::CwDexError;
+
+/// What to do with the remainders of pool assets left in the contract by the
+/// composite operations.
+#[cw_serde]
+#[derive(Default)]
+pub enum DustPolicy {
+    /// Leave the remainders in the contract.
+    #[default]
+    Ignore,
+    /// Send the remainders to `recipient` after the operation.
+    Sweep {
+        /// The address to send the remainders to
+        recipient: Addr,
+    },
+    /// Include the remainders in the next liquidity provision.
+    ProvideOnNext,
+}
+
+/// Configures how dust is handled by the composite operations in
+/// [`crate::composite`].
+///
+/// A balance of a pool asset held by the contract is considered dust if it is
+/// non-zero and at most the threshold configured for the asset. Assets without
+/// a threshold are never considered dust, so balances the contract holds on
+/// purpose are not touched.
+#[cw_serde]
+#[derive(Default)]
+pub struct DustConfig {
+    /// The policy to apply to dust
+    pub policy: DustPolicy,
+    /// The maximum amount of each asset that is considered dust
+    pub thresholds: Vec<Asset>,
+}
+
+impl DustConfig {
+    /// Returns the dust threshold for `info`, or zero if none is configured.
+    pub fn threshold(&self, info: &AssetInfo) -> Uint128 {
+        self.thresholds
+            .iter()
+            .find(|threshold| &threshold.info == info)
+            .map(|threshold| threshold.amount)
+            .unwrap_or_default()
+    }
+
+    /// Queries the contract's balances of `assets` and returns those that are
+    /// dust.
+    ///
+    /// Arguments:
+    /// - `assets`: The assets to check, e.g. the assets of a pool.
+    /// - `reserved`: Amounts of the balances that are about to be used by the
+    ///   operation and should not be considered dust.
+    pub fn query_dust(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: &[AssetInfo],
+        reserved: &AssetList,
+    ) -> Result<AssetList, CwDexError> {
+        let mut dust = vec![];
+        for info in assets {
+            let threshold = self.threshold(info);
+            if threshold.is_zero() {
+                continue;
+            }
+            let reserved_amount = reserved.find(info).map(|a| a.amount).unwrap_or_default();
+            let amount = info
+                .query_balance(&deps.querier, &env.contract.address)?
+                .saturating_sub(reserved_amount);
+            if !amount.is_zero() && amount <= threshold {
+                dust.push(Asset::new(info.clone(), amount));
+            }
+        }
+        Ok(dust.into())
+    }
+
+    /// Returns the messages sending the dust of `assets` held by the contract
+    /// to the recipient, if the policy is [`DustPolicy::Sweep`].
+    pub fn sweep_msgs(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: &[AssetInfo],
+    ) -> Result<Vec<CosmosMsg>, CwDexError> {
+        match &self.policy {
+            DustPolicy::Sweep { recipient } => Ok(self
+                .query_dust(deps, env, assets, &AssetList::new())?
+                .transfer_msgs(recipient)?),
+            _ => Ok(vec![]),
+        }
+    }
+}
@@ -3,10 +3,15 @@
 
 use std::num::TryFromIntError;
 
-use apollo_cw_asset::Asset;
-use cosmwasm_std::{DivideByZeroError, OverflowError, StdError, Uint128};
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{
+    ConversionOverflowError, Decimal256, DecimalRangeExceeded, DivideByZeroError, OverflowError,
+    StdError, Timestamp, Uint128,
+};
 use thiserror::Error;
 
+use crate::traits::ForceUnlockRestriction;
+
 #[cfg(feature = "backtraces")]
 use std::backtrace::Backtrace;
 
@@ -35,6 +40,14 @@ pub enum CwDexError {
     #[error("Infallible")]
     Infallible(#[from] std::convert::Infallible),
 
+    /// Converts from `cosmwasm_std::ConversionOverflowError`
+    #[error("{0}")]
+    ConversionOverflow(#[from] ConversionOverflowError),
+
+    /// Converts from `cosmwasm_std::DecimalRangeExceeded`
+    #[error("{0}")]
+    DecimalRangeExceeded(#[from] DecimalRangeExceeded),
+
     /// Invalid Reply ID Error
     #[error("Invalid output asset")]
     InvalidOutAsset {},
@@ -66,6 +79,81 @@ pub enum CwDexError {
     #[error("It is not possible to provide liquidity with one token for an empty pool")]
     InvalidProvideLPsWithSingleToken {},
 
+    /// Asset is not one of the assets of the pool
+    #[error("Asset is not in pool: {asset}")]
+    AssetNotInPool {
+        /// The asset in question
+        asset: AssetInfo,
+    },
+
+    /// A pre-flight check found that an asset of the pool can currently not be
+    /// used, e.g. because its token contract fails to answer queries
+    #[error("Asset {asset} is unavailable: {reason}")]
+    AssetUnavailable {
+        /// The asset in question
+        asset: AssetInfo,
+        /// The reason the asset is unavailable
+        reason: String,
+    },
+
+    /// An oracle has no price for an asset, see [`crate::oracle`]
+    #[error("No price for {asset}: {reason}")]
+    PriceUnavailable {
+        /// The asset in question
+        asset: AssetInfo,
+        /// The reason the price is unavailable
+        reason: String,
+    },
+
+    /// More of an asset than the allowed maximum was provided
+    #[error("Amount of {asset} exceeds max in of {max_in}")]
+    MaxInExceeded {
+        /// The asset in question, with the amount provided
+        asset: Asset,
+        /// The maximum amount allowed
+        max_in: Uint128,
+    },
+
+    /// An asset may not be sent or received according to an asset policy,
+    /// see [`crate::policy`]
+    #[error("Asset {asset} is not allowed: {reason}")]
+    AssetNotAllowed {
+        /// The asset in question
+        asset: AssetInfo,
+        /// The reason the asset is not allowed
+        reason: String,
+    },
+
+    /// Operations are paused, see [`crate::pause`]
+    #[error("Operations are paused")]
+    Paused {},
+
+    /// A lockup position can not be force unlocked
+    #[error("Can not force unlock lock {lock_id}: {reason}")]
+    ForceUnlockNotAllowed {
+        /// The ID of the lockup position
+        lock_id: u64,
+        /// The reason it can not be force unlocked
+        reason: ForceUnlockRestriction,
+    },
+
+    /// A lockup position contains coins of several denoms, which is not
+    /// supported as the unlock operations only unlock the LP token
+    #[error("Lock {lock_id} contains multiple denoms: {}", .denoms.join(", "))]
+    MultiCoinLock {
+        /// The ID of the lockup position
+        lock_id: u64,
+        /// The denoms of the coins in the lock
+        denoms: Vec<String>,
+    },
+
+    /// A reply ID is used by more than one submessage, see [`crate::reply`]
+    #[error("Reply ID {id} is used more than once")]
+    ReplyIdCollision {
+        /// The reply ID in question
+        id: u64,
+    },
+
     /// Asset is not an LP token
     #[error("Asset is not an LP token")]
     NotLpToken {},
@@ -84,6 +172,75 @@ pub enum CwDexError {
         /// The actual amount of tokens received
         received: Uint128,
     },
+
+    /// The pool invariant per LP token decreased across an operation
+    #[error("Pool invariant per LP token decreased from {before} to {after}")]
+    InvariantDecreased {
+        /// The invariant per LP token before the operation
+        before: Decimal256,
+        /// The invariant per LP token after the operation
+        after: Decimal256,
+    },
+
+    /// The LP token can not be staked in the staking contract, e.g. because it
+    /// is not an LP token of the dex the staking contract belongs to
+    #[error("LP token {lp_token} can not be staked in {staking_contract}: {reason}")]
+    StakingContractMismatch {
+        /// The LP token in question
+        lp_token: String,
+        /// The address of the staking contract
+        staking_contract: String,
+        /// Why the LP token can not be staked
+        reason: String,
+    },
+
+    /// Staking is not supported, e.g. because the chain has no incentives
+    /// contract to stake in
+    #[error("Staking is not supported: {reason}")]
+    StakingNotSupported {
+        /// Why staking is not supported
+        reason: String,
+    },
+
+    /// A staking implementation stakes another asset than the LP token of the
+    /// pool it is used with, see [`crate::traits::assert_compatible`], or a
+    /// lockup position only contains another asset than the LP token
+    #[error("Staking stakes {staked_asset} instead of the LP token {lp_token} of the pool")]
+    LpTokenMismatch {
+        /// The LP token of the pool
+        lp_token: AssetInfo,
+        /// The asset staked by the staking implementation
+        staked_asset: AssetInfo,
+    },
+
+    /// A list of assets contains several entries of the same asset
+    #[error("Asset {asset} is listed more than once")]
+    DuplicateAsset {
+        /// The asset in question
+        asset: AssetInfo,
+    },
+
+    /// A list of assets contains an entry with a zero amount
+    #[error("Amount of {asset} is zero")]
+    ZeroAmount {
+        /// The asset in question
+        asset: AssetInfo,
+    },
+
+    /// Claiming the selected rewards would also claim other pending rewards,
+    /// see [`crate::traits::Rewards::claim_rewards_filtered`]
+    #[error("Claiming the selected rewards would also claim {unselected}")]
+    UnselectedRewards {
+        /// The pending rewards that are not selected
+        unselected: AssetList,
+    },
+
+    /// The operation was executed after its deadline, see [`crate::builder`]
+    #[error("Deadline {deadline} exceeded")]
+    DeadlineExceeded {
+        /// The deadline of the operation
+        deadline: Timestamp,
+    },
 }
 
 impl From<CwDexError> for StdError {
@@ -0,0 +1,148 @@
+//! Contains helpers for correlating the events emitted by cw-dex operations,
+//! so that indexers can reconstruct multi-step flows such as zaps and
+//! compounds from transaction logs.
+
+use std::fmt;
+
+use apollo_cw_asset::AssetList;
+use cosmwasm_std::{Event, Response, Uint128};
+
+/// The prefix of the types of all events emitted by cw-dex operations
+pub const EVENT_TYPE_PREFIX: &str = "apollo/cw-dex/";
+
+/// The attribute holding the correlation id, see [`with_correlation_id`]
+pub const CORRELATION_ID_ATTRIBUTE: &str = "correlation_id";
+
+/// The attribute holding the position of an event among the cw-dex events of
+/// a response, see [`with_correlation_id`]
+pub const SEQUENCE_ATTRIBUTE: &str = "sequence";
+
+/// Returns true if `event` was emitted by a cw-dex operation.
+pub fn is_cw_dex_event(event: &Event) -> bool {
+    event.ty.starts_with(EVENT_TYPE_PREFIX)
+}
+
+/// Adds the caller-provided `correlation_id` and a sequence number to every
+/// event of `response` emitted by a cw-dex operation. The sequence number is
+/// the position of the event among the cw-dex events of the response,
+/// starting at zero, i.e. the order in which the operations were added.
+///
+/// Call this on the final response of a flow, e.g. after merging the
+/// responses of a swap and a provide liquidity. Events emitted when handling
+/// replies are part of another response and must be tagged separately, e.g.
+/// with the same correlation id saved for the reply. Events that already have
+/// a correlation id are left unchanged, so tagging a response twice does not
+/// overwrite the id of the first call.
+pub fn with_correlation_id(mut response: Response, correlation_id: &str) -> Response {
+    for (sequence, event) in response
+        .events
+        .iter_mut()
+        .filter(|event| is_cw_dex_event(event))
+        .enumerate()
+    {
+        if event
+            .attributes
+            .iter()
+            .any(|attr| attr.key == CORRELATION_ID_ATTRIBUTE)
+        {
+            continue;
+        }
+        event
+            .attributes
+            .push((CORRELATION_ID_ATTRIBUTE, correlation_id).into());
+        event
+            .attributes
+            .push((SEQUENCE_ATTRIBUTE, sequence.to_string()).into());
+    }
+    response
+}
+
+/// Returns the `apollo/cw-dex/provide_liquidity` event of a liquidity
+/// provision, with the attributes shared by all implementations, so that
+/// indexers can compute the realized slippage from the event alone:
+/// - `pool`: the identifier of the pool, i.e. its `Display` representation
+/// - `used`: one attribute per asset, with the amount of the asset expected to
+///   be used, i.e. the amount provided less any amount expected to be refunded
+/// - `expected_lp`: the amount of LP tokens expected to be received, omitted if
+///   the dex can not simulate the provision
+/// - `min_out`: the minimum amount of LP tokens to receive
+pub fn provide_liquidity_event(
+    pool: &impl fmt::Display,
+    used: &AssetList,
+    expected_lp: Option<Uint128>,
+    min_out: Uint128,
+) -> Event {
+    let event =
+        Event::new("apollo/cw-dex/provide_liquidity").add_attribute("pool", pool.to_string());
+    let event = used.iter().fold(event, |event, asset| {
+        event.add_attribute("used", asset.to_string())
+    });
+    match expected_lp {
+        Some(expected_lp) => event.add_attribute("expected_lp", expected_lp),
+        None => event,
+    }
+    .add_attribute("min_out", min_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetList};
+    use cosmwasm_std::{attr, Event, Response, Uint128};
+
+    use super::{provide_liquidity_event, with_correlation_id};
+
+    #[test]
+    fn test_with_correlation_id() {
+        let response = Response::new()
+            .add_event(Event::new("apollo/cw-dex/swap").add_attribute("offer", "100uosmo"))
+            .add_event(Event::new("transfer"))
+            .add_event(Event::new("apollo/cw-dex/provide_liquidity"));
+
+        let response = with_correlation_id(response, "compound-1");
+        assert_eq!(
+            response.events[0].attributes,
+            vec![
+                attr("offer", "100uosmo"),
+                attr("correlation_id", "compound-1"),
+                attr("sequence", "0")
+            ]
+        );
+        assert!(response.events[1].attributes.is_empty());
+        assert_eq!(
+            response.events[2].attributes,
+            vec![attr("correlation_id", "compound-1"), attr("sequence", "1")]
+        );
+
+        // Tagging again keeps the first correlation id
+        let tagged = with_correlation_id(response.clone(), "compound-2");
+        assert_eq!(tagged, response);
+    }
+
+    #[test]
+    fn test_provide_liquidity_event() {
+        let used = AssetList::from(vec![
+            Asset::native("uatom", 100u128),
+            Asset::native("uosmo", 250u128),
+        ]);
+        let event = provide_liquidity_event(
+            &"osmosis:pool/1",
+            &used,
+            Some(Uint128::new(50)),
+            Uint128::new(49),
+        );
+        assert_eq!(event.ty, "apollo/cw-dex/provide_liquidity");
+        assert_eq!(
+            event.attributes,
+            vec![
+                attr("pool", "osmosis:pool/1"),
+                attr("used", "uatom:100"),
+                attr("used", "uosmo:250"),
+                attr("expected_lp", "50"),
+                attr("min_out", "49"),
+            ]
+        );
+
+        let event = provide_liquidity_event(&"osmosis:pool/1", &used, None, Uint128::new(49));
+        assert!(!event.attributes.iter().any(|a| a.key == "expected_lp"));
+    }
+}
@@ -0,0 +1,237 @@
+//! Contains `FallbackSwapper`, for swapping in the first of an ordered list of
+//! pools that can currently return the requested minimum, e.g. to fall back to
+//! a secondary pool while the primary one is imbalanced or paused.
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use cosmwasm_std::{Deps, Env, Event, Response, StdError, Uint128};
+
+use crate::error::CwDexError;
+use crate::traits::Pool;
+
+/// Swaps in the first of `pools` whose simulation returns at least `min_out`.
+///
+/// Pools are tried in order. A pool is skipped if its simulation fails, e.g.
+/// because it does not contain the assets or is empty, if the simulated return
+/// is below `min_out`, or if building the swap fails, e.g. with
+/// [`CwDexError::Paused`] for a paused [`crate::pause::PausablePool`]. Messages
+/// are only returned for the chosen pool.
+pub struct FallbackSwapper<P: Pool> {
+    /// The pools to try, in order of preference
+    pub pools: Vec<P>,
+}
+
+impl<P: Pool> FallbackSwapper<P> {
+    /// Creates a new `FallbackSwapper` trying `pools` in the given order.
+    pub fn new(pools: Vec<P>) -> Self {
+        Self { pools }
+    }
+
+    /// Returns the index of the first pool whose simulation of swapping
+    /// `offer_asset` for `ask_asset_info` returns at least `min_out`, together
+    /// with the simulated return. Does not check whether building the swap
+    /// would succeed, see [`FallbackSwapper::swap`].
+    pub fn select_pool(
+        &self,
+        deps: Deps,
+        offer_asset: &Asset,
+        ask_asset_info: &AssetInfo,
+        min_out: Uint128,
+    ) -> Result<(usize, Uint128), CwDexError> {
+        self.select_pool_from(deps, offer_asset, ask_asset_info, min_out, 0)
+    }
+
+    /// Same as [`FallbackSwapper::select_pool`], but only considers the pools
+    /// from index `start` on.
+    fn select_pool_from(
+        &self,
+        deps: Deps,
+        offer_asset: &Asset,
+        ask_asset_info: &AssetInfo,
+        min_out: Uint128,
+        start: usize,
+    ) -> Result<(usize, Uint128), CwDexError> {
+        let mut best = None;
+        for (index, pool) in self.pools.iter().enumerate().skip(start) {
+            if let Ok(simulated) =
+                pool.simulate_swap(deps, offer_asset.clone(), ask_asset_info.clone())
+            {
+                if simulated >= min_out {
+                    return Ok((index, simulated));
+                }
+                best = best.max(Some(simulated));
+            }
+        }
+        Err(self.no_pool_error(offer_asset, ask_asset_info, min_out, best))
+    }
+
+    /// Swaps `offer_asset` for `ask_asset_info` in the first pool that can
+    /// return at least `min_out` and builds the swap, see [`FallbackSwapper`].
+    /// `min_out` is passed to [`Pool::swap`] of the chosen pool.
+    ///
+    /// Emits an event with the index of the chosen pool in `pools` and its
+    /// simulated return. If no pool can be used and the last pool that could
+    /// return `min_out` failed to build the swap, its error is returned.
+    pub fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        let mut start = 0;
+        let mut build_err = None;
+        loop {
+            let (index, simulated) =
+                match self.select_pool_from(deps, &offer_asset, &ask_asset_info, min_out, start) {
+                    Ok(selected) => selected,
+                    // The error of the last pool that failed to build the swap is
+                    // more telling than that no later pool returns enough
+                    Err(err) => return Err(build_err.unwrap_or(err)),
+                };
+            let response = match self.pools[index].swap(
+                deps,
+                env,
+                offer_asset.clone(),
+                ask_asset_info.clone(),
+                min_out,
+            ) {
+                Ok(response) => response,
+                Err(err) => {
+                    // Building the swap failed, e.g. because the pool is
+                    // paused, so the next pool is tried
+                    build_err = Some(err);
+                    start = index + 1;
+                    continue;
+                }
+            };
+
+            let event = Event::new("apollo/cw-dex/fallback_swap")
+                .add_attribute("pool_index", index.to_string())
+                .add_attribute("simulated_out", simulated)
+                .add_attribute("minimum_out_amount", min_out);
+            return Ok(response.add_event(event));
+        }
+    }
+
+    /// Returns [`CwDexError::MinOutNotReceived`] with the best simulated
+    /// return if any pool could simulate the swap, or a generic error
+    /// otherwise.
+    fn no_pool_error(
+        &self,
+        offer_asset: &Asset,
+        ask_asset_info: &AssetInfo,
+        min_out: Uint128,
+        best: Option<Uint128>,
+    ) -> CwDexError {
+        match best {
+            Some(received) => CwDexError::MinOutNotReceived { min_out, received },
+            None => StdError::generic_err(format!(
+                "None of {} pools can swap {} for {}",
+                self.pools.len(),
+                offer_asset,
+                ask_asset_info
+            ))
+            .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Decimal, Uint128};
+
+    use super::FallbackSwapper;
+    use crate::error::CwDexError;
+    use crate::testing::{attribute, MockPool};
+
+    /// Returns `rate` times the offer amount, or fails to simulate if `rate`
+    /// is `None`. Fails to build swaps if `paused`.
+    fn pool(rate: Option<u128>, paused: bool) -> MockPool {
+        let pool = MockPool::new(&["uosmo", "uatom"])
+            .with_swap_rate(rate.map(|rate| Decimal::from_ratio(rate, 1u128)));
+        if paused {
+            pool.paused()
+        } else {
+            pool
+        }
+    }
+
+    #[test]
+    fn test_fallback_swap() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let offer = Asset::native("uosmo", 10u128);
+        let ask = AssetInfo::native("uatom");
+
+        let swapper = FallbackSwapper::new(vec![
+            pool(None, false),
+            pool(Some(1), false),
+            pool(Some(3), true),
+            pool(Some(2), false),
+        ]);
+
+        // The pool paused when building the swap is skipped
+        let (index, simulated) = swapper
+            .select_pool(deps.as_ref(), &offer, &ask, Uint128::new(15))
+            .unwrap();
+        assert_eq!((index, simulated), (2, Uint128::new(30)));
+        let res = swapper
+            .swap(
+                deps.as_ref(),
+                &env,
+                offer.clone(),
+                ask.clone(),
+                Uint128::new(15),
+            )
+            .unwrap();
+        assert_eq!(
+            attribute(&res, "apollo/cw-dex/fallback_swap", "pool_index"),
+            "3"
+        );
+        assert_eq!(
+            attribute(&res, "apollo/cw-dex/fallback_swap", "simulated_out"),
+            "20"
+        );
+
+        // No pool returns enough
+        let err = swapper
+            .swap(
+                deps.as_ref(),
+                &env,
+                offer.clone(),
+                ask.clone(),
+                Uint128::new(40),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::MinOutNotReceived {
+                min_out: Uint128::new(40),
+                received: Uint128::new(30),
+            }
+        );
+
+        // The error of the only pool returning enough is returned when it
+        // fails to build the swap
+        let swapper = FallbackSwapper::new(vec![pool(Some(1), false), pool(Some(3), true)]);
+        let err = swapper
+            .swap(
+                deps.as_ref(),
+                &env,
+                offer.clone(),
+                ask.clone(),
+                Uint128::new(15),
+            )
+            .unwrap_err();
+        assert_eq!(err, CwDexError::Paused {});
+
+        // No pool can simulate the swap
+        let swapper = FallbackSwapper::new(vec![pool(None, false)]);
+        assert!(swapper
+            .swap(deps.as_ref(), &env, offer, ask, Uint128::zero())
+            .is_err());
+    }
+}
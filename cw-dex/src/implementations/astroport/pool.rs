@@ -1,5 +1,6 @@
 //! Pool trait implementation for Astroport
 
+use std::fmt;
 use std::str::FromStr;
 
 use apollo_cw_asset::{Asset, AssetInfo, AssetInfoBase, AssetList};
@@ -39,6 +40,12 @@ pub struct AstroportPool {
     pub liquidity_manager: Addr,
 }
 
+impl fmt::Display for AstroportPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "astroport:pair {}/{}", self.pair_addr, self.pair_type)
+    }
+}
+
 impl AstroportPool {
     /// Creates a new instance of `AstroportPool`
     ///
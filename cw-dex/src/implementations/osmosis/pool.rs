@@ -1,5 +1,6 @@
 //! Pool trait implementation for Osmosis
 
+use std::fmt;
 use std::ops::Deref;
 use std::str::FromStr;
 
@@ -30,6 +31,12 @@ pub struct OsmosisPool {
     pool_id: u64,
 }
 
+impl fmt::Display for OsmosisPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "osmosis:pool/{}", self.pool_id)
+    }
+}
+
 impl OsmosisPool {
     /// Creates a new `OsmosisPool` instance with the given `pool_id` and
     /// validates that the pool exists.
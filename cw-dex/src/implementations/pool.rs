@@ -1,6 +1,8 @@
 //! Contains an enum with variants for Pool implementations.
 //! For use in serialization.
 
+use std::fmt;
+
 use crate::error::CwDexError;
 use crate::traits::pool::Pool as PoolTrait;
 use apollo_cw_asset::{Asset, AssetInfo, AssetList};
@@ -106,6 +108,22 @@ impl Pool {
     }
 }
 
+/// Formats the pool as a compact identifier, e.g. `osmosis:pool/3` or
+/// `astroport:pair terra1.../xyk`.
+impl fmt::Display for Pool {
+    // This is needed to avoid a warning when compiling with all features
+    #[allow(unreachable_patterns)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "osmosis")]
+            Pool::Osmosis(x) => x.fmt(f),
+            #[cfg(feature = "astroport")]
+            Pool::Astroport(x) => x.fmt(f),
+            _ => f.write_str("unknown"),
+        }
+    }
+}
+
 // Implement the Pool trait for the Pool enum so we can use all the trait
 // methods directly on the enum type.
 impl PoolTrait for Pool {
@@ -14,10 +14,34 @@
 //!    - Via crate `cw-dex-osmosis`
 //! - [Astroport]
 //!    - Via crate `cw-dex-astroport`
+//! - [Kujira] (FIN order book)
+//!    - Via crate `cw-dex-kujira`
 
+pub mod accounting;
+pub mod assets;
+pub mod builder;
+pub mod cache;
+pub mod compound;
+pub mod composite;
+pub mod conv;
+pub mod dust;
 pub mod error;
+pub mod events;
+pub mod fallback;
+pub mod metrics;
+pub mod oracle;
+pub mod pause;
+pub mod policy;
+pub mod pool_type;
+pub mod prelude;
+pub mod received;
+pub mod reply;
+pub mod router;
 pub mod traits;
 
+#[cfg(test)]
+pub(crate) mod testing;
+
 #[deprecated(
     since = "0.5.2",
     note = "Please use separate implementation crates such as `cw-dex-astroport`, and `cw-dex-osmosis` instead"
@@ -27,3 +51,4 @@ pub mod implementations;
 pub use error::*;
 #[allow(deprecated)]
 pub use implementations::*;
+pub use pool_type::PoolType;
@@ -0,0 +1,190 @@
+//! Contains the `PoolMetrics` struct and the `QueryPoolMetrics` trait for
+//! querying a snapshot of the on-chain state of a pool. The snapshot contains
+//! everything needed by an off-chain APR calculator that can be derived
+//! without indexing swap volume.
+//!
+//! Also contains the `QueryImbalance` trait for querying the deviation of the
+//! reserves of a pool from its target weights.
+
+use apollo_cw_asset::{AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Decimal256, Deps, SignedDecimal, StdError, Uint128};
+
+use crate::error::CwDexError;
+use crate::pool_type::PoolType;
+
+/// The rate at which a reward asset is emitted to the stakers of a pool.
+#[cw_serde]
+pub struct RewardEmission {
+    /// The reward asset being emitted
+    pub info: AssetInfo,
+    /// The amount of the reward asset emitted per second to the whole pool
+    pub per_second: Decimal,
+}
+
+/// A snapshot of the on-chain metrics of a pool.
+#[cw_serde]
+pub struct PoolMetrics {
+    /// The LP token of the pool
+    pub lp_token: AssetInfo,
+    /// The type of the pool
+    pub pool_type: PoolType,
+    /// The current reserves of the pool
+    pub reserves: AssetList,
+    /// The total supply of LP tokens of the pool
+    pub total_shares: Uint128,
+    /// The fee charged on swaps, as a fraction of the offered amount
+    pub swap_fee: Decimal,
+    /// The fee charged on withdrawals of liquidity, as a fraction of the
+    /// withdrawn LP tokens
+    #[serde(default)]
+    pub exit_fee: Decimal,
+    /// The incentive emission rates of the pool. Empty if the venue does not
+    /// expose emission rates on-chain or they are queried from a separate
+    /// staking contract.
+    pub emissions: Vec<RewardEmission>,
+}
+
+impl PoolMetrics {
+    /// Returns the metrics with the given emission rates added, e.g. those
+    /// returned by the staking implementation of the venue.
+    pub fn with_emissions(mut self, emissions: Vec<RewardEmission>) -> Self {
+        self.emissions.extend(emissions);
+        self
+    }
+}
+
+/// Trait for pools that can return a [`PoolMetrics`] snapshot.
+pub trait QueryPoolMetrics {
+    /// Queries the current reserves, total shares, swap fee and, where
+    /// available, incentive emission rates of the pool.
+    fn query_pool_metrics(&self, deps: Deps) -> Result<PoolMetrics, CwDexError>;
+}
+
+/// Trait for pools with target weights of their reserves, e.g. weighted and
+/// stableswap pools, that can return the deviation of the reserves from the
+/// targets. Used e.g. by rebalancers, and by zaps to enter the pool with the
+/// most under-weighted asset, see [`most_underweighted`].
+pub trait QueryImbalance {
+    /// Returns for each asset of the pool the deviation of its share of the
+    /// normalized reserves from its target weight, relative to the target
+    /// weight, see [`compute_imbalance`]. Negative for under-weighted and
+    /// positive for over-weighted assets.
+    fn query_imbalance(&self, deps: Deps) -> Result<Vec<(AssetInfo, SignedDecimal)>, CwDexError>;
+}
+
+/// Returns for each asset the deviation of its share of the total `reserves`
+/// from its share of the total `weights`, as `share / target - 1`. E.g. an
+/// asset with a target of half of the reserves and a share of a quarter has a
+/// deviation of -0.5.
+///
+/// The reserves must be normalized so that they are comparable with each
+/// other, e.g. divided by the scaling factors of a stableswap pool. The
+/// weights need not sum to one. All deviations are zero for an empty pool.
+///
+/// Returns an error if the lengths of `reserves` and `weights` differ or a
+/// weight is zero.
+pub fn compute_imbalance(
+    reserves: &[(AssetInfo, Decimal256)],
+    weights: &[Decimal256],
+) -> Result<Vec<(AssetInfo, SignedDecimal)>, CwDexError> {
+    if reserves.len() != weights.len() || weights.iter().any(|weight| weight.is_zero()) {
+        return Err(
+            StdError::generic_err("there must be a non-zero weight for each reserve").into(),
+        );
+    }
+    let total_reserves = reserves
+        .iter()
+        .try_fold(Decimal256::zero(), |total, (_, reserve)| {
+            total.checked_add(*reserve)
+        })?;
+    let total_weights = weights
+        .iter()
+        .try_fold(Decimal256::zero(), |total, weight| {
+            total.checked_add(*weight)
+        })?;
+
+    reserves
+        .iter()
+        .zip(weights)
+        .map(|((info, reserve), weight)| {
+            if total_reserves.is_zero() {
+                return Ok((info.clone(), SignedDecimal::zero()));
+            }
+            // share / target = (reserve / total_reserves) / (weight / total_weights)
+            let ratio = reserve
+                .checked_mul(total_weights)?
+                .checked_div(weight.checked_mul(total_reserves)?)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            let ratio = SignedDecimal::try_from(Decimal::try_from(ratio)?)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            Ok((info.clone(), ratio.checked_sub(SignedDecimal::one())?))
+        })
+        .collect()
+}
+
+/// Returns the asset with the lowest deviation in `imbalance`, i.e. the most
+/// under-weighted asset of the pool. Returns `None` if `imbalance` is empty.
+pub fn most_underweighted(imbalance: &[(AssetInfo, SignedDecimal)]) -> Option<&AssetInfo> {
+    imbalance
+        .iter()
+        .min_by_key(|(_, deviation)| *deviation)
+        .map(|(info, _)| info)
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::{Decimal256, SignedDecimal};
+
+    use super::{compute_imbalance, most_underweighted};
+
+    fn reserves(amounts: &[(&str, u128)]) -> Vec<(AssetInfo, Decimal256)> {
+        amounts
+            .iter()
+            .map(|(denom, amount)| {
+                (
+                    AssetInfo::native(*denom),
+                    Decimal256::from_atomics(*amount, 0).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compute_imbalance() {
+        // A 2:1 pool with a share of a half instead of two thirds of the first asset
+        let weights = [Decimal256::percent(200), Decimal256::percent(100)];
+        let imbalance =
+            compute_imbalance(&reserves(&[("uosmo", 100), ("uatom", 100)]), &weights).unwrap();
+        assert_eq!(
+            imbalance,
+            vec![
+                (AssetInfo::native("uosmo"), SignedDecimal::percent(-25)),
+                (AssetInfo::native("uatom"), SignedDecimal::percent(50)),
+            ]
+        );
+        assert_eq!(
+            most_underweighted(&imbalance),
+            Some(&AssetInfo::native("uosmo"))
+        );
+
+        // Balanced and empty pools have no deviation
+        for amounts in [
+            [("uosmo", 200), ("uatom", 100)],
+            [("uosmo", 0), ("uatom", 0)],
+        ] {
+            assert!(compute_imbalance(&reserves(&amounts), &weights)
+                .unwrap()
+                .iter()
+                .all(|(_, deviation)| deviation.is_zero()));
+        }
+
+        assert!(compute_imbalance(&reserves(&[("uosmo", 100)]), &weights).is_err());
+        assert!(compute_imbalance(
+            &reserves(&[("uosmo", 100), ("uatom", 100)]),
+            &[Decimal256::one(), Decimal256::zero()]
+        )
+        .is_err());
+    }
+}
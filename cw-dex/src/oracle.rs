@@ -0,0 +1,119 @@
+//! Contains the [`Oracle`] trait, an interface to price sources that quote
+//! assets in a common quote asset, so that valuations and guards against
+//! price manipulation can be written once for any price source.
+//!
+//! Adapters for dex price sources are implemented in the implementation
+//! crates, e.g. `OsmosisTwapOracle` in `cw-dex-osmosis` and
+//! `AstroportCumulativePriceOracle` in `cw-dex-astroport`.
+
+use apollo_cw_asset::{AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Deps, Env, Uint128};
+
+use crate::error::CwDexError;
+
+/// A source of asset prices in terms of a quote asset.
+pub trait Oracle {
+    /// Returns the asset prices are quoted in.
+    fn quote_asset(&self) -> AssetInfo;
+
+    /// Returns the price of `asset`, in units of the quote asset per unit of
+    /// `asset`. The price of the quote asset is one.
+    ///
+    /// Returns [`CwDexError::PriceUnavailable`] if the oracle has no price for
+    /// `asset`.
+    fn price(&self, deps: Deps, env: &Env, asset: &AssetInfo) -> Result<Decimal, CwDexError>;
+
+    /// Returns the value of `assets` in units of the quote asset, rounding
+    /// down the value of each asset.
+    fn value(&self, deps: Deps, env: &Env, assets: &AssetList) -> Result<Uint128, CwDexError> {
+        assets.iter().try_fold(Uint128::zero(), |total, asset| {
+            let price = self.price(deps, env, &asset.info)?;
+            Ok(
+                total.checked_add(asset.amount.checked_mul_floor(price).map_err(|err| {
+                    CwDexError::PriceUnavailable {
+                        asset: asset.info.clone(),
+                        reason: err.to_string(),
+                    }
+                })?)?,
+            )
+        })
+    }
+}
+
+/// An [`Oracle`] with fixed prices, e.g. for tests or for assets pegged to the
+/// quote asset.
+#[cw_serde]
+pub struct FixedPriceOracle {
+    /// The asset prices are quoted in
+    pub quote: AssetInfo,
+    /// The prices of the assets, in units of `quote` per unit of the asset
+    pub prices: Vec<(AssetInfo, Decimal)>,
+}
+
+impl Oracle for FixedPriceOracle {
+    fn quote_asset(&self) -> AssetInfo {
+        self.quote.clone()
+    }
+
+    fn price(&self, _deps: Deps, _env: &Env, asset: &AssetInfo) -> Result<Decimal, CwDexError> {
+        if asset == &self.quote {
+            return Ok(Decimal::one());
+        }
+        self.prices
+            .iter()
+            .find(|(info, _)| info == asset)
+            .map(|(_, price)| *price)
+            .ok_or_else(|| CwDexError::PriceUnavailable {
+                asset: asset.clone(),
+                reason: "no fixed price".to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Decimal, Uint128};
+
+    use super::{FixedPriceOracle, Oracle};
+    use crate::CwDexError;
+
+    #[test]
+    fn test_fixed_price_oracle() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let oracle = FixedPriceOracle {
+            quote: AssetInfo::native("uusdc"),
+            prices: vec![(AssetInfo::native("uosmo"), Decimal::percent(150))],
+        };
+
+        assert_eq!(
+            oracle
+                .price(deps.as_ref(), &env, &AssetInfo::native("uusdc"))
+                .unwrap(),
+            Decimal::one()
+        );
+        let assets = AssetList::from(vec![
+            Asset::native("uosmo", 101u128),
+            Asset::native("uusdc", 10u128),
+        ]);
+        assert_eq!(
+            oracle.value(deps.as_ref(), &env, &assets).unwrap(),
+            Uint128::new(161)
+        );
+
+        let err = oracle
+            .price(deps.as_ref(), &env, &AssetInfo::native("uatom"))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::PriceUnavailable {
+                asset: AssetInfo::native("uatom"),
+                reason: "no fixed price".to_string(),
+            }
+        );
+        assert_eq!(err.to_string(), "No price for uatom: no fixed price");
+    }
+}
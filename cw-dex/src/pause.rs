@@ -0,0 +1,325 @@
+//! Contains the `PausablePool` and `PausableStaking` wrappers for refusing to
+//! build messages while a consumer-controlled pause flag is set.
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{Addr, Deps, Env, QuerierWrapper, Response, StdResult, Uint128};
+use cw_utils::Duration as CwDuration;
+
+use crate::error::CwDexError;
+use crate::traits::{ForceUnlock, LockedStaking, Pool, Rewards, Stake, Staking, Unlock, Unstake};
+
+/// A source of the pause flag checked by [`PausablePool`] and
+/// [`PausableStaking`], usually read from the consumer's storage.
+///
+/// Implemented for all closures of the form `Fn(Deps) -> StdResult<bool>`,
+/// e.g. `|deps: Deps| Ok(PAUSED.may_load(deps.storage)?.unwrap_or(false))`.
+pub trait PauseSwitch {
+    /// Returns whether operations are currently paused.
+    fn is_paused(&self, deps: Deps) -> StdResult<bool>;
+
+    /// Returns [`CwDexError::Paused`] if operations are currently paused.
+    fn ensure_not_paused(&self, deps: Deps) -> Result<(), CwDexError> {
+        if self.is_paused(deps)? {
+            return Err(CwDexError::Paused {});
+        }
+        Ok(())
+    }
+}
+
+impl<F> PauseSwitch for F
+where
+    F: Fn(Deps) -> StdResult<bool>,
+{
+    fn is_paused(&self, deps: Deps) -> StdResult<bool> {
+        self(deps)
+    }
+}
+
+/// A wrapper around a [`Pool`] that returns [`CwDexError::Paused`] instead of
+/// building messages while `switch` reports that operations are paused.
+///
+/// Queries and simulations are passed through to the wrapped pool, also while
+/// paused.
+pub struct PausablePool<P: Pool, S: PauseSwitch> {
+    pool: P,
+    switch: S,
+}
+
+impl<P: Pool, S: PauseSwitch> PausablePool<P, S> {
+    /// Creates a new `PausablePool` wrapping `pool`, paused according to
+    /// `switch`.
+    pub fn new(pool: P, switch: S) -> Self {
+        Self { pool, switch }
+    }
+
+    /// Returns a reference to the wrapped pool.
+    pub fn inner(&self) -> &P {
+        &self.pool
+    }
+
+    /// Consumes the wrapper, returning the wrapped pool.
+    pub fn into_inner(self) -> P {
+        self.pool
+    }
+}
+
+impl<P: Pool, S: PauseSwitch> Pool for PausablePool<P, S> {
+    fn provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.pool.provide_liquidity(deps, env, assets, min_out)
+    }
+
+    fn lp_token(&self) -> AssetInfo {
+        self.pool.lp_token()
+    }
+
+    fn withdraw_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+    ) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.pool.withdraw_liquidity(deps, env, lp_token, min_out)
+    }
+
+    fn withdraw_liquidity_to(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+        recipient: Option<Addr>,
+    ) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.pool
+            .withdraw_liquidity_to(deps, env, lp_token, min_out, recipient)
+    }
+
+    fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.pool
+            .swap(deps, env, offer_asset, ask_asset_info, min_out)
+    }
+
+    fn get_pool_liquidity(&self, deps: Deps) -> Result<AssetList, CwDexError> {
+        self.pool.get_pool_liquidity(deps)
+    }
+
+    fn simulate_provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+    ) -> Result<Asset, CwDexError> {
+        self.pool.simulate_provide_liquidity(deps, env, assets)
+    }
+
+    fn simulate_withdraw_liquidity(
+        &self,
+        deps: Deps,
+        lp_token: &Asset,
+    ) -> Result<AssetList, CwDexError> {
+        self.pool.simulate_withdraw_liquidity(deps, lp_token)
+    }
+
+    fn simulate_swap(
+        &self,
+        deps: Deps,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+    ) -> StdResult<Uint128> {
+        self.pool.simulate_swap(deps, offer_asset, ask_asset_info)
+    }
+
+    fn pool_assets(&self, deps: Deps) -> StdResult<Vec<AssetInfo>> {
+        self.pool.pool_assets(deps)
+    }
+}
+
+/// A wrapper around a staking implementation that returns
+/// [`CwDexError::Paused`] instead of building messages while `switch` reports
+/// that operations are paused.
+///
+/// Implements each of the staking traits implemented by the wrapped type.
+/// Queries are passed through to the wrapped type, also while paused.
+pub struct PausableStaking<T, S: PauseSwitch> {
+    staking: T,
+    switch: S,
+}
+
+impl<T, S: PauseSwitch> PausableStaking<T, S> {
+    /// Creates a new `PausableStaking` wrapping `staking`, paused according
+    /// to `switch`.
+    pub fn new(staking: T, switch: S) -> Self {
+        Self { staking, switch }
+    }
+
+    /// Returns a reference to the wrapped staking implementation.
+    pub fn inner(&self) -> &T {
+        &self.staking
+    }
+
+    /// Consumes the wrapper, returning the wrapped staking implementation.
+    pub fn into_inner(self) -> T {
+        self.staking
+    }
+}
+
+impl<T: Rewards, S: PauseSwitch> Rewards for PausableStaking<T, S> {
+    fn claim_rewards(&self, deps: Deps, env: &Env) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.staking.claim_rewards(deps, env)
+    }
+
+    fn query_pending_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<AssetList, CwDexError> {
+        self.staking.query_pending_rewards(querier, user)
+    }
+}
+
+impl<T: Stake, S: PauseSwitch> Stake for PausableStaking<T, S> {
+    fn stake(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.staking.stake(deps, env, amount)
+    }
+
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        self.staking.stake_asset()
+    }
+}
+
+impl<T: Unstake, S: PauseSwitch> Unstake for PausableStaking<T, S> {
+    fn unstake(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.staking.unstake(deps, env, amount)
+    }
+}
+
+impl<T: Staking, S: PauseSwitch> Staking for PausableStaking<T, S> {}
+
+impl<T: Unlock, S: PauseSwitch> Unlock for PausableStaking<T, S> {
+    fn unlock(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.staking.unlock(deps, env, amount)
+    }
+
+    fn withdraw_unlocked(
+        &self,
+        deps: Deps,
+        env: &Env,
+        amount: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.staking.withdraw_unlocked(deps, env, amount)
+    }
+}
+
+impl<T: LockedStaking, S: PauseSwitch> LockedStaking for PausableStaking<T, S> {
+    fn get_lockup_duration(&self, deps: Deps) -> Result<CwDuration, CwDexError> {
+        self.staking.get_lockup_duration(deps)
+    }
+}
+
+impl<T: ForceUnlock, S: PauseSwitch> ForceUnlock for PausableStaking<T, S> {
+    fn force_unlock(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lockup_id: Option<u64>,
+        amount: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.switch.ensure_not_paused(deps)?;
+        self.staking.force_unlock(deps, env, lockup_id, amount)
+    }
+
+    fn can_force_unlock(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lockup_id: Option<u64>,
+        amount: Uint128,
+    ) -> Result<(), CwDexError> {
+        self.staking.can_force_unlock(deps, env, lockup_id, amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Addr, Deps, Uint128};
+
+    use super::{PausablePool, PausableStaking};
+    use crate::error::CwDexError;
+    use crate::testing::{MockPool, MockStaking};
+    use crate::traits::{Pool, Rewards, Stake};
+
+    #[test]
+    fn test_pausable() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let paused = Cell::new(false);
+        let switch = |_: Deps| Ok(paused.get());
+        let pool = PausablePool::new(MockPool::new(&["uosmo", "uatom"]), switch);
+        let staking = PausableStaking::new(MockStaking::new("lp"), switch);
+        let offer = Asset::native("uosmo", 100u128);
+        let ask = AssetInfo::native("uatom");
+
+        // Not paused
+        pool.swap(
+            deps.as_ref(),
+            &env,
+            offer.clone(),
+            ask.clone(),
+            Uint128::one(),
+        )
+        .unwrap();
+        staking.stake(deps.as_ref(), &env, Uint128::one()).unwrap();
+
+        // Paused. Queries still work.
+        paused.set(true);
+        let err = pool
+            .swap(
+                deps.as_ref(),
+                &env,
+                offer.clone(),
+                ask.clone(),
+                Uint128::one(),
+            )
+            .unwrap_err();
+        assert_eq!(err, CwDexError::Paused {});
+        let err = pool
+            .provide_liquidity(deps.as_ref(), &env, AssetList::new(), Uint128::one())
+            .unwrap_err();
+        assert_eq!(err, CwDexError::Paused {});
+        let err = staking.claim_rewards(deps.as_ref(), &env).unwrap_err();
+        assert_eq!(err, CwDexError::Paused {});
+        assert_eq!(
+            pool.simulate_swap(deps.as_ref(), offer, ask).unwrap(),
+            Uint128::new(100)
+        );
+        assert!(staking
+            .query_pending_rewards(&deps.as_ref().querier, &Addr::unchecked("user"))
+            .is_ok());
+    }
+}
@@ -0,0 +1,366 @@
+//! Contains the `AssetPolicy` hook and the `PolicyPool` wrapper for refusing
+//! to build messages that send or receive assets not allowed by a
+//! consumer-defined policy, e.g. a deny-list of tokens received as rewards.
+//!
+//! Composite operations such as [`crate::composite::swap_and_stake`] and
+//! [`crate::fallback::FallbackSwapper`] only build messages through the
+//! [`Pool`] trait, so passing them a [`PolicyPool`] enforces the policy for
+//! all of their steps.
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{Addr, Deps, Env, Response, StdResult, Uint128};
+
+use crate::error::CwDexError;
+use crate::traits::Pool;
+
+/// Whether an asset checked by an [`AssetPolicy`] is sent or received by the
+/// contract in the operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetFlow {
+    /// The asset is sent by the contract, e.g. the offer asset of a swap or
+    /// the assets provided as liquidity
+    Offered,
+    /// The asset is received by the contract, e.g. the ask asset of a swap or
+    /// the assets returned when withdrawing liquidity
+    Received,
+}
+
+/// A policy deciding which assets may be sent or received in the operations
+/// of a [`PolicyPool`].
+///
+/// Implemented for [`AssetListPolicy`] and for all closures of the form
+/// `Fn(Deps, &AssetInfo, AssetFlow) -> Result<(), CwDexError>`.
+pub trait AssetPolicy {
+    /// Returns an error, usually [`CwDexError::AssetNotAllowed`], if `asset`
+    /// may not be sent or received as given by `flow`.
+    fn check_asset(&self, deps: Deps, asset: &AssetInfo, flow: AssetFlow)
+        -> Result<(), CwDexError>;
+
+    /// Checks each of `assets` with [`AssetPolicy::check_asset`].
+    fn check_assets<'a>(
+        &self,
+        deps: Deps,
+        assets: impl IntoIterator<Item = &'a AssetInfo>,
+        flow: AssetFlow,
+    ) -> Result<(), CwDexError> {
+        assets
+            .into_iter()
+            .try_for_each(|asset| self.check_asset(deps, asset, flow))
+    }
+}
+
+impl<F> AssetPolicy for F
+where
+    F: Fn(Deps, &AssetInfo, AssetFlow) -> Result<(), CwDexError>,
+{
+    fn check_asset(
+        &self,
+        deps: Deps,
+        asset: &AssetInfo,
+        flow: AssetFlow,
+    ) -> Result<(), CwDexError> {
+        self(deps, asset, flow)
+    }
+}
+
+/// An [`AssetPolicy`] restricting the assets the contract may receive to an
+/// allow-list, or excluding a deny-list of them. Sending assets is always
+/// allowed, so that e.g. a denied token received as rewards can still be
+/// swapped away.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetListPolicy {
+    /// Only the listed assets may be received
+    Allow(Vec<AssetInfo>),
+    /// All assets except the listed ones may be received
+    Deny(Vec<AssetInfo>),
+}
+
+impl AssetPolicy for AssetListPolicy {
+    fn check_asset(
+        &self,
+        _deps: Deps,
+        asset: &AssetInfo,
+        flow: AssetFlow,
+    ) -> Result<(), CwDexError> {
+        if flow == AssetFlow::Offered {
+            return Ok(());
+        }
+        let reason = match self {
+            AssetListPolicy::Allow(assets) if !assets.contains(asset) => "not in the allow-list",
+            AssetListPolicy::Deny(assets) if assets.contains(asset) => "in the deny-list",
+            _ => return Ok(()),
+        };
+        Err(CwDexError::AssetNotAllowed {
+            asset: asset.clone(),
+            reason: reason.to_string(),
+        })
+    }
+}
+
+/// A wrapper around a [`Pool`] that checks the assets sent and received by
+/// each operation against `policy` before building messages, returning the
+/// error of the policy instead if an asset is not allowed.
+///
+/// The LP token is checked as received when providing liquidity and as
+/// offered when withdrawing liquidity. All pool assets are checked as received
+/// when withdrawing liquidity. Queries and simulations are passed through to
+/// the wrapped pool without checks.
+pub struct PolicyPool<P: Pool, A: AssetPolicy> {
+    pool: P,
+    policy: A,
+}
+
+impl<P: Pool, A: AssetPolicy> PolicyPool<P, A> {
+    /// Creates a new `PolicyPool` wrapping `pool`, enforcing `policy`.
+    pub fn new(pool: P, policy: A) -> Self {
+        Self { pool, policy }
+    }
+
+    /// Returns a reference to the wrapped pool.
+    pub fn inner(&self) -> &P {
+        &self.pool
+    }
+
+    /// Consumes the wrapper, returning the wrapped pool.
+    pub fn into_inner(self) -> P {
+        self.pool
+    }
+
+    fn check_withdraw(&self, deps: Deps) -> Result<(), CwDexError> {
+        self.policy
+            .check_asset(deps, &self.pool.lp_token(), AssetFlow::Offered)?;
+        self.policy
+            .check_assets(deps, &self.pool.pool_assets(deps)?, AssetFlow::Received)
+    }
+}
+
+impl<P: Pool, A: AssetPolicy> Pool for PolicyPool<P, A> {
+    fn provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.policy.check_assets(
+            deps,
+            assets.into_iter().map(|asset| &asset.info),
+            AssetFlow::Offered,
+        )?;
+        self.policy
+            .check_asset(deps, &self.pool.lp_token(), AssetFlow::Received)?;
+        self.pool.provide_liquidity(deps, env, assets, min_out)
+    }
+
+    fn lp_token(&self) -> AssetInfo {
+        self.pool.lp_token()
+    }
+
+    fn withdraw_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+    ) -> Result<Response, CwDexError> {
+        self.check_withdraw(deps)?;
+        self.pool.withdraw_liquidity(deps, env, lp_token, min_out)
+    }
+
+    fn withdraw_liquidity_to(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+        recipient: Option<Addr>,
+    ) -> Result<Response, CwDexError> {
+        self.check_withdraw(deps)?;
+        self.pool
+            .withdraw_liquidity_to(deps, env, lp_token, min_out, recipient)
+    }
+
+    fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.policy
+            .check_asset(deps, &offer_asset.info, AssetFlow::Offered)?;
+        self.policy
+            .check_asset(deps, &ask_asset_info, AssetFlow::Received)?;
+        self.pool
+            .swap(deps, env, offer_asset, ask_asset_info, min_out)
+    }
+
+    fn get_pool_liquidity(&self, deps: Deps) -> Result<AssetList, CwDexError> {
+        self.pool.get_pool_liquidity(deps)
+    }
+
+    fn simulate_provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+    ) -> Result<Asset, CwDexError> {
+        self.pool.simulate_provide_liquidity(deps, env, assets)
+    }
+
+    fn simulate_withdraw_liquidity(
+        &self,
+        deps: Deps,
+        lp_token: &Asset,
+    ) -> Result<AssetList, CwDexError> {
+        self.pool.simulate_withdraw_liquidity(deps, lp_token)
+    }
+
+    fn simulate_swap(
+        &self,
+        deps: Deps,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+    ) -> StdResult<Uint128> {
+        self.pool.simulate_swap(deps, offer_asset, ask_asset_info)
+    }
+
+    fn pool_assets(&self, deps: Deps) -> StdResult<Vec<AssetInfo>> {
+        self.pool.pool_assets(deps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Deps, Uint128};
+
+    use super::{AssetFlow, AssetListPolicy, PolicyPool};
+    use crate::error::CwDexError;
+    use crate::fallback::FallbackSwapper;
+    use crate::testing::{attribute, MockPool};
+    use crate::traits::Pool;
+
+    fn mock_pool() -> MockPool {
+        MockPool::new(&["uosmo", "scam"])
+    }
+
+    fn not_allowed(asset: &str, reason: &str) -> CwDexError {
+        CwDexError::AssetNotAllowed {
+            asset: AssetInfo::native(asset),
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_deny_list() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = PolicyPool::new(
+            mock_pool(),
+            AssetListPolicy::Deny(vec![AssetInfo::native("scam")]),
+        );
+
+        // Swapping a denied asset away is allowed, swapping into it is not
+        pool.swap(
+            deps.as_ref(),
+            &env,
+            Asset::native("scam", 100u128),
+            AssetInfo::native("uosmo"),
+            Uint128::one(),
+        )
+        .unwrap();
+        let err = pool
+            .swap(
+                deps.as_ref(),
+                &env,
+                Asset::native("uosmo", 100u128),
+                AssetInfo::native("scam"),
+                Uint128::one(),
+            )
+            .unwrap_err();
+        assert_eq!(err, not_allowed("scam", "in the deny-list"));
+
+        // Withdrawing would receive the denied asset
+        let err = pool
+            .withdraw_liquidity(
+                deps.as_ref(),
+                &env,
+                Asset::native("lp", 100u128),
+                AssetList::new(),
+            )
+            .unwrap_err();
+        assert_eq!(err, not_allowed("scam", "in the deny-list"));
+
+        // Simulations are not checked
+        pool.simulate_swap(
+            deps.as_ref(),
+            Asset::native("uosmo", 100u128),
+            AssetInfo::native("scam"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = PolicyPool::new(
+            mock_pool(),
+            AssetListPolicy::Allow(vec![AssetInfo::native("uosmo")]),
+        );
+
+        pool.swap(
+            deps.as_ref(),
+            &env,
+            Asset::native("scam", 100u128),
+            AssetInfo::native("uosmo"),
+            Uint128::one(),
+        )
+        .unwrap();
+        let err = pool
+            .provide_liquidity(
+                deps.as_ref(),
+                &env,
+                vec![Asset::native("uosmo", 100u128)].into(),
+                Uint128::one(),
+            )
+            .unwrap_err();
+        assert_eq!(err, not_allowed("lp", "not in the allow-list"));
+    }
+
+    #[test]
+    fn test_closure_policy_in_fallback_swapper() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        type PolicyFn = dyn Fn(Deps, &AssetInfo, AssetFlow) -> Result<(), CwDexError>;
+        let deny_all: &PolicyFn = &|_, asset, _| {
+            Err(CwDexError::AssetNotAllowed {
+                asset: asset.clone(),
+                reason: "denied".to_string(),
+            })
+        };
+        let allow_all: &PolicyFn = &|_, _, _| Ok(());
+
+        // The pool denying the swap is skipped
+        let swapper = FallbackSwapper::new(vec![
+            PolicyPool::new(mock_pool(), deny_all),
+            PolicyPool::new(mock_pool(), allow_all),
+        ]);
+        let res = swapper
+            .swap(
+                deps.as_ref(),
+                &env,
+                Asset::native("uosmo", 100u128),
+                AssetInfo::native("uatom"),
+                Uint128::one(),
+            )
+            .unwrap();
+        assert_eq!(
+            attribute(&res, "apollo/cw-dex/fallback_swap", "pool_index"),
+            "1"
+        );
+    }
+}
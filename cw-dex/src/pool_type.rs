@@ -0,0 +1,37 @@
+//! Contains [`PoolType`], the type of the curve of a pool, independent of the
+//! dex.
+
+use std::fmt;
+
+use cosmwasm_schema::cw_serde;
+
+/// The type of a pool, mapped from the pool types of the dexes, e.g. for
+/// strategies that handle constant product and stableswap pools differently
+/// without depending on the types of a dex. See
+/// [`crate::metrics::PoolMetrics::pool_type`].
+#[cw_serde]
+pub enum PoolType {
+    /// A constant product (`x * y = k`) pool with equally weighted assets
+    ConstantProduct,
+    /// A stableswap pool for assets of (nearly) equal value
+    StableSwap,
+    /// A constant product pool with unequally weighted assets
+    Weighted,
+    /// A pool with concentrated liquidity, e.g. an Astroport PCL pair or an
+    /// Osmosis CL pool
+    Concentrated,
+    /// Any other type of pool, named by the dex
+    Custom(String),
+}
+
+impl fmt::Display for PoolType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolType::ConstantProduct => write!(f, "constant_product"),
+            PoolType::StableSwap => write!(f, "stable_swap"),
+            PoolType::Weighted => write!(f, "weighted"),
+            PoolType::Concentrated => write!(f, "concentrated"),
+            PoolType::Custom(name) => write!(f, "custom:{name}"),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Re-exports the traits of `cw-dex` and the types used in their signatures,
+//! so that contracts can import them with `use cw_dex::prelude::*;` instead of
+//! from the modules they are defined in.
+
+pub use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+
+pub use crate::error::CwDexError;
+pub use crate::oracle::Oracle;
+pub use crate::traits::{
+    ForceUnlock, ForceUnlockRestriction, LimitOrder, LockedStaking, MigratePosition, MinOut,
+    OrderBook, Pool, QueryPosition, Rewards, Router, SimulateStaking, SimulationMode, Stake,
+    Staking, StakingPosition, StakingPositionInfo, Unlock, UnlockingPosition, Unstake,
+};
@@ -0,0 +1,197 @@
+//! Contains `ReceivedAsset` for using assets sent to a contract, either as
+//! native funds or via a cw20 `Send`, in the `Pool` trait methods.
+//!
+//! Both kinds of assets are already in the contract's balance when the
+//! contract executes, so adding the received amounts to a queried balance
+//! counts them twice. `ReceivedAsset` keeps track of exactly what was received
+//! in the current message, so only those amounts are used.
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Deps, Env, MessageInfo, Response, StdError, Uint128};
+#[cfg(feature = "cw20")]
+use cw20::Cw20ReceiveMsg;
+
+use crate::error::CwDexError;
+use crate::traits::Pool;
+
+/// Assets received by the contract in the message being executed.
+#[cw_serde]
+pub enum ReceivedAsset {
+    /// Native funds sent along with the message
+    Native {
+        /// The sender of the message
+        sender: Addr,
+        /// The funds sent
+        coins: Vec<Coin>,
+    },
+    /// Cw20 tokens sent with a cw20 `Send`, received in a `Receive` message
+    Cw20 {
+        /// The sender of the tokens, i.e. the `sender` of the `Cw20ReceiveMsg`,
+        /// not the token contract
+        sender: Addr,
+        /// The tokens sent
+        asset: Asset,
+    },
+}
+
+impl ReceivedAsset {
+    /// Creates a `ReceivedAsset` from the native funds of `info`.
+    pub fn from_funds(info: &MessageInfo) -> Self {
+        Self::Native {
+            sender: info.sender.clone(),
+            coins: info.funds.clone(),
+        }
+    }
+
+    /// Creates a `ReceivedAsset` from a cw20 `Receive` message. `info` must
+    /// be the `MessageInfo` of the `Receive` message, so that `info.sender`
+    /// is the token contract.
+    ///
+    /// Returns an error if native funds were sent along with the `Receive`
+    /// message, as they would otherwise be ignored.
+    ///
+    /// Requires the `cw20` feature.
+    #[cfg(feature = "cw20")]
+    pub fn from_cw20_receive(
+        deps: Deps,
+        info: &MessageInfo,
+        receive_msg: &Cw20ReceiveMsg,
+    ) -> Result<Self, CwDexError> {
+        if !info.funds.is_empty() {
+            return Err(StdError::generic_err("Native funds sent along with cw20 Receive").into());
+        }
+        Ok(Self::Cw20 {
+            sender: deps.api.addr_validate(&receive_msg.sender)?,
+            asset: Asset::cw20(info.sender.clone(), receive_msg.amount),
+        })
+    }
+
+    /// Returns the sender of the assets.
+    pub fn sender(&self) -> &Addr {
+        match self {
+            Self::Native { sender, .. } | Self::Cw20 { sender, .. } => sender,
+        }
+    }
+
+    /// Returns the received assets.
+    pub fn assets(&self) -> AssetList {
+        match self {
+            Self::Native { coins, .. } => coins.clone().into(),
+            Self::Cw20 { asset, .. } => vec![asset.clone()].into(),
+        }
+    }
+
+    /// Returns the received amount of `info`.
+    pub fn amount_of(&self, info: &AssetInfo) -> Uint128 {
+        self.assets()
+            .find(info)
+            .map(|asset| asset.amount)
+            .unwrap_or_default()
+    }
+
+    /// Returns the contract's balance of `info` before the assets were
+    /// received, i.e. its current balance minus the received amount.
+    pub fn query_balance_before(
+        &self,
+        deps: Deps,
+        env: &Env,
+        info: &AssetInfo,
+    ) -> Result<Uint128, CwDexError> {
+        let balance = info.query_balance(&deps.querier, &env.contract.address)?;
+        Ok(balance.checked_sub(self.amount_of(info))?)
+    }
+
+    /// Provides the received assets as liquidity to `pool`, see
+    /// [`Pool::provide_liquidity`].
+    pub fn provide_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        pool: &impl Pool,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        pool.provide_liquidity(deps, env, self.assets(), min_out)
+    }
+
+    /// Swaps the received asset for `ask_asset_info` in `pool`, see
+    /// [`Pool::swap`]. Returns an error unless exactly one asset was received.
+    pub fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        pool: &impl Pool,
+        ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        let assets = self.assets();
+        let offer_asset = match assets.to_vec().as_slice() {
+            [asset] => asset.clone(),
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "Expected exactly one received asset to swap, got: {}",
+                    assets
+                ))
+                .into())
+            }
+        };
+        pool.swap(deps, env, offer_asset, ask_asset_info, min_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo};
+    use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
+    use cosmwasm_std::{coin, coins, Addr, Uint128};
+
+    use super::ReceivedAsset;
+
+    #[test]
+    fn test_received_asset() {
+        let deps = mock_dependencies_with_balance(&coins(150, "uosmo"));
+        let env = mock_env();
+
+        // Native funds
+        let info = mock_info("user", &[coin(100, "uosmo"), coin(5, "uatom")]);
+        let received = ReceivedAsset::from_funds(&info);
+        assert_eq!(received.sender(), &Addr::unchecked("user"));
+        assert_eq!(
+            received.amount_of(&AssetInfo::native("uosmo")),
+            Uint128::new(100)
+        );
+        // The received funds are already part of the contract's balance
+        assert_eq!(
+            received
+                .query_balance_before(deps.as_ref(), &env, &AssetInfo::native("uosmo"))
+                .unwrap(),
+            Uint128::new(50)
+        );
+    }
+
+    #[cfg(feature = "cw20")]
+    #[test]
+    fn test_received_cw20() {
+        use cosmwasm_std::to_json_binary;
+        use cw20::Cw20ReceiveMsg;
+
+        let deps = mock_dependencies_with_balance(&[]);
+        let receive_msg = Cw20ReceiveMsg {
+            sender: "user".to_string(),
+            amount: Uint128::new(42),
+            msg: to_json_binary(&()).unwrap(),
+        };
+        let info = mock_info("token", &[]);
+        let received =
+            ReceivedAsset::from_cw20_receive(deps.as_ref(), &info, &receive_msg).unwrap();
+        assert_eq!(received.sender(), &Addr::unchecked("user"));
+        assert_eq!(
+            received.assets().to_vec(),
+            vec![Asset::cw20(Addr::unchecked("token"), 42u128)]
+        );
+
+        // Native funds along with a cw20 Receive are rejected
+        let info = mock_info("token", &coins(1, "uosmo"));
+        assert!(ReceivedAsset::from_cw20_receive(deps.as_ref(), &info, &receive_msg).is_err());
+    }
+}
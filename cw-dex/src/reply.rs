@@ -0,0 +1,106 @@
+//! Helpers for the reply IDs of the submessages sent by implementations and
+//! composite operations.
+//!
+//! Every implementation that sends submessages uses a default reply ID, which
+//! can be changed per instance, e.g. with
+//! `OsmosisStaking::with_reply_ids` in `cw-dex-osmosis`. Contracts that send
+//! submessages of their own should check at instantiation that the reply IDs
+//! do not collide, using [`assert_unique_reply_ids`], and route replies by
+//! comparing against the configured IDs rather than the default constants.
+
+use cosmwasm_std::{ReplyOn, Response, StdError};
+
+use crate::error::CwDexError;
+
+/// Sends the last message of `response` as a submessage with reply ID `id`,
+/// replying on success.
+///
+/// Returns an error if `response` has no messages, or if its last message is
+/// already a submessage with a reply, which would otherwise be lost.
+pub fn reply_on_last_message(mut response: Response, id: u64) -> Result<Response, CwDexError> {
+    let last_msg = response
+        .messages
+        .last_mut()
+        .ok_or_else(|| StdError::generic_err("response has no messages to reply on"))?;
+    if last_msg.reply_on != ReplyOn::Never {
+        return Err(StdError::generic_err(format!(
+            "last message of the response already replies with ID {}",
+            last_msg.id
+        ))
+        .into());
+    }
+    last_msg.id = id;
+    last_msg.reply_on = ReplyOn::Success;
+    Ok(response)
+}
+
+/// Checks that each reply ID occurs only once in `ids`, e.g. the reply IDs of
+/// the implementations used by a contract along with its own reply IDs.
+///
+/// Returns [`CwDexError::ReplyIdCollision`] for the first ID that occurs more
+/// than once.
+pub fn assert_unique_reply_ids(ids: impl IntoIterator<Item = u64>) -> Result<(), CwDexError> {
+    let mut seen = vec![];
+    for id in ids {
+        if seen.contains(&id) {
+            return Err(CwDexError::ReplyIdCollision { id });
+        }
+        seen.push(id);
+    }
+    Ok(())
+}
+
+/// Returns the position of `id` in `ids`, or `None` if the reply is not one of
+/// them. Useful to route replies when the reply IDs are configured at runtime
+/// and can not be matched against constants.
+pub fn route_reply(id: u64, ids: &[u64]) -> Option<usize> {
+    ids.iter().position(|x| *x == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{BankMsg, ReplyOn, Response, SubMsg};
+
+    use super::*;
+
+    #[test]
+    fn test_reply_on_last_message() {
+        let response = Response::new()
+            .add_message(BankMsg::Burn { amount: vec![] })
+            .add_message(BankMsg::Burn { amount: vec![] });
+        let response = reply_on_last_message(response, 7).unwrap();
+        assert_eq!(response.messages[0].reply_on, ReplyOn::Never);
+        assert_eq!(response.messages[1].id, 7);
+        assert_eq!(response.messages[1].reply_on, ReplyOn::Success);
+
+        assert!(reply_on_last_message(Response::new(), 7).is_err());
+
+        // An existing reply is not overwritten
+        let response = Response::new()
+            .add_submessage(SubMsg::reply_always(BankMsg::Burn { amount: vec![] }, 3));
+        let err = reply_on_last_message(response, 7).unwrap_err();
+        assert!(
+            err.to_string().contains("already replies with ID 3"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_assert_unique_reply_ids() {
+        assert_unique_reply_ids([1, 2, 3]).unwrap();
+        assert_eq!(
+            assert_unique_reply_ids([1, 2, 1, 2]).unwrap_err(),
+            CwDexError::ReplyIdCollision { id: 1 }
+        );
+        assert_eq!(
+            CwDexError::ReplyIdCollision { id: 1 }.to_string(),
+            "Reply ID 1 is used more than once"
+        );
+    }
+
+    #[test]
+    fn test_route_reply() {
+        assert_eq!(route_reply(124, &[123, 124]), Some(1));
+        assert_eq!(route_reply(1, &[123, 124]), None);
+    }
+}
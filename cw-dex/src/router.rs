@@ -0,0 +1,238 @@
+//! Contains `SwapOperation` and `SwapOperationsList`, mirroring the types of
+//! the same name in `cw-dex-router`, for converting between swap routes and
+//! calls to the `Pool` trait.
+//!
+//! The types serialize to the same JSON as their `cw-dex-router`
+//! counterparts when `P` is the pool type used by the router, so messages and
+//! query responses of the router can be deserialized into them directly.
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Deps, Env, Response, StdError, StdResult, Uint128};
+
+use crate::error::CwDexError;
+use crate::traits::Pool;
+
+/// A single swap of `offer_asset_info` for `ask_asset_info` in `pool`.
+#[cw_serde]
+pub struct SwapOperation<P> {
+    /// The pool to swap in
+    pub pool: P,
+    /// The asset to offer
+    pub offer_asset_info: AssetInfo,
+    /// The asset to ask for
+    pub ask_asset_info: AssetInfo,
+}
+
+impl<P: Pool> SwapOperation<P> {
+    /// Creates a new `SwapOperation` and validates it against the assets of
+    /// the pool.
+    pub fn new(
+        deps: Deps,
+        pool: P,
+        offer_asset_info: AssetInfo,
+        ask_asset_info: AssetInfo,
+    ) -> Result<Self, CwDexError> {
+        let operation = Self {
+            pool,
+            offer_asset_info,
+            ask_asset_info,
+        };
+        operation.validate(deps)?;
+        Ok(operation)
+    }
+
+    /// Checks that the offer and ask assets are different and both assets of
+    /// the pool.
+    pub fn validate(&self, deps: Deps) -> Result<(), CwDexError> {
+        if self.offer_asset_info == self.ask_asset_info {
+            return Err(StdError::generic_err(format!(
+                "Swap operation offers and asks for the same asset {}",
+                self.offer_asset_info
+            ))
+            .into());
+        }
+        self.pool
+            .validate_swap_assets(deps, &self.offer_asset_info, &self.ask_asset_info)
+    }
+
+    /// Swaps `offer_amount` of the offer asset in the pool, see [`Pool::swap`].
+    pub fn swap(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_amount: Uint128,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        self.pool.swap(
+            deps,
+            env,
+            Asset::new(self.offer_asset_info.clone(), offer_amount),
+            self.ask_asset_info.clone(),
+            min_out,
+        )
+    }
+
+    /// Simulates swapping `offer_amount` of the offer asset in the pool, see
+    /// [`Pool::simulate_swap`].
+    pub fn simulate_swap(&self, deps: Deps, offer_amount: Uint128) -> StdResult<Uint128> {
+        self.pool.simulate_swap(
+            deps,
+            Asset::new(self.offer_asset_info.clone(), offer_amount),
+            self.ask_asset_info.clone(),
+        )
+    }
+}
+
+/// A route of swap operations, where the ask asset of each operation is the
+/// offer asset of the next.
+#[cw_serde]
+pub struct SwapOperationsList<P>(pub Vec<SwapOperation<P>>);
+
+impl<P: Pool> SwapOperationsList<P> {
+    /// Creates a new `SwapOperationsList` and validates it, see
+    /// [`SwapOperationsList::validate`].
+    pub fn new(deps: Deps, operations: Vec<SwapOperation<P>>) -> Result<Self, CwDexError> {
+        let list = Self(operations);
+        list.validate(deps)?;
+        Ok(list)
+    }
+
+    /// Creates a `SwapOperationsList` swapping along `path` through `pools`,
+    /// where the `i`th pool is used to swap `path[i]` for `path[i + 1]`.
+    pub fn from_path(deps: Deps, pools: Vec<P>, path: &[AssetInfo]) -> Result<Self, CwDexError> {
+        if path.len() != pools.len() + 1 {
+            return Err(StdError::generic_err(format!(
+                "Path of {} assets does not match {} pools",
+                path.len(),
+                pools.len()
+            ))
+            .into());
+        }
+        let operations = pools
+            .into_iter()
+            .zip(path.windows(2))
+            .map(|(pool, pair)| SwapOperation {
+                pool,
+                offer_asset_info: pair[0].clone(),
+                ask_asset_info: pair[1].clone(),
+            })
+            .collect();
+        Self::new(deps, operations)
+    }
+
+    /// Checks that the list is non-empty, that each operation is valid for its
+    /// pool, and that the operations are connected.
+    pub fn validate(&self, deps: Deps) -> Result<(), CwDexError> {
+        if self.0.is_empty() {
+            return Err(StdError::generic_err("Swap operations list is empty").into());
+        }
+        for operation in &self.0 {
+            operation.validate(deps)?;
+        }
+        for pair in self.0.windows(2) {
+            if pair[0].ask_asset_info != pair[1].offer_asset_info {
+                return Err(StdError::generic_err(format!(
+                    "Swap operations are not connected: {} is followed by an operation offering {}",
+                    pair[0].ask_asset_info, pair[1].offer_asset_info
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the asset offered by the first operation.
+    pub fn offer_asset_info(&self) -> Option<&AssetInfo> {
+        self.0.first().map(|op| &op.offer_asset_info)
+    }
+
+    /// Returns the asset received from the last operation.
+    pub fn ask_asset_info(&self) -> Option<&AssetInfo> {
+        self.0.last().map(|op| &op.ask_asset_info)
+    }
+
+    /// Returns the pools of the operations, in order.
+    pub fn pools(&self) -> Vec<&P> {
+        self.0.iter().map(|op| &op.pool).collect()
+    }
+
+    /// Simulates swapping `offer_amount` of the offer asset through all
+    /// operations and returns the amount of the ask asset received.
+    pub fn simulate_swaps(&self, deps: Deps, offer_amount: Uint128) -> StdResult<Uint128> {
+        self.0.iter().try_fold(offer_amount, |amount, operation| {
+            operation.simulate_swap(deps, amount)
+        })
+    }
+}
+
+impl<P> From<SwapOperationsList<P>> for Vec<SwapOperation<P>> {
+    fn from(list: SwapOperationsList<P>) -> Self {
+        list.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::{Decimal, Uint128};
+
+    use super::{SwapOperation, SwapOperationsList};
+    use crate::error::CwDexError;
+    use crate::testing::MockPool;
+
+    /// A pool of `a` and `b` that returns half of the offer amount on swaps
+    fn mock_pool(a: &str, b: &str) -> MockPool {
+        MockPool::new(&[a, b])
+            .with_lp(format!("lp/{a}/{b}"))
+            .with_swap_rate(Some(Decimal::percent(50)))
+    }
+
+    #[test]
+    fn test_swap_operations_list() {
+        let deps = mock_dependencies();
+        let path = vec![
+            AssetInfo::native("uatom"),
+            AssetInfo::native("uosmo"),
+            AssetInfo::native("uusdc"),
+        ];
+        let pools = vec![mock_pool("uosmo", "uatom"), mock_pool("uosmo", "uusdc")];
+
+        let list = SwapOperationsList::from_path(deps.as_ref(), pools.clone(), &path).unwrap();
+        assert_eq!(list.offer_asset_info(), Some(&path[0]));
+        assert_eq!(list.ask_asset_info(), Some(&path[2]));
+        assert_eq!(list.pools(), pools.iter().collect::<Vec<_>>());
+        assert_eq!(
+            list.simulate_swaps(deps.as_ref(), Uint128::new(100))
+                .unwrap(),
+            Uint128::new(25)
+        );
+
+        // Pools and path of different lengths
+        assert!(SwapOperationsList::from_path(deps.as_ref(), pools.clone(), &path[..2]).is_err());
+
+        // Asset not in pool
+        let err = SwapOperation::new(
+            deps.as_ref(),
+            mock_pool("uosmo", "uatom"),
+            AssetInfo::native("uosmo"),
+            AssetInfo::native("uusdc"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::AssetNotInPool {
+                asset: AssetInfo::native("uusdc")
+            }
+        );
+
+        // Operations not connected
+        let operations: Vec<_> = list.into();
+        assert!(SwapOperationsList::new(
+            deps.as_ref(),
+            vec![operations[1].clone(), operations[0].clone()]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,423 @@
+//! Mock implementations of the pool and staking traits shared by the unit
+//! tests of the crate.
+
+use std::cell::{Cell, RefCell};
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{
+    Addr, BankMsg, Decimal, Deps, Env, Event, QuerierWrapper, Response, StdError, StdResult,
+    Uint128,
+};
+
+use crate::error::CwDexError;
+use crate::traits::{Pool, QueryPosition, Rewards, SimulateStaking, Stake};
+
+/// A pool whose assets are worth one of each other. Its operations only add
+/// an event with their arguments, of type `provide`, `withdraw` or `swap`.
+///
+/// Providing returns one LP token per asset provided, withdrawing returns the
+/// LP amount times `withdraw_rates` of each asset, and swapping returns the
+/// offer amount times `swap_rate`. The simulations and liquidity queries are
+/// counted in `queries`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MockPool {
+    /// The denom of the LP token
+    pub lp: String,
+    /// The assets of the pool
+    pub assets: Vec<AssetInfo>,
+    /// The reserve of each asset
+    pub reserve: Uint128,
+    /// The amount of each asset returned per LP token withdrawn, in the order
+    /// of `assets`
+    pub withdraw_rates: Vec<Decimal>,
+    /// The amount returned per unit offered, or `None` if swap simulations
+    /// fail as in an empty pool
+    pub swap_rate: Option<Decimal>,
+    /// Whether swaps fail with [`CwDexError::Paused`]
+    pub paused: bool,
+    /// The number of simulations and liquidity queries made to the pool
+    pub queries: Cell<u32>,
+}
+
+impl MockPool {
+    /// Returns a pool of the native `denoms` with LP token `lp`, 100 of each
+    /// asset in reserve, an equal share of each asset per LP token withdrawn
+    /// and a swap rate of one
+    pub fn new(denoms: &[&str]) -> Self {
+        Self::with_assets(
+            denoms
+                .iter()
+                .map(|denom| AssetInfo::native(*denom))
+                .collect(),
+        )
+    }
+
+    /// Same as [`MockPool::new`], with any assets
+    pub fn with_assets(assets: Vec<AssetInfo>) -> Self {
+        let share = Decimal::from_ratio(1u128, assets.len() as u128);
+        Self {
+            lp: "lp".to_string(),
+            withdraw_rates: vec![share; assets.len()],
+            assets,
+            reserve: Uint128::new(100),
+            swap_rate: Some(Decimal::one()),
+            paused: false,
+            queries: Cell::new(0),
+        }
+    }
+
+    pub fn with_lp(mut self, lp: impl Into<String>) -> Self {
+        self.lp = lp.into();
+        self
+    }
+
+    pub fn with_withdraw_rates(mut self, rates: &[u128]) -> Self {
+        self.withdraw_rates = rates
+            .iter()
+            .map(|rate| Decimal::from_ratio(*rate, 1u128))
+            .collect();
+        self
+    }
+
+    pub fn with_swap_rate(mut self, rate: Option<Decimal>) -> Self {
+        self.swap_rate = rate;
+        self
+    }
+
+    pub fn paused(mut self) -> Self {
+        self.paused = true;
+        self
+    }
+
+    fn count(&self) {
+        self.queries.set(self.queries.get() + 1);
+    }
+}
+
+impl Pool for MockPool {
+    fn provide_liquidity(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        Ok(Response::new().add_event(
+            Event::new("provide")
+                .add_attribute("pool", &self.lp)
+                .add_attribute("assets", assets.to_string())
+                .add_attribute("min_out", min_out),
+        ))
+    }
+
+    fn lp_token(&self) -> AssetInfo {
+        AssetInfo::native(&self.lp)
+    }
+
+    fn withdraw_liquidity(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+    ) -> Result<Response, CwDexError> {
+        Ok(Response::new().add_event(
+            Event::new("withdraw")
+                .add_attribute("lp_token", lp_token.to_string())
+                .add_attribute("min_out", min_out.to_string()),
+        ))
+    }
+
+    fn swap(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        offer_asset: Asset,
+        _ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        if self.paused {
+            return Err(CwDexError::Paused {});
+        }
+        Ok(Response::new().add_event(
+            Event::new("swap")
+                .add_attribute("offer", offer_asset.to_string())
+                .add_attribute("min_out", min_out),
+        ))
+    }
+
+    fn get_pool_liquidity(&self, _deps: Deps) -> Result<AssetList, CwDexError> {
+        self.count();
+        Ok(self
+            .assets
+            .iter()
+            .map(|info| Asset::new(info.clone(), self.reserve))
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    fn simulate_provide_liquidity(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        assets: AssetList,
+    ) -> Result<Asset, CwDexError> {
+        self.count();
+        let amount = assets
+            .into_iter()
+            .map(|asset| asset.amount)
+            .sum::<Uint128>();
+        Ok(Asset::new(self.lp_token(), amount))
+    }
+
+    fn simulate_withdraw_liquidity(
+        &self,
+        _deps: Deps,
+        lp_token: &Asset,
+    ) -> Result<AssetList, CwDexError> {
+        self.count();
+        Ok(self
+            .assets
+            .iter()
+            .zip(&self.withdraw_rates)
+            .map(|(info, rate)| Asset::new(info.clone(), lp_token.amount.mul_floor(*rate)))
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    fn simulate_swap(
+        &self,
+        _deps: Deps,
+        offer_asset: Asset,
+        _ask_asset_info: AssetInfo,
+    ) -> StdResult<Uint128> {
+        self.count();
+        match self.swap_rate {
+            Some(rate) => Ok(offer_asset.amount.mul_floor(rate)),
+            None => Err(StdError::generic_err("Pool is empty")),
+        }
+    }
+
+    fn pool_assets(&self, _deps: Deps) -> StdResult<Vec<AssetInfo>> {
+        Ok(self.assets.clone())
+    }
+}
+
+/// A constant product pool of uatom and usdc without fees, whose LP token
+/// supply equals the uatom reserve, i.e. one LP token is worth one uatom and
+/// `usdc / atom` usdc. Providing and swapping add an event like [`MockPool`],
+/// and providing sends a message so that it can be replied on.
+pub(crate) struct XykPool {
+    /// The uatom reserve
+    pub atom: u128,
+    /// The usdc reserve
+    pub usdc: u128,
+}
+
+impl XykPool {
+    /// Returns the reserves of the offer and the ask asset of a swap offering
+    /// `offer`
+    fn reserves(&self, offer: &AssetInfo) -> (Uint128, Uint128) {
+        if offer == &AssetInfo::native("uatom") {
+            (self.atom.into(), self.usdc.into())
+        } else {
+            (self.usdc.into(), self.atom.into())
+        }
+    }
+}
+
+impl Pool for XykPool {
+    fn provide_liquidity(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        Ok(Response::new()
+            .add_message(BankMsg::Burn { amount: vec![] })
+            .add_event(
+                Event::new("provide")
+                    .add_attribute("assets", assets.to_string())
+                    .add_attribute("min_out", min_out),
+            ))
+    }
+
+    fn lp_token(&self) -> AssetInfo {
+        AssetInfo::native("lp")
+    }
+
+    fn withdraw_liquidity(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        _lp_token: Asset,
+        _min_out: AssetList,
+    ) -> Result<Response, CwDexError> {
+        unimplemented!()
+    }
+
+    fn swap(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        offer_asset: Asset,
+        _ask_asset_info: AssetInfo,
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError> {
+        Ok(Response::new().add_event(
+            Event::new("swap")
+                .add_attribute("offer", offer_asset.to_string())
+                .add_attribute("min_out", min_out),
+        ))
+    }
+
+    fn get_pool_liquidity(&self, _deps: Deps) -> Result<AssetList, CwDexError> {
+        Ok(vec![
+            Asset::native("uatom", self.atom),
+            Asset::native("usdc", self.usdc),
+        ]
+        .into())
+    }
+
+    fn simulate_provide_liquidity(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        assets: AssetList,
+    ) -> Result<Asset, CwDexError> {
+        let amount_of = |denom: &str| assets.find(&AssetInfo::native(denom)).map(|a| a.amount);
+        let atom = amount_of("uatom").unwrap_or_default();
+        let usdc = amount_of("usdc").unwrap_or_default();
+        Ok(Asset::new(
+            self.lp_token(),
+            atom.min(usdc.multiply_ratio(self.atom, self.usdc)),
+        ))
+    }
+
+    fn simulate_withdraw_liquidity(
+        &self,
+        _deps: Deps,
+        lp_token: &Asset,
+    ) -> Result<AssetList, CwDexError> {
+        Ok(vec![
+            Asset::native("uatom", lp_token.amount),
+            Asset::native("usdc", lp_token.amount.multiply_ratio(self.usdc, self.atom)),
+        ]
+        .into())
+    }
+
+    fn simulate_swap(
+        &self,
+        _deps: Deps,
+        offer_asset: Asset,
+        _ask_asset_info: AssetInfo,
+    ) -> StdResult<Uint128> {
+        let (offer_reserve, ask_reserve) = self.reserves(&offer_asset.info);
+        Ok(offer_asset
+            .amount
+            .multiply_ratio(ask_reserve, offer_reserve + offer_asset.amount))
+    }
+
+    fn pool_assets(&self, _deps: Deps) -> StdResult<Vec<AssetInfo>> {
+        Ok(vec![AssetInfo::native("uatom"), AssetInfo::native("usdc")])
+    }
+}
+
+/// Staking of the LP token `lp` with `staked` staked. Claiming pays out the
+/// `pending` rewards with `claim_messages` messages and an `action` attribute,
+/// and staking adds a `stake` event with the amount.
+pub(crate) struct MockStaking {
+    /// The denom of the staked LP token
+    pub lp: &'static str,
+    /// The amount staked by any user
+    pub staked: Uint128,
+    /// The pending rewards, which are reset by claiming
+    pub pending: RefCell<AssetList>,
+    /// The number of messages sent by claiming
+    pub claim_messages: usize,
+    /// Whether rewards are paid out when the staked amount changes
+    pub pays_rewards: bool,
+}
+
+impl MockStaking {
+    /// Returns a staking of `lp` with 100 staked, no pending rewards and a
+    /// claim of one message
+    pub fn new(lp: &'static str) -> Self {
+        Self {
+            lp,
+            staked: Uint128::new(100),
+            pending: RefCell::new(AssetList::new()),
+            claim_messages: 1,
+            pays_rewards: true,
+        }
+    }
+
+    pub fn with_pending(self, pending: Vec<Asset>) -> Self {
+        self.pending.replace(pending.into());
+        self
+    }
+
+    pub fn with_claim_messages(mut self, messages: usize) -> Self {
+        self.claim_messages = messages;
+        self
+    }
+
+    pub fn with_pays_rewards(mut self, pays_rewards: bool) -> Self {
+        self.pays_rewards = pays_rewards;
+        self
+    }
+}
+
+impl Rewards for MockStaking {
+    fn claim_rewards(&self, _deps: Deps, _env: &Env) -> Result<Response, CwDexError> {
+        self.pending.replace(AssetList::new());
+        Ok(Response::new()
+            .add_messages((0..self.claim_messages).map(|_| BankMsg::Burn { amount: vec![] }))
+            .add_attribute("action", "claim_rewards"))
+    }
+
+    fn query_pending_rewards(
+        &self,
+        _querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<AssetList, CwDexError> {
+        Ok(self.pending.borrow().clone())
+    }
+}
+
+impl Stake for MockStaking {
+    fn stake(&self, _deps: Deps, _env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        Ok(Response::new().add_event(Event::new("stake").add_attribute("amount", amount)))
+    }
+
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        Some(AssetInfo::native(self.lp))
+    }
+}
+
+impl SimulateStaking for MockStaking {
+    fn query_staked_amount(
+        &self,
+        _querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<Uint128, CwDexError> {
+        Ok(self.staked)
+    }
+
+    fn pays_rewards_on_stake_change(&self) -> bool {
+        self.pays_rewards
+    }
+}
+
+impl QueryPosition for MockStaking {}
+
+/// Returns the value of `key` in the event of type `ty` of `res`
+pub(crate) fn attribute(res: &Response, ty: &str, key: &str) -> String {
+    res.events
+        .iter()
+        .find(|event| event.ty == ty)
+        .and_then(|event| event.attributes.iter().find(|attr| attr.key == key))
+        .map(|attr| attr.value.clone())
+        .unwrap_or_else(|| panic!("no attribute {key} in event {ty}"))
+}
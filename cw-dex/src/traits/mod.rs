@@ -1,8 +1,12 @@
 //! Traits that define the common interface of all supported decentralized
 //! exchanges
 
+pub mod orderbook;
 pub mod pool;
+pub mod router;
 pub mod staking;
 
+pub use orderbook::*;
 pub use pool::*;
+pub use router::*;
 pub use staking::*;
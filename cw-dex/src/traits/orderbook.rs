@@ -0,0 +1,87 @@
+//! Contains the `OrderBook` trait for abstracting the behavior of an order
+//! book style dex.
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Deps, Env, QuerierWrapper, Response, Uint128};
+
+use crate::error::CwDexError;
+
+/// A limit order as returned by [`OrderBook::query_order`].
+#[cw_serde]
+pub struct LimitOrder {
+    /// The unique ID of the order on the order book
+    pub id: Uint128,
+    /// The address that placed the order
+    pub owner: Addr,
+    /// The remaining (unfilled) part of the offered asset
+    pub offer: Asset,
+    /// The amount of the offered asset at the time the order was placed
+    pub original_offer_amount: Uint128,
+    /// The asset asked for in return
+    pub ask_asset_info: AssetInfo,
+    /// The limit price of the order, expressed as the amount of
+    /// `ask_asset_info` received per unit of the offered asset
+    pub price: Decimal,
+    /// The amount of `ask_asset_info` that has been filled and can be claimed
+    /// by calling [`OrderBook::claim_filled`]
+    pub filled_amount: Uint128,
+}
+
+/// Trait to represent an order book market for a pair of assets.
+pub trait OrderBook {
+    /// Place a limit order on the order book.
+    ///
+    /// Returns a Response with the necessary messages to place the order.
+    /// Depending on the implementation, the ID of the new order may be
+    /// returned in the data of a submessage and you may need to handle this
+    /// in a reply.
+    ///
+    /// Arguments:
+    /// - `offer_asset`: The asset to offer.
+    /// - `ask_asset_info`: The asset to receive when the order is filled.
+    /// - `price`: The limit price, expressed as the amount of `ask_asset_info`
+    ///   to receive per unit of `offer_asset`.
+    fn place_limit_order(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        price: Decimal,
+    ) -> Result<Response, CwDexError>;
+
+    /// Cancel an order, returning the unfilled part of the offered asset.
+    ///
+    /// Arguments:
+    /// - `order_id`: The ID of the order to cancel.
+    fn cancel_order(
+        &self,
+        deps: Deps,
+        env: &Env,
+        order_id: Uint128,
+    ) -> Result<Response, CwDexError>;
+
+    /// Claim the filled amounts of the given orders.
+    ///
+    /// Arguments:
+    /// - `order_ids`: The IDs of the orders to claim.
+    fn claim_filled(
+        &self,
+        deps: Deps,
+        env: &Env,
+        order_ids: Vec<Uint128>,
+    ) -> Result<Response, CwDexError>;
+
+    // === Query functions ===
+
+    /// Returns the current state of the order with the given ID.
+    fn query_order(
+        &self,
+        querier: &QuerierWrapper,
+        order_id: Uint128,
+    ) -> Result<LimitOrder, CwDexError>;
+
+    /// Returns the two assets traded on the order book.
+    fn market_assets(&self) -> Vec<AssetInfo>;
+}
@@ -1,8 +1,10 @@
 //! Contains the `Pool` trait for abstracting the behavior of a dex pool.
 
 use apollo_cw_asset::{Asset, AssetInfo, AssetList};
-use cosmwasm_std::{Deps, Env, Response, StdResult, Uint128};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Deps, Env, Event, Response, StdError, StdResult, Uint128};
 
+use crate::assets::merge_assets;
 use crate::error::CwDexError;
 
 /// Trait to represent an AMM pool.
@@ -21,7 +23,9 @@ pub trait Pool {
     ///
     /// Arguments:
     /// - `assets`: the assets to provide liquidity with
-    /// - `min_out`: the minimum amount of LP tokens to receive
+    /// - `min_out`: the minimum amount of LP tokens to receive, as an absolute
+    ///   amount. See [`MinOut`] for how it is enforced and
+    ///   [`Pool::provide_liquidity_with_min_out`] to pass a slippage instead.
     fn provide_liquidity(
         &self,
         deps: Deps,
@@ -30,6 +34,45 @@ pub trait Pool {
         min_out: Uint128,
     ) -> Result<Response, CwDexError>;
 
+    /// Provide liquidity to the pool, first checking that no more than
+    /// `max_in` of each asset is provided.
+    ///
+    /// This protects against accidentally depositing more than intended, e.g.
+    /// when `assets` is computed from balance queries that race with other
+    /// messages in the same transaction. The check is done before any
+    /// messages are created, see [`Pool::provide_liquidity`] for the rest of
+    /// the behavior.
+    ///
+    /// Arguments:
+    /// - `assets`: the assets to provide liquidity with
+    /// - `min_out`: the minimum amount of LP tokens to receive
+    /// - `max_in`: the maximum amounts of the assets to provide. Assets without
+    ///   an entry in `max_in` are not limited. If `None`, this is the same as
+    ///   [`Pool::provide_liquidity`].
+    fn provide_liquidity_with_max_in(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        min_out: Uint128,
+        max_in: Option<AssetList>,
+    ) -> Result<Response, CwDexError> {
+        if let Some(max_in) = max_in {
+            let assets = self.canonicalize_assets(deps, assets.clone())?;
+            for asset in assets.iter() {
+                if let Some(max) = max_in.find(&asset.info) {
+                    if asset.amount > max.amount {
+                        return Err(CwDexError::MaxInExceeded {
+                            asset: asset.clone(),
+                            max_in: max.amount,
+                        });
+                    }
+                }
+            }
+        }
+        self.provide_liquidity(deps, env, assets, min_out)
+    }
+
     /// Get the LP token for this pool
     fn lp_token(&self) -> AssetInfo;
 
@@ -50,12 +93,122 @@ pub trait Pool {
         min_out: AssetList,
     ) -> Result<Response, CwDexError>;
 
+    /// Withdraw liquidity from the pool and send the withdrawn assets to
+    /// `recipient`.
+    ///
+    /// The default implementation appends transfer messages for the `min_out`
+    /// amounts to the response of [`Pool::withdraw_liquidity`]. The amounts
+    /// received are only known once the withdrawal has executed and may differ
+    /// from [`Pool::simulate_withdraw_liquidity`], e.g. by rounding, so only
+    /// the amounts the dex guarantees are sent. Anything received in excess of
+    /// `min_out` stays with the contract. Pass the simulated amounts as
+    /// `min_out` to send all of the withdrawn assets, failing the withdrawal
+    /// if less is received.
+    ///
+    /// Arguments:
+    /// - `lp_token`: the LP tokens to withdraw as an [`Asset`].
+    /// - `min_out`: the minimum amounts of the assets to receive, which are
+    ///   sent to `recipient`. Assets not in `min_out` are not sent.
+    /// - `recipient`: the address to send the withdrawn assets to. If `None`,
+    ///   the assets are kept by the contract, same as
+    ///   [`Pool::withdraw_liquidity`].
+    fn withdraw_liquidity_to(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        min_out: AssetList,
+        recipient: Option<Addr>,
+    ) -> Result<Response, CwDexError> {
+        let recipient = match recipient {
+            Some(recipient) if recipient != env.contract.address => recipient,
+            _ => return self.withdraw_liquidity(deps, env, lp_token, min_out),
+        };
+
+        let mut sent = self.canonicalize_assets(deps, min_out.clone())?;
+        let res = self.withdraw_liquidity(deps, env, lp_token, min_out)?;
+
+        Ok(res
+            .add_messages(sent.purge().transfer_msgs(&recipient)?)
+            .add_event(
+                Event::new("apollo/cw-dex/withdraw_liquidity_to")
+                    .add_attribute("recipient", recipient)
+                    .add_attribute("assets", sent.to_string()),
+            ))
+    }
+
+    /// Returns the minimum amounts of the assets to receive for withdrawing
+    /// `lp_token`, allowing for `max_slippage` relative to
+    /// [`Pool::simulate_withdraw_liquidity`]. The amounts are rounded down.
+    ///
+    /// Returns an error if `max_slippage` is larger than one.
+    fn simulate_withdraw_min_out(
+        &self,
+        deps: Deps,
+        lp_token: &Asset,
+        max_slippage: Decimal,
+    ) -> Result<AssetList, CwDexError> {
+        let factor = Decimal::one().checked_sub(max_slippage)?;
+        let min_out: Vec<Asset> = self
+            .simulate_withdraw_liquidity(deps, lp_token)?
+            .into_iter()
+            .map(|asset| Asset::new(asset.info.clone(), asset.amount.mul_floor(factor)))
+            .collect();
+        Ok(min_out.into())
+    }
+
+    /// Withdraw liquidity from the pool, receiving at least the simulated
+    /// amounts minus `max_slippage`, see [`Pool::simulate_withdraw_min_out`].
+    /// The minimum amounts are passed to [`Pool::withdraw_liquidity`], which
+    /// enforces them using the protections of the dex where available.
+    fn withdraw_liquidity_with_slippage(
+        &self,
+        deps: Deps,
+        env: &Env,
+        lp_token: Asset,
+        max_slippage: Decimal,
+    ) -> Result<Response, CwDexError> {
+        let min_out = self.simulate_withdraw_min_out(deps, &lp_token, max_slippage)?;
+        self.withdraw_liquidity(deps, env, lp_token, min_out)
+    }
+
+    /// Withdraws the whole balance of [`Pool::lp_token`] held by the contract,
+    /// as queried when this is called, allowing for `max_slippage`, see
+    /// [`Pool::withdraw_liquidity_with_slippage`].
+    ///
+    /// If the balance is zero, nothing is withdrawn and the response has an
+    /// `apollo/cw-dex/withdraw_liquidity` event with the attribute `skipped`
+    /// set to `zero_balance`.
+    fn withdraw_all_liquidity(
+        &self,
+        deps: Deps,
+        env: &Env,
+        max_slippage: Decimal,
+    ) -> Result<Response, CwDexError> {
+        let lp_token = self.lp_token();
+        let balance = lp_token.query_balance(&deps.querier, &env.contract.address)?;
+        if balance.is_zero() {
+            let event = Event::new("apollo/cw-dex/withdraw_liquidity")
+                .add_attribute("asset", lp_token.to_string())
+                .add_attribute("skipped", "zero_balance");
+            return Ok(Response::new().add_event(event));
+        }
+        self.withdraw_liquidity_with_slippage(
+            deps,
+            env,
+            Asset::new(lp_token, balance),
+            max_slippage,
+        )
+    }
+
     /// Swap assets in the pool.
     ///
     /// Arguments:
     /// - `offer_asset`: The asset we want to swap.
     /// - `ask_asset`: The asset we want to receive from the swap.
-    /// - `min_out`: The minimum amount of `ask_asset` to receive.
+    /// - `min_out`: The minimum amount of `ask_asset` to receive, as an
+    ///   absolute amount. See [`MinOut`] for how it is enforced and
+    ///   [`Pool::swap_with_min_out`] to pass a slippage instead.
     ///
     /// Returns a Response containing the messages to swap assets in the pool.
     fn swap(
@@ -67,6 +220,83 @@ pub trait Pool {
         min_out: Uint128,
     ) -> Result<Response, CwDexError>;
 
+    /// Provide liquidity to the pool, receiving at least `min_out` LP tokens.
+    /// A [`MinOut::Slippage`] is resolved against
+    /// [`Pool::simulate_provide_liquidity`], see [`MinOut::resolve`], and the
+    /// resulting amount passed to [`Pool::provide_liquidity`].
+    fn provide_liquidity_with_min_out(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        min_out: MinOut,
+    ) -> Result<Response, CwDexError> {
+        let min_out = match min_out {
+            MinOut::Absolute(amount) => amount,
+            MinOut::Slippage(_) => min_out.resolve(
+                self.simulate_provide_liquidity(deps, env, assets.clone())?
+                    .amount,
+            )?,
+        };
+        self.provide_liquidity(deps, env, assets, min_out)
+    }
+
+    /// Provide liquidity to the pool, receiving at least the simulated amount
+    /// of LP tokens minus `max_slippage`. Same as
+    /// [`Pool::provide_liquidity_with_min_out`] with [`MinOut::Slippage`].
+    fn provide_liquidity_with_slippage(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: AssetList,
+        max_slippage: Decimal,
+    ) -> Result<Response, CwDexError> {
+        self.provide_liquidity_with_min_out(deps, env, assets, MinOut::Slippage(max_slippage))
+    }
+
+    /// Swap assets in the pool, receiving at least `min_out` of
+    /// `ask_asset_info`. A [`MinOut::Slippage`] is resolved against
+    /// [`Pool::simulate_swap`], see [`MinOut::resolve`], and the resulting
+    /// amount passed to [`Pool::swap`].
+    fn swap_with_min_out(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        min_out: MinOut,
+    ) -> Result<Response, CwDexError> {
+        let min_out = match min_out {
+            MinOut::Absolute(amount) => amount,
+            MinOut::Slippage(_) => min_out.resolve(self.simulate_swap(
+                deps,
+                offer_asset.clone(),
+                ask_asset_info.clone(),
+            )?)?,
+        };
+        self.swap(deps, env, offer_asset, ask_asset_info, min_out)
+    }
+
+    /// Swap assets in the pool, receiving at least the simulated amount of
+    /// `ask_asset_info` minus `max_slippage`. Same as
+    /// [`Pool::swap_with_min_out`] with [`MinOut::Slippage`].
+    fn swap_with_slippage(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        max_slippage: Decimal,
+    ) -> Result<Response, CwDexError> {
+        self.swap_with_min_out(
+            deps,
+            env,
+            offer_asset,
+            ask_asset_info,
+            MinOut::Slippage(max_slippage),
+        )
+    }
+
     // === Query functions ===
 
     /// Returns the current balance of the underlying assets in the pool.
@@ -118,4 +348,427 @@ pub trait Pool {
             .map(|asset| asset.info.clone())
             .collect())
     }
+
+    /// Returns `assets` ordered the same way as [`Pool::pool_assets`], with
+    /// duplicate entries merged and zero amounts removed, see
+    /// [`merge_assets`]. Implementations use this internally, so
+    /// callers do not need to care about the order of the assets they pass in.
+    /// Canonicalizing an already canonical list returns the same list.
+    ///
+    /// Returns an error if `assets` contains an asset that is not in the pool.
+    ///
+    /// Arguments:
+    /// - `assets`: the assets to order
+    fn canonicalize_assets(&self, deps: Deps, assets: AssetList) -> Result<AssetList, CwDexError> {
+        let pool_assets = self.pool_assets(deps)?;
+        if let Some(asset) = assets
+            .iter()
+            .find(|asset| !pool_assets.contains(&asset.info))
+        {
+            return Err(CwDexError::AssetNotInPool {
+                asset: asset.info.clone(),
+            });
+        }
+
+        let merged = merge_assets(&assets)?;
+        let canonical: Vec<Asset> = pool_assets
+            .iter()
+            .filter_map(|info| merged.find(info).cloned())
+            .collect();
+
+        Ok(canonical.into())
+    }
+
+    /// Checks that `offer_asset_info` and `ask_asset_info` are assets of the
+    /// pool, and not the same asset. Implementations call this in
+    /// [`Pool::swap`] before building the swap message, so that an invalid
+    /// swap fails early with [`CwDexError::AssetNotInPool`] identifying the
+    /// offending asset, rather than with an error of the dex on execution.
+    fn validate_swap_assets(
+        &self,
+        deps: Deps,
+        offer_asset_info: &AssetInfo,
+        ask_asset_info: &AssetInfo,
+    ) -> Result<(), CwDexError> {
+        let pool_assets = self.pool_assets(deps)?;
+        for info in [offer_asset_info, ask_asset_info] {
+            if !pool_assets.contains(info) {
+                return Err(CwDexError::AssetNotInPool {
+                    asset: info.clone(),
+                });
+            }
+        }
+        if offer_asset_info == ask_asset_info {
+            return Err(StdError::generic_err(format!(
+                "Can not swap {offer_asset_info} for itself"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// The minimum amount to receive from an operation on a pool.
+///
+/// The `min_out` arguments of the [`Pool`] methods are always absolute amounts,
+/// enforced by the dex when the messages are executed. Osmosis enforces them in
+/// the chain's swap and join messages, and additionally fails when creating a
+/// join if `min_out` exceeds the simulated amount. Astroport enforces them
+/// through the minimum amounts of the liquidity manager and, for swaps, a
+/// belief price with a max spread of zero.
+#[cw_serde]
+#[derive(Copy)]
+pub enum MinOut {
+    /// Receive at least this amount
+    Absolute(Uint128),
+    /// Receive at least the simulated amount less this share of it, rounded
+    /// down. Must not be larger than one.
+    ///
+    /// The simulation runs against the state the messages are created in, so
+    /// this only covers changes made by messages executed before them in the
+    /// same transaction. Use [`MinOut::Absolute`] with an amount simulated off
+    /// chain to protect against price changes before the transaction.
+    Slippage(Decimal),
+}
+
+impl MinOut {
+    /// Returns the absolute minimum amount given the `simulated` amount of the
+    /// operation. Returns an error if the slippage is larger than one.
+    pub fn resolve(&self, simulated: Uint128) -> Result<Uint128, CwDexError> {
+        match self {
+            MinOut::Absolute(amount) => Ok(*amount),
+            MinOut::Slippage(slippage) => {
+                Ok(simulated.mul_floor(Decimal::one().checked_sub(*slippage)?))
+            }
+        }
+    }
+}
+
+impl From<Uint128> for MinOut {
+    fn from(amount: Uint128) -> Self {
+        MinOut::Absolute(amount)
+    }
+}
+
+/// Selects how a pool computes the results of its simulations, e.g.
+/// [`Pool::simulate_swap`].
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum SimulationMode {
+    /// Query the dex for the simulation. Always exact, but costs the gas of
+    /// the simulation query of the dex.
+    #[default]
+    OnChain,
+    /// Compute the simulation in the calling contract, using math ported from
+    /// the dex and the current state of the pool, e.g. its reserves and fees.
+    /// May be off by a few units due to rounding. Whether this is cheaper in
+    /// gas depends on the queries the implementation needs for the state of
+    /// the pool. Pool types without ported math fall back to
+    /// [`SimulationMode::OnChain`] or return an error, as documented by the
+    /// implementation.
+    Local,
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_dependencies_with_balance, mock_env};
+    use cosmwasm_std::{coins, Addr, BankMsg, Decimal, Event, Uint128};
+
+    use super::{MinOut, Pool};
+    use crate::error::CwDexError;
+    use crate::testing::{attribute, MockPool};
+
+    /// A pool of uosmo, a cw20 token and uatom, withdrawing one uosmo and three
+    /// uatom per LP token and returning twice the offer amount on swaps
+    fn mock_pool() -> MockPool {
+        MockPool::with_assets(vec![
+            AssetInfo::native("uosmo"),
+            AssetInfo::cw20(Addr::unchecked("token")),
+            AssetInfo::native("uatom"),
+        ])
+        .with_withdraw_rates(&[1, 0, 3])
+        .with_swap_rate(Some(Decimal::percent(200)))
+    }
+
+    #[test]
+    fn test_canonicalize_assets() {
+        let deps = mock_dependencies();
+        let pool = mock_pool();
+
+        let assets: AssetList = vec![
+            Asset::native("uatom", 10u128),
+            Asset::native("uosmo", 20u128),
+            Asset::native("uatom", 5u128),
+        ]
+        .into();
+        let canonical = pool.canonicalize_assets(deps.as_ref(), assets).unwrap();
+        assert_eq!(
+            canonical.to_vec(),
+            vec![
+                Asset::native("uosmo", 20u128),
+                Asset::native("uatom", 15u128)
+            ]
+        );
+
+        // Canonicalizing is idempotent
+        assert_eq!(
+            pool.canonicalize_assets(deps.as_ref(), canonical.clone())
+                .unwrap(),
+            canonical
+        );
+
+        // Assets not in the pool are rejected
+        let assets: AssetList = vec![Asset::native("uion", 10u128)].into();
+        assert!(matches!(
+            pool.canonicalize_assets(deps.as_ref(), assets),
+            Err(CwDexError::AssetNotInPool { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_swap_assets() {
+        let deps = mock_dependencies();
+        let pool = mock_pool();
+        let uosmo = AssetInfo::native("uosmo");
+        let uatom = AssetInfo::native("uatom");
+        let uion = AssetInfo::native("uion");
+
+        pool.validate_swap_assets(deps.as_ref(), &uosmo, &uatom)
+            .unwrap();
+        assert_eq!(
+            pool.validate_swap_assets(deps.as_ref(), &uosmo, &uion)
+                .unwrap_err(),
+            CwDexError::AssetNotInPool {
+                asset: uion.clone()
+            }
+        );
+        assert_eq!(
+            pool.validate_swap_assets(deps.as_ref(), &uion, &uatom)
+                .unwrap_err(),
+            CwDexError::AssetNotInPool { asset: uion }
+        );
+        assert!(pool
+            .validate_swap_assets(deps.as_ref(), &uosmo, &uosmo)
+            .is_err());
+    }
+
+    #[test]
+    fn test_provide_liquidity_with_max_in() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = mock_pool();
+
+        let assets: AssetList = vec![
+            Asset::native("uosmo", 60u128),
+            Asset::native("uatom", 10u128),
+            Asset::native("uosmo", 60u128),
+        ]
+        .into();
+        let max_in: AssetList = vec![Asset::native("uosmo", 100u128)].into();
+
+        // Duplicate entries are merged before comparing with the cap
+        let err = pool
+            .provide_liquidity_with_max_in(
+                deps.as_ref(),
+                &env,
+                assets.clone(),
+                Uint128::zero(),
+                Some(max_in),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::MaxInExceeded {
+                asset: Asset::native("uosmo", 120u128),
+                max_in: Uint128::new(100),
+            }
+        );
+
+        // Assets without a cap are not limited
+        let max_in: AssetList = vec![Asset::native("uosmo", 120u128)].into();
+        pool.provide_liquidity_with_max_in(
+            deps.as_ref(),
+            &env,
+            assets.clone(),
+            Uint128::zero(),
+            Some(max_in),
+        )
+        .unwrap();
+        pool.provide_liquidity_with_max_in(deps.as_ref(), &env, assets, Uint128::zero(), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_withdraw_liquidity_with_slippage() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = mock_pool();
+        let lp_token = Asset::native("lp", 333u128);
+
+        let min_out = pool
+            .simulate_withdraw_min_out(deps.as_ref(), &lp_token, Decimal::percent(1))
+            .unwrap();
+        // 333 * 0.99 = 329.67 and 999 * 0.99 = 989.01 are rounded down
+        assert_eq!(
+            min_out.to_vec(),
+            vec![
+                Asset::native("uosmo", 329u128),
+                Asset::native("uatom", 989u128)
+            ]
+        );
+
+        let res = pool
+            .withdraw_liquidity_with_slippage(
+                deps.as_ref(),
+                &env,
+                lp_token.clone(),
+                Decimal::percent(1),
+            )
+            .unwrap();
+        assert_eq!(attribute(&res, "withdraw", "min_out"), min_out.to_string());
+
+        assert!(pool
+            .simulate_withdraw_min_out(deps.as_ref(), &lp_token, Decimal::percent(101))
+            .is_err());
+    }
+
+    #[test]
+    fn test_withdraw_liquidity_to() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = mock_pool();
+        let lp_token = Asset::native("lp", 100u128);
+        let recipient = Addr::unchecked("recipient");
+
+        // Only the guaranteed amounts are sent, not the simulated 100 uosmo and
+        // 300 uatom
+        let min_out: AssetList = vec![
+            Asset::native("uosmo", 99u128),
+            Asset::native("uatom", 297u128),
+        ]
+        .into();
+        let res = pool
+            .withdraw_liquidity_to(
+                deps.as_ref(),
+                &env,
+                lp_token.clone(),
+                min_out.clone(),
+                Some(recipient.clone()),
+            )
+            .unwrap();
+        assert_eq!(attribute(&res, "withdraw", "min_out"), min_out.to_string());
+        let msgs: Vec<_> = res.messages.into_iter().map(|msg| msg.msg).collect();
+        assert_eq!(
+            msgs,
+            vec![
+                BankMsg::Send {
+                    to_address: recipient.to_string(),
+                    amount: coins(99, "uosmo"),
+                }
+                .into(),
+                BankMsg::Send {
+                    to_address: recipient.to_string(),
+                    amount: coins(297, "uatom"),
+                }
+                .into(),
+            ]
+        );
+
+        // Withdrawing to the contract itself does not send anything
+        let res = pool
+            .withdraw_liquidity_to(
+                deps.as_ref(),
+                &env,
+                lp_token,
+                min_out,
+                Some(env.contract.address.clone()),
+            )
+            .unwrap();
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn test_swap_with_min_out() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = mock_pool();
+        let swap = |min_out: MinOut| {
+            pool.swap_with_min_out(
+                deps.as_ref(),
+                &env,
+                Asset::native("uosmo", 333u128),
+                AssetInfo::native("uatom"),
+                min_out,
+            )
+            .map(|res| attribute(&res, "swap", "min_out"))
+        };
+
+        assert_eq!(swap(MinOut::Absolute(Uint128::new(10))).unwrap(), "10");
+        assert_eq!(swap(Uint128::new(10).into()).unwrap(), "10");
+        // 666 * 0.99 = 659.34 is rounded down
+        assert_eq!(swap(MinOut::Slippage(Decimal::percent(1))).unwrap(), "659");
+        assert!(swap(MinOut::Slippage(Decimal::percent(101))).is_err());
+    }
+
+    #[test]
+    fn test_slippage_defaults() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let pool = mock_pool();
+
+        // 333 * 2 * 0.99 = 659.34 is rounded down
+        let res = pool
+            .swap_with_slippage(
+                deps.as_ref(),
+                &env,
+                Asset::native("uosmo", 333u128),
+                AssetInfo::native("uatom"),
+                Decimal::percent(1),
+            )
+            .unwrap();
+        assert_eq!(attribute(&res, "swap", "min_out"), "659");
+
+        // (100 + 233) * 0.99 = 329.67 is rounded down
+        let assets: AssetList = vec![
+            Asset::native("uosmo", 100u128),
+            Asset::native("uatom", 233u128),
+        ]
+        .into();
+        let res = pool
+            .provide_liquidity_with_slippage(deps.as_ref(), &env, assets, Decimal::percent(1))
+            .unwrap();
+        assert_eq!(attribute(&res, "provide", "min_out"), "329");
+    }
+
+    #[test]
+    fn test_withdraw_all_liquidity() {
+        let env = mock_env();
+        let pool = mock_pool();
+
+        let deps = mock_dependencies();
+        let res = pool
+            .withdraw_all_liquidity(deps.as_ref(), &env, Decimal::percent(1))
+            .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            res.events[0],
+            Event::new("apollo/cw-dex/withdraw_liquidity")
+                .add_attribute("asset", "lp")
+                .add_attribute("skipped", "zero_balance")
+        );
+
+        let deps = mock_dependencies_with_balance(&coins(100, "lp"));
+        let res = pool
+            .withdraw_all_liquidity(deps.as_ref(), &env, Decimal::percent(1))
+            .unwrap();
+        assert_eq!(
+            attribute(&res, "withdraw", "min_out"),
+            AssetList::from(vec![
+                Asset::native("uosmo", 99u128),
+                Asset::native("uatom", 297u128),
+            ])
+            .to_string()
+        );
+    }
 }
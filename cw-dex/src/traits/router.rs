@@ -0,0 +1,121 @@
+//! Contains the `Router` trait for abstracting the router contract of a dex,
+//! which executes a multi-hop swap in a single message.
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use cosmwasm_std::{Deps, Env, Response, StdError, StdResult, Uint128};
+
+use crate::error::CwDexError;
+
+/// Trait to represent the router contract of a dex. Unlike swapping pool by
+/// pool with [`crate::router::SwapOperationsList`], the router resolves the
+/// pool of each hop itself and only the final return is checked against
+/// `min_out`, saving a message and a return check per hop.
+pub trait Router {
+    /// Swap `offer_asset` along `path`.
+    ///
+    /// Returns a Response containing the messages to execute the swaps.
+    ///
+    /// Arguments:
+    /// - `offer_asset`: The asset to swap. Must be the first asset of `path`.
+    /// - `path`: The assets to swap through, starting with the offered asset
+    ///   and ending with the asset to receive.
+    /// - `min_out`: The minimum amount of the last asset of `path` to receive.
+    fn swap_path(
+        &self,
+        deps: Deps,
+        env: &Env,
+        offer_asset: Asset,
+        path: &[AssetInfo],
+        min_out: Uint128,
+    ) -> Result<Response, CwDexError>;
+
+    /// Simulates swapping `offer_asset` along `path`, see
+    /// [`Router::swap_path`]. Returns the amount of the last asset of `path`
+    /// received.
+    fn simulate_swap_path(
+        &self,
+        deps: Deps,
+        offer_asset: Asset,
+        path: &[AssetInfo],
+    ) -> StdResult<Uint128>;
+
+    /// Checks that `path` starts with `offer_asset_info`, has at least one hop,
+    /// and does not swap an asset for itself. Implementations call this in
+    /// [`Router::swap_path`] before building the swap message.
+    fn validate_path(
+        &self,
+        offer_asset_info: &AssetInfo,
+        path: &[AssetInfo],
+    ) -> Result<(), CwDexError> {
+        if path.len() < 2 {
+            return Err(StdError::generic_err("Swap path must contain at least two assets").into());
+        }
+        if &path[0] != offer_asset_info {
+            return Err(StdError::generic_err(format!(
+                "Swap path starts with {} instead of the offered asset {}",
+                path[0], offer_asset_info
+            ))
+            .into());
+        }
+        if let Some(hop) = path.windows(2).find(|hop| hop[0] == hop[1]) {
+            return Err(
+                StdError::generic_err(format!("Can not swap {} for itself", hop[0])).into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo};
+    use cosmwasm_std::{Deps, Env, Response, StdResult, Uint128};
+
+    use super::Router;
+    use crate::CwDexError;
+
+    struct MockRouter;
+
+    impl Router for MockRouter {
+        fn swap_path(
+            &self,
+            _deps: Deps,
+            _env: &Env,
+            _offer_asset: Asset,
+            _path: &[AssetInfo],
+            _min_out: Uint128,
+        ) -> Result<Response, CwDexError> {
+            Ok(Response::new())
+        }
+
+        fn simulate_swap_path(
+            &self,
+            _deps: Deps,
+            offer_asset: Asset,
+            _path: &[AssetInfo],
+        ) -> StdResult<Uint128> {
+            Ok(offer_asset.amount)
+        }
+    }
+
+    #[test]
+    fn test_validate_path() {
+        let uosmo = AssetInfo::native("uosmo");
+        let uatom = AssetInfo::native("uatom");
+        let uluna = AssetInfo::native("uluna");
+
+        MockRouter
+            .validate_path(&uosmo, &[uosmo.clone(), uatom.clone(), uluna.clone()])
+            .unwrap();
+        // Too short
+        assert!(MockRouter.validate_path(&uosmo, &[uosmo.clone()]).is_err());
+        // Does not start with the offered asset
+        assert!(MockRouter
+            .validate_path(&uosmo, &[uatom.clone(), uluna.clone()])
+            .is_err());
+        // Swaps an asset for itself
+        assert!(MockRouter
+            .validate_path(&uosmo, &[uosmo.clone(), uatom.clone(), uatom])
+            .is_err());
+    }
+}
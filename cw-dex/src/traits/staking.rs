@@ -1,10 +1,14 @@
 //! Contains traits related to various forms of staking
 
-use apollo_cw_asset::AssetList;
-use cosmwasm_std::{Addr, Deps, Env, QuerierWrapper, Response, Uint128};
-use cw_utils::Duration as CwDuration;
+use std::fmt;
+
+use apollo_cw_asset::{AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Deps, Env, Event, QuerierWrapper, Response, StdError, Uint128};
+use cw_utils::{Duration as CwDuration, Expiration};
 
 use crate::error::CwDexError;
+use crate::traits::Pool;
 
 /// Defines an interface for claiming and querying rewards accrued from staking
 pub trait Rewards {
@@ -25,6 +29,62 @@ pub trait Rewards {
         querier: &QuerierWrapper,
         user: &Addr,
     ) -> Result<AssetList, CwDexError>;
+
+    /// Claims the pending rewards if any of the given `assets` are pending,
+    /// e.g. to compound some reward tokens and skip claims that would not
+    /// pay any of them.
+    ///
+    /// Nothing is claimed if none of `assets` are pending. Otherwise the
+    /// default implementation calls [`Rewards::claim_rewards`], which claims
+    /// all pending rewards, as the supported staking contracts, e.g. the
+    /// Astroport incentives contract, cannot claim a subset of them. The
+    /// pending rewards not in `assets` are then sent on to `return_to` after
+    /// the claim, so that they do not end up in the contract's balance. If
+    /// `return_to` is `None` and other rewards are pending, nothing is claimed
+    /// and [`CwDexError::UnselectedRewards`] is returned. Implementations
+    /// should override this if the staking contract supports claiming
+    /// selected rewards.
+    ///
+    /// The returned Response contains an event with the selected pending
+    /// rewards in attribute `rewards`, and the rewards sent to `return_to` in
+    /// attributes `returned` and `return_to` if there are any.
+    fn claim_rewards_filtered(
+        &self,
+        deps: Deps,
+        env: &Env,
+        assets: &[AssetInfo],
+        return_to: Option<&Addr>,
+    ) -> Result<Response, CwDexError> {
+        let pending_rewards = self.query_pending_rewards(&deps.querier, &env.contract.address)?;
+        let (selected, unselected): (Vec<_>, Vec<_>) = pending_rewards
+            .into_iter()
+            .cloned()
+            .partition(|asset| assets.contains(&asset.info));
+        let selected = AssetList::from(selected);
+        let unselected = AssetList::from(unselected);
+
+        let event = Event::new("apollo/cw-dex/claim_rewards_filtered");
+        if selected.len() == 0 {
+            return Ok(Response::new().add_event(event.add_attribute("skipped", "no_rewards")));
+        }
+
+        let mut event = event.add_attribute("rewards", selected.to_string());
+        let mut return_msgs = vec![];
+        // Empty attribute values are not allowed, so an empty list is left out
+        if unselected.len() > 0 {
+            let return_to = return_to.ok_or_else(|| CwDexError::UnselectedRewards {
+                unselected: unselected.clone(),
+            })?;
+            return_msgs = unselected.transfer_msgs(return_to)?;
+            event = event
+                .add_attribute("returned", unselected.to_string())
+                .add_attribute("return_to", return_to.to_string());
+        }
+        Ok(self
+            .claim_rewards(deps, env)?
+            .add_messages(return_msgs)
+            .add_event(event))
+    }
 }
 
 /// Trait to abstract interaction with a staking contract or module with an
@@ -40,6 +100,58 @@ pub trait Stake: Rewards {
     /// implementation requires a CW20 allowance, the `Response` should
     /// contain messages to increase the allowance.
     fn stake(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError>;
+
+    /// Returns the asset staked by [`Stake::stake`], or `None` if the
+    /// implementation does not know it, in which case [`Stake::stake_all`] is
+    /// not supported.
+    ///
+    /// The default implementation returns `None`.
+    fn stake_asset(&self) -> Option<AssetInfo> {
+        None
+    }
+
+    /// Stakes the whole balance of [`Stake::stake_asset`] held by the
+    /// contract, as queried when this is called.
+    ///
+    /// Use this rather than staking an amount computed earlier, which may be
+    /// stale if the balance changed in the meantime, e.g. because of refunds
+    /// of a liquidity provision. As the balance is queried when building the
+    /// response, call this after the operations changing the balance have
+    /// executed, e.g. when handling their reply or in a callback message.
+    ///
+    /// If the balance is zero, nothing is staked and the response has an
+    /// `apollo/cw-dex/stake` event with the attribute `skipped` set to
+    /// `zero_balance`.
+    fn stake_all(&self, deps: Deps, env: &Env) -> Result<Response, CwDexError> {
+        let asset = self.stake_asset().ok_or_else(|| {
+            StdError::generic_err("stake_all is not supported: the staked asset is unknown")
+        })?;
+        let balance = asset.query_balance(&deps.querier, &env.contract.address)?;
+        if balance.is_zero() {
+            let event = Event::new("apollo/cw-dex/stake")
+                .add_attribute("asset", asset.to_string())
+                .add_attribute("skipped", "zero_balance");
+            return Ok(Response::new().add_event(event));
+        }
+        self.stake(deps, env, balance)
+    }
+}
+
+/// Checks that `staking` stakes the LP token of `pool`, so that a pool and a
+/// staking implementation of different LP tokens are not used together, e.g.
+/// after a config change updated only one of them.
+///
+/// Returns [`CwDexError::LpTokenMismatch`] if [`Stake::stake_asset`] is not the
+/// LP token of `pool`. Staking implementations that do not know their staked
+/// asset can not be checked and are assumed compatible.
+pub fn assert_compatible(pool: &dyn Pool, staking: &dyn Stake) -> Result<(), CwDexError> {
+    match staking.stake_asset() {
+        Some(staked_asset) if staked_asset != pool.lp_token() => Err(CwDexError::LpTokenMismatch {
+            lp_token: pool.lp_token(),
+            staked_asset,
+        }),
+        _ => Ok(()),
+    }
 }
 
 /// Defines an interface for unstaking
@@ -56,6 +168,157 @@ pub trait Unstake {
 /// A compound trait containing `Stake`, `Unstake` and `Rewards`
 pub trait Staking: Stake + Unstake + Rewards {}
 
+/// Defines an interface for moving a staked position to another staking target
+/// of the same implementation, e.g. to a new incentives contract or to a longer
+/// lockup duration, without the staked assets going through an unlocking
+/// period. Used to migrate vaults when the dex migrates its staking contracts.
+pub trait MigratePosition: Stake {
+    /// Moves `amount` of the position staked by the contract in `self` to
+    /// `to`.
+    ///
+    /// Returns a Response containing the messages in the order they must be
+    /// executed, and an `apollo/cw-dex/migrate_position` event with the `from`
+    /// and `to` targets. Returns an error if the position can not be moved to
+    /// `to`, e.g. because it stakes another asset.
+    fn migrate_position(
+        &self,
+        deps: Deps,
+        env: &Env,
+        amount: Uint128,
+        to: &Self,
+    ) -> Result<Response, CwDexError>;
+}
+
+/// The expected staking position of a user after a staking operation, see
+/// [`SimulateStaking`].
+#[cw_serde]
+pub struct StakingPosition {
+    /// The amount staked by the user after the operation
+    pub staked: Uint128,
+    /// The rewards paid out to the user as part of the operation
+    pub rewards: AssetList,
+}
+
+/// Defines an interface for simulating the effect of staking operations on a
+/// user's position, so that balance changes can be computed up front and
+/// asserted when handling replies.
+pub trait SimulateStaking: Stake {
+    /// Returns the amount currently staked by `user`.
+    fn query_staked_amount(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<Uint128, CwDexError>;
+
+    /// Returns true if staking and unstaking pay out the pending rewards of
+    /// the user, as is the case for e.g. the Astroport incentives contract.
+    fn pays_rewards_on_stake_change(&self) -> bool;
+
+    /// Returns the position of `user` after staking `amount`.
+    fn simulate_stake(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+        amount: Uint128,
+    ) -> Result<StakingPosition, CwDexError> {
+        Ok(StakingPosition {
+            staked: self
+                .query_staked_amount(querier, user)?
+                .checked_add(amount)?,
+            rewards: self.simulate_stake_change_rewards(querier, user)?,
+        })
+    }
+
+    /// Returns the position of `user` after unstaking, or starting to unlock,
+    /// `amount`. Returns an error if `amount` exceeds the staked amount.
+    fn simulate_unstake(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+        amount: Uint128,
+    ) -> Result<StakingPosition, CwDexError> {
+        Ok(StakingPosition {
+            staked: self
+                .query_staked_amount(querier, user)?
+                .checked_sub(amount)?,
+            rewards: self.simulate_stake_change_rewards(querier, user)?,
+        })
+    }
+
+    /// Returns the rewards paid out to `user` when staking or unstaking.
+    fn simulate_stake_change_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+    ) -> Result<AssetList, CwDexError> {
+        if self.pays_rewards_on_stake_change() {
+            self.query_pending_rewards(querier, user)
+        } else {
+            Ok(AssetList::new())
+        }
+    }
+}
+
+/// Assets that are unlocking and can be withdrawn once `expiration` has
+/// passed, see [`QueryPosition`].
+#[cw_serde]
+pub struct UnlockingPosition {
+    /// The ID of the unlocking position, if the implementation assigns one
+    pub id: Option<u64>,
+    /// The amount unlocking
+    pub amount: Uint128,
+    /// When the amount becomes fully unlocked
+    pub expiration: Expiration,
+}
+
+/// The current staking position of a user, see
+/// [`QueryPosition::query_position`].
+#[cw_serde]
+pub struct StakingPositionInfo {
+    /// The amount staked, not including the amounts unlocking
+    pub staked: Uint128,
+    /// The amounts that are unlocking
+    pub unlocking: Vec<UnlockingPosition>,
+    /// The rewards that can be claimed with [`Rewards::claim_rewards`]
+    pub pending_rewards: AssetList,
+    /// The lockup duration of the staked amount, or `None` if it can be
+    /// unstaked instantly
+    pub lockup_duration: Option<CwDuration>,
+}
+
+/// Defines an interface for querying the whole staking position of the
+/// contract in one call, so that e.g. the position queries of vaults can be
+/// built once on top of all staking implementations.
+pub trait QueryPosition: SimulateStaking {
+    /// Returns the amounts of `user` that are unlocking. The default
+    /// implementation returns none, for staking that is unstaked instantly.
+    fn query_unlocking(
+        &self,
+        _querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<Vec<UnlockingPosition>, CwDexError> {
+        Ok(vec![])
+    }
+
+    /// Returns the lockup duration of the staked amount, or `None` if it can
+    /// be unstaked instantly, which is the default.
+    fn query_lockup_duration(&self, _deps: Deps) -> Result<Option<CwDuration>, CwDexError> {
+        Ok(None)
+    }
+
+    /// Returns the staked and unlocking amounts, pending rewards and lockup
+    /// duration of the position of the contract.
+    fn query_position(&self, deps: Deps, env: &Env) -> Result<StakingPositionInfo, CwDexError> {
+        let user = &env.contract.address;
+        Ok(StakingPositionInfo {
+            staked: self.query_staked_amount(&deps.querier, user)?,
+            unlocking: self.query_unlocking(&deps.querier, user)?,
+            pending_rewards: self.query_pending_rewards(&deps.querier, user)?,
+            lockup_duration: self.query_lockup_duration(deps)?,
+        })
+    }
+}
+
 /// Defines an interface for unlocking assets
 pub trait Unlock {
     /// Start unlocking `amount` of the locked asset. Depending on the
@@ -76,8 +339,17 @@ pub trait Unlock {
 /// Defines an interface for interacting with a staking module with a lockup
 /// period
 pub trait LockedStaking: Stake + Unlock + Rewards {
-    /// Returns the lockup duration for the staked assets.
+    /// Returns the lockup duration for the staked assets. Depending on the
+    /// implementation this is either a [`CwDuration::Time`] in seconds or a
+    /// [`CwDuration::Height`] in blocks.
     fn get_lockup_duration(&self, deps: Deps) -> Result<CwDuration, CwDexError>;
+
+    /// Returns when assets that start unlocking in the current block become
+    /// fully unlocked, as a height or time [`Expiration`] depending on the
+    /// kind of the lockup duration.
+    fn get_unlock_expiration(&self, deps: Deps, env: &Env) -> Result<Expiration, CwDexError> {
+        Ok(self.get_lockup_duration(deps)?.after(&env.block))
+    }
 }
 
 /// Defines an interface for forced unlocking of locked assets
@@ -88,7 +360,8 @@ pub trait ForceUnlock: LockedStaking {
     ///
     /// Arguments:
     /// `lockup_id`: The ID of the lockup position to force unlock.
-    /// `assets`: The assets to unlock. If empty, all assets are unlocked.
+    /// `amount`: The amount to unlock. May be less than the locked amount,
+    /// see [`ForceUnlock::can_force_unlock`] for the restrictions.
     fn force_unlock(
         &self,
         deps: Deps,
@@ -96,4 +369,251 @@ pub trait ForceUnlock: LockedStaking {
         lockup_id: Option<u64>,
         amount: Uint128,
     ) -> Result<Response, CwDexError>;
+
+    /// Checks whether `amount` can be force unlocked from the lockup position
+    /// with ID `lockup_id`, taking the same arguments as
+    /// [`ForceUnlock::force_unlock`]. Returns
+    /// [`CwDexError::ForceUnlockNotAllowed`] with the reason if not.
+    ///
+    /// The default implementation places no restrictions on force unlocking.
+    fn can_force_unlock(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        _lockup_id: Option<u64>,
+        _amount: Uint128,
+    ) -> Result<(), CwDexError> {
+        Ok(())
+    }
+}
+
+/// The reason a lockup position can not be force unlocked, see
+/// [`ForceUnlock::can_force_unlock`].
+#[cw_serde]
+pub enum ForceUnlockRestriction {
+    /// The contract is not on the list of addresses allowed to force unlock
+    /// by the lockup module
+    NotWhitelisted,
+    /// The lockup position does not exist
+    NotFound,
+    /// The lockup position is owned by another address
+    NotOwner {
+        /// The owner of the lockup position
+        owner: String,
+    },
+    /// The locked assets are delegated via superfluid staking and must be
+    /// undelegated first
+    SuperfluidDelegated {
+        /// The validator the assets are delegated to
+        validator: String,
+    },
+    /// The superfluid delegation of the locked assets is being undelegated,
+    /// and the lockup position can only be force unlocked once it completes
+    SuperfluidUndelegating {
+        /// The end of the undelegation, in seconds since the unix epoch
+        until: u64,
+    },
+    /// The lockup position is already unlocking, so only all of it can be
+    /// force unlocked
+    PartiallyUnlocking,
+    /// Less than the requested amount is locked
+    InsufficientLocked {
+        /// The amount locked
+        locked: Uint128,
+        /// The amount requested to be unlocked
+        requested: Uint128,
+    },
+}
+
+impl fmt::Display for ForceUnlockRestriction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotWhitelisted => write!(f, "contract is not allowed to force unlock"),
+            Self::NotFound => write!(f, "lock not found"),
+            Self::NotOwner { owner } => write!(f, "lock is owned by {}", owner),
+            Self::SuperfluidDelegated { validator } => {
+                write!(f, "lock is superfluid delegated to {}", validator)
+            }
+            Self::SuperfluidUndelegating { until } => {
+                write!(f, "lock is superfluid undelegating until {}", until)
+            }
+            Self::PartiallyUnlocking => {
+                write!(
+                    f,
+                    "lock is unlocking and can only be force unlocked in full"
+                )
+            }
+            Self::InsufficientLocked { locked, requested } => {
+                write!(f, "requested {} but only {} is locked", requested, locked)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+    use cosmwasm_std::testing::{mock_dependencies, mock_dependencies_with_balance, mock_env};
+    use cosmwasm_std::{attr, coins, Addr, BankMsg, Coin, CosmosMsg, Event, Uint128};
+
+    use super::{QueryPosition, Rewards, SimulateStaking, Stake, StakingPosition};
+    use crate::error::CwDexError;
+    use crate::testing::{attribute, MockStaking};
+
+    /// Staking with 100 staked and 5 uastro and 2 untrn pending rewards
+    fn mock_staking(pays_rewards: bool) -> MockStaking {
+        MockStaking::new("ulp")
+            .with_pending(vec![
+                Asset::native("uastro", 5u128),
+                Asset::native("untrn", 2u128),
+            ])
+            .with_pays_rewards(pays_rewards)
+    }
+
+    #[test]
+    fn test_simulate_staking() {
+        let deps = mock_dependencies();
+        let querier = deps.as_ref().querier;
+        let user = Addr::unchecked("user");
+
+        let staking = mock_staking(true);
+        assert_eq!(
+            staking
+                .simulate_stake(&querier, &user, Uint128::new(50))
+                .unwrap(),
+            StakingPosition {
+                staked: Uint128::new(150),
+                rewards: vec![
+                    Asset::native("uastro", 5u128),
+                    Asset::native("untrn", 2u128),
+                ]
+                .into(),
+            }
+        );
+        assert_eq!(
+            staking
+                .simulate_unstake(&querier, &user, Uint128::new(100))
+                .unwrap()
+                .staked,
+            Uint128::zero()
+        );
+        assert!(staking
+            .simulate_unstake(&querier, &user, Uint128::new(101))
+            .is_err());
+
+        let staking = mock_staking(false);
+        assert_eq!(
+            staking
+                .simulate_stake(&querier, &user, Uint128::new(50))
+                .unwrap()
+                .rewards,
+            AssetList::new()
+        );
+    }
+
+    #[test]
+    fn test_stake_all() {
+        let env = mock_env();
+        let staking = mock_staking(true);
+
+        let deps = mock_dependencies_with_balance(&[Coin::new(42, "ulp")]);
+        let res = staking.stake_all(deps.as_ref(), &env).unwrap();
+        assert_eq!(attribute(&res, "stake", "amount"), "42");
+
+        let deps = mock_dependencies();
+        let res = staking.stake_all(deps.as_ref(), &env).unwrap();
+        assert!(res.attributes.is_empty());
+        assert_eq!(
+            res.events,
+            vec![Event::new("apollo/cw-dex/stake")
+                .add_attribute("asset", "ulp")
+                .add_attribute("skipped", "zero_balance")]
+        );
+    }
+
+    #[test]
+    fn test_query_position() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = mock_staking(true);
+
+        let position = staking.query_position(deps.as_ref(), &env).unwrap();
+        assert_eq!(position.staked, Uint128::new(100));
+        assert_eq!(
+            position.pending_rewards,
+            vec![
+                Asset::native("uastro", 5u128),
+                Asset::native("untrn", 2u128)
+            ]
+            .into()
+        );
+        assert!(position.unlocking.is_empty());
+        assert_eq!(position.lockup_duration, None);
+    }
+
+    #[test]
+    fn test_claim_rewards_filtered() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let staking = mock_staking(true);
+
+        // The unselected rewards are claimed along with the selected ones and
+        // sent on to `return_to`
+        let treasury = Addr::unchecked("treasury");
+        let res = staking
+            .claim_rewards_filtered(
+                deps.as_ref(),
+                &env,
+                &[AssetInfo::native("uastro")],
+                Some(&treasury),
+            )
+            .unwrap();
+        assert_eq!(res.attributes[0].value, "claim_rewards");
+        assert_eq!(
+            res.messages.last().unwrap().msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: coins(2, "untrn"),
+            })
+        );
+        assert_eq!(
+            res.events[0].attributes,
+            vec![
+                attr("rewards", "uastro:5"),
+                attr("returned", "untrn:2"),
+                attr("return_to", "treasury"),
+            ]
+        );
+
+        // Without `return_to` nothing is claimed if other rewards are pending
+        let err = mock_staking(true)
+            .claim_rewards_filtered(deps.as_ref(), &env, &[AssetInfo::native("uastro")], None)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CwDexError::UnselectedRewards {
+                unselected: vec![Asset::native("untrn", 2u128)].into(),
+            }
+        );
+
+        let res = mock_staking(true)
+            .claim_rewards_filtered(
+                deps.as_ref(),
+                &env,
+                &[AssetInfo::native("uastro"), AssetInfo::native("untrn")],
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            res.events[0].attributes,
+            vec![attr("rewards", "uastro:5,untrn:2")]
+        );
+
+        // Nothing is claimed without pending rewards of the selected assets
+        let res = mock_staking(true)
+            .claim_rewards_filtered(deps.as_ref(), &env, &[AssetInfo::native("uosmo")], None)
+            .unwrap();
+        assert!(res.attributes.is_empty());
+        assert_eq!(res.events[0].attributes[0].key, "skipped");
+    }
 }
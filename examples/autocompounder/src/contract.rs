@@ -0,0 +1,348 @@
+use apollo_utils::responses::merge_responses;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    StdError, StdResult, Uint128,
+};
+use cw_dex::builder::{ProvideLiquidity, WithdrawLiquidity};
+use cw_dex::composite::{
+    claim_rewards_if_any, swap_and_stake, swap_and_stake_reply, SWAP_AND_STAKE_REPLY_ID,
+};
+use cw_dex::dust::{DustConfig, DustPolicy};
+use cw_dex::prelude::*;
+use cw_dex::received::ReceivedAsset;
+
+use crate::error::ContractError;
+use crate::msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse};
+use crate::state::{Config, CONFIG, SHARES, TOTAL_SHARES};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let pool = msg.pool.build(deps.as_ref())?;
+    let staking = msg.staking.build(deps.as_ref())?;
+    if staking.stake_asset() != Some(pool.lp_token()) {
+        return Err(ContractError::StakingMismatch {});
+    }
+    if staking.query_lockup_duration(deps.as_ref())?.is_some() {
+        return Err(ContractError::LockedStaking {});
+    }
+
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            pool,
+            staking,
+            max_slippage: msg.max_slippage,
+        },
+    )?;
+    TOTAL_SHARES.save(deps.storage, &Uint128::zero())?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Deposit { recipient } => {
+            let recipient = validate_recipient(deps.as_ref(), recipient, &info.sender)?;
+            execute_deposit(deps, env, ReceivedAsset::from_funds(&info), recipient)
+        }
+        ExecuteMsg::Withdraw { shares, recipient } => {
+            let recipient = validate_recipient(deps.as_ref(), recipient, &info.sender)?;
+            execute_withdraw(deps, env, info.sender, shares, recipient)
+        }
+        ExecuteMsg::Compound {} => execute_compound(deps, env),
+        ExecuteMsg::Callback(msg) => {
+            if info.sender != env.contract.address {
+                return Err(ContractError::Unauthorized {});
+            }
+            match msg {
+                CallbackMsg::ProvideAndStake {} => execute_provide_and_stake(deps, env),
+            }
+        }
+    }
+}
+
+fn validate_recipient(deps: Deps, recipient: Option<String>, sender: &Addr) -> StdResult<Addr> {
+    recipient.map_or_else(|| Ok(sender.clone()), |addr| deps.api.addr_validate(&addr))
+}
+
+/// Returns the LP tokens held and staked by the vault.
+///
+/// Deposits only stake the minimum amount of LP tokens to receive, so the vault
+/// may hold the rest until they are staked by the next compound.
+fn query_lp_tokens(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let held = config
+        .pool
+        .lp_token()
+        .query_balance(&deps.querier, &env.contract.address)?;
+    let staked = config
+        .staking
+        .query_staked_amount(&deps.querier, &env.contract.address)?;
+    Ok((held, staked))
+}
+
+/// Returns the LP tokens held and staked by the vault and the total shares.
+fn query_totals(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let (held, staked) = query_lp_tokens(deps, env, config)?;
+    Ok((held + staked, TOTAL_SHARES.load(deps.storage)?))
+}
+
+pub fn execute_deposit(
+    deps: DepsMut,
+    env: Env,
+    received: ReceivedAsset,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let assets = received.assets();
+    if assets.iter().all(|asset| asset.amount.is_zero()) {
+        return Err(ContractError::NoFunds {});
+    }
+
+    // Shares are minted for the simulated LP tokens. Only the minimum amount
+    // is staked by `ProvideLiquidity::auto_stake`, the rest is held by the
+    // vault until the next compound.
+    let lp_out = config
+        .pool
+        .simulate_provide_liquidity(deps.as_ref(), &env, assets.clone())?
+        .amount;
+    let (lp_tokens, total_shares) = query_totals(deps.as_ref(), &env, &config)?;
+    let shares = if total_shares.is_zero() || lp_tokens.is_zero() {
+        lp_out
+    } else {
+        lp_out.multiply_ratio(total_shares, lp_tokens)
+    };
+    if shares.is_zero() {
+        return Err(ContractError::ZeroShares {});
+    }
+    TOTAL_SHARES.save(deps.storage, &(total_shares + shares))?;
+    SHARES.update(deps.storage, &recipient, |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default() + shares)
+    })?;
+
+    let res = ProvideLiquidity::builder(&config.pool)
+        .assets(assets)
+        .min_out(MinOut::Slippage(config.max_slippage))
+        .auto_stake(&config.staking)
+        .build(deps.as_ref(), &env)?;
+
+    Ok(res
+        .add_attribute("action", "deposit")
+        .add_attribute("recipient", recipient)
+        .add_attribute("shares", shares))
+}
+
+pub fn execute_withdraw(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    shares: Uint128,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let balance = SHARES.may_load(deps.storage, &sender)?.unwrap_or_default();
+    if shares.is_zero() || shares > balance {
+        return Err(ContractError::InsufficientShares { shares, balance });
+    }
+
+    let (held, staked) = query_lp_tokens(deps.as_ref(), &env, &config)?;
+    let total_shares = TOTAL_SHARES.load(deps.storage)?;
+    let lp_amount = shares.multiply_ratio(held + staked, total_shares);
+    SHARES.save(deps.storage, &sender, &(balance - shares))?;
+    TOTAL_SHARES.save(deps.storage, &(total_shares - shares))?;
+
+    // Held LP tokens are withdrawn first, the rest is unstaked. The unstaked
+    // LP tokens are received before the withdrawal is executed, as the
+    // staking has no lockup duration.
+    let mut responses = vec![];
+    let unstake_amount = lp_amount.saturating_sub(held);
+    if !unstake_amount.is_zero() {
+        responses.push(
+            config
+                .staking
+                .unstake(deps.as_ref(), &env, unstake_amount)?,
+        );
+    }
+    // The withdrawal is simulated against the state it is executed on, so
+    // requiring the simulated amounts sends all of the withdrawn assets to the
+    // recipient, while failing if less is received
+    let lp_token = Asset::new(config.pool.lp_token(), lp_amount);
+    let min_out = config
+        .pool
+        .simulate_withdraw_liquidity(deps.as_ref(), &lp_token)?;
+    responses.push(
+        WithdrawLiquidity::builder(&config.pool)
+            .lp_token(lp_token)
+            .min_out(min_out)
+            .recipient(recipient.clone())
+            .build(deps.as_ref(), &env)?,
+    );
+
+    Ok(merge_responses(responses)
+        .add_attribute("action", "withdraw")
+        .add_attribute("recipient", recipient)
+        .add_attribute("shares", shares))
+}
+
+pub fn execute_compound(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    // The claimed rewards are only in the vault's balance once the claim is
+    // executed, so they are provided in a callback
+    let claim_res = claim_rewards_if_any(deps.as_ref(), &env, &config.staking)?;
+
+    Ok(claim_res
+        .add_message(CallbackMsg::ProvideAndStake {}.into_cosmos_msg(&env)?)
+        .add_attribute("action", "compound"))
+}
+
+/// Provides the pool assets held by the vault and stakes the LP tokens.
+///
+/// The held assets, e.g. claimed rewards that are assets of the pool and the
+/// remainders of earlier provides, are generally not in the ratio of the pool
+/// reserves. With two asset pools, the asset that is the largest share of its
+/// reserve is partly swapped into the other with [`swap_and_stake`], so that
+/// no value is lost to an imbalanced provide, and the LP tokens are staked
+/// when handling its reply.
+pub fn execute_provide_and_stake(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let mut held = vec![];
+    for info in config.pool.pool_assets(deps.as_ref())? {
+        let balance = info.query_balance(&deps.querier, &env.contract.address)?;
+        if !balance.is_zero() {
+            held.push(Asset::new(info, balance));
+        }
+    }
+    if held.is_empty() {
+        return Ok(Response::new()
+            .add_attribute("action", "provide_and_stake")
+            .add_attribute("skipped", "no_assets"));
+    }
+
+    let offer = if config.pool.pool_assets(deps.as_ref())?.len() == 2 {
+        let reserves = config.pool.get_pool_liquidity(deps.as_ref())?;
+        largest_reserve_share(&held, &reserves)
+    } else {
+        None
+    };
+
+    let res = match offer {
+        Some(offer) => {
+            // The whole balance of the other asset is provided along as dust
+            let dust = DustConfig {
+                policy: DustPolicy::ProvideOnNext,
+                thresholds: held.clone(),
+            };
+            // Single sided provides can not be simulated on every dex. The
+            // swap and provide of `swap_and_stake` are simulated against the
+            // state they are executed on, so requiring any LP tokens is as
+            // safe as a min out relative to a simulation.
+            swap_and_stake(
+                deps.as_ref(),
+                &env,
+                offer,
+                &config.pool,
+                &config.staking,
+                Uint128::one(),
+                &dust,
+            )?
+        }
+        None => ProvideLiquidity::builder(&config.pool)
+            .assets(held.into())
+            .min_out(MinOut::Slippage(config.max_slippage))
+            .auto_stake(&config.staking)
+            .build(deps.as_ref(), &env)?,
+    };
+
+    Ok(res.add_attribute("action", "provide_and_stake"))
+}
+
+/// Returns the held asset that is the largest share of its reserve, or `None`
+/// if any of the held assets has no reserve. An empty pool has no price to
+/// balance the provide to, so the held assets are then provided as they are.
+fn largest_reserve_share(held: &[Asset], reserves: &AssetList) -> Option<Asset> {
+    let mut offer = None;
+    let mut max_share = Decimal::zero();
+    for asset in held {
+        let reserve = reserves
+            .find(&asset.info)
+            .map(|reserve| reserve.amount)
+            .filter(|amount| !amount.is_zero())?;
+        let share = Decimal::checked_from_ratio(asset.amount, reserve).unwrap_or(Decimal::MAX);
+        if offer.is_none() || share > max_share {
+            offer = Some(asset.clone());
+            max_share = share;
+        }
+    }
+    offer
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        SWAP_AND_STAKE_REPLY_ID => {
+            let config = CONFIG.load(deps.storage)?;
+            Ok(swap_and_stake_reply(
+                deps.as_ref(),
+                &env,
+                &config.pool,
+                &config.staking,
+                &DustConfig::default(),
+            )?)
+        }
+        id => Err(StdError::generic_err(format!("Unknown reply id: {id}")).into()),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let config = CONFIG.load(deps.storage)?;
+    match msg {
+        QueryMsg::State {} => to_json_binary(&StateResponse {
+            total_shares: TOTAL_SHARES.load(deps.storage)?,
+            held: config
+                .pool
+                .lp_token()
+                .query_balance(&deps.querier, &env.contract.address)?,
+            position: config.staking.query_position(deps, &env)?,
+        }),
+        QueryMsg::Shares { address } => to_json_binary(
+            &SHARES
+                .may_load(deps.storage, &deps.api.addr_validate(&address)?)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::SimulateWithdraw { shares } => {
+            let (lp_tokens, total_shares) = query_totals(deps, &env, &config)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            if total_shares.is_zero() {
+                return to_json_binary(&AssetList::new());
+            }
+            let lp_token = Asset::new(
+                config.pool.lp_token(),
+                shares.multiply_ratio(lp_tokens, total_shares),
+            );
+            to_json_binary(&config.pool.simulate_withdraw_liquidity(deps, &lp_token)?)
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! An autocompounding vault built on the cw-dex traits. Deposits of pool
+//! assets are provided as liquidity and staked, and the staking rewards are
+//! compounded into more staked LP tokens, increasing the LP tokens each share
+//! of the vault is redeemable for.
+//!
+//! The pool and staking are configured with `cw-dex-config`, so the same
+//! contract works with every supported dex. Staking with a lockup duration is
+//! not supported, as withdrawals unstake and withdraw in the same transaction.
+
+pub mod contract;
+mod error;
+pub mod msg;
+pub mod state;
+
+pub use crate::error::ContractError;
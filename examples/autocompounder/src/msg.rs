@@ -0,0 +1,81 @@
+use apollo_cw_asset::AssetList;
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{to_json_binary, CosmosMsg, Decimal, Env, StdResult, Uint128, WasmMsg};
+use cw_dex::traits::StakingPositionInfo;
+use cw_dex_config::{PoolConfig, StakingConfig};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The pool whose LP tokens are compounded
+    pub pool: PoolConfig,
+    /// The staking of the LP tokens. Must not have a lockup duration.
+    pub staking: StakingConfig,
+    /// The maximum slippage of providing liquidity, relative to the simulated
+    /// amount. Withdrawals require the simulated amounts.
+    pub max_slippage: Decimal,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Provides the sent pool assets as liquidity, stakes the minimum amount
+    /// of LP tokens to receive and mints shares of the vault for the simulated
+    /// amount to `recipient`, the sender by default.
+    Deposit { recipient: Option<String> },
+    /// Burns `shares` of the sender, unstakes the LP tokens they are
+    /// redeemable for and withdraws them, sending the pool assets to
+    /// `recipient`, the sender by default.
+    Withdraw {
+        shares: Uint128,
+        recipient: Option<String>,
+    },
+    /// Claims the pending rewards and provides the pool assets held by the
+    /// vault as liquidity, staking the LP tokens. Can be executed by anyone.
+    Compound {},
+    /// Steps of the execute messages that depend on the results of earlier
+    /// messages. Can only be executed by the vault itself.
+    Callback(CallbackMsg),
+}
+
+#[cw_serde]
+pub enum CallbackMsg {
+    /// Provides the pool assets held by the vault as liquidity and stakes the
+    /// LP tokens, e.g. after claiming rewards that are assets of the pool.
+    ProvideAndStake {},
+}
+
+impl CallbackMsg {
+    /// Returns the message executing the callback on the vault.
+    pub fn into_cosmos_msg(self, env: &Env) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&ExecuteMsg::Callback(self))?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Returns the total shares and the staking position of the vault
+    #[returns(StateResponse)]
+    State {},
+    /// Returns the shares of `address`
+    #[returns(Uint128)]
+    Shares { address: String },
+    /// Returns the pool assets `shares` are redeemable for
+    #[returns(AssetList)]
+    SimulateWithdraw { shares: Uint128 },
+}
+
+#[cw_serde]
+pub struct StateResponse {
+    /// The total shares of the vault
+    pub total_shares: Uint128,
+    /// The LP tokens held by the vault, which are staked by the next compound
+    pub held: Uint128,
+    /// The staking position of the vault. Each share is redeemable for an
+    /// equal part of the held and staked LP tokens.
+    pub position: StakingPositionInfo,
+}
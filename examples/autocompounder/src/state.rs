@@ -0,0 +1,19 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Uint128};
+use cw_dex_config::{DexPool, DexStaking};
+use cw_storage_plus::{Item, Map};
+
+/// The configuration of the vault
+#[cw_serde]
+pub struct Config {
+    /// The pool whose LP tokens are compounded
+    pub pool: DexPool,
+    /// The staking of the LP tokens
+    pub staking: DexStaking,
+    /// The maximum slippage of providing and withdrawing liquidity
+    pub max_slippage: Decimal,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+pub const TOTAL_SHARES: Item<Uint128> = Item::new("total_shares");
+pub const SHARES: Map<&Addr, Uint128> = Map::new("shares");
@@ -0,0 +1,200 @@
+use apollo_utils::responses::merge_responses;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+};
+use cw_dex::builder::{ProvideLiquidity, WithdrawLiquidity};
+use cw_dex::composite::claim_rewards_if_any;
+use cw_dex::prelude::*;
+
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg, PositionResponse, QueryMsg};
+use crate::state::{Config, CONFIG};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let pool = msg.pool.build(deps.as_ref())?;
+    let staking = msg
+        .staking
+        .map(|staking| staking.build(deps.as_ref()))
+        .transpose()?;
+    if let Some(staking) = &staking {
+        if staking.stake_asset() != Some(pool.lp_token()) {
+            return Err(ContractError::StakingMismatch {});
+        }
+        if staking.query_lockup_duration(deps.as_ref())?.is_some() {
+            return Err(ContractError::LockedStaking {});
+        }
+    }
+
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            owner: deps.api.addr_validate(&msg.owner)?,
+            pool,
+            staking,
+            max_slippage: msg.max_slippage,
+        },
+    )?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match msg {
+        ExecuteMsg::AddLiquidity { assets } => execute_add_liquidity(deps, env, config, assets),
+        ExecuteMsg::RemoveLiquidity { amount, recipient } => {
+            let recipient = recipient
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?
+                .unwrap_or_else(|| config.owner.clone());
+            execute_remove_liquidity(deps, env, config, amount, recipient)
+        }
+        ExecuteMsg::ClaimRewards {} => {
+            let staking = config
+                .staking
+                .as_ref()
+                .ok_or(ContractError::StakingNotConfigured {})?;
+            Ok(claim_rewards_if_any(deps.as_ref(), &env, staking)?
+                .add_attribute("action", "claim_rewards"))
+        }
+        ExecuteMsg::UpdateOwner { owner } => {
+            let owner = deps.api.addr_validate(&owner)?;
+            CONFIG.save(
+                deps.storage,
+                &Config {
+                    owner: owner.clone(),
+                    ..config
+                },
+            )?;
+            Ok(Response::new()
+                .add_attribute("action", "update_owner")
+                .add_attribute("owner", owner))
+        }
+    }
+}
+
+pub fn execute_add_liquidity(
+    deps: DepsMut,
+    env: Env,
+    config: Config,
+    assets: AssetList,
+) -> Result<Response, ContractError> {
+    let mut builder = ProvideLiquidity::builder(&config.pool)
+        .assets(assets)
+        .min_out(MinOut::Slippage(config.max_slippage));
+    if let Some(staking) = &config.staking {
+        builder = builder.auto_stake(staking);
+    }
+
+    Ok(builder
+        .build(deps.as_ref(), &env)?
+        .add_attribute("action", "add_liquidity"))
+}
+
+pub fn execute_remove_liquidity(
+    deps: DepsMut,
+    env: Env,
+    config: Config,
+    amount: Option<Uint128>,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let (held, staked) = query_lp_tokens(deps.as_ref(), &env, &config)?;
+    let amount = amount.unwrap_or(held + staked);
+
+    // Held LP tokens are withdrawn first, the rest is unstaked. The unstaked
+    // LP tokens are received before the withdrawal is executed, as the
+    // staking has no lockup duration.
+    let mut responses = vec![];
+    let unstake_amount = amount.saturating_sub(held);
+    if !unstake_amount.is_zero() {
+        let staking = config
+            .staking
+            .as_ref()
+            .ok_or(ContractError::StakingNotConfigured {})?;
+        responses.push(staking.unstake(deps.as_ref(), &env, unstake_amount)?);
+    }
+    // The withdrawal is simulated against the state it is executed on, so
+    // requiring the simulated amounts sends all of the withdrawn assets to the
+    // recipient, while failing if less is received
+    let lp_token = Asset::new(config.pool.lp_token(), amount);
+    let min_out = config
+        .pool
+        .simulate_withdraw_liquidity(deps.as_ref(), &lp_token)?;
+    responses.push(
+        WithdrawLiquidity::builder(&config.pool)
+            .lp_token(lp_token)
+            .min_out(min_out)
+            .recipient(recipient.clone())
+            .build(deps.as_ref(), &env)?,
+    );
+
+    Ok(merge_responses(responses)
+        .add_attribute("action", "remove_liquidity")
+        .add_attribute("recipient", recipient))
+}
+
+/// Returns the LP tokens held and staked by the manager.
+fn query_lp_tokens(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+) -> Result<(Uint128, Uint128), CwDexError> {
+    let held = config
+        .pool
+        .lp_token()
+        .query_balance(&deps.querier, &env.contract.address)?;
+    let staked = match &config.staking {
+        Some(staking) => staking.query_staked_amount(&deps.querier, &env.contract.address)?,
+        None => Uint128::zero(),
+    };
+    Ok((held, staked))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let config = CONFIG.load(deps.storage)?;
+    match msg {
+        QueryMsg::Config {} => to_json_binary(&config),
+        QueryMsg::Position {} => {
+            let (held, staked) = query_lp_tokens(deps, &env, &config)?;
+            let underlying = if (held + staked).is_zero() {
+                AssetList::new()
+            } else {
+                config.pool.simulate_withdraw_liquidity(
+                    deps,
+                    &Asset::new(config.pool.lp_token(), held + staked),
+                )?
+            };
+            let pending_rewards = match &config.staking {
+                Some(staking) => {
+                    staking.query_pending_rewards(&deps.querier, &env.contract.address)?
+                }
+                None => AssetList::new(),
+            };
+            to_json_binary(&PositionResponse {
+                held,
+                staked,
+                underlying,
+                pending_rewards,
+            })
+        }
+    }
+}
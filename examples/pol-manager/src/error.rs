@@ -0,0 +1,24 @@
+use cosmwasm_std::StdError;
+use cw_dex::CwDexError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    CwDex(#[from] CwDexError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("The staking does not stake the LP token of the pool")]
+    StakingMismatch {},
+
+    #[error("Staking with a lockup duration is not supported")]
+    LockedStaking {},
+
+    #[error("No staking configured")]
+    StakingNotConfigured {},
+}
@@ -0,0 +1,15 @@
+//! A manager of protocol owned liquidity built on the cw-dex traits. The owner
+//! adds the assets of a protocol as liquidity to a pool, optionally staking
+//! the LP tokens, claims the staking rewards and removes the liquidity again.
+//!
+//! The pool and staking are configured with `cw-dex-config`, so the same
+//! contract manages liquidity on every supported dex. Staking with a lockup
+//! duration is not supported, as removing liquidity unstakes and withdraws in
+//! the same transaction.
+
+pub mod contract;
+mod error;
+pub mod msg;
+pub mod state;
+
+pub use crate::error::ContractError;
@@ -0,0 +1,62 @@
+use apollo_cw_asset::AssetList;
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Decimal, Uint128};
+use cw_dex_config::{PoolConfig, StakingConfig};
+
+use crate::state::Config;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The address allowed to manage the liquidity
+    pub owner: String,
+    /// The pool to provide liquidity to
+    pub pool: PoolConfig,
+    /// The staking of the LP tokens. Must not have a lockup duration. If
+    /// `None`, the LP tokens are held by the manager.
+    pub staking: Option<StakingConfig>,
+    /// The maximum slippage of providing liquidity, relative to the simulated
+    /// amount. Withdrawals require the simulated amounts.
+    pub max_slippage: Decimal,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Provides `assets` as liquidity and stakes the LP tokens if staking is
+    /// configured. The assets must be sent along or already be held by the
+    /// manager.
+    AddLiquidity { assets: AssetList },
+    /// Withdraws `amount` LP tokens, all of them by default, unstaking them if
+    /// needed, and sends the withdrawn assets to `recipient`, the owner by
+    /// default.
+    RemoveLiquidity {
+        amount: Option<Uint128>,
+        recipient: Option<String>,
+    },
+    /// Claims the pending rewards of the staking to the manager
+    ClaimRewards {},
+    /// Transfers the ownership of the manager to `owner`
+    UpdateOwner { owner: String },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Returns the configuration of the manager
+    #[returns(Config)]
+    Config {},
+    /// Returns the liquidity position of the manager
+    #[returns(PositionResponse)]
+    Position {},
+}
+
+#[cw_serde]
+pub struct PositionResponse {
+    /// The LP tokens held by the manager
+    pub held: Uint128,
+    /// The LP tokens staked by the manager
+    pub staked: Uint128,
+    /// The pool assets the held and staked LP tokens are redeemable for
+    pub underlying: AssetList,
+    /// The rewards that can be claimed with [`ExecuteMsg::ClaimRewards`]
+    pub pending_rewards: AssetList,
+}
@@ -0,0 +1,19 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal};
+use cw_dex_config::{DexPool, DexStaking};
+use cw_storage_plus::Item;
+
+/// The configuration of the manager
+#[cw_serde]
+pub struct Config {
+    /// The address allowed to manage the liquidity
+    pub owner: Addr,
+    /// The pool liquidity is provided to
+    pub pool: DexPool,
+    /// The staking of the LP tokens, if they are staked
+    pub staking: Option<DexStaking>,
+    /// The maximum slippage of providing and withdrawing liquidity
+    pub max_slippage: Decimal,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
@@ -0,0 +1,303 @@
+use apollo_utils::responses::merge_responses;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Order,
+    Reply, Response, StdError, StdResult, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
+use cw_dex::composite::{simulate_zap_out, swap_and_provide};
+use cw_dex::dust::DustConfig;
+use cw_dex::prelude::*;
+use cw_dex::received::ReceivedAsset;
+use cw_dex_config::DexPool;
+
+use crate::error::ContractError;
+use crate::msg::{CallbackMsg, ExecuteMsg, InstantiateMsg, PoolResponse, QueryMsg, ReceiveMsg};
+use crate::state::{PendingZapIn, PENDING_ZAP_IN, POOLS};
+
+/// Reply ID of the provide liquidity message of a zap in
+pub const ZAP_IN_REPLY_ID: u64 = 1;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    for config in msg.pools {
+        let pool = config.build(deps.as_ref())?;
+        POOLS.save(deps.storage, pool.lp_token().to_string(), &pool)?;
+    }
+
+    Ok(Response::default())
+}
+
+fn load_pool(deps: Deps, lp_token: &AssetInfo) -> Result<DexPool, ContractError> {
+    POOLS
+        .may_load(deps.storage, lp_token.to_string())?
+        .ok_or_else(|| ContractError::UnknownPool {
+            lp_token: lp_token.clone(),
+        })
+}
+
+fn validate_recipient(deps: Deps, recipient: Option<String>, sender: &Addr) -> StdResult<Addr> {
+    recipient.map_or_else(|| Ok(sender.clone()), |addr| deps.api.addr_validate(&addr))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::ZapIn {
+            lp_token,
+            min_lp_out,
+            recipient,
+        } => {
+            let offer = cw_utils::one_coin(&info)?;
+            let recipient = validate_recipient(deps.as_ref(), recipient, &info.sender)?;
+            execute_zap_in(deps, env, offer.into(), lp_token, min_lp_out, recipient)
+        }
+        ExecuteMsg::ZapOut {
+            ask,
+            min_out,
+            recipient,
+        } => {
+            let received = ReceivedAsset::from_funds(&info);
+            let recipient = validate_recipient(deps.as_ref(), recipient, received.sender())?;
+            execute_zap_out(deps, env, received, ask, min_out, recipient)
+        }
+        ExecuteMsg::Receive(receive_msg) => execute_receive(deps, env, info, receive_msg),
+        ExecuteMsg::Callback(msg) => {
+            if info.sender != env.contract.address {
+                return Err(ContractError::Unauthorized {});
+            }
+            match msg {
+                CallbackMsg::SwapWithdrawn {
+                    lp_token,
+                    ask,
+                    balances_before,
+                } => execute_swap_withdrawn(deps, env, lp_token, ask, balances_before),
+                CallbackMsg::SendReceived {
+                    ask,
+                    balance_before,
+                    min_out,
+                    recipient,
+                } => execute_send_received(deps, env, ask, balance_before, min_out, recipient),
+            }
+        }
+    }
+}
+
+fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let received = ReceivedAsset::from_cw20_receive(deps.as_ref(), &info, &receive_msg)?;
+    match from_json(&receive_msg.msg)? {
+        ReceiveMsg::ZapOut {
+            ask,
+            min_out,
+            recipient,
+        } => {
+            let recipient = validate_recipient(deps.as_ref(), recipient, received.sender())?;
+            execute_zap_out(deps, env, received, ask, min_out, recipient)
+        }
+    }
+}
+
+pub fn execute_zap_in(
+    deps: DepsMut,
+    env: Env,
+    offer: Asset,
+    lp_token: AssetInfo,
+    min_lp_out: Uint128,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let pool = load_pool(deps.as_ref(), &lp_token)?;
+    PENDING_ZAP_IN.save(
+        deps.storage,
+        &PendingZapIn {
+            lp_token,
+            recipient: recipient.clone(),
+        },
+    )?;
+
+    // The LP tokens are sent to the recipient when handling the reply of the
+    // provide liquidity message, as the received amount is only known then
+    let res = swap_and_provide(
+        deps.as_ref(),
+        &env,
+        offer,
+        &pool,
+        min_lp_out,
+        &DustConfig::default(),
+        ZAP_IN_REPLY_ID,
+    )?;
+
+    Ok(res
+        .add_attribute("action", "zap_in")
+        .add_attribute("recipient", recipient))
+}
+
+pub fn execute_zap_out(
+    deps: DepsMut,
+    env: Env,
+    received: ReceivedAsset,
+    ask: AssetInfo,
+    min_out: Uint128,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let lp_token = match received.assets().to_vec().as_slice() {
+        [asset] => asset.clone(),
+        _ => return Err(ContractError::InvalidFunds {}),
+    };
+    let pool = load_pool(deps.as_ref(), &lp_token.info)?;
+    if !pool.pool_assets(deps.as_ref())?.contains(&ask) {
+        return Err(CwDexError::AssetNotInPool { asset: ask }.into());
+    }
+    let balance_before = received.query_balance_before(deps.as_ref(), &env, &ask)?;
+
+    // The withdrawn amounts are only known once the withdrawal is executed, so
+    // they are swapped in a callback. `min_out` is checked on the total
+    // received in a second callback instead of on each swap.
+    let mut balances_before = vec![];
+    for info in pool.pool_assets(deps.as_ref())? {
+        if info != ask {
+            let balance = received.query_balance_before(deps.as_ref(), &env, &info)?;
+            balances_before.push(Asset::new(info, balance));
+        }
+    }
+    let res = pool.withdraw_liquidity(deps.as_ref(), &env, lp_token.clone(), AssetList::new())?;
+    let swap_callback = CallbackMsg::SwapWithdrawn {
+        lp_token: lp_token.info,
+        ask: ask.clone(),
+        balances_before,
+    };
+    let callback = CallbackMsg::SendReceived {
+        ask,
+        balance_before,
+        min_out,
+        recipient: recipient.clone(),
+    };
+
+    Ok(res
+        .add_message(swap_callback.into_cosmos_msg(&env)?)
+        .add_message(callback.into_cosmos_msg(&env)?)
+        .add_attribute("action", "zap_out")
+        .add_attribute("recipient", recipient))
+}
+
+pub fn execute_swap_withdrawn(
+    deps: DepsMut,
+    env: Env,
+    lp_token: AssetInfo,
+    ask: AssetInfo,
+    balances_before: Vec<Asset>,
+) -> Result<Response, ContractError> {
+    let pool = load_pool(deps.as_ref(), &lp_token)?;
+
+    let mut responses = vec![Response::new()];
+    for before in balances_before.into_iter() {
+        let balance = before
+            .info
+            .query_balance(&deps.querier, &env.contract.address)?;
+        let withdrawn = balance.checked_sub(before.amount).map_err(StdError::from)?;
+        if withdrawn.is_zero() {
+            continue;
+        }
+        responses.push(pool.swap(
+            deps.as_ref(),
+            &env,
+            Asset::new(before.info.clone(), withdrawn),
+            ask.clone(),
+            Uint128::zero(),
+        )?);
+    }
+
+    Ok(merge_responses(responses).add_attribute("action", "swap_withdrawn"))
+}
+
+pub fn execute_send_received(
+    deps: DepsMut,
+    env: Env,
+    ask: AssetInfo,
+    balance_before: Uint128,
+    min_out: Uint128,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let balance = ask.query_balance(&deps.querier, env.contract.address)?;
+    let received = balance
+        .checked_sub(balance_before)
+        .map_err(StdError::from)?;
+    if received < min_out {
+        return Err(CwDexError::MinOutNotReceived { min_out, received }.into());
+    }
+
+    let asset = Asset::new(ask, received);
+    Ok(Response::new()
+        .add_message(asset.transfer_msg(recipient)?)
+        .add_attribute("action", "send_received")
+        .add_attribute("received", asset.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        ZAP_IN_REPLY_ID => {
+            let pending = PENDING_ZAP_IN.load(deps.storage)?;
+            PENDING_ZAP_IN.remove(deps.storage);
+            let pool = load_pool(deps.as_ref(), &pending.lp_token)?;
+
+            // Send the LP tokens and the remainders of the pool assets, which
+            // the router does not hold otherwise
+            let mut msgs = vec![];
+            for info in std::iter::once(pending.lp_token).chain(pool.pool_assets(deps.as_ref())?) {
+                let balance = info.query_balance(&deps.querier, &env.contract.address)?;
+                if !balance.is_zero() {
+                    msgs.push(Asset::new(info, balance).transfer_msg(&pending.recipient)?);
+                }
+            }
+
+            Ok(Response::new().add_messages(msgs))
+        }
+        id => Err(StdError::generic_err(format!("Unknown reply id: {id}")).into()),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Pools {} => {
+            let pools = POOLS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| {
+                    let (_, pool) = item?;
+                    Ok(PoolResponse {
+                        lp_token: pool.lp_token(),
+                        assets: pool.pool_assets(deps)?,
+                    })
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&pools)
+        }
+        QueryMsg::SimulateZapOut { lp_token, ask } => {
+            let pool = load_pool(deps, &lp_token.info)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+            to_json_binary(&simulate_zap_out(
+                deps,
+                &pool,
+                &lp_token,
+                &ask,
+                Decimal::zero(),
+            )?)
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use apollo_cw_asset::AssetInfo;
+use cosmwasm_std::StdError;
+use cw_dex::CwDexError;
+use cw_utils::PaymentError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    CwDex(#[from] CwDexError),
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("No pool configured for LP token {lp_token}")]
+    UnknownPool { lp_token: AssetInfo },
+
+    #[error("Expected exactly one asset to be sent")]
+    InvalidFunds {},
+}
@@ -0,0 +1,15 @@
+//! A zap router built on the cw-dex traits. Zapping in provides liquidity with
+//! a single asset, swapping part of it into the other asset of the pool, and
+//! zapping out withdraws liquidity and swaps the withdrawn assets into a single
+//! asset. The received assets are sent to the user, the router holds no funds
+//! between transactions.
+//!
+//! The pools are configured with `cw-dex-config`, so the same contract routes
+//! into pools of every supported dex.
+
+pub mod contract;
+mod error;
+pub mod msg;
+pub mod state;
+
+pub use crate::error::ContractError;
@@ -0,0 +1,97 @@
+use apollo_cw_asset::{Asset, AssetInfo};
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{to_json_binary, Addr, CosmosMsg, Env, StdResult, Uint128, WasmMsg};
+use cw20::Cw20ReceiveMsg;
+use cw_dex::composite::ZapOutSimulation;
+use cw_dex_config::PoolConfig;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The pools to route into. Zapping in requires pools with two assets.
+    pub pools: Vec<PoolConfig>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Swaps part of the single sent asset into the other asset of the pool
+    /// of `lp_token` and provides liquidity, sending the LP tokens and any
+    /// remainder of the pool assets to `recipient`, the sender by default.
+    ZapIn {
+        lp_token: AssetInfo,
+        min_lp_out: Uint128,
+        recipient: Option<String>,
+    },
+    /// Withdraws the sent LP tokens and swaps the withdrawn assets into `ask`,
+    /// sending it to `recipient`, the sender by default. Cw20 LP tokens are
+    /// zapped out with [`ReceiveMsg::ZapOut`].
+    ZapOut {
+        ask: AssetInfo,
+        min_out: Uint128,
+        recipient: Option<String>,
+    },
+    /// Receives cw20 LP tokens sent with a [`ReceiveMsg`]
+    Receive(Cw20ReceiveMsg),
+    /// Steps of the execute messages that depend on the results of earlier
+    /// messages. Can only be executed by the router itself.
+    Callback(CallbackMsg),
+}
+
+#[cw_serde]
+pub enum ReceiveMsg {
+    /// Same as [`ExecuteMsg::ZapOut`]
+    ZapOut {
+        ask: AssetInfo,
+        min_out: Uint128,
+        recipient: Option<String>,
+    },
+}
+
+#[cw_serde]
+pub enum CallbackMsg {
+    /// Swaps the assets of the pool of `lp_token` other than `ask` that were
+    /// received since the balances were `balances_before` into `ask`.
+    SwapWithdrawn {
+        lp_token: AssetInfo,
+        ask: AssetInfo,
+        balances_before: Vec<Asset>,
+    },
+    /// Sends the amount of `ask` received since the balance was
+    /// `balance_before` to `recipient`, failing if it is less than `min_out`.
+    SendReceived {
+        ask: AssetInfo,
+        balance_before: Uint128,
+        min_out: Uint128,
+        recipient: Addr,
+    },
+}
+
+impl CallbackMsg {
+    /// Returns the message executing the callback on the router.
+    pub fn into_cosmos_msg(self, env: &Env) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_json_binary(&ExecuteMsg::Callback(self))?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Returns the pools of the router
+    #[returns(Vec<PoolResponse>)]
+    Pools {},
+    /// Simulates zapping `lp_token` out into `ask`
+    #[returns(ZapOutSimulation)]
+    SimulateZapOut { lp_token: Asset, ask: AssetInfo },
+}
+
+#[cw_serde]
+pub struct PoolResponse {
+    /// The LP token of the pool
+    pub lp_token: AssetInfo,
+    /// The assets of the pool
+    pub assets: Vec<AssetInfo>,
+}
@@ -0,0 +1,18 @@
+use apollo_cw_asset::AssetInfo;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Addr;
+use cw_dex_config::DexPool;
+use cw_storage_plus::{Item, Map};
+
+/// A zap in waiting for the reply of its provide liquidity message
+#[cw_serde]
+pub struct PendingZapIn {
+    /// The LP token of the pool liquidity is provided to
+    pub lp_token: AssetInfo,
+    /// The address to send the LP tokens to
+    pub recipient: Addr,
+}
+
+/// The pools of the router, keyed by their LP token
+pub const POOLS: Map<String, DexPool> = Map::new("pools");
+pub const PENDING_ZAP_IN: Item<PendingZapIn> = Item::new("pending_zap_in");
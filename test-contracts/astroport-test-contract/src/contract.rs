@@ -1,14 +1,27 @@
 use crate::error::ContractError;
-use crate::state::{POOL, STAKING};
+use crate::state::{CLAIM_TRACKER, DUST, POOL, REFUND_TRACKER, STAKING};
 use apollo_cw_asset::{Asset, AssetInfo, AssetList};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
-    Uint128,
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
+use cw_dex::accounting::{claim_rewards_with_accounting, CLAIM_REWARDS_ACCOUNTING_REPLY_ID};
+use cw_dex::composite::{
+    route_and_provide_liquidity, swap_and_stake, swap_and_stake_reply, SWAP_AND_STAKE_REPLY_ID,
+};
+use cw_dex::dust::DustConfig;
+use cw_dex::metrics::QueryPoolMetrics;
+use cw_dex::traits::{
+    MigratePosition, Pool, QueryPosition, Rewards, Router, SimulateStaking, SimulationMode, Stake,
+    Unstake,
+};
+use cw_dex_astroport::astroport::pair::{ConfigResponse, QueryMsg as PairQueryMsg};
+use cw_dex_astroport::{
+    AstroportPool, AstroportRouter, AstroportStaking, Cw20AllowanceMode,
+    ASTROPORT_PROVIDE_REFUND_REPLY_ID,
 };
-use cw_dex::traits::{Pool, Rewards, Stake, Unstake};
-use cw_dex_astroport::{AstroportPool, AstroportStaking};
 use cw_dex_test_contract::msg::{
     AstroportContractInstantiateMsg as InstantiateMsg, AstroportExecuteMsg as ExecuteMsg, QueryMsg,
 };
@@ -25,13 +38,12 @@ pub fn instantiate(
     let pool = AstroportPool::new(deps.as_ref(), pair_addr, liquidity_manager)?;
     POOL.save(deps.storage, &pool)?;
 
-    STAKING.save(
-        deps.storage,
-        &AstroportStaking {
-            lp_token_addr: Addr::unchecked(msg.lp_token_addr),
-            incentives: Addr::unchecked(msg.incentives_addr),
-        },
+    let staking = AstroportStaking::new_checked(
+        deps.as_ref(),
+        deps.api.addr_validate(&msg.lp_token_addr)?,
+        deps.api.addr_validate(&msg.incentives_addr)?,
     )?;
+    STAKING.save(deps.storage, &staking)?;
 
     Ok(Response::default())
 }
@@ -50,14 +62,83 @@ pub fn execute(
         ExecuteMsg::WithdrawLiquidity { amount, min_out } => {
             execute_withdraw_liquidity(deps, env, info, amount, min_out)
         }
+        ExecuteMsg::WithdrawLiquidityTo {
+            amount,
+            min_out,
+            recipient,
+        } => execute_withdraw_liquidity_to(deps, env, amount, min_out, recipient),
         ExecuteMsg::Stake { amount } => execute_stake(deps, env, info, amount),
         ExecuteMsg::Unstake { amount } => execute_unstake(deps, env, info, amount),
         ExecuteMsg::ClaimRewards {} => execute_claim_rewards(deps, env, info),
+        ExecuteMsg::ClaimRewardsFiltered { assets, return_to } => {
+            let staking = STAKING.load(deps.storage)?;
+            let return_to = return_to
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            Ok(staking.claim_rewards_filtered(deps.as_ref(), &env, &assets, return_to.as_ref())?)
+        }
+        ExecuteMsg::ClaimRewardsWithAccounting { reward_tokens } => {
+            execute_claim_rewards_with_accounting(deps, env, reward_tokens)
+        }
+        ExecuteMsg::SetCw20Adapter { adapter } => {
+            let adapter = deps.api.addr_validate(&adapter)?;
+            STAKING.update(deps.storage, |staking| {
+                StdResult::Ok(staking.with_cw20_adapter(adapter))
+            })?;
+            Ok(Response::default())
+        }
+        ExecuteMsg::ClaimVested { vesting } => {
+            let staking = STAKING.load(deps.storage)?;
+            let vesting = deps.api.addr_validate(&vesting)?;
+            Ok(staking.claim_vested(deps.as_ref(), &env, &vesting)?)
+        }
+        ExecuteMsg::MigratePosition { amount, incentives } => {
+            let staking = STAKING.load(deps.storage)?;
+            let to = AstroportStaking {
+                strict_rewards: staking.strict_rewards,
+                cw20_adapter: staking.cw20_adapter.clone(),
+                chain_profile: staking.chain_profile,
+                ..AstroportStaking::new_checked(
+                    deps.as_ref(),
+                    staking.lp_token_addr.clone(),
+                    deps.api.addr_validate(&incentives)?,
+                )?
+            };
+            let res = staking.migrate_position(deps.as_ref(), &env, amount, &to)?;
+            STAKING.save(deps.storage, &to)?;
+            Ok(res)
+        }
         ExecuteMsg::Swap {
             offer,
             ask,
             min_out,
         } => execute_swap(deps, env, offer, ask, min_out),
+        ExecuteMsg::SwapPath {
+            router,
+            offer,
+            path,
+            min_out,
+        } => execute_swap_path(deps, env, router, offer, path, min_out),
+        ExecuteMsg::SwapAndStake {
+            offer,
+            min_lp_out,
+            dust,
+        } => execute_swap_and_stake(deps, env, offer, min_lp_out, dust.unwrap_or_default()),
+        ExecuteMsg::ProvideLiquidityTrackingRefunds { assets, min_out } => {
+            execute_provide_liquidity_tracking_refunds(deps, env, assets, min_out)
+        }
+        ExecuteMsg::ProvideLiquidityDecreasingAllowances { assets, min_out } => {
+            let pool = POOL
+                .load(deps.storage)?
+                .with_allowance_mode(Cw20AllowanceMode::DecreaseAfterProvide);
+            Ok(pool.provide_liquidity(deps.as_ref(), &env, assets, min_out)?)
+        }
+        ExecuteMsg::RouteAndProvideLiquidity {
+            assets,
+            intermediate_pair,
+            min_lp_out,
+        } => execute_route_and_provide_liquidity(deps, env, assets, intermediate_pair, min_lp_out),
+        ExecuteMsg::Receive(receive_msg) => execute_receive(deps, info, receive_msg),
     }
 }
 
@@ -89,6 +170,23 @@ pub fn execute_withdraw_liquidity(
     Ok(pool.withdraw_liquidity(deps.as_ref(), &env, lp_token, min_out)?)
 }
 
+pub fn execute_withdraw_liquidity_to(
+    deps: DepsMut,
+    env: Env,
+    amount: Uint128,
+    min_out: AssetList,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let pool = POOL.load(deps.storage)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let lp_token = Asset {
+        info: pool.lp_token(),
+        amount,
+    };
+
+    Ok(pool.withdraw_liquidity_to(deps.as_ref(), &env, lp_token, min_out, Some(recipient))?)
+}
+
 pub fn execute_stake(
     deps: DepsMut,
     env: Env,
@@ -119,6 +217,26 @@ pub fn execute_claim_rewards(
     Ok(staking.claim_rewards(deps.as_ref(), &env)?)
 }
 
+pub fn execute_claim_rewards_with_accounting(
+    deps: DepsMut,
+    env: Env,
+    reward_tokens: Vec<AssetInfo>,
+) -> Result<Response, ContractError> {
+    let staking = STAKING.load(deps.storage)?;
+    let (res, tracker) = claim_rewards_with_accounting(
+        deps.as_ref(),
+        &env,
+        &staking,
+        &reward_tokens,
+        Response::new(),
+    )?;
+    if let Some(tracker) = tracker {
+        CLAIM_TRACKER.save(deps.storage, &tracker)?;
+    }
+
+    Ok(res)
+}
+
 pub fn execute_swap(
     deps: DepsMut,
     env: Env,
@@ -131,6 +249,93 @@ pub fn execute_swap(
     Ok(pool.swap(deps.as_ref(), &env, offer, ask, min_out)?)
 }
 
+pub fn execute_swap_path(
+    deps: DepsMut,
+    env: Env,
+    router: String,
+    offer: Asset,
+    path: Vec<AssetInfo>,
+    min_out: Uint128,
+) -> Result<Response, ContractError> {
+    let router = AstroportRouter::new(deps.as_ref(), deps.api.addr_validate(&router)?)?;
+
+    Ok(router.swap_path(deps.as_ref(), &env, offer, &path, min_out)?)
+}
+
+pub fn execute_swap_and_stake(
+    deps: DepsMut,
+    env: Env,
+    offer: Asset,
+    min_lp_out: Uint128,
+    dust: DustConfig,
+) -> Result<Response, ContractError> {
+    let pool = POOL.load(deps.storage)?;
+    let staking = STAKING.load(deps.storage)?;
+    DUST.save(deps.storage, &dust)?;
+
+    Ok(swap_and_stake(
+        deps.as_ref(),
+        &env,
+        offer,
+        &pool,
+        &staking,
+        min_lp_out,
+        &dust,
+    )?)
+}
+
+pub fn execute_route_and_provide_liquidity(
+    deps: DepsMut,
+    env: Env,
+    assets: AssetList,
+    intermediate_pair: String,
+    min_lp_out: Uint128,
+) -> Result<Response, ContractError> {
+    let pool = POOL.load(deps.storage)?;
+    let intermediate_pair = deps.api.addr_validate(&intermediate_pair)?;
+    let intermediate_pool = AstroportPool::new(
+        deps.as_ref(),
+        intermediate_pair,
+        pool.liquidity_manager.clone(),
+    )?;
+
+    Ok(route_and_provide_liquidity(
+        deps.as_ref(),
+        &env,
+        assets,
+        &intermediate_pool,
+        &pool,
+        min_lp_out,
+    )?)
+}
+
+pub fn execute_provide_liquidity_tracking_refunds(
+    deps: DepsMut,
+    env: Env,
+    assets: AssetList,
+    min_out: Uint128,
+) -> Result<Response, ContractError> {
+    let pool = POOL.load(deps.storage)?;
+    let (res, tracker) =
+        pool.provide_liquidity_tracking_refunds(deps.as_ref(), &env, assets, min_out)?;
+    REFUND_TRACKER.save(deps.storage, &tracker)?;
+
+    Ok(res)
+}
+
+pub fn execute_receive(
+    deps: DepsMut,
+    info: MessageInfo,
+    receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let staking = STAKING.load(deps.storage)?;
+    let recipient = staking.verify_stake_hook(deps.as_ref(), &info, &receive_msg)?;
+
+    Ok(Response::new().add_message(
+        staking.stake_msg(receive_msg.amount, recipient.map(|addr| addr.to_string()))?,
+    ))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     let pool = POOL.load(deps.storage)?;
@@ -143,6 +348,11 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             &pool.simulate_withdraw_liquidity(deps, &Asset::new(pool.lp_token(), amount))?,
         ),
         QueryMsg::SimulateSwap { offer, ask } => query_simulate_swap(deps, offer, ask),
+        QueryMsg::SimulateSwapLocal { offer, ask } => to_json_binary(
+            &pool
+                .with_simulation_mode(SimulationMode::Local)
+                .simulate_swap(deps, offer, ask)?,
+        ),
         QueryMsg::GetPoolForLpToken { lp_token } => to_json_binary(
             &AstroportPool::get_pool_for_lp_token(deps, &lp_token, pool.liquidity_manager)?,
         ),
@@ -150,6 +360,77 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let staking = STAKING.load(deps.storage)?;
             to_json_binary(&staking.query_pending_rewards(&deps.querier, &env.contract.address)?)
         }
+        QueryMsg::PoolMetrics {} => {
+            let staking = STAKING.load(deps.storage)?;
+            let metrics = pool
+                .query_pool_metrics(deps)?
+                .with_emissions(staking.query_reward_emissions(&deps.querier)?);
+            to_json_binary(&metrics)
+        }
+        QueryMsg::SimulateStake { amount } => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.simulate_stake(&deps.querier, &env.contract.address, amount)?)
+        }
+        QueryMsg::SimulateUnstake { amount } => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.simulate_unstake(
+                &deps.querier,
+                &env.contract.address,
+                amount,
+            )?)
+        }
+        QueryMsg::StakingPosition {} => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.query_position(deps, &env)?)
+        }
+        QueryMsg::CanForceUnlock { .. } => unimplemented!(),
+        QueryMsg::SimulateWithdrawLiquiditySingleSided { .. } => unimplemented!(),
+        QueryMsg::ExternalIncentiveGauges {} => unimplemented!(),
+        QueryMsg::FindPools { asset_a, asset_b } => {
+            let config: ConfigResponse = deps
+                .querier
+                .query_wasm_smart(&pool.pair_addr, &PairQueryMsg::Config {})?;
+            to_json_binary(&AstroportPool::find_pools(
+                deps,
+                &config.factory_addr,
+                &pool.liquidity_manager,
+                &asset_a,
+                &asset_b,
+            )?)
+        }
+        QueryMsg::FeeShare {} => to_json_binary(&pool.query_fee_share(&deps.querier)?),
+        QueryMsg::VestingRewards { vesting } => {
+            let staking = STAKING.load(deps.storage)?;
+            let vesting = deps.api.addr_validate(&vesting)?;
+            to_json_binary(&staking.query_vesting_rewards(
+                &deps.querier,
+                &vesting,
+                &env.contract.address,
+            )?)
+        }
+        QueryMsg::UserRewardIndex {} => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.query_user_reward_index(&deps.querier, &env.contract.address)?)
+        }
+        QueryMsg::ProjectedRewards { duration } => {
+            let staking = STAKING.load(deps.storage)?;
+            let staked = staking.query_staked_amount(&deps.querier, &env.contract.address)?;
+            to_json_binary(&staking.query_projected_rewards(
+                &deps.querier,
+                &env,
+                staked,
+                duration,
+            )?)
+        }
+        QueryMsg::PoolInvariant {} => to_json_binary(&pool.query_invariant(&deps.querier)?),
+        QueryMsg::SimulateSwapPath {
+            router,
+            offer,
+            path,
+        } => {
+            let router = AstroportRouter::new(deps, deps.api.addr_validate(&router)?)?;
+            to_json_binary(&router.simulate_swap_path(deps, offer, &path)?)
+        }
     }
 }
 
@@ -159,8 +440,33 @@ pub fn query_simulate_swap(deps: Deps, offer: Asset, ask: AssetInfo) -> StdResul
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(_deps: DepsMut, _env: Env, _msg: Reply) -> Result<Response, ContractError> {
-    Ok(Response::default())
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        SWAP_AND_STAKE_REPLY_ID => {
+            let pool = POOL.load(deps.storage)?;
+            let staking = STAKING.load(deps.storage)?;
+            let dust = DUST.load(deps.storage)?;
+            Ok(swap_and_stake_reply(
+                deps.as_ref(),
+                &env,
+                &pool,
+                &staking,
+                &dust,
+            )?)
+        }
+        ASTROPORT_PROVIDE_REFUND_REPLY_ID => {
+            let tracker = REFUND_TRACKER.load(deps.storage)?;
+            let (res, _refunded) = tracker.handle_reply(deps.as_ref(), &env)?;
+            Ok(res)
+        }
+        CLAIM_REWARDS_ACCOUNTING_REPLY_ID => {
+            let tracker = CLAIM_TRACKER.load(deps.storage)?;
+            CLAIM_TRACKER.remove(deps.storage);
+            let (res, _claimed) = tracker.handle_reply(deps.as_ref(), &env)?;
+            Ok(res)
+        }
+        _ => Ok(Response::default()),
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,10 @@
-use cw_dex_astroport::{AstroportPool, AstroportStaking};
+use cw_dex::accounting::ClaimRewardsTracker;
+use cw_dex::dust::DustConfig;
+use cw_dex_astroport::{AstroportPool, AstroportStaking, ProvideRefundTracker};
 use cw_storage_plus::Item;
 
 pub const POOL: Item<AstroportPool> = Item::new("pool");
 pub const STAKING: Item<AstroportStaking> = Item::new("staking");
+pub const DUST: Item<DustConfig> = Item::new("dust");
+pub const REFUND_TRACKER: Item<ProvideRefundTracker> = Item::new("refund_tracker");
+pub const CLAIM_TRACKER: Item<ClaimRewardsTracker> = Item::new("claim_tracker");
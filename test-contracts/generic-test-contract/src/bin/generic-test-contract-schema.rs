@@ -0,0 +1,11 @@
+use cosmwasm_schema::write_api;
+
+use cw_dex_test_contract::msg::{ExecuteMsg, GenericTestContractInstantiateMsg, QueryMsg};
+
+fn main() {
+    write_api! {
+        instantiate: GenericTestContractInstantiateMsg,
+        execute: ExecuteMsg,
+        query: QueryMsg,
+    }
+}
@@ -0,0 +1,162 @@
+use crate::error::ContractError;
+use crate::state::{POOL, RESOLVER, STAKING};
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
+    Storage, Uint128,
+};
+use cw_dex::prelude::*;
+use cw_dex_config::{DexPool, DexStaking};
+use cw_dex_test_contract::msg::{
+    ExecuteMsg, GenericTestContractInstantiateMsg as InstantiateMsg, QueryMsg,
+};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let pool = msg.pool.build(deps.as_ref())?;
+    POOL.save(deps.storage, &pool)?;
+
+    if let Some(staking) = msg.staking {
+        let staking = staking.build(deps.as_ref())?;
+        STAKING.save(deps.storage, &staking)?;
+    }
+
+    if let Some(resolver) = msg.resolver {
+        // Validate the config before storing it
+        resolver.build(deps.as_ref())?;
+        RESOLVER.save(deps.storage, &resolver)?;
+    }
+
+    Ok(Response::default())
+}
+
+fn load_staking(storage: &dyn Storage) -> Result<DexStaking, ContractError> {
+    STAKING
+        .may_load(storage)?
+        .ok_or(ContractError::StakingNotConfigured {})
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::ProvideLiquidity { assets, min_out } => {
+            execute_provide_liquidity(deps, env, assets, min_out)
+        }
+        ExecuteMsg::WithdrawLiquidity { amount, min_out } => {
+            execute_withdraw_liquidity(deps, env, amount, min_out)
+        }
+        ExecuteMsg::Stake { amount } => {
+            let staking = load_staking(deps.storage)?;
+            Ok(staking.stake(deps.as_ref(), &env, amount)?)
+        }
+        ExecuteMsg::Unlock { amount } => {
+            let staking = load_staking(deps.storage)?;
+            Ok(staking.unstake(deps.as_ref(), &env, amount)?)
+        }
+        ExecuteMsg::Swap {
+            offer,
+            ask,
+            min_out,
+        } => {
+            let pool = POOL.load(deps.storage)?;
+            Ok(pool.swap(deps.as_ref(), &env, offer, ask, min_out)?)
+        }
+        ExecuteMsg::SuperfluidStake { .. }
+        | ExecuteMsg::SuperfluidUnlock { .. }
+        | ExecuteMsg::WithdrawUnlocked { .. }
+        | ExecuteMsg::ForceUnlock { .. }
+        | ExecuteMsg::ForceUnlockAll { .. }
+        | ExecuteMsg::WithdrawLiquiditySingleSided { .. } => unimplemented!(),
+    }
+}
+
+pub fn execute_provide_liquidity(
+    deps: DepsMut,
+    env: Env,
+    assets: AssetList,
+    min_out: Uint128,
+) -> Result<Response, ContractError> {
+    let pool = POOL.load(deps.storage)?;
+
+    Ok(pool.provide_liquidity(deps.as_ref(), &env, assets, min_out)?)
+}
+
+pub fn execute_withdraw_liquidity(
+    deps: DepsMut,
+    env: Env,
+    amount: Uint128,
+    min_out: AssetList,
+) -> Result<Response, ContractError> {
+    let pool = POOL.load(deps.storage)?;
+    let lp_token = Asset {
+        info: pool.lp_token(),
+        amount,
+    };
+
+    Ok(pool.withdraw_liquidity(deps.as_ref(), &env, lp_token, min_out)?)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let pool = POOL.load(deps.storage)?;
+    let staking = || load_staking(deps.storage).map_err(|e| StdError::generic_err(e.to_string()));
+    match msg {
+        QueryMsg::PoolLiquidity {} => to_json_binary(&pool.get_pool_liquidity(deps)?),
+        QueryMsg::SimulateProvideLiquidity { assets } => {
+            to_json_binary(&pool.simulate_provide_liquidity(deps, &env, assets)?.amount)
+        }
+        QueryMsg::SimulateWithdrawLiquidty { amount } => to_json_binary(
+            &pool.simulate_withdraw_liquidity(deps, &Asset::new(pool.lp_token(), amount))?,
+        ),
+        QueryMsg::SimulateSwap { offer, ask } => {
+            to_json_binary(&pool.simulate_swap(deps, offer, ask)?)
+        }
+        QueryMsg::PendingRewards {} => {
+            to_json_binary(&staking()?.query_pending_rewards(&deps.querier, &env.contract.address)?)
+        }
+        QueryMsg::SimulateStake { amount } => to_json_binary(&staking()?.simulate_stake(
+            &deps.querier,
+            &env.contract.address,
+            amount,
+        )?),
+        QueryMsg::SimulateUnstake { amount } => to_json_binary(&staking()?.simulate_unstake(
+            &deps.querier,
+            &env.contract.address,
+            amount,
+        )?),
+        QueryMsg::StakingPosition {} => to_json_binary(&staking()?.query_position(deps, &env)?),
+        QueryMsg::GetPoolForLpToken { lp_token } => {
+            let resolver = RESOLVER.may_load(deps.storage)?.unwrap_or_default();
+            let resolver = resolver.build(deps)?;
+            to_json_binary(&DexPool::get_pool_for_lp_token(deps, &lp_token, &resolver)?)
+        }
+        QueryMsg::SimulateSwapLocal { .. }
+        | QueryMsg::PoolMetrics {}
+        | QueryMsg::CanForceUnlock { .. }
+        | QueryMsg::FindPools { .. }
+        | QueryMsg::FeeShare {}
+        | QueryMsg::VestingRewards { .. }
+        | QueryMsg::UserRewardIndex {}
+        | QueryMsg::ProjectedRewards { .. }
+        | QueryMsg::PoolInvariant {}
+        | QueryMsg::SimulateSwapPath { .. }
+        | QueryMsg::SimulateWithdrawLiquiditySingleSided { .. }
+        | QueryMsg::ExternalIncentiveGauges {} => unimplemented!(),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, _msg: Reply) -> Result<Response, ContractError> {
+    Ok(Response::default())
+}
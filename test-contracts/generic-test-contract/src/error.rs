@@ -0,0 +1,19 @@
+use cosmwasm_std::StdError;
+use cw_dex::CwDexError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("No staking configured")]
+    StakingNotConfigured {},
+    // Add any other custom errors you like here.
+    // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
+    #[error("cw-dex")]
+    CwDex(#[from] CwDexError),
+}
@@ -0,0 +1,6 @@
+use cw_dex_config::{DexPool, DexStaking, LpTokenResolverConfig};
+use cw_storage_plus::Item;
+
+pub const POOL: Item<DexPool> = Item::new("pool");
+pub const STAKING: Item<DexStaking> = Item::new("staking");
+pub const RESOLVER: Item<LpTokenResolverConfig> = Item::new("resolver");
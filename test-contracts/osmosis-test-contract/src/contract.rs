@@ -5,8 +5,13 @@ use cosmwasm_std::{
     to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
     Uint128,
 };
-use cw_dex::traits::{ForceUnlock, Pool, Stake, Unlock};
-use cw_dex_osmosis::{OsmosisPool, OsmosisStaking, OsmosisSuperfluidStaking};
+use cw_dex::metrics::QueryPoolMetrics;
+use cw_dex::traits::{
+    ForceUnlock, Pool, QueryPosition, SimulateStaking, SimulationMode, Stake, Unlock,
+};
+use cw_dex_osmosis::{
+    query_external_incentive_gauges, OsmosisPool, OsmosisStaking, OsmosisSuperfluidStaking,
+};
 
 use crate::error::ContractError;
 use crate::state::{POOL, STAKING, SUPERFLUID};
@@ -62,11 +67,23 @@ pub fn execute(
         ExecuteMsg::WithdrawLiquidity { amount, min_out } => {
             execute_withdraw_liquidity(deps, env, info, amount, min_out)
         }
+        ExecuteMsg::WithdrawLiquiditySingleSided {
+            amount,
+            ask,
+            min_out,
+        } => {
+            let pool = POOL.load(deps.storage)?;
+            let lp_token = Asset::new(pool.lp_token(), amount);
+            Ok(pool.withdraw_liquidity_single_sided(&env, lp_token, &ask, min_out)?)
+        }
         ExecuteMsg::Stake { amount } => execute_stake(deps, env, info, amount),
         ExecuteMsg::Unlock { amount } => execute_unlock(deps, env, info, amount),
         ExecuteMsg::ForceUnlock { amount, lockup_id } => {
             execute_force_unlock(deps, env, info, amount, lockup_id)
         }
+        ExecuteMsg::ForceUnlockAll { lockup_id } => {
+            execute_force_unlock_all(deps, env, info, lockup_id)
+        }
         ExecuteMsg::WithdrawUnlocked { amount } => {
             execute_withdraw_unlocked(deps, env, info, amount)
         }
@@ -181,6 +198,17 @@ pub fn execute_force_unlock(
     Ok(staking.force_unlock(deps.as_ref(), &env, lockup_id, amount)?)
 }
 
+pub fn execute_force_unlock_all(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    lockup_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let staking = STAKING.load(deps.storage)?;
+
+    Ok(staking.force_unlock_all(deps.as_ref(), &env, lockup_id)?)
+}
+
 pub fn execute_swap(
     deps: DepsMut,
     env: Env,
@@ -204,11 +232,57 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::SimulateWithdrawLiquidty { amount } => to_json_binary(
             &pool.simulate_withdraw_liquidity(deps, &Asset::new(pool.lp_token(), amount))?,
         ),
+        QueryMsg::SimulateWithdrawLiquiditySingleSided { amount, ask } => {
+            to_json_binary(&pool.simulate_single_sided_exit(
+                &deps.querier,
+                &Asset::new(pool.lp_token(), amount),
+                &ask,
+            )?)
+        }
         QueryMsg::SimulateSwap { offer, ask } => query_simulate_swap(deps, offer, ask),
+        QueryMsg::SimulateSwapLocal { offer, ask } => to_json_binary(
+            &pool
+                .with_simulation_mode(SimulationMode::Local)
+                .simulate_swap(deps, offer, ask)?,
+        ),
         QueryMsg::GetPoolForLpToken { lp_token } => {
             to_json_binary(&OsmosisPool::get_pool_for_lp_token(deps, &lp_token)?)
         }
         QueryMsg::PendingRewards {} => unimplemented!(),
+        QueryMsg::PoolMetrics {} => to_json_binary(&pool.query_pool_metrics(deps)?),
+        QueryMsg::SimulateStake { amount } => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.simulate_stake(&deps.querier, &env.contract.address, amount)?)
+        }
+        QueryMsg::SimulateUnstake { amount } => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.simulate_unstake(
+                &deps.querier,
+                &env.contract.address,
+                amount,
+            )?)
+        }
+        QueryMsg::StakingPosition {} => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.query_position(deps, &env)?)
+        }
+        QueryMsg::CanForceUnlock { amount, lockup_id } => {
+            let staking = STAKING.load(deps.storage)?;
+            to_json_binary(&staking.can_force_unlock(deps, &env, lockup_id, amount)?)
+        }
+        QueryMsg::FindPools { asset_a, asset_b } => {
+            to_json_binary(&OsmosisPool::find_pools(deps, &asset_a, &asset_b)?)
+        }
+        QueryMsg::FeeShare {} => unimplemented!(),
+        QueryMsg::VestingRewards { .. } => unimplemented!(),
+        QueryMsg::UserRewardIndex {} => unimplemented!(),
+        QueryMsg::ProjectedRewards { .. } => unimplemented!(),
+        QueryMsg::PoolInvariant {} => unimplemented!(),
+        QueryMsg::SimulateSwapPath { .. } => unimplemented!(),
+        QueryMsg::ExternalIncentiveGauges {} => to_json_binary(&query_external_incentive_gauges(
+            &deps.querier,
+            &pool.lp_token().to_string(),
+        )?),
     }
 }
 
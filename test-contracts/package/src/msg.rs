@@ -1,6 +1,11 @@
 use apollo_cw_asset::{Asset, AssetInfo, AssetList};
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{to_json_binary, Addr, Coin, CosmosMsg, Uint128, WasmMsg};
+use cw20::Cw20ReceiveMsg;
+use cw_dex::dust::DustConfig;
+use cw_dex::metrics::PoolMetrics;
+use cw_dex::traits::{StakingPosition, StakingPositionInfo};
+use cw_dex_config::{LpTokenResolverConfig, PoolConfig, StakingConfig};
 
 #[cw_serde]
 pub struct OsmosisTestContractInstantiateMsg {
@@ -19,6 +24,18 @@ pub struct AstroportContractInstantiateMsg {
     pub liquidity_manager_addr: String,
 }
 
+/// Instantiates the generic test contract, which works with the pool and
+/// staking of any dex, built from configuration with `cw-dex-config`.
+#[cw_serde]
+pub struct GenericTestContractInstantiateMsg {
+    pub pool: PoolConfig,
+    pub staking: Option<StakingConfig>,
+    /// The resolvers of the `GetPoolForLpToken` query. Without it the query
+    /// rejects all tokens.
+    #[serde(default)]
+    pub resolver: Option<LpTokenResolverConfig>,
+}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     ProvideLiquidity {
@@ -29,6 +46,11 @@ pub enum ExecuteMsg {
         amount: Uint128,
         min_out: AssetList,
     },
+    WithdrawLiquiditySingleSided {
+        amount: Uint128,
+        ask: String,
+        min_out: Uint128,
+    },
     Stake {
         amount: Uint128,
     },
@@ -48,6 +70,9 @@ pub enum ExecuteMsg {
         amount: Uint128,
         lockup_id: Option<u64>,
     },
+    ForceUnlockAll {
+        lockup_id: Option<u64>,
+    },
     Swap {
         offer: Asset,
         ask: AssetInfo,
@@ -81,11 +106,51 @@ pub enum QueryMsg {
     #[returns(AssetList)]
     SimulateWithdrawLiquidty { amount: Uint128 },
     #[returns(Uint128)]
+    SimulateWithdrawLiquiditySingleSided { amount: Uint128, ask: String },
+    #[returns(Uint128)]
     SimulateSwap { offer: Asset, ask: AssetInfo },
+    #[returns(Uint128)]
+    SimulateSwapLocal { offer: Asset, ask: AssetInfo },
     #[returns(Unknown)]
     GetPoolForLpToken { lp_token: AssetInfo },
     #[returns(AssetList)]
     PendingRewards {},
+    #[returns(PoolMetrics)]
+    PoolMetrics {},
+    #[returns(StakingPosition)]
+    SimulateStake { amount: Uint128 },
+    #[returns(StakingPosition)]
+    SimulateUnstake { amount: Uint128 },
+    #[returns(StakingPositionInfo)]
+    StakingPosition {},
+    #[returns(())]
+    CanForceUnlock {
+        amount: Uint128,
+        lockup_id: Option<u64>,
+    },
+    #[returns(Vec<Unknown>)]
+    FindPools {
+        asset_a: AssetInfo,
+        asset_b: AssetInfo,
+    },
+    #[returns(Unknown)]
+    FeeShare {},
+    #[returns(Unknown)]
+    VestingRewards { vesting: String },
+    #[returns(Unknown)]
+    UserRewardIndex {},
+    #[returns(AssetList)]
+    ProjectedRewards { duration: u64 },
+    #[returns(Unknown)]
+    PoolInvariant {},
+    #[returns(Uint128)]
+    SimulateSwapPath {
+        router: String,
+        offer: Asset,
+        path: Vec<AssetInfo>,
+    },
+    #[returns(Vec<Unknown>)]
+    ExternalIncentiveGauges {},
 }
 
 #[cw_serde]
@@ -98,6 +163,11 @@ pub enum AstroportExecuteMsg {
         amount: Uint128,
         min_out: AssetList,
     },
+    WithdrawLiquidityTo {
+        amount: Uint128,
+        min_out: AssetList,
+        recipient: String,
+    },
     Stake {
         amount: Uint128,
     },
@@ -105,11 +175,53 @@ pub enum AstroportExecuteMsg {
         amount: Uint128,
     },
     ClaimRewards {},
+    ClaimRewardsFiltered {
+        assets: Vec<AssetInfo>,
+        return_to: Option<String>,
+    },
+    ClaimRewardsWithAccounting {
+        reward_tokens: Vec<AssetInfo>,
+    },
+    SetCw20Adapter {
+        adapter: String,
+    },
+    ClaimVested {
+        vesting: String,
+    },
+    MigratePosition {
+        amount: Uint128,
+        incentives: String,
+    },
     Swap {
         offer: Asset,
         ask: AssetInfo,
         min_out: Uint128,
     },
+    SwapPath {
+        router: String,
+        offer: Asset,
+        path: Vec<AssetInfo>,
+        min_out: Uint128,
+    },
+    SwapAndStake {
+        offer: Asset,
+        min_lp_out: Uint128,
+        dust: Option<DustConfig>,
+    },
+    ProvideLiquidityTrackingRefunds {
+        assets: AssetList,
+        min_out: Uint128,
+    },
+    ProvideLiquidityDecreasingAllowances {
+        assets: AssetList,
+        min_out: Uint128,
+    },
+    RouteAndProvideLiquidity {
+        assets: AssetList,
+        intermediate_pair: String,
+        min_lp_out: Uint128,
+    },
+    Receive(Cw20ReceiveMsg),
 }
 
 impl AstroportExecuteMsg {
@@ -3,7 +3,7 @@ use apollo_utils::assets::separate_natives_and_cw20s;
 use astroport::asset::{Asset as AstroAsset, AssetInfo as AstroAssetInfo};
 use astroport::factory::PairType;
 use astroport::pair::{ExecuteMsg as PairExecuteMsg, StablePoolParams};
-use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal, Uint128};
+use cosmwasm_std::{to_json_binary, Addr, Binary, Coin, Decimal, Uint128};
 use cw20::{Cw20ExecuteMsg, MinterResponse};
 use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
 use cw_dex_test_contract::msg::AstroportContractInstantiateMsg;
@@ -23,7 +23,6 @@ use cw_it::Artifact;
 use crate::{common_pcl_params, cw20_mint, instantiate_cw20};
 
 /// Setup a pool and test contract for testing.
-#[allow(unused_variables)]
 pub fn setup_pool_and_test_contract<'a>(
     runner: &'a TestRunner<'a>,
     pool_type: PairType,
@@ -37,6 +36,48 @@ pub fn setup_pool_and_test_contract<'a>(
     String,
     AssetList,
     AstroportContracts,
+)> {
+    let init_params = match &pool_type {
+        PairType::Stable {} => Some(
+            to_json_binary(&StablePoolParams {
+                amp: 10u64,
+                owner: None,
+            })
+            .unwrap(),
+        ),
+        PairType::Custom(t) => match t.as_str() {
+            "concentrated" => Some(to_json_binary(&common_pcl_params()).unwrap()),
+            _ => None,
+        },
+        _ => None,
+    };
+    setup_pool_and_test_contract_with_params(
+        runner,
+        pool_type,
+        initial_liquidity,
+        native_denom_count,
+        wasm_file_path,
+        init_params,
+    )
+}
+
+/// Setup a pool created with the pair init params `init_params` and test
+/// contract for testing.
+#[allow(unused_variables)]
+pub fn setup_pool_and_test_contract_with_params<'a>(
+    runner: &'a TestRunner<'a>,
+    pool_type: PairType,
+    initial_liquidity: Vec<(&str, u64)>,
+    native_denom_count: usize,
+    wasm_file_path: &str,
+    init_params: Option<Binary>,
+) -> RunnerResult<(
+    Vec<SigningAccount>,
+    String,
+    String,
+    String,
+    AssetList,
+    AstroportContracts,
 )> {
     let wasm = Wasm::new(runner);
 
@@ -197,20 +238,6 @@ pub fn setup_pool_and_test_contract<'a>(
     }
 
     // Create pool
-    let init_params = match &pool_type {
-        PairType::Stable {} => Some(
-            to_json_binary(&StablePoolParams {
-                amp: 10u64,
-                owner: None,
-            })
-            .unwrap(),
-        ),
-        PairType::Custom(t) => match t.as_str() {
-            "concentrated" => Some(to_json_binary(&common_pcl_params()).unwrap()),
-            _ => None,
-        },
-        _ => None,
-    };
     let (pair_addr, lp_token_addr) = create_astroport_pair(
         runner,
         &astroport_contracts.factory.address,
@@ -145,3 +145,36 @@ pub fn common_pcl_params() -> ConcentratedPoolParams {
         track_asset_balances: None,
     }
 }
+
+/// Named PCL parameter sets, used to check that the simulations of
+/// concentrated pairs match execution. The initial liquidity of a pair should
+/// be provided at the ratio given by its `price_scale`.
+pub fn pcl_param_sets() -> Vec<(&'static str, ConcentratedPoolParams)> {
+    let common = common_pcl_params();
+    vec![
+        ("common", common.clone()),
+        (
+            "high_fee",
+            ConcentratedPoolParams {
+                mid_fee: f64_to_dec(0.01),
+                out_fee: f64_to_dec(0.03),
+                ..common.clone()
+            },
+        ),
+        (
+            "low_amp",
+            ConcentratedPoolParams {
+                amp: f64_to_dec(10f64),
+                gamma: f64_to_dec(0.0001),
+                ..common.clone()
+            },
+        ),
+        (
+            "price_scale_2",
+            ConcentratedPoolParams {
+                price_scale: Decimal::from_ratio(2u128, 1u128),
+                ..common
+            },
+        ),
+    ]
+}
@@ -7,3 +7,6 @@ pub mod astroport;
 
 #[cfg(feature = "osmosis")]
 pub mod osmosis;
+
+#[cfg(feature = "osmosis-multi-test")]
+pub mod osmosis_multi_test;
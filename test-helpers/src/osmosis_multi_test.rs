@@ -0,0 +1,1119 @@
+//! A Stargate shim for `MultiTestRunner` emulating the parts of the Osmosis
+//! gamm and poolmanager modules used by `OsmosisPool`, so that its logic can
+//! be tested without osmosis-test-tube.
+//!
+//! Only balancer pools are supported. Swaps and single sided joins use the
+//! weighted pool math of [`cw_dex_osmosis::math`]. No pool creation fee is
+//! charged. The taker fee of [`OsmosisPoolsModule::taker_fee`] is deducted
+//! from the input of each hop of swaps and their estimates, and reported as
+//! the default and pair taker fee of the poolmanager.
+
+use std::str::FromStr;
+
+use anyhow::{bail, ensure};
+use cosmwasm_std::{
+    from_json, to_json_binary, to_json_vec, Addr, Api, BankMsg, Binary, BlockInfo, Coin, Decimal,
+    Decimal256, Empty, Querier, Storage, Uint128, Uint256,
+};
+use cw_it::cw_multi_test::{
+    AppResponse, BankSudo, CosmosRouter, StargateKeeper, StargateMessageHandler, StargateMsg,
+    StargateQueryHandler, SudoMsg,
+};
+use cw_it::multi_test::MultiTestRunner;
+use cw_it::osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use cw_it::osmosis_std::types::osmosis::gamm::poolmodels::balancer::v1beta1::{
+    MsgCreateBalancerPool, MsgCreateBalancerPoolResponse,
+};
+use cw_it::osmosis_std::types::osmosis::gamm::v1beta1::{
+    MsgExitPool, MsgExitPoolResponse, MsgJoinPool, MsgJoinPoolResponse, MsgJoinSwapExternAmountIn,
+    MsgJoinSwapExternAmountInResponse, MsgSwapExactAmountIn as GammMsgSwapExactAmountIn,
+    Pool as BalancerPool, PoolAsset, PoolParams, QueryCalcExitPoolCoinsFromSharesRequest,
+    QueryCalcExitPoolCoinsFromSharesResponse, QueryCalcJoinPoolNoSwapSharesRequest,
+    QueryCalcJoinPoolNoSwapSharesResponse, QueryCalcJoinPoolSharesRequest,
+    QueryCalcJoinPoolSharesResponse, QueryPoolParamsRequest, QueryPoolParamsResponse,
+    QueryTotalSharesRequest, QueryTotalSharesResponse,
+};
+use cw_it::osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    EstimateSwapExactAmountInRequest, EstimateSwapExactAmountInResponse,
+    MsgSwapExactAmountIn as PoolmanagerMsgSwapExactAmountIn, MsgSwapExactAmountInResponse,
+    Params as PoolmanagerParams, ParamsResponse as PoolmanagerParamsResponse, PoolRequest,
+    PoolResponse, SwapAmountInRoute, TakerFeeParams, TotalPoolLiquidityRequest,
+    TotalPoolLiquidityResponse, TradingPairTakerFeeResponse,
+};
+use serde::{Deserialize, Serialize};
+
+use cw_dex_osmosis::deduct_taker_fee;
+use cw_dex_osmosis::math::{calc_out_amount_given_in, calc_pool_shares_out_given_single_asset_in};
+
+/// The storage key under which the pools are saved
+const POOLS_KEY: &[u8] = b"cw-dex-test-helpers/osmosis-pools";
+
+/// The address holding the reserves of all pools
+const GAMM_MODULE_ADDR: &str = "gamm_module";
+
+/// The address receiving the taker fees of swaps
+pub const TAKER_FEE_COLLECTOR_ADDR: &str = "taker_fee_collector";
+
+/// The amount of shares minted on pool creation, same as on Osmosis
+const INIT_POOL_SHARES: u128 = 100_000_000_000_000_000_000;
+
+const CALC_JOIN_POOL_SHARES_PATH: &str = "/osmosis.gamm.v1beta1.Query/CalcJoinPoolShares";
+const CALC_JOIN_POOL_NO_SWAP_SHARES_PATH: &str =
+    "/osmosis.gamm.v1beta1.Query/CalcJoinPoolNoSwapShares";
+const CALC_EXIT_POOL_COINS_FROM_SHARES_PATH: &str =
+    "/osmosis.gamm.v1beta1.Query/CalcExitPoolCoinsFromShares";
+const POOL_PARAMS_PATH: &str = "/osmosis.gamm.v1beta1.Query/PoolParams";
+const TOTAL_SHARES_PATH: &str = "/osmosis.gamm.v1beta1.Query/TotalShares";
+const TOTAL_POOL_LIQUIDITY_PATH: &str = "/osmosis.poolmanager.v1beta1.Query/TotalPoolLiquidity";
+const ESTIMATE_SWAP_EXACT_AMOUNT_IN_PATH: &str =
+    "/osmosis.poolmanager.v1beta1.Query/EstimateSwapExactAmountIn";
+const POOL_PATH: &str = "/osmosis.poolmanager.v1beta1.Query/Pool";
+const POOLMANAGER_PARAMS_PATH: &str = "/osmosis.poolmanager.v1beta1.Query/Params";
+const TRADING_PAIR_TAKER_FEE_PATH: &str = "/osmosis.poolmanager.v1beta1.Query/TradingPairTakerFee";
+
+/// The in-memory model of a balancer pool
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MockOsmosisPool {
+    pub pool_id: u64,
+    pub assets: Vec<Coin>,
+    /// The weights of the assets, in the same order as `assets`
+    pub weights: Vec<Uint128>,
+    pub total_shares: Uint128,
+    pub swap_fee: Decimal,
+    pub exit_fee: Decimal,
+}
+
+impl MockOsmosisPool {
+    pub fn lp_denom(&self) -> String {
+        format!("gamm/pool/{}", self.pool_id)
+    }
+
+    fn reserve(&self, denom: &str) -> anyhow::Result<Uint128> {
+        match self.assets.iter().find(|c| c.denom == denom) {
+            Some(coin) => Ok(coin.amount),
+            None => bail!("denom {denom} is not in pool {}", self.pool_id),
+        }
+    }
+
+    fn weight(&self, denom: &str) -> anyhow::Result<Uint128> {
+        match self.assets.iter().position(|c| c.denom == denom) {
+            Some(i) => Ok(self.weights[i]),
+            None => bail!("denom {denom} is not in pool {}", self.pool_id),
+        }
+    }
+
+    /// Returns the pool as returned by the poolmanager `Pool` query.
+    pub fn to_balancer_pool(&self) -> BalancerPool {
+        BalancerPool {
+            address: String::new(),
+            id: self.pool_id,
+            pool_params: Some(self.pool_params()),
+            future_pool_governor: String::new(),
+            total_shares: Some(Coin::new(self.total_shares.u128(), self.lp_denom()).into()),
+            pool_assets: self
+                .assets
+                .iter()
+                .zip(&self.weights)
+                .map(|(coin, weight)| PoolAsset {
+                    token: Some(coin.clone().into()),
+                    weight: weight.to_string(),
+                })
+                .collect(),
+            total_weight: self.weights.iter().sum::<Uint128>().to_string(),
+        }
+    }
+
+    fn pool_params(&self) -> PoolParams {
+        PoolParams {
+            swap_fee: self.swap_fee.to_string(),
+            exit_fee: self.exit_fee.to_string(),
+            smooth_weight_change_params: None,
+        }
+    }
+
+    fn update_reserve(&mut self, denom: &str, amount: Uint128) {
+        if let Some(coin) = self.assets.iter_mut().find(|c| c.denom == denom) {
+            coin.amount = amount;
+        }
+    }
+
+    /// Returns the amount of `out_denom` received for swapping `token_in`.
+    pub fn swap_exact_amount_in(
+        &self,
+        token_in: &Coin,
+        out_denom: &str,
+    ) -> anyhow::Result<Uint128> {
+        ensure!(
+            token_in.denom != out_denom,
+            "cannot swap a denom for itself"
+        );
+        Ok(calc_out_amount_given_in(
+            self.reserve(&token_in.denom)?,
+            self.weight(&token_in.denom)?,
+            self.reserve(out_denom)?,
+            self.weight(out_denom)?,
+            token_in.amount,
+            self.swap_fee.into(),
+        )?)
+    }
+
+    /// Returns the shares minted and tokens used for a join with all of the
+    /// assets of the pool, without swapping.
+    pub fn join_no_swap(&self, tokens_in: &[Coin]) -> anyhow::Result<(Uint128, Vec<Coin>)> {
+        ensure!(
+            tokens_in.len() == self.assets.len(),
+            "no swap joins must contain all assets of the pool"
+        );
+        let mut share_ratio: Option<Decimal256> = None;
+        for token in tokens_in {
+            let ratio = Decimal256::from_ratio(token.amount, self.reserve(&token.denom)?);
+            share_ratio = Some(share_ratio.map_or(ratio, |r| r.min(ratio)));
+        }
+        let shares: Uint128 =
+            (Uint256::from(self.total_shares) * share_ratio.unwrap_or_default()).try_into()?;
+        ensure!(!shares.is_zero(), "no shares minted");
+
+        Ok((shares, self.tokens_for_shares(shares)?))
+    }
+
+    /// Returns the shares minted for a single sided join with `token_in`.
+    pub fn join_single_sided(&self, token_in: &Coin) -> anyhow::Result<Uint128> {
+        let total_weight: Uint128 = self.weights.iter().sum();
+        let shares = calc_pool_shares_out_given_single_asset_in(
+            self.reserve(&token_in.denom)?,
+            Decimal256::from_ratio(self.weight(&token_in.denom)?, total_weight),
+            self.total_shares,
+            token_in.amount,
+            self.swap_fee.into(),
+        )?;
+        ensure!(!shares.is_zero(), "no shares minted");
+        Ok(shares)
+    }
+
+    /// Returns the tokens needed to mint `shares`, rounded up.
+    fn tokens_for_shares(&self, shares: Uint128) -> anyhow::Result<Vec<Coin>> {
+        let total_shares = Uint256::from(self.total_shares);
+        self.assets
+            .iter()
+            .map(|coin| {
+                let amount: Uint128 =
+                    ((coin.amount.full_mul(shares) + total_shares - Uint256::one()) / total_shares)
+                        .try_into()?;
+                Ok(Coin::new(amount.u128(), coin.denom.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns the tokens received for exiting the pool with `shares`.
+    pub fn exit(&self, shares: Uint128) -> anyhow::Result<Vec<Coin>> {
+        ensure!(shares <= self.total_shares, "insufficient pool shares");
+        let shares_after_fee: Uint128 = (Uint256::from(shares)
+            * (Decimal256::one() - Decimal256::from(self.exit_fee)))
+        .try_into()?;
+        Ok(self
+            .assets
+            .iter()
+            .map(|coin| {
+                Coin::new(
+                    coin.amount
+                        .multiply_ratio(shares_after_fee, self.total_shares)
+                        .u128(),
+                    coin.denom.clone(),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Implements [`StargateQueryHandler`] and [`StargateMessageHandler`] for the
+/// gamm and poolmanager queries and messages used by `OsmosisPool`. Pools are
+/// saved in the storage of the multi-test app.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OsmosisPoolsModule {
+    /// The taker fee charged on swaps of all pairs, sent to
+    /// [`TAKER_FEE_COLLECTOR_ADDR`]
+    pub taker_fee: Decimal,
+}
+
+impl OsmosisPoolsModule {
+    /// Returns a `MultiTestRunner` with this module registered, charging no
+    /// taker fee.
+    pub fn runner(address_prefix: &str) -> MultiTestRunner<'_> {
+        Self::register(&OSMOSIS_POOLS_MODULE, address_prefix)
+    }
+
+    /// Returns a `MultiTestRunner` with this module registered, charging
+    /// `taker_fee` on swaps.
+    pub fn runner_with_taker_fee(address_prefix: &str, taker_fee: Decimal) -> MultiTestRunner<'_> {
+        // The handlers are registered for the lifetime of the runner, which is
+        // only created in tests, so leaking the module is fine.
+        let module: &'static Self = Box::leak(Box::new(Self { taker_fee }));
+        Self::register(module, address_prefix)
+    }
+
+    fn register<'a>(module: &'static Self, address_prefix: &'a str) -> MultiTestRunner<'a> {
+        let mut keeper = StargateKeeper::new();
+        module.register_queries(&mut keeper);
+        module.register_msgs(&mut keeper);
+        MultiTestRunner::new_with_stargate(address_prefix, keeper)
+    }
+
+    /// Returns all pools created with the module.
+    pub fn load_pools(storage: &dyn Storage) -> anyhow::Result<Vec<MockOsmosisPool>> {
+        Ok(match storage.get(POOLS_KEY) {
+            Some(bytes) => from_json(bytes)?,
+            None => vec![],
+        })
+    }
+
+    /// Returns the pool with `pool_id`.
+    pub fn load_pool(storage: &dyn Storage, pool_id: u64) -> anyhow::Result<MockOsmosisPool> {
+        match Self::load_pools(storage)?
+            .into_iter()
+            .find(|p| p.pool_id == pool_id)
+        {
+            Some(pool) => Ok(pool),
+            None => bail!("pool {pool_id} not found"),
+        }
+    }
+
+    fn save_pool(storage: &mut dyn Storage, pool: MockOsmosisPool) -> anyhow::Result<()> {
+        let mut pools = Self::load_pools(storage)?;
+        match pools.iter_mut().find(|p| p.pool_id == pool.pool_id) {
+            Some(existing) => *existing = pool,
+            None => pools.push(pool),
+        }
+        storage.set(POOLS_KEY, &to_json_vec(&pools)?);
+        Ok(())
+    }
+
+    fn query(&self, storage: &dyn Storage, request: StargateMsg) -> anyhow::Result<Binary> {
+        let value = request.value;
+        Ok(match request.type_url.as_str() {
+            TOTAL_POOL_LIQUIDITY_PATH => {
+                let req: TotalPoolLiquidityRequest = value.try_into()?;
+                let pool = Self::load_pool(storage, req.pool_id)?;
+                to_json_binary(&TotalPoolLiquidityResponse {
+                    liquidity: to_proto_coins(&pool.assets),
+                })?
+            }
+            TOTAL_SHARES_PATH => {
+                let req: QueryTotalSharesRequest = value.try_into()?;
+                let pool = Self::load_pool(storage, req.pool_id)?;
+                to_json_binary(&QueryTotalSharesResponse {
+                    total_shares: Some(Coin::new(pool.total_shares.u128(), pool.lp_denom()).into()),
+                })?
+            }
+            POOL_PARAMS_PATH => {
+                let req: QueryPoolParamsRequest = value.try_into()?;
+                let pool = Self::load_pool(storage, req.pool_id)?;
+                to_json_binary(&QueryPoolParamsResponse {
+                    params: Some(pool.pool_params().to_any()),
+                })?
+            }
+            CALC_JOIN_POOL_SHARES_PATH => {
+                let req: QueryCalcJoinPoolSharesRequest = value.try_into()?;
+                let pool = Self::load_pool(storage, req.pool_id)?;
+                let tokens_in = from_proto_coins(&req.tokens_in)?;
+                let (shares, tokens_out) = match tokens_in.as_slice() {
+                    [token_in] => (pool.join_single_sided(token_in)?, tokens_in.clone()),
+                    _ => pool.join_no_swap(&tokens_in)?,
+                };
+                to_json_binary(&QueryCalcJoinPoolSharesResponse {
+                    share_out_amount: shares.to_string(),
+                    tokens_out: to_proto_coins(&tokens_out),
+                })?
+            }
+            CALC_JOIN_POOL_NO_SWAP_SHARES_PATH => {
+                let req: QueryCalcJoinPoolNoSwapSharesRequest = value.try_into()?;
+                let pool = Self::load_pool(storage, req.pool_id)?;
+                let (shares, tokens_out) = pool.join_no_swap(&from_proto_coins(&req.tokens_in)?)?;
+                to_json_binary(&QueryCalcJoinPoolNoSwapSharesResponse {
+                    tokens_out: to_proto_coins(&tokens_out),
+                    shares_out: shares.to_string(),
+                })?
+            }
+            CALC_EXIT_POOL_COINS_FROM_SHARES_PATH => {
+                let req: QueryCalcExitPoolCoinsFromSharesRequest = value.try_into()?;
+                let pool = Self::load_pool(storage, req.pool_id)?;
+                let tokens_out = pool.exit(Uint128::from_str(&req.share_in_amount)?)?;
+                to_json_binary(&QueryCalcExitPoolCoinsFromSharesResponse {
+                    tokens_out: to_proto_coins(&tokens_out),
+                })?
+            }
+            ESTIMATE_SWAP_EXACT_AMOUNT_IN_PATH => {
+                let req: EstimateSwapExactAmountInRequest = value.try_into()?;
+                let token_in = Coin::from_str(&req.token_in)?;
+                let (out, _) = self.simulate_routes(storage, token_in, &req.routes)?;
+                to_json_binary(&EstimateSwapExactAmountInResponse {
+                    token_out_amount: out.amount.to_string(),
+                })?
+            }
+            POOL_PATH => {
+                let req: PoolRequest = value.try_into()?;
+                let pool = Self::load_pool(storage, req.pool_id)?;
+                to_json_binary(&PoolResponse {
+                    pool: Some(pool.to_balancer_pool().to_any()),
+                })?
+            }
+            POOLMANAGER_PARAMS_PATH => to_json_binary(&PoolmanagerParamsResponse {
+                params: Some(PoolmanagerParams {
+                    pool_creation_fee: vec![],
+                    taker_fee_params: Some(TakerFeeParams {
+                        default_taker_fee: self.taker_fee.to_string(),
+                        ..Default::default()
+                    }),
+                    authorized_quote_denoms: vec![],
+                }),
+            })?,
+            TRADING_PAIR_TAKER_FEE_PATH => to_json_binary(&TradingPairTakerFeeResponse {
+                taker_fee: self.taker_fee.to_string(),
+            })?,
+            path => bail!("unsupported stargate query: {path}"),
+        })
+    }
+
+    /// Returns the amount received for swapping `token_in` along `routes` and
+    /// the taker fees charged on the input of each hop.
+    fn simulate_routes(
+        &self,
+        storage: &dyn Storage,
+        token_in: Coin,
+        routes: &[SwapAmountInRoute],
+    ) -> anyhow::Result<(Coin, Vec<Coin>)> {
+        let mut token = token_in;
+        let mut taker_fees = vec![];
+        for route in routes {
+            let (token_after_fee, taker_fee) = self.deduct_taker_fee(token);
+            taker_fees.extend(taker_fee);
+            let pool = Self::load_pool(storage, route.pool_id)?;
+            let amount = pool.swap_exact_amount_in(&token_after_fee, &route.token_out_denom)?;
+            token = Coin::new(amount.u128(), route.token_out_denom.clone());
+        }
+        Ok((token, taker_fees))
+    }
+
+    /// Splits `token` into the amount swapped in the pool and the taker fee,
+    /// which is `None` if zero.
+    fn deduct_taker_fee(&self, token: Coin) -> (Coin, Option<Coin>) {
+        let amount = deduct_taker_fee(token.amount, self.taker_fee);
+        let fee = token.amount - amount;
+        (
+            Coin::new(amount.u128(), token.denom.clone()),
+            (!fee.is_zero()).then(|| Coin::new(fee.u128(), token.denom)),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_swap(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = Empty, QueryC = Empty>,
+        block: &BlockInfo,
+        sender: Addr,
+        routes: &[SwapAmountInRoute],
+        token_in: Option<ProtoCoin>,
+        token_out_min_amount: &str,
+    ) -> anyhow::Result<Uint128> {
+        let token_in = from_proto_coin(&token_in.unwrap_or_default())?;
+        ensure!(!routes.is_empty(), "no swap routes");
+
+        let mut token = token_in.clone();
+        let mut taker_fees = vec![];
+        for route in routes {
+            let (token_after_fee, taker_fee) = self.deduct_taker_fee(token);
+            taker_fees.extend(taker_fee);
+            token = token_after_fee;
+            let mut pool = Self::load_pool(storage, route.pool_id)?;
+            let amount = pool.swap_exact_amount_in(&token, &route.token_out_denom)?;
+            let in_reserve = pool.reserve(&token.denom)? + token.amount;
+            let out_reserve = pool.reserve(&route.token_out_denom)? - amount;
+            pool.update_reserve(&token.denom, in_reserve);
+            pool.update_reserve(&route.token_out_denom, out_reserve);
+            Self::save_pool(storage, pool)?;
+            token = Coin::new(amount.u128(), route.token_out_denom.clone());
+        }
+        ensure!(
+            token.amount >= Uint128::from_str(token_out_min_amount)?,
+            "token amount calculated ({}) is lesser than min amount ({token_out_min_amount})",
+            token.amount
+        );
+
+        send(
+            api,
+            storage,
+            router,
+            block,
+            sender.clone(),
+            GAMM_MODULE_ADDR,
+            vec![token_in],
+        )?;
+        send(
+            api,
+            storage,
+            router,
+            block,
+            Addr::unchecked(GAMM_MODULE_ADDR),
+            TAKER_FEE_COLLECTOR_ADDR,
+            taker_fees,
+        )?;
+        send(
+            api,
+            storage,
+            router,
+            block,
+            Addr::unchecked(GAMM_MODULE_ADDR),
+            sender.as_str(),
+            vec![token.clone()],
+        )?;
+        Ok(token.amount)
+    }
+}
+
+/// The instance of [`OsmosisPoolsModule`] registered by
+/// [`OsmosisPoolsModule::runner`].
+pub const OSMOSIS_POOLS_MODULE: OsmosisPoolsModule = OsmosisPoolsModule {
+    taker_fee: Decimal::zero(),
+};
+
+impl StargateQueryHandler for OsmosisPoolsModule {
+    fn stargate_query(
+        &self,
+        _api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: StargateMsg,
+    ) -> anyhow::Result<Binary> {
+        self.query(storage, request)
+    }
+
+    fn register_queries(&'static self, keeper: &mut StargateKeeper<Empty, Empty>) {
+        for path in [
+            CALC_JOIN_POOL_SHARES_PATH,
+            CALC_JOIN_POOL_NO_SWAP_SHARES_PATH,
+            CALC_EXIT_POOL_COINS_FROM_SHARES_PATH,
+            POOL_PARAMS_PATH,
+            TOTAL_SHARES_PATH,
+            TOTAL_POOL_LIQUIDITY_PATH,
+            ESTIMATE_SWAP_EXACT_AMOUNT_IN_PATH,
+            POOL_PATH,
+            POOLMANAGER_PARAMS_PATH,
+            TRADING_PAIR_TAKER_FEE_PATH,
+        ] {
+            keeper.register_query(path, Box::new(*self));
+        }
+    }
+}
+
+impl StargateMessageHandler<Empty, Empty> for OsmosisPoolsModule {
+    fn execute(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = Empty, QueryC = Empty>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: StargateMsg,
+    ) -> anyhow::Result<AppResponse> {
+        let value = msg.value;
+        let module = Addr::unchecked(GAMM_MODULE_ADDR);
+        match msg.type_url.as_str() {
+            MsgCreateBalancerPool::TYPE_URL => {
+                let msg: MsgCreateBalancerPool = value.try_into()?;
+                let params = msg.pool_params.unwrap_or_default();
+                ensure!(
+                    msg.pool_assets.len() >= 2,
+                    "pools must have at least two assets"
+                );
+                let mut pool_assets = msg
+                    .pool_assets
+                    .iter()
+                    .map(|a| {
+                        let weight = Uint128::from_str(&a.weight)?;
+                        ensure!(!weight.is_zero(), "weights must be positive");
+                        Ok((
+                            from_proto_coin(&a.token.clone().unwrap_or_default())?,
+                            weight,
+                        ))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                pool_assets.sort_by(|(a, _), (b, _)| a.denom.cmp(&b.denom));
+                let (assets, weights): (Vec<_>, Vec<_>) = pool_assets.into_iter().unzip();
+
+                let pool = MockOsmosisPool {
+                    pool_id: Self::load_pools(storage)?.len() as u64 + 1,
+                    assets: assets.clone(),
+                    weights,
+                    total_shares: Uint128::new(INIT_POOL_SHARES),
+                    swap_fee: parse_dec(&params.swap_fee)?,
+                    exit_fee: parse_dec(&params.exit_fee)?,
+                };
+                let pool_id = pool.pool_id;
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    sender.clone(),
+                    GAMM_MODULE_ADDR,
+                    assets,
+                )?;
+                mint(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.lp_denom(),
+                    pool.total_shares,
+                )?;
+                Self::save_pool(storage, pool)?;
+
+                Ok(response(MsgCreateBalancerPoolResponse { pool_id }.into()))
+            }
+            MsgJoinPool::TYPE_URL => {
+                let msg: MsgJoinPool = value.try_into()?;
+                let mut pool = Self::load_pool(storage, msg.pool_id)?;
+                let shares = Uint128::from_str(&msg.share_out_amount)?;
+                let tokens_in = pool.tokens_for_shares(shares)?;
+                let maxs = from_proto_coins(&msg.token_in_maxs)?;
+                for token in &tokens_in {
+                    let max = maxs.iter().find(|c| c.denom == token.denom);
+                    ensure!(
+                        max.map_or(false, |max| max.amount >= token.amount),
+                        "{token} exceeds max amount in"
+                    );
+                    let reserve = pool.reserve(&token.denom)? + token.amount;
+                    pool.update_reserve(&token.denom, reserve);
+                }
+                pool.total_shares += shares;
+
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    sender.clone(),
+                    GAMM_MODULE_ADDR,
+                    tokens_in.clone(),
+                )?;
+                mint(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.lp_denom(),
+                    shares,
+                )?;
+                Self::save_pool(storage, pool)?;
+
+                Ok(response(
+                    MsgJoinPoolResponse {
+                        share_out_amount: shares.to_string(),
+                        token_in: to_proto_coins(&tokens_in),
+                    }
+                    .into(),
+                ))
+            }
+            MsgJoinSwapExternAmountIn::TYPE_URL => {
+                let msg: MsgJoinSwapExternAmountIn = value.try_into()?;
+                let mut pool = Self::load_pool(storage, msg.pool_id)?;
+                let token_in = from_proto_coin(&msg.token_in.unwrap_or_default())?;
+                let shares = pool.join_single_sided(&token_in)?;
+                ensure!(
+                    shares >= Uint128::from_str(&msg.share_out_min_amount)?,
+                    "too few shares out"
+                );
+                let reserve = pool.reserve(&token_in.denom)? + token_in.amount;
+                pool.update_reserve(&token_in.denom, reserve);
+                pool.total_shares += shares;
+
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    sender.clone(),
+                    GAMM_MODULE_ADDR,
+                    vec![token_in],
+                )?;
+                mint(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &sender,
+                    &pool.lp_denom(),
+                    shares,
+                )?;
+                Self::save_pool(storage, pool)?;
+
+                Ok(response(
+                    MsgJoinSwapExternAmountInResponse {
+                        share_out_amount: shares.to_string(),
+                    }
+                    .into(),
+                ))
+            }
+            MsgExitPool::TYPE_URL => {
+                let msg: MsgExitPool = value.try_into()?;
+                let mut pool = Self::load_pool(storage, msg.pool_id)?;
+                let shares = Uint128::from_str(&msg.share_in_amount)?;
+                let tokens_out = pool.exit(shares)?;
+                for min in from_proto_coins(&msg.token_out_mins)? {
+                    let out = tokens_out.iter().find(|c| c.denom == min.denom);
+                    ensure!(
+                        out.map_or(false, |out| out.amount >= min.amount),
+                        "exit pool returned less than {min}"
+                    );
+                }
+                for token in &tokens_out {
+                    let reserve = pool.reserve(&token.denom)? - token.amount;
+                    pool.update_reserve(&token.denom, reserve);
+                }
+                pool.total_shares -= shares;
+
+                router.execute(
+                    api,
+                    storage,
+                    block,
+                    sender.clone(),
+                    BankMsg::Burn {
+                        amount: vec![Coin::new(shares.u128(), pool.lp_denom())],
+                    }
+                    .into(),
+                )?;
+                send(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    module,
+                    sender.as_str(),
+                    tokens_out.clone(),
+                )?;
+                Self::save_pool(storage, pool)?;
+
+                Ok(response(
+                    MsgExitPoolResponse {
+                        token_out: to_proto_coins(&tokens_out),
+                    }
+                    .into(),
+                ))
+            }
+            GammMsgSwapExactAmountIn::TYPE_URL => {
+                let msg: GammMsgSwapExactAmountIn = value.try_into()?;
+                let out = self.execute_swap(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    sender,
+                    &msg.routes,
+                    msg.token_in,
+                    &msg.token_out_min_amount,
+                )?;
+                Ok(response(
+                    MsgSwapExactAmountInResponse {
+                        token_out_amount: out.to_string(),
+                    }
+                    .into(),
+                ))
+            }
+            PoolmanagerMsgSwapExactAmountIn::TYPE_URL => {
+                let msg: PoolmanagerMsgSwapExactAmountIn = value.try_into()?;
+                let out = self.execute_swap(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    sender,
+                    &msg.routes,
+                    msg.token_in,
+                    &msg.token_out_min_amount,
+                )?;
+                Ok(response(
+                    MsgSwapExactAmountInResponse {
+                        token_out_amount: out.to_string(),
+                    }
+                    .into(),
+                ))
+            }
+            type_url => bail!("unsupported stargate message: {type_url}"),
+        }
+    }
+
+    fn register_msgs(&'static self, keeper: &mut StargateKeeper<Empty, Empty>) {
+        for type_url in [
+            MsgCreateBalancerPool::TYPE_URL,
+            MsgJoinPool::TYPE_URL,
+            MsgJoinSwapExternAmountIn::TYPE_URL,
+            MsgExitPool::TYPE_URL,
+            GammMsgSwapExactAmountIn::TYPE_URL,
+            PoolmanagerMsgSwapExactAmountIn::TYPE_URL,
+        ] {
+            keeper.register_msg(type_url, Box::new(*self));
+        }
+    }
+}
+
+fn response(data: Binary) -> AppResponse {
+    AppResponse {
+        events: vec![],
+        data: Some(data),
+    }
+}
+
+fn send(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    router: &dyn CosmosRouter<ExecC = Empty, QueryC = Empty>,
+    block: &BlockInfo,
+    from: Addr,
+    to: &str,
+    amount: Vec<Coin>,
+) -> anyhow::Result<()> {
+    let amount: Vec<Coin> = amount.into_iter().filter(|c| !c.amount.is_zero()).collect();
+    if amount.is_empty() {
+        return Ok(());
+    }
+    router.execute(
+        api,
+        storage,
+        block,
+        from,
+        BankMsg::Send {
+            to_address: to.to_string(),
+            amount,
+        }
+        .into(),
+    )?;
+    Ok(())
+}
+
+fn mint(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    router: &dyn CosmosRouter<ExecC = Empty, QueryC = Empty>,
+    block: &BlockInfo,
+    to: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> anyhow::Result<()> {
+    router.sudo(
+        api,
+        storage,
+        block,
+        SudoMsg::Bank(BankSudo::Mint {
+            to_address: to.to_string(),
+            amount: vec![Coin::new(amount.u128(), denom)],
+        }),
+    )?;
+    Ok(())
+}
+
+fn parse_dec(value: &str) -> anyhow::Result<Decimal> {
+    if value.is_empty() {
+        return Ok(Decimal::zero());
+    }
+    Ok(Decimal::from_str(value)?)
+}
+
+fn from_proto_coin(coin: &ProtoCoin) -> anyhow::Result<Coin> {
+    Ok(Coin::new(
+        Uint128::from_str(&coin.amount)?.u128(),
+        coin.denom.clone(),
+    ))
+}
+
+fn from_proto_coins(coins: &[ProtoCoin]) -> anyhow::Result<Vec<Coin>> {
+    coins.iter().map(from_proto_coin).collect()
+}
+
+fn to_proto_coins(coins: &[Coin]) -> Vec<ProtoCoin> {
+    coins.iter().cloned().map(Into::into).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_cw_asset::{Asset, AssetInfo};
+    use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
+    use cosmwasm_std::{coin, Addr, Coin, Decimal, Deps, Uint128};
+    use cw_dex::traits::{Pool, SimulationMode};
+    use cw_dex_osmosis::{query_poolmanager_params, query_taker_fee, OsmosisPool};
+    use cw_it::cosmrs::proto::cosmos::base::abci::v1beta1::MsgData;
+    use cw_it::helpers::bank_balance_query;
+    use cw_it::multi_test::MultiTestRunner;
+    use cw_it::osmosis_std::types::osmosis::gamm::poolmodels::balancer::v1beta1::{
+        MsgCreateBalancerPool, MsgCreateBalancerPoolResponse,
+    };
+    use cw_it::osmosis_std::types::osmosis::gamm::v1beta1::{PoolAsset, PoolParams};
+    use cw_it::test_tube::{Account, Runner, SigningAccount};
+    use cw_it::traits::CwItRunner;
+
+    use super::{OsmosisPoolsModule, TAKER_FEE_COLLECTOR_ADDR};
+
+    fn create_pool(runner: &MultiTestRunner, creator: &SigningAccount, assets: &[Coin]) -> u64 {
+        let weighted = assets.iter().map(|c| (c.clone(), 1)).collect::<Vec<_>>();
+        create_weighted_pool(runner, creator, &weighted)
+    }
+
+    fn create_weighted_pool(
+        runner: &MultiTestRunner,
+        creator: &SigningAccount,
+        assets: &[(Coin, u64)],
+    ) -> u64 {
+        let msg = MsgCreateBalancerPool {
+            sender: creator.address(),
+            pool_params: Some(PoolParams {
+                swap_fee: "0.003".to_string(),
+                exit_fee: "0".to_string(),
+                smooth_weight_change_params: None,
+            }),
+            pool_assets: assets
+                .iter()
+                .map(|(c, weight)| PoolAsset {
+                    token: Some(c.clone().into()),
+                    weight: weight.to_string(),
+                })
+                .collect(),
+            future_pool_governor: String::new(),
+        };
+        runner
+            .execute_cosmos_msgs::<MsgCreateBalancerPoolResponse>(&[msg.into()], creator)
+            .unwrap()
+            .data
+            .pool_id
+    }
+
+    #[test]
+    fn test_osmosis_pool_under_multi_test() {
+        let runner = OsmosisPoolsModule::runner("osmo");
+        let admin = runner
+            .init_account(&[coin(1_000_000_000, "uatom"), coin(1_000_000_000, "uosmo")])
+            .unwrap();
+        let pool_id = create_pool(
+            &runner,
+            &admin,
+            &[coin(1_000_000, "uosmo"), coin(1_000_000, "uatom")],
+        );
+
+        let storage = MockStorage::new();
+        let api = MockApi::default();
+        let deps = Deps {
+            storage: &storage,
+            api: &api,
+            querier: runner.app.wrap(),
+        };
+        let mut env = mock_env();
+        env.contract.address = Addr::unchecked(admin.address());
+
+        let pool = OsmosisPool::new(pool_id, deps).unwrap();
+        assert_eq!(
+            pool.query_swap_fee(&deps.querier).unwrap(),
+            Decimal::permille(3)
+        );
+
+        // Swap
+        let offer = Asset::native("uatom", 10_000u128);
+        let expected = pool
+            .simulate_swap(deps, offer.clone(), AssetInfo::native("uosmo"))
+            .unwrap();
+        assert_eq!(expected, Uint128::new(9871));
+        let res = pool
+            .swap(deps, &env, offer, AssetInfo::native("uosmo"), expected)
+            .unwrap();
+        runner
+            .execute_cosmos_msgs::<MsgData>(
+                &res.messages.into_iter().map(|m| m.msg).collect::<Vec<_>>(),
+                &admin,
+            )
+            .unwrap();
+        let balance = bank_balance_query(&runner, admin.address(), "uosmo".to_string()).unwrap();
+        assert_eq!(balance, Uint128::new(999_000_000 + 9871));
+
+        // Provide liquidity with both assets and a single asset
+        let lp_denom = format!("gamm/pool/{pool_id}");
+        let lp_before = bank_balance_query(&runner, admin.address(), lp_denom.clone()).unwrap();
+        for assets in [
+            vec![
+                Asset::native("uatom", 10_000u128),
+                Asset::native("uosmo", 10_000u128),
+            ],
+            vec![Asset::native("uosmo", 10_000u128)],
+        ] {
+            let deps = Deps {
+                storage: &storage,
+                api: &api,
+                querier: runner.app.wrap(),
+            };
+            let expected = pool
+                .simulate_provide_liquidity(deps, &env, assets.clone().into())
+                .unwrap();
+            let res = pool
+                .provide_liquidity(deps, &env, assets.into(), expected.amount)
+                .unwrap();
+            runner
+                .execute_cosmos_msgs::<MsgData>(
+                    &res.messages.into_iter().map(|m| m.msg).collect::<Vec<_>>(),
+                    &admin,
+                )
+                .unwrap();
+        }
+        let lp_after = bank_balance_query(&runner, admin.address(), lp_denom.clone()).unwrap();
+        assert!(lp_after > lp_before);
+
+        // Withdraw all liquidity provided
+        let deps = Deps {
+            storage: &storage,
+            api: &api,
+            querier: runner.app.wrap(),
+        };
+        let lp_token = Asset::native(lp_denom.clone(), lp_after - lp_before);
+        let expected = pool.simulate_withdraw_liquidity(deps, &lp_token).unwrap();
+        let res = pool
+            .withdraw_liquidity(deps, &env, lp_token, expected)
+            .unwrap();
+        runner
+            .execute_cosmos_msgs::<MsgData>(
+                &res.messages.into_iter().map(|m| m.msg).collect::<Vec<_>>(),
+                &admin,
+            )
+            .unwrap();
+        assert_eq!(
+            bank_balance_query(&runner, admin.address(), lp_denom).unwrap(),
+            lp_before
+        );
+    }
+
+    #[test]
+    fn test_weighted_pool_under_multi_test() {
+        let runner = OsmosisPoolsModule::runner("osmo");
+        let admin = runner
+            .init_account(&[coin(1_000_000_000, "uatom"), coin(1_000_000_000, "uosmo")])
+            .unwrap();
+        // 80/20 pool
+        let pool_id = create_weighted_pool(
+            &runner,
+            &admin,
+            &[(coin(1_000_000, "uatom"), 4), (coin(1_000_000, "uosmo"), 1)],
+        );
+
+        let storage = MockStorage::new();
+        let api = MockApi::default();
+        let deps = Deps {
+            storage: &storage,
+            api: &api,
+            querier: runner.app.wrap(),
+        };
+        let mut env = mock_env();
+        env.contract.address = Addr::unchecked(admin.address());
+        let pool = OsmosisPool::new(pool_id, deps).unwrap();
+
+        // 1_000_000 * (1 - (1_000_000 / 1_009_970) ^ 4)
+        let offer = Asset::native("uatom", 10_000u128);
+        let expected = pool
+            .simulate_swap(deps, offer.clone(), AssetInfo::native("uosmo"))
+            .unwrap();
+        assert_eq!(expected, Uint128::new(38905));
+        let local = pool
+            .with_simulation_mode(SimulationMode::Local)
+            .simulate_swap(deps, offer, AssetInfo::native("uosmo"))
+            .unwrap();
+        assert_eq!(local, expected);
+
+        // Single sided joins are charged the swap fee on the part of the tokens
+        // exceeding the weight of the asset
+        let lp_denom = format!("gamm/pool/{pool_id}");
+        let lp_before = bank_balance_query(&runner, admin.address(), lp_denom.clone()).unwrap();
+        let assets = vec![Asset::native("uatom", 10_000u128)];
+        let expected = pool
+            .simulate_provide_liquidity(deps, &env, assets.clone().into())
+            .unwrap();
+        let res = pool
+            .provide_liquidity(deps, &env, assets.into(), expected.amount)
+            .unwrap();
+        runner
+            .execute_cosmos_msgs::<MsgData>(
+                &res.messages.into_iter().map(|m| m.msg).collect::<Vec<_>>(),
+                &admin,
+            )
+            .unwrap();
+        let lp_after = bank_balance_query(&runner, admin.address(), lp_denom).unwrap();
+        assert_eq!(lp_after - lp_before, expected.amount);
+        // 1e20 * ((1 + 10_000 * (1 - 0.2 * 0.003) / 1_000_000) ^ 0.8 - 1)
+        assert_eq!(expected.amount.u128() / 1_000_000_000, 798_724_136);
+    }
+
+    #[test]
+    fn test_taker_fee_under_multi_test() {
+        let taker_fee: Decimal = "0.0015".parse().unwrap();
+        let runner = OsmosisPoolsModule::runner_with_taker_fee("osmo", taker_fee);
+        let admin = runner
+            .init_account(&[coin(1_000_000_000, "uatom"), coin(1_000_000_000, "uosmo")])
+            .unwrap();
+        let pool_id = create_pool(
+            &runner,
+            &admin,
+            &[coin(1_000_000, "uosmo"), coin(1_000_000, "uatom")],
+        );
+
+        let storage = MockStorage::new();
+        let api = MockApi::default();
+        let deps = Deps {
+            storage: &storage,
+            api: &api,
+            querier: runner.app.wrap(),
+        };
+        let mut env = mock_env();
+        env.contract.address = Addr::unchecked(admin.address());
+
+        let params = query_poolmanager_params(&deps.querier).unwrap();
+        assert!(params.pool_creation_fee.is_empty());
+        assert_eq!(params.default_taker_fee, taker_fee);
+        assert_eq!(
+            query_taker_fee(&deps.querier, "uatom", "uosmo").unwrap(),
+            taker_fee
+        );
+
+        // Both simulation modes deduct the taker fee, 15 of 10_000 uatom
+        let pool = OsmosisPool::new(pool_id, deps).unwrap();
+        let offer = Asset::native("uatom", 10_000u128);
+        let expected = pool
+            .simulate_swap(deps, offer.clone(), AssetInfo::native("uosmo"))
+            .unwrap();
+        let after_fee = runner.app.read_module(|_, _, storage| {
+            OsmosisPoolsModule::load_pool(storage, pool_id)
+                .unwrap()
+                .swap_exact_amount_in(&coin(9_985, "uatom"), "uosmo")
+                .unwrap()
+        });
+        assert_eq!(expected, after_fee);
+        let local = pool
+            .with_simulation_mode(SimulationMode::Local)
+            .simulate_swap(deps, offer.clone(), AssetInfo::native("uosmo"))
+            .unwrap();
+        assert_eq!(local, expected);
+
+        // The simulation can be used as min_out
+        let uosmo_before = bank_balance_query(&runner, admin.address(), "uosmo".into()).unwrap();
+        let res = pool
+            .swap(deps, &env, offer, AssetInfo::native("uosmo"), expected)
+            .unwrap();
+        runner
+            .execute_cosmos_msgs::<MsgData>(
+                &res.messages.into_iter().map(|m| m.msg).collect::<Vec<_>>(),
+                &admin,
+            )
+            .unwrap();
+        let uosmo_after = bank_balance_query(&runner, admin.address(), "uosmo".into()).unwrap();
+        assert_eq!(uosmo_after - uosmo_before, expected);
+        assert_eq!(
+            bank_balance_query(&runner, TAKER_FEE_COLLECTOR_ADDR.into(), "uatom".into()).unwrap(),
+            Uint128::new(15)
+        );
+    }
+}
@@ -1,9 +1,19 @@
-use cosmwasm_std::{Coin, Uint128};
+use std::cell::RefCell;
+use std::path::Path;
+
+use apollo_cw_asset::AssetInfo;
+use cosmwasm_std::{from_json, BankMsg, Binary, Coin, CosmosMsg, StdResult, Uint128, WasmMsg};
 use cw_dex_test_contract::msg::ExecuteMsg;
 use cw_it::helpers::bank_balance_query;
-use cw_it::test_tube::{Module, SigningAccount, Wasm};
+use cw_it::osmosis_std::types::cosmwasm::wasm::v1::{
+    QuerySmartContractStateRequest, QuerySmartContractStateResponse,
+};
+use cw_it::test_tube::cosmrs::proto::cosmwasm::wasm::v1::MsgExecuteContractResponse;
+use cw_it::test_tube::{Account, Module, Runner, SigningAccount, Wasm};
 use cw_it::traits::CwItRunner;
 
+use crate::query_asset_balance;
+
 #[cfg(feature = "osmosis")]
 use cw_it::helpers::upload_wasm_file;
 
@@ -16,6 +26,98 @@ use cw_it::ContractType;
 #[cfg(feature = "osmosis")]
 use cw_it::osmosis_test_tube::OsmosisTestApp;
 
+/// The gas used by a single operation executed through the robot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GasEntry {
+    pub operation: String,
+    pub gas_used: u64,
+}
+
+/// The gas used per operation executed through a [`CwDexTestRobot`], used to
+/// track gas regressions of the trait implementations.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GasReport {
+    pub entries: Vec<GasEntry>,
+}
+
+impl GasReport {
+    pub fn record(&mut self, operation: impl Into<String>, gas_used: u64) {
+        self.entries.push(GasEntry {
+            operation: operation.into(),
+            gas_used,
+        });
+    }
+
+    /// Returns the names of the recorded operations, in the order they were
+    /// first executed.
+    pub fn operations(&self) -> Vec<String> {
+        let mut operations: Vec<String> = vec![];
+        for entry in &self.entries {
+            if !operations.contains(&entry.operation) {
+                operations.push(entry.operation.clone());
+            }
+        }
+        operations
+    }
+
+    /// Returns the maximum gas used by any execution of `operation`, or `None`
+    /// if it was never executed.
+    pub fn max_gas_used(&self, operation: &str) -> Option<u64> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.operation == operation)
+            .map(|entry| entry.gas_used)
+            .max()
+    }
+
+    /// Returns a markdown table comparing the maximum gas used per operation
+    /// across the reports of different runners, e.g.
+    /// `[("multi-test", &report_a), ("osmosis-test-tube", &report_b)]`.
+    pub fn comparison_table(reports: &[(&str, &GasReport)]) -> String {
+        let mut operations: Vec<String> = vec![];
+        for (_, report) in reports {
+            for operation in report.operations() {
+                if !operations.contains(&operation) {
+                    operations.push(operation);
+                }
+            }
+        }
+
+        let mut table = format!(
+            "| operation | {} |\n|---|{}\n",
+            reports
+                .iter()
+                .map(|(runner, _)| *runner)
+                .collect::<Vec<_>>()
+                .join(" | "),
+            "---|".repeat(reports.len())
+        );
+        for operation in operations {
+            let row = reports
+                .iter()
+                .map(|(_, report)| {
+                    report
+                        .max_gas_used(&operation)
+                        .map(|gas| gas.to_string())
+                        .unwrap_or_else(|| "-".to_string())
+                })
+                .collect::<Vec<_>>()
+                .join(" | ");
+            table.push_str(&format!("| {operation} | {row} |\n"));
+        }
+        table
+    }
+
+    /// Writes the table returned by [`GasReport::comparison_table`] to `path`,
+    /// e.g. to be uploaded as a CI artifact.
+    pub fn write_comparison_table(
+        path: impl AsRef<Path>,
+        reports: &[(&str, &GasReport)],
+    ) -> std::io::Result<()> {
+        std::fs::write(path, Self::comparison_table(reports))
+    }
+}
+
 pub struct CwDexTestRobot<'a, R>
 where
     R: CwItRunner<'a>,
@@ -23,6 +125,7 @@ where
     pub app: &'a R,
     pub test_contract_addr: String,
     pub pool_id: u64,
+    pub gas_report: RefCell<GasReport>,
 }
 
 #[cfg(feature = "osmosis")]
@@ -47,6 +150,7 @@ impl<'a> CwDexTestRobot<'a, OsmosisTestApp> {
             app,
             test_contract_addr,
             pool_id: init_msg.pool_id,
+            gas_report: RefCell::new(GasReport::default()),
         }
     }
 
@@ -60,6 +164,44 @@ impl<'a, R> CwDexTestRobot<'a, R>
 where
     R: CwItRunner<'a>,
 {
+    /// Executes `msg` on the test contract and records the gas used under
+    /// `operation` in the gas report.
+    fn execute(
+        &self,
+        operation: &str,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+        signer: &SigningAccount,
+    ) -> &Self {
+        let wasm = Wasm::new(self.app);
+        let res = wasm
+            .execute(&self.test_contract_addr, msg, funds, signer)
+            .unwrap();
+        self.gas_report
+            .borrow_mut()
+            .record(operation, res.gas_info.gas_used);
+        self
+    }
+
+    /// Returns a copy of the gas used per operation executed so far.
+    pub fn gas_report(&self) -> GasReport {
+        self.gas_report.borrow().clone()
+    }
+
+    /// Asserts that no execution of `operation` used more than `max_gas`.
+    pub fn assert_gas_used_below(&self, operation: &str, max_gas: u64) -> &Self {
+        let gas_used = self
+            .gas_report
+            .borrow()
+            .max_gas_used(operation)
+            .unwrap_or_else(|| panic!("operation {operation} was never executed"));
+        assert!(
+            gas_used <= max_gas,
+            "{operation} used {gas_used} gas, expected at most {max_gas}"
+        );
+        self
+    }
+
     pub fn assert_native_balance(
         &self,
         address: String,
@@ -77,9 +219,8 @@ where
     }
 
     pub fn stake(&self, signer: &SigningAccount, amount: Uint128) -> &Self {
-        let wasm = Wasm::new(self.app);
-        wasm.execute(
-            &self.test_contract_addr,
+        self.execute(
+            "stake",
             &ExecuteMsg::Stake { amount },
             &[Coin {
                 amount,
@@ -87,26 +228,15 @@ where
             }],
             signer,
         )
-        .unwrap();
-        self
     }
 
     pub fn unlock(&self, signer: &SigningAccount, amount: Uint128) -> &Self {
-        let wasm = Wasm::new(self.app);
-        wasm.execute(
-            &self.test_contract_addr,
-            &ExecuteMsg::Unlock { amount },
-            &[],
-            signer,
-        )
-        .unwrap();
-        self
+        self.execute("unlock", &ExecuteMsg::Unlock { amount }, &[], signer)
     }
 
     pub fn superfluid_stake(&self, signer: &SigningAccount, amount: Uint128) -> &Self {
-        let wasm = Wasm::new(self.app);
-        wasm.execute(
-            &self.test_contract_addr,
+        self.execute(
+            "superfluid_stake",
             &ExecuteMsg::SuperfluidStake { amount },
             &[Coin {
                 amount,
@@ -114,31 +244,367 @@ where
             }],
             signer,
         )
-        .unwrap();
-        self
     }
 
     pub fn superfluid_unlock(&self, signer: &SigningAccount, amount: Uint128) -> &Self {
-        let wasm = Wasm::new(self.app);
-        wasm.execute(
-            &self.test_contract_addr,
+        self.execute(
+            "superfluid_unlock",
             &ExecuteMsg::SuperfluidUnlock { amount },
             &[],
             signer,
         )
-        .unwrap();
-        self
     }
 
     pub fn withdraw_unlocked(&self, signer: &SigningAccount, amount: Uint128) -> &Self {
-        let wasm = Wasm::new(self.app);
-        wasm.execute(
-            &self.test_contract_addr,
+        self.execute(
+            "withdraw_unlocked",
             &ExecuteMsg::WithdrawUnlocked { amount },
             &[],
             signer,
         )
-        .unwrap();
+    }
+}
+
+/// The messages of a vault contract run through a [`Scenario`]. Implemented
+/// per vault and venue, so that the same scenario runs against the vaults of
+/// all venues.
+pub trait ScenarioVault {
+    /// Returns the address of the vault contract.
+    fn address(&self) -> String;
+
+    /// Returns the execute message depositing the funds sent with it.
+    fn deposit_msg(&self) -> StdResult<Binary>;
+
+    /// Returns the execute message compounding the assets held by the vault.
+    fn compound_msg(&self) -> StdResult<Binary>;
+
+    /// Returns the execute message withdrawing `shares` of the sender.
+    fn withdraw_msg(&self, shares: Uint128) -> StdResult<Binary>;
+
+    /// Returns the query message returning the shares of `address` as a
+    /// `Uint128`.
+    fn shares_query(&self, address: &str) -> StdResult<Binary>;
+
+    /// Returns the assets received when withdrawing, whose balance changes are
+    /// recorded in [`StepOutcome::received`].
+    fn withdrawn_assets(&self) -> Vec<AssetInfo>;
+}
+
+/// A step of a [`Scenario`]. Accounts are indices into the accounts the
+/// scenario is run with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Deposits `funds` into the vault from `account`
+    Deposit { account: usize, funds: Vec<Coin> },
+    /// Compounds the vault, executed by `account`
+    Compound { account: usize },
+    /// Withdraws `shares` of `account`, or all of its shares if `None`
+    Withdraw {
+        account: usize,
+        shares: Option<Uint128>,
+    },
+    /// Sends `funds` from the funder of the scenario to the vault, e.g. to
+    /// emulate claimed rewards
+    InjectRewards { funds: Vec<Coin> },
+    /// Advances the block time by `seconds`
+    IncreaseTime { seconds: u64 },
+    /// Executes `msg` on the vault from `account`, e.g. for steps specific to
+    /// a vault like claiming unbonded assets. The gas used is recorded under
+    /// `operation`.
+    Execute {
+        account: usize,
+        operation: String,
+        msg: Binary,
+        funds: Vec<Coin>,
+    },
+    /// Expects the step to fail
+    Fails(Box<Step>),
+}
+
+impl Step {
+    /// Returns the name the gas used by the step is recorded under.
+    pub fn operation(&self) -> String {
+        match self {
+            Step::Deposit { .. } => "deposit".to_string(),
+            Step::Compound { .. } => "compound".to_string(),
+            Step::Withdraw { .. } => "withdraw".to_string(),
+            Step::InjectRewards { .. } => "inject_rewards".to_string(),
+            Step::IncreaseTime { .. } => "increase_time".to_string(),
+            Step::Execute { operation, .. } => operation.clone(),
+            Step::Fails(step) => step.operation(),
+        }
+    }
+}
+
+/// The state after a step of a [`Scenario`], see [`ScenarioReport`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepOutcome {
+    /// The step executed
+    pub step: Step,
+    /// The shares of each account after the step
+    pub shares: Vec<Uint128>,
+    /// The increase of the balances of [`ScenarioVault::withdrawn_assets`] of
+    /// the account executing the step, in the same order
+    pub received: Vec<Uint128>,
+    /// The block time in nanoseconds after the step
+    pub block_time_nanos: u64,
+}
+
+/// The outcomes of the steps of a [`Scenario`] and the gas used, as returned by
+/// [`Scenario::run`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioReport {
+    pub outcomes: Vec<StepOutcome>,
+    pub gas_report: GasReport,
+}
+
+impl ScenarioReport {
+    /// Returns the outcome of the step at `index`.
+    pub fn outcome(&self, index: usize) -> &StepOutcome {
+        &self.outcomes[index]
+    }
+
+    /// Returns the outcome of the last step.
+    pub fn last(&self) -> &StepOutcome {
+        self.outcomes.last().expect("scenario has no steps")
+    }
+
+    /// Returns the shares of `account` after the step at `index`.
+    pub fn shares(&self, index: usize, account: usize) -> Uint128 {
+        self.outcome(index).shares[account]
+    }
+}
+
+/// A sequence of steps through a vault, e.g. deposit, compound and withdraw
+/// with time travel and reward injection in between, to express regression
+/// scenarios compactly and run them against any venue and runner:
+///
+/// ```ignore
+/// let report = Scenario::new("compound after rewards")
+///     .deposit(0, coins(10_000, "uatom"))
+///     .inject_rewards(coins(1_000, "uatom"))
+///     .increase_time(86_400)
+///     .compound(1)
+///     .withdraw_all(0)
+///     .run(&runner, &vault, &accounts, &funder);
+/// ```
+///
+/// Each step panics with the name of the scenario and the index of the step if
+/// it does not succeed, or if a step wrapped in [`Scenario::fails`] succeeds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    /// Returns a scenario without steps.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            steps: vec![],
+        }
+    }
+
+    /// Appends `step`.
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
         self
     }
+
+    /// Appends a [`Step::Deposit`].
+    pub fn deposit(self, account: usize, funds: Vec<Coin>) -> Self {
+        self.step(Step::Deposit { account, funds })
+    }
+
+    /// Appends a [`Step::Compound`].
+    pub fn compound(self, account: usize) -> Self {
+        self.step(Step::Compound { account })
+    }
+
+    /// Appends a [`Step::Withdraw`] of `shares`.
+    pub fn withdraw(self, account: usize, shares: impl Into<Uint128>) -> Self {
+        self.step(Step::Withdraw {
+            account,
+            shares: Some(shares.into()),
+        })
+    }
+
+    /// Appends a [`Step::Withdraw`] of all shares of `account`.
+    pub fn withdraw_all(self, account: usize) -> Self {
+        self.step(Step::Withdraw {
+            account,
+            shares: None,
+        })
+    }
+
+    /// Appends a [`Step::InjectRewards`].
+    pub fn inject_rewards(self, funds: Vec<Coin>) -> Self {
+        self.step(Step::InjectRewards { funds })
+    }
+
+    /// Appends a [`Step::IncreaseTime`].
+    pub fn increase_time(self, seconds: u64) -> Self {
+        self.step(Step::IncreaseTime { seconds })
+    }
+
+    /// Marks the last step as expected to fail.
+    pub fn fails(mut self) -> Self {
+        let step = self.steps.pop().expect("scenario has no steps");
+        self.step(Step::Fails(Box::new(step)))
+    }
+
+    /// Runs the steps against `vault`. `accounts` are the accounts the steps
+    /// refer to by index, and `funder` sends the injected rewards.
+    pub fn run<'a, R>(
+        &self,
+        runner: &'a R,
+        vault: &impl ScenarioVault,
+        accounts: &[&SigningAccount],
+        funder: &SigningAccount,
+    ) -> ScenarioReport
+    where
+        R: CwItRunner<'a>,
+    {
+        let mut report = ScenarioReport::default();
+        for (index, step) in self.steps.iter().enumerate() {
+            let context = format!("scenario {:?}, step {index} ({step:?})", self.name);
+            let (expect_failure, inner) = match step {
+                Step::Fails(inner) => (true, inner.as_ref()),
+                step => (false, step),
+            };
+            let account = step_account(inner);
+            let before =
+                account.map(|account| balances(runner, vault, &accounts[account].address()));
+
+            match (
+                run_step(runner, vault, accounts, funder, inner),
+                expect_failure,
+            ) {
+                (Ok(gas_used), false) => {
+                    if let Some(gas_used) = gas_used {
+                        report.gas_report.record(inner.operation(), gas_used);
+                    }
+                }
+                (Err(err), false) => panic!("{context} failed: {err}"),
+                (Ok(_), true) => panic!("{context} succeeded, expected it to fail"),
+                (Err(_), true) => {}
+            }
+
+            let received = match (account, before) {
+                (Some(account), Some(before)) => {
+                    balances(runner, vault, &accounts[account].address())
+                        .into_iter()
+                        .zip(before)
+                        .map(|(after, before)| after.saturating_sub(before))
+                        .collect()
+                }
+                _ => vec![],
+            };
+            report.outcomes.push(StepOutcome {
+                step: step.clone(),
+                shares: accounts
+                    .iter()
+                    .map(|account| query_shares(runner, vault, &account.address()))
+                    .collect(),
+                received,
+                block_time_nanos: runner.query_block_time_nanos(),
+            });
+        }
+        report
+    }
+}
+
+/// Executes `step`, returning the gas used if it executed a message.
+fn run_step<'a, R>(
+    runner: &'a R,
+    vault: &impl ScenarioVault,
+    accounts: &[&SigningAccount],
+    funder: &SigningAccount,
+    step: &Step,
+) -> Result<Option<u64>, String>
+where
+    R: CwItRunner<'a>,
+{
+    let execute = |account: usize, msg: StdResult<Binary>, funds: &[Coin]| {
+        let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: vault.address(),
+            msg: msg.map_err(|err| err.to_string())?,
+            funds: funds.to_vec(),
+        });
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(&[msg], accounts[account])
+            .map(|res| Some(res.gas_info.gas_used))
+            .map_err(|err| err.to_string())
+    };
+
+    match step {
+        Step::Deposit { account, funds } => execute(*account, vault.deposit_msg(), funds),
+        Step::Compound { account } => execute(*account, vault.compound_msg(), &[]),
+        Step::Withdraw { account, shares } => {
+            let shares = shares
+                .unwrap_or_else(|| query_shares(runner, vault, &accounts[*account].address()));
+            execute(*account, vault.withdraw_msg(shares), &[])
+        }
+        Step::InjectRewards { funds } => {
+            let msg = CosmosMsg::Bank(BankMsg::Send {
+                to_address: vault.address(),
+                amount: funds.clone(),
+            });
+            runner
+                .execute_cosmos_msgs::<MsgExecuteContractResponse>(&[msg], funder)
+                .map(|_| None)
+                .map_err(|err| err.to_string())
+        }
+        Step::IncreaseTime { seconds } => runner
+            .increase_time(*seconds)
+            .map(|_| None)
+            .map_err(|err| err.to_string()),
+        Step::Execute {
+            account,
+            msg,
+            funds,
+            ..
+        } => execute(*account, Ok(msg.clone()), funds),
+        Step::Fails(step) => run_step(runner, vault, accounts, funder, step),
+    }
+}
+
+/// Returns the account executing `step`, if any.
+fn step_account(step: &Step) -> Option<usize> {
+    match step {
+        Step::Deposit { account, .. }
+        | Step::Compound { account }
+        | Step::Withdraw { account, .. }
+        | Step::Execute { account, .. } => Some(*account),
+        Step::InjectRewards { .. } | Step::IncreaseTime { .. } => None,
+        Step::Fails(step) => step_account(step),
+    }
+}
+
+fn balances<'a, R>(runner: &'a R, vault: &impl ScenarioVault, address: &str) -> Vec<Uint128>
+where
+    R: Runner<'a>,
+{
+    vault
+        .withdrawn_assets()
+        .iter()
+        .map(|info| query_asset_balance(runner, info, address))
+        .collect()
+}
+
+fn query_shares<'a, R>(runner: &'a R, vault: &impl ScenarioVault, address: &str) -> Uint128
+where
+    R: Runner<'a>,
+{
+    let res = runner
+        .query::<QuerySmartContractStateRequest, QuerySmartContractStateResponse>(
+            "/cosmwasm.wasm.v1.Query/SmartContractState",
+            &QuerySmartContractStateRequest {
+                address: vault.address(),
+                query_data: vault.shares_query(address).unwrap().to_vec(),
+            },
+        )
+        .unwrap();
+    from_json(res.data).unwrap()
 }
@@ -0,0 +1,183 @@
+//! Checks that the simulations of Astroport PCL (concentrated) pairs match the
+//! amounts actually received on execution, for a number of pair parameter
+//! sets. The snapshotted amounts catch changes in the pair math when bumping
+//! the astroport dependencies.
+//!
+//! The liquidity manager does not implement `SimulateProvide` for PCL pairs,
+//! so only the LP tokens received from providing liquidity are snapshotted.
+#![cfg(feature = "astroport")]
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use astroport::factory::PairType;
+use cosmwasm_std::{coin, to_json_binary, Coin, Uint128};
+use cw_dex_test_contract::msg::{ExecuteMsg, QueryMsg};
+use cw_dex_test_helpers::astroport::setup_pool_and_test_contract_with_params;
+use cw_dex_test_helpers::{cw20_balance_query, pcl_param_sets, query_asset_balance};
+use cw_it::multi_test::MultiTestRunner;
+use cw_it::test_tube::cosmrs::proto::cosmwasm::wasm::v1::MsgExecuteContractResponse;
+use cw_it::test_tube::{Module, Runner, SigningAccount, Wasm};
+use cw_it::{OwnedTestRunner, TestRunner};
+use test_case::test_case;
+
+const TEST_CONTRACT_WASM_FILE_PATH: &str =
+    "../target/wasm32-unknown-unknown/release/astroport_test_contract.wasm";
+
+/// Expected amounts of one parameter set
+struct Snapshot {
+    /// LP tokens received when providing 10_000 uluna and the matching amount
+    /// of uatom
+    balanced_lp: u128,
+    /// LP tokens received when providing only 10_000 uluna
+    single_sided_lp: u128,
+    /// uatom received when swapping 10_000 uluna
+    swap_out: u128,
+    /// uluna and uatom received when withdrawing all LP tokens
+    withdraw_out: [u128; 2],
+}
+
+fn setup<'a>(runner: &'a TestRunner<'a>, set: &str) -> (SigningAccount, String, String, u64) {
+    let (_, params) = pcl_param_sets()
+        .into_iter()
+        .find(|(name, _)| *name == set)
+        .unwrap();
+    // Provide the initial liquidity at the price scale of the pair
+    let price = (params.price_scale * Uint128::new(1_000_000)).u128() as u64;
+    let (accs, lp_token_addr, _pair_addr, contract_addr, _asset_list, _) =
+        setup_pool_and_test_contract_with_params(
+            runner,
+            PairType::Custom("concentrated".to_string()),
+            vec![
+                ("uluna", 1_000_000),
+                ("uatom", 1_000_000 * 1_000_000 / price),
+            ],
+            2,
+            TEST_CONTRACT_WASM_FILE_PATH,
+            Some(to_json_binary(&params).unwrap()),
+        )
+        .unwrap();
+    (
+        accs.into_iter().next().unwrap(),
+        lp_token_addr,
+        contract_addr,
+        price,
+    )
+}
+
+#[test_case("common", Snapshot { balanced_lp: 10000, single_sided_lp: 4992, swap_out: 9950, withdraw_out: [15212, 14770] }; "common")]
+#[test_case("high_fee", Snapshot { balanced_lp: 10000, single_sided_lp: 4969, swap_out: 9788, withdraw_out: [15189, 14750] }; "high_fee")]
+#[test_case("low_amp", Snapshot { balanced_lp: 10000, single_sided_lp: 4991, swap_out: 9910, withdraw_out: [15211, 14769] }; "low_amp")]
+#[test_case("price_scale_2", Snapshot { balanced_lp: 7071, single_sided_lp: 3530, swap_out: 4975, withdraw_out: [15212, 7384] }; "price_scale_2")]
+fn test_pcl_simulation_parity(set: &str, snapshot: Snapshot) {
+    let owned_runner = OwnedTestRunner::MultiTest(MultiTestRunner::new("osmo"));
+    let runner = owned_runner.as_ref();
+    let (admin, lp_token_addr, contract_addr, price) = setup(&runner, set);
+    let wasm = Wasm::new(&runner);
+
+    let provides: [(Vec<Coin>, u128); 2] = [
+        (
+            vec![
+                coin(10_000, "uluna"),
+                coin(10_000 * 1_000_000 / price as u128, "uatom"),
+            ],
+            snapshot.balanced_lp,
+        ),
+        (vec![coin(10_000, "uluna")], snapshot.single_sided_lp),
+    ];
+    for (funds, expected_lp) in provides {
+        let lp_before =
+            cw20_balance_query(&runner, lp_token_addr.clone(), contract_addr.clone()).unwrap();
+        runner
+            .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+                &[ExecuteMsg::ProvideLiquidity {
+                    assets: funds.clone().into(),
+                    min_out: Uint128::one(),
+                }
+                .into_cosmos_msg(contract_addr.clone(), funds)],
+                &admin,
+            )
+            .unwrap();
+        let lp_after =
+            cw20_balance_query(&runner, lp_token_addr.clone(), contract_addr.clone()).unwrap();
+
+        assert_eq!(
+            (lp_after - lp_before).u128(),
+            expected_lp,
+            "{set}: provide snapshot"
+        );
+    }
+
+    // Swap
+    let offer = Asset::native("uluna", 10_000u128);
+    let ask = AssetInfo::native("uatom");
+    let simulated: Uint128 = wasm
+        .query(
+            &contract_addr,
+            &QueryMsg::SimulateSwap {
+                offer: offer.clone(),
+                ask: ask.clone(),
+            },
+        )
+        .unwrap();
+
+    let balance_before = query_asset_balance(&runner, &ask, &contract_addr);
+    runner
+        .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+            &[ExecuteMsg::Swap {
+                offer,
+                ask: ask.clone(),
+                min_out: simulated,
+            }
+            .into_cosmos_msg(contract_addr.clone(), vec![coin(10_000, "uluna")])],
+            &admin,
+        )
+        .unwrap();
+    let balance_after = query_asset_balance(&runner, &ask, &contract_addr);
+
+    assert_eq!(
+        balance_after - balance_before,
+        simulated,
+        "{set}: swap parity"
+    );
+    assert_eq!(simulated.u128(), snapshot.swap_out, "{set}: swap snapshot");
+
+    // Withdraw
+    let lp_amount =
+        cw20_balance_query(&runner, lp_token_addr.clone(), contract_addr.clone()).unwrap();
+    let simulated: AssetList = wasm
+        .query(
+            &contract_addr,
+            &QueryMsg::SimulateWithdrawLiquidty { amount: lp_amount },
+        )
+        .unwrap();
+
+    let assets = [AssetInfo::native("uluna"), AssetInfo::native("uatom")];
+    let balances_before = assets
+        .iter()
+        .map(|info| query_asset_balance(&runner, info, &contract_addr))
+        .collect::<Vec<_>>();
+    runner
+        .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+            &[ExecuteMsg::WithdrawLiquidity {
+                amount: lp_amount,
+                min_out: simulated.clone(),
+            }
+            .into_cosmos_msg(contract_addr.clone(), vec![])],
+            &admin,
+        )
+        .unwrap();
+
+    for ((info, before), expected) in assets
+        .iter()
+        .zip(balances_before)
+        .zip(snapshot.withdraw_out)
+    {
+        let received = query_asset_balance(&runner, info, &contract_addr) - before;
+        let simulated = simulated.find(info).map(|a| a.amount).unwrap_or_default();
+        assert_eq!(received, simulated, "{set}: withdraw parity of {info}");
+        assert_eq!(
+            received.u128(),
+            expected,
+            "{set}: withdraw snapshot of {info}"
+        );
+    }
+}
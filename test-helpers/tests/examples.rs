@@ -0,0 +1,622 @@
+//! Runs the example contracts in `examples/` against pools of the supported
+//! dexes, so that they keep compiling against the published API and keep
+//! working as integration tests of it. Osmosis runs on multi-test with the
+//! Stargate shim of `osmosis_multi_test` emulating pools.
+#![cfg(all(feature = "astroport", feature = "osmosis-multi-test"))]
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use astroport::factory::PairType;
+use cosmwasm_std::{
+    coin, coins, to_json_binary, Addr, BankMsg, Binary, Coin, Decimal, StdResult, Uint128,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_dex::composite::ZapOutSimulation;
+use cw_dex_config::{
+    AstroportPoolConfig, AstroportStakingConfig, OsmosisPoolConfig, PoolConfig, StakingConfig,
+};
+use cw_dex_test_helpers::astroport::setup_pool_and_test_contract;
+use cw_dex_test_helpers::osmosis_multi_test::OsmosisPoolsModule;
+use cw_dex_test_helpers::query_asset_balance;
+use cw_dex_test_helpers::robot::{Scenario, ScenarioVault};
+use cw_it::cw_multi_test::ContractWrapper;
+use cw_it::helpers::upload_wasm_file;
+use cw_it::multi_test::MultiTestRunner;
+use cw_it::osmosis_std::types::osmosis::gamm::poolmodels::balancer::v1beta1::{
+    MsgCreateBalancerPool, MsgCreateBalancerPoolResponse,
+};
+use cw_it::osmosis_std::types::osmosis::gamm::v1beta1::{PoolAsset, PoolParams};
+use cw_it::test_tube::cosmrs::proto::cosmwasm::wasm::v1::MsgExecuteContractResponse;
+use cw_it::test_tube::{Account, Module, Runner, SigningAccount, Wasm};
+use cw_it::traits::CwItRunner;
+use cw_it::{ContractType, OwnedTestRunner, TestRunner};
+use serde::Serialize;
+use test_case::test_case;
+
+/// The dexes the example contracts are run against
+#[derive(Clone, Copy, Debug)]
+enum Venue {
+    Osmosis,
+    Astroport,
+}
+
+/// A uatom/uluna pool with 1_000_000 of each asset and two funded accounts
+struct Setup {
+    admin: SigningAccount,
+    user: SigningAccount,
+    pool: PoolConfig,
+    /// Staking of the LP tokens, only available on Astroport as the Osmosis
+    /// shim does not emulate the lockup module
+    staking: Option<StakingConfig>,
+    lp_token: AssetInfo,
+}
+
+impl Venue {
+    fn runner(self) -> OwnedTestRunner<'static> {
+        match self {
+            Venue::Osmosis => OwnedTestRunner::MultiTest(OsmosisPoolsModule::runner("osmo")),
+            Venue::Astroport => OwnedTestRunner::MultiTest(MultiTestRunner::new("osmo")),
+        }
+    }
+
+    fn setup<'a>(self, runner: &'a TestRunner<'a>) -> Setup {
+        // Not the maximum balance, so that receiving assets does not overflow
+        let balances = [coin(u128::MAX / 2, "uatom"), coin(u128::MAX / 2, "uluna")];
+        let admin = runner.init_account(&balances).unwrap();
+        let user = runner.init_account(&balances).unwrap();
+        match self {
+            Venue::Osmosis => {
+                let msg = MsgCreateBalancerPool {
+                    sender: admin.address(),
+                    pool_params: Some(PoolParams {
+                        swap_fee: "0.003".to_string(),
+                        exit_fee: "0".to_string(),
+                        smooth_weight_change_params: None,
+                    }),
+                    pool_assets: [coin(1_000_000, "uatom"), coin(1_000_000, "uluna")]
+                        .into_iter()
+                        .map(|c| PoolAsset {
+                            token: Some(c.into()),
+                            weight: "1".to_string(),
+                        })
+                        .collect(),
+                    future_pool_governor: String::new(),
+                };
+                let pool_id = runner
+                    .execute_cosmos_msgs::<MsgCreateBalancerPoolResponse>(&[msg.into()], &admin)
+                    .unwrap()
+                    .data
+                    .pool_id;
+                Setup {
+                    admin,
+                    user,
+                    pool: PoolConfig::Osmosis(OsmosisPoolConfig { pool_id }),
+                    staking: None,
+                    lp_token: AssetInfo::native(format!("gamm/pool/{pool_id}")),
+                }
+            }
+            Venue::Astroport => {
+                let (_, lp_token_addr, pair_addr, _, _, astroport_contracts) =
+                    setup_pool_and_test_contract(
+                        runner,
+                        PairType::Xyk {},
+                        vec![("uatom", 1_000_000), ("uluna", 1_000_000)],
+                        2,
+                        "",
+                    )
+                    .unwrap();
+                Setup {
+                    admin,
+                    user,
+                    pool: PoolConfig::Astroport(AstroportPoolConfig {
+                        pair_addr,
+                        liquidity_manager: astroport_contracts.liquidity_manager.address,
+                    }),
+                    staking: Some(StakingConfig::Astroport(AstroportStakingConfig {
+                        lp_token_addr: lp_token_addr.clone(),
+                        incentives: astroport_contracts.incentives.address,
+                        strict_rewards: false,
+                        cw20_adapter: None,
+                    })),
+                    lp_token: AssetInfo::cw20(Addr::unchecked(lp_token_addr)),
+                }
+            }
+        }
+    }
+}
+
+/// Uploads and instantiates an example contract
+fn instantiate<'a>(
+    runner: &'a TestRunner<'a>,
+    admin: &SigningAccount,
+    contract: ContractType,
+    msg: &impl Serialize,
+) -> String {
+    let code_id = upload_wasm_file(runner, admin, contract).unwrap();
+    Wasm::new(runner)
+        .instantiate(code_id, msg, None, Some("example"), &[], admin)
+        .unwrap()
+        .data
+        .address
+}
+
+fn send_coins(runner: &TestRunner, from: &SigningAccount, to: &str, amount: Vec<Coin>) {
+    runner
+        .execute_cosmos_msgs::<MsgExecuteContractResponse>(
+            &[BankMsg::Send {
+                to_address: to.to_string(),
+                amount,
+            }
+            .into()],
+            from,
+        )
+        .unwrap();
+}
+
+fn balances(runner: &TestRunner, address: &str, infos: &[&AssetInfo]) -> Vec<Uint128> {
+    infos
+        .iter()
+        .map(|info| query_asset_balance(runner, info, address))
+        .collect()
+}
+
+#[test_case(Venue::Osmosis; "osmosis")]
+#[test_case(Venue::Astroport; "astroport")]
+fn test_zap_router(venue: Venue) {
+    use zap_router_example::msg::{ExecuteMsg, InstantiateMsg, PoolResponse, QueryMsg, ReceiveMsg};
+
+    let owned_runner = venue.runner();
+    let runner = owned_runner.as_ref();
+    let setup = venue.setup(&runner);
+    let wasm = Wasm::new(&runner);
+    let uatom = AssetInfo::native("uatom");
+    let uluna = AssetInfo::native("uluna");
+
+    let contract = ContractType::MultiTestContract(Box::new(
+        ContractWrapper::new_with_empty(
+            zap_router_example::contract::execute,
+            zap_router_example::contract::instantiate,
+            zap_router_example::contract::query,
+        )
+        .with_reply(zap_router_example::contract::reply),
+    ));
+    let router = instantiate(
+        &runner,
+        &setup.admin,
+        contract,
+        &InstantiateMsg {
+            pools: vec![setup.pool.clone()],
+        },
+    );
+    let pools: Vec<PoolResponse> = wasm.query(&router, &QueryMsg::Pools {}).unwrap();
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].lp_token, setup.lp_token);
+
+    // Zap in with uatom only, receiving the LP tokens
+    let user = setup.user.address();
+    wasm.execute(
+        &router,
+        &ExecuteMsg::ZapIn {
+            lp_token: setup.lp_token.clone(),
+            min_lp_out: Uint128::one(),
+            recipient: None,
+        },
+        &coins(10_000, "uatom"),
+        &setup.user,
+    )
+    .unwrap();
+    let lp_amount = query_asset_balance(&runner, &setup.lp_token, &user);
+    assert!(!lp_amount.is_zero());
+    // The router holds nothing after the zap
+    assert!(
+        balances(&runner, &router, &[&setup.lp_token, &uatom, &uluna])
+            .iter()
+            .all(Uint128::is_zero)
+    );
+
+    // Zap out into uluna, receiving at least the worst case of the simulation
+    let lp_token = Asset::new(setup.lp_token.clone(), lp_amount);
+    let simulation: ZapOutSimulation = wasm
+        .query(
+            &router,
+            &QueryMsg::SimulateZapOut {
+                lp_token: lp_token.clone(),
+                ask: uluna.clone(),
+            },
+        )
+        .unwrap();
+    assert!(simulation.worst_case <= simulation.expected);
+    let zap_out = ReceiveMsg::ZapOut {
+        ask: uluna.clone(),
+        min_out: simulation.worst_case,
+        recipient: None,
+    };
+    let uluna_before = query_asset_balance(&runner, &uluna, &user);
+    match &setup.lp_token {
+        AssetInfo::Native(denom) => {
+            let ReceiveMsg::ZapOut {
+                ask,
+                min_out,
+                recipient,
+            } = zap_out;
+            wasm.execute(
+                &router,
+                &ExecuteMsg::ZapOut {
+                    ask,
+                    min_out,
+                    recipient,
+                },
+                &coins(lp_amount.u128(), denom),
+                &setup.user,
+            )
+            .unwrap();
+        }
+        AssetInfo::Cw20(addr) => {
+            wasm.execute(
+                addr.as_str(),
+                &Cw20ExecuteMsg::Send {
+                    contract: router.clone(),
+                    amount: lp_amount,
+                    msg: to_json_binary(&zap_out).unwrap(),
+                },
+                &[],
+                &setup.user,
+            )
+            .unwrap();
+        }
+    }
+    let received = query_asset_balance(&runner, &uluna, &user) - uluna_before;
+    assert!(received >= simulation.worst_case);
+    assert!(received <= simulation.expected);
+    assert!(query_asset_balance(&runner, &setup.lp_token, &user).is_zero());
+    assert!(
+        balances(&runner, &router, &[&setup.lp_token, &uatom, &uluna])
+            .iter()
+            .all(Uint128::is_zero)
+    );
+
+    // Zapping into a pool the router does not know fails
+    wasm.execute(
+        &router,
+        &ExecuteMsg::ZapIn {
+            lp_token: AssetInfo::native("unknown"),
+            min_lp_out: Uint128::zero(),
+            recipient: None,
+        },
+        &coins(10_000, "uatom"),
+        &setup.user,
+    )
+    .unwrap_err();
+}
+
+#[test_case(Venue::Osmosis; "osmosis")]
+#[test_case(Venue::Astroport; "astroport")]
+fn test_pol_manager(venue: Venue) {
+    use pol_manager_example::msg::{ExecuteMsg, InstantiateMsg, PositionResponse, QueryMsg};
+
+    let owned_runner = venue.runner();
+    let runner = owned_runner.as_ref();
+    let setup = venue.setup(&runner);
+    let wasm = Wasm::new(&runner);
+    let uatom = AssetInfo::native("uatom");
+    let uluna = AssetInfo::native("uluna");
+
+    let contract = ContractType::MultiTestContract(Box::new(ContractWrapper::new_with_empty(
+        pol_manager_example::contract::execute,
+        pol_manager_example::contract::instantiate,
+        pol_manager_example::contract::query,
+    )));
+    let manager = instantiate(
+        &runner,
+        &setup.admin,
+        contract,
+        &InstantiateMsg {
+            owner: setup.admin.address(),
+            pool: setup.pool.clone(),
+            staking: setup.staking.clone(),
+            max_slippage: Decimal::percent(1),
+        },
+    );
+
+    // Only the owner manages the liquidity
+    let assets: AssetList = vec![
+        Asset::new(uatom.clone(), 10_000u128),
+        Asset::new(uluna.clone(), 10_000u128),
+    ]
+    .into();
+    let funds = vec![coin(10_000, "uatom"), coin(10_000, "uluna")];
+    wasm.execute(
+        &manager,
+        &ExecuteMsg::AddLiquidity {
+            assets: assets.clone(),
+        },
+        &funds,
+        &setup.user,
+    )
+    .unwrap_err();
+
+    // Add liquidity with assets held by the manager, e.g. sent by a treasury
+    send_coins(&runner, &setup.admin, &manager, funds);
+    wasm.execute(
+        &manager,
+        &ExecuteMsg::AddLiquidity { assets },
+        &[],
+        &setup.admin,
+    )
+    .unwrap();
+    let position: PositionResponse = wasm.query(&manager, &QueryMsg::Position {}).unwrap();
+    let lp_amount = position.held + position.staked;
+    assert!(!lp_amount.is_zero());
+    // Only the minimum amount of LP tokens to receive is staked, the rest is
+    // held by the manager
+    match setup.staking {
+        Some(_) => assert!(position.held < position.staked / Uint128::new(50)),
+        None => assert!(position.staked.is_zero()),
+    }
+    for info in [&uatom, &uluna] {
+        let underlying = position.underlying.find(info).unwrap().amount.u128();
+        assert!((9_990..=10_000).contains(&underlying), "{underlying}");
+    }
+
+    // Claiming without pending rewards is a no-op
+    if setup.staking.is_some() {
+        wasm.execute(&manager, &ExecuteMsg::ClaimRewards {}, &[], &setup.admin)
+            .unwrap();
+    }
+
+    // Remove all liquidity to another account, which receives the underlying
+    // assets of the position
+    let recipient = setup.user.address();
+    let before = balances(&runner, &recipient, &[&uatom, &uluna]);
+    wasm.execute(
+        &manager,
+        &ExecuteMsg::RemoveLiquidity {
+            amount: None,
+            recipient: Some(recipient.clone()),
+        },
+        &[],
+        &setup.admin,
+    )
+    .unwrap();
+    let after = balances(&runner, &recipient, &[&uatom, &uluna]);
+    for ((info, before), after) in [&uatom, &uluna].into_iter().zip(before).zip(after) {
+        assert_eq!(
+            after - before,
+            position.underlying.find(info).unwrap().amount
+        );
+    }
+    let position: PositionResponse = wasm.query(&manager, &QueryMsg::Position {}).unwrap();
+    assert!(position.held.is_zero() && position.staked.is_zero());
+}
+
+/// Only run against Astroport, as the autocompounder requires staking
+#[test_case(Venue::Astroport; "astroport")]
+fn test_autocompounder(venue: Venue) {
+    use autocompounder_example::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse};
+
+    let owned_runner = venue.runner();
+    let runner = owned_runner.as_ref();
+    let setup = venue.setup(&runner);
+    let wasm = Wasm::new(&runner);
+    let uatom = AssetInfo::native("uatom");
+    let uluna = AssetInfo::native("uluna");
+
+    let contract = ContractType::MultiTestContract(Box::new(
+        ContractWrapper::new_with_empty(
+            autocompounder_example::contract::execute,
+            autocompounder_example::contract::instantiate,
+            autocompounder_example::contract::query,
+        )
+        .with_reply(autocompounder_example::contract::reply),
+    ));
+    let vault = instantiate(
+        &runner,
+        &setup.admin,
+        contract,
+        &InstantiateMsg {
+            pool: setup.pool.clone(),
+            staking: setup.staking.clone().unwrap(),
+            max_slippage: Decimal::percent(1),
+        },
+    );
+    let state = || -> StateResponse { wasm.query(&vault, &QueryMsg::State {}).unwrap() };
+    let shares_of = |account: &SigningAccount| -> Uint128 {
+        wasm.query(
+            &vault,
+            &QueryMsg::Shares {
+                address: account.address(),
+            },
+        )
+        .unwrap()
+    };
+
+    // Both accounts deposit the same assets and receive the same shares
+    for account in [&setup.admin, &setup.user] {
+        wasm.execute(
+            &vault,
+            &ExecuteMsg::Deposit { recipient: None },
+            &[coin(10_000, "uatom"), coin(10_000, "uluna")],
+            account,
+        )
+        .unwrap();
+    }
+    let shares = shares_of(&setup.user);
+    assert!(!shares.is_zero());
+    assert_eq!(shares_of(&setup.admin), shares);
+    // Only the minimum amount of LP tokens to receive is staked, the rest is
+    // held by the vault
+    let deposited = state();
+    assert_eq!(deposited.position.staked + deposited.held, shares + shares);
+    assert_eq!(
+        query_asset_balance(&runner, &setup.lp_token, &vault),
+        deposited.held
+    );
+    assert!(!deposited.held.is_zero());
+
+    // Compounding without rewards only provides the remainders of the
+    // deposits and stakes the held LP tokens, minting no shares
+    wasm.execute(&vault, &ExecuteMsg::Compound {}, &[], &setup.user)
+        .unwrap();
+    let deposited = state();
+    assert_eq!(deposited.total_shares, shares + shares);
+    assert!(deposited.held.is_zero());
+
+    // Assets of the pool held by the vault are compounded, both as a pair and
+    // as a single asset that is partly swapped
+    send_coins(
+        &runner,
+        &setup.admin,
+        &vault,
+        vec![coin(1_000, "uatom"), coin(1_000, "uluna")],
+    );
+    wasm.execute(&vault, &ExecuteMsg::Compound {}, &[], &setup.user)
+        .unwrap();
+    let compounded = state();
+    assert!(compounded.position.staked > deposited.position.staked);
+    send_coins(&runner, &setup.admin, &vault, coins(2_000, "uatom"));
+    wasm.execute(&vault, &ExecuteMsg::Compound {}, &[], &setup.user)
+        .unwrap();
+    let compounded_single = state();
+    assert!(compounded_single.position.staked > compounded.position.staked);
+    assert_eq!(compounded_single.total_shares, deposited.total_shares);
+
+    // Withdrawing the shares returns more than deposited
+    let expected: AssetList = wasm
+        .query(&vault, &QueryMsg::SimulateWithdraw { shares })
+        .unwrap();
+    let before = balances(&runner, &setup.user.address(), &[&uatom, &uluna]);
+    wasm.execute(
+        &vault,
+        &ExecuteMsg::Withdraw {
+            shares,
+            recipient: None,
+        },
+        &[],
+        &setup.user,
+    )
+    .unwrap();
+    let after = balances(&runner, &setup.user.address(), &[&uatom, &uluna]);
+    for ((info, before), after) in [&uatom, &uluna].into_iter().zip(before).zip(after) {
+        let received = after - before;
+        assert_eq!(received, expected.find(info).unwrap().amount);
+        assert!(received.u128() > 10_000);
+    }
+    assert!(shares_of(&setup.user).is_zero());
+    assert_eq!(state().total_shares, shares);
+
+    // Withdrawing more shares than owned fails
+    wasm.execute(
+        &vault,
+        &ExecuteMsg::Withdraw {
+            shares: Uint128::one(),
+            recipient: None,
+        },
+        &[],
+        &setup.user,
+    )
+    .unwrap_err();
+}
+
+/// The autocompounder example as a [`ScenarioVault`]
+struct Autocompounder {
+    address: String,
+    assets: Vec<AssetInfo>,
+}
+
+impl ScenarioVault for Autocompounder {
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    fn deposit_msg(&self) -> StdResult<Binary> {
+        to_json_binary(&autocompounder_example::msg::ExecuteMsg::Deposit { recipient: None })
+    }
+
+    fn compound_msg(&self) -> StdResult<Binary> {
+        to_json_binary(&autocompounder_example::msg::ExecuteMsg::Compound {})
+    }
+
+    fn withdraw_msg(&self, shares: Uint128) -> StdResult<Binary> {
+        to_json_binary(&autocompounder_example::msg::ExecuteMsg::Withdraw {
+            shares,
+            recipient: None,
+        })
+    }
+
+    fn shares_query(&self, address: &str) -> StdResult<Binary> {
+        to_json_binary(&autocompounder_example::msg::QueryMsg::Shares {
+            address: address.to_string(),
+        })
+    }
+
+    fn withdrawn_assets(&self) -> Vec<AssetInfo> {
+        self.assets.clone()
+    }
+}
+
+/// Runs a deposit, compound and withdraw scenario with injected rewards and
+/// time travel through the autocompounder, only against Astroport as it
+/// requires staking
+#[test_case(Venue::Astroport; "astroport")]
+fn test_autocompounder_scenario(venue: Venue) {
+    use autocompounder_example::msg::InstantiateMsg;
+
+    let owned_runner = venue.runner();
+    let runner = owned_runner.as_ref();
+    let setup = venue.setup(&runner);
+
+    let contract = ContractType::MultiTestContract(Box::new(
+        ContractWrapper::new_with_empty(
+            autocompounder_example::contract::execute,
+            autocompounder_example::contract::instantiate,
+            autocompounder_example::contract::query,
+        )
+        .with_reply(autocompounder_example::contract::reply),
+    ));
+    let vault = Autocompounder {
+        address: instantiate(
+            &runner,
+            &setup.admin,
+            contract,
+            &InstantiateMsg {
+                pool: setup.pool.clone(),
+                staking: setup.staking.clone().unwrap(),
+                max_slippage: Decimal::percent(1),
+            },
+        ),
+        assets: vec![AssetInfo::native("uatom"), AssetInfo::native("uluna")],
+    };
+    let deposit = vec![coin(10_000, "uatom"), coin(10_000, "uluna")];
+
+    let report = Scenario::new("compound rewards between deposits")
+        .deposit(0, deposit.clone())
+        .inject_rewards(vec![coin(1_000, "uatom"), coin(1_000, "uluna")])
+        .increase_time(86_400)
+        .compound(1)
+        .deposit(1, deposit)
+        .inject_rewards(coins(2_000, "uatom"))
+        .increase_time(86_400)
+        .compound(0)
+        .withdraw_all(0)
+        .withdraw_all(1)
+        .withdraw(1, 1u128)
+        .fails()
+        .run(&runner, &vault, &[&setup.user, &setup.admin], &setup.admin);
+
+    // The second deposit receives fewer shares, as each share is backed by
+    // the compounded rewards
+    let first_shares = report.shares(0, 0);
+    let second_shares = report.shares(4, 1);
+    assert!(!second_shares.is_zero());
+    assert!(second_shares < first_shares);
+    assert!(report.outcome(2).block_time_nanos > report.outcome(1).block_time_nanos);
+
+    // The first depositor receives all rewards compounded before the second
+    // deposit, and both share the rewards compounded after it
+    let first = &report.outcome(8).received;
+    let second = &report.outcome(9).received;
+    assert!(first.iter().all(|received| received.u128() > 10_000));
+    assert!(second[0].u128() > 10_000);
+    assert!(first[0] > second[0]);
+    assert!(report.last().shares.iter().all(Uint128::is_zero));
+    assert!(report.gas_report.max_gas_used("compound").is_some());
+}